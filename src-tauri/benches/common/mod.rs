@@ -0,0 +1,55 @@
+// Synthetic data shared by the benches. Deterministic so runs are comparable.
+#![allow(dead_code)]
+
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+pub const EMBEDDING_DIM: usize = 1536; // BAAI/bge-code-v1 hidden size
+
+/// Small deterministic PRNG (xorshift) so benches don't need an extra dependency.
+pub struct XorShift(u64);
+
+impl XorShift {
+  pub fn new(seed: u64) -> Self {
+    Self(seed.max(1))
+  }
+
+  pub fn next_f32(&mut self) -> f32 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    (self.0 % 10_000) as f32 / 10_000.0 - 0.5
+  }
+}
+
+pub fn random_embeddings(count: usize, seed: u64) -> Vec<Vec<f32>> {
+  let mut rng = XorShift::new(seed);
+  (0..count)
+    .map(|_| (0..EMBEDDING_DIM).map(|_| rng.next_f32()).collect())
+    .collect()
+}
+
+/// Writes a rustdoc-style JSON file with `num_items` documented functions spread over 100 modules.
+pub fn write_synthetic_rustdoc_json(path: &Path, num_items: usize) {
+  let mut index = Map::new();
+  let mut paths = Map::new();
+  index.insert(
+    "0".to_string(),
+    json!({ "name": "bench_crate", "kind": "module", "docs": "Synthetic crate" }),
+  );
+  for i in 1..=num_items {
+    let id = i.to_string();
+    index.insert(id.clone(), json!({
+            "name": format!("function_{}", i),
+            "kind": "function",
+            "docs": format!("Does synthetic thing number {}. Returns a value computed from the input.", i),
+            "inner": {},
+        }));
+    paths.insert(
+      id,
+      json!({ "path": ["bench_crate", format!("module_{}", i % 100)] }),
+    );
+  }
+  let root = json!({ "root": "0", "format_version": 30, "index": Value::Object(index), "paths": Value::Object(paths) });
+  std::fs::write(path, serde_json::to_vec(&root).unwrap()).unwrap();
+}
@@ -0,0 +1,34 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tauri_nextjs_template_lib::config::EmbedderSettings;
+use tauri_nextjs_template_lib::embedder::{init_global_embedder, GLOBAL_EMBEDDER};
+
+// Needs the embedding model (downloaded from the Hugging Face Hub on first run).
+// If it can't be loaded the benchmark is skipped rather than failing the whole suite.
+fn bench_embed_batch(c: &mut Criterion) {
+  if let Err(e) = init_global_embedder(&EmbedderSettings::default()) {
+    eprintln!("Skipping embedding benches, embedder unavailable: {:?}", e);
+    return;
+  }
+  let guard = GLOBAL_EMBEDDER.lock().unwrap();
+  let embedder = guard.as_ref().unwrap();
+
+  let mut group = c.benchmark_group("embed_batch");
+  group.sample_size(10);
+  for batch_size in [1, 8, 32] {
+    let sentences: Vec<String> = (0..batch_size)
+            .map(|i| format!("bench_crate::function_{} [DOCS]: Parses the input and returns a structured value number {}.", i, i))
+            .collect();
+    group.throughput(Throughput::Elements(batch_size as u64));
+    group.bench_with_input(
+      BenchmarkId::from_parameter(batch_size),
+      &sentences,
+      |b, sentences| {
+        b.iter(|| embedder.embed_batch(sentences).unwrap());
+      },
+    );
+  }
+  group.finish();
+}
+
+criterion_group!(benches, bench_embed_batch);
+criterion_main!(benches);
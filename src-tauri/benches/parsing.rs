@@ -0,0 +1,25 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tauri_nextjs_template_lib::rustdoc_processor::parse_rustdoc_json_file;
+
+mod common;
+
+fn bench_parse_rustdoc_json(c: &mut Criterion) {
+  let dir = tempfile::tempdir().unwrap();
+  let mut group = c.benchmark_group("parse_rustdoc_json_file");
+  group.sample_size(10);
+  for num_items in [10_000, 100_000] {
+    let json_path = dir.path().join(format!("bench_{}.json", num_items));
+    common::write_synthetic_rustdoc_json(&json_path, num_items);
+    group.bench_with_input(
+      BenchmarkId::from_parameter(num_items),
+      &json_path,
+      |b, path| {
+        b.iter(|| parse_rustdoc_json_file(path).unwrap());
+      },
+    );
+  }
+  group.finish();
+}
+
+criterion_group!(benches, bench_parse_rustdoc_json);
+criterion_main!(benches);
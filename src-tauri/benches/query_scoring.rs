@@ -0,0 +1,65 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::collections::HashMap;
+use tauri_nextjs_template_lib::ann::{HnswIndex, HnswParams};
+use tauri_nextjs_template_lib::mcp_server::cosine_similarity;
+
+mod common;
+
+// The brute-force scan the query paths used before the ANN index: score every item, sort, take top-k.
+fn top_k(query: &[f32], index: &HashMap<String, Vec<f32>>, k: usize) -> Vec<(String, f32)> {
+  let mut scored: Vec<(String, f32)> = index
+    .iter()
+    .map(|(path, embedding)| (path.clone(), cosine_similarity(query, embedding)))
+    .collect();
+  scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+  scored.truncate(k);
+  scored
+}
+
+fn bench_query_scoring(c: &mut Criterion) {
+  let query = common::random_embeddings(1, 42).pop().unwrap();
+  let mut group = c.benchmark_group("query_scoring_top10");
+  group.sample_size(10);
+  for num_items in [10_000, 100_000] {
+    let index: HashMap<String, Vec<f32>> = common::random_embeddings(num_items, 7)
+      .into_iter()
+      .enumerate()
+      .map(|(i, embedding)| (format!("bench_crate::item_{}", i), embedding))
+      .collect();
+    group.throughput(Throughput::Elements(num_items as u64));
+    group.bench_with_input(
+      BenchmarkId::from_parameter(num_items),
+      &index,
+      |b, index| {
+        b.iter(|| top_k(&query, index, 10));
+      },
+    );
+  }
+  group.finish();
+}
+
+fn bench_ann_search(c: &mut Criterion) {
+  let query = common::random_embeddings(1, 42).pop().unwrap();
+  let mut group = c.benchmark_group("ann_search_top10");
+  group.sample_size(10);
+  for num_items in [10_000, 100_000] {
+    let embeddings: HashMap<String, Vec<f32>> = common::random_embeddings(num_items, 7)
+      .into_iter()
+      .enumerate()
+      .map(|(i, embedding)| (format!("bench_crate::item_{}", i), embedding))
+      .collect();
+    let index = HnswIndex::build(HnswParams::default(), &embeddings);
+    group.throughput(Throughput::Elements(num_items as u64));
+    group.bench_with_input(
+      BenchmarkId::from_parameter(num_items),
+      &index,
+      |b, index| {
+        b.iter(|| index.search(&query, 10));
+      },
+    );
+  }
+  group.finish();
+}
+
+criterion_group!(benches, bench_query_scoring, bench_ann_search);
+criterion_main!(benches);
@@ -0,0 +1,241 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+const ANALYTICS_FILE_NAME: &str = "analytics.json";
+// Oldest snapshots / latency buckets are dropped beyond these limits.
+const MAX_SNAPSHOTS: usize = 1000;
+const MAX_LATENCY_DAYS: usize = 90;
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// Index size at a point in time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexSnapshot {
+    pub timestamp_ms: u64,
+    pub projects: usize,
+    pub total_items: usize,
+    pub total_embeddings: usize,
+    /// Size of the cache directory (rustdoc JSON, journals, ...).
+    pub disk_usage_bytes: u64,
+}
+
+/// Query latency aggregated per UTC day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyQueryLatency {
+    /// Days since the Unix epoch.
+    pub day: u64,
+    pub query_count: u64,
+    pub total_latency_ms: f64,
+}
+
+impl DailyQueryLatency {
+    pub fn average_ms(&self) -> f64 {
+        if self.query_count == 0 { 0.0 } else { self.total_latency_ms / self.query_count as f64 }
+    }
+}
+
+// Latency of all queries scoped to one project.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct ProjectQueryLatency {
+    query_count: u64,
+    total_latency_ms: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct AnalyticsData {
+    snapshots: Vec<IndexSnapshot>,
+    query_latency: Vec<DailyQueryLatency>,
+    project_query_latency: BTreeMap<String, ProjectQueryLatency>,
+}
+
+/// Aggregated data for the stats page.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexAnalytics {
+    pub history: Vec<IndexSnapshot>,
+    pub daily_query_latency: Vec<DailyQueryLatency>,
+    /// Average over all recorded days, None if no queries were recorded yet.
+    pub average_query_latency_ms: Option<f64>,
+}
+
+/// Statistics of one processed project, for its dashboard (`get_project_stats`).
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectStats {
+    pub project_path: String,
+    pub version: Option<String>,
+    /// Items of the project and of its indexed dependencies.
+    pub items_indexed: usize,
+    /// Stored vectors; long docs have one per chunk, so this can exceed `items_indexed`.
+    pub embeddings_stored: usize,
+    /// When the project's index was last written, None if it isn't persisted.
+    pub last_processed_ms: Option<u64>,
+    /// Queries scoped to this project since analytics were recorded.
+    pub query_count: u64,
+    pub average_query_latency_ms: Option<f64>,
+    /// Size of the persisted embeddings and search graph. The parsed docs share one database
+    /// with the other projects and aren't counted.
+    pub index_size_bytes: u64,
+}
+
+/// Local-only index statistics, persisted to `<cache_dir>/analytics.json`. Nothing leaves the machine.
+pub struct AnalyticsStore {
+    cache_dir: PathBuf,
+    file_path: PathBuf,
+    data: Mutex<AnalyticsData>,
+}
+
+impl AnalyticsStore {
+    pub fn load(cache_dir: &Path) -> Self {
+        let file_path = cache_dir.join(ANALYTICS_FILE_NAME);
+        let data = match std::fs::read_to_string(&file_path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Ignoring unreadable analytics file {}: {}", file_path.display(), e);
+                AnalyticsData::default()
+            }),
+            Err(_) => AnalyticsData::default(),
+        };
+        Self { cache_dir: cache_dir.to_path_buf(), file_path, data: Mutex::new(data) }
+    }
+
+    /// Records the current index size. Disk usage is measured here, so call it after the index changed.
+    pub fn record_snapshot(&self, projects: usize, total_items: usize, total_embeddings: usize) {
+        let snapshot = IndexSnapshot {
+            timestamp_ms: now_ms(),
+            projects,
+            total_items,
+            total_embeddings,
+            disk_usage_bytes: dir_size(&self.cache_dir),
+        };
+        let mut data = self.data.lock().expect("analytics lock poisoned");
+        data.snapshots.push(snapshot);
+        if data.snapshots.len() > MAX_SNAPSHOTS {
+            let excess = data.snapshots.len() - MAX_SNAPSHOTS;
+            data.snapshots.drain(..excess);
+        }
+        self.save(&data);
+    }
+
+    /// Records a query's latency, also for `project_path` if the query was scoped to one project.
+    pub fn record_query(&self, project_path: Option<&str>, latency: Duration) {
+        self.record_query_at(now_ms(), project_path, latency);
+    }
+
+    fn record_query_at(&self, timestamp_ms: u64, project_path: Option<&str>, latency: Duration) {
+        let day = timestamp_ms / MS_PER_DAY;
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        let mut data = self.data.lock().expect("analytics lock poisoned");
+        if let Some(project_path) = project_path {
+            let project = data.project_query_latency.entry(project_path.to_string()).or_default();
+            project.query_count += 1;
+            project.total_latency_ms += latency_ms;
+        }
+        match data.query_latency.last_mut() {
+            Some(bucket) if bucket.day == day => {
+                bucket.query_count += 1;
+                bucket.total_latency_ms += latency_ms;
+            }
+            _ => data.query_latency.push(DailyQueryLatency { day, query_count: 1, total_latency_ms: latency_ms }),
+        }
+        if data.query_latency.len() > MAX_LATENCY_DAYS {
+            let excess = data.query_latency.len() - MAX_LATENCY_DAYS;
+            data.query_latency.drain(..excess);
+        }
+        self.save(&data);
+    }
+
+    /// Number and average latency (None without queries) of the queries scoped to `project_path`.
+    pub fn project_query_latency(&self, project_path: &str) -> (u64, Option<f64>) {
+        let data = self.data.lock().expect("analytics lock poisoned");
+        match data.project_query_latency.get(project_path) {
+            Some(latency) if latency.query_count > 0 => (latency.query_count, Some(latency.total_latency_ms / latency.query_count as f64)),
+            _ => (0, None),
+        }
+    }
+
+    /// Drops the query statistics of a removed project.
+    pub fn forget_project(&self, project_path: &str) {
+        let mut data = self.data.lock().expect("analytics lock poisoned");
+        if data.project_query_latency.remove(project_path).is_some() {
+            self.save(&data);
+        }
+    }
+
+    /// Snapshots and latency buckets from the last `days` days (all history if None).
+    pub fn summary(&self, days: Option<u64>) -> IndexAnalytics {
+        let data = self.data.lock().expect("analytics lock poisoned");
+        let now = now_ms();
+        let since_ms = days.map_or(0, |d| now.saturating_sub(d * MS_PER_DAY));
+        let history: Vec<IndexSnapshot> = data.snapshots.iter().filter(|s| s.timestamp_ms >= since_ms).cloned().collect();
+        let daily_query_latency: Vec<DailyQueryLatency> = data.query_latency.iter().filter(|b| b.day >= since_ms / MS_PER_DAY).cloned().collect();
+        let (count, total) = daily_query_latency.iter().fold((0u64, 0.0f64), |(c, t), b| (c + b.query_count, t + b.total_latency_ms));
+        IndexAnalytics {
+            history,
+            daily_query_latency,
+            average_query_latency_ms: if count == 0 { None } else { Some(total / count as f64) },
+        }
+    }
+
+    // Best effort: analytics must never break indexing or queries.
+    fn save(&self, data: &AnalyticsData) {
+        if let Err(e) = write_json(&self.file_path, data) {
+            log::warn!("Failed to save analytics to {}: {:?}", self.file_path.display(), e);
+        }
+    }
+}
+
+fn write_json(path: &Path, data: &AnalyticsData) -> Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec(data)?).context("Failed to write analytics temp file")?;
+    std::fs::rename(&tmp_path, path).context("Failed to replace analytics file")
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_query_latency_buckets_and_persistence() {
+        let dir = tempdir().unwrap();
+        let store = AnalyticsStore::load(dir.path());
+        let day_one = 19_000 * MS_PER_DAY;
+        store.record_query_at(day_one, Some("/work/app"), Duration::from_millis(10));
+        store.record_query_at(day_one + 1000, None, Duration::from_millis(30));
+        store.record_query_at(day_one + MS_PER_DAY, Some("/work/app"), Duration::from_millis(50));
+        store.record_snapshot(1, 100, 80);
+
+        // Reload from disk to check persistence.
+        let reloaded = AnalyticsStore::load(dir.path());
+        let summary = reloaded.summary(None);
+        assert_eq!(summary.daily_query_latency.len(), 2);
+        assert_eq!(summary.daily_query_latency[0].query_count, 2);
+        assert_eq!(summary.daily_query_latency[0].average_ms(), 20.0);
+        assert_eq!(summary.average_query_latency_ms, Some(30.0));
+        assert_eq!(summary.history.len(), 1);
+        assert_eq!(summary.history[0].total_items, 100);
+        assert!(summary.history[0].disk_usage_bytes > 0, "analytics.json itself is in the cache dir");
+        assert_eq!(reloaded.project_query_latency("/work/app"), (2, Some(30.0)));
+        assert_eq!(reloaded.project_query_latency("/work/other"), (0, None));
+
+        reloaded.forget_project("/work/app");
+        assert_eq!(AnalyticsStore::load(dir.path()).project_query_latency("/work/app"), (0, None));
+    }
+}
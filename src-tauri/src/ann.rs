@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+// Max neighbours per node on the upper layers; layer 0 keeps twice as many.
+const DEFAULT_M: usize = 16;
+const DEFAULT_EF_CONSTRUCTION: usize = 100;
+const DEFAULT_EF_SEARCH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HnswParams {
+    pub m: usize,
+    /// Candidate list size while inserting. Higher = better graph, slower build.
+    pub ef_construction: usize,
+    /// Candidate list size while searching (raised to k if smaller). Higher = better recall, slower queries.
+    pub ef_search: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self { m: DEFAULT_M, ef_construction: DEFAULT_EF_CONSTRUCTION, ef_search: DEFAULT_EF_SEARCH }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HnswNode {
+    key: String,
+    /// Neighbour ids per layer, from layer 0 up to the node's level.
+    neighbors: Vec<Vec<usize>>,
+}
+
+/// Approximate nearest-neighbour index (HNSW) over cosine similarity.
+///
+/// Built per project while processing so queries don't have to score every embedding.
+/// Vectors are normalised on insert, so similarity is a plain dot product. The graph is
+/// serializable; the vectors are not (they'd duplicate the project's embeddings) and are
+/// re-attached with `attach_vectors` after loading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswIndex {
+    params: HnswParams,
+    nodes: Vec<HnswNode>,
+    #[serde(skip)]
+    vectors: Vec<Vec<f32>>,
+    entry_point: Option<usize>,
+    max_level: usize,
+    rng_state: u64,
+}
+
+// (similarity, node id), ordered by similarity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scored(f32, usize);
+
+impl Eq for Scored {}
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then_with(|| self.1.cmp(&other.1))
+    }
+}
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl HnswIndex {
+    pub fn new(params: HnswParams) -> Self {
+        Self { params, nodes: Vec::new(), vectors: Vec::new(), entry_point: None, max_level: 0, rng_state: 0x9E37_79B9_7F4A_7C15 }
+    }
+
+    /// Builds an index over all `(key, vector)` pairs.
+    pub fn build<'a, I>(params: HnswParams, items: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a String, &'a Vec<f32>)>,
+    {
+        let mut index = Self::new(params);
+        for (key, vector) in items {
+            index.insert(key.clone(), vector);
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Adds one vector. Keys are not deduplicated; callers insert each key once.
+    pub fn insert(&mut self, key: String, vector: &[f32]) {
+        let id = self.nodes.len();
+        let level = self.random_level();
+        self.nodes.push(HnswNode { key, neighbors: vec![Vec::new(); level + 1] });
+        self.vectors.push(normalized(vector));
+
+        let Some(mut entry) = self.entry_point else {
+            self.entry_point = Some(id);
+            self.max_level = level;
+            return;
+        };
+
+        let query = self.vectors[id].clone();
+        for layer in (level + 1..=self.max_level).rev() {
+            entry = self.greedy_closest(&query, entry, layer);
+        }
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let candidates = self.search_layer(&query, entry, self.params.ef_construction, layer);
+            let selected: Vec<usize> = candidates.iter().take(self.params.m).map(|s| s.1).collect();
+            for &neighbor in &selected {
+                self.nodes[neighbor].neighbors[layer].push(id);
+                self.prune(neighbor, layer);
+            }
+            self.nodes[id].neighbors[layer] = selected;
+            if let Some(best) = candidates.first() {
+                entry = best.1;
+            }
+        }
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry_point = Some(id);
+        }
+    }
+
+    /// The `k` most similar keys to `query` with their cosine similarity, best first.
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(&str, f32)> {
+        let Some(mut entry) = self.entry_point else { return Vec::new() };
+        if k == 0 || self.vectors.len() != self.nodes.len() {
+            return Vec::new();
+        }
+        let query = normalized(query);
+        for layer in (1..=self.max_level).rev() {
+            entry = self.greedy_closest(&query, entry, layer);
+        }
+        self.search_layer(&query, entry, self.params.ef_search.max(k), 0)
+            .into_iter()
+            .take(k)
+            .map(|Scored(score, id)| (self.nodes[id].key.as_str(), score))
+            .collect()
+    }
+
+    /// Re-attaches vectors to a deserialized index. Returns false (leaving the index unusable)
+    /// if any key is missing from `embeddings`; rebuild the index in that case.
+    pub fn attach_vectors(&mut self, embeddings: &HashMap<String, Vec<f32>>) -> bool {
+        let mut vectors = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            match embeddings.get(&node.key) {
+                Some(vector) => vectors.push(normalized(vector)),
+                None => {
+                    self.vectors.clear();
+                    return false;
+                }
+            }
+        }
+        self.vectors = vectors;
+        true
+    }
+
+    fn similarity(&self, query: &[f32], id: usize) -> f32 {
+        let vector = &self.vectors[id];
+        if vector.len() != query.len() {
+            return 0.0;
+        }
+        query.iter().zip(vector).map(|(a, b)| a * b).sum()
+    }
+
+    fn greedy_closest(&self, query: &[f32], mut current: usize, layer: usize) -> usize {
+        let mut current_sim = self.similarity(query, current);
+        loop {
+            let mut improved = false;
+            for &neighbor in self.nodes[current].neighbors.get(layer).into_iter().flatten() {
+                let sim = self.similarity(query, neighbor);
+                if sim > current_sim {
+                    current_sim = sim;
+                    current = neighbor;
+                    improved = true;
+                }
+            }
+            if !improved {
+                return current;
+            }
+        }
+    }
+
+    // Best-first search on one layer. Returns up to `ef` nodes, most similar first.
+    fn search_layer(&self, query: &[f32], entry: usize, ef: usize, layer: usize) -> Vec<Scored> {
+        let mut visited = HashSet::from([entry]);
+        let start = Scored(self.similarity(query, entry), entry);
+        let mut candidates = BinaryHeap::from([start]); // max-heap: best candidate first
+        let mut results = BinaryHeap::from([std::cmp::Reverse(start)]); // min-heap: worst result first
+
+        while let Some(candidate) = candidates.pop() {
+            let worst = results.peek().map(|r| r.0 .0).unwrap_or(f32::MIN);
+            if candidate.0 < worst && results.len() >= ef {
+                break;
+            }
+            for &neighbor in self.nodes[candidate.1].neighbors.get(layer).into_iter().flatten() {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let scored = Scored(self.similarity(query, neighbor), neighbor);
+                let worst = results.peek().map(|r| r.0 .0).unwrap_or(f32::MIN);
+                if results.len() < ef || scored.0 > worst {
+                    candidates.push(scored);
+                    results.push(std::cmp::Reverse(scored));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        let mut sorted: Vec<Scored> = results.into_iter().map(|r| r.0).collect();
+        sorted.sort_by(|a, b| b.cmp(a));
+        sorted
+    }
+
+    // Keeps the node's most similar neighbours when it exceeds the per-layer limit.
+    fn prune(&mut self, id: usize, layer: usize) {
+        let max_neighbors = if layer == 0 { self.params.m * 2 } else { self.params.m };
+        if self.nodes[id].neighbors[layer].len() <= max_neighbors {
+            return;
+        }
+        let base = self.vectors[id].clone();
+        let mut scored: Vec<Scored> = self.nodes[id].neighbors[layer]
+            .iter()
+            .map(|&n| Scored(self.similarity(&base, n), n))
+            .collect();
+        scored.sort_by(|a, b| b.cmp(a));
+        self.nodes[id].neighbors[layer] = scored.into_iter().take(max_neighbors).map(|s| s.1).collect();
+    }
+
+    // Exponentially distributed level with normalisation factor 1/ln(m). xorshift keeps builds dependency-free.
+    fn random_level(&mut self) -> usize {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 7;
+        self.rng_state ^= self.rng_state << 17;
+        let uniform = ((self.rng_state >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        let ml = 1.0 / (self.params.m.max(2) as f64).ln();
+        (-uniform.ln() * ml).floor() as usize
+    }
+}
+
+fn normalized(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_vectors(count: usize, dim: usize, seed: u64) -> HashMap<String, Vec<f32>> {
+        let mut state = seed;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+        };
+        (0..count).map(|i| (format!("item_{}", i), (0..dim).map(|_| next()).collect())).collect()
+    }
+
+    fn brute_force_top_k(embeddings: &HashMap<String, Vec<f32>>, query: &[f32], k: usize) -> Vec<String> {
+        let query = normalized(query);
+        let mut scored: Vec<(String, f32)> = embeddings.iter()
+            .map(|(key, v)| (key.clone(), normalized(v).iter().zip(&query).map(|(a, b)| a * b).sum()))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.into_iter().take(k).map(|(key, _)| key).collect()
+    }
+
+    #[test]
+    fn test_recall_against_brute_force() {
+        let embeddings = random_vectors(2000, 32, 42);
+        let index = HnswIndex::build(HnswParams::default(), &embeddings);
+        assert_eq!(index.len(), 2000);
+
+        let queries = random_vectors(20, 32, 7);
+        let k = 10;
+        let mut hits = 0;
+        for query in queries.values() {
+            let expected = brute_force_top_k(&embeddings, query, k);
+            let found: Vec<&str> = index.search(query, k).into_iter().map(|(key, _)| key).collect();
+            assert_eq!(found.len(), k);
+            hits += expected.iter().filter(|key| found.contains(&key.as_str())).count();
+        }
+        let recall = hits as f64 / (queries.len() * k) as f64;
+        assert!(recall > 0.9, "recall too low: {}", recall);
+    }
+
+    #[test]
+    fn test_exact_match_and_reattach_after_deserialize() {
+        let embeddings = random_vectors(300, 16, 1);
+        let index = HnswIndex::build(HnswParams::default(), &embeddings);
+        let target = &embeddings["item_123"];
+        assert_eq!(index.search(target, 1)[0].0, "item_123");
+
+        let json = serde_json::to_string(&index).unwrap();
+        let mut restored: HnswIndex = serde_json::from_str(&json).unwrap();
+        assert!(restored.search(target, 1).is_empty(), "no vectors until re-attached");
+        assert!(restored.attach_vectors(&embeddings));
+        assert_eq!(restored.search(target, 1)[0].0, "item_123");
+
+        let mut missing = embeddings.clone();
+        missing.remove("item_0");
+        assert!(!restored.attach_vectors(&missing));
+    }
+
+    #[test]
+    fn test_empty_index() {
+        let index = HnswIndex::new(HnswParams::default());
+        assert!(index.search(&[1.0, 0.0], 5).is_empty());
+    }
+}
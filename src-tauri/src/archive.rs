@@ -0,0 +1,250 @@
+use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::embedder;
+use crate::error::AppError;
+use crate::mcp_server::{AppState, ProjectData};
+use crate::rustdoc_processor::CrateDocs;
+
+// Bump when the archived layout changes; older archives are refused with a clear message.
+const ARCHIVE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const DATA_FILE_NAME: &str = "project.bin";
+// Largest data file read from an archive. Its size comes from the tar header, which whoever made
+// the archive controls, so it is checked before anything is read.
+const MAX_DATA_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// What an index archive holds, readable without unpacking the (large) data file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    /// The project's path on the machine it was exported from.
+    pub project_path: String,
+    pub crate_name: String,
+    pub version: Option<String>,
+    /// Model the embeddings were produced with, as `embedder::global_model_id` reports it.
+    pub embedding_model: Option<String>,
+    pub embedding_dimension: Option<usize>,
+    pub item_count: usize,
+    pub embedded_count: usize,
+    /// Version of this app that wrote the archive.
+    pub exported_by: String,
+}
+
+#[derive(Serialize)]
+struct ArchivedProjectRef<'a> {
+    crate_docs: &'a CrateDocs,
+    dependency_docs: Vec<&'a CrateDocs>,
+    embeddings: &'a HashMap<String, Vec<f32>>,
+    truncated_embeddings: &'a HashSet<String>,
+}
+
+#[derive(Deserialize)]
+struct ArchivedProject {
+    crate_docs: CrateDocs,
+    dependency_docs: Vec<CrateDocs>,
+    embeddings: HashMap<String, Vec<f32>>,
+    truncated_embeddings: HashSet<String>,
+}
+
+/// Result of `import_project_archive`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedArchive {
+    /// The key the project was imported under.
+    pub project_path: String,
+    pub manifest: ArchiveManifest,
+    /// The archive was embedded with another model than the loaded one; run `reembed_project`
+    /// before querying it.
+    pub needs_reembed: bool,
+}
+
+/// Writes a project's docs (with its dependencies'), embeddings and metadata to a gzipped tar at
+/// `archive_path`. The ANN graph isn't included; it is rebuilt on import. Blocking.
+pub fn write_archive(project_path: &str, data: &ProjectData, archive_path: &Path) -> Result<ArchiveManifest> {
+    let manifest = ArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        project_path: project_path.to_string(),
+        crate_name: data.crate_docs.crate_name.clone(),
+        version: data.version.clone(),
+        embedding_model: data.embedding_model.clone(),
+        embedding_dimension: data.embedding_dimension,
+        item_count: data.item_count(),
+        embedded_count: data.embedded_item_count(),
+        exported_by: env!("CARGO_PKG_VERSION").to_string(),
+    };
+    let payload = bincode::serialize(&ArchivedProjectRef {
+        crate_docs: &data.crate_docs,
+        dependency_docs: data.dependency_docs.iter().map(|docs| docs.as_ref()).collect(),
+        embeddings: &data.embeddings,
+        truncated_embeddings: &data.truncated_embeddings,
+    })?;
+
+    if let Some(parent) = archive_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let tmp_path = archive_path.with_extension("tmp");
+    {
+        let file = fs::File::create(&tmp_path).with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+        let mut builder = tar::Builder::new(GzEncoder::new(BufWriter::new(file), flate2::Compression::default()));
+        // The manifest goes first so readers can check it before the data.
+        append_file(&mut builder, MANIFEST_FILE_NAME, &serde_json::to_vec_pretty(&manifest)?)?;
+        append_file(&mut builder, DATA_FILE_NAME, &payload)?;
+        builder.into_inner()?.finish()?.flush()?;
+    }
+    // Rename so an interrupted export never leaves a truncated archive behind.
+    fs::rename(&tmp_path, archive_path).with_context(|| format!("Failed to replace {}", archive_path.display()))?;
+    log::info!("Exported {} to {}", project_path, archive_path.display());
+    Ok(manifest)
+}
+
+fn append_file<W: Write>(builder: &mut tar::Builder<W>, name: &str, contents: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, name, contents)?;
+    Ok(())
+}
+
+/// Reads an archive written by `write_archive`, rebuilding the project's search indexes. Blocking.
+pub fn read_archive(archive_path: &Path) -> Result<(ArchiveManifest, ProjectData)> {
+    let file = fs::File::open(archive_path).with_context(|| format!("Failed to open {}", archive_path.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(BufReader::new(file)));
+    let mut manifest: Option<ArchiveManifest> = None;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        match name.as_str() {
+            MANIFEST_FILE_NAME => {
+                let parsed: ArchiveManifest = serde_json::from_reader(&mut entry).context("Invalid archive manifest")?;
+                if parsed.format_version != ARCHIVE_FORMAT_VERSION {
+                    bail!("The archive has format version {} (written by version {}); this app reads version {}",
+                        parsed.format_version, parsed.exported_by, ARCHIVE_FORMAT_VERSION);
+                }
+                manifest = Some(parsed);
+            }
+            DATA_FILE_NAME => {
+                let manifest = manifest.take().context("The archive's data comes before its manifest")?;
+                if entry.size() > MAX_DATA_BYTES {
+                    bail!("The archive's data is {} bytes; at most {} are read", entry.size(), MAX_DATA_BYTES);
+                }
+                // Decoded as it is read rather than buffered whole, so memory follows what the data
+                // decodes to; `take` stops a stream that runs past its header.
+                let reader = BufReader::new((&mut entry).take(MAX_DATA_BYTES));
+                let project: ArchivedProject = bincode::deserialize_from(reader).context("Invalid archive data")?;
+                let data = ProjectData::new(project.crate_docs, project.dependency_docs, project.embeddings)
+                    .with_embedding_model(manifest.embedding_model.clone())
+                    .with_version(manifest.version.clone())
+                    .with_truncated_embeddings(project.truncated_embeddings);
+                return Ok((manifest, data));
+            }
+            other => log::warn!("Ignoring unexpected file '{}' in {}", other, archive_path.display()),
+        }
+    }
+    bail!("{} is not an index archive: {} or {} is missing", archive_path.display(), MANIFEST_FILE_NAME, DATA_FILE_NAME)
+}
+
+/// Exports a processed project to `archive_path`; see `write_archive`.
+pub async fn export_project_archive(app_state: &AppState, project_path: &str, archive_path: PathBuf) -> Result<ArchiveManifest, AppError> {
+    let data = app_state.projects().await.get(project_path).cloned()
+        .ok_or_else(|| AppError::ProjectNotFound { project_path: project_path.to_string() })?;
+    let project_path = project_path.to_string();
+    tokio::task::spawn_blocking(move || write_archive(&project_path, &data, &archive_path))
+        .await
+        .map_err(|e| AppError::internal(format!("Exporting the archive panicked: {}", e)))?
+        .map_err(|e| AppError::internal(format!("Failed to export the archive: {:#}", e)))
+}
+
+/// Loads an archive as a processed project, under `project_path` or the path it was exported
+/// from, replacing a project of that path. Nothing is run or embedded.
+pub async fn import_project_archive(app_state: Arc<AppState>, archive_path: PathBuf, project_path: Option<String>) -> Result<ImportedArchive, AppError> {
+    if !archive_path.is_file() {
+        return Err(AppError::PathNotFound { path: archive_path.display().to_string() });
+    }
+    let (manifest, data) = tokio::task::spawn_blocking(move || read_archive(&archive_path))
+        .await
+        .map_err(|e| AppError::internal(format!("Importing the archive panicked: {}", e)))?
+        .map_err(|e| AppError::InvalidArgument { message: format!("{:#}", e) })?;
+    let project_path = project_path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty())
+        .unwrap_or_else(|| manifest.project_path.clone());
+    let needs_reembed = embedder::global_model_id().await
+        .is_some_and(|model_id| manifest.embedding_model.as_deref() != Some(model_id.as_str()));
+    if needs_reembed {
+        log::warn!("{} was embedded with {:?}; re-embed it to query it with the loaded model", project_path, manifest.embedding_model);
+    }
+    app_state.insert_project(project_path.clone(), data).await;
+    Ok(ImportedArchive { project_path, manifest, needs_reembed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rustdoc_processor::DocItem;
+
+    #[test]
+    fn test_archive_roundtrip() -> Result<()> {
+        let item = DocItem {
+            name: "run".to_string(),
+            full_path_str: "app::run".to_string(),
+            item_type: "function".to_string(),
+            description: Some("Runs it.".to_string()),
+            ..Default::default()
+        };
+        let docs = CrateDocs { crate_name: "app".to_string(), items: HashMap::from([("app::run".to_string(), item)]) };
+        let data = ProjectData::new(docs, Vec::new(), HashMap::from([("app::run".to_string(), vec![0.6, 0.8])]))
+            .with_embedding_model(Some("BAAI/bge-small@main".to_string()))
+            .with_version(Some("0.6.3".to_string()));
+        let dir = tempfile::tempdir()?;
+        let archive_path = dir.path().join("exports/app.tar.gz");
+        let written = write_archive("/work/app", &data, &archive_path)?;
+        assert_eq!((written.item_count, written.embedding_dimension), (1, Some(2)));
+
+        let (manifest, restored) = read_archive(&archive_path)?;
+        assert_eq!(manifest, written);
+        assert_eq!(restored.get_item("app::run").and_then(|i| i.description.as_deref()), Some("Runs it."));
+        assert_eq!(restored.embeddings.get("app::run"), Some(&vec![0.6, 0.8]));
+        assert_eq!(restored.version.as_deref(), Some("0.6.3"));
+
+        fs::write(dir.path().join("bogus.tar.gz"), b"not an archive")?;
+        assert!(read_archive(&dir.path().join("bogus.tar.gz")).is_err());
+        Ok(())
+    }
+
+    // A data entry whose header claims more than `MAX_DATA_BYTES` is refused before it is read.
+    #[test]
+    fn test_archive_data_size_limit() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive_path = dir.path().join("huge.tar.gz");
+        let manifest = ArchiveManifest {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            project_path: "/work/app".to_string(),
+            crate_name: "app".to_string(),
+            version: None,
+            embedding_model: None,
+            embedding_dimension: None,
+            item_count: 0,
+            embedded_count: 0,
+            exported_by: env!("CARGO_PKG_VERSION").to_string(),
+        };
+        let mut builder = tar::Builder::new(GzEncoder::new(fs::File::create(&archive_path)?, flate2::Compression::default()));
+        append_file(&mut builder, MANIFEST_FILE_NAME, &serde_json::to_vec(&manifest)?)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_path(DATA_FILE_NAME)?;
+        header.set_size(MAX_DATA_BYTES + 1);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, &b"tiny"[..])?;
+        builder.into_inner()?.finish()?;
+
+        let error = read_archive(&archive_path).err().unwrap();
+        assert!(error.to_string().contains("at most"), "{:#}", error);
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use axum::http::{header, HeaderMap};
+
+use crate::config::{ConfigHandle, ServerSettings};
+use crate::error::AppError;
+
+/// A random 128-bit token from the OS RNG, as 32 hex digits.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("the OS random number generator is unavailable");
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Generates `server.auth_token` and saves it to the config file if there is none yet, so the
+/// token exists (and is shown in the UI) before anyone turns `auth_required` on.
+pub fn ensure_token(config: &ConfigHandle) -> Result<()> {
+    if config.current().server.auth_token.is_some() {
+        return Ok(());
+    }
+    let token = generate_token();
+    config.update(|c| {
+        c.server.auth_token.get_or_insert(token);
+    })?;
+    log::info!("Generated an auth token for the MCP and HTTP servers (see the Projects page or {}).", config.path().display());
+    Ok(())
+}
+
+/// Checks the token a client presented against the settings. Passes when auth isn't required.
+pub fn check(settings: &ServerSettings, presented: Option<&str>) -> Result<(), AppError> {
+    if !settings.auth_required {
+        return Ok(());
+    }
+    let Some(expected) = settings.auth_token.as_deref() else {
+        return Err(AppError::Unauthorized { message: "auth_required is on but no auth_token is configured".to_string() });
+    };
+    match presented {
+        None => Err(AppError::Unauthorized { message: "Missing auth token".to_string() }),
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        Some(_) => Err(AppError::Unauthorized { message: "Invalid auth token".to_string() }),
+    }
+}
+
+/// Checks the `Authorization: Bearer <token>` header of an HTTP request.
+pub fn check_headers(settings: &ServerSettings, headers: &HeaderMap) -> Result<(), AppError> {
+    let presented = headers.get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(bearer_token);
+    check(settings, presented)
+}
+
+/// The token of an `Authorization: Bearer <token>` header value.
+pub fn bearer_token(header: &str) -> Option<&str> {
+    let (scheme, token) = header.trim().split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+// Compares without stopping at the first differing byte, so timing doesn't leak the token.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_and_bearer_token() {
+        let token = generate_token();
+        assert_eq!(token.len(), 32);
+        assert_ne!(token, generate_token());
+
+        let mut settings = ServerSettings { auth_token: Some(token.clone()), ..Default::default() };
+        assert!(check(&settings, None).is_ok());
+        settings.auth_required = true;
+        assert!(check(&settings, Some(&token)).is_ok());
+        assert_eq!(check(&settings, None).unwrap_err().code(), "unauthorized");
+        assert!(check(&settings, Some("wrong")).is_err());
+
+        assert_eq!(bearer_token("Bearer abc123"), Some("abc123"));
+        assert_eq!(bearer_token("bearer  abc123 "), Some("abc123"));
+        assert_eq!(bearer_token("Basic dXNlcg=="), None);
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+// Standard BM25 parameters: term frequency saturation and document length normalisation.
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+/// Inverted index with BM25 scoring over item names, paths and descriptions.
+///
+/// Complements the embedding search: an exact identifier like `Mutex` may score low on cosine
+/// similarity but is a strong keyword match. Cheap to build, so it is rebuilt on load rather
+/// than persisted.
+#[derive(Debug, Clone, Default)]
+pub struct Bm25Index {
+    // term -> (doc id, term frequency)
+    postings: HashMap<String, Vec<(u32, u32)>>,
+    doc_keys: Vec<String>,
+    doc_lengths: Vec<u32>,
+    avg_doc_length: f32,
+}
+
+impl Bm25Index {
+    /// Builds the index from `(key, text)` pairs.
+    pub fn build<I, K>(docs: I) -> Self
+    where
+        I: IntoIterator<Item = (K, String)>,
+        K: Into<String>,
+    {
+        let mut index = Self::default();
+        for (key, text) in docs {
+            let doc_id = index.doc_keys.len() as u32;
+            let tokens = tokenize(&text);
+            let mut term_freqs: HashMap<String, u32> = HashMap::new();
+            for token in &tokens {
+                *term_freqs.entry(token.clone()).or_default() += 1;
+            }
+            for (term, tf) in term_freqs {
+                index.postings.entry(term).or_default().push((doc_id, tf));
+            }
+            index.doc_keys.push(key.into());
+            index.doc_lengths.push(tokens.len() as u32);
+        }
+        let total: u64 = index.doc_lengths.iter().map(|&l| l as u64).sum();
+        index.avg_doc_length = if index.doc_keys.is_empty() { 0.0 } else { total as f32 / index.doc_keys.len() as f32 };
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.doc_keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.doc_keys.is_empty()
+    }
+
+    /// The `k` best keyword matches for `query` with their BM25 scores, best first.
+    /// Documents sharing no term with the query are not returned.
+    pub fn search(&self, query: &str, k: usize) -> Vec<(&str, f32)> {
+        let mut scores: HashMap<u32, f32> = HashMap::new();
+        let mut query_terms = tokenize(query);
+        query_terms.sort();
+        query_terms.dedup();
+        let num_docs = self.doc_keys.len() as f32;
+        for term in &query_terms {
+            let Some(postings) = self.postings.get(term) else { continue };
+            let df = postings.len() as f32;
+            // The "+ 1" keeps idf positive for terms found in most documents.
+            let idf = ((num_docs - df + 0.5) / (df + 0.5) + 1.0).ln();
+            for &(doc_id, tf) in postings {
+                let tf = tf as f32;
+                let length_norm = 1.0 - B + B * self.doc_lengths[doc_id as usize] as f32 / self.avg_doc_length.max(1.0);
+                *scores.entry(doc_id).or_default() += idf * tf * (K1 + 1.0) / (tf + K1 * length_norm);
+            }
+        }
+        let mut ranked: Vec<(u32, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.into_iter()
+            .take(k)
+            .map(|(doc_id, score)| (self.doc_keys[doc_id as usize].as_str(), score))
+            .collect()
+    }
+}
+
+/// Lowercased word tokens. Identifiers are kept whole and also split into their camelCase /
+/// snake_case parts, so `HashMap` matches "hashmap", "hash" and "map".
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        let word = word.trim_matches('_');
+        if word.is_empty() {
+            continue;
+        }
+        let parts = identifier_parts(word);
+        if parts.len() > 1 {
+            tokens.extend(parts);
+        }
+        tokens.push(word.to_lowercase());
+    }
+    tokens
+}
+
+// Query words too common to be worth highlighting.
+const HIGHLIGHT_STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "be", "can", "do", "does", "for", "how", "i", "in", "is", "it", "my", "of", "on",
+    "or", "the", "to", "use", "what", "with",
+];
+
+/// Byte ranges of the words of `text` that match a term of `query`, in order. Matching follows
+/// `tokenize`, so "hash map" marks `HashMap` and "mutex" marks `Mutex`.
+pub fn match_spans(text: &str, query: &str) -> Vec<(usize, usize)> {
+    let terms: Vec<String> = tokenize(query).into_iter()
+        .filter(|term| !HIGHLIGHT_STOPWORDS.contains(&term.as_str()))
+        .collect();
+    if terms.is_empty() {
+        return Vec::new();
+    }
+    let mut spans = Vec::new();
+    let mut word_start = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        if c.is_alphanumeric() || c == '_' {
+            word_start.get_or_insert(i);
+            continue;
+        }
+        let Some(start) = word_start.take() else { continue };
+        let word = &text[start..i];
+        let trimmed = word.trim_matches('_');
+        if trimmed.is_empty() {
+            continue;
+        }
+        let matches = terms.contains(&trimmed.to_lowercase())
+            || identifier_parts(trimmed).iter().any(|part| terms.contains(part));
+        if matches {
+            let offset = start + (word.len() - word.trim_start_matches('_').len());
+            spans.push((offset, offset + trimmed.len()));
+        }
+    }
+    spans
+}
+
+fn identifier_parts(word: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    for segment in word.split('_').filter(|s| !s.is_empty()) {
+        let mut current = String::new();
+        let mut prev_lower = false;
+        for c in segment.chars() {
+            if c.is_uppercase() && prev_lower && !current.is_empty() {
+                parts.push(current.to_lowercase());
+                current.clear();
+            }
+            prev_lower = c.is_lowercase() || c.is_ascii_digit();
+            current.push(c);
+        }
+        if !current.is_empty() {
+            parts.push(current.to_lowercase());
+        }
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_identifiers() {
+        assert_eq!(tokenize("HashMap"), vec!["hash", "map", "hashmap"]);
+        assert_eq!(tokenize("std::sync::Mutex"), vec!["std", "sync", "mutex"]);
+        assert_eq!(tokenize("read_to_end()"), vec!["read", "to", "end", "read_to_end"]);
+    }
+
+    #[test]
+    fn test_match_spans() {
+        let text = "Creates a HashMap with the given capacity.";
+        let spans = match_spans(text, "how to create a hash map with capacity");
+        let matched: Vec<&str> = spans.iter().map(|&(start, end)| &text[start..end]).collect();
+        assert_eq!(matched, vec!["HashMap", "capacity"]);
+        assert_eq!(match_spans("Größe von _inner_", "inner größe"), vec![(0, 7), (13, 18)]);
+        assert!(match_spans(text, "how to").is_empty());
+    }
+
+    #[test]
+    fn test_exact_identifier_ranks_first() {
+        let index = Bm25Index::build([
+            ("app::Mutex", "Mutex app::Mutex A mutual exclusion primitive.".to_string()),
+            ("app::RwLock", "RwLock app::RwLock A reader-writer lock, like a mutex that allows many readers.".to_string()),
+            ("app::spawn", "spawn app::spawn Spawns a new thread and runs the closure on it.".to_string()),
+        ]);
+        let results = index.search("Mutex", 10);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "app::Mutex");
+        assert!(results[0].1 > results[1].1);
+        assert!(index.search("tokio", 10).is_empty());
+    }
+}
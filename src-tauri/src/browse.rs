@@ -0,0 +1,202 @@
+use rust_mcp_sdk::mcp_tool::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::error::AppError;
+use crate::mcp_server::ProjectData;
+use crate::rustdoc_processor::DocItem;
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// One child in a `list_crate_items` listing.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListedItem {
+    pub full_path: String,
+    pub name: String,
+    pub item_type: String,
+    /// First line of the docs.
+    pub summary: Option<String>,
+    /// Items directly below this one (module members, methods of a type); list them by passing
+    /// `full_path` as the module path.
+    pub child_count: usize,
+}
+
+/// A page of the items directly below a module (or type) of a processed project.
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemListing {
+    pub project_path: String,
+    pub module_path: String,
+    /// Children matching the kind filter, across all pages.
+    pub total: usize,
+    /// 1-based.
+    pub page: usize,
+    pub page_size: usize,
+    pub items: Vec<ListedItem>,
+}
+
+// The path an item is listed under: the type its impl block is for, else its full path minus the
+// last segment. Trait impl members (`app::Config::<Display>::fmt`) list under the type.
+fn container(item: &DocItem) -> Option<&str> {
+    item.parent.as_deref().or_else(|| item.full_path_str.rsplit_once("::").map(|(container, _)| container))
+}
+
+/// Lists the items directly below `module_path` (the crate root by default), modules first and
+/// then by path. `module_path` may be any item with children, e.g. a struct to see its methods,
+/// or a dependency crate.
+pub fn list_items(
+    project_path: &str,
+    data: &ProjectData,
+    module_path: Option<&str>,
+    item_type: Option<&str>,
+    page: usize,
+    page_size: usize,
+) -> Result<ItemListing, AppError> {
+    let module_path = module_path.map(|p| p.trim_end_matches("::")).unwrap_or(&data.crate_docs.crate_name).to_string();
+    let all_items: Vec<&DocItem> = data.crate_docs.items.values()
+        .chain(data.dependency_docs.iter().flat_map(|docs| docs.items.values()))
+        .collect();
+    let mut child_counts: HashMap<&str, usize> = HashMap::new();
+    for item in &all_items {
+        if let Some(container) = container(item) {
+            *child_counts.entry(container).or_default() += 1;
+        }
+    }
+    if !child_counts.contains_key(module_path.as_str()) && data.get_item(&module_path).is_none() {
+        return Err(AppError::InvalidArgument { message: format!("No module or item '{}' in project '{}'", module_path, project_path) });
+    }
+
+    let mut children: Vec<&DocItem> = all_items.into_iter()
+        .filter(|item| container(item) == Some(module_path.as_str()))
+        .filter(|item| item_type.is_none_or(|kind| kind.eq_ignore_ascii_case(&item.item_type)))
+        .collect();
+    children.sort_by(|a, b| (a.item_type != "module", &a.full_path_str).cmp(&(b.item_type != "module", &b.full_path_str)));
+
+    let page = page.max(1);
+    let page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+    let items = children.iter()
+        .skip((page - 1).saturating_mul(page_size))
+        .take(page_size)
+        .map(|item| ListedItem {
+            full_path: item.full_path_str.clone(),
+            name: item.name.clone(),
+            item_type: item.item_type.clone(),
+            summary: item.description.as_deref().and_then(|d| d.lines().map(str::trim).find(|l| !l.is_empty())).map(str::to_string),
+            child_count: child_counts.get(item.full_path_str.as_str()).copied().unwrap_or(0),
+        })
+        .collect();
+    Ok(ItemListing { project_path: project_path.to_string(), module_path, total: children.len(), page, page_size, items })
+}
+
+/// An item returned alongside a query hit, so a client sees its neighbourhood without more queries.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct RelatedItem {
+    pub full_path: String,
+    pub item_type: String,
+    /// "parent" (the type or module the hit is defined in), "sibling" (defined next to it) or
+    /// "child" (defined inside it, e.g. the methods of a struct).
+    pub relation: String,
+}
+
+// Related items returned per hit.
+pub const MAX_RELATED_ITEMS: usize = 8;
+
+/// Up to `MAX_RELATED_ITEMS` items around `item` in `data`: the type or module it belongs to,
+/// then siblings (same kind first) and its own members. Impl items count the type their impl
+/// block is for as their parent. Re-exports are not tracked.
+pub fn related_items(data: &ProjectData, item: &DocItem) -> Vec<RelatedItem> {
+    let full_path = item.full_path_str.as_str();
+    let own_container = container(item);
+    // (0 parent / 1 sibling / 2 child, other kind than the hit, item)
+    let mut related: Vec<(u8, bool, &DocItem)> = Vec::new();
+    for candidate in data.crate_docs.items.values().chain(data.dependency_docs.iter().flat_map(|docs| docs.items.values())) {
+        let path = candidate.full_path_str.as_str();
+        if path == full_path {
+            continue;
+        }
+        let relation = if own_container == Some(path) {
+            0
+        } else if own_container.is_some() && container(candidate) == own_container {
+            1
+        } else if container(candidate) == Some(full_path) {
+            2
+        } else {
+            continue;
+        };
+        related.push((relation, candidate.item_type != item.item_type, candidate));
+    }
+    related.sort_by(|a, b| (a.0, a.1, &a.2.full_path_str).cmp(&(b.0, b.1, &b.2.full_path_str)));
+    related.into_iter()
+        .take(MAX_RELATED_ITEMS)
+        .map(|(relation, _, related)| RelatedItem {
+            full_path: related.full_path_str.clone(),
+            item_type: related.item_type.clone(),
+            relation: ["parent", "sibling", "child"][relation as usize].to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rustdoc_processor::test_support::doc_entry as item;
+    use crate::rustdoc_processor::CrateDocs;
+
+    #[test]
+    fn test_list_items() {
+        let docs = CrateDocs {
+            crate_name: "app".to_string(),
+            items: HashMap::from([
+                item("app", "module", "The app."),
+                item("app::run", "function", "\nRuns it.\n\nMore details."),
+                item("app::net", "module", "Networking."),
+                item("app::net::Client", "struct", "A client."),
+                item("app::net::Client::get", "method", "GETs."),
+                item("app::net::Client::post", "method", "POSTs."),
+            ]),
+        };
+        let data = ProjectData::new(docs, Vec::new(), HashMap::new());
+
+        let root = list_items("/work/app", &data, None, None, 1, 10).unwrap();
+        assert_eq!(root.module_path, "app");
+        let paths: Vec<&str> = root.items.iter().map(|i| i.full_path.as_str()).collect();
+        assert_eq!(paths, vec!["app::net", "app::run"]);
+        assert_eq!(root.items[0].child_count, 1);
+        assert_eq!(root.items[1].summary.as_deref(), Some("Runs it."));
+
+        // Types list their methods; pages are 1-based.
+        let methods = list_items("/work/app", &data, Some("app::net::Client"), Some("Method"), 2, 1).unwrap();
+        assert_eq!(methods.total, 2);
+        assert_eq!(methods.items[0].full_path, "app::net::Client::post");
+        assert!(list_items("/work/app", &data, Some("app::nope"), None, 1, 10).is_err());
+    }
+
+    #[test]
+    fn test_related_items() {
+        let docs = CrateDocs {
+            crate_name: "app".to_string(),
+            items: HashMap::from([
+                item("app::net", "module", "Networking."),
+                item("app::net::Client", "struct", "A client."),
+                item("app::net::connect", "function", "Connects."),
+                item("app::net::Client::get", "method", "GETs."),
+                item("app::net::Client::post", "method", "POSTs."),
+            ]),
+        };
+        let data = ProjectData::new(docs, Vec::new(), HashMap::new());
+        let get = data.get_item("app::net::Client::get").unwrap();
+        assert_eq!(related_items(&data, get), vec![
+            RelatedItem { full_path: "app::net::Client".to_string(), item_type: "struct".to_string(), relation: "parent".to_string() },
+            RelatedItem { full_path: "app::net::Client::post".to_string(), item_type: "method".to_string(), relation: "sibling".to_string() },
+        ]);
+        let client = data.get_item("app::net::Client").unwrap();
+        let related = related_items(&data, client);
+        let relations: Vec<(&str, &str)> = related.iter().map(|r| (r.full_path.as_str(), r.relation.as_str())).collect();
+        assert_eq!(relations, vec![
+            ("app::net", "parent"),
+            ("app::net::connect", "sibling"),
+            ("app::net::Client::get", "child"),
+            ("app::net::Client::post", "child"),
+        ]);
+    }
+}
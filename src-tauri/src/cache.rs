@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::config::{AppConfig, CARGO_TARGET_DIR_NAME};
+use crate::error::AppError;
+use crate::mcp_server::AppState;
+use crate::system_info::{directory_usage, DirectoryUsage};
+
+// Rustdoc JSON downloaded from docs.rs, under the cache directory.
+const DOCS_RS_DIR_NAME: &str = "docs_rs";
+
+/// Disk usage of the cache, by what `clear_cache` can reclaim (`get_cache_stats`).
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub cache_dir: PathBuf,
+    /// Everything under `cache_dir`.
+    pub total: DirectoryUsage,
+    /// The downloaded weights of the configured embedding and reranker models, in the Hugging
+    /// Face cache (shared with other tools, so not part of `total`).
+    pub models: Vec<DirectoryUsage>,
+    pub rustdoc_json: Vec<DirectoryUsage>,
+    pub embeddings: DirectoryUsage,
+    /// Isolated cargo target directories (`rustdoc.isolated_target_dir`).
+    pub build_artifacts: DirectoryUsage,
+    /// Processed projects; reclaimed with `remove_processed_project`, not `clear_cache`.
+    pub projects: DirectoryUsage,
+}
+
+/// What `clear_cache` deletes; everything left out is kept.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct ClearCacheOptions {
+    /// The configured models' weights; they are downloaded again on the next start.
+    pub models: bool,
+    /// Generated and downloaded rustdoc JSON. Processed projects keep working, but can't be
+    /// restored from it after a store format change and are re-documented in full.
+    pub rustdoc_json: bool,
+    /// Cached vectors of embedded texts; unchanged docs are embedded again when re-processed.
+    pub embeddings: bool,
+    /// Isolated cargo target directories; the next run compiles the project's dependencies again.
+    pub build_artifacts: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClearedCache {
+    pub freed_bytes: u64,
+    /// Entries of the embedding cache deleted.
+    pub embeddings_deleted: usize,
+    /// What couldn't be deleted, e.g. a model file in use on Windows.
+    pub errors: Vec<String>,
+}
+
+// Where hf-hub keeps a model repository: `<hf cache>/models--<org>--<name>`.
+fn model_dirs(config: &AppConfig) -> Vec<PathBuf> {
+    let hf_cache = hf_hub::Cache::default();
+    let mut repos = vec![config.embedder.model_repo.as_str(), config.reranker.model_repo.as_str()];
+    repos.dedup();
+    repos.into_iter()
+        .map(|repo| hf_cache.path().join(format!("models--{}", repo.replace('/', "--"))))
+        .collect()
+}
+
+// The directories this run writes to, which stay where they were opened until the next start
+// even if `storage.cache_dir` changed since.
+struct CacheDirs {
+    cache_dir: PathBuf,
+    rustdoc_json: Vec<PathBuf>,
+    embeddings: PathBuf,
+    build_artifacts: PathBuf,
+    projects: PathBuf,
+}
+
+impl CacheDirs {
+    fn of(app_state: &AppState) -> Self {
+        let cache_dir = app_state.cache_dir.clone();
+        CacheDirs {
+            rustdoc_json: vec![app_state.rustdoc_output_dir.clone(), cache_dir.join(DOCS_RS_DIR_NAME)],
+            // An in-memory cache has no file; an empty path measures as nothing.
+            embeddings: app_state.embedding_cache.path().map(Path::to_path_buf).unwrap_or_default(),
+            build_artifacts: cache_dir.join(CARGO_TARGET_DIR_NAME),
+            projects: app_state.store.dir().to_path_buf(),
+            cache_dir,
+        }
+    }
+}
+
+/// Walks the cache directories, so it may take a moment.
+pub async fn cache_stats(app_state: &AppState) -> Result<CacheStats, AppError> {
+    let config = app_state.config.current();
+    let dirs = CacheDirs::of(app_state);
+    tokio::task::spawn_blocking(move || {
+        CacheStats {
+            total: directory_usage(&dirs.cache_dir),
+            models: model_dirs(&config).iter().map(|dir| directory_usage(dir)).collect(),
+            rustdoc_json: dirs.rustdoc_json.iter().map(|dir| directory_usage(dir)).collect(),
+            embeddings: directory_usage(&dirs.embeddings),
+            build_artifacts: directory_usage(&dirs.build_artifacts),
+            projects: directory_usage(&dirs.projects),
+            cache_dir: dirs.cache_dir,
+        }
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Collecting cache stats panicked: {}", e)))
+}
+
+/// Deletes the selected parts of the cache and reports the space reclaimed. Processed projects
+/// are never touched. Files that can't be deleted are reported and skipped.
+pub async fn clear_cache(app_state: &AppState, options: ClearCacheOptions) -> Result<ClearedCache, AppError> {
+    let config = app_state.config.current();
+    let embedding_cache = app_state.embedding_cache.clone();
+    let dirs = CacheDirs::of(app_state);
+    tokio::task::spawn_blocking(move || {
+        let mut cleared = ClearedCache { freed_bytes: 0, embeddings_deleted: 0, errors: Vec::new() };
+        if options.models {
+            for dir in model_dirs(&config) {
+                remove_dir(&dir, &mut cleared);
+            }
+        }
+        if options.rustdoc_json {
+            for dir in &dirs.rustdoc_json {
+                remove_dir(dir, &mut cleared);
+                // The generated JSON directory is expected to exist while the app runs.
+                if let Err(e) = std::fs::create_dir_all(dir) {
+                    cleared.errors.push(format!("{}: {}", dir.display(), e));
+                }
+            }
+        }
+        if options.embeddings {
+            // The database stays open, so it is emptied rather than deleted.
+            let path = &dirs.embeddings;
+            let before = directory_usage(path).bytes;
+            match embedding_cache.clear() {
+                Ok(deleted) => cleared.embeddings_deleted = deleted,
+                Err(e) => cleared.errors.push(format!("{}: {:#}", path.display(), e)),
+            }
+            cleared.freed_bytes += before.saturating_sub(directory_usage(path).bytes);
+        }
+        if options.build_artifacts {
+            remove_dir(&dirs.build_artifacts, &mut cleared);
+        }
+        log::info!("Cleared cache ({:?}): {} bytes freed, {} errors", options, cleared.freed_bytes, cleared.errors.len());
+        cleared
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Clearing the cache panicked: {}", e)))
+}
+
+fn remove_dir(dir: &Path, cleared: &mut ClearedCache) {
+    if !dir.exists() {
+        return;
+    }
+    let before = directory_usage(dir).bytes;
+    if let Err(e) = std::fs::remove_dir_all(dir) {
+        cleared.errors.push(format!("{}: {}", dir.display(), e));
+    }
+    cleared.freed_bytes += before.saturating_sub(directory_usage(dir).bytes);
+}
@@ -0,0 +1,346 @@
+use serde::{Deserialize, Serialize};
+
+/// The `#[cfg(...)]` condition an item is compiled under, e.g. `all(unix, feature = "tls")`.
+/// Read from the rustdoc JSON `attrs`: `#[cfg(..)]`, `#[doc(cfg(..))]` and
+/// `#[cfg_attr(docsrs, doc(cfg(..)))]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cfg {
+    /// `unix`, `test`.
+    Name(String),
+    /// `feature = "tls"`, `target_os = "linux"`.
+    KeyValue(String, String),
+    All(Vec<Cfg>),
+    Any(Vec<Cfg>),
+    Not(Box<Cfg>),
+}
+
+impl Cfg {
+    /// The cfg of an item with these attributes (as rustdoc renders them, `#[cfg(unix)]`):
+    /// several are combined with `all`. `None` if there is none.
+    pub fn from_attrs<'a>(attrs: impl IntoIterator<Item = &'a str>) -> Option<Cfg> {
+        let mut found: Vec<Cfg> = Vec::new();
+        for attr in attrs {
+            let Some(body) = attr.trim().strip_prefix("#[").and_then(|a| a.strip_suffix(']')) else { continue };
+            let Some(meta) = parse_meta(body) else { continue };
+            if let Some(cfg) = attr_cfg(&meta) {
+                if !found.contains(&cfg) {
+                    found.push(cfg);
+                }
+            }
+        }
+        match found.len() {
+            0 => None,
+            1 => found.pop(),
+            _ => Some(Cfg::All(found)),
+        }
+    }
+
+    /// Both conditions; `None` stands for none.
+    pub fn and(a: Option<Cfg>, b: Option<Cfg>) -> Option<Cfg> {
+        match (a, b) {
+            (Some(a), Some(b)) if a == b => Some(a),
+            (Some(Cfg::All(mut all)), Some(b)) => {
+                all.push(b);
+                Some(Cfg::All(all))
+            }
+            (Some(a), Some(b)) => Some(Cfg::All(vec![a, b])),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// What a reader needs to use the item, e.g. "Requires feature `tls`" or "Only available
+    /// on unix and feature `tls`".
+    pub fn describe(&self) -> String {
+        let features_only = match self {
+            Cfg::All(all) => all.iter().all(|cfg| cfg.feature().is_some()),
+            cfg => cfg.feature().is_some(),
+        };
+        if features_only {
+            let features: Vec<String> = match self {
+                Cfg::All(all) => all.iter().filter_map(Cfg::feature).map(|f| format!("`{}`", f)).collect(),
+                cfg => cfg.feature().map(|f| format!("`{}`", f)).into_iter().collect(),
+            };
+            let noun = if features.len() == 1 { "feature" } else { "features" };
+            return format!("Requires {} {}", noun, features.join(" and "));
+        }
+        format!("Only available on {}", self.render(false))
+    }
+
+    /// Whether the item may exist on `target`: `false` only if the condition is certainly false
+    /// there. Conditions on anything but the platform (features, `test`) are assumed to hold.
+    pub fn may_apply_to(&self, target: &Target) -> bool {
+        self.eval(target) != Some(false)
+    }
+
+    fn feature(&self) -> Option<&str> {
+        match self {
+            Cfg::KeyValue(key, value) if key == "feature" => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    // `nested` parenthesises `and`/`or` lists inside another list.
+    fn render(&self, nested: bool) -> String {
+        let list = |cfgs: &[Cfg], separator: &str| {
+            let text = cfgs.iter().map(|cfg| cfg.render(true)).collect::<Vec<_>>().join(separator);
+            if nested && cfgs.len() > 1 { format!("({})", text) } else { text }
+        };
+        match self {
+            Cfg::Name(name) => name.clone(),
+            Cfg::KeyValue(key, value) => match key.as_str() {
+                "feature" => format!("feature `{}`", value),
+                "target_os" | "target_family" | "target_arch" | "target_env" => value.clone(),
+                _ => format!("{} = \"{}\"", key, value),
+            },
+            Cfg::All(all) => list(all, " and "),
+            Cfg::Any(any) => list(any, " or "),
+            Cfg::Not(cfg) => format!("non-{}", cfg.render(true)),
+        }
+    }
+
+    fn from_meta(meta: &Meta) -> Option<Cfg> {
+        Some(match meta {
+            Meta::Word(name) => Cfg::Name(name.clone()),
+            Meta::NameValue(key, value) => Cfg::KeyValue(key.clone(), value.clone()),
+            Meta::List(name, args) => {
+                let args: Vec<Cfg> = args.iter().map(Cfg::from_meta).collect::<Option<_>>()?;
+                match name.as_str() {
+                    "all" => Cfg::All(args),
+                    "any" => Cfg::Any(args),
+                    "not" => Cfg::Not(Box::new(args.into_iter().next()?)),
+                    _ => return None,
+                }
+            }
+        })
+    }
+
+    // Three-valued: `None` when the target doesn't decide it.
+    fn eval(&self, target: &Target) -> Option<bool> {
+        match self {
+            Cfg::Name(name) => match name.as_str() {
+                "unix" | "windows" => target.family.as_deref().map(|family| family == name),
+                _ => None,
+            },
+            Cfg::KeyValue(key, value) => {
+                let known = match key.as_str() {
+                    "target_os" => &target.os,
+                    "target_family" => &target.family,
+                    "target_arch" => &target.arch,
+                    "target_env" => &target.env,
+                    _ => return None,
+                };
+                known.as_deref().map(|known| known == value)
+            }
+            Cfg::All(all) => all.iter().try_fold(Some(true), |acc, cfg| match cfg.eval(target) {
+                Some(false) => Err(()),
+                Some(true) => Ok(acc),
+                None => Ok(None),
+            }).unwrap_or(Some(false)),
+            Cfg::Any(any) => any.iter().try_fold(Some(false), |acc, cfg| match cfg.eval(target) {
+                Some(true) => Err(()),
+                Some(false) => Ok(acc),
+                None => Ok(None),
+            }).map_or(Some(true), |acc| acc),
+            Cfg::Not(cfg) => cfg.eval(target).map(|value| !value),
+        }
+    }
+}
+
+/// The platform a query's `target` filter asks about: a target triple
+/// (`x86_64-pc-windows-msvc`), an OS (`linux`, `macos`) or a family (`unix`, `windows`, `wasm`).
+/// Parts the text doesn't tell are unknown, and conditions on them don't exclude anything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Target {
+    pub os: Option<String>,
+    pub family: Option<String>,
+    pub arch: Option<String>,
+    pub env: Option<String>,
+}
+
+const UNIX_OSES: &[&str] = &["linux", "macos", "ios", "android", "freebsd", "netbsd", "openbsd", "dragonfly", "solaris", "illumos"];
+
+impl Target {
+    pub fn parse(text: &str) -> Target {
+        let text = text.trim().to_lowercase();
+        let parts: Vec<&str> = text.split('-').filter(|part| !part.is_empty()).collect();
+        let mut target = Target::default();
+        match parts.as_slice() {
+            [] => {}
+            [word] => match *word {
+                "unix" | "windows" => {
+                    target.family = Some(word.to_string());
+                    if *word == "windows" {
+                        target.os = Some(word.to_string());
+                    }
+                }
+                "wasm" | "wasm32" | "wasm64" => target.family = Some("wasm".to_string()),
+                "darwin" | "macos" => target.os = Some("macos".to_string()),
+                os => target.os = Some(os.to_string()),
+            },
+            [arch, rest @ ..] => {
+                target.arch = Some(arch.to_string());
+                target.os = rest.iter().find_map(|part| match *part {
+                    "darwin" => Some("macos".to_string()),
+                    "windows" | "wasi" => Some(part.to_string()),
+                    part if UNIX_OSES.contains(&part) => Some(part.to_string()),
+                    _ => None,
+                });
+                target.env = rest.last().filter(|_| rest.len() > 1).and_then(|last| {
+                    ["gnu", "musl", "msvc"].into_iter().find(|env| last.starts_with(env)).map(String::from)
+                });
+                if arch.starts_with("wasm") {
+                    target.family = Some("wasm".to_string());
+                }
+            }
+        }
+        if target.family.is_none() {
+            target.family = target.os.as_deref().and_then(|os| match os {
+                "windows" => Some("windows".to_string()),
+                os if UNIX_OSES.contains(&os) => Some("unix".to_string()),
+                _ => None,
+            });
+        }
+        target
+    }
+}
+
+// The condition an attribute applies, if it is a cfg one.
+fn attr_cfg(meta: &Meta) -> Option<Cfg> {
+    let Meta::List(name, args) = meta else { return None };
+    match (name.as_str(), args.as_slice()) {
+        ("cfg", [predicate]) => Cfg::from_meta(predicate),
+        ("doc", args) => args.iter().find_map(attr_cfg),
+        // `cfg_attr(docsrs, doc(cfg(..)))`: only the attributes it applies say anything.
+        ("cfg_attr", [_, applied @ ..]) => applied.iter().find_map(attr_cfg),
+        _ => None,
+    }
+}
+
+// An attribute's contents: `unix`, `feature = "tls"` or `all(unix, feature = "tls")`.
+#[derive(Debug, PartialEq)]
+enum Meta {
+    Word(String),
+    NameValue(String, String),
+    List(String, Vec<Meta>),
+}
+
+fn parse_meta(text: &str) -> Option<Meta> {
+    let tokens = tokenize(text)?;
+    let mut position = 0;
+    let meta = meta(&tokens, &mut position)?;
+    (position == tokens.len()).then_some(meta)
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Punct(char),
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => value.push(chars.next()?),
+                    c => value.push(c),
+                }
+            }
+            tokens.push(Token::Str(value));
+        } else if c.is_alphanumeric() || c == '_' || c == ':' {
+            let mut ident = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_' || **c == ':') {
+                ident.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Ident(ident));
+        } else {
+            tokens.push(Token::Punct(c));
+            chars.next();
+        }
+    }
+    Some(tokens)
+}
+
+fn meta(tokens: &[Token], position: &mut usize) -> Option<Meta> {
+    let Token::Ident(name) = tokens.get(*position)? else { return None };
+    *position += 1;
+    match tokens.get(*position) {
+        Some(Token::Punct('=')) => {
+            let Token::Str(value) = tokens.get(*position + 1)? else { return None };
+            *position += 2;
+            Some(Meta::NameValue(name.clone(), value.clone()))
+        }
+        Some(Token::Punct('(')) => {
+            *position += 1;
+            let mut args = Vec::new();
+            loop {
+                match tokens.get(*position)? {
+                    Token::Punct(')') => {
+                        *position += 1;
+                        return Some(Meta::List(name.clone(), args));
+                    }
+                    Token::Punct(',') => *position += 1,
+                    // `doc(alias = "x")` and the like hold literals we don't need.
+                    Token::Str(_) => *position += 1,
+                    _ => args.push(meta(tokens, position)?),
+                }
+            }
+        }
+        _ => Some(Meta::Word(name.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_value(key: &str, value: &str) -> Cfg {
+        Cfg::KeyValue(key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_cfg_from_attrs() {
+        let cfg = Cfg::from_attrs(["#[cfg(unix)]", "#[inline]", "#[cfg_attr(docsrs, doc(cfg(feature = \"tls\")))]"]).unwrap();
+        assert_eq!(cfg, Cfg::All(vec![Cfg::Name("unix".to_string()), key_value("feature", "tls")]));
+        assert_eq!(cfg.describe(), "Only available on unix and feature `tls`");
+
+        let cfg = Cfg::from_attrs(["#[doc(cfg(all(feature = \"rt\", feature = \"net\")))]"]).unwrap();
+        assert_eq!(cfg.describe(), "Requires features `rt` and `net`");
+        let cfg = Cfg::from_attrs(["#[cfg(any(target_os = \"linux\", not(windows)))]"]).unwrap();
+        assert_eq!(cfg.describe(), "Only available on linux or non-windows");
+
+        assert_eq!(Cfg::from_attrs(["#[must_use]", "#[doc(hidden)]", "#[cfg(broken"]), None);
+    }
+
+    #[test]
+    fn test_cfg_targets() {
+        let windows = Target::parse("x86_64-pc-windows-msvc");
+        assert_eq!(windows, Target {
+            os: Some("windows".to_string()),
+            family: Some("windows".to_string()),
+            arch: Some("x86_64".to_string()),
+            env: Some("msvc".to_string()),
+        });
+        assert_eq!(Target::parse("aarch64-apple-darwin").family.as_deref(), Some("unix"));
+        assert_eq!(Target::parse("Linux").family.as_deref(), Some("unix"));
+
+        let unix_tls = Cfg::All(vec![Cfg::Name("unix".to_string()), key_value("feature", "tls")]);
+        assert!(!unix_tls.may_apply_to(&windows));
+        assert!(unix_tls.may_apply_to(&Target::parse("linux")));
+        assert!(Cfg::Not(Box::new(Cfg::Name("unix".to_string()))).may_apply_to(&windows));
+        assert!(Cfg::Any(vec![key_value("target_os", "linux"), Cfg::Name("windows".to_string())]).may_apply_to(&windows));
+        // What the target doesn't tell doesn't exclude anything.
+        assert!(key_value("target_arch", "x86_64").may_apply_to(&Target::parse("unix")));
+        assert!(!key_value("target_arch", "aarch64").may_apply_to(&windows));
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+// Marks the embedding key of a chunk after the first: `<full path>@chunk<N>`. Not `#`, which
+// markdown doc sources use in their paths.
+const CHUNK_KEY_MARKER: &str = "@chunk";
+// Marks the embedding key of a code example of an item: `<full path>@example<N>`.
+const EXAMPLE_KEY_MARKER: &str = "@example";
+
+/// Embedding key of chunk `chunk` of an item. The first chunk is keyed by the item's full path,
+/// so short docs (one chunk) are stored exactly as before chunking existed.
+pub fn chunk_key(full_path: &str, chunk: usize) -> String {
+    if chunk == 0 {
+        full_path.to_string()
+    } else {
+        format!("{}{}{}", full_path, CHUNK_KEY_MARKER, chunk)
+    }
+}
+
+/// Embedding key of code example `example` (0-based) of an item; see `DocItem::examples`.
+pub fn example_key(full_path: &str, example: usize) -> String {
+    format!("{}{}{}", full_path, EXAMPLE_KEY_MARKER, example)
+}
+
+// The full path before `marker` and a numeric suffix, if `key` has them.
+fn strip_marker<'a>(key: &'a str, marker: &str) -> Option<&'a str> {
+    match key.rsplit_once(marker) {
+        Some((full_path, n)) if !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) => Some(full_path),
+        _ => None,
+    }
+}
+
+/// The item full path an embedding key (doc chunk or example) belongs to.
+pub fn item_path(key: &str) -> &str {
+    strip_marker(key, CHUNK_KEY_MARKER).or_else(|| strip_marker(key, EXAMPLE_KEY_MARKER)).unwrap_or(key)
+}
+
+/// Whether an embedding key is one of an item's code examples rather than its docs.
+pub fn is_example_key(key: &str) -> bool {
+    strip_marker(key, EXAMPLE_KEY_MARKER).is_some()
+}
+
+/// The example embeddings stored for an item, in example order.
+pub fn example_embeddings<'a>(embeddings: &'a HashMap<String, Vec<f32>>, full_path: &'a str) -> impl Iterator<Item = &'a Vec<f32>> + 'a {
+    (0..).map_while(move |example| embeddings.get(&example_key(full_path, example)))
+}
+
+/// All chunk embeddings stored for an item, in chunk order.
+pub fn item_embeddings<'a>(embeddings: &'a HashMap<String, Vec<f32>>, full_path: &'a str) -> impl Iterator<Item = &'a Vec<f32>> + 'a {
+    (0..).map_while(move |chunk| embeddings.get(&chunk_key(full_path, chunk)))
+}
+
+/// Number of distinct items among embedding keys (each item counts once however many chunks it has).
+pub fn embedded_item_count<'a>(keys: impl IntoIterator<Item = &'a String>) -> usize {
+    keys.into_iter().filter(|key| item_path(key).len() == key.len()).count()
+}
+
+/// Splits `text` into windows of at most `window` tokens, consecutive windows sharing `overlap`
+/// tokens. `offsets` are the tokens' byte ranges in `text`, as reported by the tokenizer. Each
+/// window runs from its first token's start to its last token's end.
+pub fn split_windows<'a>(text: &'a str, offsets: &[(usize, usize)], window: usize, overlap: usize) -> Vec<&'a str> {
+    if window == 0 || offsets.len() <= window {
+        return vec![text];
+    }
+    let step = window.saturating_sub(overlap).max(1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + window).min(offsets.len());
+        // Tokenizer offsets always fall on char boundaries; `get` guards against a misbehaving one.
+        if let Some(chunk) = text.get(offsets[start].0..offsets[end - 1].1) {
+            chunks.push(chunk);
+        }
+        if end == offsets.len() {
+            break;
+        }
+        start += step;
+    }
+    if chunks.is_empty() { vec![text] } else { chunks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One "token" per whitespace-separated word, like a tokenizer would report.
+    fn word_offsets(text: &str) -> Vec<(usize, usize)> {
+        let mut offsets = Vec::new();
+        let mut start = None;
+        for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+            match (c.is_whitespace(), start) {
+                (true, Some(s)) => { offsets.push((s, i)); start = None; }
+                (false, None) => start = Some(i),
+                _ => {}
+            }
+        }
+        offsets
+    }
+
+    #[test]
+    fn test_split_windows_with_overlap() {
+        let text = "a b c d e f g";
+        let chunks = split_windows(text, &word_offsets(text), 3, 1);
+        assert_eq!(chunks, vec!["a b c", "c d e", "e f g"]);
+        // Short texts are a single chunk.
+        assert_eq!(split_windows("a b", &word_offsets("a b"), 3, 1), vec!["a b"]);
+    }
+
+    #[test]
+    fn test_chunk_keys() {
+        assert_eq!(chunk_key("app::run", 0), "app::run");
+        assert_eq!(item_path(&chunk_key("app::run", 2)), "app::run");
+        assert_eq!(item_path("guide::intro.md#Setup"), "guide::intro.md#Setup");
+
+        let embeddings = HashMap::from([
+            (chunk_key("app::run", 0), vec![1.0]),
+            (chunk_key("app::run", 1), vec![2.0]),
+            (chunk_key("app::stop", 0), vec![3.0]),
+            (example_key("app::run", 0), vec![4.0]),
+        ]);
+        assert_eq!(item_embeddings(&embeddings, "app::run").count(), 2);
+        assert_eq!(example_embeddings(&embeddings, "app::run").count(), 1);
+        assert_eq!(embedded_item_count(embeddings.keys()), 2);
+        assert_eq!(item_path(&example_key("app::run", 0)), "app::run");
+        assert!(is_example_key("app::run@example0") && !is_example_key("app::run@chunk1"));
+    }
+}
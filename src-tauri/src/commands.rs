@@ -1,122 +1,181 @@
-use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tauri::State;
 // Ensure tokio::sync::Mutex is used if AppState's Mutex is from tokio, which it should be for async .lock().await
 // use tokio::sync::Mutex; // Already in mcp_server.rs, AppState uses tokio::sync::Mutex
 
-use crate::mcp_server::{AppState, ProjectData}; // Make these types accessible
-use crate::rustdoc_processor;
-use crate::embedder::GLOBAL_EMBEDDER;
-
-// Define the return type for query results to match the UI
-#[derive(Debug, serde::Serialize, Clone)] // Added Clone for convenience if needed later
-pub struct QueryDocResultItem {
-    pub project_path: String,
-    pub item_full_path: String,
-    pub item_type: String,
-    pub description_snippet: Option<String>,
-    pub score: f32,
-}
-
+use crate::mcp_server::{self, AppState, McpServerStatus, SearchFilter}; // Make these types accessible
+use crate::rustdoc_processor::{self, DocItem, DocTargets, ItemSource};
+use crate::render::RenderMode;
+use crate::doc_source;
+use crate::sandbox;
+use crate::jobs::{JobId, JobInfo, JobPhase, JobPriority};
+use crate::compat::CompatReport;
+use crate::system_info::{self, SystemInfo};
+use crate::analytics::{IndexAnalytics, ProjectStats};
+use crate::error::AppError;
+use crate::browse::{self, ItemListing};
+use crate::archive::{self, ArchiveManifest, ImportedArchive};
+use crate::cache::{self, CacheStats, ClearCacheOptions, ClearedCache};
+use crate::diff::{self, DocDiff};
+use crate::export::{ExportFormat, ExportSummary};
+use crate::pipeline::{self, ProcessOptions, QueryDocResultItem, QueryOptions, ResultDetail};
+use crate::embedder::{self, EmbedderInfo, EmbedderStatus, ModelPreset};
+use crate::config::{AppConfig, DevicePreference, EmbedderSettings, McpTransport, ModelDType, ServerSettings};
+use crate::events::StateEvent;
+use crate::journal::{self, ProcessingStage};
+use crate::watch;
+use crate::logging::{self, LogEntry};
+use crate::storage::{QueryHistoryEntry, DEFAULT_QUERY_HISTORY_LIMIT};
 
 #[tauri::command]
+#[tracing::instrument(skip(app_state))]
 pub async fn invoke_process_rust_project(
     path: String,
+    include_dependencies: Option<bool>, // Also index the project's direct dependencies
+    allow_untrusted: Option<bool>, // Set after the user confirmed an `untrusted_project` error
+    targets: Option<DocTargets>, // Which targets to document; None picks the lib, or the bins if there is none
     app_state: State<'_, Arc<AppState>>,
-) -> Result<String, String> {
+) -> Result<JobId, AppError> {
     log::info!("[Tauri Command] invoke_process_rust_project called for path: {}", path);
     let project_path_obj = Path::new(&path);
 
     if !project_path_obj.exists() || !project_path_obj.is_dir() {
-        let err_msg = format!("Project path does not exist or is not a directory: {}", path);
-        log::error!("{}", err_msg);
-        return Err(err_msg);
+        log::error!("Project path does not exist or is not a directory: {}", path);
+        return Err(AppError::PathNotFound { path });
     }
+    ensure_trusted(&app_state, &path, allow_untrusted.unwrap_or(false))?;
 
-    // Logic adapted from ProcessRustProjectTool in mcp_server.rs
-    match rustdoc_processor::generate_rustdoc_json(project_path_obj, &app_state.rustdoc_output_dir) {
-        Ok(json_path) => {
-            log::info!("Generated rustdoc at: {}", json_path.display());
-            match rustdoc_processor::parse_rustdoc_json_file(&json_path) {
-                Ok(crate_docs) => {
-                    log::info!("Parsed rustdoc for crate: {}", crate_docs.crate_name);
-                    let mut project_embeddings = HashMap::new();
-                    let embedder_guard = GLOBAL_EMBEDDER.lock().map_err(|e| format!("Failed to lock global embedder: {}", e))?;
-
-                    if let Some(embedder) = embedder_guard.as_ref() {
-                        let mut texts_to_embed = Vec::new();
-                        let mut item_paths_for_embedding = Vec::new();
-
-                        for (item_full_path, doc_item) in &crate_docs.items {
-                            if let Some(desc) = &doc_item.description {
-                                if !desc.trim().is_empty() {
-                                    // Using a slightly more descriptive format for embedding
-                                    texts_to_embed.push(format!("Crate: {}, Item: {}, Type: {}, Docs: {}", doc_item.crate_name, doc_item.name, doc_item.item_type, desc));
-                                    item_paths_for_embedding.push(item_full_path.clone());
-                                }
-                            }
-                        }
-
-                        if !texts_to_embed.is_empty() {
-                            log::info!("Embedding {} docs for {}", texts_to_embed.len(), crate_docs.crate_name);
-                            match embedder.embed_batch(&texts_to_embed) {
-                                Ok(embeddings_vec) => {
-                                    for (item_path_key, embedding) in item_paths_for_embedding.into_iter().zip(embeddings_vec.into_iter()) {
-                                        project_embeddings.insert(item_path_key, embedding);
-                                    }
-                                    log::info!("Successfully embedded {} items for {}.", project_embeddings.len(), crate_docs.crate_name);
-                                }
-                                Err(e) => {
-                                    log::error!("Failed to embed batch for {}: {:?}", crate_docs.crate_name, e);
-                                    // Non-fatal for now, proceed without embeddings for these items
-                                }
-                            }
-                        } else {
-                            log::info!("No suitable descriptions found for embedding in {}.", crate_docs.crate_name);
-                        }
-                    } else {
-                        log::warn!("Embedder not initialized. Skipping embedding generation.");
-                    }
-
-                    let project_data = ProjectData {
-                        crate_docs: Arc::new(crate_docs.clone()),
-                        embeddings: Arc::new(project_embeddings),
-                    };
-
-                    let mut projects_map_guard = app_state.processed_projects.lock().await;
-                    projects_map_guard.insert(path.clone(), project_data);
-
-                    let num_embedded = projects_map_guard.get(&path).map_or(0, |pd| pd.embeddings.len());
-                    let success_msg = format!("Successfully processed project {} and embedded {} items. Total processed projects: {}.", path, num_embedded, projects_map_guard.len());
-                    log::info!("{}", success_msg);
-                    Ok(success_msg)
-                }
-                Err(e) => {
-                    let err_msg = format!("Failed to parse rustdoc JSON for {}: {:?}", path, e);
-                    log::error!("{}", err_msg);
-                    Err(err_msg)
-                }
-            }
-        }
-        Err(e) => {
-            let err_msg = format!("Failed to generate rustdoc JSON for {}: {:?}", path, e);
-            log::error!("{}", err_msg);
-            Err(err_msg)
-        }
+    // Processing runs as a background job. The job id is returned right away; progress and the
+    // final result arrive as `process-progress` events.
+    let job_state = app_state.inner().clone();
+    let opts = ProcessOptions { path: path.clone(), include_dependencies: include_dependencies.unwrap_or(false), targets: targets.unwrap_or_default() };
+    let job_id = app_state.jobs.submit_with_progress("process_rust_project", &path, JobPriority::High, |progress| async move {
+        pipeline::process_project(job_state, opts, progress).await.map(|outcome| outcome.to_string())
+    })?;
+    Ok(job_id)
+}
+
+/// Indexes the standard library (std, core, alloc) from the toolchain's `rust-docs-json`
+/// component, installing it first if needed. Runs as a background job like
+/// `invoke_process_rust_project`; the docs are queryable as project `rust-std`.
+#[tauri::command]
+pub async fn process_std_library(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<JobId, AppError> {
+    log::info!("[Tauri Command] process_std_library");
+    let job_state = app_state.inner().clone();
+    let job_id = app_state.jobs.submit_with_progress("process_std_library", pipeline::STD_PROJECT_KEY, JobPriority::High, |progress| async move {
+        pipeline::process_std_library(job_state, progress).await.map(|outcome| outcome.to_string())
+    })?;
+    Ok(job_id)
+}
+
+/// Re-embeds a processed project with the loaded model (after a model switch) as a background
+/// job; progress arrives as `process-progress` events with kind `reembed_project`.
+#[tauri::command]
+pub async fn reembed_project(
+    path: String,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<JobId, AppError> {
+    log::info!("[Tauri Command] reembed_project: {}", path);
+    if !app_state.projects().await.contains_key(&path) {
+        return Err(AppError::ProjectNotFound { project_path: path });
     }
+    let job_state = app_state.inner().clone();
+    let job_path = path.clone();
+    let job_id = app_state.jobs.submit_with_progress("reembed_project", &path, JobPriority::High, |progress| async move {
+        pipeline::reembed_project(job_state, job_path, progress).await.map(|outcome| outcome.to_string())
+    })?;
+    Ok(job_id)
+}
+
+/// Turns on watch mode for a processed project: saving a file under `src/` or its `Cargo.toml`
+/// re-processes it in the background, followed by an `index_refreshed` state event.
+#[tauri::command]
+pub async fn enable_watch(
+    project_path: String,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<(), AppError> {
+    log::info!("[Tauri Command] enable_watch: {}", project_path);
+    ensure_trusted(&app_state, &project_path, false)?;
+    watch::enable_watch(app_state.inner().clone(), &project_path).await
+}
+
+#[tauri::command]
+pub async fn disable_watch(
+    project_path: String,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<(), AppError> {
+    log::info!("[Tauri Command] disable_watch: {}", project_path);
+    watch::disable_watch(&app_state, &project_path)
+}
+
+/// Paths of the projects in watch mode.
+#[tauri::command]
+pub async fn get_watched_projects(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<Vec<String>, AppError> {
+    Ok(app_state.watches.watched())
+}
+
+/// Installs `toolchain` (default: the configured rustdoc toolchain, `nightly` unless changed) with
+/// rustup, as a background job; rustup's output arrives as `process-progress` events with kind
+/// `install_toolchain`. The startup compatibility report is refreshed afterwards.
+#[tauri::command]
+pub async fn install_nightly_toolchain(
+    toolchain: Option<String>, // e.g. the toolchain of a `toolchain_missing` error
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<JobId, AppError> {
+    let toolchain = toolchain.unwrap_or_else(|| app_state.config.current().rustdoc.toolchain.clone());
+    log::info!("[Tauri Command] install_nightly_toolchain: {}", toolchain);
+    let job_state = app_state.inner().clone();
+    let label = toolchain.clone();
+    let job_id = app_state.jobs.submit_with_progress("install_toolchain", &label, JobPriority::High, |progress| async move {
+        let reporter = progress.clone();
+        let install_toolchain = toolchain.clone();
+        tokio::task::spawn_blocking(move || {
+            rustdoc_processor::install_toolchain(&install_toolchain, reporter.cancel_flag(), |line| {
+                reporter.report(JobPhase::Install { message: line.to_string() });
+            })
+        })
+        .await
+        .map_err(|e| AppError::internal(format!("Toolchain install panicked: {}", e)))?
+        .map_err(|e| progress.check_cancelled().err().unwrap_or_else(|| AppError::internal(format!("{:#}", e))))?;
+        let config = job_state.config.current();
+        if let Ok(report) = tokio::task::spawn_blocking(move || crate::compat::run_startup_checks(&config)).await {
+            *job_state.compat_report.lock().await = Some(report);
+        }
+        Ok(format!("Installed toolchain {}.", toolchain))
+    })?;
+    Ok(job_id)
+}
+
+/// Indexes an existing rustdoc JSON file (e.g. from CI) under `project_label` as a background
+/// job, without running cargo; progress arrives as `process-progress` events with kind `import_rustdoc_json`.
+#[tauri::command]
+pub async fn import_rustdoc_json(
+    json_path: String,
+    project_label: String,
+    version: Option<String>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<JobId, AppError> {
+    log::info!("[Tauri Command] import_rustdoc_json: {} as {}", json_path, project_label);
+    pipeline::submit_import(app_state.inner(), json_path, project_label, version, JobPriority::High)
 }
 
-// Helper for cosine similarity
-fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
-    if v1.is_empty() || v2.is_empty() || v1.len() != v2.len() { return 0.0; }
-    let dot_product: f32 = v1.iter().zip(v2.iter()).map(|(a, b)| a * b).sum();
-    let norm_v1: f32 = v1.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
-    let norm_v2: f32 = v2.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
-    if norm_v1 == 0.0 || norm_v2 == 0.0 { 0.0 } else { dot_product / (norm_v1 * norm_v2) }
+// In hardened mode, building a project outside the trusted directories needs explicit confirmation.
+fn ensure_trusted(app_state: &AppState, path: &str, allow_untrusted: bool) -> Result<(), AppError> {
+    let sandbox_settings = app_state.config.current().rustdoc.sandbox.clone();
+    if allow_untrusted || sandbox::is_trusted(Path::new(path), &sandbox_settings) {
+        return Ok(());
+    }
+    log::warn!("Refusing to build untrusted project {} without confirmation", path);
+    Err(AppError::UntrustedProject { path: path.to_string() })
 }
 
 #[tauri::command]
+#[tracing::instrument(skip(app_state))]
 pub async fn invoke_query_documentation(
     query: String, // Parameter name from JS: naturalLanguageQuery, but Rust style is snake_case.
                   // Tauri will match if `invoke` uses naturalLanguageQuery.
@@ -125,55 +184,699 @@ pub async fn invoke_query_documentation(
                   // If not, ensure client sends `query` or rename this to `natural_language_query`.
                   // For now, using `query` as per the command definition.
     project_path: Option<String>,
+    version: Option<String>, // e.g. "0.6.3", "0.6" or "*" for every kept version
     num_results: Option<usize>, // Added num_results parameter
+    item_types: Option<Vec<String>>, // e.g. ["function", "struct"]; None returns every kind
+    crate_name: Option<String>, // Only items of this crate, e.g. one dependency
+    module_prefix: Option<String>, // Only items under this path, e.g. "tokio::sync"
+    include_deprecated: Option<bool>, // Also return #[deprecated] items, left out by default
+    public_only: Option<bool>, // Only `pub` items; defaults to true
+    target: Option<String>, // Leave out items #[cfg]-gated away from this platform, e.g. "windows"
+    rerank: Option<bool>, // None uses reranker.enabled from the config
+    prefer_examples: Option<bool>,
+    additional_queries: Option<Vec<String>>, // Other phrasings of the query; rankings are fused
+    expand_query: Option<bool>, // Also search generated paraphrases
+    snippet_length: Option<usize>, // None uses search.snippet_length from the config
+    include_full_docs: Option<bool>, // Also return the complete docs and the declaration
+    min_score: Option<f32>, // Drop results whose 0-1 similarity (or rerank relevance) is below this
     app_state: State<'_, Arc<AppState>>,
-) -> Result<Vec<QueryDocResultItem>, String> {
-    log::info!("[Tauri Command] invoke_query_documentation: '{}', project_filter: {:?}, num_results: {:?}", query, project_path, num_results);
-    let num_results_cap = num_results.unwrap_or(5); // Use provided num_results or default
+) -> Result<Vec<QueryDocResultItem>, AppError> {
+    log::info!("[Tauri Command] invoke_query_documentation: '{}', project_filter: {:?}, version: {:?}, num_results: {:?}, item_types: {:?}, rerank: {:?}", query, project_path, version, num_results, item_types, rerank);
+    let config = app_state.config.current();
+    let detail = ResultDetail::new(snippet_length, include_full_docs, &config.search);
+    let opts = QueryOptions {
+        query: query.clone(),
+        project_path,
+        version,
+        num_results: num_results.unwrap_or(config.search.default_num_results),
+        filter: SearchFilter {
+            item_types,
+            crate_name,
+            module_prefix,
+            include_deprecated: include_deprecated.unwrap_or(false),
+            public_only: public_only.unwrap_or(true),
+            target,
+        },
+        rerank: rerank.unwrap_or(config.reranker.enabled),
+        prefer_examples: prefer_examples.unwrap_or(false),
+        additional_queries: additional_queries.unwrap_or_default(),
+        expand_query: expand_query.unwrap_or(false),
+        min_score,
+    };
+    let all_scored_items: Vec<QueryDocResultItem> = pipeline::query_docs(&app_state, &opts).await?
+        .into_iter()
+        .map(|hit| QueryDocResultItem::from_hit(hit, detail).with_highlights(&opts.query))
+        .collect();
 
-    let query_embedding = {
-        let embedder_guard = GLOBAL_EMBEDDER.lock().map_err(|e| format!("Failed to lock global embedder: {}",e))?;
-        if let Some(embedder) = embedder_guard.as_ref() {
-            embedder.embed_sentence(&query).map_err(|e| format!("Failed to embed query: {}", e))?
-        } else {
-            return Err("Embedder not initialized".to_string());
+    log::info!("Found {} results for query '{}'.", all_scored_items.len(), query);
+    Ok(all_scored_items)
+}
+
+#[tauri::command]
+pub async fn get_processed_project_list(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<Vec<String>, AppError> {
+    log::info!("[Tauri Command] get_processed_project_list");
+    let guard = app_state.projects().await;
+    Ok(guard.keys().cloned().collect())
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct InterruptedProject {
+    pub project_path: String,
+    pub stage: ProcessingStage,
+}
+
+/// Projects whose processing was interrupted (crash or force-quit) and can be resumed
+/// by calling `invoke_process_rust_project` again.
+#[tauri::command]
+pub async fn get_interrupted_projects(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<Vec<InterruptedProject>, AppError> {
+    log::info!("[Tauri Command] get_interrupted_projects");
+    Ok(journal::interrupted_projects(&app_state.journal_dir)
+        .into_iter()
+        .map(|(project_path, stage)| InterruptedProject { project_path, stage })
+        .collect())
+}
+
+/// Indexes a non-rustdoc documentation source (mdBook, Markdown folder, OpenAPI spec).
+/// `source_kind` is auto-detected from the path when omitted.
+#[tauri::command]
+#[tracing::instrument(skip(app_state))]
+pub async fn invoke_process_doc_source(
+    path: String,
+    source_kind: Option<String>,
+    allow_untrusted: Option<bool>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<String, AppError> {
+    log::info!("[Tauri Command] invoke_process_doc_source called for path: {} (kind: {:?})", path, source_kind);
+    if !Path::new(&path).exists() {
+        return Err(AppError::PathNotFound { path });
+    }
+    let runs_build = app_state.doc_sources.resolve(Path::new(&path), source_kind.as_deref()).is_some_and(|s| s.runs_build());
+    if runs_build {
+        ensure_trusted(&app_state, &path, allow_untrusted.unwrap_or(false))?;
+    }
+    let job_state = app_state.inner().clone();
+    let job_path = path.clone();
+    let job_id = app_state.jobs.submit_with_progress("process_doc_source", &path, JobPriority::High, |progress| async move {
+        match doc_source::index_with_source(&job_state, &job_path, source_kind.as_deref(), progress.cancel_flag().clone()).await {
+            Ok((num_items, num_embedded)) => Ok(format!("Successfully indexed {} items from {} and embedded {} of them.", num_items, job_path, num_embedded)),
+            Err(e) => {
+                progress.check_cancelled()?;
+                log::error!("Failed to index doc source {}: {:?}", job_path, e);
+                Err(AppError::internal(format!("Failed to index doc source {}: {:#}", job_path, e)))
+            }
         }
+    })?;
+    app_state.jobs.wait(job_id).await
+}
+
+#[tauri::command]
+pub async fn get_doc_source_kinds(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<Vec<String>, AppError> {
+    Ok(app_state.doc_sources.kinds().into_iter().map(String::from).collect())
+}
+
+/// All queued, running and recently finished background jobs.
+#[tauri::command]
+pub async fn list_jobs(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<Vec<JobInfo>, AppError> {
+    Ok(app_state.jobs.list())
+}
+
+/// State and last progress of one job, e.g. the id returned by `invoke_process_rust_project`.
+/// Finished jobs are kept for a while (the last 100), then `job_not_found`.
+#[tauri::command]
+pub async fn get_job_status(
+    job_id: JobId,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<JobInfo, AppError> {
+    app_state.jobs.get(job_id).ok_or(AppError::JobNotFound { job_id })
+}
+
+/// Result of the startup toolchain/model checks. None until the checks have finished;
+/// the frontend can also listen for the `compatibility-report` event.
+#[tauri::command]
+pub async fn get_compatibility_report(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<Option<CompatReport>, AppError> {
+    Ok(app_state.compat_report.lock().await.clone())
+}
+
+/// Device, model, memory, disk usage and toolchains, for debugging slow or failing processing.
+#[tauri::command]
+pub async fn get_system_info(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<SystemInfo, AppError> {
+    log::info!("[Tauri Command] get_system_info");
+    Ok(system_info::collect(&app_state).await)
+}
+
+/// Local index statistics over time for the stats page. `days` limits the history (all of it if omitted).
+#[tauri::command]
+pub async fn get_index_analytics(
+    days: Option<u64>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<IndexAnalytics, AppError> {
+    log::info!("[Tauri Command] get_index_analytics: days={:?}", days);
+    Ok(app_state.analytics.summary(days))
+}
+
+/// The `num_results` items of a project whose docs are closest in meaning to `item_path`, found
+/// with its stored embedding, e.g. alternatives to a function.
+#[tauri::command]
+pub async fn find_similar_items(
+    project_path: String,
+    item_path: String,
+    num_results: Option<usize>,
+    item_types: Option<Vec<String>>,
+    include_deprecated: Option<bool>,
+    public_only: Option<bool>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<Vec<QueryDocResultItem>, AppError> {
+    log::info!("[Tauri Command] find_similar_items: {} in {}", item_path, project_path);
+    let config = app_state.config.current();
+    let detail = ResultDetail::new(None, None, &config.search);
+    let filter = SearchFilter {
+        item_types,
+        include_deprecated: include_deprecated.unwrap_or(false),
+        public_only: public_only.unwrap_or(true),
+        ..Default::default()
     };
+    let hits = pipeline::find_similar_items(&app_state, &project_path, &item_path, num_results.unwrap_or(config.search.default_num_results), &filter).await?;
+    Ok(hits.into_iter().map(|hit| QueryDocResultItem::from_hit(hit, detail)).collect())
+}
 
-    let projects_map_guard = app_state.processed_projects.lock().await;
-    let mut all_scored_items = Vec::new();
-
-    for (current_proj_path, proj_data) in projects_map_guard.iter() {
-        if project_path.as_ref().map_or(true, |p| p == current_proj_path) {
-            for (item_full_path, item_embedding) in proj_data.embeddings.iter() {
-                let score = cosine_similarity(&query_embedding, item_embedding);
-                // Consider a threshold for score if results are too noisy
-                // Example: if score > 0.5 { ... }
-                if let Some(doc_item) = proj_data.crate_docs.items.get(item_full_path) {
-                    all_scored_items.push(QueryDocResultItem {
-                        project_path: current_proj_path.clone(),
-                        item_full_path: item_full_path.clone(),
-                        item_type: doc_item.item_type.clone(),
-                        description_snippet: doc_item.description.as_ref().map(|d| d.chars().take(300).collect()),
-                        score,
-                    });
-                }
-            }
+/// The newest `limit` (50 by default) queries, newest first, with their filters, best hit and
+/// latency; `project_path` keeps the queries scoped to that project.
+#[tauri::command]
+pub async fn get_query_history(
+    limit: Option<usize>,
+    project_path: Option<String>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<Vec<QueryHistoryEntry>, AppError> {
+    log::info!("[Tauri Command] get_query_history: limit={:?}, project_path={:?}", limit, project_path);
+    app_state.query_history(project_path.as_deref(), limit.unwrap_or(DEFAULT_QUERY_HISTORY_LIMIT)).await
+}
+
+/// Deletes the query history; returns the number of entries removed.
+#[tauri::command]
+pub async fn clear_query_history(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<usize, AppError> {
+    log::info!("[Tauri Command] clear_query_history");
+    app_state.clear_query_history().await
+}
+
+/// Items, embeddings, last processing time, query latency and on-disk size of one project.
+#[tauri::command]
+pub async fn get_project_stats(
+    project_path: String,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<ProjectStats, AppError> {
+    log::info!("[Tauri Command] get_project_stats: {}", project_path);
+    app_state.project_stats(&project_path).await
+}
+
+/// Changes to the MCP server settings for `restart_mcp_server`; omitted fields keep their values.
+#[derive(Debug, serde::Deserialize)]
+pub struct McpServerConfig {
+    pub enabled: Option<bool>,
+    pub transport: Option<McpTransport>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+}
+
+/// Saves `config` (if given) and restarts the MCP server with it, without restarting the app.
+/// Fails with `server_port_in_use` if the new address is taken; the old server then keeps running.
+#[tauri::command]
+pub async fn restart_mcp_server(
+    config: Option<McpServerConfig>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<McpServerStatus, AppError> {
+    log::info!("[Tauri Command] restart_mcp_server: {:?}", config);
+    let previous = app_state.config.current().server.clone();
+    if let Some(change) = config {
+        app_state.config.update(|config| {
+            if let Some(enabled) = change.enabled { config.server.enabled = enabled; }
+            if let Some(transport) = change.transport { config.server.transport = transport; }
+            if let Some(host) = change.host { config.server.host = host; }
+            if let Some(port) = change.port { config.server.port = port; }
+        })?;
+    }
+    restart_with_saved_server_settings(previous, &app_state).await
+}
+
+// Restarts the MCP server with the saved settings, restoring `previous` if that fails.
+async fn restart_with_saved_server_settings(previous: ServerSettings, app_state: &Arc<AppState>) -> Result<McpServerStatus, AppError> {
+    let restarted = mcp_server::restart_mcp_server(app_state.clone()).await;
+    if restarted.is_err() {
+        // Keep the config in line with the server that is still running.
+        app_state.config.update(|config| {
+            config.server.enabled = previous.enabled;
+            config.server.transport = previous.transport;
+            config.server.host = previous.host.clone();
+            config.server.port = previous.port;
+        })?;
+    }
+    restarted
+}
+
+/// Stops the MCP server until `restart_mcp_server` or the next app start.
+#[tauri::command]
+pub async fn stop_mcp_server(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<McpServerStatus, AppError> {
+    log::info!("[Tauri Command] stop_mcp_server");
+    mcp_server::stop_mcp_server(&app_state).await;
+    Ok(mcp_server::mcp_server_status(&app_state).await)
+}
+
+#[tauri::command]
+pub async fn get_mcp_server_status(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<McpServerStatus, AppError> {
+    Ok(mcp_server::mcp_server_status(&app_state).await)
+}
+
+/// Why the MCP server isn't running (e.g. `server_port_in_use`), or None if it started normally.
+#[tauri::command]
+pub async fn get_mcp_server_error(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<Option<AppError>, AppError> {
+    Ok(app_state.mcp_server_error.lock().await.clone())
+}
+
+/// The MCP/HTTP server auth settings, for display.
+#[derive(Debug, serde::Serialize)]
+pub struct AuthSettings {
+    pub auth_required: bool,
+    pub auth_token: Option<String>,
+}
+
+#[tauri::command]
+pub async fn get_auth_settings(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<AuthSettings, AppError> {
+    let server = app_state.config.current().server.clone();
+    Ok(AuthSettings { auth_required: server.auth_required, auth_token: server.auth_token })
+}
+
+/// Turns the auth token check on or off and saves the choice to the config file. Takes effect
+/// for the next request, without a restart.
+#[tauri::command]
+pub async fn set_auth_required(
+    required: bool,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<AuthSettings, AppError> {
+    log::info!("[Tauri Command] set_auth_required: {}", required);
+    let config = app_state.config.update(|c| c.server.auth_required = required)?;
+    Ok(AuthSettings { auth_required: config.server.auth_required, auth_token: config.server.auth_token.clone() })
+}
+
+/// The toolchain a project is documented with.
+#[derive(Debug, serde::Serialize)]
+pub struct ProjectToolchain {
+    pub project_path: String,
+    pub toolchain: String,
+    /// rustdoc JSON `format_version` the toolchain produces; checked when the toolchain is set.
+    pub format_version: Option<u32>,
+    /// Set for the project, rather than the `[rustdoc] toolchain` default.
+    pub overridden: bool,
+}
+
+/// Sets the toolchain `project_path` is documented with (e.g. "nightly-2024-05-01" or "stable")
+/// and saves it to the config file; `None` goes back to the default toolchain. The toolchain is
+/// first checked to produce rustdoc JSON in a format the parser reads. The next processing run
+/// rebuilds the docs, since the toolchain is part of the source fingerprint.
+#[tauri::command]
+pub async fn set_project_toolchain(
+    project_path: String,
+    toolchain: Option<String>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<ProjectToolchain, AppError> {
+    log::info!("[Tauri Command] set_project_toolchain: {} -> {:?}", project_path, toolchain);
+    let toolchain = toolchain.map(|t| t.trim().trim_start_matches('+').to_string()).filter(|t| !t.is_empty());
+    let format_version = match &toolchain {
+        Some(toolchain) => Some(check_toolchain(toolchain).await?),
+        None => None,
+    };
+    let config = app_state.config.update(|c| match &toolchain {
+        Some(toolchain) => { c.rustdoc.project_toolchains.insert(project_path.clone(), toolchain.clone()); }
+        None => { c.rustdoc.project_toolchains.remove(&project_path); }
+    })?;
+    Ok(ProjectToolchain {
+        toolchain: config.rustdoc.for_project(&project_path).toolchain,
+        overridden: toolchain.is_some(),
+        format_version,
+        project_path,
+    })
+}
+
+// Probes that `toolchain` is installed and writes rustdoc JSON this parser supports.
+async fn check_toolchain(toolchain: &str) -> Result<u32, AppError> {
+    let probe_toolchain = toolchain.to_string();
+    let probed = tokio::task::spawn_blocking(move || crate::compat::probe_format_version(&probe_toolchain))
+        .await
+        .map_err(|e| AppError::internal(format!("Toolchain probe panicked: {}", e)))?;
+    let format_version = match probed {
+        Ok(format_version) => format_version,
+        Err(e) if matches!(rustdoc_processor::toolchain_installed(toolchain), Ok(false)) => {
+            log::warn!("Toolchain {} is not installed: {:#}", toolchain, e);
+            return Err(AppError::ToolchainMissing { toolchain: toolchain.to_string() });
         }
+        Err(e) => return Err(AppError::InvalidArgument { message: format!("Toolchain '{}' can't produce rustdoc JSON: {:#}", toolchain, e) }),
+    };
+    match crate::compat::check_format_version(format_version) {
+        Some(warning) if warning.severity == crate::compat::Severity::Error => Err(AppError::InvalidArgument { message: warning.message }),
+        _ => Ok(format_version),
     }
+}
 
-    all_scored_items.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-    all_scored_items.truncate(num_results_cap);
+#[derive(Debug, serde::Serialize)]
+pub struct EmbeddingModelChange {
+    pub model_repo: String,
+    pub model_revision: String,
+    /// Projects embedded with the previous model. Their vectors don't match the new model's
+    /// query vectors until they are re-processed.
+    pub projects_to_reembed: Vec<String>,
+}
 
-    log::info!("Found {} results for query '{}'.", all_scored_items.len(), query);
-    Ok(all_scored_items)
+/// Switches the embedding model: saves the choice to the config file, unloads the current model
+/// and loads the new one. Omitted options keep their current values.
+#[tauri::command]
+#[tracing::instrument(skip(app_state))]
+pub async fn set_embedding_model(
+    model_repo: String,
+    model_revision: Option<String>,
+    device: Option<DevicePreference>,
+    dtype: Option<ModelDType>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<EmbeddingModelChange, AppError> {
+    log::info!("[Tauri Command] set_embedding_model: {} (revision {:?})", model_repo, model_revision);
+    if model_repo.trim().is_empty() {
+        return Err(AppError::InvalidArgument { message: "model_repo must not be empty".to_string() });
+    }
+    let previous = app_state.config.current().embedder.clone();
+    let config = app_state.config.update(|config| {
+        config.embedder.preset = None;
+        config.embedder.model_repo = model_repo.clone();
+        config.embedder.model_revision = model_revision.unwrap_or_else(|| "main".to_string());
+        if let Some(device) = device { config.embedder.device = device; }
+        if let Some(dtype) = dtype { config.embedder.dtype = dtype; }
+    })?;
+    reload_embedder(previous, config.embedder.clone(), &app_state).await
 }
 
+/// Switches to one of the built-in model presets (`fast`, `balanced` or `best`), like
+/// `set_embedding_model`.
 #[tauri::command]
-pub async fn get_processed_project_list(
+#[tracing::instrument(skip(app_state))]
+pub async fn set_embedding_preset(
+    preset: ModelPreset,
     app_state: State<'_, Arc<AppState>>,
-) -> Result<Vec<String>, String> {
-    log::info!("[Tauri Command] get_processed_project_list");
-    let guard = app_state.processed_projects.lock().await;
-    Ok(guard.keys().cloned().collect())
+) -> Result<EmbeddingModelChange, AppError> {
+    log::info!("[Tauri Command] set_embedding_preset: {}", preset.as_str());
+    let previous = app_state.config.current().embedder.clone();
+    let config = app_state.config.update(|config| preset.apply(&mut config.embedder))?;
+    reload_embedder(previous, config.embedder.clone(), &app_state).await
+}
+
+// Loads the model `settings` describe in place of the current one.
+async fn reload_embedder(previous: EmbedderSettings, settings: EmbedderSettings, app_state: &AppState) -> Result<EmbeddingModelChange, AppError> {
+    app_state.emit_state_event(StateEvent::EmbedderStateChanged { ready: false, model_repo: settings.model_repo.clone() });
+    let reload_settings = settings.clone();
+    let reloaded = tokio::task::spawn_blocking(move || embedder::reload_global_embedder(&reload_settings))
+        .await
+        .map_err(|e| AppError::internal(format!("Embedder reload task panicked: {}", e)))?;
+    if let Err(e) = reloaded {
+        log::error!("Failed to load embedding model {}: {:?}", settings.model_repo, e);
+        return Err(AppError::EmbedderUnavailable { model_repo: settings.model_repo });
+    }
+    app_state.emit_state_event(StateEvent::EmbedderStateChanged { ready: true, model_repo: settings.model_repo.clone() });
+
+    let model_changed = previous.model_repo != settings.model_repo
+        || previous.model_revision != settings.model_revision
+        || previous.quantized != settings.quantized
+        || previous.pooling != settings.pooling;
+    let projects_to_reembed = if model_changed {
+        app_state.projects().await.keys().cloned().collect()
+    } else {
+        Vec::new()
+    };
+    Ok(EmbeddingModelChange { model_repo: settings.model_repo, model_revision: settings.model_revision, projects_to_reembed })
+}
+
+/// Unloads a processed project and deletes its persisted index and cached rustdoc JSON.
+#[tauri::command]
+pub async fn remove_processed_project(
+    path: String,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<(), AppError> {
+    log::info!("[Tauri Command] remove_processed_project: {}", path);
+    app_state.remove_project(&path).await.map(|_| ())
+}
+
+/// An item's docs; `render` returns the description as plain text or sanitized HTML instead of
+/// markdown, so the UI doesn't need a markdown renderer.
+#[tauri::command]
+pub async fn get_item_docs(
+    project_path: String,
+    item_path: String,
+    render: Option<RenderMode>, // "raw" (default), "plain" or "html"
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<DocItem, AppError> {
+    log::info!("[Tauri Command] get_item_docs: {} in {} ({:?})", item_path, project_path, render);
+    app_state.item_docs(&project_path, &item_path, render.unwrap_or_default()).await
+}
+
+/// The source code of an item (e.g. a function body), read from the project at the span rustdoc recorded.
+#[tauri::command]
+pub async fn get_item_source(
+    project_path: String,
+    item_path: String,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<ItemSource, AppError> {
+    log::info!("[Tauri Command] get_item_source: {} in {}", item_path, project_path);
+    app_state.item_source(&project_path, &item_path).await
+}
+
+/// A page of the items directly below a module (the crate root by default) or type of a project.
+#[tauri::command]
+pub async fn list_crate_items(
+    project_path: String,
+    module_path: Option<String>,
+    item_type: Option<String>,
+    page: Option<usize>, // 1-based
+    page_size: Option<usize>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<ItemListing, AppError> {
+    log::info!("[Tauri Command] list_crate_items: {:?} in {} (page {:?})", module_path, project_path, page);
+    app_state.list_items(&project_path, module_path.as_deref(), item_type.as_deref(), page.unwrap_or(1), page_size.unwrap_or(browse::DEFAULT_PAGE_SIZE)).await
+}
+
+/// Items added, removed and changed (docs or signature) from `project_a` to `project_b`, e.g.
+/// `/work/app@0.6.3` and `/work/app`.
+#[tauri::command]
+pub async fn diff_documentation(
+    project_a: String,
+    project_b: String,
+    limit: Option<usize>, // entries per list; totals are always complete
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<DocDiff, AppError> {
+    log::info!("[Tauri Command] diff_documentation: {} -> {}", project_a, project_b);
+    app_state.diff_projects(&project_a, &project_b, limit.unwrap_or(diff::DEFAULT_DIFF_LIMIT)).await
+}
+
+/// Writes a project's docs to `output_dir`, one markdown (or JSON) file per module plus an index.
+#[tauri::command]
+pub async fn export_project_docs(
+    project_path: String,
+    output_dir: String,
+    format: Option<ExportFormat>, // "markdown" (default) or "json"
+    include_dependencies: Option<bool>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<ExportSummary, AppError> {
+    log::info!("[Tauri Command] export_project_docs: {} -> {} ({:?})", project_path, output_dir, format);
+    app_state.export_docs(&project_path, format.unwrap_or_default(), Path::new(&output_dir), include_dependencies.unwrap_or(false)).await
+}
+
+/// Packs a processed project (docs, embeddings, metadata) into one compressed archive that can be
+/// imported on another machine without running rustdoc or the embedder.
+#[tauri::command]
+pub async fn export_project_archive(
+    project_path: String,
+    archive_path: String,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<ArchiveManifest, AppError> {
+    log::info!("[Tauri Command] export_project_archive: {} -> {}", project_path, archive_path);
+    archive::export_project_archive(&app_state, &project_path, PathBuf::from(archive_path)).await
+}
+
+/// Loads an archive written by `export_project_archive`, under `project_path` if given (the path
+/// differs between machines) or the path it was exported from.
+#[tauri::command]
+pub async fn import_project_archive(
+    archive_path: String,
+    project_path: Option<String>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<ImportedArchive, AppError> {
+    log::info!("[Tauri Command] import_project_archive: {} as {:?}", archive_path, project_path);
+    archive::import_project_archive(app_state.inner().clone(), PathBuf::from(archive_path), project_path).await
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct Settings {
+    pub config_path: PathBuf,
+    /// The effective settings: the config file plus `RUST_LLM_MCP_*` env overrides.
+    pub settings: AppConfig,
+}
+
+/// All settings, in the shape of `config.toml`.
+#[tauri::command]
+pub async fn get_settings(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<Settings, AppError> {
+    log::info!("[Tauri Command] get_settings");
+    Ok(Settings { config_path: app_state.config.path().to_path_buf(), settings: (*app_state.config.current()).clone() })
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SettingsUpdate {
+    pub settings: AppConfig,
+    /// Changed sections that only take effect after the app restarts (`http_api`, `storage`).
+    pub restart_required: Vec<String>,
+}
+
+/// Saves `patch`, a partial `get_settings` object (e.g. `{"search": {"page_size": 50}}`), to the
+/// config file. Most settings are read on each use and apply immediately; a new embedding model
+/// is loaded and the MCP server restarted here. Unknown keys and invalid values fail with
+/// `invalid_argument` and nothing is saved.
+#[tauri::command]
+pub async fn update_settings(
+    patch: serde_json::Value,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<SettingsUpdate, AppError> {
+    log::info!("[Tauri Command] update_settings: {}", patch);
+    let previous = app_state.config.current();
+    let config = app_state.config.update_from_patch(&patch)
+        .map_err(|e| AppError::InvalidArgument { message: format!("{:#}", e) })?;
+
+    if config.embedder != previous.embedder {
+        reload_embedder(previous.embedder.clone(), config.embedder.clone(), &app_state).await?;
+    }
+    let (old_server, new_server) = (&previous.server, &config.server);
+    if (old_server.enabled, old_server.transport, &old_server.host, old_server.port) != (new_server.enabled, new_server.transport, &new_server.host, new_server.port) {
+        restart_with_saved_server_settings(old_server.clone(), &app_state).await?;
+    }
+    let mut restart_required = Vec::new();
+    if config.http_api != previous.http_api {
+        restart_required.push("http_api".to_string());
+    }
+    if config.storage != previous.storage {
+        restart_required.push("storage".to_string());
+    }
+    Ok(SettingsUpdate { settings: (*app_state.config.current()).clone(), restart_required })
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct RecentLogs {
+    /// Where the daily log files are kept, for attaching to a bug report.
+    pub log_dir: PathBuf,
+    pub entries: Vec<LogEntry>,
+}
+
+/// The last `lines` (200 by default) backend log entries since startup, oldest first, optionally
+/// only those at `level` (`error`, `warn`, `info`, `debug` or `trace`) or more severe.
+#[tauri::command]
+pub async fn get_recent_logs(
+    lines: Option<usize>,
+    level: Option<String>,
+) -> Result<RecentLogs, AppError> {
+    let min_level = match level.as_deref() {
+        Some(level) => level.parse::<tracing::Level>()
+            .map_err(|_| AppError::InvalidArgument { message: format!("Unknown log level '{}'; expected error, warn, info, debug or trace", level) })?,
+        None => tracing::Level::TRACE,
+    };
+    Ok(RecentLogs { log_dir: logging::log_dir(), entries: logging::recent_entries(lines.unwrap_or(logging::DEFAULT_RECENT_LOG_LINES), min_level) })
+}
+
+/// Disk usage of the cache directory, broken down by what `clear_cache` can delete.
+#[tauri::command]
+pub async fn get_cache_stats(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<CacheStats, AppError> {
+    log::info!("[Tauri Command] get_cache_stats");
+    cache::cache_stats(&app_state).await
+}
+
+/// Deletes downloaded models, rustdoc JSON, cached embeddings and/or isolated build artifacts to
+/// reclaim disk space. Processed projects are kept.
+#[tauri::command]
+pub async fn clear_cache(
+    models: Option<bool>,
+    rustdoc_json: Option<bool>,
+    embeddings: Option<bool>,
+    build_artifacts: Option<bool>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<ClearedCache, AppError> {
+    let options = ClearCacheOptions {
+        models: models.unwrap_or(false),
+        rustdoc_json: rustdoc_json.unwrap_or(false),
+        embeddings: embeddings.unwrap_or(false),
+        build_artifacts: build_artifacts.unwrap_or(false),
+    };
+    log::info!("[Tauri Command] clear_cache: {:?}", options);
+    cache::clear_cache(&app_state, options).await
+}
+
+/// Moves the cache to `path` (e.g. a bigger disk), or back to the default location if `None`,
+/// from the next start. Nothing is copied: processed projects are rebuilt from the new location
+/// unless the old directory's contents are moved there first. Returns the new location.
+#[tauri::command]
+pub async fn set_cache_dir(
+    path: Option<String>,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<PathBuf, AppError> {
+    log::info!("[Tauri Command] set_cache_dir: {:?}", path);
+    let path = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).map(PathBuf::from);
+    if let Some(dir) = &path {
+        if !dir.is_absolute() {
+            return Err(AppError::InvalidArgument { message: format!("The cache directory must be an absolute path: {}", dir.display()) });
+        }
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::InvalidArgument { message: format!("Cannot create {}: {}", dir.display(), e) })?;
+    }
+    let config = app_state.config.update(|c| c.storage.cache_dir = path)?;
+    Ok(config.cache_dir())
+}
+
+/// The loaded embedding model and the device it runs on.
+#[tauri::command]
+pub async fn get_embedder_info(
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<EmbedderInfo, AppError> {
+    Ok(embedder::global_embedder_info(&app_state.config.current().embedder).await?)
+}
+
+/// Whether the embedding model is still downloading or loading, ready, or failed to load.
+#[tauri::command]
+pub async fn get_embedder_status() -> Result<EmbedderStatus, AppError> {
+    Ok(embedder::embedder_status())
+}
+
+/// Cancels a queued or running job (e.g. the id returned by `invoke_process_rust_project`).
+/// A running `cargo rustdoc` is killed and embedding stops after the current batch; the job then
+/// finishes with status `cancelled`.
+#[tauri::command]
+pub async fn cancel_processing(
+    job_id: JobId,
+    app_state: State<'_, Arc<AppState>>,
+) -> Result<(), AppError> {
+    log::info!("[Tauri Command] cancel_processing: job {}", job_id);
+    app_state.jobs.cancel(job_id)
 }
@@ -0,0 +1,195 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::process::Command;
+
+use crate::config::AppConfig;
+use crate::embedder::{self, GLOBAL_EMBEDDER};
+use crate::rustdoc_processor::SUPPORTED_FORMAT_VERSIONS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// One actionable finding from the startup checks.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompatWarning {
+    /// Stable identifier the UI can switch on, e.g. "toolchain_missing".
+    pub code: String,
+    pub severity: Severity,
+    pub message: String,
+    /// Shell command that fixes the problem, if there is one.
+    pub fix_command: Option<String>,
+    /// Documentation explaining the fix.
+    pub fix_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct CompatReport {
+    pub toolchain: String,
+    /// Output of `rustdoc --version` for the configured toolchain.
+    pub rustdoc_version: Option<String>,
+    /// `format_version` of the JSON produced by that rustdoc.
+    pub rustdoc_format_version: Option<u32>,
+    pub model_repo: String,
+    pub model_cached: bool,
+    pub embedder_ready: bool,
+    pub warnings: Vec<CompatWarning>,
+}
+
+/// Probes the configured rustdoc toolchain and embedding model. Blocking; runs external processes.
+pub fn run_startup_checks(config: &AppConfig) -> CompatReport {
+    let toolchain = config.rustdoc.toolchain.clone();
+    let mut report = CompatReport {
+        toolchain: toolchain.clone(),
+        model_repo: config.embedder.model_repo.clone(),
+        ..Default::default()
+    };
+
+    match rustdoc_version(&toolchain) {
+        Ok(version) => {
+            log::info!("Found rustdoc for toolchain {}: {}", toolchain, version);
+            report.rustdoc_version = Some(version);
+            match probe_format_version(&toolchain) {
+                Ok(format_version) => {
+                    report.rustdoc_format_version = Some(format_version);
+                    if let Some(warning) = check_format_version(format_version) {
+                        report.warnings.push(warning);
+                    }
+                }
+                Err(e) => report.warnings.push(CompatWarning {
+                    code: "rustdoc_json_unavailable".to_string(),
+                    severity: Severity::Error,
+                    message: format!("Toolchain '{}' could not produce rustdoc JSON: {:#}", toolchain, e),
+                    fix_command: Some("rustup toolchain install nightly".to_string()),
+                    fix_url: Some("https://doc.rust-lang.org/nightly/rustdoc/unstable-features.html#--output-format-output-format".to_string()),
+                }),
+            }
+        }
+        Err(e) => {
+            log::warn!("rustdoc for toolchain {} not available: {:?}", toolchain, e);
+            report.warnings.push(CompatWarning {
+                code: "toolchain_missing".to_string(),
+                severity: Severity::Error,
+                message: format!("The '{}' toolchain is not installed; processing projects will fail.", toolchain),
+                fix_command: Some(format!("rustup toolchain install {}", toolchain)),
+                fix_url: Some("https://rust-lang.github.io/rustup/concepts/toolchains.html".to_string()),
+            });
+        }
+    }
+
+    report.model_cached = model_is_cached(&config.embedder.model_repo, &config.embedder.model_revision);
+    report.embedder_ready = GLOBAL_EMBEDDER.lock().map(|guard| guard.is_some()).unwrap_or(false);
+    // The embedder loads in the background; only a finished attempt that failed is worth a warning.
+    if !report.embedder_ready && !embedder::embedder_status().is_loading() {
+        report.warnings.push(CompatWarning {
+            code: "embedder_unavailable".to_string(),
+            severity: Severity::Error,
+            message: format!("The embedding model {} failed to load; semantic search is disabled. Check the logs for details.", config.embedder.model_repo),
+            fix_command: None,
+            fix_url: Some(format!("https://huggingface.co/{}", config.embedder.model_repo)),
+        });
+    } else if !report.model_cached {
+        report.warnings.push(CompatWarning {
+            code: "model_not_cached".to_string(),
+            severity: Severity::Info,
+            message: format!("Model files for {} are not in the Hugging Face cache and will be downloaded (several GB).", config.embedder.model_repo),
+            fix_command: None,
+            fix_url: Some(format!("https://huggingface.co/{}", config.embedder.model_repo)),
+        });
+    }
+
+    log::info!("Startup compatibility checks finished with {} warning(s).", report.warnings.len());
+    report
+}
+
+/// Returns a warning if rustdoc emits a JSON format this parser wasn't written for.
+pub fn check_format_version(format_version: u32) -> Option<CompatWarning> {
+    if SUPPORTED_FORMAT_VERSIONS.contains(&format_version) {
+        return None;
+    }
+    // Newer formats are still parsed; older ones are refused outright.
+    let (direction, severity, consequence, fix) = if format_version > *SUPPORTED_FORMAT_VERSIONS.end() {
+        ("newer", Severity::Warning, "parsing may miss items", "Pin an older nightly in the [rustdoc] toolchain setting, e.g. toolchain = \"nightly-2024-05-01\".")
+    } else {
+        ("older", Severity::Error, "processing will fail", "Update the toolchain with `rustup update nightly`.")
+    };
+    Some(CompatWarning {
+        code: "rustdoc_format_unsupported".to_string(),
+        severity,
+        message: format!(
+            "rustdoc JSON format_version {} is {} than the supported range {}-{}; {}. {}",
+            format_version, direction, SUPPORTED_FORMAT_VERSIONS.start(), SUPPORTED_FORMAT_VERSIONS.end(), consequence, fix
+        ),
+        fix_command: (direction == "older").then(|| "rustup update nightly".to_string()),
+        fix_url: Some("https://github.com/rust-lang/rust/blob/master/src/rustdoc-json-types/lib.rs".to_string()),
+    })
+}
+
+fn rustdoc_version(toolchain: &str) -> Result<String> {
+    let output = Command::new("rustdoc")
+        .arg(format!("+{}", toolchain))
+        .arg("--version")
+        .output()
+        .context("Failed to execute rustdoc")?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Documents a one-line crate with `toolchain` and reads the format_version from the result, i.e.
+/// checks that the toolchain can produce rustdoc JSON at all. Blocking.
+pub fn probe_format_version(toolchain: &str) -> Result<u32> {
+    let probe_dir = tempfile::tempdir().context("Failed to create probe directory")?;
+    let src = probe_dir.path().join("compat_probe.rs");
+    fs::write(&src, "//! probe\npub fn probe() {}\n")?;
+
+    let output = Command::new("rustdoc")
+        .arg(format!("+{}", toolchain))
+        .args(crate::rustdoc_processor::json_output_args(toolchain))
+        .arg("--crate-type").arg("lib")
+        .arg("--out-dir").arg(probe_dir.path())
+        .arg(&src)
+        .output()
+        .context("Failed to execute rustdoc")?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let json = fs::read_to_string(probe_dir.path().join("compat_probe.json"))
+        .context("rustdoc did not write compat_probe.json")?;
+    let root: serde_json::Value = serde_json::from_str(&json).context("Probe output is not valid JSON")?;
+    root.get("format_version")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+        .context("Probe output has no format_version")
+}
+
+fn model_is_cached(model_repo: &str, revision: &str) -> bool {
+    let repo = hf_hub::Repo::with_revision(model_repo.to_string(), hf_hub::RepoType::Model, revision.to_string());
+    hf_hub::Cache::default().repo(repo).get("config.json").is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_format_version() {
+        assert!(check_format_version(*SUPPORTED_FORMAT_VERSIONS.start()).is_none());
+
+        let newer = check_format_version(SUPPORTED_FORMAT_VERSIONS.end() + 1).unwrap();
+        assert_eq!(newer.code, "rustdoc_format_unsupported");
+        assert!(newer.message.contains("newer"));
+        assert!(newer.fix_command.is_none());
+
+        let older = check_format_version(SUPPORTED_FORMAT_VERSIONS.start() - 1).unwrap();
+        assert_eq!(older.fix_command.as_deref(), Some("rustup update nightly"));
+        assert!(matches!(older.severity, Severity::Error));
+    }
+}
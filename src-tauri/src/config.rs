@@ -0,0 +1,1001 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+
+use crate::embedder::ModelPreset;
+
+// Prefix for environment variable overrides, e.g. RUST_LLM_MCP_SERVER_PORT=3002
+const ENV_PREFIX: &str = "RUST_LLM_MCP_";
+const CONFIG_FILE_NAME: &str = "config.toml";
+// How often the config file is checked for modifications.
+const RELOAD_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Isolated cargo target directories (`rustdoc.isolated_target_dir`), under the cache directory.
+pub const CARGO_TARGET_DIR_NAME: &str = "cargo_target";
+// The `embedder` keys an `embedder.preset` overwrites (see `ModelPreset::apply`).
+const PRESET_FIELDS: [&str; 5] = ["model_repo", "model_revision", "max_length", "chunk_tokens", "chunk_overlap"];
+
+// --- Config Sections ---
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbedderSettings {
+    /// A built-in model choice ("fast", "balanced" or "best"). When set, it replaces
+    /// `model_repo`, `model_revision`, `max_length`, `chunk_tokens` and `chunk_overlap`.
+    pub preset: Option<ModelPreset>,
+    pub model_repo: String,
+    pub model_revision: String,
+    pub device: DevicePreference,
+    pub dtype: ModelDType,
+    /// Docs longer than this many tokens are split into overlapping chunks, each embedded separately.
+    pub chunk_tokens: usize,
+    /// Tokens shared by consecutive chunks.
+    pub chunk_overlap: usize,
+    /// Most sentences run through the model in one forward pass. Lower it if the GPU runs out of memory.
+    pub max_batch_size: usize,
+    /// Load quantized GGUF weights instead of the full-precision model (much less RAM).
+    /// The tokenizer still comes from `model_repo`.
+    pub quantized: Option<QuantizedModelSettings>,
+    /// What runs the model: candle (default), ONNX Runtime or a remote API.
+    pub backend: EmbedderBackend,
+    /// ONNX export in `model_repo` that the `onnx` backend loads.
+    pub onnx_file: String,
+    /// The endpoint the `remote` backend calls. `model_repo` then only supplies the tokenizer
+    /// docs are chunked with.
+    pub remote: RemoteEmbedderSettings,
+    /// How token states become one vector. Must match how the model was trained.
+    pub pooling: PoolingStrategy,
+    /// Longest input, in tokens, the model is given; longer texts (code examples, long queries)
+    /// are truncated. Docs are chunked well below this.
+    pub max_length: usize,
+    /// Prepended to search queries before embedding. `None` uses the model's known prefix (e.g.
+    /// BGE's "Represent this sentence for searching relevant passages: "); `""` disables it.
+    pub query_prefix: Option<String>,
+    /// Prepended to indexed docs and examples, like `query_prefix` (e.g. E5's "passage: ").
+    /// Changing it requires re-embedding.
+    pub passage_prefix: Option<String>,
+}
+
+impl Default for EmbedderSettings {
+    fn default() -> Self {
+        Self {
+            preset: None,
+            model_repo: "BAAI/bge-code-v1".to_string(),
+            model_revision: "main".to_string(),
+            device: DevicePreference::Auto,
+            dtype: ModelDType::Auto,
+            chunk_tokens: 512,
+            chunk_overlap: 64,
+            max_batch_size: 16,
+            quantized: None,
+            backend: EmbedderBackend::Candle,
+            onnx_file: "onnx/model.onnx".to_string(),
+            remote: RemoteEmbedderSettings::default(),
+            pooling: PoolingStrategy::Auto,
+            max_length: 2048,
+            query_prefix: None,
+            passage_prefix: None,
+        }
+    }
+}
+
+/// A GGUF file holding a quantized variant of the embedding model.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuantizedModelSettings {
+    /// Hugging Face repo containing the file.
+    pub repo: String,
+    /// File name in `repo`, e.g. a Q4_K_M or Q8_0 variant.
+    pub file: String,
+}
+
+/// An OpenAI-compatible embeddings API, e.g. OpenAI itself or a local Ollama.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteEmbedderSettings {
+    /// API base URL (`.../v1`) or the full `/embeddings` endpoint.
+    pub url: String,
+    /// Model name the API expects, e.g. "text-embedding-3-small".
+    pub model: String,
+    /// Sent as a bearer token. Prefer `RUST_LLM_MCP_EMBEDDER_REMOTE_API_KEY` over the file.
+    pub api_key: Option<String>,
+    pub timeout_secs: u64,
+    /// Retries of a request after connection errors, 429 and 5xx responses, with exponential backoff.
+    pub max_retries: u32,
+}
+
+impl Default for RemoteEmbedderSettings {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:11434/v1".to_string(),
+            model: "nomic-embed-text".to_string(),
+            api_key: None,
+            timeout_secs: 60,
+            max_retries: 3,
+        }
+    }
+}
+
+/// What runs the embedding model. `onnx` runs an ONNX export (`onnx_file`) on the CPU with ONNX
+/// Runtime, for machines where candle's GPU builds don't work; it needs a build with the `ort`
+/// feature. The tokenizer and pooling are the same either way. `remote` sends the texts to
+/// `remote.url` instead of running a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedderBackend {
+    Candle,
+    Onnx,
+    Remote,
+}
+
+impl std::str::FromStr for EmbedderBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "candle" => Ok(EmbedderBackend::Candle),
+            "onnx" => Ok(EmbedderBackend::Onnx),
+            "remote" => Ok(EmbedderBackend::Remote),
+            other => Err(format!("unknown embedder backend '{}' (expected candle, onnx or remote)", other)),
+        }
+    }
+}
+
+/// Device the embedding model runs on. `auto` uses CUDA or Metal when available, otherwise the CPU.
+/// Metal needs a build with the `metal` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DevicePreference {
+    Auto,
+    Cpu,
+    Cuda,
+    Metal,
+}
+
+/// Pooling of the model's final hidden states into one embedding, over unpadded tokens only.
+/// `auto` follows the repo's sentence-transformers `1_Pooling/config.json`, falling back to `cls`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolingStrategy {
+    Auto,
+    /// The first token's state (BERT-style models).
+    Cls,
+    /// The average of all token states.
+    Mean,
+    /// The last token's state (decoder models such as Qwen2-based embedders).
+    LastToken,
+}
+
+impl PoolingStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PoolingStrategy::Auto => "auto",
+            PoolingStrategy::Cls => "cls",
+            PoolingStrategy::Mean => "mean",
+            PoolingStrategy::LastToken => "last_token",
+        }
+    }
+}
+
+impl std::str::FromStr for PoolingStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(PoolingStrategy::Auto),
+            "cls" => Ok(PoolingStrategy::Cls),
+            "mean" => Ok(PoolingStrategy::Mean),
+            "last_token" => Ok(PoolingStrategy::LastToken),
+            other => Err(format!("unknown pooling strategy '{}' (expected auto, cls, mean or last_token)", other)),
+        }
+    }
+}
+
+/// Weight dtype for the embedding model. `auto` uses the model's default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelDType {
+    Auto,
+    F32,
+    F16,
+    Bf16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RustdocSettings {
+    /// Toolchain passed to cargo as `+<toolchain>`.
+    pub toolchain: String,
+    /// Toolchains for particular projects, by project path, e.g. `"/work/legacy" = "nightly-2024-05-01"`.
+    pub project_toolchains: BTreeMap<String, String>,
+    pub document_private_items: bool,
+    /// Drop items that aren't `pub` after parsing, so only the public API is embedded and
+    /// indexed even when `document_private_items` documents the rest.
+    pub skip_private_items: bool,
+    /// Build in a target directory of our own under the cache (`--target-dir`), one per project,
+    /// instead of the project's: doc generation then never touches the user's `target/` or waits
+    /// on their builds, at the cost of compiling the dependencies once more.
+    pub isolated_target_dir: bool,
+    /// The `--target-dir` to pass to cargo; filled in by `AppConfig::rustdoc_for_project`.
+    #[serde(skip)]
+    pub cargo_target_dir: Option<PathBuf>,
+    pub sandbox: SandboxSettings,
+}
+
+impl RustdocSettings {
+    /// The settings to document `project_path` with: `toolchain` replaced by its entry in
+    /// `project_toolchains`, if any.
+    pub fn for_project(&self, project_path: &str) -> RustdocSettings {
+        let mut settings = self.clone();
+        if let Some(toolchain) = self.project_toolchains.get(project_path) {
+            settings.toolchain = toolchain.clone();
+        }
+        settings
+    }
+}
+
+impl Default for RustdocSettings {
+    fn default() -> Self {
+        Self {
+            toolchain: "nightly".to_string(),
+            project_toolchains: BTreeMap::new(),
+            document_private_items: true,
+            skip_private_items: false,
+            isolated_target_dir: false,
+            cargo_target_dir: None,
+            sandbox: SandboxSettings::default(),
+        }
+    }
+}
+
+/// Hardened mode for running `cargo rustdoc`, which executes the project's build scripts and proc macros.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxSettings {
+    pub enabled: bool,
+    /// Pass `--offline --locked` so the build can't fetch or update dependencies.
+    pub offline: bool,
+    /// Start cargo with an empty environment plus a small allowlist (PATH, HOME, CARGO_HOME, ...).
+    pub scrub_env: bool,
+    /// Extra variables kept when `scrub_env` is on, e.g. "HTTPS_PROXY".
+    pub env_allowlist: Vec<String>,
+    /// Jail/container command cargo is run through, e.g. ["firejail", "--quiet", "--whitelist={project_dir}"].
+    /// `{project_dir}` is replaced with the project path.
+    pub wrapper_command: Vec<String>,
+    /// Projects outside these directories need explicit confirmation before they're built.
+    pub trusted_dirs: Vec<PathBuf>,
+}
+
+impl Default for SandboxSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            offline: true,
+            scrub_env: true,
+            env_allowlist: Vec::new(),
+            wrapper_command: Vec::new(),
+            trusted_dirs: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchSettings {
+    pub default_num_results: usize,
+    /// Number of characters kept in `description_snippet`.
+    pub snippet_length: usize,
+    /// Share of the BM25 keyword score in hybrid search (0 = embeddings only, 1 = keywords only).
+    pub keyword_weight: f32,
+    /// Results per `query_documentation` response; the rest are served through `next_cursor`.
+    pub page_size: usize,
+    /// How long the remaining pages of a query stay available after the last page was served.
+    pub cursor_ttl_secs: u64,
+    /// Recent queries kept in the query history (`get_query_history`); 0 records none.
+    pub history_size: usize,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self {
+            default_num_results: 5,
+            snippet_length: 300,
+            keyword_weight: 0.3,
+            page_size: 20,
+            cursor_ttl_secs: 300,
+            history_size: 1000,
+        }
+    }
+}
+
+/// Cross-encoder that rescores the top embedding hits of a query before they're truncated.
+/// It runs on the embedder's device and is downloaded and loaded on first use.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RerankerSettings {
+    /// Rerank queries that don't say otherwise.
+    pub enabled: bool,
+    pub model_repo: String,
+    pub model_revision: String,
+    /// Hits scored by the cross-encoder per query.
+    pub candidates: usize,
+    /// Query + document tokens per pair; longer pairs are truncated.
+    pub max_length: usize,
+}
+
+impl Default for RerankerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model_repo: "BAAI/bge-reranker-base".to_string(),
+            model_revision: "main".to_string(),
+            candidates: 50,
+            max_length: 512,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerSettings {
+    pub enabled: bool,
+    pub transport: McpTransport,
+    /// Only used by the HTTP transport.
+    pub host: String,
+    pub port: u16,
+    /// Refuse HTTP MCP and `http_api` requests without an `Authorization: Bearer <auth_token>` header.
+    pub auth_required: bool,
+    /// Shared secret for `auth_required`; generated on first run (see `auth::ensure_token`).
+    pub auth_token: Option<String>,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            transport: McpTransport::Http,
+            host: "127.0.0.1".to_string(),
+            port: 3001,
+            auth_required: false,
+            auth_token: None,
+        }
+    }
+}
+
+/// How MCP clients reach the server. Clients such as Claude Desktop launch the server as a
+/// subprocess and only speak stdio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum McpTransport {
+    Http,
+    Stdio,
+    Both,
+}
+
+impl McpTransport {
+    pub fn uses_http(self) -> bool {
+        matches!(self, McpTransport::Http | McpTransport::Both)
+    }
+
+    pub fn uses_stdio(self) -> bool {
+        matches!(self, McpTransport::Stdio | McpTransport::Both)
+    }
+}
+
+impl std::str::FromStr for McpTransport {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "http" => Ok(McpTransport::Http),
+            "stdio" => Ok(McpTransport::Stdio),
+            "both" => Ok(McpTransport::Both),
+            other => Err(format!("expected http, stdio or both, got '{}'", other)),
+        }
+    }
+}
+
+/// A plain HTTP server next to the MCP one, for tools that don't speak MCP. Nothing is served
+/// unless at least one endpoint is enabled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpApiSettings {
+    /// Serve the loaded embedding model at `POST /v1/embeddings` (OpenAI-compatible).
+    pub embeddings_enabled: bool,
+    /// Serve a JSON API over the doc index: `GET /api/projects`, `POST /api/query`, `GET /api/item`.
+    pub rest_enabled: bool,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for HttpApiSettings {
+    fn default() -> Self {
+        Self {
+            embeddings_enabled: false,
+            rest_enabled: false,
+            host: "127.0.0.1".to_string(),
+            port: 3002,
+        }
+    }
+}
+
+impl HttpApiSettings {
+    pub fn any_enabled(&self) -> bool {
+        self.embeddings_enabled || self.rest_enabled
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobSettings {
+    /// How many background jobs (project processing etc.) may run at once.
+    pub max_concurrent_jobs: usize,
+    /// How many jobs may wait for a slot; more are refused with `queue_full`.
+    pub max_queued_jobs: usize,
+}
+
+impl Default for JobSettings {
+    fn default() -> Self {
+        Self { max_concurrent_jobs: 1, max_queued_jobs: 32 }
+    }
+}
+
+/// Repositories cloned by `process_git_repository`, under `<cache_dir>/git`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct GitSettings {
+    pub cleanup: SourceCleanup,
+}
+
+/// What happens to a cloned repository once it is indexed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceCleanup {
+    /// Keep the checkout, so `get_item_source` works and the next run only fetches changes.
+    #[default]
+    Keep,
+    /// Delete it after processing, successful or not, to save disk space.
+    AfterProcessing,
+}
+
+/// Desktop notifications when a long job or the model download finishes while no window is focused.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// Jobs that finish faster than this don't notify.
+    pub min_job_secs: u64,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self { enabled: true, min_job_secs: 10 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct StorageSettings {
+    /// Overrides the default cache directory (BaseDirs::cache_dir()/rust_llm_mcp_server_cache).
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// The full application configuration. Every section falls back to its defaults,
+/// so a config file only needs to contain the keys the user wants to change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AppConfig {
+    pub embedder: EmbedderSettings,
+    pub rustdoc: RustdocSettings,
+    pub search: SearchSettings,
+    pub reranker: RerankerSettings,
+    pub server: ServerSettings,
+    pub http_api: HttpApiSettings,
+    pub storage: StorageSettings,
+    pub jobs: JobSettings,
+    pub git: GitSettings,
+    pub notifications: NotificationSettings,
+}
+
+impl AppConfig {
+    /// Parses a config from TOML text. Missing sections/keys use defaults.
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let mut config: Self = toml::from_str(content).context("Failed to parse config TOML")?;
+        if let Some(preset) = config.embedder.preset {
+            preset.apply(&mut config.embedder);
+        }
+        Ok(config)
+    }
+
+    /// Reads the config file at `path`, or returns the defaults if it does not exist.
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            log::info!("No config file at {}. Using defaults.", path.display());
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        Self::from_toml_str(&content)
+            .with_context(|| format!("Invalid config file: {}", path.display()))
+    }
+
+    /// Applies `RUST_LLM_MCP_*` environment variable overrides on top of the file values.
+    pub fn apply_env_overrides(&mut self) {
+        self.apply_overrides_from(|key| std::env::var(format!("{}{}", ENV_PREFIX, key)).ok());
+    }
+
+    // Split out from apply_env_overrides so tests don't have to touch the process environment.
+    fn apply_overrides_from<F: Fn(&str) -> Option<String>>(&mut self, lookup: F) {
+        // First, so the individual embedder variables below still win over the preset.
+        if let Some(v) = lookup("EMBEDDER_PRESET") {
+            match v.parse::<ModelPreset>() {
+                Ok(preset) => preset.apply(&mut self.embedder),
+                Err(e) => log::warn!("Ignoring invalid {}EMBEDDER_PRESET '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("EMBEDDER_MODEL_REPO") { self.embedder.model_repo = v; }
+        if let Some(v) = lookup("EMBEDDER_MODEL_REVISION") { self.embedder.model_revision = v; }
+        if let Some(v) = lookup("EMBEDDER_MAX_LENGTH") {
+            match v.parse() {
+                Ok(max_length) => self.embedder.max_length = max_length,
+                Err(e) => log::warn!("Ignoring invalid {}EMBEDDER_MAX_LENGTH '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("EMBEDDER_QUERY_PREFIX") { self.embedder.query_prefix = Some(v); }
+        if let Some(v) = lookup("EMBEDDER_PASSAGE_PREFIX") { self.embedder.passage_prefix = Some(v); }
+        if let Some(v) = lookup("EMBEDDER_BACKEND") {
+            match v.parse() {
+                Ok(backend) => self.embedder.backend = backend,
+                Err(e) => log::warn!("Ignoring invalid {}EMBEDDER_BACKEND '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("EMBEDDER_REMOTE_URL") { self.embedder.remote.url = v; }
+        if let Some(v) = lookup("EMBEDDER_REMOTE_MODEL") { self.embedder.remote.model = v; }
+        if let Some(v) = lookup("EMBEDDER_REMOTE_API_KEY") { self.embedder.remote.api_key = Some(v); }
+        if let Some(v) = lookup("EMBEDDER_POOLING") {
+            match v.parse() {
+                Ok(pooling) => self.embedder.pooling = pooling,
+                Err(e) => log::warn!("Ignoring invalid {}EMBEDDER_POOLING '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("RUSTDOC_TOOLCHAIN") { self.rustdoc.toolchain = v; }
+        if let Some(v) = lookup("RUSTDOC_ISOLATED_TARGET_DIR") {
+            match v.parse() {
+                Ok(isolated) => self.rustdoc.isolated_target_dir = isolated,
+                Err(e) => log::warn!("Ignoring invalid {}RUSTDOC_ISOLATED_TARGET_DIR '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("SERVER_HOST") { self.server.host = v; }
+        if let Some(v) = lookup("CACHE_DIR") { self.storage.cache_dir = Some(PathBuf::from(v)); }
+
+        if let Some(v) = lookup("SERVER_PORT") {
+            match v.parse() {
+                Ok(port) => self.server.port = port,
+                Err(e) => log::warn!("Ignoring invalid {}SERVER_PORT '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("SERVER_ENABLED") {
+            match v.parse() {
+                Ok(enabled) => self.server.enabled = enabled,
+                Err(e) => log::warn!("Ignoring invalid {}SERVER_ENABLED '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("SERVER_TRANSPORT") {
+            match v.parse() {
+                Ok(transport) => self.server.transport = transport,
+                Err(e) => log::warn!("Ignoring invalid {}SERVER_TRANSPORT '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("SERVER_AUTH_REQUIRED") {
+            match v.parse() {
+                Ok(required) => self.server.auth_required = required,
+                Err(e) => log::warn!("Ignoring invalid {}SERVER_AUTH_REQUIRED '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("SERVER_AUTH_TOKEN") {
+            self.server.auth_token = Some(v);
+        }
+        if let Some(v) = lookup("HTTP_API_PORT") {
+            match v.parse() {
+                Ok(port) => self.http_api.port = port,
+                Err(e) => log::warn!("Ignoring invalid {}HTTP_API_PORT '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("HTTP_API_EMBEDDINGS_ENABLED") {
+            match v.parse() {
+                Ok(enabled) => self.http_api.embeddings_enabled = enabled,
+                Err(e) => log::warn!("Ignoring invalid {}HTTP_API_EMBEDDINGS_ENABLED '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("HTTP_API_REST_ENABLED") {
+            match v.parse() {
+                Ok(enabled) => self.http_api.rest_enabled = enabled,
+                Err(e) => log::warn!("Ignoring invalid {}HTTP_API_REST_ENABLED '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("SANDBOX_ENABLED") {
+            match v.parse() {
+                Ok(enabled) => self.rustdoc.sandbox.enabled = enabled,
+                Err(e) => log::warn!("Ignoring invalid {}SANDBOX_ENABLED '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("MAX_CONCURRENT_JOBS") {
+            match v.parse() {
+                Ok(n) => self.jobs.max_concurrent_jobs = n,
+                Err(e) => log::warn!("Ignoring invalid {}MAX_CONCURRENT_JOBS '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("MAX_QUEUED_JOBS") {
+            match v.parse() {
+                Ok(n) => self.jobs.max_queued_jobs = n,
+                Err(e) => log::warn!("Ignoring invalid {}MAX_QUEUED_JOBS '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+        if let Some(v) = lookup("DEFAULT_NUM_RESULTS") {
+            match v.parse() {
+                Ok(n) => self.search.default_num_results = n,
+                Err(e) => log::warn!("Ignoring invalid {}DEFAULT_NUM_RESULTS '{}': {}", ENV_PREFIX, v, e),
+            }
+        }
+    }
+
+    /// The rustdoc settings for `project_path` (see `RustdocSettings::for_project`), with the
+    /// project's isolated target directory, `<cache_dir>/cargo_target/<name>-<hash of the path>`,
+    /// when `isolated_target_dir` is on. `cache_dir` is the one in use (`AppState::cache_dir`),
+    /// not a new `storage.cache_dir` awaiting a restart. The hash is SHA-256, so the directory
+    /// (and its build cache) stays the same across Rust releases.
+    pub fn rustdoc_for_project(&self, project_path: &str, cache_dir: &Path) -> RustdocSettings {
+        let mut settings = self.rustdoc.for_project(project_path);
+        if settings.isolated_target_dir {
+            let name = Path::new(project_path).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let digest = Sha256::digest(project_path.as_bytes());
+            let hash: String = digest[..4].iter().map(|byte| format!("{:02x}", byte)).collect();
+            settings.cargo_target_dir = Some(cache_dir.join(CARGO_TARGET_DIR_NAME).join(format!("{}-{}", name, hash)));
+        }
+        settings
+    }
+
+    /// Resolves the cache directory, honouring the `storage.cache_dir` override.
+    pub fn cache_dir(&self) -> PathBuf {
+        match &self.storage.cache_dir {
+            Some(dir) => dir.clone(),
+            None => {
+                let base_dirs = directories::BaseDirs::new().expect("Could not get base directories");
+                base_dirs.cache_dir().join("rust_llm_mcp_server_cache")
+            }
+        }
+    }
+}
+
+/// Default location of the config file: `<config_dir>/rust_llm_mcp_server/config.toml`.
+pub fn default_config_path() -> PathBuf {
+    let base_dirs = directories::BaseDirs::new().expect("Could not get base directories");
+    base_dirs.config_dir().join("rust_llm_mcp_server").join(CONFIG_FILE_NAME)
+}
+
+// --- Config Handle ---
+
+/// Shared handle to the live configuration.
+/// Subsystems either read `current()` on each use (picks up changes automatically)
+/// or `subscribe()` to react to changes as they happen.
+#[derive(Clone)]
+pub struct ConfigHandle {
+    path: PathBuf,
+    sender: Arc<watch::Sender<Arc<AppConfig>>>,
+}
+
+impl ConfigHandle {
+    /// Loads the config from `path` (plus env overrides). Falls back to defaults if the file is invalid.
+    pub fn load(path: PathBuf) -> Self {
+        let config = load_with_overrides(&path).unwrap_or_else(|e| {
+            log::error!("Failed to load config from {}: {:?}. Using defaults.", path.display(), e);
+            let mut config = AppConfig::default();
+            config.apply_env_overrides();
+            config
+        });
+        let (sender, _receiver) = watch::channel(Arc::new(config));
+        Self { path, sender: Arc::new(sender) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn current(&self) -> Arc<AppConfig> {
+        self.sender.borrow().clone()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Arc<AppConfig>> {
+        self.sender.subscribe()
+    }
+
+    /// Applies `edit` to the config file's contents, writes the file back and reloads it.
+    /// Env overrides are not written to the file (and still win after the reload).
+    pub fn update<F: FnOnce(&mut AppConfig)>(&self, edit: F) -> Result<Arc<AppConfig>> {
+        let mut file_config = AppConfig::load_from_file(&self.path)?;
+        edit(&mut file_config);
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create config directory: {}", parent.display()))?;
+        }
+        let content = toml::to_string_pretty(&file_config).context("Failed to serialize config")?;
+        std::fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write config file: {}", self.path.display()))?;
+        self.reload()?;
+        Ok(self.current())
+    }
+
+    /// Applies `patch`, a partial config as JSON (e.g. `{"search": {"page_size": 50}}`), to the
+    /// config file like `update`: objects merge key by key, anything else replaces the value.
+    /// Setting a field the embedder preset controls (e.g. `embedder.model_repo`) clears the preset,
+    /// like `set_embedding_model`; setting one together with a preset is rejected. Nothing is
+    /// written if a key is unknown or the result isn't a valid config.
+    pub fn update_from_patch(&self, patch: &serde_json::Value) -> Result<Arc<AppConfig>> {
+        let file_config = AppConfig::load_from_file(&self.path)?;
+        let mut merged = serde_json::to_value(&file_config).context("Failed to serialize config")?;
+        merge_json(&mut merged, patch);
+        // The preset would overwrite the patched fields again when the file is read back.
+        if let Some(embedder) = patch.get("embedder").and_then(|v| v.as_object()) {
+            let preset_fields: Vec<&str> = PRESET_FIELDS.into_iter().filter(|field| embedder.contains_key(*field)).collect();
+            if !preset_fields.is_empty() {
+                if embedder.get("preset").is_some_and(|preset| !preset.is_null()) {
+                    anyhow::bail!("embedder.preset sets {} itself; patch one or the other", preset_fields.join(", "));
+                }
+                merged["embedder"]["preset"] = serde_json::Value::Null;
+            }
+        }
+        let patched: AppConfig = serde_json::from_value(merged).context("Invalid settings")?;
+        // Serde drops keys it doesn't know, so a typo would silently do nothing.
+        let applied = serde_json::to_value(&patched).context("Failed to serialize config")?;
+        let mut unknown = Vec::new();
+        find_unknown_keys(patch, &applied, "", &mut unknown);
+        if !unknown.is_empty() {
+            anyhow::bail!("Unknown settings: {}", unknown.join(", "));
+        }
+        self.update(|config| *config = patched)
+    }
+
+    /// Re-reads the config file and notifies subscribers if anything changed.
+    pub fn reload(&self) -> Result<bool> {
+        let new_config = load_with_overrides(&self.path)?;
+        let changed = self.sender.send_if_modified(|current| {
+            if **current == new_config {
+                false
+            } else {
+                *current = Arc::new(new_config);
+                true
+            }
+        });
+        if changed {
+            log::info!("Configuration reloaded from {}", self.path.display());
+        }
+        Ok(changed)
+    }
+
+    /// Spawns a background thread that polls the config file's mtime and reloads on change.
+    pub fn spawn_hot_reload(&self) {
+        let handle = self.clone();
+        std::thread::spawn(move || {
+            let mut last_modified = file_mtime(&handle.path);
+            loop {
+                std::thread::sleep(RELOAD_POLL_INTERVAL);
+                let modified = file_mtime(&handle.path);
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                if let Err(e) = handle.reload() {
+                    // Keep the previous config if the edited file is invalid.
+                    log::error!("Failed to reload config from {}: {:?}", handle.path.display(), e);
+                }
+            }
+        });
+        log::info!("Config hot reload enabled for {}", self.path.display());
+    }
+}
+
+// Merges `patch` into `target`: objects key by key, anything else (including arrays) replaces.
+fn merge_json(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    match (target, patch) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                match target.get_mut(key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch.clone(),
+    }
+}
+
+// Dotted paths of `patch` keys that don't exist in `applied`, the config they were merged into.
+// Map sections such as `rustdoc.project_toolchains` keep new keys, so those count as known.
+fn find_unknown_keys(patch: &serde_json::Value, applied: &serde_json::Value, prefix: &str, unknown: &mut Vec<String>) {
+    let serde_json::Value::Object(patch) = patch else { return };
+    for (key, value) in patch {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match applied.get(key) {
+            Some(applied_value) => find_unknown_keys(value, applied_value, &path, unknown),
+            None => unknown.push(path),
+        }
+    }
+}
+
+fn load_with_overrides(path: &Path) -> Result<AppConfig> {
+    let mut config = AppConfig::load_from_file(path)?;
+    config.apply_env_overrides();
+    Ok(config)
+}
+
+fn file_mtime(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_partial_toml_uses_defaults() -> Result<()> {
+        let config = AppConfig::from_toml_str("[server]\nport = 4000\n")?;
+        assert_eq!(config.server.port, 4000);
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.server.transport, McpTransport::Http);
+        assert_eq!(config.embedder, EmbedderSettings::default());
+        assert_eq!(config.search.default_num_results, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let vars: HashMap<&str, &str> = [
+            ("SERVER_PORT", "3005"),
+            ("RUSTDOC_TOOLCHAIN", "nightly-2024-05-01"),
+            ("DEFAULT_NUM_RESULTS", "not-a-number"),
+            ("SERVER_TRANSPORT", "Both"),
+            ("EMBEDDER_POOLING", "last_token"),
+            ("EMBEDDER_BACKEND", "ONNX"),
+        ].into_iter().collect();
+        let mut config = AppConfig::default();
+        config.apply_overrides_from(|k| vars.get(k).map(|v| v.to_string()));
+
+        assert_eq!(config.server.port, 3005);
+        assert_eq!(config.rustdoc.toolchain, "nightly-2024-05-01");
+        assert_eq!(config.server.transport, McpTransport::Both);
+        assert_eq!(config.embedder.pooling, PoolingStrategy::LastToken);
+        assert_eq!(config.embedder.backend, EmbedderBackend::Onnx);
+        // Invalid values are ignored
+        assert_eq!(config.search.default_num_results, 5);
+    }
+
+    #[test]
+    fn test_model_preset() -> Result<()> {
+        let config = AppConfig::from_toml_str("[embedder]\npreset = \"fast\"\nmodel_repo = \"ignored/model\"\n")?;
+        assert_eq!(config.embedder.preset, Some(ModelPreset::Fast));
+        assert_eq!(config.embedder.model_repo, "sentence-transformers/all-MiniLM-L6-v2");
+        assert_eq!(config.embedder.max_length, 256);
+        assert!(config.embedder.chunk_tokens < config.embedder.max_length);
+
+        let vars: HashMap<&str, &str> = [("EMBEDDER_PRESET", "Balanced"), ("EMBEDDER_MAX_LENGTH", "384")].into_iter().collect();
+        let mut config = AppConfig::default();
+        config.apply_overrides_from(|k| vars.get(k).map(|v| v.to_string()));
+        assert_eq!(config.embedder.model_repo, "BAAI/bge-base-en-v1.5");
+        assert_eq!(config.embedder.max_length, 384);
+        assert!("tiny".parse::<ModelPreset>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_toolchains() -> Result<()> {
+        let config = AppConfig::from_toml_str("[rustdoc.project_toolchains]\n\"/work/legacy\" = \"nightly-2024-05-01\"\n")?;
+        assert_eq!(config.rustdoc.for_project("/work/legacy").toolchain, "nightly-2024-05-01");
+        assert_eq!(config.rustdoc.for_project("/work/app").toolchain, "nightly");
+        Ok(())
+    }
+
+    #[test]
+    fn test_isolated_target_dir() -> Result<()> {
+        let config = AppConfig::from_toml_str("")?;
+        assert_eq!(config.rustdoc_for_project("/work/app", Path::new("/cache")).cargo_target_dir, None);
+
+        let config = AppConfig::from_toml_str("[rustdoc]\nisolated_target_dir = true\n")?;
+        let app = config.rustdoc_for_project("/work/app", Path::new("/cache")).cargo_target_dir.unwrap();
+        assert!(app.starts_with("/cache/cargo_target"));
+        // Pinned, so a change to the hash (which would orphan every build cache) is noticed.
+        assert_eq!(app, Path::new("/cache/cargo_target/app-70467eff"));
+        // Two checkouts of one project don't share (or lock) a target directory.
+        assert_ne!(Some(app), config.rustdoc_for_project("/other/app", Path::new("/cache")).cargo_target_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_notifies_subscribers() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "[search]\ndefault_num_results = 7\n")?;
+
+        let handle = ConfigHandle::load(path.clone());
+        let mut receiver = handle.subscribe();
+        assert_eq!(handle.current().search.default_num_results, 7);
+
+        assert!(!handle.reload()?, "Unchanged file should not notify");
+        std::fs::write(&path, "[search]\ndefault_num_results = 9\n")?;
+        assert!(handle.reload()?);
+        assert!(receiver.has_changed()?);
+        assert_eq!(receiver.borrow_and_update().search.default_num_results, 9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_writes_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("nested").join(CONFIG_FILE_NAME);
+        let handle = ConfigHandle::load(path.clone());
+
+        let updated = handle.update(|config| {
+            config.embedder.model_repo = "BAAI/bge-small-en-v1.5".to_string();
+            config.embedder.device = DevicePreference::Cpu;
+            config.embedder.quantized = Some(QuantizedModelSettings { repo: "someone/gguf".to_string(), file: "model-Q8_0.gguf".to_string() });
+        })?;
+        assert_eq!(updated.embedder.model_repo, "BAAI/bge-small-en-v1.5");
+        assert_eq!(handle.current().embedder.device, DevicePreference::Cpu);
+
+        let from_disk = AppConfig::load_from_file(&path)?;
+        assert_eq!(from_disk.embedder.model_repo, "BAAI/bge-small-en-v1.5");
+        assert_eq!(from_disk.embedder.dtype, ModelDType::Auto);
+        assert_eq!(from_disk.embedder.quantized.map(|q| q.file).as_deref(), Some("model-Q8_0.gguf"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_from_patch() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "[search]\ndefault_num_results = 7\n")?;
+        let handle = ConfigHandle::load(path.clone());
+
+        let updated = handle.update_from_patch(&serde_json::json!({
+            "search": { "page_size": 50 },
+            "rustdoc": { "project_toolchains": { "/work/legacy": "nightly-2024-05-01" } },
+        }))?;
+        // Keys left out of the patch keep their values.
+        assert_eq!(updated.search.default_num_results, 7);
+        assert_eq!(updated.search.page_size, 50);
+        assert_eq!(updated.rustdoc.for_project("/work/legacy").toolchain, "nightly-2024-05-01");
+        assert_eq!(AppConfig::load_from_file(&path)?.search.page_size, 50);
+
+        let unknown = handle.update_from_patch(&serde_json::json!({ "search": { "page_sise": 10 } }));
+        assert!(unknown.unwrap_err().to_string().contains("search.page_sise"));
+        assert!(handle.update_from_patch(&serde_json::json!({ "search": { "page_size": "many" } })).is_err());
+        assert_eq!(AppConfig::load_from_file(&path)?.search.page_size, 50);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_from_patch_clears_preset() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "[embedder]\npreset = \"fast\"\n")?;
+        let handle = ConfigHandle::load(path.clone());
+
+        // Other embedder keys leave the preset alone.
+        let updated = handle.update_from_patch(&serde_json::json!({ "embedder": { "max_batch_size": 8 } }))?;
+        assert_eq!(updated.embedder.preset, Some(ModelPreset::Fast));
+        assert!(handle.update_from_patch(&serde_json::json!({ "embedder": { "preset": "best", "model_repo": "acme/embed" } })).is_err());
+
+        let updated = handle.update_from_patch(&serde_json::json!({ "embedder": { "model_repo": "acme/embed" } }))?;
+        assert_eq!(updated.embedder.preset, None);
+        assert_eq!(updated.embedder.model_repo, "acme/embed");
+        // The rest of what the preset set stays.
+        assert_eq!(updated.embedder.max_length, 256);
+        assert_eq!(AppConfig::load_from_file(&path)?.embedder.model_repo, "acme/embed");
+        Ok(())
+    }
+}
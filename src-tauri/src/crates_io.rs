@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CRATES_IO_API_URL: &str = "https://crates.io/api/v1/crates";
+/// User-Agent identifying the client; crates.io rejects requests without one.
+pub const USER_AGENT: &str = concat!("rustdoc-llm-server/", env!("CARGO_PKG_VERSION"));
+
+/// Downloads `name@version` from crates.io and unpacks it into `crates_dir`, returning the
+/// crate's source directory (`<crates_dir>/<name>-<version>`). An earlier download is reused.
+pub async fn fetch_crate(client: &reqwest::Client, crates_dir: &Path, name: &str, version: &str) -> Result<PathBuf> {
+    validate(name, version)?;
+    let crate_dir = crates_dir.join(format!("{}-{}", name, version));
+    if crate_dir.join("Cargo.toml").exists() {
+        log::info!("Using previously downloaded {}@{} at {}", name, version, crate_dir.display());
+        return Ok(crate_dir);
+    }
+
+    let url = format!("{}/{}/{}/download", CRATES_IO_API_URL, name, version);
+    log::info!("Downloading {}@{} from {}", name, version, url);
+    let response = client.get(&url)
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .send()
+        .await
+        .with_context(|| format!("Failed to download {}@{}", name, version))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        anyhow::bail!("{}@{} does not exist on crates.io", name, version);
+    }
+    let tarball = response.error_for_status()
+        .with_context(|| format!("crates.io refused the download of {}@{}", name, version))?
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to read the tarball of {}@{}", name, version))?;
+
+    let (crates_dir, name, version) = (crates_dir.to_path_buf(), name.to_string(), version.to_string());
+    tokio::task::spawn_blocking(move || unpack_crate(&tarball, &crates_dir, &name, &version))
+        .await
+        .context("Unpacking the crate panicked")?
+}
+
+/// Unpacks a `.crate` tarball (gzipped tar with a single `<name>-<version>/` directory). Blocking.
+pub fn unpack_crate(tarball: &[u8], crates_dir: &Path, name: &str, version: &str) -> Result<PathBuf> {
+    validate(name, version)?;
+    fs::create_dir_all(crates_dir)
+        .with_context(|| format!("Failed to create crates directory: {}", crates_dir.display()))?;
+    let crate_dir = crates_dir.join(format!("{}-{}", name, version));
+    // Unpack next to the final location and rename, so an interrupted unpack is never mistaken for a complete one.
+    let staging = tempfile::tempdir_in(crates_dir).context("Failed to create a staging directory")?;
+    // `unpack` refuses entries that would escape the destination (absolute paths, `..`).
+    tar::Archive::new(GzDecoder::new(tarball)).unpack(staging.path())
+        .with_context(|| format!("Failed to unpack the tarball of {}@{}", name, version))?;
+    let unpacked = staging.path().join(format!("{}-{}", name, version));
+    if !unpacked.join("Cargo.toml").exists() {
+        anyhow::bail!("The tarball of {}@{} has no {}-{}/Cargo.toml", name, version, name, version);
+    }
+    if crate_dir.exists() {
+        fs::remove_dir_all(&crate_dir).with_context(|| format!("Failed to replace {}", crate_dir.display()))?;
+    }
+    fs::rename(&unpacked, &crate_dir).with_context(|| format!("Failed to move the crate to {}", crate_dir.display()))?;
+    Ok(crate_dir)
+}
+
+/// Checks a crate name and exact version, which end up in a URL and a file name: only the
+/// characters crates.io itself allows are accepted.
+pub fn validate(name: &str, version: &str) -> Result<()> {
+    let valid_name = !name.is_empty() && name.len() <= 64 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        anyhow::bail!("Invalid crate name '{}'", name);
+    }
+    let valid_version = version.starts_with(|c: char| c.is_ascii_digit())
+        && version.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'));
+    if !valid_version {
+        anyhow::bail!("Invalid version '{}': expected an exact version such as 1.0.3", version);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+
+    fn crate_tarball(root: &str) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), flate2::Compression::default()));
+        for (file, content) in [("Cargo.toml", "[package]\nname = \"demo\"\n"), ("src/lib.rs", "//! Demo.\n")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, format!("{}/{}", root, file), content.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn test_unpack_crate() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let crate_dir = unpack_crate(&crate_tarball("demo-0.1.0"), dir.path(), "demo", "0.1.0")?;
+        assert_eq!(crate_dir, dir.path().join("demo-0.1.0"));
+        assert!(crate_dir.join("src/lib.rs").exists());
+
+        // The tarball must contain the expected top-level directory.
+        assert!(unpack_crate(&crate_tarball("other-1.0.0"), dir.path(), "demo", "0.2.0").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_name_and_version() {
+        assert!(validate("serde_json", "1.0.120").is_ok());
+        assert!(validate("tokio", "1.0.0-rc.1+build").is_ok());
+        assert!(validate("../etc", "1.0.0").is_err());
+        assert!(validate("serde", "latest").is_err());
+        assert!(validate("serde", "1.0/../../x").is_err());
+    }
+}
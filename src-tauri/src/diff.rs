@@ -0,0 +1,166 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::mcp_server::ProjectData;
+use crate::rustdoc_processor::DocItem;
+
+pub const DEFAULT_DIFF_LIMIT: usize = 200;
+
+/// An item only one of the two projects has.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffItem {
+    pub full_path: String,
+    pub item_type: String,
+    /// Kind, generics, implemented trait and deprecation, e.g. `function <T: Into<String>>`.
+    pub signature: String,
+}
+
+/// An item both projects have, with different docs or signature.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangedItem {
+    pub full_path: String,
+    pub item_type: String,
+    pub docs_changed: bool,
+    /// The signatures in `project_a` and `project_b`, when they differ.
+    pub signature_before: Option<String>,
+    pub signature_after: Option<String>,
+}
+
+/// What changed in the docs of `project_b` relative to `project_a`, e.g. two versions of a crate.
+#[derive(Debug, Clone, Serialize)]
+pub struct DocDiff {
+    pub project_a: String,
+    pub project_b: String,
+    pub version_a: Option<String>,
+    pub version_b: Option<String>,
+    /// Totals across the whole diff; the lists below stop at the requested limit.
+    pub added_count: usize,
+    pub removed_count: usize,
+    pub changed_count: usize,
+    pub unchanged_count: usize,
+    pub added: Vec<DiffItem>,
+    pub removed: Vec<DiffItem>,
+    pub changed: Vec<ChangedItem>,
+}
+
+// Only the parts of a signature the index keeps; parameter and return types aren't stored.
+fn signature(item: &DocItem) -> String {
+    let mut text = item.item_type.clone();
+    if let Some(generics) = item.generics.as_ref().map(|g| g.render()).filter(|g| !g.is_empty()) {
+        text.push(' ');
+        text.push_str(&generics);
+    }
+    if let Some(trait_name) = &item.trait_name {
+        text.push_str(&format!(" (impl {})", trait_name));
+    }
+    if let Some(deprecation) = &item.deprecation {
+        text.push_str(&format!(" [{}]", deprecation.describe()));
+    }
+    text
+}
+
+fn diff_item(item: &DocItem) -> DiffItem {
+    DiffItem { full_path: item.full_path_str.clone(), item_type: item.item_type.clone(), signature: signature(item) }
+}
+
+/// Compares the crate docs (not dependencies) of two processed projects by full item path.
+/// Each list is sorted by path and holds at most `limit` entries.
+pub fn diff_projects(project_a: &str, a: &ProjectData, project_b: &str, b: &ProjectData, limit: usize) -> DocDiff {
+    let items_a: &HashMap<String, DocItem> = &a.crate_docs.items;
+    let items_b: &HashMap<String, DocItem> = &b.crate_docs.items;
+
+    let mut added: Vec<DiffItem> = items_b.iter().filter(|(path, _)| !items_a.contains_key(*path)).map(|(_, item)| diff_item(item)).collect();
+    let mut removed: Vec<DiffItem> = items_a.iter().filter(|(path, _)| !items_b.contains_key(*path)).map(|(_, item)| diff_item(item)).collect();
+    let mut changed = Vec::new();
+    let mut unchanged_count = 0;
+    for (path, before) in items_a {
+        let Some(after) = items_b.get(path) else { continue };
+        let docs_changed = before.description.as_deref().map(str::trim) != after.description.as_deref().map(str::trim);
+        let (signature_before, signature_after) = (signature(before), signature(after));
+        if !docs_changed && signature_before == signature_after {
+            unchanged_count += 1;
+            continue;
+        }
+        let signature_changed = signature_before != signature_after;
+        changed.push(ChangedItem {
+            full_path: path.clone(),
+            item_type: after.item_type.clone(),
+            docs_changed,
+            signature_before: signature_changed.then_some(signature_before),
+            signature_after: signature_changed.then_some(signature_after),
+        });
+    }
+    added.sort_by(|x, y| x.full_path.cmp(&y.full_path));
+    removed.sort_by(|x, y| x.full_path.cmp(&y.full_path));
+    changed.sort_by(|x, y| x.full_path.cmp(&y.full_path));
+    let (added_count, removed_count, changed_count) = (added.len(), removed.len(), changed.len());
+    added.truncate(limit);
+    removed.truncate(limit);
+    changed.truncate(limit);
+
+    DocDiff {
+        project_a: project_a.to_string(),
+        project_b: project_b.to_string(),
+        version_a: a.version.clone(),
+        version_b: b.version.clone(),
+        added_count,
+        removed_count,
+        changed_count,
+        unchanged_count,
+        added,
+        removed,
+        changed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generics::{GenericParam, Generics};
+    use crate::rustdoc_processor::test_support::doc_entry as item;
+    use crate::rustdoc_processor::CrateDocs;
+
+    fn project(items: Vec<(String, DocItem)>, version: &str) -> ProjectData {
+        let docs = CrateDocs { crate_name: "app".to_string(), items: items.into_iter().collect() };
+        ProjectData::new(docs, Vec::new(), HashMap::new()).with_version(Some(version.to_string()))
+    }
+
+    #[test]
+    fn test_diff_projects() {
+        let old = project(vec![
+            item("app::run", "function", "Runs it."),
+            item("app::Config", "struct", "The config."),
+            item("app::legacy", "function", "Old API."),
+        ], "0.6.3");
+        let mut generic_run = item("app::run", "function", "Runs it.");
+        generic_run.1.generics = Some(Generics {
+            params: vec![GenericParam { name: "T".to_string(), kind: "type".to_string(), bounds: vec!["Into<String>".to_string()], default: None }],
+            where_predicates: Vec::new(),
+        });
+        let new = project(vec![
+            generic_run,
+            item("app::Config", "struct", "The configuration.\n"),
+            item("app::start", "function", "Starts it."),
+        ], "0.7.0");
+
+        let diff = diff_projects("/work/app@0.6.3", &old, "/work/app", &new, DEFAULT_DIFF_LIMIT);
+        assert_eq!((diff.version_a.as_deref(), diff.version_b.as_deref()), (Some("0.6.3"), Some("0.7.0")));
+        assert_eq!(diff.added, vec![DiffItem { full_path: "app::start".to_string(), item_type: "function".to_string(), signature: "function".to_string() }]);
+        assert_eq!(diff.removed[0].full_path, "app::legacy");
+        assert_eq!(diff.changed, vec![
+            ChangedItem { full_path: "app::Config".to_string(), item_type: "struct".to_string(), docs_changed: true, signature_before: None, signature_after: None },
+            ChangedItem {
+                full_path: "app::run".to_string(),
+                item_type: "function".to_string(),
+                docs_changed: false,
+                signature_before: Some("function".to_string()),
+                signature_after: Some("function <T: Into<String>>".to_string()),
+            },
+        ]);
+        assert_eq!(diff.unchanged_count, 0);
+
+        // Totals survive the limit.
+        let limited = diff_projects("/work/app@0.6.3", &old, "/work/app", &new, 1);
+        assert_eq!((limited.changed_count, limited.changed.len()), (2, 1));
+    }
+}
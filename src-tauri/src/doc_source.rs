@@ -0,0 +1,477 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+use crate::chunking;
+use crate::config::RustdocSettings;
+use crate::embedder;
+use crate::embedding_text::{self, MemberIndex};
+use crate::journal::EMBED_BATCH_SIZE;
+use crate::jobs::CancelFlag;
+use crate::mcp_server::{AppState, ProjectData};
+use crate::rustdoc_processor::{self, CrateDocs, DocItem, DocTargets};
+
+/// Everything a source may need while loading documentation.
+pub struct SourceContext {
+    pub rustdoc_output_dir: PathBuf,
+    pub rustdoc_settings: RustdocSettings,
+    /// Set when the indexing job is cancelled; long-running sources should stop.
+    pub cancel: CancelFlag,
+}
+
+/// A producer of documentation items that can be indexed into the shared search space.
+///
+/// The rustdoc pipeline is the built-in implementation; other sources (mdBook books,
+/// Markdown folders, OpenAPI specs) map their content onto the same `DocItem` shape.
+pub trait DocSource: Send + Sync {
+    /// Stable identifier used to select the source, e.g. "rustdoc" or "markdown".
+    fn kind(&self) -> &'static str;
+
+    /// Whether this source can handle the given location. Used for auto-detection.
+    fn detect(&self, location: &Path) -> bool;
+
+    /// Whether `load` builds the location (running build scripts), so hardened-mode trust checks apply.
+    fn runs_build(&self) -> bool {
+        false
+    }
+
+    /// Loads all documentation items from `location`.
+    fn load(&self, location: &Path, ctx: &SourceContext) -> Result<CrateDocs>;
+
+    /// Text fed to the embedder for one chunk of an item's docs; see `embedding_text::embedding_text`.
+    fn embedding_text(&self, item: &DocItem, members: &[&DocItem], chunk: &str) -> String {
+        embedding_text::embedding_text(item, members, chunk)
+    }
+}
+
+// --- Registry ---
+
+/// Registered document sources, keyed by `DocSource::kind`.
+#[derive(Clone)]
+pub struct DocSourceRegistry {
+    sources: Vec<Arc<dyn DocSource>>,
+}
+
+impl Default for DocSourceRegistry {
+    fn default() -> Self {
+        let mut registry = Self { sources: Vec::new() };
+        // Order matters for detection: more specific sources first.
+        registry.register(Arc::new(RustdocSource));
+        registry.register(Arc::new(MdBookSource));
+        registry.register(Arc::new(OpenApiSource));
+        registry.register(Arc::new(MarkdownFolderSource));
+        registry
+    }
+}
+
+impl DocSourceRegistry {
+    /// Registers a source. A source with the same kind replaces the existing one.
+    pub fn register(&mut self, source: Arc<dyn DocSource>) {
+        if let Some(existing) = self.sources.iter_mut().find(|s| s.kind() == source.kind()) {
+            *existing = source;
+        } else {
+            self.sources.push(source);
+        }
+    }
+
+    pub fn get(&self, kind: &str) -> Option<Arc<dyn DocSource>> {
+        self.sources.iter().find(|s| s.kind() == kind).cloned()
+    }
+
+    /// First registered source that recognises the location.
+    pub fn detect(&self, location: &Path) -> Option<Arc<dyn DocSource>> {
+        self.sources.iter().find(|s| s.detect(location)).cloned()
+    }
+
+    /// The source selected by `kind`, or the auto-detected one when `kind` is None.
+    pub fn resolve(&self, location: &Path, kind: Option<&str>) -> Option<Arc<dyn DocSource>> {
+        match kind {
+            Some(kind) => self.get(kind),
+            None => self.detect(location),
+        }
+    }
+
+    pub fn kinds(&self) -> Vec<&'static str> {
+        self.sources.iter().map(|s| s.kind()).collect()
+    }
+}
+
+/// Loads `location` with the given (or auto-detected) source, embeds it and stores it in AppState
+/// under the location's path. Returns the number of items and embeddings stored.
+pub async fn index_with_source(app_state: &AppState, location: &str, source_kind: Option<&str>, cancel: CancelFlag) -> Result<(usize, usize)> {
+    let location_path = Path::new(location);
+    let source = match source_kind {
+        Some(kind) => app_state.doc_sources.get(kind)
+            .with_context(|| format!("Unknown document source '{}'. Available: {:?}", kind, app_state.doc_sources.kinds()))?,
+        None => app_state.doc_sources.detect(location_path)
+            .with_context(|| format!("No document source recognises {}", location))?,
+    };
+    log::info!("Indexing {} with document source '{}'", location, source.kind());
+
+    let ctx = SourceContext {
+        rustdoc_output_dir: app_state.rustdoc_output_dir.clone(),
+        rustdoc_settings: app_state.config.current().rustdoc_for_project(location, &app_state.cache_dir),
+        cancel,
+    };
+    let crate_docs = source.load(location_path, &ctx)?;
+    if ctx.cancel.is_cancelled() {
+        anyhow::bail!("Cancelled before embedding {}", location);
+    }
+
+    let mut project_embeddings = HashMap::new();
+    let mut embedding_model = None;
+    // Wait for a model that is still warming up instead of indexing without embeddings.
+    embedder::wait_until_loaded().await;
+    let loaded = embedder::with_global_embedder(|embedder| embedder.map(|e| (e.model_id(), e.chunker(), e.passage_prefix().to_string()))).await?;
+    match loaded {
+        Some((model_id, chunker, passage_prefix)) => {
+            embedding_model = Some(model_id.clone());
+            let mut texts_to_embed = Vec::new();
+            let mut item_paths_for_embedding = Vec::new();
+            let members = MemberIndex::build(crate_docs.items.values());
+            for (item_full_path, doc_item) in &crate_docs.items {
+                // Items without docs are only found by keyword.
+                let Some(desc) = doc_item.description.as_deref().filter(|d| !d.trim().is_empty()) else {
+                    continue;
+                };
+                for (chunk_idx, chunk) in chunker.chunk_text(desc)?.into_iter().enumerate() {
+                    let text = source.embedding_text(doc_item, members.members_of(item_full_path), chunk);
+                    texts_to_embed.push(format!("{}{}", passage_prefix, text));
+                    item_paths_for_embedding.push(chunking::chunk_key(item_full_path, chunk_idx));
+                }
+            }
+            if texts_to_embed.is_empty() {
+                log::info!("No embeddable items found in {}.", location);
+            }
+            // Batch by batch, so queries get the embedder in between.
+            for (texts, paths) in texts_to_embed.chunks(EMBED_BATCH_SIZE).zip(item_paths_for_embedding.chunks(EMBED_BATCH_SIZE)) {
+                if ctx.cancel.is_cancelled() {
+                    anyhow::bail!("Cancelled while embedding {}", location);
+                }
+                let (cache, batch_texts) = (app_state.embedding_cache.clone(), texts.to_vec());
+                let embeddings_vec = embedder::with_model(&model_id, move |embedder| cache.embed_batch(embedder, &batch_texts)).await?;
+                project_embeddings.extend(paths.iter().cloned().zip(embeddings_vec));
+            }
+        }
+        None => log::warn!("Embedder not initialized. Skipping embedding generation for {}.", location),
+    }
+
+    let num_items = crate_docs.items.len();
+    let num_embedded = chunking::embedded_item_count(project_embeddings.keys());
+    let project_data = ProjectData::new(crate_docs, Vec::new(), project_embeddings).with_embedding_model(embedding_model);
+    app_state.insert_project(location.to_string(), project_data).await;
+    log::info!("Indexed {} items ({} embedded) from {} via '{}'", num_items, num_embedded, location, source.kind());
+    Ok((num_items, num_embedded))
+}
+
+// --- Built-in Sources ---
+
+/// The rustdoc JSON pipeline: `cargo rustdoc` + `parse_rustdoc_json_file`.
+pub struct RustdocSource;
+
+impl DocSource for RustdocSource {
+    fn kind(&self) -> &'static str { "rustdoc" }
+
+    fn runs_build(&self) -> bool { true }
+
+    fn detect(&self, location: &Path) -> bool {
+        location.join("Cargo.toml").exists()
+    }
+
+    fn load(&self, location: &Path, ctx: &SourceContext) -> Result<CrateDocs> {
+        let json_paths = rustdoc_processor::generate_rustdoc_json(location, &ctx.rustdoc_output_dir, &ctx.rustdoc_settings, DocTargets::Auto, &ctx.cancel)?;
+        let mut crate_docs = rustdoc_processor::parse_rustdoc_json_files(&json_paths)?;
+        if ctx.rustdoc_settings.skip_private_items {
+            crate_docs.retain_public();
+        }
+        Ok(crate_docs)
+    }
+}
+
+/// A folder of plain Markdown files. Each heading section becomes one item.
+pub struct MarkdownFolderSource;
+
+impl DocSource for MarkdownFolderSource {
+    fn kind(&self) -> &'static str { "markdown" }
+
+    fn detect(&self, location: &Path) -> bool {
+        location.is_dir() && WalkDir::new(location).into_iter().filter_map(|e| e.ok()).any(|e| is_markdown(e.path()))
+    }
+
+    fn load(&self, location: &Path, _ctx: &SourceContext) -> Result<CrateDocs> {
+        let label = dir_label(location);
+        load_markdown_dir(location, &label)
+    }
+}
+
+/// An mdBook: `book.toml` plus Markdown chapters under its `src` directory.
+pub struct MdBookSource;
+
+impl DocSource for MdBookSource {
+    fn kind(&self) -> &'static str { "mdbook" }
+
+    fn detect(&self, location: &Path) -> bool {
+        location.join("book.toml").exists()
+    }
+
+    fn load(&self, location: &Path, _ctx: &SourceContext) -> Result<CrateDocs> {
+        let book_toml = fs::read_to_string(location.join("book.toml")).context("Failed to read book.toml")?;
+        let book: toml::Value = book_toml.parse().context("Failed to parse book.toml")?;
+        let src_dir = book.get("book").and_then(|b| b.get("src")).and_then(|s| s.as_str()).unwrap_or("src");
+        let label = book.get("book").and_then(|b| b.get("title")).and_then(|t| t.as_str())
+            .map(String::from)
+            .unwrap_or_else(|| dir_label(location));
+        load_markdown_dir(&location.join(src_dir), &label)
+    }
+}
+
+/// An OpenAPI 3 / Swagger 2 spec (JSON or YAML) for a companion service.
+/// Operations and schemas become items.
+pub struct OpenApiSource;
+
+impl DocSource for OpenApiSource {
+    fn kind(&self) -> &'static str { "openapi" }
+
+    /// A JSON or YAML file whose top level has an `openapi` (3) or `swagger` (2) key; the words
+    /// merely appearing somewhere in it don't count.
+    fn detect(&self, location: &Path) -> bool {
+        location.is_file()
+            && matches!(location.extension().and_then(|e| e.to_str()), Some("json" | "yaml" | "yml"))
+            && read_spec(location).is_ok_and(|spec| spec.get("openapi").is_some() || spec.get("swagger").is_some())
+    }
+
+    fn load(&self, location: &Path, _ctx: &SourceContext) -> Result<CrateDocs> {
+        let spec = read_spec(location)?;
+        let fallback = location.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        Ok(parse_openapi_spec(&spec, &fallback))
+    }
+}
+
+// --- Helpers ---
+
+fn is_markdown(path: &Path) -> bool {
+    path.is_file() && matches!(path.extension().and_then(|e| e.to_str()), Some("md" | "markdown"))
+}
+
+fn dir_label(location: &Path) -> String {
+    location.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+fn read_spec(location: &Path) -> Result<Value> {
+    let content = fs::read_to_string(location)
+        .with_context(|| format!("Failed to read OpenAPI spec: {}", location.display()))?;
+    if location.extension().is_some_and(|e| e == "json") {
+        serde_json::from_str(&content).context("Failed to parse OpenAPI JSON")
+    } else {
+        serde_yaml::from_str(&content).context("Failed to parse OpenAPI YAML")
+    }
+}
+
+fn load_markdown_dir(dir: &Path, label: &str) -> Result<CrateDocs> {
+    let mut items = HashMap::new();
+    for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !is_markdown(path) {
+            continue;
+        }
+        let rel_path = path.strip_prefix(dir).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Markdown file: {}", path.display()))?;
+        for item in markdown_sections(label, &rel_path, &content) {
+            items.insert(item.full_path_str.clone(), item);
+        }
+    }
+    log::info!("Loaded {} Markdown sections from {}", items.len(), dir.display());
+    Ok(CrateDocs { crate_name: label.to_string(), items })
+}
+
+/// Splits a Markdown document into one item per heading (plus any preamble before the first heading).
+/// Headings inside fenced code blocks don't count. A heading repeated in the same file gets its
+/// index (`#Example-2`) in the item's path, so each section stays an item of its own.
+fn markdown_sections(label: &str, rel_path: &str, content: &str) -> Vec<DocItem> {
+    let file_name = rel_path.rsplit('/').next().unwrap_or(rel_path);
+    let file_stem = file_name.strip_suffix(".markdown").or_else(|| file_name.strip_suffix(".md")).unwrap_or(file_name).to_string();
+    let module_path: Vec<String> = rel_path.split('/').map(String::from).collect();
+    let mut sections: Vec<(String, String)> = Vec::new();
+    let mut current_title = file_stem.clone();
+    let mut current_body = String::new();
+    // The marker of the open code fence: three backticks or tildes.
+    let mut fence: Option<&str> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) if trimmed.starts_with(marker) => fence = None,
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => fence = Some(&trimmed[..3]),
+            _ => {}
+        }
+        match heading_text(line).filter(|_| fence.is_none()) {
+            Some(title) => {
+                if !current_body.trim().is_empty() {
+                    sections.push((current_title.clone(), current_body.trim().to_string()));
+                }
+                current_title = title.to_string();
+                current_body.clear();
+            }
+            None => {
+                current_body.push_str(line);
+                current_body.push('\n');
+            }
+        }
+    }
+    if !current_body.trim().is_empty() {
+        sections.push((current_title, current_body.trim().to_string()));
+    }
+
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    sections.into_iter().map(|(title, body)| {
+        let occurrence = occurrences.entry(title.clone()).or_default();
+        *occurrence += 1;
+        let full_path_str = match *occurrence {
+            1 => format!("{}::{}#{}", label, rel_path, title),
+            n => format!("{}::{}#{}-{}", label, rel_path, title, n),
+        };
+        DocItem {
+            id: full_path_str.clone(),
+            crate_name: label.to_string(),
+            name: title,
+            path: module_path.clone(),
+            description: Some(body),
+            item_type: "markdown_section".to_string(),
+            full_path_str,
+            ..Default::default()
+        }
+    }).collect()
+}
+
+// The text of an ATX heading: one to six `#` followed by a space (or nothing), so lines such as
+// `#[derive(Debug)]` or `#hashtag` aren't headings. Closing `#`s are dropped.
+fn heading_text(line: &str) -> Option<&str> {
+    let level = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with([' ', '\t'])))
+        .then(|| rest.trim().trim_end_matches('#').trim_end())
+}
+
+fn parse_openapi_spec(spec: &Value, fallback_title: &str) -> CrateDocs {
+    let title = spec.pointer("/info/title").and_then(|t| t.as_str()).unwrap_or(fallback_title).to_string();
+    let mut items = HashMap::new();
+
+    if let Some(paths) = spec.get("paths").and_then(|p| p.as_object()) {
+        for (route, operations) in paths {
+            let Some(operations) = operations.as_object() else { continue };
+            for (method, op) in operations {
+                if !matches!(method.as_str(), "get" | "put" | "post" | "delete" | "options" | "head" | "patch" | "trace") {
+                    continue; // e.g. shared "parameters"
+                }
+                let name = op.get("operationId").and_then(|o| o.as_str())
+                    .map(String::from)
+                    .unwrap_or_else(|| format!("{} {}", method.to_uppercase(), route));
+                let description = [op.get("summary"), op.get("description")].into_iter()
+                    .flatten()
+                    .filter_map(|v| v.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                let full_path_str = format!("{}::{} {}", title, method.to_uppercase(), route);
+                items.insert(full_path_str.clone(), DocItem {
+                    id: full_path_str.clone(),
+                    crate_name: title.clone(),
+                    name,
+                    path: vec![route.clone()],
+                    description: if description.is_empty() { None } else { Some(description) },
+                    item_type: "api_operation".to_string(),
+                    full_path_str,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    // OpenAPI 3 keeps schemas under components, Swagger 2 under definitions.
+    let schemas = spec.pointer("/components/schemas").or_else(|| spec.get("definitions")).and_then(|s| s.as_object());
+    if let Some(schemas) = schemas {
+        for (schema_name, schema) in schemas {
+            let full_path_str = format!("{}::schemas::{}", title, schema_name);
+            items.insert(full_path_str.clone(), DocItem {
+                id: full_path_str.clone(),
+                crate_name: title.clone(),
+                name: schema_name.clone(),
+                path: vec!["schemas".to_string()],
+                description: schema.get("description").and_then(|d| d.as_str()).map(String::from),
+                item_type: "api_schema".to_string(),
+                full_path_str,
+                ..Default::default()
+            });
+        }
+    }
+
+    CrateDocs { crate_name: title, items }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_markdown_sections_split_on_headings() {
+        let content = "Intro text\n# Install\nRun `cargo add`.\n```sh\n# not a heading\n```\n## Usage\nCall it.\n";
+        let items = markdown_sections("guide", "getting-started.md", content);
+        let names: Vec<&str> = items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["getting-started", "Install", "Usage"]);
+        assert!(items[1].description.as_deref().unwrap().contains("# not a heading"));
+        assert_eq!(items[2].full_path_str, "guide::getting-started.md#Usage");
+    }
+
+    #[test]
+    fn test_markdown_sections_edge_cases() {
+        let content = "# Example\nFirst.\n#[derive(Debug)] isn't a heading, nor is #hashtag.\n~~~rust\n# hidden line\n~~~\n## Example ##\nSecond.\n";
+        let items = markdown_sections("guide", "notes/derive.markdown", content);
+        let paths: Vec<&str> = items.iter().map(|i| i.full_path_str.as_str()).collect();
+        assert_eq!(paths, vec!["guide::notes/derive.markdown#Example", "guide::notes/derive.markdown#Example-2"]);
+        assert!(items[0].description.as_deref().unwrap().contains("#[derive(Debug)]"));
+        assert!(items[0].description.as_deref().unwrap().contains("# hidden line"));
+        assert_eq!(items[1].name, "Example");
+
+        let preamble = markdown_sections("guide", "intro.markdown", "Welcome.\n");
+        assert_eq!(preamble[0].name, "intro");
+    }
+
+    #[test]
+    fn test_openapi_detect_needs_top_level_key() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let spec = dir.path().join("billing.yaml");
+        fs::write(&spec, "openapi: 3.0.0\ninfo:\n  title: billing\npaths: {}\n")?;
+        let mentions = dir.path().join("package.json");
+        fs::write(&mentions, r#"{"name": "ui", "devDependencies": {"swagger-ui": "5.0.0", "openapi-types": "12.0.0"}}"#)?;
+        assert!(OpenApiSource.detect(&spec));
+        assert!(!OpenApiSource.detect(&mentions));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_openapi_spec() {
+        let spec = json!({
+            "openapi": "3.0.0",
+            "info": { "title": "billing" },
+            "paths": {
+                "/invoices": {
+                    "parameters": [],
+                    "get": { "operationId": "listInvoices", "summary": "List invoices" }
+                }
+            },
+            "components": { "schemas": { "Invoice": { "description": "An invoice" } } }
+        });
+        let docs = parse_openapi_spec(&spec, "fallback");
+        assert_eq!(docs.crate_name, "billing");
+        assert_eq!(docs.items.len(), 2);
+        let op = &docs.items["billing::GET /invoices"];
+        assert_eq!(op.name, "listInvoices");
+        assert_eq!(op.item_type, "api_operation");
+        assert_eq!(docs.items["billing::schemas::Invoice"].description.as_deref(), Some("An invoice"));
+    }
+}
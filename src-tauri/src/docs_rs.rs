@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::crates_io;
+
+const DOCS_RS_URL: &str = "https://docs.rs/crate";
+
+/// Project key crates fetched from docs.rs are indexed under. The version is kept on the project,
+/// so a newer version archives the older one as `docs.rs:<name>@<version>`.
+pub fn project_key(name: &str) -> String {
+    format!("docs.rs:{}", name)
+}
+
+// docs.rs serves the JSON of its default target gzipped at this URL (`/json` is zstd).
+fn json_url(name: &str, version: &str) -> String {
+    format!("{}/{}/{}/json.gz", DOCS_RS_URL, name, version)
+}
+
+/// Downloads the rustdoc JSON docs.rs built for `name@version` and decompresses it into
+/// `json_dir`, returning the file. An earlier download is reused. No toolchain is needed.
+pub async fn fetch_json(client: &reqwest::Client, json_dir: &Path, name: &str, version: &str) -> Result<PathBuf> {
+    crates_io::validate(name, version)?;
+    let json_path = json_dir.join(format!("{}-{}.json", name, version));
+    if json_path.exists() {
+        log::info!("Using previously downloaded docs.rs JSON of {}@{} at {}", name, version, json_path.display());
+        return Ok(json_path);
+    }
+
+    let url = json_url(name, version);
+    log::info!("Downloading rustdoc JSON of {}@{} from {}", name, version, url);
+    let response = client.get(&url)
+        .header(reqwest::header::USER_AGENT, crates_io::USER_AGENT)
+        .send()
+        .await
+        .with_context(|| format!("Failed to download the docs.rs JSON of {}@{}", name, version))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        anyhow::bail!("docs.rs has no rustdoc JSON for {}@{} (it is only built for releases since May 2025, and not when the docs build failed)", name, version);
+    }
+    let compressed = response.error_for_status()
+        .with_context(|| format!("docs.rs refused the download of {}@{}", name, version))?
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to read the docs.rs JSON of {}@{}", name, version))?;
+
+    let json_dir = json_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || write_decompressed(&compressed, &json_dir, &json_path).map(|()| json_path))
+        .await
+        .context("Decompressing the docs.rs JSON panicked")?
+}
+
+/// Gunzips `compressed` into `json_path` (inside `json_dir`). Writes to a temporary file first, so
+/// an interrupted download is never mistaken for a complete one. Blocking.
+pub fn write_decompressed(compressed: &[u8], json_dir: &Path, json_path: &Path) -> Result<()> {
+    fs::create_dir_all(json_dir)
+        .with_context(|| format!("Failed to create docs.rs directory: {}", json_dir.display()))?;
+    let mut json = Vec::new();
+    GzDecoder::new(compressed).read_to_end(&mut json).context("The docs.rs download is not valid gzip")?;
+    let staging = tempfile::NamedTempFile::new_in(json_dir).context("Failed to create a staging file")?;
+    fs::write(staging.path(), &json).with_context(|| format!("Failed to write {}", staging.path().display()))?;
+    staging.persist(json_path).with_context(|| format!("Failed to move the JSON to {}", json_path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    #[test]
+    fn test_write_decompressed() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(br#"{"format_version":39}"#)?;
+        let json_path = dir.path().join("demo-0.1.0.json");
+        write_decompressed(&encoder.finish()?, dir.path(), &json_path)?;
+        assert_eq!(fs::read_to_string(&json_path)?, r#"{"format_version":39}"#);
+
+        assert!(write_decompressed(b"not gzip", dir.path(), &dir.path().join("bad.json")).is_err());
+        assert!(!dir.path().join("bad.json").exists());
+        assert_eq!(json_url("serde", "1.0.200"), "https://docs.rs/crate/serde/1.0.200/json.gz");
+        Ok(())
+    }
+}
@@ -1,184 +1,1108 @@
 use anyhow::{Context, Result, Error as AnyhowError};
-use candle_core::{Device, Tensor, D};
+use candle_core::{DType, Device, IndexOp, Module, Tensor, D};
 use candle_nn::VarBuilder;
 // Assuming Qwen2Model and Qwen2Config are available.
 // If these lines cause a compilation error, candle-transformers doesn't support Qwen2Model as expected.
 use candle_transformers::models::qwen2::{Model as Qwen2Model, Config as Qwen2Config, DTYPE};
-use hf_hub::{api::sync::Api, Repo, RepoType};
+use candle_transformers::models::bert::{BertModel, Config as BertConfig};
+use candle_transformers::models::jina_bert::{BertModel as JinaBertModel, Config as JinaBertConfig};
+use hf_hub::{api::sync::{Api, ApiRepo}, Repo, RepoType};
 use tokenizers::Tokenizer;
 use std::collections::{HashMap, HashSet}; // HashSet for collecting unique filenames
-use std::path::PathBuf; // Keep for potential future use
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
 
-// Configuration for the embedding model
-const EMBEDDING_MODEL_REPO: &str = "BAAI/bge-code-v1"; // Updated to bge-code-v1
-const EMBEDDING_MODEL_REVISION: &str = "main";
+use crate::config::{DevicePreference, EmbedderBackend, EmbedderSettings, ModelDType, PoolingStrategy, RemoteEmbedderSettings};
+use crate::quantized_encoder::QuantizedQwen2;
+use crate::remote_embedder::RemoteEmbedder;
 
 pub struct Embedder {
-    model: Qwen2Model, // Updated model type
+    model: Box<dyn EmbeddingBackend>,
     tokenizer: Tokenizer,
     device: Device,
+    dtype: DType,
+    model_repo: String,
+    model_revision: String,
+    quantized_file: Option<String>,
+    // `model@url` when the remote backend embeds; `model_repo` is then just the tokenizer.
+    remote_model: Option<String>,
+    chunk_tokens: usize,
+    chunk_overlap: usize,
+    // `max_batch_size` from the settings, lowered (never raised) when the GPU runs out of memory.
+    batch_size: AtomicUsize,
+    // Length of the vectors, recorded from the first embedding; 0 until then.
+    dimension: AtomicUsize,
+    // Kept to load a CPU copy of the model from if the GPU runs out of memory.
+    weights: WeightFiles,
+    // The CPU copy every forward pass uses once a single text no longer fits on the GPU.
+    cpu_fallback: OnceLock<Box<dyn EmbeddingBackend>>,
+    pad_token_id: u32,
+    /// Resolved from the settings; never `Auto`.
+    pooling: PoolingStrategy,
+    max_length: usize,
+    query_prefix: String,
+    passage_prefix: String,
 }
 
-impl Embedder {
-    pub fn new() -> Result<Self> {
-        log::info!("Initializing Embedder with model: {}", EMBEDDING_MODEL_REPO);
-        let device = match Device::cuda_if_available(0) {
-            Ok(cuda_device) => cuda_device,
-            Err(_) => {
-                log::warn!("CUDA device not found or CUDA not compiled. Falling back to CPU.");
-                Device::Cpu
+pub(crate) fn select_device(preference: DevicePreference) -> Result<Device> {
+    match preference {
+        DevicePreference::Cpu => Ok(Device::Cpu),
+        DevicePreference::Cuda => Device::new_cuda(0).context("CUDA device requested in settings but not available"),
+        DevicePreference::Metal => Device::new_metal(0)
+            .context("Metal device requested in settings but not available (build with `--features metal` on macOS)"),
+        DevicePreference::Auto => {
+            if candle_core::utils::cuda_is_available() {
+                match Device::new_cuda(0) {
+                    Ok(cuda_device) => return Ok(cuda_device),
+                    Err(e) => log::warn!("CUDA is compiled in but the device failed to open: {}", e),
+                }
             }
+            if candle_core::utils::metal_is_available() {
+                match Device::new_metal(0) {
+                    Ok(metal_device) => return Ok(metal_device),
+                    Err(e) => log::warn!("Metal is compiled in but the device failed to open: {}", e),
+                }
+            }
+            log::warn!("No CUDA or Metal device found (or not compiled in). Falling back to CPU.");
+            Ok(Device::Cpu)
+        }
+    }
+}
+
+fn device_name(device: &Device) -> &'static str {
+    match device {
+        Device::Cpu => "cpu",
+        Device::Cuda(_) => "cuda",
+        Device::Metal(_) => "metal",
+    }
+}
+
+/// Whether `error` is a device allocation failure, e.g. CUDA's `CUDA_ERROR_OUT_OF_MEMORY` or a
+/// Metal buffer that couldn't be allocated. candle has no variant for these, so the messages
+/// of the error chain are checked.
+pub fn is_out_of_memory(error: &AnyhowError) -> bool {
+    const MARKERS: [&str; 5] = ["out of memory", "out_of_memory", "outofmemory", "failed to allocate", "insufficient memory"];
+    error.chain().any(|cause| {
+        let message = cause.to_string().to_lowercase();
+        MARKERS.iter().any(|marker| message.contains(marker))
+    })
+}
+
+/// The embedder moved from the GPU to the CPU after running out of memory. Processing goes on,
+/// more slowly; a smaller or quantized model (or a lower `max_batch_size`) avoids it.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceFallback {
+    pub model_repo: String,
+    /// "cuda" or "metal".
+    pub from_device: String,
+    pub reason: String,
+}
+
+static DEVICE_FALLBACKS: Lazy<broadcast::Sender<DeviceFallback>> = Lazy::new(|| broadcast::channel(16).0);
+
+/// Device fallbacks from now on, for forwarding as warnings to windows.
+pub fn subscribe_device_fallbacks() -> broadcast::Receiver<DeviceFallback> {
+    DEVICE_FALLBACKS.subscribe()
+}
+
+fn notify_device_fallback(model_repo: &str, from: &Device, cause: &AnyhowError) {
+    let _ = DEVICE_FALLBACKS.send(DeviceFallback {
+        model_repo: model_repo.to_string(),
+        from_device: device_name(from).to_string(),
+        reason: format!("{:#}", cause),
+    });
+}
+
+// Loads the weights onto `device`.
+fn load_model(weights: &WeightFiles, model_repo: &str, dtype: DType, device: &Device) -> Result<Box<dyn EmbeddingBackend>> {
+    match weights {
+        WeightFiles::Gguf(gguf_path) => {
+            log::info!("Loading quantized weights for {} from {:?}", model_repo, gguf_path);
+            Ok(Box::new(QuantizedQwen2::from_gguf(gguf_path, device)?))
+        }
+        WeightFiles::Full { config, shards, architecture } => load_full_model(config, shards, *architecture, model_repo, dtype, device),
+        #[cfg(feature = "ort")]
+        WeightFiles::Onnx(onnx_path) => {
+            log::info!("Loading ONNX model for {} from {:?}", model_repo, onnx_path);
+            Ok(Box::new(crate::onnx_backend::OnnxModel::load(onnx_path)?))
+        }
+        #[cfg(not(feature = "ort"))]
+        WeightFiles::Onnx(_) => anyhow::bail!(NO_ORT_SUPPORT),
+        WeightFiles::Remote { settings, batch_size } => Ok(Box::new(RemoteEmbedder::new(settings, *batch_size)?)),
+    }
+}
+
+const NO_ORT_SUPPORT: &str = "embedder.backend = \"onnx\" needs a build with the `ort` feature";
+
+/// Built-in model choices for machines of different speeds, selected with `embedder.preset`.
+/// A preset sets the model repo and the lengths that suit it; `best` is the default model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelPreset {
+    /// all-MiniLM-L6-v2: a 22M-parameter BERT with 384 dimensions, usable on any CPU.
+    Fast,
+    /// bge-base-en-v1.5: a 110M-parameter BERT with 768 dimensions.
+    Balanced,
+    /// bge-code-v1: a 1.5B-parameter Qwen2 with 1536 dimensions, trained on code. Wants a GPU.
+    Best,
+}
+
+impl ModelPreset {
+    pub const ALL: [ModelPreset; 3] = [ModelPreset::Fast, ModelPreset::Balanced, ModelPreset::Best];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ModelPreset::Fast => "fast",
+            ModelPreset::Balanced => "balanced",
+            ModelPreset::Best => "best",
+        }
+    }
+
+    pub fn model_repo(self) -> &'static str {
+        match self {
+            ModelPreset::Fast => "sentence-transformers/all-MiniLM-L6-v2",
+            ModelPreset::Balanced => "BAAI/bge-base-en-v1.5",
+            ModelPreset::Best => "BAAI/bge-code-v1",
+        }
+    }
+
+    /// Length of the vectors the model produces.
+    pub fn dimensions(self) -> usize {
+        match self {
+            ModelPreset::Fast => 384,
+            ModelPreset::Balanced => 768,
+            ModelPreset::Best => 1536,
+        }
+    }
+
+    /// Points `settings` at the preset's model. The BERT presets only see 256 and 512 tokens,
+    /// so their docs are chunked smaller.
+    pub fn apply(self, settings: &mut EmbedderSettings) {
+        let (max_length, chunk_tokens, chunk_overlap) = match self {
+            ModelPreset::Fast => (256, 200, 32),
+            ModelPreset::Balanced => (512, 400, 48),
+            ModelPreset::Best => (2048, 512, 64),
+        };
+        settings.preset = Some(self);
+        settings.model_repo = self.model_repo().to_string();
+        settings.model_revision = "main".to_string();
+        settings.max_length = max_length;
+        settings.chunk_tokens = chunk_tokens;
+        settings.chunk_overlap = chunk_overlap;
+    }
+}
+
+impl std::str::FromStr for ModelPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|preset| preset.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown model preset '{}' (expected fast, balanced or best)", s))
+    }
+}
+
+/// The candle model a repo's weights are loaded into, from its config.json.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModelArchitecture {
+    /// Decoder embedders such as bge-code-v1 and gte-Qwen2.
+    Qwen2,
+    /// BERT encoders: bge-small/base/large, all-MiniLM, e5.
+    Bert,
+    /// BERT with ALiBi positions, e.g. jina-embeddings-v2-base-code (8192 tokens).
+    JinaBert,
+}
+
+impl ModelArchitecture {
+    // `model_type` picks the family; JinaBERT repos say "bert" too but name their own classes
+    // or use ALiBi positions.
+    fn detect(config_json: &str) -> Result<Self> {
+        let config: serde_json::Value = serde_json::from_str(config_json).context("config.json is not valid JSON")?;
+        let model_type = config.get("model_type").and_then(|v| v.as_str()).unwrap_or_default();
+        let jina = config.get("architectures").and_then(|v| v.as_array())
+            .is_some_and(|names| names.iter().filter_map(|name| name.as_str()).any(|name| name.starts_with("JinaBert")))
+            || config.get("position_embedding_type").and_then(|v| v.as_str()) == Some("alibi");
+        match model_type {
+            "qwen2" => Ok(ModelArchitecture::Qwen2),
+            "bert" if jina => Ok(ModelArchitecture::JinaBert),
+            "bert" => Ok(ModelArchitecture::Bert),
+            other => anyhow::bail!("Unsupported model type '{}'; supported are qwen2 and bert (including JinaBERT)", other),
+        }
+    }
+}
+
+fn select_dtype(dtype: ModelDType, architecture: ModelArchitecture) -> DType {
+    match dtype {
+        // BERT-family encoders are small enough to run in full precision.
+        ModelDType::Auto => match architecture {
+            ModelArchitecture::Qwen2 => DTYPE,
+            ModelArchitecture::Bert | ModelArchitecture::JinaBert => DType::F32,
+        },
+        ModelDType::F32 => DType::F32,
+        ModelDType::F16 => DType::F16,
+        ModelDType::Bf16 => DType::BF16,
+    }
+}
+
+// Downloads (or finds in the hub cache) the config and the sharded safetensors weights listed in
+// model.safetensors.index.json, or the single model.safetensors of smaller models.
+fn fetch_full_model(repo: &ApiRepo, model_repo: &str) -> Result<(PathBuf, Vec<PathBuf>)> {
+    let config_filename = repo.get("config.json")
+        .context(format!("Failed to get config.json from {}", model_repo))?;
+
+    // Handle sharded weights using model.safetensors.index.json
+    let model_files = match repo.get("model.safetensors.index.json") {
+        Ok(index_json_path) => {
+            log::info!("Found model.safetensors.index.json. Processing sharded weights for {}.", model_repo);
+            let index_json_content = std::fs::read_to_string(index_json_path)
+                .context("Failed to read model.safetensors.index.json")?;
+            let index: serde_json::Value = serde_json::from_str(&index_json_content)
+                .context("Failed to parse model.safetensors.index.json")?;
+            let weight_map = index.get("weight_map")
+                .context("Missing 'weight_map' in model.safetensors.index.json")?
+                .as_object()
+                .context("'weight_map' is not an object")?;
+
+            let mut filenames = HashSet::new();
+            for filename_val in weight_map.values() {
+                if let Some(filename_str) = filename_val.as_str() {
+                    filenames.insert(filename_str.to_string());
+                } else {
+                    // This case should ideally not happen in a valid index.json
+                    log::warn!("Non-string or null value found in weight_map: {:?}. Skipping.", filename_val);
+                }
+            }
+
+            if filenames.is_empty() {
+                return Err(anyhow::anyhow!("No filenames found in weight_map of model.safetensors.index.json for {}", model_repo));
+            }
+            log::info!("Identified sharded weight files for {}: {:?}", model_repo, filenames);
+
+            filenames.into_iter().map(|f| {
+                log::debug!("Fetching sharded weight file: {}", f);
+                repo.get(&f) // hf-hub will cache these
+            }).collect::<Result<Vec<_>, _>>()
+                .map_err(|e| anyhow::anyhow!("Failed to download or access a sharded weight file for {}: {}", model_repo, e))?
+        }
+        Err(index_error) => {
+            // Small models such as the BERT presets ship one unsharded file.
+            let single = repo.get("model.safetensors").map_err(|e| anyhow::anyhow!(
+                "Neither model.safetensors.index.json ({}) nor model.safetensors ({}) could be fetched for {}", index_error, e, model_repo))?;
+            vec![single]
+        }
+    };
+
+    log::info!("Model config file for {}: {:?}", model_repo, config_filename);
+    log::info!("Model weight files to load for {}: {:?}", model_repo, model_files);
+    Ok((config_filename, model_files))
+}
+
+// Full-precision safetensors weights from the files `fetch_full_model` returned.
+fn load_full_model(config_filename: &Path, model_files: &[PathBuf], architecture: ModelArchitecture, model_repo: &str, dtype: DType, device: &Device) -> Result<Box<dyn EmbeddingBackend>> {
+    let config_str = std::fs::read_to_string(config_filename)?;
+    let vb = unsafe {
+        VarBuilder::from_mmaped_safetensors(model_files, dtype, device)?
+    };
+    Ok(match architecture {
+        ModelArchitecture::Qwen2 => {
+            let config: Qwen2Config = serde_json::from_str(&config_str)
+                .context(format!("Failed to parse Qwen2Config from config.json for {}", model_repo))?;
+            Box::new(Qwen2Model::load(vb, &config)?)
+        }
+        ModelArchitecture::Bert => {
+            let config: BertConfig = serde_json::from_str(&config_str)
+                .context(format!("Failed to parse BertConfig from config.json for {}", model_repo))?;
+            Box::new(BertModel::load(vb, &config)?)
+        }
+        ModelArchitecture::JinaBert => {
+            let config: JinaBertConfig = serde_json::from_str(&config_str)
+                .context(format!("Failed to parse the JinaBERT config from config.json for {}", model_repo))?;
+            Box::new(JinaBertModel::new(vb, &config)?)
+        }
+    })
+}
+
+// The strategy a sentence-transformers `1_Pooling/config.json` selects, if it names one we support.
+fn pooling_from_sentence_transformers(config_json: &str) -> Option<PoolingStrategy> {
+    let config: serde_json::Value = serde_json::from_str(config_json).ok()?;
+    let enabled = |key: &str| config.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+    if enabled("pooling_mode_lasttoken") {
+        Some(PoolingStrategy::LastToken)
+    } else if enabled("pooling_mode_mean_tokens") {
+        Some(PoolingStrategy::Mean)
+    } else if enabled("pooling_mode_cls_token") {
+        Some(PoolingStrategy::Cls)
+    } else {
+        None
+    }
+}
+
+/// The instructions a model family expects in front of queries and indexed passages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ModelProfile {
+    query_prefix: &'static str,
+    passage_prefix: &'static str,
+}
+
+// Known prefixes by model repo; unknown models get none.
+fn model_profile(model_repo: &str) -> ModelProfile {
+    let name = model_repo.rsplit('/').next().unwrap_or(model_repo).to_ascii_lowercase();
+    let (query_prefix, passage_prefix) = if name.starts_with("bge-code") {
+        // bge-code-v1 takes an `<instruct>` task description; passages go in as they are.
+        ("<instruct>Given a question about a Rust API, retrieve the documentation and code that answer it.\n<query>", "")
+    } else if name.starts_with("bge-") && name.contains("-en") {
+        ("Represent this sentence for searching relevant passages: ", "")
+    } else if name.starts_with("bge-") && name.contains("-zh") {
+        ("为这个句子生成表示以用于检索相关文章：", "")
+    } else if name.starts_with("e5-") || name.starts_with("multilingual-e5") {
+        ("query: ", "passage: ")
+    } else if name.starts_with("nomic-embed") {
+        ("search_query: ", "search_document: ")
+    } else {
+        ("", "")
+    };
+    ModelProfile { query_prefix, passage_prefix }
+}
+
+// `Auto` becomes what the repo's pooling config asks for, or CLS for repos without one.
+fn resolve_pooling(repo: &ApiRepo, model_repo: &str, configured: PoolingStrategy) -> PoolingStrategy {
+    if configured != PoolingStrategy::Auto {
+        return configured;
+    }
+    let detected = repo.get("1_Pooling/config.json").ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| pooling_from_sentence_transformers(&json));
+    match detected {
+        Some(pooling) => {
+            log::info!("Using {} pooling for {} (from 1_Pooling/config.json)", pooling.as_str(), model_repo);
+            pooling
+        }
+        None => {
+            log::info!("No pooling config found for {}; using CLS pooling", model_repo);
+            PoolingStrategy::Cls
+        }
+    }
+}
+
+// Pools right-padded hidden states (batch, seq_len, hidden) into (batch, hidden). `attention_mask`
+// is (batch, seq_len) with 1 for real tokens; `lengths` holds each row's unpadded length.
+fn pool(hidden: &Tensor, attention_mask: &Tensor, lengths: &[usize], pooling: PoolingStrategy) -> Result<Tensor> {
+    match pooling {
+        PoolingStrategy::Mean => {
+            let mask = attention_mask.to_dtype(hidden.dtype())?.unsqueeze(D::Minus1)?;
+            let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+            let counts = mask.sum(1)?.maximum(1.0)?;
+            Ok(summed.broadcast_div(&counts)?)
+        }
+        PoolingStrategy::LastToken => {
+            let rows = lengths.iter().enumerate()
+                .map(|(row, &len)| hidden.i((row, len.saturating_sub(1))))
+                .collect::<candle_core::Result<Vec<_>>>()?;
+            Ok(Tensor::stack(&rows, 0)?)
+        }
+        // Right padding keeps the first token of every row at position 0.
+        PoolingStrategy::Cls | PoolingStrategy::Auto => Ok(hidden.i((.., 0, ..))?),
+    }
+}
+
+/// A loaded embedding model: token ids in, final hidden states out. Tokenization, truncation,
+/// pooling and normalisation are shared and happen around it.
+pub trait EmbeddingBackend: Send {
+    /// Final hidden states, (batch_size, seq_len, hidden_size), for right-padded rows.
+    /// `attention_mask` is (batch_size, seq_len): 1 for real tokens, 0 for padding.
+    fn forward(&self, token_ids: &Tensor, attention_mask: &Tensor) -> Result<Tensor>;
+
+    /// Embeds whole texts, for backends that aren't given token ids (a remote API). Returns
+    /// finished, normalised embeddings in input order; `None` means `forward` is to be used.
+    fn embed_texts(&self, _texts: &[String]) -> Option<Result<Vec<Vec<f32>>>> {
+        None
+    }
+}
+
+impl EmbeddingBackend for Qwen2Model {
+    // candle's Qwen2 turns an explicit mask into a bidirectional one, but the model was trained
+    // causally. With the causal default and right padding, no real token can see a pad token,
+    // and pooling drops the pad positions.
+    fn forward(&self, token_ids: &Tensor, _attention_mask: &Tensor) -> Result<Tensor> {
+        Ok(Qwen2Model::forward(self, token_ids, 0, None)?)
+    }
+}
+
+impl EmbeddingBackend for QuantizedQwen2 {
+    // Applies causal and padding masks together.
+    fn forward(&self, token_ids: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        QuantizedQwen2::forward(self, token_ids, attention_mask)
+    }
+}
+
+impl EmbeddingBackend for BertModel {
+    // Bidirectional; every token is in the first (only) segment.
+    fn forward(&self, token_ids: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        Ok(BertModel::forward(self, token_ids, &token_ids.zeros_like()?, Some(attention_mask))?)
+    }
+}
+
+impl EmbeddingBackend for JinaBertModel {
+    // candle's JinaBERT takes no attention mask, so rows run one at a time without their padding,
+    // and the padded positions of the output are zero.
+    fn forward(&self, token_ids: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        let seq_len = token_ids.dim(1)?;
+        let lengths = attention_mask.to_dtype(DType::U32)?.sum(1)?.to_vec1::<u32>()?;
+        let rows = lengths.iter().enumerate().map(|(row, &len)| {
+            let len = len as usize;
+            let hidden = Module::forward(self, &token_ids.i((row..row + 1, ..len))?)?;
+            Ok(hidden.pad_with_zeros(1, 0, seq_len - len)?)
+        }).collect::<Result<Vec<_>>>()?;
+        Ok(Tensor::cat(&rows, 0)?)
+    }
+}
+
+// The real tokens of an encoding: tokenizers configured with padding (`padding` in
+// tokenizer.json) return pad ids too, marked 0 in the attention mask. They're dropped here and
+// every batch is re-padded to its own longest row.
+fn unpadded_ids(ids: &[u32], attention_mask: &[u32]) -> Vec<u32> {
+    ids.iter().zip(attention_mask).filter(|(_, &mask)| mask != 0).map(|(&id, _)| id).collect()
+}
+
+// Cuts `ids` to `max_length` tokens, keeping the trailing special tokens (e.g. the EOS token
+// last-token pooling reads). Returns whether anything was cut.
+fn truncate_ids(ids: &mut Vec<u32>, trailing_special: usize, max_length: usize) -> bool {
+    if ids.len() <= max_length {
+        return false;
+    }
+    let trailing_special = trailing_special.min(max_length);
+    let tail = ids.split_off(ids.len() - trailing_special);
+    ids.truncate(max_length - trailing_special);
+    ids.extend(tail);
+    true
+}
+
+// Special tokens at the end of an encoding's real (unpadded) tokens.
+fn trailing_special_tokens(attention_mask: &[u32], special_tokens_mask: &[u32]) -> usize {
+    attention_mask.iter().zip(special_tokens_mask).rev()
+        .skip_while(|(&mask, _)| mask == 0)
+        .take_while(|(_, &special)| special != 0)
+        .count()
+}
+
+// Weight files fetched from the hub, before they are loaded.
+#[derive(Clone)]
+enum WeightFiles {
+    Full { config: PathBuf, shards: Vec<PathBuf>, architecture: ModelArchitecture },
+    Gguf(PathBuf),
+    Onnx(PathBuf),
+    Remote { settings: RemoteEmbedderSettings, batch_size: usize },
+}
+
+impl Embedder {
+    pub fn new(settings: &EmbedderSettings) -> Result<Self> {
+        Self::new_with_progress(settings, || {})
+    }
+
+    // `downloaded` runs once every file is on disk, before the weights are loaded.
+    fn new_with_progress(settings: &EmbedderSettings, downloaded: impl FnOnce()) -> Result<Self> {
+        let model_repo = settings.model_repo.as_str();
+        log::info!("Initializing Embedder with model: {} (revision {})", model_repo, settings.model_revision);
+        if settings.backend == EmbedderBackend::Onnx && !cfg!(feature = "ort") {
+            anyhow::bail!(NO_ORT_SUPPORT);
+        }
+        // ONNX Runtime runs on the CPU here (and a remote API needs no device); candle picks the
+        // device from the settings.
+        let mut device = match settings.backend {
+            EmbedderBackend::Candle => select_device(settings.device)?,
+            EmbedderBackend::Onnx | EmbedderBackend::Remote => Device::Cpu,
         };
-        log::info!("Embedder will use device: {:?}", device);
 
         let api = Api::new().context("Failed to create HuggingFace API client")?;
         let repo = api.repo(Repo::with_revision(
-            EMBEDDING_MODEL_REPO.to_string(),
+            model_repo.to_string(),
             RepoType::Model,
-            EMBEDDING_MODEL_REVISION.to_string(),
+            settings.model_revision.clone(),
         ));
 
-        log::info!("Fetching model files from HuggingFace Hub: {}", EMBEDDING_MODEL_REPO);
-        let config_filename = repo.get("config.json")
-            .context(format!("Failed to get config.json from {}", EMBEDDING_MODEL_REPO))?;
+        log::info!("Fetching model files from HuggingFace Hub: {}", model_repo);
         let tokenizer_filename = repo.get("tokenizer.json")
-            .context(format!("Failed to get tokenizer.json from {}", EMBEDDING_MODEL_REPO))?;
-
-        // Handle sharded weights using model.safetensors.index.json
-        let model_files = match repo.get("model.safetensors.index.json") {
-            Ok(index_json_path) => {
-                log::info!("Found model.safetensors.index.json. Processing sharded weights for {}.", EMBEDDING_MODEL_REPO);
-                let index_json_content = std::fs::read_to_string(index_json_path)
-                    .context("Failed to read model.safetensors.index.json")?;
-                let index: serde_json::Value = serde_json::from_str(&index_json_content)
-                    .context("Failed to parse model.safetensors.index.json")?;
-                let weight_map = index.get("weight_map")
-                    .context("Missing 'weight_map' in model.safetensors.index.json")?
-                    .as_object()
-                    .context("'weight_map' is not an object")?;
-
-                let mut filenames = HashSet::new();
-                for filename_val in weight_map.values() {
-                    if let Some(filename_str) = filename_val.as_str() {
-                        filenames.insert(filename_str.to_string());
-                    } else {
-                        // This case should ideally not happen in a valid index.json
-                        log::warn!("Non-string or null value found in weight_map: {:?}. Skipping.", filename_val);
-                    }
-                }
-
-                if filenames.is_empty() {
-                    return Err(anyhow::anyhow!("No filenames found in weight_map of model.safetensors.index.json for {}", EMBEDDING_MODEL_REPO));
-                }
-                log::info!("Identified sharded weight files for {}: {:?}", EMBEDDING_MODEL_REPO, filenames);
-
-                filenames.into_iter().map(|f| {
-                    log::debug!("Fetching sharded weight file: {}", f);
-                    repo.get(&f) // hf-hub will cache these
-                }).collect::<Result<Vec<_>, _>>()
-                    .map_err(|e| anyhow::anyhow!("Failed to download or access a sharded weight file for {}: {}", EMBEDDING_MODEL_REPO, e))?
+            .context(format!("Failed to get tokenizer.json from {}", model_repo))?;
+        log::info!("Tokenizer file for {}: {:?}", model_repo, tokenizer_filename);
+        // Quantized weights are dequantized on the fly; activations are always f32 there.
+        let (weight_files, mut dtype) = match (settings.backend, &settings.quantized) {
+            (EmbedderBackend::Remote, _) => {
+                log::info!("Embedding with {} at {}; {} only supplies the tokenizer", settings.remote.model, settings.remote.url, model_repo);
+                (WeightFiles::Remote { settings: settings.remote.clone(), batch_size: settings.max_batch_size }, DType::F32)
             }
-            Err(e) => {
-                // This model ('BAAI/bge-code-v1') IS sharded. If index.json is missing, it's an issue with the repo or network.
-                log::error!("model.safetensors.index.json not found for {} (Error: {}). This model is expected to be sharded. Cannot proceed without it.", EMBEDDING_MODEL_REPO, e);
-                return Err(anyhow::anyhow!("model.safetensors.index.json is required for sharded model {} but was not found. Error: {}", EMBEDDING_MODEL_REPO, e));
+            (EmbedderBackend::Onnx, _) => {
+                let onnx_path = repo.get(&settings.onnx_file)
+                    .with_context(|| format!("Failed to get {} from {}", settings.onnx_file, model_repo))?;
+                (WeightFiles::Onnx(onnx_path), DType::F32)
+            }
+            (EmbedderBackend::Candle, Some(quantized)) => {
+                let gguf_repo = api.model(quantized.repo.clone());
+                let gguf_path = gguf_repo.get(&quantized.file)
+                    .with_context(|| format!("Failed to get {} from {}", quantized.file, quantized.repo))?;
+                (WeightFiles::Gguf(gguf_path), DType::F32)
+            }
+            (EmbedderBackend::Candle, None) => {
+                let (config, shards) = fetch_full_model(&repo, model_repo)?;
+                let architecture = ModelArchitecture::detect(&std::fs::read_to_string(&config)?)
+                    .with_context(|| format!("Can't load {}", model_repo))?;
+                log::info!("{} is a {:?} model", model_repo, architecture);
+                (WeightFiles::Full { config, shards, architecture }, select_dtype(settings.dtype, architecture))
             }
         };
-
-        log::info!("Model config file for {}: {:?}", EMBEDDING_MODEL_REPO, config_filename);
-        log::info!("Tokenizer file for {}: {:?}", EMBEDDING_MODEL_REPO, tokenizer_filename);
-        log::info!("Model weight files to load for {}: {:?}", EMBEDDING_MODEL_REPO, model_files);
-
-        let config_str = std::fs::read_to_string(config_filename)?;
-        let config: Qwen2Config = serde_json::from_str(&config_str)
-            .context(format!("Failed to parse Qwen2Config from config.json for {}", EMBEDDING_MODEL_REPO))?;
+        log::info!("Embedder will use device: {:?} (dtype {:?})", device, dtype);
+        let pooling = resolve_pooling(&repo, model_repo, settings.pooling);
+        let remote_model = (settings.backend == EmbedderBackend::Remote).then(|| settings.remote.model.clone());
+        let profile = model_profile(remote_model.as_deref().unwrap_or(model_repo));
+        let query_prefix = settings.query_prefix.clone().unwrap_or_else(|| profile.query_prefix.to_string());
+        let passage_prefix = settings.passage_prefix.clone().unwrap_or_else(|| profile.passage_prefix.to_string());
+        downloaded();
 
         let tokenizer = Tokenizer::from_file(&tokenizer_filename)
-            .map_err(|e| AnyhowError::msg(format!("Failed to load tokenizer for {}: {}", EMBEDDING_MODEL_REPO, e)))?;
-
-        let vb = unsafe {
-            VarBuilder::from_mmaped_safetensors(&model_files, DTYPE, &device)?
+            .map_err(|e| AnyhowError::msg(format!("Failed to load tokenizer for {}: {}", model_repo, e)))?;
+        let model = match load_model(&weight_files, model_repo, dtype, &device) {
+            // Small GPUs can't hold large models such as bge-code-v1; the CPU is slow but works.
+            Err(e) if !device.is_cpu() && is_out_of_memory(&e) => {
+                log::warn!("Not enough {} memory to load {}; falling back to the CPU: {:#}", device_name(&device), model_repo, e);
+                notify_device_fallback(model_repo, &device, &e);
+                (device, dtype) = (Device::Cpu, DType::F32);
+                load_model(&weight_files, model_repo, dtype, &device)?
+            }
+            loaded => loaded?,
         };
+        // Padded positions are masked out, so the exact pad token doesn't matter much.
+        let pad_token_id = tokenizer.get_padding().map(|p| p.pad_id)
+            .or_else(|| tokenizer.token_to_id("<|endoftext|>"))
+            .unwrap_or(0);
 
-        let model = Qwen2Model::load(vb, &config)?;
-
-        log::info!("Embedding model {} loaded successfully.", EMBEDDING_MODEL_REPO);
+        log::info!("Embedding model {} loaded successfully.", model_repo);
 
         Ok(Self {
             model,
             tokenizer,
             device,
+            dtype,
+            model_repo: model_repo.to_string(),
+            model_revision: settings.model_revision.clone(),
+            quantized_file: settings.quantized.as_ref().filter(|_| settings.backend == EmbedderBackend::Candle).map(|q| q.file.clone()),
+            remote_model: remote_model.map(|model| format!("{}@{}", model, settings.remote.url)),
+            chunk_tokens: settings.chunk_tokens,
+            chunk_overlap: settings.chunk_overlap,
+            batch_size: AtomicUsize::new(settings.max_batch_size.max(1)),
+            dimension: AtomicUsize::new(0),
+            weights: weight_files,
+            cpu_fallback: OnceLock::new(),
+            pad_token_id,
+            pooling,
+            max_length: settings.max_length.max(1),
+            query_prefix,
+            passage_prefix,
         })
     }
 
+    /// Embeds `sentences`, returning one L2-normalised vector per sentence in input order.
+    /// Sentences run through the model `max_batch_size` at a time, padded to the longest in
+    /// their batch and masked. When the GPU runs out of memory, batches are halved (for the rest
+    /// of the session too), and a single text that still doesn't fit moves the model to the CPU.
+    #[tracing::instrument(skip_all, fields(batch_size = sentences.len(), model = %self.model_repo))]
     pub fn embed_batch(&self, sentences: &[String]) -> Result<Vec<Vec<f32>>> {
         if sentences.is_empty() {
             return Ok(Vec::new());
         }
+        if let Some(embeddings) = self.model.embed_texts(sentences) {
+            return embeddings.inspect(|embeddings| self.record_dimension(embeddings));
+        }
 
         let encodings = self.tokenizer.encode_batch(
             sentences.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
             true // add_special_tokens
         ).map_err(|e| AnyhowError::msg(format!("Failed to tokenize batch: {}", e)))?;
 
-        let mut all_embeddings = Vec::new();
+        let mut truncated = 0;
+        let token_rows: Vec<Vec<u32>> = encodings.iter().map(|e| {
+            let mut ids = unpadded_ids(e.get_ids(), e.get_attention_mask());
+            let trailing_special = trailing_special_tokens(e.get_attention_mask(), e.get_special_tokens_mask());
+            truncated += usize::from(truncate_ids(&mut ids, trailing_special, self.max_length));
+            ids
+        }).collect();
+        if truncated > 0 {
+            log::warn!("Truncated {} of {} texts to max_length = {} tokens", truncated, sentences.len(), self.max_length);
+        }
+        // Group sentences of similar length so each forward pass pads as little as possible.
+        let mut order: Vec<usize> = (0..token_rows.len()).collect();
+        order.sort_by_key(|&i| token_rows[i].len());
 
-        for encoding in encodings {
-            let token_ids_vec: Vec<u32> = encoding.get_ids().to_vec();
-            let token_ids = Tensor::new(token_ids_vec.as_slice(), &self.device)?.unsqueeze(0)?;
+        let mut all_embeddings = vec![Vec::new(); token_rows.len()];
+        for batch in order.chunks(self.batch_size.load(Ordering::Relaxed)) {
+            let rows: Vec<&[u32]> = batch.iter().map(|&i| token_rows[i].as_slice()).collect();
+            for (&i, embedding) in batch.iter().zip(self.forward_with_fallback(&rows)?) {
+                all_embeddings[i] = embedding;
+            }
+        }
 
-            // Qwen2Model::forward takes (tokens, start_pos)
-            // start_pos = 0 for non-kv-cached scenario (i.e., full sequence processing)
-            let model_output = self.model.forward(&token_ids, 0)?;
-            // log::trace!("Raw Qwen2 model output (last_hidden_state) shape: {:?}", model_output.shape());
+        log::debug!("Generated {} embeddings with model {}.", all_embeddings.len(), self.model_repo);
+        self.record_dimension(&all_embeddings);
+        Ok(all_embeddings)
+    }
 
-            // Pooling strategy: For BGE, typically CLS token embedding is used.
-            // This assumes the first token [CLS] is used for pooling.
-            // Output shape from Qwen2Model forward is (batch_size, seq_len, hidden_size)
-            let sentence_embedding = model_output.i((0, 0, ..))?;
-            // log::trace!("CLS token embedding shape: {:?}", sentence_embedding.shape());
+    // `forward_padded`, retried in halves on out-of-memory errors and, for a single row, on the CPU.
+    fn forward_with_fallback(&self, rows: &[&[u32]]) -> Result<Vec<Vec<f32>>> {
+        match self.forward_padded(rows) {
+            Err(e) if self.cpu_fallback.get().is_none() && !self.device.is_cpu() && is_out_of_memory(&e) => {
+                if rows.len() > 1 {
+                    let half = rows.len().div_ceil(2);
+                    let previous = self.batch_size.fetch_min(half, Ordering::Relaxed);
+                    if half < previous {
+                        log::warn!("Out of {} memory embedding {} texts at once; lowering the batch size to {}", device_name(&self.device), rows.len(), half);
+                    }
+                    let (first, second) = rows.split_at(half);
+                    let mut embeddings = self.forward_with_fallback(first)?;
+                    embeddings.extend(self.forward_with_fallback(second)?);
+                    Ok(embeddings)
+                } else {
+                    self.fall_back_to_cpu(&e)?;
+                    self.forward_padded(rows)
+                }
+            }
+            result => result,
+        }
+    }
 
-            // Normalization (L2 norm) - crucial for BGE models
-            let norm = sentence_embedding.sqr()?.sum_keepdim(D::Last)?.sqrt()?;
-            let sentence_embedding_normalized = sentence_embedding.broadcast_div(&norm)?;
-            // log::trace!("Normalized CLS token embedding shape: {:?}", sentence_embedding_normalized.shape());
+    // Loads the CPU copy of the model that every later forward pass uses.
+    fn fall_back_to_cpu(&self, cause: &AnyhowError) -> Result<()> {
+        log::warn!("Out of {} memory even for a single text; moving {} to the CPU: {:#}", device_name(&self.device), self.model_repo, cause);
+        let cpu_model = load_model(&self.weights, &self.model_repo, DType::F32, &Device::Cpu)
+            .context("Failed to load the model on the CPU after running out of GPU memory")?;
+        notify_device_fallback(&self.model_repo, &self.device, cause);
+        let _ = self.cpu_fallback.set(cpu_model);
+        Ok(())
+    }
 
-            all_embeddings.push(sentence_embedding_normalized.to_vec1::<f32>()?);
+    // One forward pass over rows of token ids, right-padded to the longest with a matching attention
+    // mask (1 = token, 0 = padding); returns one normalised embedding per row.
+    fn forward_padded(&self, rows: &[&[u32]]) -> Result<Vec<Vec<f32>>> {
+        let (model, device) = match self.cpu_fallback.get() {
+            Some(cpu_model) => (cpu_model, &Device::Cpu),
+            None => (&self.model, &self.device),
+        };
+        let max_len = rows.iter().map(|row| row.len()).max().unwrap_or(0).max(1);
+        let mut token_ids = Vec::with_capacity(rows.len() * max_len);
+        let mut attention_mask = Vec::with_capacity(rows.len() * max_len);
+        for row in rows {
+            let padding = max_len - row.len();
+            token_ids.extend_from_slice(row);
+            token_ids.extend(std::iter::repeat(self.pad_token_id).take(padding));
+            attention_mask.extend(std::iter::repeat(1u8).take(row.len()));
+            attention_mask.extend(std::iter::repeat(0u8).take(padding));
         }
+        let token_ids = Tensor::from_vec(token_ids, (rows.len(), max_len), device)?;
+        let attention_mask = Tensor::from_vec(attention_mask, (rows.len(), max_len), device)?;
 
-        log::debug!("Generated {} embeddings with model {}.", all_embeddings.len(), EMBEDDING_MODEL_REPO);
-        Ok(all_embeddings)
+        // Output shape is (batch_size, seq_len, hidden_size).
+        let model_output = model.forward(&token_ids, &attention_mask)?;
+
+        let lengths: Vec<usize> = rows.iter().map(|row| row.len()).collect();
+        let sentence_embeddings = pool(&model_output, &attention_mask, &lengths, self.pooling)?;
+
+        // Normalization (L2 norm) - crucial for BGE models
+        let norm = sentence_embeddings.sqr()?.sum_keepdim(D::Last)?.sqrt()?;
+        let normalized = sentence_embeddings.broadcast_div(&norm)?;
+        Ok(normalized.to_dtype(DType::F32)?.to_vec2::<f32>()?)
     }
 
     pub fn embed_sentence(&self, sentence: &str) -> Result<Vec<f32>> {
         let embeddings_batch = self.embed_batch(&[sentence.to_string()])?;
         embeddings_batch.into_iter().next()
-            .context(format!("Embedding batch returned no results for a single sentence using model {}", EMBEDDING_MODEL_REPO))
+            .context(format!("Embedding batch returned no results for a single sentence using model {}", self.model_repo))
+    }
+
+    /// Number of tokens the model sees for `texts`, special tokens included, after truncation.
+    pub fn count_tokens(&self, texts: &[String]) -> Result<usize> {
+        let encodings = self.tokenizer.encode_batch(texts.iter().map(|s| s.as_str()).collect::<Vec<_>>(), true)
+            .map_err(|e| AnyhowError::msg(format!("Failed to tokenize batch: {}", e)))?;
+        Ok(encodings.iter().map(|e| e.get_attention_mask().iter().filter(|&&mask| mask != 0).count().min(self.max_length)).sum())
+    }
+
+    /// The chunker for this model's docs.
+    pub fn chunker(&self) -> TextChunker {
+        TextChunker { tokenizer: self.tokenizer.clone(), chunk_tokens: self.chunk_tokens, chunk_overlap: self.chunk_overlap, max_length: self.max_length }
     }
+
+    fn record_dimension(&self, embeddings: &[Vec<f32>]) {
+        if let Some(embedding) = embeddings.first() {
+            self.dimension.store(embedding.len(), Ordering::Relaxed);
+        }
+    }
+
+    /// Length of the model's vectors. Embeds a probe text if nothing was embedded yet.
+    pub fn dimension(&self) -> Result<usize> {
+        match self.dimension.load(Ordering::Relaxed) {
+            0 => Ok(self.embed_sentence("dimension probe")?.len()),
+            dimension => Ok(dimension),
+        }
+    }
+
+    /// Texts per forward pass at the moment: `max_batch_size`, or less after the GPU ran out of memory.
+    pub fn batch_size(&self) -> usize {
+        self.batch_size.load(Ordering::Relaxed)
+    }
+
+    /// Size of the weight files on disk, which is roughly what the model takes in memory
+    /// (more if the weights are converted to a wider dtype). 0 for the remote backend.
+    pub fn weights_size_bytes(&self) -> u64 {
+        let file_size = |path: &PathBuf| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        match &self.weights {
+            WeightFiles::Full { shards, .. } => shards.iter().map(file_size).sum(),
+            WeightFiles::Gguf(path) | WeightFiles::Onnx(path) => file_size(path),
+            WeightFiles::Remote { .. } => 0,
+        }
+    }
+
+    /// The device the model runs on: "cpu", "cuda" or "metal", or "remote" for the remote backend.
+    pub fn device_name(&self) -> &'static str {
+        if self.remote_model.is_some() {
+            "remote"
+        } else if self.cpu_fallback.get().is_some() {
+            "cpu"
+        } else {
+            device_name(&self.device)
+        }
+    }
+
+    pub fn model_repo(&self) -> &str {
+        &self.model_repo
+    }
+
+    pub fn model_revision(&self) -> &str {
+        &self.model_revision
+    }
+
+    pub fn pooling(&self) -> PoolingStrategy {
+        self.pooling
+    }
+
+    /// `query` as the model expects search queries, i.e. with the query prefix.
+    pub fn query_text(&self, query: &str) -> String {
+        format!("{}{}", self.query_prefix, query)
+    }
+
+    /// Prefix for texts indexed to be found by queries.
+    pub fn passage_prefix(&self) -> &str {
+        &self.passage_prefix
+    }
+
+    /// Identifies the weights behind the embeddings: `repo@revision`, plus `/file` for GGUF weights
+    /// and `#pooling` unless CLS, or `remote:model@url`. Embeddings with different ids are not comparable.
+    pub fn model_id(&self) -> String {
+        if let Some(remote_model) = &self.remote_model {
+            return format!("remote:{}", remote_model);
+        }
+        let mut id = match &self.quantized_file {
+            Some(file) => format!("{}@{}/{}", self.model_repo, self.model_revision, file),
+            None => format!("{}@{}", self.model_repo, self.model_revision),
+        };
+        if self.pooling != PoolingStrategy::Cls {
+            id.push('#');
+            id.push_str(self.pooling.as_str());
+        }
+        id
+    }
+}
+
+/// Splits docs into the token windows an embedder embeds. Holds a copy of the model's tokenizer,
+/// so chunking doesn't occupy the embedder worker.
+#[derive(Clone)]
+pub struct TextChunker {
+    tokenizer: Tokenizer,
+    chunk_tokens: usize,
+    chunk_overlap: usize,
+    max_length: usize,
+}
+
+impl TextChunker {
+    /// Splits a long doc into overlapping token windows (`chunk_tokens` / `chunk_overlap`), so
+    /// text past the model's useful length still gets embedded. Short docs come back whole.
+    pub fn chunk_text<'a>(&self, text: &'a str) -> Result<Vec<&'a str>> {
+        let encoding = self.tokenizer.encode(text, false)
+            .map_err(|e| AnyhowError::msg(format!("Failed to tokenize text for chunking: {}", e)))?;
+        Ok(crate::chunking::split_windows(text, encoding.get_offsets(), self.chunk_tokens, self.chunk_overlap))
+    }
+
+    /// Whether the embedder truncates `text` (special tokens included) to `max_length`.
+    pub fn exceeds_max_length(&self, text: &str) -> Result<bool> {
+        let encoding = self.tokenizer.encode(text, true)
+            .map_err(|e| AnyhowError::msg(format!("Failed to tokenize text: {}", e)))?;
+        Ok(encoding.get_attention_mask().iter().filter(|&&mask| mask != 0).count() > self.max_length)
+    }
+}
+
+/// What the embedder is running on, shown in the UI.
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbedderInfo {
+    pub loaded: bool,
+    pub model_repo: String,
+    pub model_revision: String,
+    /// Device in use ("cpu", "cuda" or "metal"); `None` while no model is loaded.
+    pub device: Option<String>,
+    pub dtype: Option<String>,
+    /// GGUF file the weights were loaded from, if quantized.
+    pub quantized_file: Option<String>,
+    /// Pooling in use ("cls", "mean" or "last_token"); `None` while no model is loaded.
+    pub pooling: Option<String>,
+    /// Preset selected in the settings, if any.
+    pub preset: Option<ModelPreset>,
+    /// Runtime selected in the settings.
+    pub backend: EmbedderBackend,
+    /// Device requested in the settings.
+    pub device_preference: DevicePreference,
+    /// Accelerators compiled into this build (`metal` needs the `metal` cargo feature).
+    pub cuda_available: bool,
+    pub metal_available: bool,
+}
+
+/// Describes the loaded global embedder, falling back to `settings` when none is loaded.
+pub async fn global_embedder_info(settings: &EmbedderSettings) -> Result<EmbedderInfo> {
+    let settings = settings.clone();
+    with_global_embedder(move |embedder| EmbedderInfo {
+        loaded: embedder.is_some(),
+        model_repo: embedder.map_or_else(|| settings.model_repo.clone(), |e| e.model_repo.clone()),
+        model_revision: embedder.map_or_else(|| settings.model_revision.clone(), |e| e.model_revision.clone()),
+        device: embedder.map(|e| e.device_name().to_string()),
+        dtype: embedder.map(|e| format!("{:?}", e.dtype).to_lowercase()),
+        quantized_file: embedder.and_then(|e| e.quantized_file.clone()),
+        pooling: embedder.map(|e| e.pooling.as_str().to_string()),
+        preset: settings.preset,
+        backend: settings.backend,
+        device_preference: settings.device,
+        cuda_available: candle_core::utils::cuda_is_available(),
+        metal_available: candle_core::utils::metal_is_available(),
+    }).await
+}
+
+/// `Embedder::model_id` of the loaded embedder, if any.
+pub async fn global_model_id() -> Option<String> {
+    with_global_embedder(|embedder| embedder.map(|e| e.model_id())).await.ok().flatten()
 }
 
 use once_cell::sync::Lazy;
 pub static GLOBAL_EMBEDDER: Lazy<Mutex<Option<Embedder>>> = Lazy::new(|| Mutex::new(None));
+// Counts the loads of the global embedder started so far. A load only installs its model (and
+// reports its status) while it is the latest one, so a slow startup load finishing after a
+// hot reload can't replace the reloaded model. Held while a load's status is checked and sent,
+// so a superseded load can't report in between. Lock order: GLOBAL_EMBEDDER, then this, then
+// EMBEDDER_STATUS's channel lock (taken by `set_status`'s `send_replace`).
+static EMBEDDER_GENERATION: Mutex<usize> = Mutex::new(0);
 
-pub fn init_global_embedder() -> Result<()> {
-    log::info!("Attempting to initialize global embedder with model {}...", EMBEDDING_MODEL_REPO);
-    let mut guard = GLOBAL_EMBEDDER.lock().map_err(|e| AnyhowError::msg(format!("Failed to acquire lock on GLOBAL_EMBEDDER: {}",e)))?;
-    if guard.is_none() {
-        match Embedder::new() {
+type EmbedderJob = Box<dyn FnOnce(Option<&Embedder>) + Send>;
+
+// Every model call runs on this one thread, so inference never blocks the async runtime and
+// concurrent requests queue instead of contending for the lock. Jobs run in arrival order;
+// processing submits one batch per job, so a query waits for at most one batch.
+static EMBEDDER_WORKER: Lazy<mpsc::UnboundedSender<EmbedderJob>> = Lazy::new(|| {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<EmbedderJob>();
+    std::thread::Builder::new()
+        .name("embedder-worker".to_string())
+        .spawn(move || {
+            while let Some(job) = receiver.blocking_recv() {
+                let guard = GLOBAL_EMBEDDER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                // A panicking job only fails its own request: its reply sender is dropped.
+                if std::panic::catch_unwind(AssertUnwindSafe(|| job(guard.as_ref()))).is_err() {
+                    log::error!("An embedder job panicked");
+                }
+            }
+        })
+        .expect("Failed to spawn the embedder worker thread");
+    sender
+});
+
+/// Runs `f` on the embedder worker thread with the loaded global embedder (`None` while no model
+/// is loaded) and returns its result.
+pub async fn with_global_embedder<T: Send + 'static>(f: impl FnOnce(Option<&Embedder>) -> T + Send + 'static) -> Result<T> {
+    let (reply, result) = oneshot::channel();
+    EMBEDDER_WORKER.send(Box::new(move |embedder| { let _ = reply.send(f(embedder)); }))
+        .map_err(|_| AnyhowError::msg("The embedder worker has stopped"))?;
+    result.await.map_err(|_| AnyhowError::msg("The embedder job panicked"))
+}
+
+/// `with_global_embedder` for work that must use the model `model_id`, e.g. the batches of one
+/// processing run; fails if no model is loaded or another one was loaded in the meantime.
+pub async fn with_model<T: Send + 'static>(model_id: &str, f: impl FnOnce(&Embedder) -> Result<T> + Send + 'static) -> Result<T> {
+    let model_id = model_id.to_string();
+    with_global_embedder(move |embedder| match embedder {
+        Some(embedder) if embedder.model_id() == model_id => f(embedder),
+        Some(embedder) => Err(AnyhowError::msg(format!("The embedding model changed from {} to {}", model_id, embedder.model_id()))),
+        None => Err(AnyhowError::msg(format!("The embedding model {} is no longer loaded", model_id))),
+    }).await?
+}
+
+/// Embeds the search query `text` (with the model's query prefix) with the global embedder on
+/// the worker, returning the vector and the `model_id` that produced it (to check against stored
+/// vectors); `Ok(None)` while no model is loaded.
+pub async fn embed_with_global(text: &str) -> Result<Option<(Vec<f32>, String)>> {
+    let text = text.to_string();
+    with_global_embedder(move |embedder| embedder.map(|e| Ok((e.embed_sentence(&e.query_text(&text))?, e.model_id()))).transpose()).await?
+}
+
+/// Where loading the global embedder stands, for the UI and for requests that arrive before
+/// the model is ready. Serialized as `{ "state": "<snake_case state>", ... }`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum EmbedderStatus {
+    NotStarted,
+    /// Fetching the model files from the HuggingFace Hub; quick once they are cached.
+    Downloading { model_repo: String },
+    /// Loading the weights onto the device.
+    Loading { model_repo: String },
+    Ready { model_repo: String },
+    Failed { model_repo: String, message: String },
+}
+
+impl EmbedderStatus {
+    /// Whether the embedder is on its way to `Ready`, i.e. callers should wait rather than fail.
+    pub fn is_loading(&self) -> bool {
+        matches!(self, EmbedderStatus::Downloading { .. } | EmbedderStatus::Loading { .. })
+    }
+}
+
+static EMBEDDER_STATUS: Lazy<watch::Sender<EmbedderStatus>> = Lazy::new(|| watch::channel(EmbedderStatus::NotStarted).0);
+
+fn set_status(status: EmbedderStatus) {
+    log::debug!("Embedder status: {:?}", status);
+    EMBEDDER_STATUS.send_replace(status);
+}
+
+pub fn embedder_status() -> EmbedderStatus {
+    EMBEDDER_STATUS.borrow().clone()
+}
+
+/// Follows the global embedder's status, e.g. to report when a download finishes.
+pub fn subscribe_status() -> watch::Receiver<EmbedderStatus> {
+    EMBEDDER_STATUS.subscribe()
+}
+
+/// Waits while the global embedder is downloading or loading, then returns its status.
+pub async fn wait_until_loaded() -> EmbedderStatus {
+    let mut receiver = EMBEDDER_STATUS.subscribe();
+    let settled = receiver.wait_for(|status| !status.is_loading()).await.map(|status| status.clone());
+    settled.unwrap_or_else(|_| embedder_status())
+}
+
+// Starts a load of the global embedder, superseding any load still running; returns its generation.
+fn start_load() -> usize {
+    let mut generation = EMBEDDER_GENERATION.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    *generation += 1;
+    *generation
+}
+
+fn is_current_load(generation: usize) -> bool {
+    *EMBEDDER_GENERATION.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) == generation
+}
+
+// Reports the status of load `generation`, unless a later load has started since.
+fn set_load_status(generation: usize, status: EmbedderStatus) {
+    let current = EMBEDDER_GENERATION.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if *current == generation {
+        set_status(status);
+    }
+}
+
+// Installs `embedder` as the global embedder and reports it `Ready` if load `generation` is still
+// the latest; a superseded load's model is dropped. The check, install and status all happen under
+// both locks, so a reload that starts meanwhile always reports after it. Returns whether it was
+// installed.
+fn install_if_current(generation: usize, embedder: Embedder) -> Result<bool> {
+    let mut guard = GLOBAL_EMBEDDER.lock().map_err(|e| AnyhowError::msg(format!("Failed to acquire lock on GLOBAL_EMBEDDER: {}", e)))?;
+    let current = EMBEDDER_GENERATION.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if *current != generation {
+        log::info!("Dropping embedding model {}: a later load replaced it.", embedder.model_repo());
+        return Ok(false);
+    }
+    set_status(EmbedderStatus::Ready { model_repo: embedder.model_repo().to_string() });
+    *guard = Some(embedder);
+    Ok(true)
+}
+
+// Loads an embedder with `settings` as load `generation`, keeping EMBEDDER_STATUS up to date.
+fn load_tracked(settings: &EmbedderSettings, generation: usize) -> Result<Embedder> {
+    let model_repo = settings.model_repo.clone();
+    set_load_status(generation, EmbedderStatus::Downloading { model_repo: model_repo.clone() });
+    match Embedder::new_with_progress(settings, || set_load_status(generation, EmbedderStatus::Loading { model_repo: model_repo.clone() })) {
+        Ok(embedder) => Ok(embedder),
+        Err(e) => {
+            set_load_status(generation, EmbedderStatus::Failed { model_repo, message: format!("{:#}", e) });
+            Err(e)
+        }
+    }
+}
+
+pub fn init_global_embedder(settings: &EmbedderSettings) -> Result<()> {
+    let model_repo = settings.model_repo.as_str();
+    log::info!("Attempting to initialize global embedder with model {}...", model_repo);
+    let loaded = GLOBAL_EMBEDDER.lock().map_err(|e| AnyhowError::msg(format!("Failed to acquire lock on GLOBAL_EMBEDDER: {}",e)))?.is_some();
+    if !loaded {
+        let generation = start_load();
+        // Load without holding the lock, so the worker keeps answering "not loaded" meanwhile.
+        match load_tracked(settings, generation) {
             Ok(embedder) => {
-                *guard = Some(embedder);
-                log::info!("Global embedder initialized successfully with model {}.", EMBEDDING_MODEL_REPO);
+                if install_if_current(generation, embedder)? {
+                    log::info!("Global embedder initialized successfully with model {}.", model_repo);
+                }
+            }
+            Err(e) if !is_current_load(generation) => {
+                log::info!("Ignoring the failed initial load of {}: a later load replaced it. ({:#})", model_repo, e);
             }
             Err(e) => {
-                log::error!("Failed to initialize global embedder with model {}: {:?}", EMBEDDING_MODEL_REPO, e);
-                return Err(e.context(format!("Embedder::new() failed for model {} during global initialization", EMBEDDING_MODEL_REPO)));
+                log::error!("Failed to initialize global embedder with model {}: {:?}", model_repo, e);
+                return Err(e.context(format!("Embedder::new() failed for model {} during global initialization", model_repo)));
             }
         }
     } else {
         // Check if the existing embedder is for the correct model, though this function is usually called once.
-        log::info!("Global embedder (model {}) already initialized or initialization was attempted.", EMBEDDING_MODEL_REPO);
+        log::info!("Global embedder (model {}) already initialized or initialization was attempted.", model_repo);
+    }
+    Ok(())
+}
+
+/// Runs `init_global_embedder` on a background thread, so startup doesn't wait for a
+/// multi-GB download. The status is `Downloading` as soon as this returns; `on_done` gets
+/// the final status (`Ready` or `Failed`).
+pub fn spawn_global_embedder_init(settings: EmbedderSettings, on_done: impl FnOnce(EmbedderStatus) + Send + 'static) {
+    let model_repo = settings.model_repo.clone();
+    set_status(EmbedderStatus::Downloading { model_repo: model_repo.clone() });
+    let spawned = std::thread::Builder::new().name("embedder-init".to_string()).spawn(move || {
+        if let Err(e) = init_global_embedder(&settings) {
+            log::error!("Failed to initialize global embedder in the background: {:?}. Some features might not work.", e);
+        }
+        on_done(embedder_status());
+    });
+    if let Err(e) = spawned {
+        log::error!("Failed to spawn the embedder init thread: {:?}", e);
+        set_status(EmbedderStatus::Failed { model_repo, message: e.to_string() });
+    }
+}
+
+/// Drops the current global embedder and loads one with `settings`. The old model is freed
+/// before the new one loads, so both never sit in memory at once; queries fail with "still
+/// loading" in between. On failure the global embedder stays empty.
+pub fn reload_global_embedder(settings: &EmbedderSettings) -> Result<()> {
+    // Started before unloading, so a load already running can't install its model afterwards.
+    let generation = start_load();
+    {
+        let mut guard = GLOBAL_EMBEDDER.lock().map_err(|e| AnyhowError::msg(format!("Failed to acquire lock on GLOBAL_EMBEDDER: {}", e)))?;
+        if let Some(old) = guard.take() {
+            log::info!("Unloading embedding model {} (revision {}).", old.model_repo(), old.model_revision());
+        }
+    }
+    // Load without holding the lock; downloading a model can take minutes.
+    let embedder = load_tracked(settings, generation)
+        .with_context(|| format!("Embedder::new() failed for model {} during reload", settings.model_repo))?;
+    if !install_if_current(generation, embedder)? {
+        anyhow::bail!("Reloading {} was superseded by a later reload", settings.model_repo);
     }
+    log::info!("Global embedder reloaded with model {}.", settings.model_repo);
     Ok(())
 }
 
@@ -186,14 +1110,108 @@ pub fn init_global_embedder() -> Result<()> {
 mod tests {
     use super::*;
 
+    // Held by the tests that start loads or set the global status, so they don't supersede each
+    // other's loads.
+    static LOAD_LOCK: Mutex<()> = Mutex::new(());
+
+    #[tokio::test]
+    async fn test_worker_survives_panicking_jobs() {
+        let panicked = with_global_embedder(|_| -> usize { panic!("boom") }).await;
+        assert!(panicked.is_err());
+        assert_eq!(with_global_embedder(|_| 42).await.unwrap(), 42);
+    }
+
+    #[test]
+    fn test_superseded_load_keeps_quiet() {
+        let _lock = LOAD_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = embedder_status();
+        let startup = start_load();
+        let reload = start_load();
+        let failed = EmbedderStatus::Failed { model_repo: "BAAI/bge-code-v1".to_string(), message: "disk full".to_string() };
+        set_load_status(reload, failed.clone());
+        // The startup load finishing late doesn't report over the reload.
+        set_load_status(startup, EmbedderStatus::Ready { model_repo: "BAAI/bge-small-en-v1.5".to_string() });
+        assert!(!is_current_load(startup));
+        assert_eq!(embedder_status(), failed);
+        set_status(previous);
+    }
+
+    #[test]
+    fn test_pooling() -> Result<()> {
+        // Two rows of three tokens with two-dimensional states; the second row has one pad token.
+        let hidden = Tensor::new(&[[[1f32, 0.], [3., 2.], [5., 4.]], [[2., 2.], [4., 0.], [9., 9.]]], &Device::Cpu)?;
+        let mask = Tensor::new(&[[1u8, 1, 1], [1, 1, 0]], &Device::Cpu)?;
+        let pooled = |pooling| -> Result<Vec<Vec<f32>>> { Ok(pool(&hidden, &mask, &[3, 2], pooling)?.to_vec2()?) };
+        assert_eq!(pooled(PoolingStrategy::Cls)?, vec![vec![1., 0.], vec![2., 2.]]);
+        assert_eq!(pooled(PoolingStrategy::Mean)?, vec![vec![3., 2.], vec![3., 1.]]);
+        assert_eq!(pooled(PoolingStrategy::LastToken)?, vec![vec![5., 4.], vec![4., 0.]]);
+
+        assert_eq!(pooling_from_sentence_transformers(r#"{"word_embedding_dimension": 1536, "pooling_mode_cls_token": false, "pooling_mode_lasttoken": true}"#), Some(PoolingStrategy::LastToken));
+        assert_eq!(pooling_from_sentence_transformers(r#"{"pooling_mode_mean_tokens": true}"#), Some(PoolingStrategy::Mean));
+        assert_eq!(pooling_from_sentence_transformers("{}"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_profile() {
+        assert_eq!(model_profile("BAAI/bge-small-en-v1.5").query_prefix, "Represent this sentence for searching relevant passages: ");
+        assert!(model_profile("BAAI/bge-code-v1").query_prefix.starts_with("<instruct>"));
+        assert_eq!(model_profile("intfloat/e5-base-v2"), ModelProfile { query_prefix: "query: ", passage_prefix: "passage: " });
+        assert_eq!(model_profile("someone/custom-embedder"), ModelProfile { query_prefix: "", passage_prefix: "" });
+    }
+
+    #[test]
+    fn test_detect_architecture() -> Result<()> {
+        assert_eq!(ModelArchitecture::detect(r#"{"model_type": "qwen2", "architectures": ["Qwen2Model"]}"#)?, ModelArchitecture::Qwen2);
+        assert_eq!(ModelArchitecture::detect(r#"{"model_type": "bert", "architectures": ["BertModel"]}"#)?, ModelArchitecture::Bert);
+        assert_eq!(ModelArchitecture::detect(r#"{"model_type": "bert", "architectures": ["JinaBertForMaskedLM"]}"#)?, ModelArchitecture::JinaBert);
+        assert_eq!(ModelArchitecture::detect(r#"{"model_type": "bert", "position_embedding_type": "alibi"}"#)?, ModelArchitecture::JinaBert);
+        assert!(ModelArchitecture::detect(r#"{"model_type": "t5"}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_out_of_memory() {
+        let cuda = anyhow::anyhow!("DriverError(CUDA_ERROR_OUT_OF_MEMORY, \"out of memory\")").context("Failed to run the model");
+        assert!(is_out_of_memory(&cuda));
+        assert!(is_out_of_memory(&anyhow::anyhow!("Metal: failed to allocate buffer of 1073741824 bytes")));
+        assert!(!is_out_of_memory(&anyhow::anyhow!("shape mismatch in matmul")));
+    }
+
+    #[test]
+    fn test_unpadded_ids() {
+        // Tokenizer-side padding, left or right, is dropped.
+        assert_eq!(unpadded_ids(&[5, 6, 7, 0, 0], &[1, 1, 1, 0, 0]), vec![5, 6, 7]);
+        assert_eq!(unpadded_ids(&[0, 5, 6], &[0, 1, 1]), vec![5, 6]);
+        assert_eq!(unpadded_ids(&[5, 6], &[1, 1]), vec![5, 6]);
+    }
+
+    #[test]
+    fn test_truncate_ids() {
+        // The trailing EOS survives truncation.
+        let mut ids = vec![1, 2, 3, 4, 5, 99];
+        assert!(truncate_ids(&mut ids, 1, 4));
+        assert_eq!(ids, vec![1, 2, 3, 99]);
+        let mut short = vec![1, 2, 99];
+        assert!(!truncate_ids(&mut short, 1, 4));
+        assert_eq!(short, vec![1, 2, 99]);
+
+        // Right padding is skipped when counting trailing special tokens.
+        assert_eq!(trailing_special_tokens(&[1, 1, 1, 0], &[0, 0, 1, 1]), 1);
+        assert_eq!(trailing_special_tokens(&[1, 1], &[1, 0]), 0);
+    }
+
     #[tokio::test]
     // Test is enabled
     async fn test_embedder_init_and_embed() -> Result<()> {
-        // It's good practice to ensure test output is visible, env_logger or similar.
+        // It's good practice to ensure test output is visible, tracing-subscriber or similar.
         // RUST_LOG=info cargo test -- --nocapture embedder::tests::test_embedder_init_and_embed
-        let _ = env_logger::builder().is_test(true).filter_level(log::LevelFilter::Debug).try_init();
+        let _ = tracing_subscriber::fmt().with_test_writer().with_max_level(tracing::Level::DEBUG).try_init();
 
-        init_global_embedder().context("Test failed to initialize global embedder")?;
+        {
+            let _lock = LOAD_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            init_global_embedder(&EmbedderSettings::default()).context("Test failed to initialize global embedder")?;
+        }
 
         let embedder_guard = GLOBAL_EMBEDDER.lock().unwrap();
         let embedder = embedder_guard.as_ref().context("Embedder not initialized after init_global_embedder call")?;
@@ -203,8 +1221,8 @@ mod tests {
 
         assert!(!embedding.is_empty(), "Embedding should not be empty");
         // BAAI/bge-code-v1 has a hidden size of 1536
-        assert_eq!(embedding.len(), 1536, "Embedding dimension mismatch for {}. Expected 1536, got {}", EMBEDDING_MODEL_REPO, embedding.len());
-        log::info!("Single sentence embedding (first 5 dims for {}): {:?}", EMBEDDING_MODEL_REPO, &embedding[..5.min(embedding.len())]);
+        assert_eq!(embedding.len(), 1536, "Embedding dimension mismatch for {}. Expected 1536, got {}", embedder.model_repo(), embedding.len());
+        log::info!("Single sentence embedding (first 5 dims for {}): {:?}", embedder.model_repo(), &embedding[..5.min(embedding.len())]);
 
         let sentences = vec![
             "fn main() { println!(\"Hello, world!\"); }".to_string(),
@@ -214,9 +1232,18 @@ mod tests {
         assert_eq!(batch_embeddings.len(), sentences.len(), "Number of embeddings should match number of input sentences");
 
         for (i, emb) in batch_embeddings.iter().enumerate() {
-            assert_eq!(emb.len(), 1536, "Embedding dimension mismatch for sentence {} in batch (model {}). Expected 1536, got {}", i, EMBEDDING_MODEL_REPO, emb.len());
+            assert_eq!(emb.len(), 1536, "Embedding dimension mismatch for sentence {} in batch (model {}). Expected 1536, got {}", i, embedder.model_repo(), emb.len());
+        }
+        log::info!("Batch embeddings generated for {} sentences using {}.", batch_embeddings.len(), embedder.model_repo());
+
+        // Padding and masking must not change the result: a batch matches embedding one by one.
+        let mixed = vec![sentences[1].clone(), format!("{} {}", sentence, sentences[0])];
+        let batched = embedder.embed_batch(&mixed)?;
+        for (text, batched_embedding) in mixed.iter().zip(&batched) {
+            let single = embedder.embed_sentence(text)?;
+            let similarity: f32 = single.iter().zip(batched_embedding).map(|(a, b)| a * b).sum();
+            assert!(similarity > 0.99, "Batched embedding differs from the single one (cosine {})", similarity);
         }
-        log::info!("Batch embeddings generated for {} sentences using {}.", batch_embeddings.len(), EMBEDDING_MODEL_REPO);
 
         let empty_batch_embeddings = embedder.embed_batch(&[]).context("Failed to process empty batch")?;
         assert!(empty_batch_embeddings.is_empty(), "Embedding an empty batch should result in an empty list of embeddings");
@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::embedder::Embedder;
+
+/// Content-addressed store of embeddings: SHA-256 of (model id, text) -> vector, in an SQLite
+/// file in the cache directory. Identical doc text (re-exports, the same crate in several
+/// projects or versions) is embedded once per model, however many times it is processed.
+#[derive(Debug, Clone)]
+pub struct EmbeddingCache {
+    conn: Arc<Mutex<Connection>>,
+    path: Option<PathBuf>,
+}
+
+impl EmbeddingCache {
+    /// Opens (or creates) the cache at `path`. Blocking.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).with_context(|| format!("Failed to open embedding cache {}", path.display()))?;
+        Self::init(conn, Some(path.to_path_buf()))
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?, None)
+    }
+
+    /// The database file, None for an in-memory cache.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn init(conn: Connection, path: Option<PathBuf>) -> Result<Self> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch("CREATE TABLE IF NOT EXISTS embeddings (key BLOB PRIMARY KEY, vector BLOB NOT NULL) WITHOUT ROWID;")
+            .context("Failed to create the embedding cache table")?;
+        Ok(Self { conn: Arc::new(Mutex::new(conn)), path })
+    }
+
+    fn conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
+        self.conn.lock().map_err(|e| anyhow::anyhow!("Embedding cache lock poisoned: {}", e))
+    }
+
+    /// The cached vector of each text, in input order. Blocking.
+    pub fn get_many(&self, model_id: &str, texts: &[String]) -> Result<Vec<Option<Vec<f32>>>> {
+        let conn = self.conn()?;
+        let mut query = conn.prepare_cached("SELECT vector FROM embeddings WHERE key = ?1")?;
+        texts.iter().map(|text| {
+            let vector: Option<Vec<u8>> = query.query_row(params![cache_key(model_id, text)], |row| row.get(0)).optional()?;
+            Ok(vector.map(|bytes| decode_vector(&bytes)))
+        }).collect()
+    }
+
+    /// Blocking.
+    pub fn put_many(&self, model_id: &str, entries: &[(&String, &Vec<f32>)]) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare_cached("INSERT OR REPLACE INTO embeddings (key, vector) VALUES (?1, ?2)")?;
+            for (text, vector) in entries {
+                insert.execute(params![cache_key(model_id, text), encode_vector(vector)])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Deletes every cached vector and shrinks the file. Returns how many were deleted. Blocking.
+    pub fn clear(&self) -> Result<usize> {
+        let conn = self.conn()?;
+        let deleted = conn.execute("DELETE FROM embeddings", [])?;
+        conn.execute_batch("VACUUM;").context("Failed to shrink the embedding cache")?;
+        Ok(deleted)
+    }
+
+    /// `Embedder::embed_batch` that only runs the model on texts not cached for its model, and
+    /// caches the new vectors. A failing cache is logged and bypassed. Blocking.
+    pub fn embed_batch(&self, embedder: &Embedder, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let model_id = embedder.model_id();
+        let cached = self.get_many(&model_id, texts).unwrap_or_else(|e| {
+            log::warn!("Embedding cache lookup failed, embedding everything: {:?}", e);
+            vec![None; texts.len()]
+        });
+        let missing: Vec<usize> = (0..texts.len()).filter(|&i| cached[i].is_none()).collect();
+        if missing.len() < texts.len() {
+            log::debug!("Embedding cache hit for {} of {} texts", texts.len() - missing.len(), texts.len());
+        }
+        let missing_texts: Vec<String> = missing.iter().map(|&i| texts[i].clone()).collect();
+        let computed = embedder.embed_batch(&missing_texts)?;
+        let new_entries: Vec<(&String, &Vec<f32>)> = missing_texts.iter().zip(&computed).collect();
+        if let Err(e) = self.put_many(&model_id, &new_entries) {
+            log::warn!("Failed to store {} embeddings in the cache: {:?}", new_entries.len(), e);
+        }
+
+        let mut computed = computed.into_iter();
+        Ok(cached.into_iter().map(|vector| vector.or_else(|| computed.next()).unwrap_or_default()).collect())
+    }
+}
+
+fn cache_key(model_id: &str, text: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(model_id.as_bytes());
+    // Separator, so ("a", "bc") and ("ab", "c") differ.
+    hasher.update([0u8]);
+    hasher.update(text.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_is_keyed_by_model_and_text() -> Result<()> {
+        let cache = EmbeddingCache::open_in_memory()?;
+        let (text, other) = ("Parses a config.".to_string(), "Runs it.".to_string());
+        cache.put_many("bge@main", &[(&text, &vec![0.5, -1.25])])?;
+
+        let hits = cache.get_many("bge@main", &[text.clone(), other.clone()])?;
+        assert_eq!(hits, vec![Some(vec![0.5, -1.25]), None]);
+        // Another model's vectors are never returned.
+        assert_eq!(cache.get_many("bge@v2", &[text.clone()])?, vec![None]);
+        assert_ne!(cache_key("a", "bc"), cache_key("ab", "c"));
+
+        assert_eq!(cache.clear()?, 1);
+        assert_eq!(cache.get_many("bge@main", &[text])?, vec![None]);
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use crate::pipeline::item_signature;
+use crate::rustdoc_processor::DocItem;
+
+// Members named in the header of a struct, enum or trait; the rest are summarized as a count.
+const MAX_LISTED_MEMBERS: usize = 12;
+
+/// The direct members of every item (fields and methods of a type, variants of an enum, items of
+/// a trait), so a type's embedding text can name them.
+#[derive(Default)]
+pub struct MemberIndex<'a> {
+    members: HashMap<&'a str, Vec<&'a DocItem>>,
+}
+
+impl<'a> MemberIndex<'a> {
+    /// Members are the items whose path continues the container's (`Config::port`) or whose impl
+    /// block belongs to it (`parent`), in path order.
+    pub fn build(items: impl IntoIterator<Item = &'a DocItem>) -> Self {
+        let mut members: HashMap<&str, Vec<&DocItem>> = HashMap::new();
+        for item in items {
+            let container = item.parent.as_deref()
+                .or_else(|| item.full_path_str.rsplit_once("::").map(|(container, _)| container));
+            if let Some(container) = container {
+                members.entry(container).or_default().push(item);
+            }
+        }
+        for list in members.values_mut() {
+            list.sort_by(|a, b| a.full_path_str.cmp(&b.full_path_str));
+        }
+        Self { members }
+    }
+
+    pub fn members_of(&self, full_path: &str) -> &[&'a DocItem] {
+        self.members.get(full_path).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// The text embedded for one chunk of an item's docs: a short header the retriever can match on
+/// (kind, path, declaration, enclosing type, crate, and for types their members), then the chunk.
+/// Every indexing path (rustdoc projects, doc sources) goes through here so their vectors are
+/// comparable. Changing the templates changes every vector; bump `store::STORE_FORMAT_VERSION`
+/// with them so stored projects are re-embedded.
+pub fn embedding_text(item: &DocItem, members: &[&DocItem], chunk: &str) -> String {
+    let mut header = format!("{} {}", kind_label(&item.item_type), item.full_path_str);
+    match item.item_type.as_str() {
+        "function" | "method" | "assoc_const" | "assoc_type" | "constant" | "static" | "type_alias" => {
+            header.push_str(&format!("\nSignature: {}", item_signature(item)));
+        }
+        "struct" | "union" => {
+            header.push_str(&format!("\nSignature: {}", item_signature(item)));
+            push_members(&mut header, "Fields", members, &["struct_field"]);
+            push_members(&mut header, "Methods", members, &["method", "function"]);
+        }
+        "enum" => {
+            header.push_str(&format!("\nSignature: {}", item_signature(item)));
+            push_members(&mut header, "Variants", members, &["variant"]);
+            push_members(&mut header, "Methods", members, &["method", "function"]);
+        }
+        "trait" => {
+            header.push_str(&format!("\nSignature: {}", item_signature(item)));
+            push_members(&mut header, "Items", members, &["method", "function", "assoc_type", "assoc_const"]);
+        }
+        _ => {}
+    }
+    if let Some(parent) = &item.parent {
+        match &item.trait_name {
+            Some(trait_name) => header.push_str(&format!("\nIn: impl {} for {}", trait_name, parent)),
+            None => header.push_str(&format!("\nIn: impl {}", parent)),
+        }
+    }
+    format!("{}\nCrate: {}\n\n{}", header, item.crate_name, chunk)
+}
+
+/// The text embedded for one code example of an item, tagged as such.
+pub fn example_text(item: &DocItem, code: &str) -> String {
+    format!("Example of {} {}\nCrate: {}\n\n{}", kind_label(&item.item_type), item.full_path_str, item.crate_name, code)
+}
+
+// rustdoc's kind names in words, e.g. `struct_field` -> "field".
+fn kind_label(item_type: &str) -> &str {
+    match item_type {
+        "struct_field" => "field",
+        "type_alias" => "type alias",
+        "assoc_type" => "associated type",
+        "assoc_const" => "associated constant",
+        "trait_alias" => "trait alias",
+        other => other,
+    }
+}
+
+fn push_members(header: &mut String, label: &str, members: &[&DocItem], kinds: &[&str]) {
+    let names: Vec<&str> = members.iter()
+        .filter(|member| kinds.contains(&member.item_type.as_str()))
+        .map(|member| member.name.as_str())
+        .collect();
+    if names.is_empty() {
+        return;
+    }
+    header.push_str(&format!("\n{}: {}", label, names.iter().take(MAX_LISTED_MEMBERS).copied().collect::<Vec<_>>().join(", ")));
+    if names.len() > MAX_LISTED_MEMBERS {
+        header.push_str(&format!(" and {} more", names.len() - MAX_LISTED_MEMBERS));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(full_path: &str, item_type: &str, parent: Option<&str>) -> DocItem {
+        DocItem {
+            crate_name: "app".to_string(),
+            name: full_path.rsplit("::").next().unwrap().to_string(),
+            full_path_str: full_path.to_string(),
+            item_type: item_type.to_string(),
+            parent: parent.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_embedding_text_templates() {
+        let items = [
+            item("app::Config", "struct", None),
+            item("app::Config::port", "struct_field", None),
+            item("app::Config::host", "struct_field", None),
+            item("app::Config::load", "method", Some("app::Config")),
+        ];
+        let index = MemberIndex::build(&items);
+        let text = embedding_text(&items[0], index.members_of("app::Config"), "Server settings.");
+        assert_eq!(text, "struct app::Config\nSignature: struct Config\nFields: host, port\nMethods: load\nCrate: app\n\nServer settings.");
+
+        let text = embedding_text(&items[3], index.members_of("app::Config::load"), "Reads the file.");
+        assert_eq!(text, "method app::Config::load\nSignature: fn load\nIn: impl app::Config\nCrate: app\n\nReads the file.");
+
+        let module = item("app::net", "module", None);
+        assert_eq!(embedding_text(&module, &[], "Networking."), "module app::net\nCrate: app\n\nNetworking.");
+    }
+}
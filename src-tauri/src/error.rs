@@ -0,0 +1,161 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::embedder;
+
+/// Error returned by every Tauri command (and carried by failed jobs).
+///
+/// Serialized as `{ "code": "<snake_case code>", "details": { ... } }`. The codes are stable so the
+/// frontend can switch on them and offer a targeted fix; the TypeScript definition in
+/// `src/bindings/AppError.ts` is generated from this type by `cargo test`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "../../src/bindings/"))]
+#[serde(tag = "code", content = "details", rename_all = "snake_case")]
+pub enum AppError {
+    /// The configured rustdoc toolchain is not installed (fix: `rustup toolchain install <toolchain>`).
+    ToolchainMissing { toolchain: String },
+    /// `cargo rustdoc` ran but failed, usually because the project doesn't compile.
+    RustdocFailed { message: String },
+    /// The rustdoc JSON could not be parsed (often an unsupported `format_version`).
+    ParseFailed { message: String },
+    /// The embedding model is not loaded (fix: re-download the model and restart).
+    EmbedderUnavailable { model_repo: String },
+    /// The embedding model is still downloading or loading (fix: wait; see `get_embedder_status`).
+    EmbedderLoading { model_repo: String },
+    /// The project was embedded with another model than the loaded one, so its vectors can't be
+    /// compared with queries (fix: `reembed_project`).
+    EmbeddingModelMismatch { project_path: String, project_model: String, current_model: String },
+    /// The MCP server could not bind its address (fix: free the port or change `server.port`).
+    ServerPortInUse { host: String, port: u16 },
+    PathNotFound { path: String },
+    /// Hardened mode is on and the project is outside the trusted directories (fix: confirm, or add it to `trusted_dirs`).
+    UntrustedProject { path: String },
+    ProjectNotFound { project_path: String },
+    JobNotFound {
+        #[cfg_attr(test, ts(type = "number"))]
+        job_id: u64,
+    },
+    /// The job was cancelled by the user.
+    JobCancelled {
+        #[cfg_attr(test, ts(type = "number"))]
+        job_id: u64,
+    },
+    /// `[jobs] max_queued_jobs` jobs are already waiting (fix: wait, or cancel queued jobs).
+    QueueFull {
+        #[cfg_attr(test, ts(type = "number"))]
+        max_queued_jobs: usize,
+    },
+    InvalidArgument { message: String },
+    /// `server.auth_required` is on and the request had no or a wrong auth token.
+    Unauthorized { message: String },
+    Internal { message: String },
+}
+
+impl AppError {
+    /// The stable code the error serializes with.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::ToolchainMissing { .. } => "toolchain_missing",
+            AppError::RustdocFailed { .. } => "rustdoc_failed",
+            AppError::ParseFailed { .. } => "parse_failed",
+            AppError::EmbedderUnavailable { .. } => "embedder_unavailable",
+            AppError::EmbedderLoading { .. } => "embedder_loading",
+            AppError::EmbeddingModelMismatch { .. } => "embedding_model_mismatch",
+            AppError::ServerPortInUse { .. } => "server_port_in_use",
+            AppError::PathNotFound { .. } => "path_not_found",
+            AppError::UntrustedProject { .. } => "untrusted_project",
+            AppError::ProjectNotFound { .. } => "project_not_found",
+            AppError::JobNotFound { .. } => "job_not_found",
+            AppError::JobCancelled { .. } => "job_cancelled",
+            AppError::QueueFull { .. } => "queue_full",
+            AppError::InvalidArgument { .. } => "invalid_argument",
+            AppError::Unauthorized { .. } => "unauthorized",
+            AppError::Internal { .. } => "internal",
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        AppError::Internal { message: message.into() }
+    }
+
+    /// The error for a request that needs the embedder when none is loaded: `EmbedderLoading`
+    /// while it is on its way, `EmbedderUnavailable` otherwise.
+    pub fn embedder_not_ready(model_repo: &str) -> Self {
+        match embedder::embedder_status() {
+            status if status.is_loading() => AppError::EmbedderLoading { model_repo: model_repo.to_string() },
+            _ => AppError::EmbedderUnavailable { model_repo: model_repo.to_string() },
+        }
+    }
+
+    /// Classifies a `generate_rustdoc_json` failure. rustup reports a missing toolchain on stderr,
+    /// which ends up in the error message.
+    pub fn from_rustdoc_error(toolchain: &str, error: &anyhow::Error) -> Self {
+        let message = format!("{:#}", error);
+        if message.contains("toolchain") && message.contains("is not installed") {
+            AppError::ToolchainMissing { toolchain: toolchain.to_string() }
+        } else {
+            AppError::RustdocFailed { message }
+        }
+    }
+
+    /// Classifies an MCP server start failure.
+    pub fn from_server_error(host: &str, port: u16, error: &anyhow::Error) -> Self {
+        let in_use = error.chain().any(|cause| {
+            cause.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::AddrInUse)
+        }) || format!("{:#}", error).contains("Address already in use");
+        if in_use {
+            AppError::ServerPortInUse { host: host.to_string(), port }
+        } else {
+            AppError::Internal { message: format!("MCP server failed: {:#}", error) }
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::ToolchainMissing { toolchain } => write!(f, "The '{}' toolchain is not installed. Run `rustup toolchain install {}`.", toolchain, toolchain),
+            AppError::RustdocFailed { message } => write!(f, "Failed to generate rustdoc JSON: {}", message),
+            AppError::ParseFailed { message } => write!(f, "Failed to parse rustdoc JSON: {}", message),
+            AppError::EmbedderUnavailable { model_repo } => write!(f, "The embedding model {} is not loaded.", model_repo),
+            AppError::EmbedderLoading { model_repo } => write!(f, "The embedding model {} is still warming up (downloading or loading); try again shortly.", model_repo),
+            AppError::EmbeddingModelMismatch { project_path, project_model, current_model } => write!(f, "{} was embedded with {}, but the loaded model is {}; its vectors aren't comparable. Re-embed it (reembed_project).", project_path, project_model, current_model),
+            AppError::ServerPortInUse { host, port } => write!(f, "{}:{} is already in use; the MCP server could not start.", host, port),
+            AppError::PathNotFound { path } => write!(f, "Path does not exist: {}", path),
+            AppError::UntrustedProject { path } => write!(f, "{} is outside the trusted directories; building it runs its build scripts. Confirm to continue.", path),
+            AppError::ProjectNotFound { project_path } => write!(f, "Project not processed: {}", project_path),
+            AppError::JobNotFound { job_id } => write!(f, "Unknown job id {}", job_id),
+            AppError::JobCancelled { job_id } => write!(f, "Job {} was cancelled", job_id),
+            AppError::QueueFull { max_queued_jobs } => write!(f, "{} jobs are already queued; try again once some have finished.", max_queued_jobs),
+            AppError::InvalidArgument { message } => write!(f, "{}", message),
+            AppError::Unauthorized { message } => write!(f, "Unauthorized: {}", message),
+            AppError::Internal { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<anyhow::Error> for AppError {
+    fn from(error: anyhow::Error) -> Self {
+        AppError::Internal { message: format!("{:#}", error) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialized_shape_and_codes() {
+        let error = AppError::ToolchainMissing { toolchain: "nightly".to_string() };
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json, serde_json::json!({ "code": "toolchain_missing", "details": { "toolchain": "nightly" } }));
+        assert_eq!(json["code"], error.code());
+
+        let missing = anyhow::anyhow!("`cargo rustdoc` command failed. STDERR: error: toolchain 'nightly-x86_64-unknown-linux-gnu' is not installed");
+        assert_eq!(AppError::from_rustdoc_error("nightly", &missing), AppError::ToolchainMissing { toolchain: "nightly".to_string() });
+        let compile_error = anyhow::anyhow!("`cargo rustdoc` command failed. STDERR: error[E0425]");
+        assert_eq!(AppError::from_rustdoc_error("nightly", &compile_error).code(), "rustdoc_failed");
+    }
+}
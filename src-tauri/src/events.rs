@@ -0,0 +1,30 @@
+use serde::Serialize;
+
+use crate::error::AppError;
+
+/// Tauri event carrying a `StateEvent`, broadcast to every window.
+pub const STATE_CHANGED_EVENT: &str = "state-changed";
+
+// Events buffered for slow listeners before the oldest are dropped.
+pub const STATE_EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// A change to shared backend state. Windows listen for these instead of polling
+/// `get_processed_project_list`, so a detached search window and the main manager window stay in sync.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StateEvent {
+    /// A project was indexed for the first time.
+    ProjectAdded { project_path: String, item_count: usize, embedded_count: usize },
+    /// An already indexed project was re-processed and its data replaced.
+    IndexRefreshed { project_path: String, item_count: usize, embedded_count: usize },
+    ProjectRemoved { project_path: String },
+    /// Watch mode was turned on or off for a project (see `watch::enable_watch`).
+    WatchChanged { project_path: String, watching: bool },
+    EmbedderStateChanged { ready: bool, model_repo: String },
+    /// The GPU ran out of memory and the embedder moved to the CPU; processing goes on, slower.
+    EmbedderDeviceFallback { model_repo: String, from_device: String, reason: String },
+    /// The MCP server stopped or could not start, e.g. `server_port_in_use`.
+    McpServerFailed { error: AppError },
+    /// The MCP server was started, or stopped for any reason (see `mcp_server::restart_mcp_server`).
+    McpServerStateChanged { running: bool },
+}
@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::mcp_server::ProjectData;
+use crate::pipeline::item_signature;
+use crate::rustdoc_processor::DocItem;
+
+/// File format of `export_project_docs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "json" => Ok(ExportFormat::Json),
+            other => Err(format!("expected markdown or json, got '{}'", other)),
+        }
+    }
+}
+
+/// What `export_project` wrote.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    pub project_path: String,
+    pub output_dir: PathBuf,
+    pub format: ExportFormat,
+    /// One per module, plus the index.
+    pub files: usize,
+    pub items: usize,
+}
+
+// A module's items as written to its JSON file.
+#[derive(Serialize)]
+struct ModuleExport<'a> {
+    module_path: &'a str,
+    docs: Option<&'a str>,
+    items: Vec<ExportedItem<'a>>,
+}
+
+#[derive(Serialize)]
+struct ExportedItem<'a> {
+    signature: String,
+    #[serde(flatten)]
+    item: &'a DocItem,
+}
+
+/// Writes one file per module of the project (and of its dependencies, with
+/// `include_dependencies`) to `output_dir`, each holding the module's docs and its items with
+/// their signatures and docs, plus an index (`_index.<md|json>`) listing the modules. Existing
+/// files of the same name are overwritten.
+pub fn export_project(
+    project_path: &str,
+    data: &ProjectData,
+    format: ExportFormat,
+    output_dir: &Path,
+    include_dependencies: bool,
+) -> Result<ExportSummary, AppError> {
+    let crates = std::iter::once(&data.crate_docs).chain(data.dependency_docs.iter().filter(|_| include_dependencies));
+    let items: Vec<&DocItem> = crates.flat_map(|docs| docs.items.values()).collect();
+    let modules = group_by_module(&items);
+
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| AppError::internal(format!("Could not create {}: {}", output_dir.display(), e)))?;
+    let write = |file_name: &str, contents: String| {
+        let path = output_dir.join(file_name);
+        std::fs::write(&path, contents).map_err(|e| AppError::internal(format!("Could not write {}: {}", path.display(), e)))
+    };
+    let module_items: HashMap<&str, &DocItem> = items.iter()
+        .filter(|item| item.item_type == "module")
+        .map(|item| (item.full_path_str.as_str(), *item))
+        .collect();
+    let module_docs = |module_path: &str| module_items.get(module_path).and_then(|item| item.description.as_deref());
+
+    for (module_path, members) in &modules {
+        let contents = match format {
+            ExportFormat::Markdown => render_module(module_path, module_docs(module_path), members),
+            ExportFormat::Json => {
+                let export = ModuleExport {
+                    module_path,
+                    docs: module_docs(module_path),
+                    items: members.iter().map(|item| ExportedItem { signature: item_signature(item), item }).collect(),
+                };
+                serde_json::to_string_pretty(&export).map_err(|e| AppError::internal(format!("Failed to serialize {}: {}", module_path, e)))?
+            }
+        };
+        write(&module_file_name(module_path, format), contents)?;
+    }
+    let index = match format {
+        ExportFormat::Markdown => {
+            let mut text = format!("# Documentation of `{}`\n\n", project_path);
+            for (module_path, members) in &modules {
+                text.push_str(&format!("- [`{}`]({}) ({} items)\n", module_path, module_file_name(module_path, format), members.len()));
+            }
+            text
+        }
+        ExportFormat::Json => {
+            let index: BTreeMap<&str, String> = modules.keys().map(|m| (m.as_str(), module_file_name(m, format))).collect();
+            serde_json::to_string_pretty(&index).map_err(|e| AppError::internal(format!("Failed to serialize the index: {}", e)))?
+        }
+    };
+    write(&index_file_name(format), index)?;
+
+    Ok(ExportSummary {
+        project_path: project_path.to_string(),
+        output_dir: output_dir.to_path_buf(),
+        format,
+        files: modules.len() + 1,
+        items: modules.values().map(Vec::len).sum(),
+    })
+}
+
+// Items by the module they are declared in, sorted by path so methods follow their type; every
+// documented module has an entry, even one holding only submodules. Items whose enclosing module
+// wasn't documented (e.g. doc sources) go to their crate root.
+fn group_by_module<'a>(items: &[&'a DocItem]) -> BTreeMap<String, Vec<&'a DocItem>> {
+    let module_paths: HashSet<&str> = items.iter()
+        .filter(|item| item.item_type == "module")
+        .map(|item| item.full_path_str.as_str())
+        .collect();
+    let mut modules: BTreeMap<String, Vec<&DocItem>> = module_paths.iter().map(|path| (path.to_string(), Vec::new())).collect();
+    for item in items.iter().filter(|item| item.item_type != "module") {
+        let mut module = item.full_path_str.as_str();
+        while let Some((container, _)) = module.rsplit_once("::") {
+            module = container;
+            if module_paths.contains(module) {
+                break;
+            }
+        }
+        modules.entry(module.to_string()).or_default().push(item);
+    }
+    for members in modules.values_mut() {
+        members.sort_by(|a, b| a.full_path_str.cmp(&b.full_path_str));
+    }
+    modules
+}
+
+// `tokio::sync` -> `tokio.sync.md`; anything but identifier characters becomes `_`.
+fn module_file_name(module_path: &str, format: ExportFormat) -> String {
+    let name: String = module_path.replace("::", ".").chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '_' | '.' | '-') { c } else { '_' })
+        .collect();
+    format!("{}.{}", name, format.extension())
+}
+
+// Module file names start with their crate's name, which can't start with `_`, so this never
+// collides with one.
+fn index_file_name(format: ExportFormat) -> String {
+    format!("_index.{}", format.extension())
+}
+
+fn render_module(module_path: &str, docs: Option<&str>, items: &[&DocItem]) -> String {
+    let mut text = format!("# Module `{}`\n\n", module_path);
+    if let Some(docs) = docs.map(str::trim).filter(|d| !d.is_empty()) {
+        text.push_str(docs);
+        text.push_str("\n\n");
+    }
+    for item in items {
+        // Methods and other associated items nest under their type's heading.
+        let heading = if item.parent.is_some() { "###" } else { "##" };
+        let name = item.full_path_str.strip_prefix(module_path).map(|p| p.trim_start_matches("::")).unwrap_or(&item.full_path_str);
+        text.push_str(&format!("{} `{}`\n\n*{}*", heading, name, item.item_type));
+        if let Some(trait_name) = &item.trait_name {
+            text.push_str(&format!(" (impl `{}`)", trait_name));
+        }
+        text.push_str(&format!("\n\n```rust\n{}\n```\n\n", item_signature(item)));
+        if let Some(deprecation) = &item.deprecation {
+            text.push_str(&format!("**{}**\n\n", deprecation.describe()));
+        }
+        if let Some(cfg) = &item.cfg {
+            text.push_str(&format!("*{}.*\n\n", cfg.describe()));
+        }
+        match item.description.as_deref().map(str::trim) {
+            Some(docs) if !docs.is_empty() => text.push_str(docs),
+            _ => text.push_str("_No documentation._"),
+        }
+        text.push_str("\n\n");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(full_path: &str, item_type: &str) -> DocItem {
+        DocItem {
+            name: full_path.rsplit("::").next().unwrap().to_string(),
+            full_path_str: full_path.to_string(),
+            item_type: item_type.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_group_by_module() {
+        let items = [
+            item("app", "module"),
+            item("app::net", "module"),
+            item("app::empty", "module"),
+            item("app::net::Client", "struct"),
+            item("app::net::Client::get", "method"),
+            item("app::run", "function"),
+            item("app::guide::intro.md#Setup", "section"),
+        ];
+        let refs: Vec<&DocItem> = items.iter().collect();
+        let modules = group_by_module(&refs);
+        let paths = |module: &str| modules[module].iter().map(|i| i.full_path_str.as_str()).collect::<Vec<_>>();
+        assert_eq!(modules.keys().collect::<Vec<_>>(), ["app", "app::empty", "app::net"]);
+        assert!(modules["app::empty"].is_empty());
+        assert_eq!(paths("app::net"), ["app::net::Client", "app::net::Client::get"]);
+        assert_eq!(paths("app"), ["app::guide::intro.md#Setup", "app::run"]);
+    }
+
+    #[test]
+    fn test_module_file_name() {
+        assert_eq!(module_file_name("tokio::sync", ExportFormat::Markdown), "tokio.sync.md");
+        // A crate named `index` doesn't clash with the index.
+        assert_ne!(module_file_name("index", ExportFormat::Markdown), index_file_name(ExportFormat::Markdown));
+        assert_eq!(module_file_name("guide::intro md", ExportFormat::Json), "guide.intro_md.json");
+        assert_eq!("JSON".parse::<ExportFormat>(), Ok(ExportFormat::Json));
+        assert!("html".parse::<ExportFormat>().is_err());
+    }
+}
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Generic parameters and where-clauses of an item, from the rustdoc JSON `generics` object.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Generics {
+    pub params: Vec<GenericParam>,
+    /// Rendered predicates, e.g. `T: Clone + Send` or `'a: 'b`.
+    pub where_predicates: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenericParam {
+    /// `T`, `'a` or `N`.
+    pub name: String,
+    /// "lifetime", "type" or "const".
+    pub kind: String,
+    /// Rendered bounds (`Serialize`, `?Sized`, `'a`); for a const parameter, its type.
+    pub bounds: Vec<String>,
+    pub default: Option<String>,
+}
+
+impl Generics {
+    /// Reads a rustdoc `generics` object. `None` if the item declares no parameters or
+    /// predicates; synthetic parameters (from `impl Trait` arguments) don't count.
+    pub fn from_json(generics: &Value) -> Option<Self> {
+        let params: Vec<GenericParam> = generics.get("params").and_then(|p| p.as_array()).into_iter().flatten()
+            .filter_map(generic_param)
+            .collect();
+        let where_predicates: Vec<String> = generics.get("where_predicates").and_then(|p| p.as_array()).into_iter().flatten()
+            .filter_map(where_predicate)
+            .collect();
+        if params.is_empty() && where_predicates.is_empty() {
+            return None;
+        }
+        Some(Self { params, where_predicates })
+    }
+
+    /// Rust syntax: `<'a, T: Serialize = String> where T: Send`.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        if !self.params.is_empty() {
+            let params: Vec<String> = self.params.iter().map(GenericParam::render).collect();
+            text.push_str(&format!("<{}>", params.join(", ")));
+        }
+        if !self.where_predicates.is_empty() {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(&format!("where {}", self.where_predicates.join(", ")));
+        }
+        text
+    }
+}
+
+impl GenericParam {
+    fn render(&self) -> String {
+        let mut text = match (self.kind.as_str(), self.bounds.is_empty()) {
+            ("const", _) => format!("const {}: {}", self.name, self.bounds.join("")),
+            (_, true) => self.name.clone(),
+            (_, false) => format!("{}: {}", self.name, self.bounds.join(" + ")),
+        };
+        if let Some(default) = &self.default {
+            text.push_str(&format!(" = {}", default));
+        }
+        text
+    }
+}
+
+fn generic_param(param: &Value) -> Option<GenericParam> {
+    let name = param.get("name")?.as_str()?.to_string();
+    let (kind, payload) = variant(param.get("kind")?)?;
+    let (bounds, default) = match kind {
+        "lifetime" => (string_list(payload.get("outlives")), None),
+        "type" => {
+            // `synthetic` in older formats.
+            let synthetic = payload.get("is_synthetic").or_else(|| payload.get("synthetic")).and_then(|s| s.as_bool()).unwrap_or(false);
+            if synthetic {
+                return None;
+            }
+            (render_bounds(payload.get("bounds")), payload.get("default").filter(|d| !d.is_null()).map(render_type))
+        }
+        "const" => (
+            payload.get("type").map(render_type).into_iter().collect(),
+            payload.get("default").and_then(|d| d.as_str()).map(String::from),
+        ),
+        _ => return None,
+    };
+    Some(GenericParam { name, kind: kind.to_string(), bounds, default })
+}
+
+fn where_predicate(predicate: &Value) -> Option<String> {
+    let (kind, payload) = variant(predicate)?;
+    match kind {
+        "bound_predicate" => {
+            let bounds = render_bounds(payload.get("bounds"));
+            Some(format!("{}{}: {}", for_lifetimes(payload.get("generic_params")), render_type(payload.get("type")?), bounds.join(" + ")))
+        }
+        // `region_predicate` in older formats.
+        "lifetime_predicate" | "region_predicate" => {
+            let lifetime = payload.get("lifetime")?.as_str()?;
+            let outlives = payload.get("outlives").map(|o| match o.as_array() {
+                Some(_) => string_list(Some(o)),
+                None => render_bounds(Some(o)),
+            }).unwrap_or_default();
+            Some(format!("{}: {}", lifetime, outlives.join(" + ")))
+        }
+        "eq_predicate" => {
+            let rhs = payload.get("rhs")?;
+            let rhs = match variant(rhs) {
+                Some(("type", ty)) => render_type(ty),
+                Some(("constant", constant)) => constant.get("expr").and_then(|e| e.as_str()).unwrap_or("_").to_string(),
+                _ => render_type(rhs),
+            };
+            Some(format!("{} = {}", render_type(payload.get("lhs")?), rhs))
+        }
+        _ => None,
+    }
+}
+
+fn render_bounds(bounds: Option<&Value>) -> Vec<String> {
+    bounds.and_then(|b| b.as_array()).into_iter().flatten().filter_map(render_bound).collect()
+}
+
+fn render_bound(bound: &Value) -> Option<String> {
+    let (kind, payload) = variant(bound)?;
+    match kind {
+        "trait_bound" => {
+            let maybe = match payload.get("modifier").and_then(|m| m.as_str()) {
+                Some("maybe") => "?",
+                Some("maybe_const") => "~const ",
+                _ => "",
+            };
+            Some(format!("{}{}{}", for_lifetimes(payload.get("generic_params")), maybe, render_path(payload.get("trait")?)))
+        }
+        "outlives" => payload.as_str().map(String::from),
+        "use" => Some(format!("use<{}>", string_list(Some(payload)).join(", "))),
+        _ => None,
+    }
+}
+
+// Higher-ranked lifetimes: `for<'a> `.
+fn for_lifetimes(generic_params: Option<&Value>) -> String {
+    let names: Vec<&str> = generic_params.and_then(|p| p.as_array()).into_iter().flatten()
+        .filter_map(|p| p.get("name").and_then(|n| n.as_str()))
+        .collect();
+    if names.is_empty() { String::new() } else { format!("for<{}> ", names.join(", ")) }
+}
+
+/// Renders a rustdoc `Type` as Rust syntax. Unknown shapes render as `_`.
+pub fn render_type(ty: &Value) -> String {
+    if ty.as_str() == Some("infer") {
+        return "_".to_string();
+    }
+    let Some((kind, payload)) = variant(ty) else { return "_".to_string() };
+    match kind {
+        "resolved_path" => render_path(payload),
+        "generic" | "primitive" => payload.as_str().unwrap_or("_").to_string(),
+        "borrowed_ref" => {
+            let lifetime = payload.get("lifetime").and_then(|l| l.as_str()).map(|l| format!("{} ", l)).unwrap_or_default();
+            let mutable = if is_mutable(payload) { "mut " } else { "" };
+            format!("&{}{}{}", lifetime, mutable, payload.get("type").map(render_type).unwrap_or_default())
+        }
+        "raw_pointer" => {
+            let pointer = if is_mutable(payload) { "*mut" } else { "*const" };
+            format!("{} {}", pointer, payload.get("type").map(render_type).unwrap_or_default())
+        }
+        "slice" => format!("[{}]", render_type(payload)),
+        "array" => format!(
+            "[{}; {}]",
+            payload.get("type").map(render_type).unwrap_or_default(),
+            payload.get("len").and_then(|l| l.as_str()).unwrap_or("_")
+        ),
+        "tuple" => {
+            let types: Vec<String> = payload.as_array().into_iter().flatten().map(render_type).collect();
+            if types.len() == 1 { format!("({},)", types[0]) } else { format!("({})", types.join(", ")) }
+        }
+        "impl_trait" => format!("impl {}", render_bounds(Some(payload)).join(" + ")),
+        "dyn_trait" => {
+            let mut traits: Vec<String> = payload.get("traits").and_then(|t| t.as_array()).into_iter().flatten()
+                .filter_map(|t| t.get("trait").map(|path| format!("{}{}", for_lifetimes(t.get("generic_params")), render_path(path))))
+                .collect();
+            traits.extend(payload.get("lifetime").and_then(|l| l.as_str()).map(String::from));
+            format!("dyn {}", traits.join(" + "))
+        }
+        "qualified_path" => {
+            let name = payload.get("name").and_then(|n| n.as_str()).unwrap_or("_");
+            let self_type = payload.get("self_type").map(render_type).unwrap_or_default();
+            match payload.get("trait").filter(|t| !t.is_null()) {
+                Some(trait_path) => format!("<{} as {}>::{}", self_type, render_path(trait_path), name),
+                None => format!("{}::{}", self_type, name),
+            }
+        }
+        "function_pointer" => "fn(..)".to_string(),
+        "pat" => payload.get("type").map(render_type).unwrap_or_else(|| "_".to_string()),
+        _ => "_".to_string(),
+    }
+}
+
+// A rustdoc `Path`: `path` (or `name` in older formats) plus optional generic args.
+fn render_path(path: &Value) -> String {
+    let name = path.get("path").or_else(|| path.get("name")).and_then(|n| n.as_str()).unwrap_or("_");
+    let args = path.get("args").filter(|a| !a.is_null()).map(render_generic_args).unwrap_or_default();
+    format!("{}{}", name, args)
+}
+
+pub fn render_generic_args(args: &Value) -> String {
+    match variant(args) {
+        Some(("angle_bracketed", payload)) => {
+            let mut rendered: Vec<String> = payload.get("args").and_then(|a| a.as_array()).into_iter().flatten()
+                .map(|arg| match variant(arg) {
+                    Some(("lifetime", lifetime)) => lifetime.as_str().unwrap_or("'_").to_string(),
+                    Some(("type", ty)) => render_type(ty),
+                    Some(("const", constant)) => constant.get("expr").and_then(|e| e.as_str()).unwrap_or("_").to_string(),
+                    _ => "_".to_string(),
+                })
+                .collect();
+            // `constraints` in newer formats, `bindings` in older ones.
+            let constraints = payload.get("constraints").or_else(|| payload.get("bindings"));
+            for constraint in constraints.and_then(|c| c.as_array()).into_iter().flatten() {
+                let Some(name) = constraint.get("name").and_then(|n| n.as_str()) else { continue };
+                match constraint.get("binding").and_then(variant) {
+                    Some(("equality", term)) => {
+                        let value = match variant(term) {
+                            Some(("type", ty)) => render_type(ty),
+                            _ => render_type(term),
+                        };
+                        rendered.push(format!("{} = {}", name, value));
+                    }
+                    Some(("constraint", bounds)) => rendered.push(format!("{}: {}", name, render_bounds(Some(bounds)).join(" + "))),
+                    _ => {}
+                }
+            }
+            if rendered.is_empty() { String::new() } else { format!("<{}>", rendered.join(", ")) }
+        }
+        Some(("parenthesized", payload)) => {
+            let inputs: Vec<String> = payload.get("inputs").and_then(|i| i.as_array()).into_iter().flatten().map(render_type).collect();
+            let output = payload.get("output").filter(|o| !o.is_null()).map(|o| format!(" -> {}", render_type(o))).unwrap_or_default();
+            format!("({}){}", inputs.join(", "), output)
+        }
+        _ => String::new(),
+    }
+}
+
+// `is_mutable` in newer formats, `mutable` in older ones.
+fn is_mutable(payload: &Value) -> bool {
+    payload.get("is_mutable").or_else(|| payload.get("mutable")).and_then(|m| m.as_bool()).unwrap_or(false)
+}
+
+// rustdoc enums are externally tagged: `{ "variant": payload }`.
+fn variant(value: &Value) -> Option<(&str, &Value)> {
+    let object = value.as_object()?;
+    if object.len() != 1 {
+        return None;
+    }
+    object.iter().next().map(|(kind, payload)| (kind.as_str(), payload))
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value.and_then(|v| v.as_array()).into_iter().flatten().filter_map(|s| s.as_str().map(String::from)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_generics_and_where_clauses() {
+        let generics = json!({
+            "params": [
+                { "name": "'a", "kind": { "lifetime": { "outlives": [] } } },
+                { "name": "T", "kind": { "type": {
+                    "bounds": [{ "trait_bound": { "trait": { "path": "Serialize", "id": 3, "args": null }, "generic_params": [], "modifier": "none" } }],
+                    "default": null, "is_synthetic": false
+                } } },
+                { "name": "N", "kind": { "const": { "type": { "primitive": "usize" }, "default": null } } },
+                { "name": "impl Display", "kind": { "type": { "bounds": [], "default": null, "is_synthetic": true } } }
+            ],
+            "where_predicates": [
+                { "bound_predicate": {
+                    "type": { "generic": "T" },
+                    "bounds": [
+                        { "trait_bound": { "trait": { "path": "Sized", "id": 4, "args": null }, "generic_params": [], "modifier": "maybe" } },
+                        { "trait_bound": { "trait": { "path": "Iterator", "id": 5, "args": { "angle_bracketed": {
+                            "args": [],
+                            "constraints": [{ "name": "Item", "args": null, "binding": { "equality": { "type": {
+                                "borrowed_ref": { "lifetime": "'a", "is_mutable": false, "type": { "primitive": "str" } }
+                            } } } }]
+                        } } }, "generic_params": [], "modifier": "none" } }
+                    ],
+                    "generic_params": []
+                } }
+            ]
+        });
+        let generics = Generics::from_json(&generics).unwrap();
+        // The synthetic `impl Trait` parameter is left out.
+        assert_eq!(generics.params.len(), 3);
+        assert_eq!(generics.render(), "<'a, T: Serialize, const N: usize> where T: ?Sized + Iterator<Item = &'a str>");
+
+        assert_eq!(Generics::from_json(&json!({ "params": [], "where_predicates": [] })), None);
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Directories searched below the checkout for a Cargo.toml when the root has none.
+const MAX_MANIFEST_DEPTH: usize = 3;
+
+/// Fetches `rev` (a branch, tag or commit; the default branch if `None`) of the repository at
+/// `url` into `repos_dir` with a depth-1 fetch and checks it out, returning the checkout. An
+/// earlier checkout of the same URL and revision is reused and only fetches what changed.
+/// Blocking.
+pub fn clone_repository(repos_dir: &Path, url: &str, rev: Option<&str>) -> Result<PathBuf> {
+    validate(url, rev)?;
+    let checkout = checkout_dir(repos_dir, url, rev);
+    if !checkout.join(".git").exists() {
+        fs::create_dir_all(&checkout).with_context(|| format!("Failed to create {}", checkout.display()))?;
+        git(&checkout, &["init", "--quiet"])?;
+        git(&checkout, &["remote", "add", "origin", url])?;
+    }
+    log::info!("Fetching {} ({}) into {}", url, rev.unwrap_or("default branch"), checkout.display());
+    // `validate` made sure neither the URL nor the revision can be read as an option.
+    git(&checkout, &["fetch", "--depth", "1", "--quiet", "origin", rev.unwrap_or("HEAD")])?;
+    git(&checkout, &["checkout", "--quiet", "--force", "--detach", "FETCH_HEAD"])?;
+    // Submodules are left out: most crates don't need them to build their docs.
+    Ok(checkout)
+}
+
+// Runs git in `dir` without prompting for credentials, which would hang a background job.
+fn git(dir: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .arg("-C").arg(dir)
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("Failed to run git; is it installed and on PATH?")?;
+    if !output.status.success() {
+        anyhow::bail!("git {} failed: {}", args.first().unwrap_or(&""), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Where `rev` of `url` is checked out: `<repos_dir>/<repo name>[-<rev>]-<hash of URL and rev>`,
+/// so two forks of a repository, or two revisions of one, don't share a checkout. The checkout
+/// is the project's path, so each revision is also indexed as a project of its own. The hash
+/// is SHA-256, so the directory stays the same across Rust releases and restarts.
+pub fn checkout_dir(repos_dir: &Path, url: &str, rev: Option<&str>) -> PathBuf {
+    let name = url.trim_end_matches('/').trim_end_matches(".git")
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or("repo");
+    let mut hasher = Sha256::new();
+    hasher.update(url.as_bytes());
+    if let Some(rev) = rev {
+        // Separator, so ("a", "bc") and ("ab", "c") differ.
+        hasher.update([0u8]);
+        hasher.update(rev.as_bytes());
+    }
+    let digest = hasher.finalize();
+    let label = match rev {
+        Some(rev) => format!("{}-{}", name, rev),
+        None => name.to_string(),
+    };
+    let label: String = label.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect();
+    let hash: String = digest[..4].iter().map(|byte| format!("{:02x}", byte)).collect();
+    repos_dir.join(format!("{}-{}", label, hash))
+}
+
+/// Accepts https, http, ssh and git URLs and scp-style `user@host:path`. Anything git could read
+/// as an option, and transports that run commands (`ext::`), are refused.
+pub fn validate(url: &str, rev: Option<&str>) -> Result<()> {
+    let url_ok = !url.starts_with('-')
+        && !url.chars().any(char::is_whitespace)
+        && (["https://", "http://", "ssh://", "git://"].iter().any(|scheme| url.starts_with(scheme))
+            || url.split_once(':').is_some_and(|(host, path)| host.contains('@') && !host.contains('/') && !path.is_empty()));
+    if !url_ok {
+        anyhow::bail!("Unsupported repository URL '{}': expected https://, ssh://, git:// or user@host:path", url);
+    }
+    if let Some(rev) = rev {
+        let rev_ok = !rev.is_empty() && !rev.starts_with('-')
+            && rev.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '/'));
+        if !rev_ok {
+            anyhow::bail!("Invalid revision '{}': expected a branch, tag or commit", rev);
+        }
+    }
+    Ok(())
+}
+
+/// The directory to document: the checkout itself if it has a Cargo.toml, otherwise the
+/// shallowest Cargo.toml below it, preferring workspace roots. Blocking.
+pub fn locate_project_root(checkout: &Path) -> Result<PathBuf> {
+    if checkout.join("Cargo.toml").is_file() {
+        return Ok(checkout.to_path_buf());
+    }
+    let manifests = walkdir::WalkDir::new(checkout)
+        .min_depth(1)
+        .max_depth(MAX_MANIFEST_DEPTH + 1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| !matches!(entry.file_name().to_str(), Some(".git" | "target")))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && entry.file_name() == "Cargo.toml");
+    let mut best: Option<(bool, usize, PathBuf)> = None;
+    for manifest in manifests {
+        let is_workspace = fs::read_to_string(manifest.path()).is_ok_and(|content| content.contains("[workspace]"));
+        let candidate = (is_workspace, manifest.depth(), manifest.path().parent().unwrap_or(checkout).to_path_buf());
+        let better = match &best {
+            None => true,
+            Some((best_workspace, best_depth, _)) => (is_workspace, std::cmp::Reverse(candidate.1)) > (*best_workspace, std::cmp::Reverse(*best_depth)),
+        };
+        if better {
+            best = Some(candidate);
+        }
+    }
+    best.map(|(_, _, dir)| dir)
+        .with_context(|| format!("No Cargo.toml found in {} or {} levels below it", checkout.display(), MAX_MANIFEST_DEPTH))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("https://github.com/serde-rs/json.git", Some("v1.0.120")).is_ok());
+        assert!(validate("git@github.com:serde-rs/json.git", None).is_ok());
+        assert!(validate("ssh://git@host/repo", Some("feature/x")).is_ok());
+        assert!(validate("--upload-pack=touch /tmp/x", None).is_err());
+        assert!(validate("ext::sh -c touch% /tmp/x", None).is_err());
+        assert!(validate("/home/me/repo", None).is_err());
+        assert!(validate("https://github.com/a/b", Some("--output=/tmp/x")).is_err());
+    }
+
+    #[test]
+    fn test_checkout_dir() {
+        let repos_dir = Path::new("/cache/git");
+        let dir = checkout_dir(repos_dir, "https://github.com/serde-rs/json.git", None);
+        assert!(dir.file_name().unwrap().to_str().unwrap().starts_with("json-"));
+        assert_ne!(dir, checkout_dir(repos_dir, "https://github.com/fork/json.git", None));
+        // Each revision gets its own checkout, and so its own project.
+        let tagged = checkout_dir(repos_dir, "https://github.com/serde-rs/json.git", Some("v1.0.120"));
+        assert!(tagged.file_name().unwrap().to_str().unwrap().starts_with("json-v1.0.120-"));
+        assert_ne!(tagged, dir);
+        assert_ne!(tagged, checkout_dir(repos_dir, "https://github.com/serde-rs/json.git", Some("v1.0.119")));
+        let branch = checkout_dir(repos_dir, "https://github.com/serde-rs/json.git", Some("feature/x"));
+        assert_eq!(branch.parent(), Some(repos_dir));
+        // Pinned: a checkout must be found again after the app is rebuilt.
+        assert_eq!(dir, repos_dir.join("json-c769f575"));
+    }
+
+    #[test]
+    fn test_locate_project_root() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert!(locate_project_root(dir.path()).is_err());
+        fs::create_dir_all(dir.path().join("rust/crates/core"))?;
+        fs::write(dir.path().join("rust/crates/core/Cargo.toml"), "[package]\nname = \"core\"\n")?;
+        assert_eq!(locate_project_root(dir.path())?, dir.path().join("rust/crates/core"));
+        // A workspace root wins over a shallower package.
+        fs::create_dir_all(dir.path().join("bindings"))?;
+        fs::write(dir.path().join("bindings/Cargo.toml"), "[package]\nname = \"bindings\"\n")?;
+        fs::write(dir.path().join("rust/Cargo.toml"), "[workspace]\nmembers = [\"crates/*\"]\n")?;
+        assert_eq!(locate_project_root(dir.path())?, dir.path().join("rust"));
+        fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"root\"\n")?;
+        assert_eq!(locate_project_root(dir.path())?, dir.path());
+        Ok(())
+    }
+}
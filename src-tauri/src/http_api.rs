@@ -0,0 +1,334 @@
+use anyhow::{Context, Result};
+use axum::extract::{Query, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::auth;
+use crate::embedder;
+use crate::error::AppError;
+use crate::mcp_server::{AppState, SearchFilter};
+use crate::pipeline::{self, QueryDocResultItem, QueryOptions, ResultDetail};
+use crate::render::{self, RenderMode};
+use crate::rustdoc_processor::DocItem;
+
+// OpenAI's limit on inputs per embeddings request.
+const MAX_EMBEDDING_INPUTS: usize = 2048;
+
+/// An `AppError` as an HTTP response: an OpenAI-style `{ "error": { message, type, code } }` body
+/// with a status matching the error.
+pub struct ApiError {
+    status: StatusCode,
+    error: AppError,
+}
+
+impl From<AppError> for ApiError {
+    fn from(error: AppError) -> Self {
+        let status = match error {
+            AppError::InvalidArgument { .. } => StatusCode::BAD_REQUEST,
+            AppError::ProjectNotFound { .. } | AppError::PathNotFound { .. } | AppError::JobNotFound { .. } => StatusCode::NOT_FOUND,
+            AppError::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            AppError::UntrustedProject { .. } => StatusCode::FORBIDDEN,
+            AppError::EmbeddingModelMismatch { .. } => StatusCode::CONFLICT,
+            AppError::EmbedderLoading { .. } | AppError::EmbedderUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::QueueFull { .. } => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self { status, error }
+    }
+}
+
+impl ApiError {
+    // A 404 for lookups `AppError` has no variant for, e.g. a missing item.
+    fn not_found(message: String) -> Self {
+        Self { status: StatusCode::NOT_FOUND, error: AppError::InvalidArgument { message } }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({
+            "error": { "message": self.error.to_string(), "type": self.error.code(), "code": self.error.code() }
+        });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum EmbeddingInput {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingsRequest {
+    input: EmbeddingInput,
+    /// Accepted for compatibility; the loaded model always answers, and is named in the response.
+    model: Option<String>,
+    /// "float" (default) or "base64" (little-endian f32s).
+    encoding_format: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingData {
+    object: &'static str,
+    index: usize,
+    embedding: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingsUsage {
+    prompt_tokens: usize,
+    total_tokens: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingsResponse {
+    object: &'static str,
+    data: Vec<EmbeddingData>,
+    model: String,
+    usage: EmbeddingsUsage,
+}
+
+// The texts of a request, checked against OpenAI's rules.
+fn embedding_texts(input: EmbeddingInput) -> Result<Vec<String>, AppError> {
+    let texts = match input {
+        EmbeddingInput::One(text) => vec![text],
+        EmbeddingInput::Many(texts) => texts,
+    };
+    if texts.is_empty() || texts.len() > MAX_EMBEDDING_INPUTS {
+        return Err(AppError::InvalidArgument { message: format!("'input' must hold 1 to {} texts", MAX_EMBEDDING_INPUTS) });
+    }
+    if texts.iter().any(|text| text.is_empty()) {
+        return Err(AppError::InvalidArgument { message: "'input' must not contain empty strings".to_string() });
+    }
+    Ok(texts)
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Standard padded base64 of the vector's little-endian bytes, as OpenAI's `encoding_format: base64`.
+fn base64_f32(vector: &[f32]) -> String {
+    let bytes: Vec<u8> = vector.iter().flat_map(|x| x.to_le_bytes()).collect();
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let n = (group[0] as u32) << 16 | (*group.get(1).unwrap_or(&0) as u32) << 8 | *group.get(2).unwrap_or(&0) as u32;
+        for (i, shift) in [18, 12, 6, 0].into_iter().enumerate() {
+            text.push(if i <= group.len() { BASE64_ALPHABET[(n >> shift & 0x3f) as usize] as char } else { '=' });
+        }
+    }
+    text
+}
+
+// `POST /v1/embeddings`: embeds with the model the app already has loaded, on the embedder worker.
+async fn embeddings(State(app_state): State<Arc<AppState>>, Json(request): Json<EmbeddingsRequest>) -> Result<Json<EmbeddingsResponse>, ApiError> {
+    let base64 = match request.encoding_format.as_deref() {
+        None | Some("float") => false,
+        Some("base64") => true,
+        Some(other) => return Err(AppError::InvalidArgument { message: format!("Unsupported encoding_format '{}'; use float or base64", other) }.into()),
+    };
+    let texts = embedding_texts(request.input)?;
+    log::debug!("/v1/embeddings: {} texts (requested model {:?})", texts.len(), request.model);
+    let result = embedder::with_global_embedder(move |embedder| {
+        embedder.map(|e| -> Result<_> { Ok((e.embed_batch(&texts)?, e.count_tokens(&texts)?, e.model_repo().to_string())) })
+    }).await.map_err(|e| AppError::internal(format!("Embedder worker failed: {:#}", e)))?;
+    let (vectors, tokens, model) = result
+        .ok_or_else(|| AppError::embedder_not_ready(&app_state.config.current().embedder.model_repo))?
+        .map_err(|e| AppError::internal(format!("Failed to embed: {:#}", e)))?;
+
+    let data = vectors.into_iter().enumerate().map(|(index, vector)| EmbeddingData {
+        object: "embedding",
+        index,
+        embedding: if base64 { serde_json::Value::String(base64_f32(&vector)) } else { serde_json::json!(vector) },
+    }).collect();
+    Ok(Json(EmbeddingsResponse {
+        object: "list",
+        data,
+        model,
+        usage: EmbeddingsUsage { prompt_tokens: tokens, total_tokens: tokens },
+    }))
+}
+
+/// A processed project, as listed by `GET /api/projects`.
+#[derive(Debug, Serialize)]
+struct ProjectSummary {
+    /// The key other endpoints take as `project_path`; older versions are `<path>@<version>`.
+    project_path: String,
+    crate_name: String,
+    version: Option<String>,
+    item_count: usize,
+    embedded_count: usize,
+}
+
+// `GET /api/projects`: the processed projects, sorted by key.
+async fn list_projects(State(app_state): State<Arc<AppState>>) -> Json<Vec<ProjectSummary>> {
+    let mut projects: Vec<ProjectSummary> = app_state.projects().await.iter().map(|(key, data)| ProjectSummary {
+        project_path: key.clone(),
+        crate_name: data.crate_docs.crate_name.clone(),
+        version: data.version.clone(),
+        item_count: data.item_count(),
+        embedded_count: data.embedded_item_count(),
+    }).collect();
+    projects.sort_by(|a, b| a.project_path.cmp(&b.project_path));
+    Json(projects)
+}
+
+/// Body of `POST /api/query`; the same options as the `query_documentation` MCP tool.
+#[derive(Debug, Deserialize)]
+struct QueryRequest {
+    query: String,
+    project_path: Option<String>,
+    version: Option<String>,
+    num_results: Option<usize>,
+    item_types: Option<Vec<String>>,
+    crate_name: Option<String>,
+    module_prefix: Option<String>,
+    include_deprecated: Option<bool>,
+    public_only: Option<bool>,
+    target: Option<String>,
+    rerank: Option<bool>,
+    prefer_examples: Option<bool>,
+    additional_queries: Option<Vec<String>>,
+    expand_query: Option<bool>,
+    snippet_length: Option<usize>,
+    include_full_docs: Option<bool>,
+    min_score: Option<f32>,
+}
+
+// `POST /api/query`: hybrid search, as in the UI.
+async fn query(State(app_state): State<Arc<AppState>>, Json(request): Json<QueryRequest>) -> Result<Json<Vec<QueryDocResultItem>>, ApiError> {
+    if request.query.trim().is_empty() {
+        return Err(AppError::InvalidArgument { message: "'query' must not be empty".to_string() }.into());
+    }
+    let config = app_state.config.current();
+    let detail = ResultDetail::new(request.snippet_length, request.include_full_docs, &config.search);
+    let opts = QueryOptions {
+        query: request.query,
+        project_path: request.project_path,
+        version: request.version,
+        num_results: request.num_results.unwrap_or(config.search.default_num_results),
+        filter: SearchFilter {
+            item_types: request.item_types,
+            crate_name: request.crate_name,
+            module_prefix: request.module_prefix,
+            include_deprecated: request.include_deprecated.unwrap_or(false),
+            public_only: request.public_only.unwrap_or(true),
+            target: request.target,
+        },
+        rerank: request.rerank.unwrap_or(config.reranker.enabled),
+        prefer_examples: request.prefer_examples.unwrap_or(false),
+        additional_queries: request.additional_queries.unwrap_or_default(),
+        expand_query: request.expand_query.unwrap_or(false),
+        min_score: request.min_score,
+    };
+    let results = pipeline::query_docs(&app_state, &opts).await?
+        .into_iter()
+        .map(|hit| QueryDocResultItem::from_hit(hit, detail).with_highlights(&opts.query))
+        .collect();
+    Ok(Json(results))
+}
+
+#[derive(Debug, Deserialize)]
+struct ItemQuery {
+    project_path: String,
+    item_path: String,
+    #[serde(default)]
+    render: RenderMode,
+}
+
+// `GET /api/item?project_path=..&item_path=..[&render=plain|html]`: an item's full docs, like
+// `get_raw_documentation`.
+async fn get_item(State(app_state): State<Arc<AppState>>, Query(params): Query<ItemQuery>) -> Result<Json<DocItem>, ApiError> {
+    let projects = app_state.projects().await;
+    let project_data = projects.get(&params.project_path)
+        .ok_or_else(|| AppError::ProjectNotFound { project_path: params.project_path.clone() })?;
+    let item = project_data.get_item(&params.item_path)
+        .ok_or_else(|| ApiError::not_found(format!("Item '{}' not found in project '{}'", params.item_path, params.project_path)))?;
+    Ok(Json(render::rendered_item(item, params.render)))
+}
+
+// Enforces `server.auth_required` with an `Authorization: Bearer <token>` header.
+async fn require_auth(State(app_state): State<Arc<AppState>>, request: Request, next: Next) -> Result<Response, ApiError> {
+    auth::check_headers(&app_state.config.current().server, request.headers())?;
+    Ok(next.run(request).await)
+}
+
+/// The routes enabled in the `http_api` settings.
+pub fn router(app_state: Arc<AppState>) -> Router {
+    let settings = app_state.config.current().http_api.clone();
+    let mut router = Router::new();
+    if settings.embeddings_enabled {
+        router = router.route("/v1/embeddings", post(embeddings));
+    }
+    if settings.rest_enabled {
+        router = router
+            .route("/api/projects", get(list_projects))
+            .route("/api/query", post(query))
+            .route("/api/item", get(get_item));
+    }
+    router
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), require_auth))
+        .with_state(app_state)
+}
+
+/// Serves the enabled HTTP endpoints until the server fails.
+pub async fn start_http_api(app_state: Arc<AppState>) -> Result<()> {
+    let settings = app_state.config.current().http_api.clone();
+    let listener = tokio::net::TcpListener::bind((settings.host.as_str(), settings.port)).await
+        .with_context(|| format!("Failed to bind the HTTP API to {}:{}", settings.host, settings.port))?;
+    log::info!("HTTP API listening on {}:{}", settings.host, settings.port);
+    axum::serve(listener, router(app_state)).await.context("HTTP API server failed")
+}
+
+/// Starts the HTTP API in the background if any of its endpoints is enabled. Settings changes
+/// take effect after a restart, like the MCP server's.
+pub fn init_http_api(app_state: Arc<AppState>) {
+    if !app_state.config.current().http_api.any_enabled() {
+        log::info!("HTTP API disabled in config. Not starting.");
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = start_http_api(app_state).await {
+            log::error!("HTTP API failed: {:?}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_request_parsing() {
+        let request: EmbeddingsRequest = serde_json::from_str(r#"{"input": "fn main() {}", "model": "text-embedding-3-small"}"#).unwrap();
+        assert_eq!(embedding_texts(request.input).unwrap(), vec!["fn main() {}"]);
+        let request: EmbeddingsRequest = serde_json::from_str(r#"{"input": ["a", "b"], "encoding_format": "base64"}"#).unwrap();
+        assert_eq!(embedding_texts(request.input).unwrap().len(), 2);
+        assert!(embedding_texts(EmbeddingInput::Many(Vec::new())).is_err());
+        assert!(embedding_texts(EmbeddingInput::Many(vec!["a".to_string(), String::new()])).is_err());
+    }
+
+    #[test]
+    fn test_error_status() {
+        let status = |error: AppError| ApiError::from(error).status;
+        assert_eq!(status(AppError::InvalidArgument { message: String::new() }), StatusCode::BAD_REQUEST);
+        assert_eq!(status(AppError::Unauthorized { message: String::new() }), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(AppError::ProjectNotFound { project_path: "/work/app".to_string() }), StatusCode::NOT_FOUND);
+        assert_eq!(status(AppError::embedder_not_ready("BAAI/bge-code-v1")), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(AppError::internal("boom")), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(ApiError::not_found("gone".to_string()).status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_base64_f32() {
+        // 1.0f32 is 00 00 80 3f little-endian.
+        assert_eq!(base64_f32(&[1.0]), "AACAPw==");
+        assert_eq!(base64_f32(&[1.0, -2.0, 0.5]), "AACAPwAAAMAAAAA/");
+        assert_eq!(base64_f32(&[]), "");
+    }
+}
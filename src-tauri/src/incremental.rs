@@ -0,0 +1,172 @@
+use anyhow::{Context, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::chunking;
+use crate::config::RustdocSettings;
+use crate::mcp_server::ProjectData;
+use crate::rustdoc_processor::{self, DocTargets};
+
+/// Content hash of everything `cargo rustdoc` reads for a project: its `.rs` files, manifests and
+/// lockfile (skipping `target/` and hidden directories), plus the rustdoc settings, target
+/// selection and parser version that change the output. Equal fingerprints mean re-running rustdoc would produce the
+/// same docs. Blocking.
+///
+/// Only compared against fingerprints from the same build; `DefaultHasher` may change between
+/// Rust releases, which at worst costs one full re-process.
+pub fn source_fingerprint(project_dir: &Path, settings: &RustdocSettings, targets: DocTargets) -> Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    rustdoc_processor::PARSER_VERSION.hash(&mut hasher);
+    settings.toolchain.hash(&mut hasher);
+    settings.document_private_items.hash(&mut hasher);
+    settings.skip_private_items.hash(&mut hasher);
+    targets.hash(&mut hasher);
+    let entries = WalkDir::new(project_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !is_skipped_dir(e));
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to walk {}", project_dir.display()))?;
+        if !entry.file_type().is_file() || !is_source_file(entry.path()) {
+            continue;
+        }
+        let content = fs::read(entry.path()).with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        entry.path().strip_prefix(project_dir).unwrap_or(entry.path()).hash(&mut hasher);
+        content.hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+fn is_skipped_dir(entry: &walkdir::DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+    entry.file_type().is_dir() && (name == "target" || name.starts_with('.'))
+}
+
+fn is_source_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "rs")
+        || path.file_name().is_some_and(|name| name == "Cargo.toml" || name == "Cargo.lock")
+}
+
+/// Whether `previous` can stand as the result of re-processing: same sources and settings
+/// (`fingerprint`), dependency docs as requested, and embeddings from the loaded model
+/// (`model_id`, `None` when no embedder is loaded).
+pub fn is_up_to_date(previous: &ProjectData, fingerprint: u64, model_id: Option<&str>, include_dependencies: bool) -> bool {
+    previous.source_fingerprint == Some(fingerprint)
+        && previous.dependency_docs.is_empty() != include_dependencies
+        && (model_id.is_none() || previous.embedding_model.as_deref() == model_id)
+}
+
+/// Hash of the exact strings given to the embedder for one item (its chunks and examples, with the
+/// passage prefix) and of the model embedding them. Anything that changes the embedded text, such
+/// as a new signature or member list in the `embedding_text` header, changes the hash. Like
+/// `source_fingerprint`, only compared within one build.
+pub fn embedded_text_hash<'t>(model_id: &str, texts: impl IntoIterator<Item = &'t String>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    model_id.hash(&mut hasher);
+    for text in texts {
+        text.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// The chunk embeddings `previous` holds for the item at `full_path`, if they are still valid:
+/// they were embedded from texts with the same `embedded_text_hash` (`text_hash`), all
+/// `chunk_count` of them. `None` means the item is new or changed and must be embedded again.
+pub fn reusable_embeddings<'p>(previous: &'p ProjectData, full_path: &str, text_hash: u64, chunk_count: usize) -> Option<Vec<&'p Vec<f32>>> {
+    if previous.embedded_text_hashes.get(full_path) != Some(&text_hash) {
+        return None;
+    }
+    let embeddings: Vec<&Vec<f32>> = chunking::item_embeddings(&previous.embeddings, full_path).collect();
+    (embeddings.len() == chunk_count).then_some(embeddings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedding_text::embedding_text;
+    use crate::generics::{GenericParam, Generics};
+    use crate::rustdoc_processor::{CrateDocs, DocItem};
+    use std::collections::HashMap;
+
+    const MODEL: &str = "BAAI/bge-small-en-v1.5@main";
+
+    fn item(description: &str) -> DocItem {
+        DocItem {
+            id: "0:1".to_string(),
+            crate_name: "app".to_string(),
+            name: "run".to_string(),
+            path: vec!["app".to_string(), "run".to_string()],
+            description: Some(description.to_string()),
+            item_type: "function".to_string(),
+            full_path_str: "app::run".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_source_fingerprint_tracks_sources() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("src"))?;
+        fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\n")?;
+        fs::write(dir.path().join("src/lib.rs"), "/// Runs it.\npub fn run() {}\n")?;
+        let settings = RustdocSettings::default();
+        let fingerprint = source_fingerprint(dir.path(), &settings, DocTargets::Auto)?;
+
+        // Build output and non-source files don't count.
+        fs::create_dir_all(dir.path().join("target/doc"))?;
+        fs::write(dir.path().join("target/doc/app.json"), "{}")?;
+        fs::write(dir.path().join("notes.txt"), "todo")?;
+        assert_eq!(source_fingerprint(dir.path(), &settings, DocTargets::Auto)?, fingerprint);
+        // Documenting other targets changes the output.
+        assert_ne!(source_fingerprint(dir.path(), &settings, DocTargets::All)?, fingerprint);
+
+        fs::write(dir.path().join("src/lib.rs"), "/// Runs it twice.\npub fn run() {}\n")?;
+        assert_ne!(source_fingerprint(dir.path(), &settings, DocTargets::Auto)?, fingerprint);
+        Ok(())
+    }
+
+    // The hash of the item's texts as `embed_items` builds them, for docs split into two chunks.
+    fn text_hash(model_id: &str, item: &DocItem) -> u64 {
+        let description = item.description.as_deref().unwrap();
+        let texts: Vec<String> = [description, "More."].iter()
+            .map(|chunk| format!("passage: {}", embedding_text(item, &[], chunk)))
+            .collect();
+        embedded_text_hash(model_id, &texts)
+    }
+
+    fn previous_project() -> ProjectData {
+        let docs = CrateDocs { crate_name: "app".to_string(), items: HashMap::from([("app::run".to_string(), item("Runs it."))]) };
+        ProjectData::new(docs, Vec::new(), HashMap::from([
+            ("app::run".to_string(), vec![1.0, 0.0]),
+            (chunking::chunk_key("app::run", 1), vec![0.0, 1.0]),
+        ])).with_embedded_text_hashes(HashMap::from([("app::run".to_string(), text_hash(MODEL, &item("Runs it.")))]))
+    }
+
+    #[test]
+    fn test_reusable_embeddings_only_for_unchanged_items() {
+        let previous = previous_project();
+        let hash = text_hash(MODEL, &item("Runs it."));
+        let reused = reusable_embeddings(&previous, "app::run", hash, 2).unwrap();
+        assert_eq!(reused, vec![&vec![1.0, 0.0], &vec![0.0, 1.0]]);
+        // Edited docs, another model, a different chunking or an unknown item are embedded again.
+        assert!(reusable_embeddings(&previous, "app::run", text_hash(MODEL, &item("Runs it, fast.")), 2).is_none());
+        assert!(reusable_embeddings(&previous, "app::run", text_hash("BAAI/bge-code-v1@main", &item("Runs it.")), 2).is_none());
+        assert!(reusable_embeddings(&previous, "app::run", hash, 1).is_none());
+        assert!(reusable_embeddings(&previous, "app::stop", hash, 2).is_none());
+    }
+
+    // Same docs, but the signature in the embedded header changed.
+    #[test]
+    fn test_reusable_embeddings_track_signature() {
+        let previous = previous_project();
+        let mut generic_run = item("Runs it.");
+        generic_run.generics = Some(Generics {
+            params: vec![GenericParam { name: "T".to_string(), kind: "type".to_string(), bounds: vec!["Into<String>".to_string()], default: None }],
+            where_predicates: Vec::new(),
+        });
+        assert!(reusable_embeddings(&previous, "app::run", text_hash(MODEL, &generic_run), 2).is_none());
+    }
+}
@@ -0,0 +1,595 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, oneshot};
+
+use crate::config::ConfigHandle;
+use crate::error::AppError;
+
+pub type JobId = u64;
+pub type JobResult = Result<String, AppError>;
+type JobFuture = Pin<Box<dyn Future<Output = JobResult> + Send>>;
+
+// Finished jobs kept around for status queries before the oldest are pruned.
+const MAX_FINISHED_JOBS: usize = 100;
+// Progress events buffered for slow listeners before the oldest are dropped.
+const PROGRESS_CHANNEL_CAPACITY: usize = 256;
+
+/// Tauri event carrying a `JobProgressEvent`.
+pub const JOB_PROGRESS_EVENT: &str = "process-progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPriority {
+    Low,
+    Normal,
+    /// Interactive requests from the UI.
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed { message: String },
+    Failed { error: AppError },
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Completed { .. } | JobStatus::Failed { .. } | JobStatus::Cancelled)
+    }
+
+    fn from_result(result: &JobResult) -> Self {
+        match result {
+            Ok(message) => JobStatus::Completed { message: message.clone() },
+            Err(AppError::JobCancelled { .. }) => JobStatus::Cancelled,
+            Err(error) => JobStatus::Failed { error: error.clone() },
+        }
+    }
+}
+
+/// Set when a job is cancelled. Long-running work polls it: between embedding batches, and while
+/// waiting on `cargo rustdoc` (which is killed).
+#[derive(Debug, Clone, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    pub fn cancel(&self) {
+        self.0.store(true, AtomicOrdering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(AtomicOrdering::SeqCst)
+    }
+}
+
+/// Where a processing job is. Each phase maps to a fixed share of the progress bar; embedding
+/// (usually the slowest part) takes most of it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum JobPhase {
+    Rustdoc,
+    Parse,
+    Dependencies,
+    /// One of the job's rustdoc JSON files (a target, dependency or std crate) was parsed.
+    CrateParsed { crate_name: String, done: usize, total: usize },
+    Embed { batch: usize, total_batches: usize },
+    Index,
+    /// A line of output from a tool install (e.g. rustup downloading a toolchain).
+    Install { message: String },
+    Finished { status: JobStatus },
+}
+
+impl JobPhase {
+    pub fn percent(&self) -> f32 {
+        match self {
+            JobPhase::Rustdoc => 0.0,
+            JobPhase::Parse => 30.0,
+            JobPhase::Dependencies => 35.0,
+            JobPhase::CrateParsed { done, total, .. } => 30.0 + 10.0 * (*done as f32 / (*total).max(1) as f32).min(1.0),
+            JobPhase::Embed { batch, total_batches } => {
+                40.0 + 55.0 * (*batch as f32 / (*total_batches).max(1) as f32).min(1.0)
+            }
+            JobPhase::Index => 95.0,
+            // Tool output has no overall progress.
+            JobPhase::Install { .. } => 0.0,
+            JobPhase::Finished { .. } => 100.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobProgress {
+    #[serde(flatten)]
+    pub phase: JobPhase,
+    pub percent: f32,
+}
+
+impl JobProgress {
+    pub fn new(phase: JobPhase) -> Self {
+        Self { percent: phase.percent(), phase }
+    }
+}
+
+/// Payload of the `process-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobProgressEvent {
+    pub job_id: JobId,
+    pub kind: String,
+    pub label: String,
+    #[serde(flatten)]
+    pub progress: JobProgress,
+}
+
+/// Handed to a job so it can report its phase and notice cancellation. Cheap to clone.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    id: JobId,
+    manager: JobManager,
+    cancel: CancelFlag,
+}
+
+impl ProgressReporter {
+    pub fn job_id(&self) -> JobId {
+        self.id
+    }
+
+    pub fn report(&self, phase: JobPhase) {
+        self.manager.set_progress(self.id, phase);
+    }
+
+    pub fn cancel_flag(&self) -> &CancelFlag {
+        &self.cancel
+    }
+
+    /// `Err(JobCancelled)` once the job was cancelled; use with `?` between units of work.
+    pub fn check_cancelled(&self) -> Result<(), AppError> {
+        if self.cancel.is_cancelled() {
+            Err(AppError::JobCancelled { job_id: self.id })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: JobId,
+    /// What the job does, e.g. "process_rust_project".
+    pub kind: String,
+    /// Human readable target, usually the project path.
+    pub label: String,
+    pub priority: JobPriority,
+    pub status: JobStatus,
+    /// Last reported progress, None until the job reports any.
+    pub progress: Option<JobProgress>,
+    pub submitted_at_ms: u128,
+    pub started_at_ms: Option<u128>,
+    pub finished_at_ms: Option<u128>,
+}
+
+struct QueuedJob {
+    id: JobId,
+    priority: JobPriority,
+    future: JobFuture,
+}
+
+// Highest priority first; FIFO (lowest id) within a priority.
+impl Ord for QueuedJob {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority).then_with(|| other.id.cmp(&self.id))
+    }
+}
+impl PartialOrd for QueuedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+impl PartialEq for QueuedJob {
+    fn eq(&self, other: &Self) -> bool { self.id == other.id }
+}
+impl Eq for QueuedJob {}
+
+#[derive(Default)]
+struct JobManagerInner {
+    next_id: JobId,
+    jobs: HashMap<JobId, JobInfo>,
+    queue: BinaryHeap<QueuedJob>,
+    running: usize,
+    waiters: HashMap<JobId, Vec<oneshot::Sender<JobResult>>>,
+    // Dropped when the job finishes.
+    cancel_flags: HashMap<JobId, CancelFlag>,
+}
+
+/// Background job queue shared by Tauri commands and MCP tools.
+///
+/// Jobs are started in priority order while fewer than `[jobs] max_concurrent_jobs` are running;
+/// the limit is read from the live config, so raising it takes effect on the next dispatch. Jobs
+/// with the same label (the same project) never run at once, so processing and re-embedding a
+/// project can't race on its files or cargo target directory. At most `[jobs] max_queued_jobs`
+/// wait; submitting more fails with `QueueFull`.
+#[derive(Clone)]
+pub struct JobManager {
+    inner: Arc<Mutex<JobManagerInner>>,
+    config: ConfigHandle,
+    progress_events: broadcast::Sender<JobProgressEvent>,
+}
+
+impl JobManager {
+    pub fn new(config: ConfigHandle) -> Self {
+        let (progress_events, _) = broadcast::channel(PROGRESS_CHANNEL_CAPACITY);
+        Self { inner: Arc::new(Mutex::new(JobManagerInner::default())), config, progress_events }
+    }
+
+    /// Queues a job and returns its id immediately. Must be called from within a tokio runtime.
+    pub fn submit<F>(&self, kind: &str, label: &str, priority: JobPriority, job: F) -> Result<JobId, AppError>
+    where
+        F: Future<Output = JobResult> + Send + 'static,
+    {
+        self.submit_with_progress(kind, label, priority, |_| job)
+    }
+
+    /// Like `submit`, but `make_job` receives a `ProgressReporter` for the new job (progress and cancellation).
+    pub fn submit_with_progress<M, F>(&self, kind: &str, label: &str, priority: JobPriority, make_job: M) -> Result<JobId, AppError>
+    where
+        M: FnOnce(ProgressReporter) -> F,
+        F: Future<Output = JobResult> + Send + 'static,
+    {
+        let max_queued = self.config.current().jobs.max_queued_jobs.max(1);
+        let id = {
+            let mut inner = self.inner.lock().expect("job manager lock poisoned");
+            if inner.queue.len() >= max_queued {
+                return Err(AppError::QueueFull { max_queued_jobs: max_queued });
+            }
+            inner.next_id += 1;
+            inner.next_id
+        };
+        let cancel = CancelFlag::default();
+        // Built outside the lock: the job may hold a reporter that locks the manager.
+        let job = make_job(ProgressReporter { id, manager: self.clone(), cancel: cancel.clone() });
+        {
+            // Registered in one critical section so `cancel` never sees a queued job without its future.
+            let mut inner = self.inner.lock().expect("job manager lock poisoned");
+            if inner.queue.len() >= max_queued {
+                return Err(AppError::QueueFull { max_queued_jobs: max_queued });
+            }
+            inner.jobs.insert(id, JobInfo {
+                id,
+                kind: kind.to_string(),
+                label: label.to_string(),
+                priority,
+                status: JobStatus::Queued,
+                progress: None,
+                submitted_at_ms: now_ms(),
+                started_at_ms: None,
+                finished_at_ms: None,
+            });
+            inner.cancel_flags.insert(id, cancel);
+            inner.queue.push(QueuedJob { id, priority, future: Box::pin(job) });
+        }
+        log::info!("Queued job {} ({} for {}, priority {:?})", id, kind, label, priority);
+        self.dispatch();
+        Ok(id)
+    }
+
+    /// Waits for a job to finish and returns its result.
+    pub async fn wait(&self, id: JobId) -> JobResult {
+        let receiver = {
+            let mut inner = self.inner.lock().expect("job manager lock poisoned");
+            match inner.jobs.get(&id).map(|job| job.status.clone()) {
+                None => return Err(AppError::JobNotFound { job_id: id }),
+                Some(JobStatus::Completed { message }) => return Ok(message),
+                Some(JobStatus::Failed { error }) => return Err(error),
+                Some(JobStatus::Cancelled) => return Err(AppError::JobCancelled { job_id: id }),
+                Some(_) => {
+                    let (sender, receiver) = oneshot::channel();
+                    inner.waiters.entry(id).or_default().push(sender);
+                    receiver
+                }
+            }
+        };
+        receiver.await.unwrap_or_else(|_| Err(AppError::internal(format!("Job {} was dropped before completing", id))))
+    }
+
+    pub fn get(&self, id: JobId) -> Option<JobInfo> {
+        self.inner.lock().expect("job manager lock poisoned").jobs.get(&id).cloned()
+    }
+
+    /// All known jobs, newest first.
+    pub fn list(&self) -> Vec<JobInfo> {
+        let inner = self.inner.lock().expect("job manager lock poisoned");
+        let mut jobs: Vec<JobInfo> = inner.jobs.values().cloned().collect();
+        jobs.sort_by(|a, b| b.id.cmp(&a.id));
+        jobs
+    }
+
+    /// Cancels a job. A queued job is dropped right away; a running job is flagged and stops at its
+    /// next cancellation point (its final status becomes `cancelled` once it does).
+    pub fn cancel(&self, id: JobId) -> Result<(), AppError> {
+        let finished_event = {
+            let mut inner = self.inner.lock().expect("job manager lock poisoned");
+            let status = inner.jobs.get(&id).map(|job| job.status.clone()).ok_or(AppError::JobNotFound { job_id: id })?;
+            match status {
+                JobStatus::Running => {
+                    if let Some(flag) = inner.cancel_flags.get(&id) {
+                        flag.cancel();
+                    }
+                    log::info!("Cancellation requested for running job {}", id);
+                    return Ok(());
+                }
+                JobStatus::Queued => {
+                    inner.queue.retain(|queued| queued.id != id);
+                    inner.cancel_flags.remove(&id);
+                    for waiter in inner.waiters.remove(&id).unwrap_or_default() {
+                        let _ = waiter.send(Err(AppError::JobCancelled { job_id: id }));
+                    }
+                    log::info!("Cancelled queued job {}", id);
+                    let info = inner.jobs.get_mut(&id).expect("job checked above");
+                    info.status = JobStatus::Cancelled;
+                    info.finished_at_ms = Some(now_ms());
+                    let progress = JobProgress::new(JobPhase::Finished { status: JobStatus::Cancelled });
+                    info.progress = Some(progress.clone());
+                    JobProgressEvent { job_id: id, kind: info.kind.clone(), label: info.label.clone(), progress }
+                }
+                _ => return Err(AppError::InvalidArgument { message: format!("Job {} has already finished", id) }),
+            }
+        };
+        let _ = self.progress_events.send(finished_event);
+        Ok(())
+    }
+
+    /// The queued or running job of `kind` for `label` (e.g. a project path), if any.
+    pub fn find_active(&self, kind: &str, label: &str) -> Option<JobId> {
+        let inner = self.inner.lock().expect("job manager lock poisoned");
+        inner.jobs.values()
+            .filter(|job| job.kind == kind && job.label == label && !job.status.is_finished())
+            .map(|job| job.id)
+            .max()
+    }
+
+    /// The latest queued or running job of any kind for `label`, e.g. every job processing,
+    /// re-embedding or importing one project path.
+    pub fn find_active_by_label(&self, label: &str) -> Option<JobId> {
+        let inner = self.inner.lock().expect("job manager lock poisoned");
+        inner.jobs.values()
+            .filter(|job| job.label == label && !job.status.is_finished())
+            .map(|job| job.id)
+            .max()
+    }
+
+    /// Progress of every job, including a final `finished` event.
+    pub fn subscribe_progress(&self) -> broadcast::Receiver<JobProgressEvent> {
+        self.progress_events.subscribe()
+    }
+
+    fn set_progress(&self, id: JobId, phase: JobPhase) {
+        let progress = JobProgress::new(phase);
+        let event = {
+            let mut inner = self.inner.lock().expect("job manager lock poisoned");
+            let Some(info) = inner.jobs.get_mut(&id) else { return };
+            info.progress = Some(progress.clone());
+            JobProgressEvent { job_id: id, kind: info.kind.clone(), label: info.label.clone(), progress }
+        };
+        // No receivers (e.g. headless mode) is fine.
+        let _ = self.progress_events.send(event);
+    }
+
+    // Starts queued jobs while there is spare capacity, skipping those whose label is running.
+    fn dispatch(&self) {
+        let max_concurrent = self.config.current().jobs.max_concurrent_jobs.max(1);
+        let mut inner = self.inner.lock().expect("job manager lock poisoned");
+        let mut deferred = Vec::new();
+        while inner.running < max_concurrent {
+            let Some(queued) = inner.queue.pop() else { break };
+            if inner.jobs.get(&queued.id).map(|job| &job.status) != Some(&JobStatus::Queued) {
+                // Cancelled (or otherwise settled) while queued: never run it.
+                continue;
+            }
+            let label = inner.jobs.get(&queued.id).map(|job| job.label.as_str());
+            if inner.jobs.values().any(|job| job.status == JobStatus::Running && Some(job.label.as_str()) == label) {
+                deferred.push(queued);
+                continue;
+            }
+            inner.running += 1;
+            if let Some(info) = inner.jobs.get_mut(&queued.id) {
+                info.status = JobStatus::Running;
+                info.started_at_ms = Some(now_ms());
+            }
+            let manager = self.clone();
+            tokio::spawn(async move {
+                log::info!("Starting job {}", queued.id);
+                // Run on a task of its own so a panic still finishes the job: otherwise its label
+                // would stay running and its waiters would hang.
+                let result = match tokio::spawn(queued.future).await {
+                    Ok(result) => result,
+                    Err(e) => Err(AppError::internal(format!("Job {} panicked: {}", queued.id, e))),
+                };
+                manager.finish(queued.id, result);
+            });
+        }
+        inner.queue.extend(deferred);
+    }
+
+    fn finish(&self, id: JobId, result: JobResult) {
+        let finished_event = {
+            let mut inner = self.inner.lock().expect("job manager lock poisoned");
+            inner.running = inner.running.saturating_sub(1);
+            inner.cancel_flags.remove(&id);
+            let mut finished_event = None;
+            if let Some(info) = inner.jobs.get_mut(&id) {
+                info.finished_at_ms = Some(now_ms());
+                info.status = JobStatus::from_result(&result);
+                let progress = JobProgress::new(JobPhase::Finished { status: info.status.clone() });
+                info.progress = Some(progress.clone());
+                finished_event = Some(JobProgressEvent { job_id: id, kind: info.kind.clone(), label: info.label.clone(), progress });
+            }
+            match &result {
+                Ok(_) => log::info!("Job {} completed", id),
+                Err(AppError::JobCancelled { .. }) => log::info!("Job {} cancelled", id),
+                Err(e) => log::error!("Job {} failed: {}", id, e),
+            }
+            for waiter in inner.waiters.remove(&id).unwrap_or_default() {
+                let _ = waiter.send(result.clone());
+            }
+            prune_finished(&mut inner);
+            finished_event
+        };
+        if let Some(event) = finished_event {
+            let _ = self.progress_events.send(event);
+        }
+        self.dispatch();
+    }
+}
+
+fn prune_finished(inner: &mut JobManagerInner) {
+    let mut finished: Vec<JobId> = inner.jobs.values().filter(|j| j.status.is_finished()).map(|j| j.id).collect();
+    if finished.len() <= MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort_unstable();
+    for id in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+        inner.jobs.remove(id);
+    }
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn test_manager() -> JobManager {
+        // Nonexistent config file -> defaults (one job at a time).
+        JobManager::new(ConfigHandle::load(PathBuf::from("/nonexistent/config.toml")))
+    }
+
+    #[tokio::test]
+    async fn test_jobs_run_in_priority_order() {
+        let manager = test_manager();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (release_tx, release_rx) = oneshot::channel::<()>();
+
+        // Occupies the single slot until released, so the rest queue up.
+        let blocker = manager.submit("test", "blocker", JobPriority::Normal, async move {
+            let _ = release_rx.await;
+            Ok("blocker".to_string())
+        }).unwrap();
+        let mut ids = Vec::new();
+        for (label, priority) in [("low", JobPriority::Low), ("normal", JobPriority::Normal), ("high", JobPriority::High)] {
+            let order = order.clone();
+            ids.push(manager.submit("test", label, priority, async move {
+                order.lock().unwrap().push(label);
+                Ok(label.to_string())
+            }).unwrap());
+        }
+        assert_eq!(manager.get(ids[0]).unwrap().status, JobStatus::Queued);
+
+        release_tx.send(()).unwrap();
+        assert_eq!(manager.wait(blocker).await, Ok("blocker".to_string()));
+        for id in ids {
+            assert!(manager.wait(id).await.is_ok());
+        }
+        assert_eq!(*order.lock().unwrap(), vec!["high", "normal", "low"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_job_status() {
+        let manager = test_manager();
+        let id = manager.submit("test", "failing", JobPriority::Normal, async { Err(AppError::internal("boom")) }).unwrap();
+        assert_eq!(manager.wait(id).await, Err(AppError::internal("boom")));
+        assert_eq!(manager.get(id).unwrap().status, JobStatus::Failed { error: AppError::internal("boom") });
+        assert_eq!(manager.wait(9999).await, Err(AppError::JobNotFound { job_id: 9999 }));
+    }
+
+    #[tokio::test]
+    async fn test_panicked_job_frees_its_label() {
+        let manager = test_manager();
+        let panicked = manager.submit("process", "/work/app", JobPriority::Normal, async { panic!("job blew up") }).unwrap();
+        let next = manager.submit("process", "/work/app", JobPriority::Normal, async { Ok("next".to_string()) }).unwrap();
+        assert!(matches!(manager.wait(panicked).await, Err(AppError::Internal { .. })));
+        assert!(matches!(manager.get(panicked).unwrap().status, JobStatus::Failed { .. }));
+        assert_eq!(manager.wait(next).await, Ok("next".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_progress_events() {
+        let manager = test_manager();
+        let mut events = manager.subscribe_progress();
+        let id = manager.submit_with_progress("test", "progress", JobPriority::Normal, |progress| async move {
+            progress.report(JobPhase::Parse);
+            progress.report(JobPhase::Embed { batch: 1, total_batches: 2 });
+            Ok("done".to_string())
+        }).unwrap();
+        assert!(manager.wait(id).await.is_ok());
+
+        let percents: Vec<f32> = (0..3).map(|_| events.try_recv().unwrap().progress.percent).collect();
+        assert_eq!(percents, vec![30.0, 67.5, 100.0]);
+        let info = manager.get(id).unwrap();
+        assert_eq!(info.progress.unwrap().phase, JobPhase::Finished { status: JobStatus::Completed { message: "done".to_string() } });
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running_and_queued_jobs() {
+        let manager = test_manager();
+        let (started_tx, started_rx) = oneshot::channel::<()>();
+        // Polls for cancellation like the embedding loop does between batches.
+        let running = manager.submit_with_progress("test", "running", JobPriority::Normal, |progress| async move {
+            let _ = started_tx.send(());
+            loop {
+                progress.check_cancelled()?;
+                tokio::task::yield_now().await;
+            }
+        }).unwrap();
+        let queued = manager.submit("test", "queued", JobPriority::Normal, async { Ok("never runs".to_string()) }).unwrap();
+        started_rx.await.unwrap();
+        assert_eq!(manager.find_active("test", "queued"), Some(queued));
+        assert_eq!(manager.find_active_by_label("queued"), Some(queued));
+        assert_eq!(manager.find_active("other", "queued"), None);
+
+        manager.cancel(queued).unwrap();
+        assert_eq!(manager.get(queued).unwrap().status, JobStatus::Cancelled);
+        manager.cancel(running).unwrap();
+        assert_eq!(manager.wait(running).await, Err(AppError::JobCancelled { job_id: running }));
+        assert_eq!(manager.get(running).unwrap().status, JobStatus::Cancelled);
+        assert_eq!(manager.wait(queued).await, Err(AppError::JobCancelled { job_id: queued }));
+        assert!(matches!(manager.cancel(running), Err(AppError::InvalidArgument { .. })));
+        assert_eq!(manager.find_active("test", "running"), None);
+        assert_eq!(manager.find_active_by_label("running"), None);
+    }
+
+    #[tokio::test]
+    async fn test_same_label_waits_and_queue_is_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(&config_path, "[jobs]\nmax_concurrent_jobs = 2\nmax_queued_jobs = 2\n").unwrap();
+        let manager = JobManager::new(ConfigHandle::load(config_path));
+        let (release_tx, release_rx) = oneshot::channel::<()>();
+
+        let first = manager.submit("process", "/work/app", JobPriority::Normal, async move {
+            let _ = release_rx.await;
+            Ok("first".to_string())
+        }).unwrap();
+        // A slot is free, but not for a second job on the same project.
+        let second = manager.submit("reembed", "/work/app", JobPriority::High, async { Ok("second".to_string()) }).unwrap();
+        let other = manager.submit("process", "/work/other", JobPriority::Normal, async { Ok("other".to_string()) }).unwrap();
+        assert_eq!(manager.wait(other).await, Ok("other".to_string()));
+        assert_eq!(manager.get(second).unwrap().status, JobStatus::Queued);
+
+        let third = manager.submit("process", "/work/app", JobPriority::Low, async { Ok("third".to_string()) }).unwrap();
+        assert_eq!(manager.submit("process", "/work/new", JobPriority::Normal, async { Ok(String::new()) }), Err(AppError::QueueFull { max_queued_jobs: 2 }));
+
+        release_tx.send(()).unwrap();
+        assert_eq!(manager.wait(first).await, Ok("first".to_string()));
+        assert_eq!(manager.wait(second).await, Ok("second".to_string()));
+        assert_eq!(manager.wait(third).await, Ok("third".to_string()));
+    }
+}
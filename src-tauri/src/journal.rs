@@ -0,0 +1,317 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of items embedded between journal checkpoints.
+pub const EMBED_BATCH_SIZE: usize = 64;
+
+/// The last pipeline stage that completed for a project.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ProcessingStage {
+    Started,
+    /// `json_paths` holds one rustdoc JSON per documented target.
+    Generated { json_paths: Vec<PathBuf> },
+    Parsed { json_paths: Vec<PathBuf>, total_to_embed: usize },
+    EmbeddedBatch { json_paths: Vec<PathBuf>, batch: usize, total_batches: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalState {
+    project_path: String,
+    stage: ProcessingStage,
+    /// The model that produced the journaled embeddings, and their length; see
+    /// `bind_embedding_model`.
+    #[serde(default)]
+    embedding_model: Option<String>,
+    #[serde(default)]
+    dimension: Option<usize>,
+}
+
+// One line of the embeddings sidecar file: the vectors produced by a single batch.
+#[derive(Serialize, Deserialize)]
+struct EmbeddedBatchRecord {
+    batch: usize,
+    embeddings: Vec<(String, Vec<f32>)>,
+}
+
+/// Crash-safe record of how far processing got for one project.
+///
+/// The journal lives in `<journal_dir>/<project hash>.json`, with the vectors of each completed
+/// embedding batch appended to a `.embeddings.jsonl` sidecar. Both files are removed once
+/// processing finishes, so a journal that still exists at startup means the run was interrupted.
+pub struct ProcessingJournal {
+    state_path: PathBuf,
+    embeddings_path: PathBuf,
+    state: JournalState,
+}
+
+impl ProcessingJournal {
+    /// Opens the existing journal for `project_path`, or starts a new one.
+    pub fn open(journal_dir: &Path, project_path: &str) -> Result<Self> {
+        fs::create_dir_all(journal_dir)
+            .with_context(|| format!("Failed to create journal directory: {}", journal_dir.display()))?;
+        let key = journal_key(project_path);
+        let state_path = journal_dir.join(format!("{}.json", key));
+        let embeddings_path = journal_dir.join(format!("{}.embeddings.jsonl", key));
+        if !state_path.exists() {
+            adopt_legacy_journal(journal_dir, project_path, &state_path, &embeddings_path);
+        }
+
+        let existing = if state_path.exists() {
+            match read_state(&state_path) {
+                Ok(state) if state.project_path == project_path => Some(state),
+                Ok(_) => None, // Hash collision with another project; start fresh.
+                Err(e) => {
+                    log::warn!("Ignoring unreadable journal {}: {:?}", state_path.display(), e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut journal = match existing {
+            Some(state) => {
+                log::info!("Resuming processing of {} from journal stage {:?}", project_path, state.stage);
+                Self { state_path, embeddings_path, state }
+            }
+            None => {
+                let _ = fs::remove_file(&embeddings_path);
+                Self {
+                    state_path,
+                    embeddings_path,
+                    state: JournalState { project_path: project_path.to_string(), stage: ProcessingStage::Started, embedding_model: None, dimension: None },
+                }
+            }
+        };
+        journal.write_state()?;
+        Ok(journal)
+    }
+
+    pub fn stage(&self) -> &ProcessingStage {
+        &self.state.stage
+    }
+
+    /// The rustdoc JSON produced by an earlier run, if that stage completed and the files still exist.
+    pub fn generated_json_paths(&self) -> Option<Vec<PathBuf>> {
+        let json_paths = match &self.state.stage {
+            ProcessingStage::Started => return None,
+            ProcessingStage::Generated { json_paths }
+            | ProcessingStage::Parsed { json_paths, .. }
+            | ProcessingStage::EmbeddedBatch { json_paths, .. } => json_paths,
+        };
+        match json_paths.iter().find(|path| !path.exists()) {
+            None if !json_paths.is_empty() => Some(json_paths.clone()),
+            missing => {
+                log::warn!("Journaled rustdoc JSON {} is missing; it will be regenerated.", missing.map_or_else(|| "list".to_string(), |p| p.display().to_string()));
+                None
+            }
+        }
+    }
+
+    pub fn record_generated(&mut self, json_paths: &[PathBuf]) -> Result<()> {
+        self.state.stage = ProcessingStage::Generated { json_paths: json_paths.to_vec() };
+        self.write_state()
+    }
+
+    pub fn record_parsed(&mut self, json_paths: &[PathBuf], total_to_embed: usize) -> Result<()> {
+        self.state.stage = ProcessingStage::Parsed { json_paths: json_paths.to_vec(), total_to_embed };
+        self.write_state()
+    }
+
+    /// Appends a batch's vectors to the sidecar file, then advances the journal stage.
+    pub fn record_embedded_batch(&mut self, json_paths: &[PathBuf], batch: usize, total_batches: usize, embeddings: Vec<(String, Vec<f32>)>) -> Result<()> {
+        let record = EmbeddedBatchRecord { batch, embeddings };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.embeddings_path)
+            .with_context(|| format!("Failed to open journal embeddings file: {}", self.embeddings_path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        file.sync_data()?;
+
+        self.state.stage = ProcessingStage::EmbeddedBatch { json_paths: json_paths.to_vec(), batch, total_batches };
+        self.write_state()
+    }
+
+    /// Records the model embedding this run. Embeddings journaled by an earlier run with another
+    /// model or vector length (or before models were recorded) are deleted, since they aren't
+    /// comparable; returns whether there were any, so the caller drops them too.
+    pub fn bind_embedding_model(&mut self, model_id: &str, dimension: Option<usize>) -> Result<bool> {
+        let same_model = self.state.embedding_model.as_deref() == Some(model_id) && self.state.dimension == dimension;
+        let discarded = !same_model && self.embeddings_path.exists();
+        if discarded {
+            fs::remove_file(&self.embeddings_path)
+                .with_context(|| format!("Failed to remove journal embeddings: {}", self.embeddings_path.display()))?;
+        }
+        if !same_model {
+            self.state.embedding_model = Some(model_id.to_string());
+            self.state.dimension = dimension;
+            self.write_state()?;
+        }
+        Ok(discarded)
+    }
+
+    /// Embeddings from batches completed by an earlier (interrupted) run.
+    pub fn completed_embeddings(&self) -> Result<HashMap<String, Vec<f32>>> {
+        let mut embeddings = HashMap::new();
+        if !self.embeddings_path.exists() {
+            return Ok(embeddings);
+        }
+        let file = fs::File::open(&self.embeddings_path)?;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            // A crash mid-write can leave a truncated last line; that batch is simply redone.
+            match serde_json::from_str::<EmbeddedBatchRecord>(&line) {
+                Ok(record) => embeddings.extend(record.embeddings),
+                Err(e) => log::warn!("Skipping corrupt journal embeddings line: {}", e),
+            }
+        }
+        Ok(embeddings)
+    }
+
+    /// Processing finished; remove the journal and its sidecar.
+    pub fn complete(self) -> Result<()> {
+        let _ = fs::remove_file(&self.embeddings_path);
+        fs::remove_file(&self.state_path)
+            .with_context(|| format!("Failed to remove journal: {}", self.state_path.display()))
+    }
+
+    // Write to a temp file and rename so a crash never leaves a half-written journal.
+    fn write_state(&mut self) -> Result<()> {
+        let tmp_path = self.state_path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(&self.state)?)
+            .with_context(|| format!("Failed to write journal: {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.state_path)
+            .with_context(|| format!("Failed to commit journal: {}", self.state_path.display()))
+    }
+}
+
+/// Projects whose processing was interrupted (their journal was never completed).
+pub fn interrupted_projects(journal_dir: &Path) -> Vec<(String, ProcessingStage)> {
+    let Ok(entries) = fs::read_dir(journal_dir) else { return Vec::new() };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| read_state(&path).ok())
+        .map(|state| (state.project_path, state.stage))
+        .collect()
+}
+
+fn read_state(path: &Path) -> Result<JournalState> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse journal: {}", path.display()))
+}
+
+// SHA-256, so a journal left by an interrupted run is found again after a Rust upgrade.
+fn journal_key(project_path: &str) -> String {
+    let digest = Sha256::digest(project_path.as_bytes());
+    digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Moves a journal for `project_path` saved under another name (an older key scheme) to the
+// current one, with its sidecar, so the interrupted run resumes instead of starting over.
+fn adopt_legacy_journal(journal_dir: &Path, project_path: &str, state_path: &Path, embeddings_path: &Path) {
+    let Ok(entries) = fs::read_dir(journal_dir) else { return };
+    let legacy = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .find(|path| read_state(path).is_ok_and(|state| state.project_path == project_path));
+    let Some(legacy) = legacy else { return };
+    log::info!("Moving journal {} to {}", legacy.display(), state_path.display());
+    let legacy_embeddings = legacy.with_extension("embeddings.jsonl");
+    if legacy_embeddings.exists() {
+        if let Err(e) = fs::rename(&legacy_embeddings, embeddings_path) {
+            log::warn!("Failed to move journal embeddings {}: {}", legacy_embeddings.display(), e);
+        }
+    }
+    if let Err(e) = fs::rename(&legacy, state_path) {
+        log::warn!("Failed to move journal {}: {}", legacy.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_journal_resume_and_complete() -> Result<()> {
+        let dir = tempdir()?;
+        let json_paths = vec![dir.path().join("my_crate.json"), dir.path().join("my_crate.bin.tool.json")];
+        for json_path in &json_paths {
+            fs::write(json_path, "{}")?;
+        }
+
+        let mut journal = ProcessingJournal::open(dir.path(), "/projects/my_crate")?;
+        assert_eq!(journal.stage(), &ProcessingStage::Started);
+        assert!(journal.generated_json_paths().is_none());
+        journal.record_generated(&json_paths)?;
+        assert!(!journal.bind_embedding_model("BAAI/bge-small-en-v1.5@main", Some(2))?);
+        journal.record_parsed(&json_paths, 3)?;
+        journal.record_embedded_batch(&json_paths, 0, 2, vec![("my_crate::a".to_string(), vec![1.0, 0.0])])?;
+        drop(journal); // Simulate a crash before completion.
+
+        let interrupted = interrupted_projects(dir.path());
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].0, "/projects/my_crate");
+
+        let mut journal = ProcessingJournal::open(dir.path(), "/projects/my_crate")?;
+        assert_eq!(journal.generated_json_paths(), Some(json_paths.clone()));
+        assert!(!journal.bind_embedding_model("BAAI/bge-small-en-v1.5@main", Some(2))?);
+        let embeddings = journal.completed_embeddings()?;
+        assert_eq!(embeddings.get("my_crate::a"), Some(&vec![1.0, 0.0]));
+
+        journal.complete()?;
+        assert!(interrupted_projects(dir.path()).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_journal_drops_embeddings_of_another_model() -> Result<()> {
+        let dir = tempdir()?;
+        let json_paths = vec![dir.path().join("my_crate.json")];
+        let mut journal = ProcessingJournal::open(dir.path(), "/projects/my_crate")?;
+        journal.bind_embedding_model("BAAI/bge-small-en-v1.5@main", Some(2))?;
+        journal.record_embedded_batch(&json_paths, 0, 2, vec![("my_crate::a".to_string(), vec![1.0, 0.0])])?;
+        drop(journal);
+
+        // The model changed before the run was resumed.
+        let mut journal = ProcessingJournal::open(dir.path(), "/projects/my_crate")?;
+        assert!(journal.bind_embedding_model("BAAI/bge-code-v1@main", Some(1536))?);
+        assert!(journal.completed_embeddings()?.is_empty());
+        // Batches of the new model are kept from then on.
+        journal.record_embedded_batch(&json_paths, 0, 2, vec![("my_crate::a".to_string(), vec![0.5; 1536])])?;
+        drop(journal);
+        let mut journal = ProcessingJournal::open(dir.path(), "/projects/my_crate")?;
+        assert!(!journal.bind_embedding_model("BAAI/bge-code-v1@main", Some(1536))?);
+        assert_eq!(journal.completed_embeddings()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_journal_under_legacy_name_is_resumed() -> Result<()> {
+        let dir = tempdir()?;
+        let json_paths = vec![dir.path().join("my_crate.json")];
+        let mut journal = ProcessingJournal::open(dir.path(), "/projects/my_crate")?;
+        journal.record_embedded_batch(&json_paths, 0, 2, vec![("my_crate::a".to_string(), vec![1.0, 0.0])])?;
+        drop(journal);
+        let key = journal_key("/projects/my_crate");
+        fs::rename(dir.path().join(format!("{}.json", key)), dir.path().join("0123456789abcdef.json"))?;
+        fs::rename(dir.path().join(format!("{}.embeddings.jsonl", key)), dir.path().join("0123456789abcdef.embeddings.jsonl"))?;
+
+        let journal = ProcessingJournal::open(dir.path(), "/projects/my_crate")?;
+        assert!(matches!(journal.stage(), ProcessingStage::EmbeddedBatch { batch: 0, .. }));
+        assert_eq!(journal.completed_embeddings()?.len(), 1);
+        journal.complete()?;
+        assert_eq!(fs::read_dir(dir.path())?.count(), 0);
+        Ok(())
+    }
+}
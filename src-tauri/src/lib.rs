@@ -2,15 +2,61 @@
 
 // Add module declaration
 pub mod mcp_server;
+pub mod mcp_http;
 pub mod rustdoc_processor;
 pub mod embedder;
 pub mod commands; // Declare commands module
+pub mod config;
+pub mod logging;
+pub mod journal;
+pub mod doc_source;
+pub mod jobs;
+pub mod compat;
+pub mod events;
+pub mod analytics;
+pub mod archive;
+pub mod error;
+pub mod sandbox;
+pub mod auth;
+pub mod store;
+pub mod storage;
+pub mod cache;
+pub mod embedding_cache;
+pub mod embedding_text;
+pub mod browse;
+pub mod diff;
+pub mod export;
+pub mod pipeline;
+pub mod http_api;
+pub mod watch;
+pub mod ann;
+pub mod bm25;
+pub mod chunking;
+pub mod crates_io;
+pub mod git_repo;
+pub mod docs_rs;
+pub mod quantized_encoder;
+pub mod incremental;
+pub mod resources;
+pub mod prompts;
+pub mod notifications;
+pub mod query_cache;
+pub mod generics;
+pub mod cfg;
+pub mod render;
+pub mod reranker;
+pub mod parse_set;
+pub mod remote_embedder;
+pub mod system_info;
+#[cfg(feature = "ort")]
+pub mod onnx_backend;
 
 // Keep existing if used, add others as needed
 use std::sync::Arc;
 use std::collections::HashMap;
 use tokio::sync::Mutex; // Ensure AppState uses this
 use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::Emitter;
 
 
 #[tauri::command]
@@ -20,25 +66,12 @@ fn greet() -> String {
   format!("Hello world from Rust! Current epoch: {}", epoch_ms)
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-  // Initialize env_logger for backend logging
-  // It's good to do this early.
-  // Consider making the log level configurable (e.g., via an environment variable).
-  env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
-
-  // Initialize the global embedder
-  // This can take time, so consider if it should block startup or be async.
-  // For now, let's do it synchronously and log errors.
-  if let Err(e) = embedder::init_global_embedder() {
-    log::error!("Failed to initialize global embedder during startup: {:?}. Some features might not work.", e);
-    // Depending on how critical the embedder is, you might want to panic or show an error to the user.
-  }
+// Shared by the GUI and the headless stdio entry point: embedder, cache directories and AppState.
+fn init_backend(config_handle: &config::ConfigHandle) -> Arc<mcp_server::AppState> {
+  let app_config = config_handle.current();
 
-  // Initialize and spawn the MCP server
-  // Create AppState instance first
-  let base_dirs = directories::BaseDirs::new().expect("Could not get base directories");
-  let cache_dir = base_dirs.cache_dir().join("rust_llm_mcp_server_cache"); // Ensure this matches mcp_server if it also constructs path
+  // Create AppState instance
+  let cache_dir = app_config.cache_dir();
   if !cache_dir.exists() {
       std::fs::create_dir_all(&cache_dir).expect("Could not create main cache directory");
   }
@@ -47,20 +80,207 @@ pub fn run() {
       std::fs::create_dir_all(&rustdoc_json_output_dir).expect("Could not create rustdoc_json_output_dir for AppState");
   }
 
-  let app_state_instance = Arc::new(mcp_server::AppState::new(rustdoc_json_output_dir));
+  let journal_dir = cache_dir.join("journals");
+  if !journal_dir.exists() {
+      std::fs::create_dir_all(&journal_dir).expect("Could not create journal_dir for AppState");
+  }
+  for (project_path, stage) in journal::interrupted_projects(&journal_dir) {
+      log::warn!("Processing of {} was interrupted at stage {:?}. Re-process it to resume.", project_path, stage);
+  }
+
+  let analytics = analytics::AnalyticsStore::load(&cache_dir);
+  let project_store = store::ProjectStore::open(cache_dir.join("projects")).expect("Could not open the project store");
+  let embedding_cache = embedding_cache::EmbeddingCache::open(&cache_dir.join("embeddings.sqlite")).expect("Could not open the embedding cache");
+  let app_state = Arc::new(mcp_server::AppState::new(config_handle.clone(), cache_dir.clone(), rustdoc_json_output_dir, journal_dir, analytics, project_store, embedding_cache));
+  // Projects whose stored files can't be loaded are re-parsed from their cached rustdoc JSON.
+  tauri::async_runtime::spawn(pipeline::restore_registry(app_state.clone()));
+
+  // Load the embedder in the background: the first run downloads several GB. Until it is ready,
+  // queries fail with `embedder_loading` and processing jobs wait for it.
+  let embedder_state = app_state.clone();
+  // Subscribed first so an out-of-memory fallback while loading is not missed.
+  let mut fallback_receiver = embedder::subscribe_device_fallbacks();
+  let fallback_state = app_state.clone();
+  tauri::async_runtime::spawn(async move {
+    loop {
+      match fallback_receiver.recv().await {
+        Ok(embedder::DeviceFallback { model_repo, from_device, reason }) => {
+          fallback_state.emit_state_event(events::StateEvent::EmbedderDeviceFallback { model_repo, from_device, reason });
+        }
+        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+      }
+    }
+  });
+  embedder::spawn_global_embedder_init(app_config.embedder.clone(), move |status| {
+    let model_repo = embedder_state.config.current().embedder.model_repo.clone();
+    let ready = matches!(status, embedder::EmbedderStatus::Ready { .. });
+    embedder_state.emit_state_event(events::StateEvent::EmbedderStateChanged { ready, model_repo });
+  });
+  app_state
+}
 
+/// Headless entry point (`--mcp-stdio`): serves MCP over stdin/stdout without opening a window,
+/// for clients that launch the server as a subprocess. Exits when the client disconnects.
+pub fn run_mcp_stdio() {
+  if let Err(e) = logging::init() {
+    eprintln!("Failed to initialize logging: {:?}", e);
+  }
+  let config_handle = config::ConfigHandle::load(config::default_config_path());
+  let runtime = tokio::runtime::Runtime::new().expect("Failed to start the tokio runtime");
+  runtime.block_on(async {
+    config_handle.spawn_hot_reload();
+    let app_state_instance = init_backend(&config_handle);
+    if let Err(e) = mcp_server::start_stdio_server(app_state_instance).await {
+      log::error!("MCP stdio server failed: {:?}", e);
+      std::process::exit(1);
+    }
+  });
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+  // Initialize tracing (stderr + rotating log file + in-app viewer) for backend logging
+  // It's good to do this early. The level can be changed with RUST_LOG.
+  if let Err(e) = logging::init() {
+    eprintln!("Failed to initialize logging: {:?}", e);
+  }
+
+  // Load the central config (TOML file + RUST_LLM_MCP_* env overrides) and watch it for edits.
+  let config_handle = config::ConfigHandle::load(config::default_config_path());
+  config_handle.spawn_hot_reload();
+  if let Err(e) = auth::ensure_token(&config_handle) {
+    log::warn!("Failed to save a generated auth token: {:?}", e);
+  }
+  let app_state_instance = init_backend(&config_handle);
 
   // Pass the same AppState instance to the MCP server
   mcp_server::init_mcp_server(app_state_instance.clone());
+  // Optional plain HTTP endpoints (embeddings, JSON API), off by default
+  http_api::init_http_api(app_state_instance.clone());
+
+  let compat_state = app_state_instance.clone();
+  let events_state = app_state_instance.clone();
 
   tauri::Builder::default()
     .manage(app_state_instance) // Add AppState to Tauri's managed state
     .plugin(tauri_plugin_opener::init())
+    .plugin(tauri_plugin_notification::init())
+    .setup(|app| {
+      // Forward backend log entries to the frontend log viewer.
+      if let Some(mut log_receiver) = logging::subscribe() {
+        let app_handle = app.handle().clone();
+        tauri::async_runtime::spawn(async move {
+          loop {
+            match log_receiver.recv().await {
+              Ok(entry) => { let _ = app_handle.emit("log-entry", entry); }
+              Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+              Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+          }
+        });
+      }
+
+      // Broadcast AppState changes to every window so they stay in sync without polling.
+      let mut state_receiver = events_state.subscribe_state_events();
+      let app_handle = app.handle().clone();
+      tauri::async_runtime::spawn(async move {
+        loop {
+          match state_receiver.recv().await {
+            Ok(event) => { let _ = app_handle.emit(events::STATE_CHANGED_EVENT, event); }
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => log::warn!("Dropped {} state events for slow windows", n),
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+          }
+        }
+      });
+      // Forward job progress (rustdoc, parse, embed batch N/M, ...) for progress bars.
+      let mut progress_receiver = events_state.jobs.subscribe_progress();
+      let app_handle = app.handle().clone();
+      tauri::async_runtime::spawn(async move {
+        loop {
+          match progress_receiver.recv().await {
+            Ok(event) => { let _ = app_handle.emit(jobs::JOB_PROGRESS_EVENT, event); }
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+          }
+        }
+      });
+      // OS notifications for long jobs and the model download finishing in the background.
+      notifications::spawn_notifier(app.handle().clone(), events_state.clone());
+      // The embedder may have finished loading before anyone subscribed.
+      events_state.emit_state_event(events::StateEvent::EmbedderStateChanged {
+        ready: matches!(embedder::embedder_status(), embedder::EmbedderStatus::Ready { .. }),
+        model_repo: events_state.config.current().embedder.model_repo.clone(),
+      });
+
+      // Probe the rustdoc toolchain and model in the background and report actionable warnings,
+      // rather than failing deep inside the first processing run.
+      let app_handle = app.handle().clone();
+      tauri::async_runtime::spawn(async move {
+        let config = compat_state.config.current();
+        match tokio::task::spawn_blocking(move || compat::run_startup_checks(&config)).await {
+          Ok(report) => {
+            for warning in &report.warnings {
+              log::warn!("[Startup check] {}: {}", warning.code, warning.message);
+            }
+            *compat_state.compat_report.lock().await = Some(report.clone());
+            let _ = app_handle.emit("compatibility-report", report);
+          }
+          Err(e) => log::error!("Startup compatibility checks panicked: {:?}", e),
+        }
+      });
+      Ok(())
+    })
     .invoke_handler(tauri::generate_handler![
         greet,
         commands::invoke_process_rust_project,
+        commands::process_std_library,
+        commands::reembed_project,
+        commands::import_rustdoc_json,
+        commands::install_nightly_toolchain,
+        commands::enable_watch,
+        commands::disable_watch,
+        commands::get_watched_projects,
         commands::invoke_query_documentation,
-        commands::get_processed_project_list
+        commands::get_processed_project_list,
+        commands::get_interrupted_projects,
+        commands::invoke_process_doc_source,
+        commands::get_doc_source_kinds,
+        commands::list_jobs,
+        commands::get_job_status,
+        commands::get_compatibility_report,
+        commands::get_index_analytics,
+        commands::get_project_stats,
+        commands::find_similar_items,
+        commands::get_query_history,
+        commands::clear_query_history,
+        commands::get_mcp_server_error,
+        commands::get_mcp_server_status,
+        commands::restart_mcp_server,
+        commands::stop_mcp_server,
+        commands::get_auth_settings,
+        commands::set_auth_required,
+        commands::set_project_toolchain,
+        commands::set_embedding_model,
+        commands::set_embedding_preset,
+        commands::get_system_info,
+        commands::get_embedder_info,
+        commands::get_embedder_status,
+        commands::cancel_processing,
+        commands::remove_processed_project,
+        commands::get_item_docs,
+        commands::get_item_source,
+        commands::list_crate_items,
+        commands::diff_documentation,
+        commands::export_project_docs,
+        commands::export_project_archive,
+        commands::import_project_archive,
+        commands::get_cache_stats,
+        commands::clear_cache,
+        commands::set_cache_dir,
+        commands::get_settings,
+        commands::update_settings,
+        commands::get_recent_logs
     ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
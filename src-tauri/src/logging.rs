@@ -0,0 +1,185 @@
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+const LOG_FILE_PREFIX: &str = "backend.log";
+// Daily rotation, so this keeps roughly a week of logs.
+const MAX_LOG_FILES: usize = 7;
+// Entries buffered for slow log viewers before the oldest are dropped.
+const LOG_CHANNEL_CAPACITY: usize = 1024;
+/// Entries `get_recent_logs` returns when no count is given.
+pub const DEFAULT_RECENT_LOG_LINES: usize = 200;
+// Entries kept in memory for `get_recent_logs`; older ones are only in the log files.
+const RECENT_LOG_CAPACITY: usize = 2000;
+
+// Keeps the non-blocking file writer flushing for the lifetime of the process.
+static FILE_WRITER_GUARD: OnceCell<WorkerGuard> = OnceCell::new();
+static LOG_CHANNEL: OnceCell<broadcast::Sender<LogEntry>> = OnceCell::new();
+static RECENT_LOGS: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+
+/// A single formatted log record, as forwarded to the in-app log viewer.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub timestamp_ms: u128,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// Directory holding the rotating log files: `<data_dir>/rust_llm_mcp_server/logs`.
+pub fn log_dir() -> PathBuf {
+    let base_dirs = directories::BaseDirs::new().expect("Could not get base directories");
+    base_dirs.data_dir().join("rust_llm_mcp_server").join("logs")
+}
+
+/// Installs the global tracing subscriber: stderr output, a daily-rotated log file and
+/// the in-app log channel. Existing `log::` macros are bridged into tracing.
+/// The filter defaults to `info` and can be changed with `RUST_LOG`.
+pub fn init() -> Result<()> {
+    let dir = log_dir();
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create log directory: {}", dir.display()))?;
+
+    let file_appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .context("Failed to create rolling log file appender")?;
+    let (file_writer, guard) = tracing_appender::non_blocking(file_appender);
+    let _ = FILE_WRITER_GUARD.set(guard);
+
+    let (sender, _receiver) = broadcast::channel(LOG_CHANNEL_CAPACITY);
+    let _ = LOG_CHANNEL.set(sender.clone());
+
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(tracing_subscriber::fmt::layer().with_ansi(false).with_writer(file_writer))
+        .with(ChannelLayer { sender })
+        .try_init()
+        .context("Failed to install tracing subscriber")?;
+
+    log::info!("Logging initialized. Log files are written to {}", dir.display());
+    Ok(())
+}
+
+/// Subscribes to log entries as they are recorded. Returns None if logging was not initialized.
+pub fn subscribe() -> Option<broadcast::Receiver<LogEntry>> {
+    LOG_CHANNEL.get().map(|sender| sender.subscribe())
+}
+
+/// The last `lines` entries recorded since startup at `min_level` or more severe (`warn` keeps
+/// warnings and errors), oldest first. Older entries are only in the files under `log_dir()`.
+pub fn recent_entries(lines: usize, min_level: Level) -> Vec<LogEntry> {
+    let recent = RECENT_LOGS.lock().unwrap_or_else(|e| e.into_inner());
+    filter_entries(&recent, lines, min_level)
+}
+
+fn filter_entries(entries: &VecDeque<LogEntry>, lines: usize, min_level: Level) -> Vec<LogEntry> {
+    // `Level` orders by verbosity: ERROR < WARN < ... < TRACE.
+    let mut matching: Vec<LogEntry> = entries.iter().rev()
+        .filter(|entry| entry.level.parse::<Level>().ok().is_none_or(|level| level <= min_level))
+        .take(lines)
+        .cloned()
+        .collect();
+    matching.reverse();
+    matching
+}
+
+// --- In-app log viewer layer ---
+
+struct ChannelLayer {
+    sender: broadcast::Sender<LogEntry>,
+}
+
+impl<S: Subscriber> Layer<S> for ChannelLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: LayerContext<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        let entry = LogEntry {
+            timestamp_ms: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or_default(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.finish(),
+        };
+        {
+            let mut recent = RECENT_LOGS.lock().unwrap_or_else(|e| e.into_inner());
+            if recent.len() == RECENT_LOG_CAPACITY {
+                recent.pop_front();
+            }
+            recent.push_back(entry.clone());
+        }
+        // Fails only if nobody is listening (e.g. no window open yet).
+        let _ = self.sender.send(entry);
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl MessageVisitor {
+    // Message first, then any structured fields as key=value pairs.
+    fn finish(self) -> String {
+        std::iter::once(self.message)
+            .chain(self.fields)
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else if !field.name().starts_with("log.") {
+            // `log.*` fields are metadata added by the log -> tracing bridge.
+            self.fields.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(level: Level, message: &str) -> LogEntry {
+        LogEntry { timestamp_ms: 0, level: level.to_string(), target: "test".to_string(), message: message.to_string() }
+    }
+
+    #[test]
+    fn test_filter_entries() {
+        let entries: VecDeque<LogEntry> = [
+            entry(Level::ERROR, "first error"),
+            entry(Level::INFO, "info"),
+            entry(Level::WARN, "warning"),
+            entry(Level::DEBUG, "debug"),
+            entry(Level::ERROR, "second error"),
+        ].into_iter().collect();
+
+        let messages = |lines, level| filter_entries(&entries, lines, level).into_iter().map(|e| e.message).collect::<Vec<_>>();
+        assert_eq!(messages(10, Level::WARN), vec!["first error", "warning", "second error"]);
+        // The newest entries are kept, in the order they were logged.
+        assert_eq!(messages(2, Level::TRACE), vec!["debug", "second error"]);
+        assert_eq!(messages(1, Level::ERROR), vec!["second error"]);
+    }
+}
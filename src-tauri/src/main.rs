@@ -1,6 +1,10 @@
-// Prevents additional console window on Windows in release, DO NOT REMOVE!!
-#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-
-fn main() {
-  tauri_nextjs_template_lib::run()
-}
+// Prevents additional console window on Windows in release, DO NOT REMOVE!!
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+fn main() {
+  if std::env::args().any(|arg| arg == "--mcp-stdio") {
+    tauri_nextjs_template_lib::run_mcp_stdio();
+  } else {
+    tauri_nextjs_template_lib::run()
+  }
+}
@@ -0,0 +1,293 @@
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use futures_util::stream::{self, StreamExt};
+use rust_mcp_sdk::mcp_server::server_runtime;
+use rust_mcp_sdk::transport::SseTransport;
+use rust_mcp_sdk::TransportOptions;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use tokio::io::{duplex, AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+use crate::auth;
+use crate::error::AppError;
+use crate::mcp_server::{server_details, AppState, MyMcpServerHandler};
+
+/// Where a client opens a session (an SSE stream carrying the server's messages).
+pub const SSE_ENDPOINT: &str = "/sse";
+/// Where a client posts its messages, with the `sessionId` the SSE stream announced.
+pub const MESSAGES_ENDPOINT: &str = "/messages";
+// Bytes buffered between the HTTP handlers and a session's MCP server.
+const DUPLEX_BUFFER_SIZE: usize = 64 * 1024;
+
+// The writing end of each open session's message stream, by session id.
+type Sessions = Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<DuplexStream>>>>>;
+
+#[derive(Clone)]
+struct McpHttpState {
+    app_state: Arc<AppState>,
+    sessions: Sessions,
+}
+
+/// MCP over HTTP (the SSE transport): every session gets an MCP server of its own, connected to
+/// the HTTP handlers through in-memory streams rather than a socket, so these handlers, which
+/// check the `Authorization: Bearer` header against `server.auth_required` on every request,
+/// are the only way to reach `MyMcpServerHandler`.
+pub fn router(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(SSE_ENDPOINT, get(open_session))
+        .route(MESSAGES_ENDPOINT, post(post_message))
+        .with_state(McpHttpState { app_state, sessions: Arc::default() })
+}
+
+fn check_auth(state: &McpHttpState, headers: &HeaderMap) -> Result<(), Response> {
+    auth::check_headers(&state.app_state.config.current().server, headers)
+        .map_err(|error| (StatusCode::UNAUTHORIZED, error.to_string()).into_response())
+}
+
+// Removes a session when its SSE stream is dropped (the client went away). That closes the
+// session's incoming messages, which ends its MCP server.
+struct SessionGuard {
+    sessions: Sessions,
+    session_id: String,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.session_id);
+    }
+}
+
+async fn open_session(State(state): State<McpHttpState>, headers: HeaderMap) -> Response {
+    if let Err(response) = check_auth(&state, &headers) {
+        return response;
+    }
+    // Client messages go from `incoming_tx` to the server; its messages from `outgoing_tx` to the stream.
+    let (incoming_tx, incoming_rx) = duplex(DUPLEX_BUFFER_SIZE);
+    let (outgoing_tx, outgoing_rx) = duplex(DUPLEX_BUFFER_SIZE);
+    let transport = match SseTransport::new(incoming_rx, outgoing_tx, Arc::new(TransportOptions::default())) {
+        Ok(transport) => transport,
+        Err(e) => {
+            let error = AppError::internal(format!("Failed to open an MCP session: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response();
+        }
+    };
+    let session_id = auth::generate_token();
+    state.sessions.lock().unwrap_or_else(|e| e.into_inner())
+        .insert(session_id.clone(), Arc::new(tokio::sync::Mutex::new(incoming_tx)));
+    let server = server_runtime::create_server(server_details(), transport, MyMcpServerHandler::new(state.app_state.clone()));
+    tokio::spawn(async move {
+        if let Err(e) = server.start().await {
+            log::warn!("MCP session ended with an error: {}", e);
+        }
+    });
+    log::debug!("Opened MCP session {}", session_id);
+
+    let endpoint = Event::default().event("endpoint").data(format!("{}?sessionId={}", MESSAGES_ENDPOINT, session_id));
+    let guard = SessionGuard { sessions: state.sessions.clone(), session_id };
+    // The server writes one JSON-RPC message per line.
+    let messages = stream::unfold((BufReader::new(outgoing_rx), guard), |(mut reader, guard)| async move {
+        let mut line = String::new();
+        match reader.read_line(&mut line).await {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some((Event::default().event("message").data(line.trim_end()), (reader, guard))),
+        }
+    });
+    let events = stream::once(async { endpoint }).chain(messages).map(Ok::<_, Infallible>);
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+#[derive(Deserialize)]
+struct MessageParams {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+}
+
+async fn post_message(State(state): State<McpHttpState>, headers: HeaderMap, Query(params): Query<MessageParams>, body: String) -> Response {
+    if let Err(response) = check_auth(&state, &headers) {
+        return response;
+    }
+    // Re-serialized, so the message is valid JSON on a single line, as the transport reads it.
+    let message = match serde_json::from_str::<serde_json::Value>(&body) {
+        Ok(message) => format!("{}\n", message),
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid JSON-RPC message: {}", e)).into_response(),
+    };
+    let session = state.sessions.lock().unwrap_or_else(|e| e.into_inner()).get(&params.session_id).cloned();
+    let Some(session) = session else {
+        return (StatusCode::NOT_FOUND, format!("Unknown MCP session '{}'", params.session_id)).into_response();
+    };
+    let mut writer = session.lock().await;
+    match writer.write_all(message.as_bytes()).await {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(e) => (StatusCode::GONE, format!("The MCP session has ended: {}", e)).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::AnalyticsStore;
+    use crate::config::ConfigHandle;
+    use crate::embedding_cache::EmbeddingCache;
+    use crate::store::ProjectStore;
+    use rust_mcp_sdk::mcp_types::LATEST_PROTOCOL_VERSION;
+    use serde_json::{json, Value};
+
+    // The server for a fresh app state configured by `config` (TOML), on an ephemeral port;
+    // returns its URL. The directory holds the state's files.
+    async fn serve(config: &str) -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(&config_path, config).unwrap();
+        let app_state = Arc::new(AppState::new(
+            ConfigHandle::load(config_path),
+            dir.path().to_path_buf(),
+            dir.path().join("rustdoc_json"),
+            dir.path().join("journals"),
+            AnalyticsStore::load(dir.path()),
+            ProjectStore::open(dir.path().join("projects")).unwrap(),
+            EmbeddingCache::open_in_memory().unwrap(),
+        ));
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(app_state)).await });
+        (dir, url)
+    }
+
+    // Requires the token `secret`.
+    async fn serve_secured() -> (tempfile::TempDir, String) {
+        serve("[server]\nauth_required = true\nauth_token = \"secret\"\n").await
+    }
+
+    // An initialized MCP session, as a client sees it.
+    struct Session {
+        client: reqwest::Client,
+        events: reqwest::Response,
+        buffer: String,
+        messages_url: String,
+    }
+
+    impl Session {
+        async fn open(url: &str, token: Option<&str>) -> Session {
+            let client = reqwest::Client::new();
+            let mut request = client.get(format!("{}{}", url, SSE_ENDPOINT));
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            let events = request.send().await.unwrap();
+            assert_eq!(events.status(), StatusCode::OK);
+            let mut session = Session { client, events, buffer: String::new(), messages_url: String::new() };
+            session.messages_url = format!("{}{}", url, session.next_data().await);
+            let init = json!({ "protocolVersion": LATEST_PROTOCOL_VERSION, "capabilities": {}, "clientInfo": { "name": "test", "version": "0" } });
+            session.call(token, 0, "initialize", init).await;
+            let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+            assert_eq!(session.post(token, &initialized).await, StatusCode::ACCEPTED);
+            session
+        }
+
+        // The data of the next SSE event, skipping keep-alive comments.
+        async fn next_data(&mut self) -> String {
+            loop {
+                if let Some(end) = self.buffer.find("\n\n") {
+                    let event: String = self.buffer.drain(..end + 2).collect();
+                    if let Some(data) = event.lines().find_map(|line| line.strip_prefix("data:")) {
+                        return data.trim_start().to_string();
+                    }
+                    continue;
+                }
+                let chunk = self.events.chunk().await.unwrap().expect("the SSE stream ended");
+                self.buffer.push_str(&String::from_utf8_lossy(&chunk));
+            }
+        }
+
+        async fn post(&self, token: Option<&str>, message: &Value) -> StatusCode {
+            let mut request = self.client.post(&self.messages_url).json(message);
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            request.send().await.unwrap().status()
+        }
+
+        // Sends a request and waits for the server's response to it.
+        async fn call(&mut self, token: Option<&str>, id: u64, method: &str, params: Value) -> Value {
+            let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+            assert_eq!(self.post(token, &request).await, StatusCode::ACCEPTED, "{}", method);
+            loop {
+                let message: Value = serde_json::from_str(&self.next_data().await).unwrap();
+                if message["id"] == id {
+                    return message;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mcp_http_checks_bearer_token() {
+        let (_dir, url) = serve_secured().await;
+        let client = reqwest::Client::new();
+        let denied = client.get(format!("{}{}", url, SSE_ENDPOINT)).send().await.unwrap();
+        assert_eq!(denied.status(), StatusCode::UNAUTHORIZED);
+        let wrong = client.get(format!("{}{}", url, SSE_ENDPOINT)).bearer_auth("guess").send().await.unwrap();
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+
+        // Knowing an open session's id isn't enough to post to it.
+        let mut session = Session::open(&url, Some("secret")).await;
+        let call = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": { "name": "list_projects", "arguments": {} } });
+        assert_eq!(session.post(None, &call).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(session.post(Some("guess"), &call).await, StatusCode::UNAUTHORIZED);
+
+        let tools = session.call(Some("secret"), 2, "tools/list", json!({})).await;
+        assert!(tools["result"]["tools"].as_array().is_some_and(|tools| !tools.is_empty()), "{}", tools);
+    }
+
+    // Recent queries are what users asked; reading them takes the token like tool calls do.
+    #[tokio::test]
+    async fn test_mcp_http_guards_query_history() {
+        let (_dir, url) = serve_secured().await;
+        let mut session = Session::open(&url, Some("secret")).await;
+        let params = json!({ "uri": crate::resources::QUERY_HISTORY_URI });
+        let read = json!({ "jsonrpc": "2.0", "id": 1, "method": "resources/read", "params": params });
+        assert_eq!(session.post(None, &read).await, StatusCode::UNAUTHORIZED);
+
+        let history = session.call(Some("secret"), 2, "resources/read", params).await;
+        let contents = &history["result"]["contents"][0];
+        assert_eq!(contents["uri"], crate::resources::QUERY_HISTORY_URI, "{}", history);
+        let entries: Value = serde_json::from_str(contents["text"].as_str().unwrap()).unwrap();
+        assert_eq!(entries, json!([]));
+    }
+
+    // Prompts run documentation searches to fill their templates.
+    #[tokio::test]
+    async fn test_mcp_http_guards_prompts() {
+        let (_dir, url) = serve_secured().await;
+        let mut session = Session::open(&url, Some("secret")).await;
+        let params = json!({ "name": "answer_question", "arguments": { "question": "How do I spawn a task?" } });
+        let get = json!({ "jsonrpc": "2.0", "id": 1, "method": "prompts/get", "params": params });
+        assert_eq!(session.post(None, &get).await, StatusCode::UNAUTHORIZED);
+        let list = json!({ "jsonrpc": "2.0", "id": 2, "method": "prompts/list", "params": {} });
+        assert_eq!(session.post(None, &list).await, StatusCode::UNAUTHORIZED);
+
+        let prompts = session.call(Some("secret"), 3, "prompts/list", json!({})).await;
+        let names: Vec<&str> = prompts["result"]["prompts"].as_array().unwrap().iter().filter_map(|p| p["name"].as_str()).collect();
+        assert!(names.contains(&"answer_question"), "{}", prompts);
+        // With the token the prompt's search runs, and fails only because no model is loaded here.
+        let rendered = session.call(Some("secret"), 4, "prompts/get", params).await;
+        assert!(rendered["error"]["message"].as_str().is_some_and(|m| m.contains("embedding model")), "{}", rendered);
+    }
+
+    #[tokio::test]
+    async fn test_mcp_http_without_auth() {
+        let (_dir, url) = serve("").await;
+        let mut session = Session::open(&url, None).await;
+        let tools = session.call(None, 1, "tools/list", json!({})).await;
+        assert!(tools["result"]["tools"].is_array(), "{}", tools);
+
+        let unknown = reqwest::Client::new().post(format!("{}{}?sessionId=nope", url, MESSAGES_ENDPOINT)).body("{}").send().await.unwrap();
+        assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,20 +1,46 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use rust_mcp_sdk::mcp_server::{
-    CallToolError, CallToolRequest, CallToolResult, InitializeResult, ListToolsRequest,
+    server_runtime, CallToolError, CallToolRequest, CallToolResult, InitializeResult, ListToolsRequest,
     ListToolsResult, McpServer, ServerCapabilities, ServerHandler, ServerHandlerCore,
 };
-use rust_mcp_sdk::mcp_types::{Implementation, ServerCapabilitiesTools, LATEST_PROTOCOL_VERSION};
-use rust_mcp_sdk::hyper_server::create_hyper_server;
-use rust_mcp_sdk::transport::HyperServerOptions;
+use rust_mcp_sdk::mcp_types::{
+    GetPromptRequest, GetPromptResult, Implementation, ListPromptsRequest, ListPromptsResult, ListResourcesRequest,
+    ListResourcesResult, Prompt, PromptArgument, PromptMessage, PromptMessageContent, ReadResourceRequest,
+    ReadResourceResult, ReadResourceResultContentsItem, Resource, Role, RpcError, ServerCapabilitiesPrompts,
+    ServerCapabilitiesResources, ServerCapabilitiesTools, TextContent, TextResourceContents, LATEST_PROTOCOL_VERSION,
+};
+use rust_mcp_sdk::{StdioTransport, TransportOptions};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{broadcast, oneshot, Mutex, MutexGuard, OnceCell};
 
 use serde::{Deserialize, Serialize};
 use rust_mcp_sdk::mcp_tool::{self, JsonSchema};
-use crate::rustdoc_processor::{CrateDocs, DocItem};
-use std::collections::HashMap;
-use crate::embedder::GLOBAL_EMBEDDER;
+use crate::rustdoc_processor::{CrateDocs, DocItem, DocTargets, ItemSource};
+use crate::cfg::Target;
+use std::collections::{HashMap, HashSet};
+use crate::config::{ConfigHandle, McpTransport, ServerSettings, SourceCleanup};
+use crate::doc_source::{self, DocSourceRegistry};
+use crate::jobs::{JobManager, JobPriority};
+use crate::events::{StateEvent, STATE_EVENT_CHANNEL_CAPACITY};
+use crate::analytics::{AnalyticsStore, ProjectStats};
+use crate::error::AppError;
+use crate::sandbox;
+use crate::mcp_http;
+use crate::store::ProjectStore;
+use crate::storage::{KeywordHit, QueryHistoryEntry};
+use crate::embedding_cache::EmbeddingCache;
+use crate::browse::{self, ItemListing};
+use crate::diff::{self, DocDiff};
+use crate::export::{self, ExportFormat, ExportSummary};
+use crate::render::{self, RenderMode};
+use crate::pipeline::{self, ProcessOptions, QueryDocResultItem, QueryHit, QueryOptions, ResultDetail};
+use crate::ann::{HnswIndex, HnswParams};
+use crate::bm25::Bm25Index;
+use crate::chunking;
+use crate::{git_repo, prompts, resources};
+use crate::query_cache::QueryResultCache;
 use std::path::{Path, PathBuf};
 use serde_json::json; // For creating simple JSON responses if needed
 
@@ -25,22 +51,77 @@ use serde_json::json; // For creating simple JSON responses if needed
 pub struct ProcessRustProjectTool {
     #[schemars(description = "Absolute path to the Rust project directory (containing Cargo.toml).")]
     pub path: String,
+    #[schemars(description = "Also index the docs of the project's direct dependencies (slower). Defaults to false.")]
+    pub include_dependencies: Option<bool>,
+    #[schemars(description = "Which targets to document: \"lib\", \"bin\", \"example\" or \"all\". Defaults to \"auto\": the library, or the binaries if there is none.")]
+    pub targets: Option<String>,
 }
 
 fn default_num_results() -> Option<usize> { Some(5) }
 
-#[mcp_tool(name = "query_documentation", description = "Queries the processed Rust documentation using a natural language query.")]
+#[mcp_tool(name = "query_documentation", description = "Queries the processed Rust documentation using a natural language query. Each result lists related items (its parent type or module, siblings and members) and code examples from its docs.")]
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct QueryDocumentationTool {
     #[schemars(description = "The natural language query.")]
     pub natural_language_query: String,
     #[schemars(description = "Optional: Absolute path of a specific Rust project to query. If None, queries all processed projects.")]
     pub project_path: Option<String>,
+    #[schemars(description = "Optional: only search this package version, e.g. \"0.6.3\" or \"0.6\" for any 0.6.x. Older versions of a project stay available after it is re-processed at a new version; \"*\" searches all of them, so results of several versions can be compared.")]
+    pub version: Option<String>,
     #[schemars(description = "Number of results to return.", default = "default_num_results")]
     pub num_results: Option<usize>,
+    #[schemars(description = "Optional: only return items of these kinds, e.g. [\"function\", \"struct\"]. Kinds are rustdoc's: module, struct, enum, variant, struct_field, trait, function, method, type_alias, constant, macro, ...")]
+    pub item_types: Option<Vec<String>>,
+    #[schemars(description = "Optional: only return items of this crate, e.g. \"tokio\" in a project indexed with its dependencies. Hyphens and underscores are interchangeable.")]
+    pub crate_name: Option<String>,
+    #[schemars(description = "Optional: only return items at or under this path, e.g. \"tokio::sync\".")]
+    pub module_prefix: Option<String>,
+    #[schemars(description = "Optional: also return #[deprecated] items, which are left out by default. Defaults to false.")]
+    pub include_deprecated: Option<bool>,
+    #[schemars(description = "Optional: only items declared `pub` (default true). Projects processed with private items documented also hold their internals; pass false to search those too.")]
+    pub public_only: Option<bool>,
+    #[schemars(description = "Optional: leave out items whose #[cfg] rules out this platform, e.g. \"x86_64-pc-windows-msvc\", \"linux\" or \"windows\". Conditions on features are not checked.")]
+    pub target: Option<String>,
+    #[schemars(description = "Optional: rescore the top hits with the cross-encoder reranker for better ordering (slower). Defaults to the server's reranker.enabled setting.")]
+    pub rerank: Option<bool>,
+    #[schemars(description = "Optional: rank items whose docs contain runnable code examples first, for \"how do I use X\" questions. Results always include up to 2 examples.")]
+    pub prefer_examples: Option<bool>,
+    #[schemars(description = "Optional: other phrasings of the same question, e.g. [\"run a future in the background\"]. Each is searched and the rankings are fused (reciprocal rank fusion), which helps terse queries. Up to 4 phrasings in total.")]
+    pub additional_queries: Option<Vec<String>>,
+    #[schemars(description = "Optional: also search generated paraphrases of the query (prefixed with the crate name, as a how-to question) and fuse the rankings. Defaults to false.")]
+    pub expand_query: Option<bool>,
+    #[schemars(description = "Optional: characters of each item's docs to return as description_snippet (default: the server's search.snippet_length, 300). 0 leaves it out.")]
+    pub snippet_length: Option<usize>,
+    #[schemars(description = "Optional: also return each item's complete docs (full_docs) and declaration (signature), saving a get_raw_documentation call per result. Defaults to false.")]
+    pub include_full_docs: Option<bool>,
+    #[schemars(description = "Optional: omit results scoring below this. It is compared with the 0-1 hybrid similarity of each phrasing's hits before they are fused (relevant items usually score above 0.5, noise around 0.1-0.3), or with rerank, the 0-1 reranker relevance; returned scores of fused rankings are RRF sums and aren't on this scale. When nothing passes, the tool says no relevant documentation was found.")]
+    pub min_score: Option<f32>,
+    #[schemars(description = "Optional: results per response (default: the server's search.page_size, 20). When the query has more, the response carries a next_cursor.")]
+    pub page_size: Option<usize>,
+    #[schemars(description = "Optional: next_cursor of a previous response, to get its next page. The other arguments (except page_size) are ignored; cursors expire search.cursor_ttl_secs (default 5 minutes) after their last use.")]
+    pub cursor: Option<String>,
 }
 
 
+#[mcp_tool(name = "answer_question", description = "Retrieves the documentation needed to answer a question about the processed Rust code: the most relevant items' full docs as numbered sources, ready to be quoted and cited as [n] in an answer.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct AnswerQuestionTool {
+    #[schemars(description = "The question to answer, in natural language.")]
+    pub question: String,
+    #[schemars(description = "Optional: Absolute path of a specific Rust project to draw sources from. If None, uses all processed projects.")]
+    pub project_path: Option<String>,
+    #[schemars(description = "Number of sources to return (default 5).")]
+    pub num_sources: Option<usize>,
+    #[schemars(description = "Optional: only use items of these kinds, e.g. [\"function\", \"struct\"].")]
+    pub item_types: Option<Vec<String>>,
+    #[schemars(description = "Optional: also use #[deprecated] items, which are left out by default. Defaults to false.")]
+    pub include_deprecated: Option<bool>,
+    #[schemars(description = "Optional: only items declared `pub` (default true). Projects processed with private items documented also hold their internals; pass false to search those too.")]
+    pub public_only: Option<bool>,
+    #[schemars(description = "Optional: rescore the top hits with the cross-encoder reranker (slower). Defaults to the server's reranker.enabled setting.")]
+    pub rerank: Option<bool>,
+}
+
 #[mcp_tool(name = "get_raw_documentation", description = "Retrieves raw documentation for a specific Rust item from a processed project.")]
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct GetRawDocumentationTool {
@@ -48,28 +129,601 @@ pub struct GetRawDocumentationTool {
     pub item_path: String,
     #[schemars(description = "Absolute path of the Rust project the item belongs to.")]
     pub project_path: String,
+    #[schemars(description = "Optional: how to return the description: \"raw\" markdown (default), \"plain\" text or sanitized \"html\". Rust code blocks lose their hidden `# ` lines in plain and html.")]
+    pub render: Option<String>,
+}
+
+#[mcp_tool(name = "find_similar_items", description = "Finds the items whose documentation is closest in meaning to an existing item, e.g. API alternatives to a function, using its stored embedding. No query text is needed.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct FindSimilarItemsTool {
+    #[schemars(description = "Absolute path of the processed project the item belongs to.")]
+    pub project_path: String,
+    #[schemars(description = "The full path of the item to find neighbours of (e.g., my_crate::module::MyStruct).")]
+    pub item_path: String,
+    #[schemars(description = "Optional: number of similar items to return. Defaults to the server's search.default_num_results.")]
+    pub num_results: Option<usize>,
+    #[schemars(description = "Optional: only return items of these kinds, e.g. [\"function\", \"method\"].")]
+    pub item_types: Option<Vec<String>>,
+    #[schemars(description = "Optional: also return #[deprecated] items, which are left out by default. Defaults to false.")]
+    pub include_deprecated: Option<bool>,
+    #[schemars(description = "Optional: only items declared `pub` (default true). Projects processed with private items documented also hold their internals; pass false to search those too.")]
+    pub public_only: Option<bool>,
+}
+
+#[mcp_tool(name = "process_doc_source", description = "Indexes a non-rustdoc documentation source (mdBook, Markdown folder, OpenAPI spec) into the same search space.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ProcessDocSourceTool {
+    #[schemars(description = "Absolute path to the documentation source (a directory, or a file for OpenAPI specs).")]
+    pub path: String,
+    #[schemars(description = "Optional: source kind (rustdoc, mdbook, openapi, markdown). Auto-detected if omitted.")]
+    pub source_kind: Option<String>,
+}
+
+#[mcp_tool(name = "cancel_processing", description = "Cancels a queued or running processing job. Kills a running cargo rustdoc and stops embedding after the current batch.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct CancelProcessingTool {
+    #[schemars(description = "Optional: id of the job to cancel.")]
+    pub job_id: Option<u64>,
+    #[schemars(description = "Optional: cancel the active job for this project or doc source path instead (processing, re-embedding, importing, or a crate or repository label as list_jobs shows it).")]
+    pub project_path: Option<String>,
+}
+
+#[mcp_tool(name = "process_crates_io_crate", description = "Downloads a published crate from crates.io, then documents, embeds and indexes it like process_rust_project.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ProcessCratesIoCrateTool {
+    #[schemars(description = "Crate name on crates.io, e.g. serde_json.")]
+    pub name: String,
+    #[schemars(description = "Exact version to process, e.g. 1.0.120.")]
+    pub version: String,
+    #[schemars(description = "Also index the docs of the crate's direct dependencies (slower). Defaults to false.")]
+    pub include_dependencies: Option<bool>,
+}
+
+#[mcp_tool(name = "process_git_repository", description = "Shallow-clones a git repository into the cache directory, finds its Cargo.toml (or workspace root), then documents, embeds and indexes it like process_rust_project. Query it by the project path the result reports.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ProcessGitRepositoryTool {
+    #[schemars(description = "Repository URL: https://, ssh://, git:// or user@host:path, e.g. https://github.com/serde-rs/json.git.")]
+    pub url: String,
+    #[schemars(description = "Optional: branch, tag or commit to check out. Defaults to the default branch.")]
+    pub rev: Option<String>,
+    #[schemars(description = "Also index the docs of the crate's direct dependencies (slower). Defaults to false.")]
+    pub include_dependencies: Option<bool>,
+}
+
+#[mcp_tool(name = "process_docsrs_crate", description = "Downloads the rustdoc JSON docs.rs built for a published crate and embeds and indexes it, without building anything or needing a local toolchain. Query it as project \"docs.rs:<name>\". Only releases docs.rs built JSON for are available; otherwise use process_crates_io_crate.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ProcessDocsRsCrateTool {
+    #[schemars(description = "Crate name, e.g. serde_json.")]
+    pub name: String,
+    #[schemars(description = "Exact version to process, e.g. 1.0.120.")]
+    pub version: String,
+}
+
+#[mcp_tool(name = "remove_processed_project", description = "Unloads a processed project or doc source and deletes its persisted index and cached rustdoc JSON.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct RemoveProcessedProjectTool {
+    #[schemars(description = "Absolute path of the processed project (as passed to process_rust_project).")]
+    pub project_path: String,
+}
+
+#[mcp_tool(name = "reembed_project", description = "Re-embeds a processed project with the currently loaded embedding model, without rebuilding its docs. Needed after the model changed: queries skip projects embedded with another model. Returns once done.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ReembedProjectTool {
+    #[schemars(description = "Key of the processed project, as listed by get_processed_project_list.")]
+    pub project_path: String,
+}
+
+#[mcp_tool(name = "import_rustdoc_json", description = "Indexes an existing rustdoc JSON file (e.g. built in CI with `cargo +nightly rustdoc -- -Z unstable-options --output-format json`) without running cargo: parses, embeds and indexes it under the given project label. Returns once done.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ImportRustdocJsonTool {
+    #[schemars(description = "Absolute path of the rustdoc JSON file.")]
+    pub json_path: String,
+    #[schemars(description = "Key to index the docs under and to pass as project_path when querying, e.g. \"serde-ci\".")]
+    pub project_label: String,
+    #[schemars(description = "Optional: Version of the documented crate, e.g. \"1.0.200\".")]
+    pub version: Option<String>,
+}
+
+#[mcp_tool(name = "get_item_source", description = "Returns the source code of a Rust item (its full definition, e.g. a function body) from a processed project.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct GetItemSourceTool {
+    #[schemars(description = "The full path to the Rust item (e.g., my_crate::module::MyStruct::new).")]
+    pub item_path: String,
+    #[schemars(description = "Absolute path of the Rust project the item belongs to.")]
+    pub project_path: String,
+}
+
+#[mcp_tool(name = "list_crate_items", description = "Lists the items directly inside a module (the crate root by default) or type of a processed project, page by page, to browse a crate's structure. Each entry has its kind, first doc line and number of children.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ListCrateItemsTool {
+    #[schemars(description = "Absolute path of the processed Rust project.")]
+    pub project_path: String,
+    #[schemars(description = "Optional: full path of the module or type to list, e.g. my_crate::net or my_crate::net::Client. Defaults to the crate root.")]
+    pub module_path: Option<String>,
+    #[schemars(description = "Optional: only list items of this kind, e.g. \"function\".")]
+    pub item_type: Option<String>,
+    #[schemars(description = "1-based page number (default 1).")]
+    pub page: Option<usize>,
+    #[schemars(description = "Items per page (default 100, at most 1000).")]
+    pub page_size: Option<usize>,
+}
+
+#[mcp_tool(name = "diff_documentation", description = "Compares the docs of two processed projects, typically two versions of one crate (older versions are kept as <path>@<version>), and lists the items added, removed and changed (docs or signature: kind, generics, implemented trait, deprecation). Use it to answer what changed between versions.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct DiffDocumentationTool {
+    #[schemars(description = "The older project, e.g. /work/app@0.6.3 (as listed by get_processed_project_list).")]
+    pub project_a: String,
+    #[schemars(description = "The newer project, e.g. /work/app.")]
+    pub project_b: String,
+    #[schemars(description = "Optional: at most this many entries per list (default 200); the totals are always complete.")]
+    pub limit: Option<usize>,
+}
+
+#[mcp_tool(name = "export_project_docs", description = "Writes a processed project's docs to a directory, one file per module with its items, signatures and docs, plus an index. Useful as static context for other tools or for offline review.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ExportProjectDocsTool {
+    #[schemars(description = "Absolute path of the processed Rust project.")]
+    pub project_path: String,
+    #[schemars(description = "Directory to write the files to; created if missing. Existing files of the same name are overwritten.")]
+    pub output_dir: String,
+    #[schemars(description = "Optional: \"markdown\" (default) or \"json\".")]
+    pub format: Option<String>,
+    #[schemars(description = "Optional: also export the modules of the project's indexed dependencies. Defaults to false.")]
+    pub include_dependencies: Option<bool>,
+}
+
+#[mcp_tool(name = "process_std_library", description = "Indexes the Rust standard library (std, core and alloc) from the toolchain's rust-docs-json component, installing it if needed, so questions about Vec, Iterator or Option can be answered without a user project. Query it as project \"rust-std\". Takes a few minutes the first time; returns once done.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ProcessStdLibraryTool {}
+
+#[mcp_tool(name = "get_system_info", description = "Reports what the server runs on, to explain slow or failing processing: embedding device (CUDA/Metal/CPU) and model, vector dimension, estimated RAM/VRAM use, cache directory sizes and the installed rustup toolchains.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct GetSystemInfoTool {}
+
+#[mcp_tool(name = "search_keywords", description = "Finds Rust items whose name, path or documentation contains every given word, exactly (no semantic matching). Use it for identifiers and error messages; use query_documentation for questions.")]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SearchKeywordsTool {
+    #[schemars(description = "Words to look for, e.g. \"from_str utf8\". Items must contain all of them; `_` is part of a word.")]
+    pub keywords: String,
+    #[schemars(description = "Optional: Absolute path of a specific Rust project to search. If None, searches all processed projects.")]
+    pub project_path: Option<String>,
+    #[schemars(description = "Number of results to return.")]
+    pub num_results: Option<usize>,
+    #[schemars(description = "Optional: only return items of these kinds, e.g. [\"function\", \"struct\"].")]
+    pub item_types: Option<Vec<String>>,
+    #[schemars(description = "Optional: only return items of this crate, e.g. \"tokio\" in a project indexed with its dependencies. Hyphens and underscores are interchangeable.")]
+    pub crate_name: Option<String>,
+    #[schemars(description = "Optional: only return items at or under this path, e.g. \"tokio::sync\".")]
+    pub module_prefix: Option<String>,
+    #[schemars(description = "Optional: also return #[deprecated] items, which are left out by default. Defaults to false.")]
+    pub include_deprecated: Option<bool>,
+    #[schemars(description = "Optional: only items declared `pub` (default true). Projects processed with private items documented also hold their internals; pass false to search those too.")]
+    pub public_only: Option<bool>,
+    #[schemars(description = "Optional: leave out items whose #[cfg] rules out this platform, e.g. \"x86_64-pc-windows-msvc\", \"linux\" or \"windows\". Conditions on features are not checked.")]
+    pub target: Option<String>,
+    #[schemars(description = "Optional: characters of each item's docs to return as description_snippet (default: the server's search.snippet_length, 300). 0 leaves it out.")]
+    pub snippet_length: Option<usize>,
+    #[schemars(description = "Optional: also return each item's complete docs (full_docs) and declaration (signature). Defaults to false.")]
+    pub include_full_docs: Option<bool>,
 }
 
 // --- Helper Structs and Functions ---
 
+/// Restricts which items a search may return.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchFilter {
+    /// Item kinds to keep, e.g. `["function", "struct"]`; `None` keeps every kind.
+    pub item_types: Option<Vec<String>>,
+    /// Only items of this crate, e.g. a single dependency of a project indexed with its
+    /// dependencies. `tokio-util` and `tokio_util` are the same crate.
+    pub crate_name: Option<String>,
+    /// Only items at or under this path, e.g. `tokio::sync` keeps `tokio::sync::Mutex`.
+    pub module_prefix: Option<String>,
+    /// Also keep `#[deprecated]` items, which are left out by default so that clients aren't
+    /// pointed at APIs on their way out.
+    #[serde(default)]
+    pub include_deprecated: bool,
+    /// Only `pub` items (see `Visibility`), the default: projects documented with
+    /// `--document-private-items` also hold their internals.
+    #[serde(default = "default_public_only")]
+    pub public_only: bool,
+    /// Leave out items whose `#[cfg]` rules out this platform: a target triple
+    /// (`x86_64-pc-windows-msvc`), an OS (`linux`) or a family (`unix`, `windows`); see `Target`.
+    pub target: Option<String>,
+}
+
+fn default_public_only() -> bool { true }
+
+impl Default for SearchFilter {
+    fn default() -> Self {
+        Self { item_types: None, crate_name: None, module_prefix: None, include_deprecated: false, public_only: true, target: None }
+    }
+}
+
+impl SearchFilter {
+    /// Whether the filter keeps every item.
+    pub fn is_empty(&self) -> bool {
+        self.item_types.is_none() && self.crate_name.is_none() && self.module_prefix.is_none() && self.include_deprecated && !self.public_only
+            && self.target.is_none()
+    }
+
+    /// Whether the item's `#[cfg]` allows `target`; always true without one of them.
+    pub fn allows_target(&self, item: &DocItem) -> bool {
+        match (&self.target, &item.cfg) {
+            (Some(target), Some(cfg)) => cfg.may_apply_to(&Target::parse(target)),
+            _ => true,
+        }
+    }
+
+    /// `crate_name` as rustdoc spells crate names, with underscores.
+    pub fn crate_name(&self) -> Option<String> {
+        self.crate_name.as_deref().map(|name| name.trim().replace('-', "_"))
+    }
+
+    /// `module_prefix` without a trailing `::`.
+    pub fn module_prefix(&self) -> Option<&str> {
+        self.module_prefix.as_deref().map(|prefix| prefix.trim().trim_end_matches("::"))
+    }
+
+    pub fn matches(&self, item: &DocItem) -> bool {
+        self.item_types.as_ref().is_none_or(|types| types.iter().any(|t| t.eq_ignore_ascii_case(&item.item_type)))
+            && self.crate_name().is_none_or(|name| item.crate_name == name)
+            && self.module_prefix().is_none_or(|prefix| is_under(&item.full_path_str, prefix))
+            && (self.include_deprecated || !item.is_deprecated())
+            && (!self.public_only || item.is_public())
+            && self.allows_target(item)
+    }
+}
+
+// Whether `full_path` is `prefix` or a path inside it; `tokio::sync` doesn't contain `tokio::sync_io`.
+fn is_under(full_path: &str, prefix: &str) -> bool {
+    full_path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
 #[derive(Clone)]
 pub struct ProjectData {
     pub crate_docs: Arc<CrateDocs>,
+    /// Docs of the project's direct dependencies, one entry per crate (empty unless requested).
+    pub dependency_docs: Vec<Arc<CrateDocs>>,
+    /// Embeddings for items of `crate_docs` and `dependency_docs`, keyed by full path. Long docs
+    /// have one embedding per chunk; see `chunking::chunk_key`.
     pub embeddings: Arc<HashMap<String, Vec<f32>>>,
+    /// ANN index over `embeddings`, used for top-k queries.
+    pub index: Arc<HnswIndex>,
+    /// BM25 index over item names, paths and descriptions, for hybrid queries.
+    pub keywords: Arc<Bm25Index>,
+    /// `incremental::source_fingerprint` of the sources these docs were generated from, if known.
+    pub source_fingerprint: Option<u64>,
+    /// `Embedder::model_id` of the model that produced `embeddings`; vectors are only reused
+    /// across re-processing runs with the same model.
+    pub embedding_model: Option<String>,
+    /// Package version from Cargo.toml when the docs were generated (`None` for non-Cargo sources).
+    pub version: Option<String>,
+    /// Targets the docs were generated for; watch-triggered refreshes re-process with the same ones.
+    pub doc_targets: DocTargets,
+    /// Length of the vectors in `embeddings`; `None` if nothing was embedded.
+    pub embedding_dimension: Option<usize>,
+    /// Keys of `embeddings` whose text exceeded the model's `max_length` and was truncated.
+    pub truncated_embeddings: Arc<HashSet<String>>,
+    /// `incremental::embedded_text_hash` of each embedded item, by full path; its vectors are
+    /// reused by the next run only if the item's texts still hash the same.
+    pub embedded_text_hashes: Arc<HashMap<String, u64>>,
+    /// Full path of each item by its `DocItem::reexports` paths.
+    pub reexports: Arc<HashMap<String, String>>,
+    // Every item as (0 for `crate_docs`, else 1 + its index in `dependency_docs`; key), sorted by
+    // full path, so `resources/list` pages don't re-sort the project.
+    sorted_items: Arc<Vec<(usize, String)>>,
+}
+
+// A filtered ANN query widens its candidate set by this factor until it has `k` matches.
+const FILTERED_FETCH_GROWTH: usize = 4;
+// Items per `resources/list` page.
+const RESOURCE_PAGE_SIZE: usize = 500;
+// Each side of a hybrid query fetches this many times `k` candidates before the scores are merged.
+const HYBRID_CANDIDATE_FACTOR: usize = 4;
+// ANN queries fetch this many times `k` chunk hits, since several can belong to the same item.
+const CHUNK_CANDIDATE_FACTOR: usize = 2;
+
+impl ProjectData {
+    /// Bundles processed docs and builds the ANN index over the embeddings.
+    pub fn new(crate_docs: CrateDocs, dependency_docs: Vec<CrateDocs>, embeddings: HashMap<String, Vec<f32>>) -> Self {
+        let index = HnswIndex::build(HnswParams::default(), &embeddings);
+        Self::from_parts(crate_docs, dependency_docs, embeddings, index)
+    }
+
+    /// Like `new`, with an already built ANN index (e.g. loaded from disk).
+    pub fn from_parts(crate_docs: CrateDocs, dependency_docs: Vec<CrateDocs>, embeddings: HashMap<String, Vec<f32>>, index: HnswIndex) -> Self {
+        let keywords = Bm25Index::build(
+            crate_docs.items.iter()
+                .chain(dependency_docs.iter().flat_map(|docs| docs.items.iter()))
+                .map(|(full_path, item)| (full_path.clone(), keyword_text(item))),
+        );
+        let embedding_dimension = embeddings.values().next().map(|v| v.len());
+        let reexports = crate_docs.items.values()
+            .chain(dependency_docs.iter().flat_map(|docs| docs.items.values()))
+            .flat_map(|item| item.reexports.iter().map(|path| (path.clone(), item.full_path_str.clone())))
+            .collect();
+        let mut sorted_items: Vec<(usize, String)> = std::iter::once(&crate_docs).chain(&dependency_docs)
+            .enumerate()
+            .flat_map(|(position, docs)| docs.items.keys().map(move |key| (position, key.clone())))
+            .collect();
+        sorted_items.sort_by(|a, b| (&a.1, a.0).cmp(&(&b.1, b.0)));
+        Self {
+            crate_docs: Arc::new(crate_docs),
+            dependency_docs: dependency_docs.into_iter().map(Arc::new).collect(),
+            embeddings: Arc::new(embeddings),
+            index: Arc::new(index),
+            keywords: Arc::new(keywords),
+            source_fingerprint: None,
+            embedding_model: None,
+            version: None,
+            doc_targets: DocTargets::default(),
+            embedding_dimension,
+            truncated_embeddings: Arc::new(HashSet::new()),
+            embedded_text_hashes: Arc::new(HashMap::new()),
+            reexports: Arc::new(reexports),
+            sorted_items: Arc::new(sorted_items),
+        }
+    }
+
+    pub fn with_source_fingerprint(mut self, source_fingerprint: Option<u64>) -> Self {
+        self.source_fingerprint = source_fingerprint;
+        self
+    }
+
+    pub fn with_embedding_model(mut self, embedding_model: Option<String>) -> Self {
+        self.embedding_model = embedding_model;
+        self
+    }
+
+    pub fn with_version(mut self, version: Option<String>) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn with_doc_targets(mut self, doc_targets: DocTargets) -> Self {
+        self.doc_targets = doc_targets;
+        self
+    }
+
+    pub fn with_truncated_embeddings(mut self, truncated_embeddings: HashSet<String>) -> Self {
+        self.truncated_embeddings = Arc::new(truncated_embeddings);
+        self
+    }
+
+    pub fn with_embedded_text_hashes(mut self, embedded_text_hashes: HashMap<String, u64>) -> Self {
+        self.embedded_text_hashes = Arc::new(embedded_text_hashes);
+        self
+    }
+
+    /// Checks that a query vector from `model_id` can be compared with this project's vectors:
+    /// same model (when recorded) and same dimension. Projects without vectors always pass.
+    pub fn check_embedding_model(&self, project_path: &str, model_id: &str, dimension: usize) -> Result<(), AppError> {
+        let Some(project_dimension) = self.embedding_dimension else {
+            return Ok(());
+        };
+        let same_model = self.embedding_model.as_deref().is_none_or(|m| m == model_id);
+        if same_model && project_dimension == dimension {
+            return Ok(());
+        }
+        Err(AppError::EmbeddingModelMismatch {
+            project_path: project_path.to_string(),
+            project_model: self.embedding_model.clone().unwrap_or_else(|| format!("unknown ({} dimensions)", project_dimension)),
+            current_model: model_id.to_string(),
+        })
+    }
+
+    /// Whether this project's version is `pin`, or starts with it at a component boundary
+    /// ("0.6" matches 0.6.3 but not 0.60.0). `*` matches every version.
+    pub fn matches_version(&self, pin: &str) -> bool {
+        if pin == "*" {
+            return true;
+        }
+        self.version.as_deref().is_some_and(|v| v == pin || v.strip_prefix(pin).is_some_and(|rest| rest.starts_with('.')))
+    }
+
+    /// The `k` items most similar to `query_embedding` that pass `filter`, with their cosine
+    /// similarity, best first. Items with several chunk or example embeddings score as the best one.
+    pub fn search(&self, query_embedding: &[f32], k: usize, filter: &SearchFilter) -> Vec<(&DocItem, f32)> {
+        self.item_scores(query_embedding, k, filter)
+            .into_iter()
+            .filter_map(|(full_path, score)| self.get_item(full_path).map(|item| (item, score)))
+            .collect()
+    }
+
+    // ANN hits max-pooled per item, best first. Over-fetches so that items with many chunks
+    // don't crowd out the others, and keeps widening the search while the filter rejects too
+    // many hits.
+    fn item_scores(&self, query_embedding: &[f32], k: usize, filter: &SearchFilter) -> Vec<(&str, f32)> {
+        let mut fetch = k.saturating_mul(CHUNK_CANDIDATE_FACTOR);
+        loop {
+            let mut seen = HashSet::new();
+            let scores: Vec<(&str, f32)> = self.index.search(query_embedding, fetch)
+                .into_iter()
+                .map(|(key, score)| (chunking::item_path(key), score))
+                .filter(|(full_path, _)| seen.insert(*full_path))
+                .filter(|(full_path, _)| filter.is_empty() || self.get_item(full_path).is_some_and(|item| filter.matches(item)))
+                .take(k)
+                .collect();
+            if scores.len() >= k || fetch >= self.embeddings.len() {
+                return scores;
+            }
+            fetch = fetch.saturating_mul(FILTERED_FETCH_GROWTH);
+        }
+    }
+
+    /// The `k` items whose embeddings are closest to the first doc chunk of `full_path`, excluding
+    /// the item itself, best first. `None` if the item has no embedding.
+    pub fn similar_items(&self, full_path: &str, k: usize, filter: &SearchFilter) -> Option<Vec<(&DocItem, f32)>> {
+        let query_embedding = self.embeddings.get(full_path)?;
+        let mut similar: Vec<(&DocItem, f32)> = self.item_scores(query_embedding, k.saturating_add(1), filter)
+            .into_iter()
+            .filter(|(path, _)| *path != full_path)
+            .filter_map(|(path, score)| self.get_item(path).map(|item| (item, score)))
+            .collect();
+        similar.truncate(k);
+        Some(similar)
+    }
+
+    /// Hybrid search: `(1 - keyword_weight) * cosine + keyword_weight * bm25`, with BM25 scores
+    /// scaled to 0..1 by the best keyword match in this project. Items found by only one side get
+    /// their cosine similarity computed exactly (or 0 without an embedding) and a BM25 score of 0.
+    pub fn hybrid_search(&self, query_embedding: &[f32], query_text: &str, k: usize, keyword_weight: f32, filter: &SearchFilter) -> Vec<(&DocItem, f32)> {
+        let keyword_weight = keyword_weight.clamp(0.0, 1.0);
+        if keyword_weight == 0.0 {
+            return self.search(query_embedding, k, filter);
+        }
+        let candidates = k.saturating_mul(HYBRID_CANDIDATE_FACTOR);
+        let mut vector_scores: HashMap<&str, f32> = self.item_scores(query_embedding, candidates, filter).into_iter().collect();
+        // BM25 search is cheap, so a filtered query simply ranks every keyword match.
+        let keyword_candidates = if filter.is_empty() { candidates } else { self.keywords.len() };
+        let keyword_hits: Vec<(&str, f32)> = self.keywords.search(query_text, keyword_candidates)
+            .into_iter()
+            .filter(|(full_path, _)| filter.is_empty() || self.get_item(full_path).is_some_and(|item| filter.matches(item)))
+            .take(candidates)
+            .collect();
+        let max_keyword_score = keyword_hits.first().map(|(_, score)| *score).unwrap_or(0.0);
+        let keyword_scores: HashMap<&str, f32> = keyword_hits.into_iter()
+            .map(|(full_path, score)| (full_path, if max_keyword_score > 0.0 { score / max_keyword_score } else { 0.0 }))
+            .collect();
+        for full_path in keyword_scores.keys() {
+            vector_scores.entry(full_path).or_insert_with(|| {
+                chunking::item_embeddings(&self.embeddings, full_path)
+                    .chain(chunking::example_embeddings(&self.embeddings, full_path))
+                    .map(|embedding| cosine_similarity(query_embedding, embedding))
+                    .reduce(f32::max)
+                    .unwrap_or(0.0)
+            });
+        }
+
+        let mut scored: Vec<(&DocItem, f32)> = vector_scores.into_iter()
+            .filter_map(|(full_path, cosine)| {
+                let keyword = keyword_scores.get(full_path).copied().unwrap_or(0.0);
+                let item = self.get_item(full_path)?;
+                Some((item, (1.0 - keyword_weight) * cosine + keyword_weight * keyword))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(k);
+        scored
+    }
+
+    /// Looks an item up in the project crate first, then in its dependencies. Re-export paths
+    /// resolve to the re-exported item, also for members (`app::Client::new` when `app::Client`
+    /// re-exports `app::net::Client`), and to the defining crate's item when it is indexed.
+    pub fn get_item(&self, full_path: &str) -> Option<&DocItem> {
+        if let Some(item) = self.find_item(full_path) {
+            return Some(item);
+        }
+        let (parent, member) = full_path.rsplit_once("::")?;
+        let parent = self.find_item(parent)?;
+        self.lookup_item(&format!("{}::{}", parent.full_path_str, member))
+    }
+
+    // Exact paths and re-export paths, following a stand-in to the item it re-exports.
+    fn find_item(&self, full_path: &str) -> Option<&DocItem> {
+        let item = self.lookup_item(full_path)
+            .or_else(|| self.reexports.get(full_path).and_then(|canonical| self.lookup_item(canonical)))?;
+        match &item.reexport_of {
+            Some(original) => self.lookup_item(original).or(Some(item)),
+            None => Some(item),
+        }
+    }
+
+    fn lookup_item(&self, full_path: &str) -> Option<&DocItem> {
+        self.crate_docs.items.get(full_path)
+            .or_else(|| self.dependency_docs.iter().find_map(|docs| docs.items.get(full_path)))
+    }
+
+    /// Number of items with at least one embedding.
+    pub fn embedded_item_count(&self) -> usize {
+        chunking::embedded_item_count(self.embeddings.keys())
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.crate_docs.items.len() + self.dependency_docs.iter().map(|docs| docs.items.len()).sum::<usize>()
+    }
+
+    /// Up to `limit` items of `crate_docs` and `dependency_docs` in full path order, skipping the
+    /// first `offset`.
+    pub fn items_by_path(&self, offset: usize, limit: usize) -> impl Iterator<Item = &DocItem> {
+        self.sorted_items.get(offset..).unwrap_or_default().iter()
+            .take(limit)
+            .map(|(docs, key)| match docs {
+                0 => &self.crate_docs.items[key],
+                _ => &self.dependency_docs[docs - 1].items[key],
+            })
+    }
+}
+
+// What the keyword index sees for an item: its name (for exact identifier matches), paths and docs.
+fn keyword_text(item: &DocItem) -> String {
+    format!("{} {} {} {}", item.name, item.full_path_str, item.reexports.join(" "), item.description.as_deref().unwrap_or(""))
 }
 
-// Struct for query results
-#[derive(Debug, Serialize, JsonSchema)]
-struct QueryDocResultItem {
+// Sources returned by answer_question when the caller doesn't say.
+const DEFAULT_ANSWER_SOURCES: usize = 5;
+// Longer docs are cut here (on a char boundary) so a handful of sources fit a prompt.
+const MAX_EXCERPT_CHARS: usize = 4000;
+
+// What answer_question returns: numbered sources with their full docs, and the same sources as
+// one prompt-ready block.
+#[derive(Debug, Serialize)]
+struct AnswerContext {
+    question: String,
+    sources: Vec<AnswerSource>,
+    /// `[1] path (kind)` headers followed by the docs, one source after another.
+    context: String,
+    instructions: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct AnswerSource {
+    /// `[n]`, as it should appear in the answer.
+    citation: String,
     project_path: String,
     item_full_path: String,
     item_type: String,
-    description_snippet: Option<String>,
+    generics: Option<String>,
     score: f32,
+    excerpt: String,
+    truncated: bool,
+}
+
+impl AnswerContext {
+    fn new(question: &str, hits: Vec<QueryHit>) -> Self {
+        let sources: Vec<AnswerSource> = hits.into_iter().enumerate().map(|(i, QueryHit { project_path, item, score, .. })| {
+            let docs = item.description.as_deref().map(str::trim).filter(|d| !d.is_empty()).unwrap_or("(no documentation)");
+            let excerpt: String = docs.chars().take(MAX_EXCERPT_CHARS).collect();
+            AnswerSource {
+                citation: format!("[{}]", i + 1),
+                truncated: excerpt.len() < docs.len(),
+                project_path,
+                item_full_path: item.full_path_str,
+                item_type: item.item_type,
+                generics: item.generics.as_ref().map(|g| g.render()),
+                score,
+                excerpt,
+            }
+        }).collect();
+        let context = sources.iter()
+            .map(|source| {
+                let generics = source.generics.as_deref().map(|g| format!(" {}", g)).unwrap_or_default();
+                format!("{} {}{} ({})\n{}", source.citation, source.item_full_path, generics, source.item_type, source.excerpt)
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let instructions = if sources.is_empty() {
+            "No relevant documentation was found; say so rather than guessing."
+        } else {
+            "Answer from these sources only, citing each claim with its [n]. If they don't cover the question, say so."
+        };
+        Self { question: question.to_string(), sources, context, instructions }
+    }
 }
 
 // Cosine similarity function
-fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
+pub fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
     if v1.is_empty() || v2.is_empty() || v1.len() != v2.len() {
         log::warn!("Cosine similarity: invalid vectors. v1_len={}, v2_len={}", v1.len(), v2.len());
         return 0.0;
@@ -90,14 +744,55 @@ fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
 
 // --- AppState Definition ---
 pub struct AppState {
+    // Accessed through `projects()`, which loads the persisted projects first.
     processed_projects: Mutex<HashMap<String, ProjectData>>,
-    http_client: reqwest::Client,
-    rustdoc_output_dir: PathBuf,
+    projects_loaded: OnceCell<()>,
+    pub store: ProjectStore,
+    /// Vectors of every doc text embedded so far, so repeated text isn't embedded again.
+    pub embedding_cache: EmbeddingCache,
+    pub http_client: reqwest::Client,
+    /// The cache directory this run keeps its files in. A changed `storage.cache_dir` only
+    /// applies from the next start, so use this rather than the live config.
+    pub cache_dir: PathBuf,
+    pub rustdoc_output_dir: PathBuf,
+    pub journal_dir: PathBuf,
+    pub config: ConfigHandle,
+    pub doc_sources: DocSourceRegistry,
+    pub jobs: JobManager,
+    /// File watchers of the projects in watch mode.
+    pub watches: crate::watch::WatchRegistry,
+    /// Result of the startup compatibility checks; None while they are still running.
+    pub compat_report: Mutex<Option<crate::compat::CompatReport>>,
+    pub analytics: AnalyticsStore,
+    /// Results of `query_documentation` calls with more than one page, behind their cursors.
+    pub query_results: QueryResultCache<QueryDocResultItem>,
+    /// Why the MCP server isn't running, if it failed to start.
+    pub mcp_server_error: Mutex<Option<AppError>>,
+    // The MCP server task started by `spawn_mcp_server`, if any.
+    mcp_server: Mutex<Option<McpServerHandle>>,
+    state_events: broadcast::Sender<StateEvent>,
 }
 
 impl AppState {
-    pub fn new(rustdoc_output_dir: PathBuf) -> Self {
+    /// Projects saved in `store` by earlier runs are not read here but on the first `projects()` call,
+    /// so startup isn't blocked on deserializing large indexes.
+    pub fn new(config: ConfigHandle, cache_dir: PathBuf, rustdoc_output_dir: PathBuf, journal_dir: PathBuf, analytics: AnalyticsStore, store: ProjectStore, embedding_cache: EmbeddingCache) -> Self {
         Self {
+            analytics,
+            store,
+            embedding_cache,
+            projects_loaded: OnceCell::new(),
+            mcp_server_error: Mutex::new(None),
+            mcp_server: Mutex::new(None),
+            query_results: QueryResultCache::default(),
+            jobs: JobManager::new(config.clone()),
+            watches: Default::default(),
+            compat_report: Mutex::new(None),
+            state_events: broadcast::channel(STATE_EVENT_CHANNEL_CAPACITY).0,
+            config,
+            cache_dir,
+            journal_dir,
+            doc_sources: DocSourceRegistry::default(),
             processed_projects: Mutex::new(HashMap::new()),
             http_client: reqwest::Client::builder()
                 .timeout(std::time::Duration::from_secs(60))
@@ -106,9 +801,237 @@ impl AppState {
             rustdoc_output_dir,
         }
     }
+
+    /// The processed projects, keyed by project path. Loads the persisted projects on first use.
+    pub async fn projects(&self) -> MutexGuard<'_, HashMap<String, ProjectData>> {
+        self.projects_loaded.get_or_init(|| async {
+            let store = self.store.clone();
+            let persisted = tokio::task::spawn_blocking(move || store.load_all()).await.unwrap_or_else(|e| {
+                log::error!("Loading persisted projects panicked: {:?}", e);
+                Vec::new()
+            });
+            let mut projects_guard = self.processed_projects.lock().await;
+            for (project_path, project_data) in persisted {
+                // Never overwrite data processed during this run.
+                projects_guard.entry(project_path).or_insert(project_data);
+            }
+        }).await;
+        self.processed_projects.lock().await
+    }
+
+    /// Stores processed project data (replacing any previous data for the path), persists it and
+    /// notifies listeners. Returns the total number of processed projects.
+    pub async fn insert_project(&self, project_path: String, project_data: ProjectData) -> usize {
+        let item_count = project_data.item_count();
+        let embedded_count = project_data.embedded_item_count();
+
+        // Persisting failures are logged only: the data is still usable for this session.
+        let store = self.store.clone();
+        let (save_path, save_data) = (project_path.clone(), project_data.clone());
+        match tokio::task::spawn_blocking(move || store.save(&save_path, &save_data)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log::error!("Failed to persist project {}: {:?}", project_path, e),
+            Err(e) => log::error!("Persisting project {} panicked: {:?}", project_path, e),
+        }
+
+        let (replaced, total, total_items, total_embeddings) = {
+            let mut projects_guard = self.projects().await;
+            let replaced = projects_guard.insert(project_path.clone(), project_data).is_some();
+            let total_items = projects_guard.values().map(|p| p.item_count()).sum();
+            let total_embeddings = projects_guard.values().map(|p| p.embeddings.len()).sum();
+            (replaced, projects_guard.len(), total_items, total_embeddings)
+        };
+        self.analytics.record_snapshot(total, total_items, total_embeddings);
+        self.emit_state_event(if replaced {
+            StateEvent::IndexRefreshed { project_path, item_count, embedded_count }
+        } else {
+            StateEvent::ProjectAdded { project_path, item_count, embedded_count }
+        });
+        total
+    }
+
+    /// An item's docs, with the description rendered as asked.
+    pub async fn item_docs(&self, project_path: &str, item_path: &str, mode: RenderMode) -> Result<DocItem, AppError> {
+        let projects_guard = self.projects().await;
+        let project_data = projects_guard.get(project_path)
+            .ok_or_else(|| AppError::ProjectNotFound { project_path: project_path.to_string() })?;
+        let item = project_data.get_item(item_path)
+            .ok_or_else(|| AppError::InvalidArgument { message: format!("Item '{}' not found in project '{}'", item_path, project_path) })?;
+        Ok(render::rendered_item(item, mode))
+    }
+
+    /// The source code of an item, read from the project's files at the span rustdoc recorded.
+    pub async fn item_source(&self, project_path: &str, item_path: &str) -> Result<ItemSource, AppError> {
+        let span = {
+            let projects_guard = self.projects().await;
+            let project_data = projects_guard.get(project_path)
+                .ok_or_else(|| AppError::ProjectNotFound { project_path: project_path.to_string() })?;
+            let item = project_data.get_item(item_path)
+                .ok_or_else(|| AppError::InvalidArgument { message: format!("Item '{}' not found in project '{}'", item_path, project_path) })?;
+            item.span.clone()
+                .ok_or_else(|| AppError::InvalidArgument { message: format!("rustdoc recorded no source location for '{}'", item_path) })?
+        };
+        let (project_dir, item_path) = (PathBuf::from(project_path), item_path.to_string());
+        tokio::task::spawn_blocking(move || crate::rustdoc_processor::read_item_source(&project_dir, &item_path, &span))
+            .await
+            .map_err(|e| AppError::internal(format!("Reading item source panicked: {}", e)))?
+            .map_err(|e| AppError::internal(format!("{:#}", e)))
+    }
+
+    /// Size, freshness and query latency of a processed project.
+    pub async fn project_stats(&self, project_path: &str) -> Result<ProjectStats, AppError> {
+        let project_data = self.projects().await.get(project_path).cloned()
+            .ok_or_else(|| AppError::ProjectNotFound { project_path: project_path.to_string() })?;
+        let file_info = self.store.file_info(project_path);
+        let (query_count, average_query_latency_ms) = self.analytics.project_query_latency(project_path);
+        Ok(ProjectStats {
+            project_path: project_path.to_string(),
+            version: project_data.version.clone(),
+            items_indexed: project_data.item_count(),
+            embeddings_stored: project_data.embeddings.len(),
+            last_processed_ms: file_info.and_then(|(_, modified)| modified.duration_since(std::time::UNIX_EPOCH).ok()).map(|d| d.as_millis() as u64),
+            query_count,
+            average_query_latency_ms,
+            index_size_bytes: file_info.map_or(0, |(bytes, _)| bytes),
+        })
+    }
+
+    /// A page of the items directly below `module_path` in a project; see `browse::list_items`.
+    pub async fn list_items(&self, project_path: &str, module_path: Option<&str>, item_type: Option<&str>, page: usize, page_size: usize) -> Result<ItemListing, AppError> {
+        let project_data = self.projects().await.get(project_path).cloned()
+            .ok_or_else(|| AppError::ProjectNotFound { project_path: project_path.to_string() })?;
+        browse::list_items(project_path, &project_data, module_path, item_type, page, page_size)
+    }
+
+    /// What changed between the docs of two processed projects; see `diff::diff_projects`.
+    pub async fn diff_projects(&self, project_a: &str, project_b: &str, limit: usize) -> Result<DocDiff, AppError> {
+        let (a, b) = {
+            let projects_guard = self.projects().await;
+            let get = |project_path: &str| projects_guard.get(project_path).cloned()
+                .ok_or_else(|| AppError::ProjectNotFound { project_path: project_path.to_string() });
+            (get(project_a)?, get(project_b)?)
+        };
+        Ok(diff::diff_projects(project_a, &a, project_b, &b, limit))
+    }
+
+    /// Writes a project's docs to `output_dir`, one file per module; see `export::export_project`.
+    pub async fn export_docs(&self, project_path: &str, format: ExportFormat, output_dir: &Path, include_dependencies: bool) -> Result<ExportSummary, AppError> {
+        let project_data = self.projects().await.get(project_path).cloned()
+            .ok_or_else(|| AppError::ProjectNotFound { project_path: project_path.to_string() })?;
+        let (project_path, output_dir) = (project_path.to_string(), output_dir.to_path_buf());
+        tokio::task::spawn_blocking(move || export::export_project(&project_path, &project_data, format, &output_dir, include_dependencies))
+            .await
+            .map_err(|e| AppError::internal(format!("Exporting docs panicked: {}", e)))?
+    }
+
+    /// Exact keyword search over the stored docs of every processed project; see
+    /// `DocStorage::keyword_search`.
+    pub async fn keyword_search(&self, keywords: &str, project_path: Option<&str>, filter: &SearchFilter, limit: usize) -> Result<Vec<KeywordHit>, AppError> {
+        let docs = self.store.docs().clone();
+        let (keywords, project_path, filter) = (keywords.to_string(), project_path.map(str::to_string), filter.clone());
+        tokio::task::spawn_blocking(move || docs.keyword_search(&keywords, project_path.as_deref(), &filter, limit))
+            .await
+            .map_err(|e| AppError::internal(format!("Keyword search panicked: {}", e)))?
+            .map_err(|e| AppError::internal(format!("Keyword search failed: {:#}", e)))
+    }
+
+    /// Adds a query to the history in the background, unless `search.history_size` is 0.
+    pub fn record_query_history(&self, entry: QueryHistoryEntry) {
+        let max_entries = self.config.current().search.history_size;
+        if max_entries == 0 {
+            return;
+        }
+        let docs = self.store.docs().clone();
+        tokio::task::spawn_blocking(move || {
+            // Best effort: a failed write must not fail the query.
+            if let Err(e) = docs.record_query(&entry, max_entries) {
+                log::warn!("Failed to record query '{}' in the history: {:#}", entry.query, e);
+            }
+        });
+    }
+
+    /// The newest `limit` recorded queries, newest first; see `DocStorage::query_history`.
+    pub async fn query_history(&self, project_path: Option<&str>, limit: usize) -> Result<Vec<QueryHistoryEntry>, AppError> {
+        let docs = self.store.docs().clone();
+        let project_path = project_path.map(str::to_string);
+        tokio::task::spawn_blocking(move || docs.query_history(project_path.as_deref(), limit))
+            .await
+            .map_err(|e| AppError::internal(format!("Reading the query history panicked: {}", e)))?
+            .map_err(|e| AppError::internal(format!("Failed to read the query history: {:#}", e)))
+    }
+
+    /// Deletes the query history; returns the number of entries removed.
+    pub async fn clear_query_history(&self) -> Result<usize, AppError> {
+        let docs = self.store.docs().clone();
+        tokio::task::spawn_blocking(move || docs.clear_query_history())
+            .await
+            .map_err(|e| AppError::internal(format!("Clearing the query history panicked: {}", e)))?
+            .map_err(|e| AppError::internal(format!("Failed to clear the query history: {:#}", e)))
+    }
+
+    /// Unloads a processed project, deletes its persisted copy and its cached rustdoc JSON (of every
+    /// documented target), and notifies listeners. Dependency JSON is kept, since other projects
+    /// may share it. Returns the number of projects left.
+    pub async fn remove_project(&self, project_path: &str) -> Result<usize, AppError> {
+        let json_crate = {
+            let projects_guard = self.projects().await;
+            let project_data = projects_guard.get(project_path)
+                .ok_or_else(|| AppError::ProjectNotFound { project_path: project_path.to_string() })?;
+            let crate_name = &project_data.crate_docs.crate_name;
+            // Two checkouts of the same crate share the JSON file name.
+            let shared = projects_guard.iter().any(|(path, other)| path != project_path && &other.crate_docs.crate_name == crate_name);
+            (!shared).then(|| crate_name.clone())
+        };
+
+        // Delete the files first: if that fails the project stays loaded and the removal can be retried.
+        let store = self.store.clone();
+        let remove_path = project_path.to_string();
+        let rustdoc_output_dir = self.rustdoc_output_dir.clone();
+        tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+            store.remove(&remove_path)?;
+            let json_paths = json_crate.map(|crate_name| crate::rustdoc_processor::generated_json_files(&rustdoc_output_dir, &crate_name));
+            for json_path in json_paths.into_iter().flatten() {
+                std::fs::remove_file(&json_path)
+                    .map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", json_path.display(), e))?;
+            }
+            Ok(())
+        })
+        .await
+        .map_err(|e| AppError::internal(format!("Removing project files panicked: {}", e)))??;
+
+        let (total, total_items, total_embeddings) = {
+            let mut projects_guard = self.projects().await;
+            projects_guard.remove(project_path);
+            let total_items = projects_guard.values().map(|p| p.item_count()).sum();
+            let total_embeddings = projects_guard.values().map(|p| p.embeddings.len()).sum();
+            (projects_guard.len(), total_items, total_embeddings)
+        };
+        log::info!("Removed project {}", project_path);
+        if self.watches.remove(project_path) {
+            self.emit_state_event(StateEvent::WatchChanged { project_path: project_path.to_string(), watching: false });
+        }
+        self.analytics.record_snapshot(total, total_items, total_embeddings);
+        self.analytics.forget_project(project_path);
+        self.emit_state_event(StateEvent::ProjectRemoved { project_path: project_path.to_string() });
+        Ok(total)
+    }
+
+    /// Broadcasts a state change to every subscriber (the Tauri windows, via `subscribe_state_events`).
+    pub fn emit_state_event(&self, event: StateEvent) {
+        log::debug!("State event: {:?}", event);
+        // An error only means nobody is subscribed yet.
+        let _ = self.state_events.send(event);
+    }
+
+    pub fn subscribe_state_events(&self) -> broadcast::Receiver<StateEvent> {
+        self.state_events.subscribe()
+    }
 }
 
 // --- MCP Server Handler ---
+/// Handles requests of both transports. On HTTP, `mcp_http` checks `server.auth_required` on every
+/// request before it is handed to the handler, and nothing else can reach it; stdio isn't
+/// checked, since its client launched the process itself.
 pub struct MyMcpServerHandler {
     app_state: Arc<AppState>,
 }
@@ -119,6 +1042,30 @@ impl MyMcpServerHandler {
     }
 }
 
+/// Tool errors carry the stable `AppError::code` in front of the message, so MCP clients can react
+/// to `[embedder_loading]` or `[project_not_found]` the way the frontend does, and use the matching
+/// MCP error kind: unknown resources, bad arguments, or a failure on the server's side.
+impl From<AppError> for CallToolError {
+    fn from(error: AppError) -> Self {
+        let message = format!("[{}] {}", error.code(), error);
+        match error {
+            AppError::ProjectNotFound { .. } | AppError::PathNotFound { .. } | AppError::JobNotFound { .. } => CallToolError::resource_not_found(message),
+            AppError::InvalidArgument { .. }
+            | AppError::UntrustedProject { .. }
+            | AppError::Unauthorized { .. }
+            | AppError::EmbeddingModelMismatch { .. } => CallToolError::invalid_arguments(message),
+            _ => CallToolError::internal_error(message),
+        }
+    }
+}
+
+impl MyMcpServerHandler {
+    // The `k` best hits for `query` across the processed projects (or just `project_path`), best first.
+    async fn search(&self, opts: QueryOptions) -> Result<Vec<QueryHit>, CallToolError> {
+        pipeline::query_docs(&self.app_state, &opts).await.map_err(CallToolError::from)
+    }
+}
+
 #[async_trait]
 impl ServerHandler for MyMcpServerHandler {
     async fn handle_list_tools_request(
@@ -131,12 +1078,181 @@ impl ServerHandler for MyMcpServerHandler {
                 ProcessRustProjectTool::tool(),
                 QueryDocumentationTool::tool(),
                 GetRawDocumentationTool::tool(),
+                FindSimilarItemsTool::tool(),
+                ProcessDocSourceTool::tool(),
+                CancelProcessingTool::tool(),
+                ProcessCratesIoCrateTool::tool(),
+                ProcessGitRepositoryTool::tool(),
+                ProcessDocsRsCrateTool::tool(),
+                RemoveProcessedProjectTool::tool(),
+                GetItemSourceTool::tool(),
+                SearchKeywordsTool::tool(),
+                ListCrateItemsTool::tool(),
+                AnswerQuestionTool::tool(),
+                DiffDocumentationTool::tool(),
+                ExportProjectDocsTool::tool(),
+                ProcessStdLibraryTool::tool(),
+                GetSystemInfoTool::tool(),
+                ReembedProjectTool::tool(),
+                ImportRustdocJsonTool::tool(),
             ],
             meta: None,
             next_cursor: None,
         })
     }
 
+    // The query history, then every processed item, as `rustdoc://<project>/<item path>`, ordered
+    // by project then path. The cursor is the offset of the next item.
+    async fn handle_list_resources_request(
+        &self,
+        request: ListResourcesRequest,
+        _runtime: &dyn McpServer,
+    ) -> Result<ListResourcesResult, RpcError> {
+        let offset = match request.params.and_then(|p| p.cursor) {
+            Some(cursor) => cursor.parse::<usize>()
+                .map_err(|_| RpcError::invalid_params().with_message(format!("Invalid cursor '{}'", cursor)))?,
+            None => 0,
+        };
+        let projects_guard = self.app_state.projects().await;
+        let mut project_paths: Vec<&String> = projects_guard.keys().collect();
+        project_paths.sort();
+        let total: usize = projects_guard.values().map(|p| p.item_count()).sum();
+        // Skips whole projects, then seeks into the one the page starts in.
+        let mut resources: Vec<Resource> = Vec::new();
+        let mut skip = offset;
+        for project_path in project_paths {
+            if resources.len() == RESOURCE_PAGE_SIZE {
+                break;
+            }
+            let project_data = &projects_guard[project_path];
+            let item_count = project_data.item_count();
+            if skip >= item_count {
+                skip -= item_count;
+                continue;
+            }
+            resources.extend(project_data.items_by_path(skip, RESOURCE_PAGE_SIZE - resources.len()).map(|item| Resource {
+                uri: resources::item_uri(project_path, &item.full_path_str),
+                name: item.full_path_str.clone(),
+                description: Some(format!("{} in {}", item.item_type, project_path)),
+                mime_type: Some(resources::RESOURCE_MIME_TYPE.to_string()),
+                annotations: None,
+                size: None,
+            }));
+            skip = 0;
+        }
+        let next_offset = offset + resources.len();
+        if offset == 0 {
+            resources.insert(0, Resource {
+                uri: resources::QUERY_HISTORY_URI.to_string(),
+                name: "Query history".to_string(),
+                description: Some(format!("The last {} queries, to re-run past searches", resources::QUERY_HISTORY_RESOURCE_LIMIT)),
+                mime_type: Some(resources::QUERY_HISTORY_MIME_TYPE.to_string()),
+                annotations: None,
+                size: None,
+            });
+        }
+        Ok(ListResourcesResult {
+            resources,
+            meta: None,
+            next_cursor: (next_offset < total).then(|| next_offset.to_string()),
+        })
+    }
+
+    async fn handle_read_resource_request(
+        &self,
+        request: ReadResourceRequest,
+        _runtime: &dyn McpServer,
+    ) -> Result<ReadResourceResult, RpcError> {
+        let uri = request.params.uri;
+        if uri == resources::QUERY_HISTORY_URI {
+            let history = self.app_state.query_history(None, resources::QUERY_HISTORY_RESOURCE_LIMIT).await
+                .map_err(|e| RpcError::internal_error().with_message(e.to_string()))?;
+            return Ok(ReadResourceResult {
+                contents: vec![ReadResourceResultContentsItem::TextResourceContents(TextResourceContents {
+                    text: serde_json::to_string_pretty(&history).unwrap_or_default(),
+                    mime_type: Some(resources::QUERY_HISTORY_MIME_TYPE.to_string()),
+                    uri,
+                })],
+                meta: None,
+            });
+        }
+        let (project_path, item_path) = resources::parse_item_uri(&uri)
+            .ok_or_else(|| RpcError::invalid_params().with_message(format!("Not a rustdoc:// item URI: {}", uri)))?;
+        let projects_guard = self.app_state.projects().await;
+        let project_data = projects_guard.get(&project_path)
+            .ok_or_else(|| RpcError::invalid_params().with_message(AppError::ProjectNotFound { project_path: project_path.clone() }.to_string()))?;
+        let item = project_data.get_item(&item_path)
+            .ok_or_else(|| RpcError::invalid_params().with_message(format!("Item '{}' not found in project '{}'", item_path, project_path)))?;
+        Ok(ReadResourceResult {
+            contents: vec![ReadResourceResultContentsItem::TextResourceContents(TextResourceContents {
+                text: resources::render_item(item),
+                mime_type: Some(resources::RESOURCE_MIME_TYPE.to_string()),
+                uri,
+            })],
+            meta: None,
+        })
+    }
+
+    async fn handle_list_prompts_request(
+        &self,
+        _request: ListPromptsRequest,
+        _runtime: &dyn McpServer,
+    ) -> Result<ListPromptsResult, RpcError> {
+        let prompts = prompts::PROMPT_TEMPLATES.iter()
+            .map(|template| Prompt {
+                name: template.name.to_string(),
+                description: Some(template.description.to_string()),
+                arguments: template.arguments.iter()
+                    .map(|(name, description, required)| PromptArgument {
+                        name: name.to_string(),
+                        description: Some(description.to_string()),
+                        required: Some(*required),
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(ListPromptsResult { prompts, meta: None, next_cursor: None })
+    }
+
+    // Runs the template's retrieval query and returns one user message with the docs embedded.
+    async fn handle_get_prompt_request(
+        &self,
+        request: GetPromptRequest,
+        _runtime: &dyn McpServer,
+    ) -> Result<GetPromptResult, RpcError> {
+        let template = prompts::find_template(&request.params.name)
+            .ok_or_else(|| RpcError::invalid_params().with_message(format!("Unknown prompt '{}'", request.params.name)))?;
+        let arguments = template.check_arguments(request.params.arguments)
+            .map_err(|message| RpcError::invalid_params().with_message(message))?;
+        let num_results = match arguments.get("num_results") {
+            Some(n) => n.parse::<usize>()
+                .map_err(|_| RpcError::invalid_params().with_message(format!("Invalid num_results '{}'", n)))?,
+            None => prompts::DEFAULT_PROMPT_RESULTS,
+        };
+        log::info!("Rendering prompt '{}'", template.name);
+        let hits = pipeline::query_docs(&self.app_state, &QueryOptions {
+            query: template.retrieval_query(&arguments),
+            project_path: arguments.get("project_path").cloned(),
+            version: None,
+            num_results,
+            filter: SearchFilter::default(),
+            rerank: self.app_state.config.current().reranker.enabled,
+            prefer_examples: template.prefer_examples,
+            additional_queries: Vec::new(),
+            expand_query: false,
+            min_score: None,
+        }).await.map_err(|e| RpcError::internal_error().with_message(e.to_string()))?;
+        Ok(GetPromptResult {
+            description: Some(template.description.to_string()),
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: PromptMessageContent::TextContent(TextContent::new(template.render(&arguments, &hits), None)),
+            }],
+            meta: None,
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(tool = %request.tool_name()))]
     async fn handle_call_tool_request(
         &self,
         request: CallToolRequest,
@@ -149,122 +1265,361 @@ impl ServerHandler for MyMcpServerHandler {
                 log::info!("Processing project at path: {}", params.path);
                 let project_path_obj = Path::new(&params.path);
                 if !project_path_obj.exists() || !project_path_obj.is_dir() {
-                    return Err(CallToolError::invalid_arguments(format!(
-                        "Project path does not exist or is not a directory: {}", params.path
-                    )));
+                    return Err(AppError::PathNotFound { path: params.path }.into());
                 }
-                let rustdoc_output_dir = self.app_state.rustdoc_output_dir.clone();
-                match crate::rustdoc_processor::generate_rustdoc_json(project_path_obj, &rustdoc_output_dir) {
-                    Ok(json_path) => {
-                        log::info!("Successfully generated rustdoc JSON at: {}", json_path.display());
-                        match crate::rustdoc_processor::parse_rustdoc_json_file(&json_path) {
-                            Ok(crate_docs) => {
-                                log::info!("Successfully parsed rustdoc JSON for crate: {}", crate_docs.crate_name);
-                                let mut project_embeddings = HashMap::new();
-                                let embedder_guard = GLOBAL_EMBEDDER.lock().map_err(|e| CallToolError::internal_error(format!("Failed to lock global embedder: {}", e)))?;
-                                if let Some(embedder) = embedder_guard.as_ref() {
-                                    let mut texts_to_embed = Vec::new();
-                                    let mut item_paths_for_embedding = Vec::new();
-                                    for (item_full_path, doc_item) in &crate_docs.items {
-                                        if let Some(desc) = &doc_item.description {
-                                            if !desc.trim().is_empty() {
-                                                texts_to_embed.push(format!("{}::{} [DOCS]: {}", doc_item.crate_name, doc_item.name, desc));
-                                                item_paths_for_embedding.push(item_full_path.clone());
-                                            }
-                                        }
-                                    }
-                                    if !texts_to_embed.is_empty() {
-                                        log::info!("Embedding {} documentation items for {}...", texts_to_embed.len(), crate_docs.crate_name);
-                                        match embedder.embed_batch(&texts_to_embed) {
-                                            Ok(embeddings_vec) => {
-                                                for (path, embedding) in item_paths_for_embedding.into_iter().zip(embeddings_vec.into_iter()) {
-                                                    project_embeddings.insert(path, embedding);
-                                                }
-                                                log::info!("Successfully embedded {} items for {}.", project_embeddings.len(), crate_docs.crate_name);
-                                            }
-                                            Err(e) => log::error!("Failed to embed batch for {}: {:?}", crate_docs.crate_name, e),
-                                        }
-                                    } else { log::info!("No descriptions found to embed for {}.", crate_docs.crate_name); }
-                                } else { log::warn!("Embedder not initialized. Skipping embedding generation for {}.", crate_docs.crate_name); }
-                                let project_data = ProjectData { crate_docs: Arc::new(crate_docs.clone()), embeddings: Arc::new(project_embeddings) };
-                                let mut projects_guard = self.app_state.processed_projects.lock().await;
-                                projects_guard.insert(params.path.clone(), project_data);
-                                let num_embedded = projects_guard.get(&params.path).map_or(0, |pd| pd.embeddings.len());
-                                Ok(CallToolResult::text_content(format!("Successfully processed project {} and embedded {} items. Total processed projects: {}.", params.path, num_embedded, projects_guard.len()), None))
-                            }
-                            Err(e) => Err(CallToolError::internal_error(format!("Failed to parse rustdoc JSON for {}: {}", params.path, e))),
-                        }
-                    }
-                    Err(e) => Err(CallToolError::internal_error(format!("Failed to generate rustdoc JSON for {}: {}", params.path, e))),
+                // An MCP client can't confirm interactively, so untrusted projects are always refused.
+                if !sandbox::is_trusted(project_path_obj, &self.app_state.config.current().rustdoc.sandbox) {
+                    return Err(AppError::UntrustedProject { path: params.path }.into());
+                }
+                let include_dependencies = params.include_dependencies.unwrap_or(false);
+                let targets = match params.targets.as_deref() {
+                    Some(targets) => targets.parse::<DocTargets>().map_err(|message| AppError::InvalidArgument { message })?,
+                    None => DocTargets::default(),
+                };
+                let job_state = self.app_state.clone();
+                let opts = ProcessOptions { path: params.path.clone(), include_dependencies, targets };
+                let job_id = self.app_state.jobs.submit_with_progress("process_rust_project", &params.path, JobPriority::Normal, |progress| async move {
+                    pipeline::process_project(job_state, opts, progress).await.map(|outcome| outcome.to_string())
+                })?;
+                match self.app_state.jobs.wait(job_id).await {
+                    Ok(message) => Ok(CallToolResult::text_content(message, None)),
+                    Err(e) => Err(e.into()),
                 }
             }
             GetRawDocumentationTool::TOOL_NAME => {
                 let params: GetRawDocumentationTool = request.arguments()?;
                 log::info!("Attempting to get raw documentation for item '{}' in project '{}'", params.item_path, params.project_path);
-                let projects_guard = self.app_state.processed_projects.lock().await;
-                match projects_guard.get(&params.project_path) {
-                    Some(project_data) => {
-                        match project_data.crate_docs.items.get(&params.item_path) {
-                            Some(doc_item) => {
-                                log::info!("Found item '{}'. Returning its details.", params.item_path);
-                                CallToolResult::json_content(serde_json::to_value(doc_item)
-                                    .map_err(|e| CallToolError::internal_error(format!("Failed to serialize DocItem: {}", e)))?, None)
-                            }
-                            None => {
-                                log::warn!("Item '{}' not found in project '{}'", params.item_path, params.project_path);
-                                Err(CallToolError::resource_not_found(format!("Item '{}' not found in project '{}'", params.item_path, params.project_path)))
-                            }
-                        }
+                let mode = match params.render.as_deref() {
+                    Some(render) => render.parse::<RenderMode>().map_err(|message| AppError::InvalidArgument { message })?,
+                    None => RenderMode::default(),
+                };
+                match self.app_state.item_docs(&params.project_path, &params.item_path, mode).await {
+                    Ok(doc_item) => {
+                        log::info!("Found item '{}'. Returning its details.", params.item_path);
+                        CallToolResult::json_content(serde_json::to_value(doc_item)
+                            .map_err(|e| CallToolError::internal_error(format!("Failed to serialize DocItem: {}", e)))?, None)
                     }
-                    None => {
-                        log::warn!("Project '{}' not found.", params.project_path);
-                        Err(CallToolError::resource_not_found(format!("Project '{}' has not been processed or was not found.", params.project_path)))
+                    Err(e) => {
+                        log::warn!("Getting documentation for '{}' in '{}' failed: {}", params.item_path, params.project_path, e);
+                        Err(e.into())
                     }
                 }
             }
+            FindSimilarItemsTool::TOOL_NAME => {
+                let params: FindSimilarItemsTool = request.arguments()?;
+                log::info!("Finding items similar to '{}' in project '{}'", params.item_path, params.project_path);
+                let config = self.app_state.config.current();
+                let detail = ResultDetail::new(None, None, &config.search);
+                let filter = SearchFilter {
+                    item_types: params.item_types,
+                    include_deprecated: params.include_deprecated.unwrap_or(false),
+                    public_only: params.public_only.unwrap_or(true),
+                    ..Default::default()
+                };
+                let num_results = params.num_results.unwrap_or(config.search.default_num_results);
+                let results: Vec<QueryDocResultItem> = pipeline::find_similar_items(&self.app_state, &params.project_path, &params.item_path, num_results, &filter).await?
+                    .into_iter()
+                    .map(|hit| QueryDocResultItem::from_hit(hit, detail))
+                    .collect();
+                CallToolResult::json_content(serde_json::to_value(results)
+                    .map_err(|e| CallToolError::internal_error(format!("Failed to serialize similar items: {}", e)))?, None)
+            }
             QueryDocumentationTool::TOOL_NAME => {
                 let params: QueryDocumentationTool = request.arguments()?;
+                let config = self.app_state.config.current();
+                let page_size = params.page_size.unwrap_or(config.search.page_size);
+                let cursor_ttl = Duration::from_secs(config.search.cursor_ttl_secs);
+                if let Some(cursor) = &params.cursor {
+                    let page = self.app_state.query_results.page(cursor, page_size, cursor_ttl)?;
+                    return CallToolResult::json_content(serde_json::to_value(page)
+                        .map_err(|e| CallToolError::internal_error(format!("Failed to serialize query results: {}", e)))?, None);
+                }
                 log::info!("Querying documentation with: '{}'", params.natural_language_query);
+                let detail = ResultDetail::new(params.snippet_length, params.include_full_docs, &config.search);
+                let scored_items = self.search(QueryOptions {
+                    query: params.natural_language_query.clone(),
+                    project_path: params.project_path.clone(),
+                    version: params.version.clone(),
+                    num_results: params.num_results.unwrap_or(config.search.default_num_results),
+                    filter: SearchFilter {
+                        item_types: params.item_types.clone(),
+                        crate_name: params.crate_name.clone(),
+                        module_prefix: params.module_prefix.clone(),
+                        include_deprecated: params.include_deprecated.unwrap_or(false),
+                        public_only: params.public_only.unwrap_or(true),
+                        target: params.target.clone(),
+                    },
+                    rerank: params.rerank.unwrap_or(config.reranker.enabled),
+                    prefer_examples: params.prefer_examples.unwrap_or(false),
+                    additional_queries: params.additional_queries.clone().unwrap_or_default(),
+                    expand_query: params.expand_query.unwrap_or(false),
+                    min_score: params.min_score,
+                }).await?;
 
-                let embedder_guard = GLOBAL_EMBEDDER.lock().map_err(|e| CallToolError::internal_error(format!("Failed to lock global embedder: {}", e)))?;
-                let embedder = embedder_guard.as_ref().ok_or_else(|| CallToolError::internal_error("Embedder not initialized. Cannot generate query embedding.".to_string()))?;
-
-                let query_embedding = embedder.embed_sentence(&params.natural_language_query)
-                    .map_err(|e| CallToolError::internal_error(format!("Failed to embed query: {}", e)))?;
+                let results: Vec<QueryDocResultItem> = scored_items.into_iter()
+                    .map(|hit| QueryDocResultItem::from_hit(hit, detail).with_highlights(&params.natural_language_query))
+                    .collect();
 
-                let projects_guard = self.app_state.processed_projects.lock().await;
-                let mut scored_items = Vec::new();
-
-                for (proj_path_key, project_data) in projects_guard.iter() {
-                    if params.project_path.is_some() && params.project_path.as_ref() != Some(proj_path_key) {
-                        continue; // Skip if a specific project is requested and this is not it
-                    }
-                    for (item_full_path, item_embedding) in project_data.embeddings.iter() {
-                        if let Some(doc_item) = project_data.crate_docs.items.get(item_full_path) {
-                            let score = cosine_similarity(&query_embedding, item_embedding);
-                            scored_items.push((doc_item.clone(), score, proj_path_key.clone()));
+                log::info!("Found {} results for query '{}'", results.len(), params.natural_language_query);
+                if let (true, Some(min_score)) = (results.is_empty(), params.min_score) {
+                    return Ok(CallToolResult::text_content(format!("No relevant documentation found for '{}' (no result scored {} or more).", params.natural_language_query, min_score), None));
+                }
+                let page = self.app_state.query_results.first_page(results, page_size, cursor_ttl);
+                CallToolResult::json_content(serde_json::to_value(page)
+                    .map_err(|e| CallToolError::internal_error(format!("Failed to serialize query results: {}", e)))?, None)
+            }
+            AnswerQuestionTool::TOOL_NAME => {
+                let params: AnswerQuestionTool = request.arguments()?;
+                log::info!("Gathering sources to answer: '{}'", params.question);
+                let scored_items = self.search(QueryOptions {
+                    query: params.question.clone(),
+                    project_path: params.project_path.clone(),
+                    version: None,
+                    num_results: params.num_sources.unwrap_or(DEFAULT_ANSWER_SOURCES),
+                    filter: SearchFilter {
+                        item_types: params.item_types.clone(),
+                        include_deprecated: params.include_deprecated.unwrap_or(false),
+                        public_only: params.public_only.unwrap_or(true),
+                        ..Default::default()
+                    },
+                    rerank: params.rerank.unwrap_or(self.app_state.config.current().reranker.enabled),
+                    prefer_examples: false,
+                    additional_queries: Vec::new(),
+                    expand_query: false,
+                    min_score: None,
+                }).await?;
+                let answer = AnswerContext::new(&params.question, scored_items);
+                CallToolResult::json_content(serde_json::to_value(answer)
+                    .map_err(|e| CallToolError::internal_error(format!("Failed to serialize answer context: {}", e)))?, None)
+            }
+            ProcessDocSourceTool::TOOL_NAME => {
+                let params: ProcessDocSourceTool = request.arguments()?;
+                log::info!("Processing doc source at path: {} (kind: {:?})", params.path, params.source_kind);
+                if !Path::new(&params.path).exists() {
+                    return Err(AppError::PathNotFound { path: params.path }.into());
+                }
+                let runs_build = self.app_state.doc_sources.resolve(Path::new(&params.path), params.source_kind.as_deref()).is_some_and(|s| s.runs_build());
+                if runs_build && !sandbox::is_trusted(Path::new(&params.path), &self.app_state.config.current().rustdoc.sandbox) {
+                    return Err(AppError::UntrustedProject { path: params.path }.into());
+                }
+                let job_state = self.app_state.clone();
+                let job_path = params.path.clone();
+                let source_kind = params.source_kind.clone();
+                let job_id = self.app_state.jobs.submit_with_progress("process_doc_source", &params.path, JobPriority::Normal, |progress| async move {
+                    doc_source::index_with_source(&job_state, &job_path, source_kind.as_deref(), progress.cancel_flag().clone()).await
+                        .map(|(num_items, num_embedded)| format!("Successfully indexed {} items from {} and embedded {} of them.", num_items, job_path, num_embedded))
+                        .map_err(|e| progress.check_cancelled().err().unwrap_or_else(|| AppError::internal(format!("Failed to index doc source {}: {}", job_path, e))))
+                })?;
+                match self.app_state.jobs.wait(job_id).await {
+                    Ok(message) => Ok(CallToolResult::text_content(message, None)),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            CancelProcessingTool::TOOL_NAME => {
+                let params: CancelProcessingTool = request.arguments()?;
+                let job_id = match (params.job_id, &params.project_path) {
+                    (Some(job_id), _) => job_id,
+                    (None, Some(path)) => self.app_state.jobs.find_active_by_label(path)
+                        .ok_or_else(|| AppError::InvalidArgument { message: format!("No active job for {}", path) })?,
+                    (None, None) => return Err(AppError::InvalidArgument { message: "Either job_id or project_path is required".to_string() }.into()),
+                };
+                match self.app_state.jobs.cancel(job_id) {
+                    Ok(()) => Ok(CallToolResult::text_content(format!("Cancellation requested for job {}.", job_id), None)),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            ProcessStdLibraryTool::TOOL_NAME => {
+                let job_state = self.app_state.clone();
+                let job_id = self.app_state.jobs.submit_with_progress("process_std_library", pipeline::STD_PROJECT_KEY, JobPriority::Normal, |progress| async move {
+                    pipeline::process_std_library(job_state, progress).await.map(|outcome| outcome.to_string())
+                })?;
+                match self.app_state.jobs.wait(job_id).await {
+                    Ok(message) => Ok(CallToolResult::text_content(message, None)),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            ReembedProjectTool::TOOL_NAME => {
+                let params: ReembedProjectTool = request.arguments()?;
+                if !self.app_state.projects().await.contains_key(&params.project_path) {
+                    return Err(AppError::ProjectNotFound { project_path: params.project_path }.into());
+                }
+                let (job_state, path) = (self.app_state.clone(), params.project_path.clone());
+                let job_id = self.app_state.jobs.submit_with_progress("reembed_project", &params.project_path, JobPriority::Normal, |progress| async move {
+                    pipeline::reembed_project(job_state, path, progress).await.map(|outcome| outcome.to_string())
+                })?;
+                match self.app_state.jobs.wait(job_id).await {
+                    Ok(message) => Ok(CallToolResult::text_content(message, None)),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            ProcessDocsRsCrateTool::TOOL_NAME => {
+                let params: ProcessDocsRsCrateTool = request.arguments()?;
+                let label = format!("{}@{}", params.name, params.version);
+                let job_state = self.app_state.clone();
+                let job_id = self.app_state.jobs.submit_with_progress("process_docsrs_crate", &label, JobPriority::Normal, |progress| async move {
+                    pipeline::process_docsrs_crate(job_state, params.name, params.version, progress).await.map(|outcome| outcome.to_string())
+                })?;
+                match self.app_state.jobs.wait(job_id).await {
+                    Ok(message) => Ok(CallToolResult::text_content(message, None)),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            ImportRustdocJsonTool::TOOL_NAME => {
+                let params: ImportRustdocJsonTool = request.arguments()?;
+                let job_id = pipeline::submit_import(&self.app_state, params.json_path, params.project_label, params.version, JobPriority::Normal)?;
+                match self.app_state.jobs.wait(job_id).await {
+                    Ok(message) => Ok(CallToolResult::text_content(message, None)),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            ProcessGitRepositoryTool::TOOL_NAME => {
+                let params: ProcessGitRepositoryTool = request.arguments()?;
+                let config = self.app_state.config.current();
+                let repos_dir = self.app_state.cache_dir.join("git");
+                // Like downloaded crates, a cloned repository's build script runs while documenting it.
+                if !sandbox::is_trusted(&repos_dir, &config.rustdoc.sandbox) {
+                    return Err(AppError::UntrustedProject { path: repos_dir.display().to_string() }.into());
+                }
+                git_repo::validate(&params.url, params.rev.as_deref()).map_err(|e| AppError::InvalidArgument { message: e.to_string() })?;
+                let include_dependencies = params.include_dependencies.unwrap_or(false);
+                let cleanup = config.git.cleanup;
+                let label = match &params.rev {
+                    Some(rev) => format!("{}@{}", params.url, rev),
+                    None => params.url.clone(),
+                };
+                let job_state = self.app_state.clone();
+                let job_id = self.app_state.jobs.submit_with_progress("process_git_repository", &label, JobPriority::Normal, |progress| async move {
+                    let (checkout, project_dir) = tokio::task::spawn_blocking(move || {
+                        let checkout = git_repo::clone_repository(&repos_dir, &params.url, params.rev.as_deref())?;
+                        let project_dir = git_repo::locate_project_root(&checkout)?;
+                        anyhow::Ok((checkout, project_dir))
+                    })
+                    .await
+                    .map_err(|e| AppError::internal(format!("Cloning panicked: {}", e)))?
+                    .map_err(|e| AppError::internal(format!("{:#}", e)))?;
+                    let outcome = async {
+                        progress.check_cancelled()?;
+                        let path = project_dir.to_string_lossy().into_owned();
+                        let opts = ProcessOptions { path, include_dependencies, targets: DocTargets::default() };
+                        pipeline::process_project(job_state, opts, progress).await
+                    }.await;
+                    if cleanup == SourceCleanup::AfterProcessing {
+                        if let Err(e) = std::fs::remove_dir_all(&checkout) {
+                            log::warn!("Failed to delete the checkout {}: {}", checkout.display(), e);
                         }
                     }
+                    outcome.map(|outcome| outcome.to_string())
+                })?;
+                match self.app_state.jobs.wait(job_id).await {
+                    Ok(message) => Ok(CallToolResult::text_content(message, None)),
+                    Err(e) => Err(e.into()),
                 }
-
-                // Sort by score descending
-                scored_items.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-
-                let num_results = params.num_results.unwrap_or_else(|| default_num_results().unwrap_or(5));
-
-                let results: Vec<QueryDocResultItem> = scored_items.into_iter().take(num_results).map(|(item, score, proj_path)| {
-                    QueryDocResultItem {
-                        project_path: proj_path,
-                        item_full_path: item.full_path_str.clone(),
-                        item_type: item.item_type.clone(),
-                        description_snippet: item.description.as_ref().map(|d| d.chars().take(150).collect::<String>() + "..."), // Truncate description
-                        score,
-                    }
+            }
+            ProcessCratesIoCrateTool::TOOL_NAME => {
+                let params: ProcessCratesIoCrateTool = request.arguments()?;
+                let config = self.app_state.config.current();
+                let crates_dir = self.app_state.cache_dir.join("crates");
+                // Building a downloaded crate runs its build script; hardened mode must trust the download directory.
+                if !sandbox::is_trusted(&crates_dir, &config.rustdoc.sandbox) {
+                    return Err(AppError::UntrustedProject { path: crates_dir.display().to_string() }.into());
+                }
+                let include_dependencies = params.include_dependencies.unwrap_or(false);
+                let label = format!("{}@{}", params.name, params.version);
+                let job_state = self.app_state.clone();
+                let job_id = self.app_state.jobs.submit_with_progress("process_crates_io_crate", &label, JobPriority::Normal, |progress| async move {
+                    let crate_dir = crate::crates_io::fetch_crate(&job_state.http_client, &crates_dir, &params.name, &params.version)
+                        .await
+                        .map_err(|e| AppError::internal(format!("{:#}", e)))?;
+                    progress.check_cancelled()?;
+                    let path = crate_dir.to_string_lossy().into_owned();
+                    let opts = ProcessOptions { path, include_dependencies, targets: DocTargets::default() };
+                    pipeline::process_project(job_state, opts, progress).await.map(|outcome| outcome.to_string())
+                })?;
+                match self.app_state.jobs.wait(job_id).await {
+                    Ok(message) => Ok(CallToolResult::text_content(message, None)),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            RemoveProcessedProjectTool::TOOL_NAME => {
+                let params: RemoveProcessedProjectTool = request.arguments()?;
+                match self.app_state.remove_project(&params.project_path).await {
+                    Ok(remaining) => Ok(CallToolResult::text_content(
+                        format!("Removed project {}. Remaining processed projects: {}.", params.project_path, remaining), None)),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            GetItemSourceTool::TOOL_NAME => {
+                let params: GetItemSourceTool = request.arguments()?;
+                match self.app_state.item_source(&params.project_path, &params.item_path).await {
+                    Ok(source) => CallToolResult::json_content(serde_json::to_value(source)
+                        .map_err(|e| CallToolError::internal_error(format!("Failed to serialize item source: {}", e)))?, None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            SearchKeywordsTool::TOOL_NAME => {
+                let params: SearchKeywordsTool = request.arguments()?;
+                log::info!("Keyword search for: '{}'", params.keywords);
+                let search_settings = self.app_state.config.current().search.clone();
+                let num_results = params.num_results.unwrap_or(search_settings.default_num_results);
+                let detail = ResultDetail::new(params.snippet_length, params.include_full_docs, &search_settings);
+                let filter = SearchFilter {
+                    item_types: params.item_types.clone(),
+                    crate_name: params.crate_name.clone(),
+                    module_prefix: params.module_prefix.clone(),
+                    include_deprecated: params.include_deprecated.unwrap_or(false),
+                    public_only: params.public_only.unwrap_or(true),
+                    target: params.target.clone(),
+                };
+                let hits = self.app_state.keyword_search(&params.keywords, params.project_path.as_deref(), &filter, num_results).await?;
+                let projects_guard = self.app_state.projects().await;
+                let results: Vec<QueryDocResultItem> = hits.into_iter().map(|hit| {
+                    let project_data = projects_guard.get(&hit.project_path);
+                    let related_items = project_data.map(|data| browse::related_items(data, &hit.item)).unwrap_or_default();
+                    let version = project_data.and_then(|data| data.version.clone());
+                    QueryDocResultItem::new(hit.project_path, version, &hit.item, hit.score, related_items, detail)
+                        .with_highlights(&params.keywords)
                 }).collect();
-
-                log::info!("Found {} results for query '{}'", results.len(), params.natural_language_query);
                 CallToolResult::json_content(serde_json::to_value(results)
-                    .map_err(|e| CallToolError::internal_error(format!("Failed to serialize query results: {}", e)))?, None)
+                    .map_err(|e| CallToolError::internal_error(format!("Failed to serialize keyword results: {}", e)))?, None)
+            }
+            DiffDocumentationTool::TOOL_NAME => {
+                let params: DiffDocumentationTool = request.arguments()?;
+                match self.app_state.diff_projects(&params.project_a, &params.project_b, params.limit.unwrap_or(diff::DEFAULT_DIFF_LIMIT)).await {
+                    Ok(diff) => CallToolResult::json_content(serde_json::to_value(diff)
+                        .map_err(|e| CallToolError::internal_error(format!("Failed to serialize doc diff: {}", e)))?, None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            ExportProjectDocsTool::TOOL_NAME => {
+                let params: ExportProjectDocsTool = request.arguments()?;
+                let format = match params.format.as_deref() {
+                    Some(format) => format.parse::<ExportFormat>().map_err(|message| AppError::InvalidArgument { message })?,
+                    None => ExportFormat::default(),
+                };
+                log::info!("Exporting docs of {} to {} as {:?}", params.project_path, params.output_dir, format);
+                let summary = self.app_state.export_docs(&params.project_path, format, Path::new(&params.output_dir), params.include_dependencies.unwrap_or(false)).await?;
+                CallToolResult::json_content(serde_json::to_value(summary)
+                    .map_err(|e| CallToolError::internal_error(format!("Failed to serialize export summary: {}", e)))?, None)
+            }
+            ListCrateItemsTool::TOOL_NAME => {
+                let params: ListCrateItemsTool = request.arguments()?;
+                let listing = self.app_state.list_items(
+                    &params.project_path,
+                    params.module_path.as_deref(),
+                    params.item_type.as_deref(),
+                    params.page.unwrap_or(1),
+                    params.page_size.unwrap_or(browse::DEFAULT_PAGE_SIZE),
+                ).await;
+                match listing {
+                    Ok(listing) => CallToolResult::json_content(serde_json::to_value(listing)
+                        .map_err(|e| CallToolError::internal_error(format!("Failed to serialize item listing: {}", e)))?, None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            GetSystemInfoTool::TOOL_NAME => {
+                let info = crate::system_info::collect(&self.app_state).await;
+                CallToolResult::json_content(serde_json::to_value(info)
+                    .map_err(|e| CallToolError::internal_error(format!("Failed to serialize system info: {}", e)))?, None)
             }
             _ => Err(CallToolError::unknown_tool(request.tool_name().to_string())),
         }
@@ -272,32 +1627,188 @@ impl ServerHandler for MyMcpServerHandler {
 }
 
 // --- Server Initialization ---
-pub async fn start_mcp_server(app_state: Arc<AppState>) -> Result<()> {
-    log::info!("Starting MCP Server...");
-    let server_details = InitializeResult {
+pub(crate) fn server_details() -> InitializeResult {
+    InitializeResult {
         server_info: Implementation {
             name: "RustDoc LLM MCP Server".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
         },
-        capabilities: ServerCapabilities { tools: Some(ServerCapabilitiesTools { list_changed: None }), ..Default::default() },
+        capabilities: ServerCapabilities {
+            tools: Some(ServerCapabilitiesTools { list_changed: None }),
+            resources: Some(ServerCapabilitiesResources { list_changed: None, subscribe: None }),
+            prompts: Some(ServerCapabilitiesPrompts { list_changed: None }),
+            ..Default::default()
+        },
         meta: None,
         instructions: Some("This server provides tools for LLMs to interact with Rust documentation.".to_string()),
         protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
-    };
+    }
+}
+
+/// Runs the MCP server on the configured transport(s) until it stops.
+pub async fn start_mcp_server(app_state: Arc<AppState>) -> Result<()> {
+    log::info!("Starting MCP Server...");
+    let server_settings = app_state.config.current().server.clone();
+    match server_settings.transport {
+        McpTransport::Http => start_http_server(app_state, &server_settings).await,
+        McpTransport::Stdio => start_stdio_server(app_state).await,
+        McpTransport::Both => {
+            tokio::try_join!(start_http_server(app_state.clone(), &server_settings), start_stdio_server(app_state))?;
+            Ok(())
+        }
+    }
+}
+
+// Every session's MCP server runs in-process behind `mcp_http`, which checks the bearer token.
+async fn start_http_server(app_state: Arc<AppState>, server_settings: &ServerSettings) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind((server_settings.host.as_str(), server_settings.port)).await
+        .with_context(|| format!("Failed to bind the MCP server to {}:{}", server_settings.host, server_settings.port))?;
+    log::info!("MCP Server will listen on {}:{}", server_settings.host, server_settings.port);
+    axum::serve(listener, mcp_http::router(app_state)).await.context("MCP server failed")
+}
+
+/// Serves MCP over stdin/stdout; returns when the client closes stdin. Logs go to stderr and the
+/// log file, so stdout carries nothing but protocol messages.
+pub async fn start_stdio_server(app_state: Arc<AppState>) -> Result<()> {
+    log::info!("MCP Server will communicate over stdio");
+    let transport = StdioTransport::new(TransportOptions::default())?;
     let handler = MyMcpServerHandler::new(app_state);
-    let options = HyperServerOptions { host: "127.0.0.1".to_string(), port: 3001, ..Default::default() };
-    log::info!("MCP Server will listen on {}:{}", options.host, options.port);
-    let server_runtime = create_hyper_server(server_details, handler, options)?;
+    let server_runtime = server_runtime::create_server(server_details(), transport, handler);
     server_runtime.start().await?;
+    log::info!("MCP stdio client disconnected");
     Ok(())
 }
 
-// Modified to accept AppState instance
-pub fn init_mcp_server(app_state_instance: Arc<AppState>) {
-    tokio::spawn(async move { // app_state_instance is moved into the async block
-        if let Err(e) = start_mcp_server(app_state_instance).await { // Pass it to start_mcp_server
-            log::error!("MCP Server failed: {:?}", e);
+// The hyper server can't rebind while running, so transport/host/port changes only take effect
+// after a restart. Auth settings are read per request and apply immediately.
+fn spawn_server_config_watcher(config: &ConfigHandle, running: ServerSettings) -> tauri::async_runtime::JoinHandle<()> {
+    let mut receiver = config.subscribe();
+    tauri::async_runtime::spawn(async move {
+        while receiver.changed().await.is_ok() {
+            let latest = receiver.borrow_and_update().server.clone();
+            if (latest.transport, &latest.host, latest.port) != (running.transport, &running.host, running.port) {
+                log::warn!("MCP server settings changed ({:?} {}:{} -> {:?} {}:{}). Restart the MCP server to apply them.", running.transport, running.host, running.port, latest.transport, latest.host, latest.port);
+            }
+        }
+    })
+}
+
+/// How long `stop_mcp_server` waits for the server task to wind down.
+const MCP_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// A running MCP server task: dropping the server future on `shutdown` closes its listener.
+struct McpServerHandle {
+    settings: ServerSettings,
+    shutdown: oneshot::Sender<()>,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl McpServerHandle {
+    // The task owns the receiver, so a closed channel means it has ended (stopped or crashed).
+    fn is_running(&self) -> bool {
+        !self.shutdown.is_closed()
+    }
+}
+
+/// Where the MCP server stands, for `get_mcp_server_status` and `restart_mcp_server`.
+#[derive(Debug, Clone, Serialize)]
+pub struct McpServerStatus {
+    pub running: bool,
+    pub transport: McpTransport,
+    pub host: String,
+    pub port: u16,
+    /// Why it stopped, e.g. `server_port_in_use`.
+    pub error: Option<AppError>,
+}
+
+/// Starts the MCP server at startup, unless it is disabled in the config.
+pub fn init_mcp_server(app_state: Arc<AppState>) {
+    tauri::async_runtime::spawn(spawn_mcp_server(app_state));
+}
+
+/// Starts the MCP server on the configured transport(s) as a background task, unless it is
+/// disabled or already running. If it fails (e.g. its port is taken), the error is stored in
+/// `mcp_server_error` and emitted as `McpServerFailed`.
+pub async fn spawn_mcp_server(app_state: Arc<AppState>) {
+    let settings = app_state.config.current().server.clone();
+    if !settings.enabled {
+        log::info!("MCP Server disabled in config. Not starting.");
+        return;
+    }
+    let mut slot = app_state.mcp_server.lock().await;
+    if slot.as_ref().is_some_and(McpServerHandle::is_running) {
+        return;
+    }
+    *app_state.mcp_server_error.lock().await = None;
+    let (shutdown, shutdown_signal) = oneshot::channel();
+    let task_state = app_state.clone();
+    let task_settings = settings.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let config_watcher = spawn_server_config_watcher(&task_state.config, task_settings.clone());
+        let result = tokio::select! {
+            result = start_mcp_server(task_state.clone()) => Some(result),
+            _ = shutdown_signal => None,
+        };
+        config_watcher.abort();
+        match result {
+            None => log::info!("MCP Server stopped"),
+            Some(Ok(())) => log::info!("MCP Server exited"),
+            Some(Err(e)) => {
+                log::error!("MCP Server failed: {:?}", e);
+                let error = AppError::from_server_error(&task_settings.host, task_settings.port, &e);
+                // Stored as well as emitted: this can happen before any window is listening.
+                *task_state.mcp_server_error.lock().await = Some(error.clone());
+                task_state.emit_state_event(StateEvent::McpServerFailed { error });
+            }
         }
+        task_state.emit_state_event(StateEvent::McpServerStateChanged { running: false });
     });
+    *slot = Some(McpServerHandle { settings, shutdown, task });
+    app_state.emit_state_event(StateEvent::McpServerStateChanged { running: true });
     log::info!("MCP Server initialization process started using shared AppState.");
 }
+
+/// Stops the MCP server, if it is running. Returns whether it was.
+pub async fn stop_mcp_server(app_state: &AppState) -> bool {
+    let Some(server) = app_state.mcp_server.lock().await.take() else {
+        return false;
+    };
+    let was_running = server.is_running();
+    let _ = server.shutdown.send(());
+    if tokio::time::timeout(MCP_SHUTDOWN_TIMEOUT, server.task).await.is_err() {
+        log::warn!("The MCP server did not stop within {:?}", MCP_SHUTDOWN_TIMEOUT);
+    }
+    was_running
+}
+
+/// Stops the MCP server and starts it again with the current config, e.g. after its port changed.
+/// A new HTTP address is checked first: if it can't be bound, the running server is left alone.
+pub async fn restart_mcp_server(app_state: Arc<AppState>) -> Result<McpServerStatus, AppError> {
+    let settings = app_state.config.current().server.clone();
+    let running_on = app_state.mcp_server.lock().await.as_ref()
+        .filter(|server| server.is_running())
+        .map(|server| (server.settings.transport, server.settings.host.clone(), server.settings.port));
+    let serves_http = settings.enabled && settings.transport != McpTransport::Stdio;
+    let address_taken_by_us = running_on.is_some_and(|(transport, host, port)| {
+        transport != McpTransport::Stdio && host == settings.host && port == settings.port
+    });
+    if serves_http && !address_taken_by_us {
+        std::net::TcpListener::bind((settings.host.as_str(), settings.port))
+            .map_err(|e| AppError::from_server_error(&settings.host, settings.port, &anyhow::Error::from(e)))?;
+    }
+    stop_mcp_server(&app_state).await;
+    spawn_mcp_server(app_state.clone()).await;
+    Ok(mcp_server_status(&app_state).await)
+}
+
+pub async fn mcp_server_status(app_state: &AppState) -> McpServerStatus {
+    let running = app_state.mcp_server.lock().await.as_ref().is_some_and(McpServerHandle::is_running);
+    let settings = app_state.config.current().server.clone();
+    McpServerStatus {
+        running,
+        transport: settings.transport,
+        host: settings.host,
+        port: settings.port,
+        error: app_state.mcp_server_error.lock().await.clone(),
+    }
+}
@@ -0,0 +1,99 @@
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::embedder::{self, EmbedderStatus};
+use crate::jobs::{JobPhase, JobStatus};
+use crate::mcp_server::AppState;
+use crate::watch::WATCH_JOB_KIND;
+
+/// Raises an OS notification when a job (project processing, re-embedding, a toolchain install)
+/// or the model download finishes while no window of the app is focused, so a user who switched
+/// away during a long run learns the outcome. Cancelled jobs, watch mode refreshes and jobs
+/// shorter than `[notifications] min_job_secs` are left out.
+pub fn spawn_notifier(app_handle: AppHandle, app_state: Arc<AppState>) {
+    let mut progress_receiver = app_state.jobs.subscribe_progress();
+    let job_handle = app_handle.clone();
+    let job_state = app_state.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let event = match progress_receiver.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let JobPhase::Finished { status } = &event.progress.phase else { continue };
+            let settings = job_state.config.current().notifications.clone();
+            if !settings.enabled || event.kind == WATCH_JOB_KIND {
+                continue;
+            }
+            let ran_ms = job_state.jobs.get(event.job_id)
+                .and_then(|job| Some(job.finished_at_ms?.saturating_sub(job.started_at_ms?)))
+                .unwrap_or_default();
+            if ran_ms < u128::from(settings.min_job_secs) * 1000 {
+                continue;
+            }
+            let name = display_name(&event.label);
+            let (title, body) = match status {
+                JobStatus::Completed { message } => (format!("{} is ready", name), message.clone()),
+                JobStatus::Failed { error } => (format!("{} failed", name), error.to_string()),
+                _ => continue,
+            };
+            notify_if_unfocused(&job_handle, &title, &body);
+        }
+    });
+
+    // The first run downloads several GB, so the model becoming ready is worth a notification too.
+    let mut status_receiver = embedder::subscribe_status();
+    tauri::async_runtime::spawn(async move {
+        let mut was_loading = status_receiver.borrow_and_update().is_loading();
+        while status_receiver.changed().await.is_ok() {
+            let status = status_receiver.borrow_and_update().clone();
+            let (title, body) = match &status {
+                EmbedderStatus::Ready { model_repo } if was_loading => ("Embedding model ready".to_string(), format!("{} is loaded; projects can be processed and queried.", model_repo)),
+                EmbedderStatus::Failed { model_repo, message } if was_loading => (format!("Could not load {}", model_repo), message.clone()),
+                _ => {
+                    was_loading = status.is_loading();
+                    continue;
+                }
+            };
+            was_loading = false;
+            if app_state.config.current().notifications.enabled {
+                notify_if_unfocused(&app_handle, &title, &body);
+            }
+        }
+    });
+}
+
+// The last path segment of a job label: `/work/my-crate` -> `my-crate`,
+// `https://github.com/serde-rs/json@v1` -> `json@v1`; `serde@1.0.0` stays.
+fn display_name(label: &str) -> String {
+    Path::new(label.trim_end_matches(['/', '\\'])).file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| label.to_string())
+}
+
+fn notify_if_unfocused(app_handle: &AppHandle, title: &str, body: &str) {
+    let focused = app_handle.webview_windows().values().any(|window| window.is_focused().unwrap_or(false));
+    if focused {
+        return;
+    }
+    if let Err(e) = app_handle.notification().builder().title(title).body(body).show() {
+        log::warn!("Failed to show notification '{}': {}", title, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_name() {
+        assert_eq!(display_name("/work/my-crate"), "my-crate");
+        assert_eq!(display_name("/work/my-crate/"), "my-crate");
+        assert_eq!(display_name("serde@1.0.0"), "serde@1.0.0");
+        assert_eq!(display_name("https://github.com/serde-rs/json@v1"), "json@v1");
+    }
+}
@@ -0,0 +1,43 @@
+use anyhow::{Context, Result};
+use candle_core::{Device, Tensor};
+use ndarray::Array2;
+use ort::session::Session;
+use std::path::Path;
+
+use crate::embedder::EmbeddingBackend;
+
+/// An ONNX export of an embedding model (e.g. a repo's `onnx/model.onnx`), run on the CPU by
+/// ONNX Runtime instead of candle. Needs the `ort` cargo feature.
+pub struct OnnxModel {
+    session: Session,
+    // BERT exports take `token_type_ids`; others don't accept it.
+    takes_token_type_ids: bool,
+}
+
+impl OnnxModel {
+    pub fn load(onnx_path: &Path) -> Result<Self> {
+        let session = Session::builder()?.commit_from_file(onnx_path)
+            .with_context(|| format!("Failed to load ONNX model {}", onnx_path.display()))?;
+        let takes_token_type_ids = session.inputs.iter().any(|input| input.name == "token_type_ids");
+        Ok(Self { session, takes_token_type_ids })
+    }
+}
+
+impl EmbeddingBackend for OnnxModel {
+    fn forward(&self, token_ids: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        let (batch_size, seq_len) = token_ids.dims2()?;
+        let as_i64 = |values: Vec<i64>| Array2::from_shape_vec((batch_size, seq_len), values);
+        let ids = as_i64(token_ids.flatten_all()?.to_vec1::<u32>()?.into_iter().map(i64::from).collect())?;
+        let mask = as_i64(attention_mask.flatten_all()?.to_vec1::<u8>()?.into_iter().map(i64::from).collect())?;
+        let outputs = if self.takes_token_type_ids {
+            let token_type_ids = Array2::<i64>::zeros((batch_size, seq_len));
+            self.session.run(ort::inputs!["input_ids" => ids, "attention_mask" => mask, "token_type_ids" => token_type_ids]?)?
+        } else {
+            self.session.run(ort::inputs!["input_ids" => ids, "attention_mask" => mask]?)?
+        };
+        // sentence-transformers and Optimum exports call it `last_hidden_state`; otherwise take the first output.
+        let hidden = outputs.get("last_hidden_state").unwrap_or(&outputs[0]).try_extract_tensor::<f32>()?;
+        let shape = hidden.shape().to_vec();
+        Ok(Tensor::from_iter(hidden.iter().copied(), &Device::Cpu)?.reshape(shape)?)
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::{anyhow, Result};
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::jobs::{JobPhase, ProgressReporter};
+use crate::rustdoc_processor::{self, CrateDocs};
+
+/// Rustdoc JSON files parsed at once. Parsing is CPU-bound and a large crate's JSON takes
+/// hundreds of MB while it is read, so more would mostly cost memory.
+pub const PARSE_CONCURRENCY: usize = 4;
+
+/// Parses rustdoc JSON files on the blocking pool, at most `PARSE_CONCURRENCY` at a time, so a
+/// job can go on generating the next file (or parse several targets and crates) meanwhile.
+/// Each parsed crate is reported as `JobPhase::CrateParsed`.
+pub struct ParseSet {
+    tasks: JoinSet<(usize, PathBuf, Result<CrateDocs>)>,
+    permits: Arc<Semaphore>,
+    parsed: Arc<AtomicUsize>,
+    // Files expected in total, for progress; the actual count may end up lower.
+    total: usize,
+    next_position: usize,
+    progress: ProgressReporter,
+}
+
+impl ParseSet {
+    pub fn new(total: usize, progress: &ProgressReporter) -> Self {
+        Self {
+            tasks: JoinSet::new(),
+            permits: Arc::new(Semaphore::new(PARSE_CONCURRENCY)),
+            parsed: Arc::default(),
+            total,
+            next_position: 0,
+            progress: progress.clone(),
+        }
+    }
+
+    /// Starts parsing `json_path`, first waiting while `PARSE_CONCURRENCY` parses are running.
+    pub async fn spawn(&mut self, json_path: PathBuf) {
+        let permit = self.permits.clone().acquire_owned().await.expect("the parse semaphore is never closed");
+        let position = self.next_position;
+        self.next_position += 1;
+        let (parsed, total, progress) = (self.parsed.clone(), self.total, self.progress.clone());
+        self.tasks.spawn_blocking(move || {
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| rustdoc_processor::parse_rustdoc_json_file(&json_path)))
+                .unwrap_or_else(|_| Err(anyhow!("Parsing {} panicked", json_path.display())));
+            drop(permit);
+            if let Ok(crate_docs) = &result {
+                let done = parsed.fetch_add(1, Ordering::SeqCst) + 1;
+                progress.report(JobPhase::CrateParsed { crate_name: crate_docs.crate_name.clone(), done, total });
+            }
+            (position, json_path, result)
+        });
+    }
+
+    /// Waits for every parse. Results are in the order the files were added.
+    pub async fn finish(mut self) -> Vec<(PathBuf, Result<CrateDocs>)> {
+        let mut results = Vec::with_capacity(self.next_position);
+        while let Some(joined) = self.tasks.join_next().await {
+            // Panics are caught in the task and nothing aborts it, so joining can't fail.
+            results.push(joined.expect("parse task failed to join"));
+        }
+        results.sort_by_key(|(position, ..)| *position);
+        results.into_iter().map(|(_, json_path, result)| (json_path, result)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigHandle;
+    use crate::jobs::{JobManager, JobPriority};
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_parse_set_keeps_order() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let crate_json = |name: &str| serde_json::json!({
+            "format_version": 39,
+            "root": "0",
+            "index": { "0": { "name": name, "docs": null, "inner": { "module": { "items": [] } } } },
+            "paths": {}
+        }).to_string();
+        let mut json_paths = Vec::new();
+        for name in ["alpha", "beta", "gamma", "delta", "epsilon", "zeta"] {
+            let json_path = dir.path().join(format!("{}.json", name));
+            fs::write(&json_path, crate_json(name))?;
+            json_paths.push(json_path);
+        }
+        let broken = dir.path().join("broken.json");
+        fs::write(&broken, "{")?;
+        json_paths.insert(2, broken.clone());
+
+        let manager = JobManager::new(ConfigHandle::load(PathBuf::from("/nonexistent/config.toml")));
+        let mut events = manager.subscribe_progress();
+        let id = manager.submit_with_progress("test", "parse", JobPriority::Normal, |progress| async move {
+            let mut parses = ParseSet::new(json_paths.len(), &progress);
+            for json_path in json_paths {
+                parses.spawn(json_path).await;
+            }
+            let names: Vec<String> = parses.finish().await.into_iter()
+                .map(|(json_path, result)| result.map(|docs| docs.crate_name).unwrap_or_else(|_| format!("error:{}", json_path.display())))
+                .collect();
+            Ok(names.join(","))
+        })?;
+        let names = manager.wait(id).await.expect("the job succeeds");
+        assert_eq!(names, format!("alpha,beta,error:{},gamma,delta,epsilon,zeta", broken.display()));
+
+        let mut parsed = 0;
+        while let Ok(event) = events.try_recv() {
+            if let JobPhase::CrateParsed { total, .. } = event.progress.phase {
+                assert_eq!(total, 7);
+                parsed += 1;
+            }
+        }
+        assert_eq!(parsed, 6);
+        Ok(())
+    }
+}
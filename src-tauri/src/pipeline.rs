@@ -0,0 +1,1109 @@
+use rust_mcp_sdk::mcp_tool::JsonSchema;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::bm25;
+use crate::browse::{self, RelatedItem};
+use crate::chunking;
+use crate::config::{RustdocSettings, SearchSettings};
+use crate::docs_rs;
+use crate::embedder;
+use crate::embedding_text::{embedding_text, example_text, MemberIndex};
+use crate::error::AppError;
+use crate::events::StateEvent;
+use crate::incremental;
+use crate::jobs::{JobId, JobPhase, JobPriority, ProgressReporter};
+use crate::journal::{ProcessingJournal, EMBED_BATCH_SIZE};
+use crate::mcp_server::{AppState, ProjectData, SearchFilter};
+use crate::parse_set::ParseSet;
+use crate::reranker;
+use crate::store::RegistryEntry;
+use crate::storage::QueryHistoryEntry;
+use crate::rustdoc_processor::{self, CrateDocs, Deprecation, DocItem, DocTargets};
+use crate::cfg::Cfg;
+
+/// What to process: a Cargo project directory and which parts of it.
+#[derive(Debug, Clone)]
+pub struct ProcessOptions {
+    pub path: String,
+    /// Also document and index the project's direct dependencies.
+    pub include_dependencies: bool,
+    pub targets: DocTargets,
+}
+
+/// The outcome of a processing run.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessOutcome {
+    pub project_path: String,
+    pub item_count: usize,
+    pub embedded_count: usize,
+    /// The sources were unchanged since the last run, so nothing was redone.
+    pub up_to_date: bool,
+    /// Processed projects, this one included.
+    pub total_projects: usize,
+}
+
+impl fmt::Display for ProcessOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.up_to_date {
+            write!(f, "Project {} is up to date ({} items, {} embedded).", self.project_path, self.item_count, self.embedded_count)
+        } else {
+            write!(f, "Successfully processed project {} and embedded {} items. Total processed projects: {}.", self.project_path, self.embedded_count, self.total_projects)
+        }
+    }
+}
+
+/// The key older versions of a project are kept under once a newer version is processed.
+pub fn versioned_key(project_path: &str, version: &str) -> String {
+    format!("{}@{}", project_path, version)
+}
+
+// Whether the project stored under `key` is `project_path`, current or archived.
+fn is_version_of(key: &str, project_path: &str, data: &ProjectData) -> bool {
+    key == project_path || data.version.as_deref().is_some_and(|v| key == versioned_key(project_path, v))
+}
+
+fn journal_error(e: anyhow::Error) -> AppError {
+    AppError::internal(format!("Failed to write processing journal: {:#}", e))
+}
+
+// A parse failure ends the run. Its journal is dropped rather than left at `Generated`, where a
+// resume would reuse the same JSON and fail again.
+fn discard_journal(journal: ProcessingJournal, path: &str) {
+    if let Err(e) = journal.complete() {
+        log::warn!("Failed to clear the processing journal of {}: {:#}", path, e);
+    }
+}
+
+/// The rustdoc processing pipeline, run on the job queue by both the Tauri commands and the MCP
+/// server: `cargo rustdoc`, parse, (dependencies), embed, index. Each stage is journaled, so an
+/// interrupted run resumes where it stopped; unchanged sources are skipped entirely. When the
+/// package version changed, the previous docs stay available under `versioned_key`.
+pub async fn process_project(app_state: Arc<AppState>, opts: ProcessOptions, progress: ProgressReporter) -> Result<ProcessOutcome, AppError> {
+    let ProcessOptions { path, include_dependencies, targets } = opts;
+    let project_path_obj = Path::new(&path);
+    let rustdoc_settings = app_state.config.current().rustdoc_for_project(&path, &app_state.cache_dir);
+    // Unchanged sources need no work at all; changed ones still reuse the vectors of unchanged items.
+    let previous = app_state.projects().await.get(&path).cloned();
+    let fingerprint = incremental::source_fingerprint(project_path_obj, &rustdoc_settings, targets)
+        .map_err(|e| log::warn!("Failed to fingerprint {}, processing it in full: {:?}", path, e))
+        .ok();
+    if let (Some(previous), Some(fingerprint)) = (&previous, fingerprint) {
+        if incremental::is_up_to_date(previous, fingerprint, embedder::global_model_id().await.as_deref(), include_dependencies) {
+            log::info!("Sources of {} are unchanged since it was last processed; skipping", path);
+            return Ok(ProcessOutcome {
+                item_count: previous.item_count(),
+                embedded_count: previous.embedded_item_count(),
+                up_to_date: true,
+                total_projects: app_state.projects().await.len(),
+                project_path: path,
+            });
+        }
+    }
+
+    // The journal lets a crashed/force-quit run resume from its last completed stage. Opened only
+    // now: it is written right away, and an up-to-date run has nothing to complete it.
+    let mut journal = ProcessingJournal::open(&app_state.journal_dir, &path)
+        .map_err(|e| AppError::internal(format!("Failed to open processing journal for {}: {:#}", path, e)))?;
+    let version = rustdoc_processor::package_version(project_path_obj);
+
+    progress.report(JobPhase::Rustdoc);
+    let generated = match journal.generated_json_paths() {
+        Some(json_paths) => {
+            log::info!("Reusing rustdoc JSON from interrupted run: {:?}", json_paths);
+            Ok(json_paths)
+        }
+        None => rustdoc_processor::generate_rustdoc_json(project_path_obj, &app_state.rustdoc_output_dir, &rustdoc_settings, targets, progress.cancel_flag()),
+    };
+    let json_paths = match generated {
+        Ok(json_paths) => json_paths,
+        Err(e) => {
+            progress.check_cancelled()?;
+            log::error!("Failed to generate rustdoc JSON for {}: {:?}", path, e);
+            return Err(AppError::from_rustdoc_error(&rustdoc_settings.toolchain, &e));
+        }
+    };
+    log::info!("Generated rustdoc at: {:?}", json_paths);
+    journal.record_generated(&json_paths).map_err(journal_error)?;
+
+    progress.report(JobPhase::Parse);
+    let mut crate_docs = match parse_targets(&json_paths, &progress).await {
+        Ok(crate_docs) => crate_docs,
+        Err(e) => {
+            log::error!("Failed to parse rustdoc JSON for {}: {:?}", path, e);
+            discard_journal(journal, &path);
+            return Err(AppError::ParseFailed { message: format!("{}: {:#}", path, e) });
+        }
+    };
+    log::info!("Parsed rustdoc for crate: {}", crate_docs.crate_name);
+    skip_private_items(&mut crate_docs, rustdoc_settings.skip_private_items);
+    // Embeddings from batches that finished before an interruption are kept.
+    let mut project_embeddings = journal.completed_embeddings()
+        .map_err(|e| AppError::internal(format!("Failed to read journaled embeddings: {:#}", e)))?;
+    let dependency_docs = if include_dependencies {
+        progress.report(JobPhase::Dependencies);
+        load_dependency_docs(project_path_obj, &app_state.rustdoc_output_dir, &rustdoc_settings, &progress).await
+            .map_err(|e| progress.check_cancelled().err().unwrap_or_else(|| AppError::from_rustdoc_error(&rustdoc_settings.toolchain, &e)))?
+    } else {
+        Vec::new()
+    };
+
+    let (mut truncated, mut text_hashes) = (HashSet::new(), HashMap::new());
+    let embedding_model = embed_items(&app_state, &crate_docs, &dependency_docs, previous.as_ref(), &mut project_embeddings, &mut truncated, &mut text_hashes, &mut journal, &json_paths, &progress).await?;
+
+    let embedded_count = chunking::embedded_item_count(project_embeddings.keys());
+    // Builds the ANN index; can take a moment for large projects.
+    progress.report(JobPhase::Index);
+    let project_data = ProjectData::new(crate_docs, dependency_docs, project_embeddings)
+        .with_source_fingerprint(fingerprint)
+        .with_embedding_model(embedding_model)
+        .with_truncated_embeddings(truncated)
+        .with_embedded_text_hashes(text_hashes)
+        .with_version(version)
+        .with_doc_targets(targets);
+    store_project(&app_state, path, project_data, previous, embedded_count, journal).await
+}
+
+// Drops the items that aren't `pub` when `rustdoc.skip_private_items` is set.
+fn skip_private_items(crate_docs: &mut CrateDocs, skip: bool) {
+    if skip {
+        let dropped = crate_docs.retain_public();
+        log::info!("Skipped {} private items of {}", dropped, crate_docs.crate_name);
+    }
+}
+
+// Parses the JSON of a package's targets concurrently and merges them as
+// `rustdoc_processor::parse_rustdoc_json_files` does.
+async fn parse_targets(json_paths: &[PathBuf], progress: &ProgressReporter) -> anyhow::Result<CrateDocs> {
+    let mut parses = ParseSet::new(json_paths.len(), progress);
+    for json_path in json_paths {
+        parses.spawn(json_path.clone()).await;
+    }
+    let targets = parses.finish().await.into_iter()
+        .map(|(json_path, result)| result.map_err(|e| e.context(format!("Failed to parse {}", json_path.display()))))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    rustdoc_processor::merge_target_docs(targets)
+}
+
+// Documents the direct dependencies one `cargo rustdoc` at a time (they share the target
+// directory's lock anyway) while the JSON generated so far is parsed concurrently. Dependencies
+// that fail to document are logged and skipped so one broken crate doesn't fail the whole
+// project; cancellation is not.
+async fn load_dependency_docs(project_path: &Path, target_dir: &Path, settings: &RustdocSettings, progress: &ProgressReporter) -> anyhow::Result<Vec<CrateDocs>> {
+    let (metadata_dir, metadata_settings) = (project_path.to_path_buf(), settings.clone());
+    let (dependencies, cargo_target_dir) = tokio::task::spawn_blocking(move || rustdoc_processor::direct_dependencies(&metadata_dir, &metadata_settings)).await??;
+    log::info!("Documenting {} direct dependencies of {}", dependencies.len(), project_path.display());
+    let mut parses = ParseSet::new(dependencies.len(), progress);
+    for dependency in dependencies {
+        if progress.cancel_flag().is_cancelled() {
+            anyhow::bail!("Cancelled while documenting dependencies");
+        }
+        let (project_dir, cargo_target_dir, target_dir) = (project_path.to_path_buf(), cargo_target_dir.clone(), target_dir.to_path_buf());
+        let (settings, cancel) = (settings.clone(), progress.cancel_flag().clone());
+        let package = dependency.clone();
+        let generated = tokio::task::spawn_blocking(move || rustdoc_processor::generate_dependency_rustdoc_json(&project_dir, &package, &cargo_target_dir, &target_dir, &settings, &cancel)).await?;
+        match generated {
+            Ok(json_path) => parses.spawn(json_path).await,
+            Err(e) => log::warn!("Skipping docs for dependency {} {}: {:?}", dependency.name, dependency.version, e),
+        }
+    }
+    Ok(parses.finish().await.into_iter()
+        .filter_map(|(json_path, result)| result.map_err(|e| log::warn!("Skipping docs for dependency JSON {}: {:?}", json_path.display(), e)).ok())
+        .collect())
+}
+
+/// The project key the standard library is indexed under.
+pub const STD_PROJECT_KEY: &str = "rust-std";
+
+/// Indexes std, core and alloc from the `rust-docs-json` component of the configured toolchain
+/// (installed on first use) as the project `STD_PROJECT_KEY`, so questions about `Vec` or
+/// `Iterator` work without any user project. std is the project crate, core and alloc its
+/// dependencies. Re-running is a no-op until the toolchain's rustc version changes.
+pub async fn process_std_library(app_state: Arc<AppState>, progress: ProgressReporter) -> Result<ProcessOutcome, AppError> {
+    let path = STD_PROJECT_KEY.to_string();
+    let rustdoc_settings = app_state.config.current().rustdoc.clone();
+
+    progress.report(JobPhase::Rustdoc);
+    let settings = rustdoc_settings.clone();
+    let std_json = tokio::task::spawn_blocking(move || rustdoc_processor::std_library_json(&settings))
+        .await
+        .map_err(|e| AppError::internal(format!("Installing rust-docs-json panicked: {}", e)))?
+        .map_err(|e| {
+            log::error!("Failed to get the standard library rustdoc JSON: {:?}", e);
+            AppError::from_rustdoc_error(&rustdoc_settings.toolchain, &e)
+        })?;
+    progress.check_cancelled()?;
+
+    let previous = app_state.projects().await.get(&path).cloned();
+    if let Some(previous) = &previous {
+        let model_id = embedder::global_model_id().await;
+        if previous.version.as_deref() == Some(std_json.rustc_version.as_str()) && (model_id.is_none() || previous.embedding_model == model_id) {
+            log::info!("The standard library docs of rustc {} are already indexed; skipping", std_json.rustc_version);
+            return Ok(ProcessOutcome {
+                item_count: previous.item_count(),
+                embedded_count: previous.embedded_item_count(),
+                up_to_date: true,
+                total_projects: app_state.projects().await.len(),
+                project_path: path,
+            });
+        }
+    }
+    let mut journal = ProcessingJournal::open(&app_state.journal_dir, &path)
+        .map_err(|e| AppError::internal(format!("Failed to open processing journal for {}: {:#}", path, e)))?;
+    journal.record_generated(&std_json.json_paths).map_err(journal_error)?;
+
+    progress.report(JobPhase::Parse);
+    let mut parses = ParseSet::new(std_json.json_paths.len(), &progress);
+    for json_path in &std_json.json_paths {
+        parses.spawn(json_path.clone()).await;
+    }
+    let mut parsed = Vec::new();
+    for (json_path, result) in parses.finish().await {
+        match result {
+            Ok(docs) => parsed.push(docs),
+            Err(e) => {
+                log::error!("Failed to parse {}: {:?}", json_path.display(), e);
+                discard_journal(journal, &path);
+                return Err(AppError::ParseFailed { message: format!("{}: {:#}", json_path.display(), e) });
+            }
+        }
+    }
+    let crate_docs = parsed.remove(0);
+    let dependency_docs = parsed;
+    let mut project_embeddings = journal.completed_embeddings()
+        .map_err(|e| AppError::internal(format!("Failed to read journaled embeddings: {:#}", e)))?;
+    let (mut truncated, mut text_hashes) = (HashSet::new(), HashMap::new());
+    let embedding_model = embed_items(&app_state, &crate_docs, &dependency_docs, previous.as_ref(), &mut project_embeddings, &mut truncated, &mut text_hashes, &mut journal, &std_json.json_paths, &progress).await?;
+
+    let embedded_count = chunking::embedded_item_count(project_embeddings.keys());
+    progress.report(JobPhase::Index);
+    let project_data = ProjectData::new(crate_docs, dependency_docs, project_embeddings)
+        .with_embedding_model(embedding_model)
+        .with_truncated_embeddings(truncated)
+        .with_embedded_text_hashes(text_hashes)
+        .with_version(Some(std_json.rustc_version));
+    store_project(&app_state, path, project_data, previous, embedded_count, journal).await
+}
+
+/// Indexes an existing rustdoc JSON file (e.g. built in CI) as the project `project_key`, without
+/// running cargo: parse, embed, index. Re-importing reuses the vectors of unchanged items.
+pub async fn import_rustdoc_json(app_state: Arc<AppState>, json_path: PathBuf, project_key: String, version: Option<String>, progress: ProgressReporter) -> Result<ProcessOutcome, AppError> {
+    if !json_path.is_file() {
+        return Err(AppError::PathNotFound { path: json_path.display().to_string() });
+    }
+    let previous = app_state.projects().await.get(&project_key).cloned();
+    let mut journal = ProcessingJournal::open(&app_state.journal_dir, &project_key)
+        .map_err(|e| AppError::internal(format!("Failed to open processing journal for {}: {:#}", project_key, e)))?;
+    let json_paths = vec![json_path];
+    journal.record_generated(&json_paths).map_err(journal_error)?;
+
+    progress.report(JobPhase::Parse);
+    let parse_paths = json_paths.clone();
+    let parsed = tokio::task::spawn_blocking(move || rustdoc_processor::parse_rustdoc_json_files(&parse_paths))
+        .await
+        .map_err(|e| AppError::internal(format!("Parsing rustdoc JSON panicked: {}", e)))?;
+    let mut crate_docs = match parsed {
+        Ok(crate_docs) => crate_docs,
+        Err(e) => {
+            log::error!("Failed to parse {}: {:?}", json_paths[0].display(), e);
+            discard_journal(journal, &project_key);
+            return Err(AppError::ParseFailed { message: format!("{}: {:#}", json_paths[0].display(), e) });
+        }
+    };
+    log::info!("Imported rustdoc JSON of crate {} as {}", crate_docs.crate_name, project_key);
+    skip_private_items(&mut crate_docs, app_state.config.current().rustdoc.skip_private_items);
+    progress.check_cancelled()?;
+    let mut project_embeddings = journal.completed_embeddings()
+        .map_err(|e| AppError::internal(format!("Failed to read journaled embeddings: {:#}", e)))?;
+    let (mut truncated, mut text_hashes) = (HashSet::new(), HashMap::new());
+    let embedding_model = embed_items(&app_state, &crate_docs, &[], previous.as_ref(), &mut project_embeddings, &mut truncated, &mut text_hashes, &mut journal, &json_paths, &progress).await?;
+
+    let embedded_count = chunking::embedded_item_count(project_embeddings.keys());
+    progress.report(JobPhase::Index);
+    let project_data = ProjectData::new(crate_docs, Vec::new(), project_embeddings)
+        .with_embedding_model(embedding_model)
+        .with_truncated_embeddings(truncated)
+        .with_embedded_text_hashes(text_hashes)
+        .with_version(version);
+    store_project(&app_state, project_key, project_data, previous, embedded_count, journal).await
+}
+
+/// Indexes `name@version` from the rustdoc JSON docs.rs built for it, without a local toolchain, as
+/// the project `docs_rs::project_key(name)`. A no-op if that version is already indexed with the
+/// loaded model.
+pub async fn process_docsrs_crate(app_state: Arc<AppState>, name: String, version: String, progress: ProgressReporter) -> Result<ProcessOutcome, AppError> {
+    let path = docs_rs::project_key(&name);
+    if let Some(previous) = app_state.projects().await.get(&path) {
+        let model_id = embedder::global_model_id().await;
+        if previous.version.as_deref() == Some(version.as_str()) && (model_id.is_none() || previous.embedding_model == model_id) {
+            log::info!("{}@{} from docs.rs is already indexed; skipping", name, version);
+            return Ok(ProcessOutcome {
+                item_count: previous.item_count(),
+                embedded_count: previous.embedded_item_count(),
+                up_to_date: true,
+                total_projects: app_state.projects().await.len(),
+                project_path: path,
+            });
+        }
+    }
+    let json_dir = app_state.cache_dir.join("docs_rs");
+    let json_path = docs_rs::fetch_json(&app_state.http_client, &json_dir, &name, &version).await
+        .map_err(|e| AppError::internal(format!("{:#}", e)))?;
+    progress.check_cancelled()?;
+    import_rustdoc_json(app_state, json_path, path, Some(version), progress).await
+}
+
+/// Validates the arguments of an import and submits it as an `import_rustdoc_json` job. Shared by
+/// the Tauri command and the MCP tool.
+pub fn submit_import(app_state: &Arc<AppState>, json_path: String, project_label: String, version: Option<String>, priority: JobPriority) -> Result<JobId, AppError> {
+    let project_label = project_label.trim().to_string();
+    if project_label.is_empty() {
+        return Err(AppError::InvalidArgument { message: "project_label must not be empty".to_string() });
+    }
+    let json_path = PathBuf::from(json_path);
+    if !json_path.is_file() {
+        return Err(AppError::PathNotFound { path: json_path.display().to_string() });
+    }
+    let job_state = app_state.clone();
+    let label = project_label.clone();
+    app_state.jobs.submit_with_progress("import_rustdoc_json", &label, priority, |progress| async move {
+        import_rustdoc_json(job_state, json_path, project_label, version, progress).await.map(|outcome| outcome.to_string())
+    })
+}
+
+/// Re-embeds an already processed project with the loaded model, without running rustdoc again,
+/// e.g. after switching models. Vectors the current model already produced are kept.
+pub async fn reembed_project(app_state: Arc<AppState>, path: String, progress: ProgressReporter) -> Result<ProcessOutcome, AppError> {
+    let previous = app_state.projects().await.get(&path).cloned()
+        .ok_or_else(|| AppError::ProjectNotFound { project_path: path.clone() })?;
+    embedder::wait_until_loaded().await;
+    if embedder::global_model_id().await.is_none() {
+        return Err(AppError::embedder_not_ready(&app_state.config.current().embedder.model_repo));
+    }
+    let mut journal = ProcessingJournal::open(&app_state.journal_dir, &path)
+        .map_err(|e| AppError::internal(format!("Failed to open processing journal for {}: {:#}", path, e)))?;
+    let crate_docs = CrateDocs::clone(&previous.crate_docs);
+    let dependency_docs: Vec<CrateDocs> = previous.dependency_docs.iter().map(|docs| CrateDocs::clone(docs)).collect();
+    let mut project_embeddings = journal.completed_embeddings()
+        .map_err(|e| AppError::internal(format!("Failed to read journaled embeddings: {:#}", e)))?;
+    // No rustdoc JSON is involved; the journal only keeps the finished batches.
+    let (mut truncated, mut text_hashes) = (HashSet::new(), HashMap::new());
+    let embedding_model = embed_items(&app_state, &crate_docs, &dependency_docs, Some(&previous), &mut project_embeddings, &mut truncated, &mut text_hashes, &mut journal, &[], &progress).await?;
+
+    let embedded_count = chunking::embedded_item_count(project_embeddings.keys());
+    progress.report(JobPhase::Index);
+    let project_data = ProjectData::new(crate_docs, dependency_docs, project_embeddings)
+        .with_source_fingerprint(previous.source_fingerprint)
+        .with_embedding_model(embedding_model)
+        .with_truncated_embeddings(truncated)
+        .with_embedded_text_hashes(text_hashes)
+        .with_version(previous.version.clone())
+        .with_doc_targets(previous.doc_targets);
+    store_project(&app_state, path, project_data, None, embedded_count, journal).await
+}
+
+/// Brings back the projects of the store's registry (see `RegistryEntry`) whose project files
+/// couldn't be loaded, e.g. after a store format change. Each is re-parsed from its cached rustdoc
+/// JSON right away, so it is listed and browsable, then re-embedded by a low-priority
+/// `reembed_project` job. Dependency docs aren't recorded; re-process the project for those.
+pub async fn restore_registry(app_state: Arc<AppState>) {
+    let loaded: HashSet<String> = app_state.projects().await.keys().cloned().collect();
+    let store = app_state.store.clone();
+    let entries = tokio::task::spawn_blocking(move || store.registry()).await.unwrap_or_default();
+    for entry in entries.into_iter().filter(|entry| !loaded.contains(&entry.project_path)) {
+        let RegistryEntry { project_path, json_paths, version } = entry;
+        if let Some(missing) = json_paths.iter().find(|json_path| !json_path.is_file()) {
+            log::warn!("Can't restore {}: its rustdoc JSON {} is gone. Process it again.", project_path, missing.display());
+            continue;
+        }
+        let mut crate_docs = match tokio::task::spawn_blocking(move || rustdoc_processor::parse_rustdoc_json_files(&json_paths)).await {
+            Ok(Ok(crate_docs)) => crate_docs,
+            Ok(Err(e)) => {
+                log::warn!("Can't restore {}: {:#}", project_path, e);
+                continue;
+            }
+            Err(e) => {
+                log::error!("Restoring {} panicked: {:?}", project_path, e);
+                continue;
+            }
+        };
+        skip_private_items(&mut crate_docs, app_state.config.current().rustdoc.skip_private_items);
+        // Kept in memory only until re-embedded, so an interrupted restore is retried next start.
+        let project_data = ProjectData::new(crate_docs, Vec::new(), HashMap::new()).with_version(version);
+        let item_count = project_data.item_count();
+        app_state.projects().await.entry(project_path.clone()).or_insert(project_data);
+        log::info!("Restored {} ({} items) from its rustdoc JSON; re-embedding it in the background", project_path, item_count);
+        app_state.emit_state_event(StateEvent::ProjectAdded { project_path: project_path.clone(), item_count, embedded_count: 0 });
+
+        let (job_state, job_path) = (app_state.clone(), project_path.clone());
+        let submitted = app_state.jobs.submit_with_progress("reembed_project", &project_path, JobPriority::Low, |progress| async move {
+            reembed_project(job_state, job_path, progress).await.map(|outcome| outcome.to_string())
+        });
+        if let Err(e) = submitted {
+            log::warn!("Could not queue re-embedding {}: {}. Re-embed it once the queue has room.", project_path, e);
+        }
+    }
+}
+
+// Inserts (and persists) a freshly processed project and clears its journal. A version bump
+// keeps the previous version's docs queryable under `versioned_key` instead of overwriting them.
+// The rustdoc JSON it came from is recorded in the store's registry.
+async fn store_project(app_state: &AppState, path: String, project_data: ProjectData, previous: Option<ProjectData>, embedded_count: usize, journal: ProcessingJournal) -> Result<ProcessOutcome, AppError> {
+    let item_count = project_data.item_count();
+    if let Some(json_paths) = journal.generated_json_paths() {
+        let (store, key, version) = (app_state.store.clone(), path.clone(), project_data.version.clone());
+        match tokio::task::spawn_blocking(move || store.record_json_paths(&key, &json_paths, version.as_deref())).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log::warn!("Failed to record the rustdoc JSON of {}: {:?}", path, e),
+            Err(e) => log::error!("Recording the rustdoc JSON of {} panicked: {:?}", path, e),
+        }
+    }
+    if let Some(previous) = previous.filter(|p| p.version.is_some() && p.version != project_data.version) {
+        let archived_key = versioned_key(&path, previous.version.as_deref().unwrap_or_default());
+        log::info!("Keeping the previous docs of {} as {}", path, archived_key);
+        app_state.insert_project(archived_key, previous).await;
+    }
+    let total_projects = app_state.insert_project(path.clone(), project_data).await;
+    if let Err(e) = journal.complete() {
+        log::warn!("Failed to clear processing journal for {}: {:?}", path, e);
+    }
+
+    let outcome = ProcessOutcome { project_path: path, item_count, embedded_count, up_to_date: false, total_projects };
+    log::info!("{}", outcome);
+    Ok(outcome)
+}
+
+// Embeds the docs and code examples of every item not embedded yet, reusing the vectors of
+// unchanged items from `previous` and journaling each batch. Keys whose text is longer than the
+// model's `max_length` go into `truncated`, and each embedded item's `incremental::embedded_text_hash`
+// into `text_hashes`. Returns the model used, or `None` (and embeds nothing) if no model is loaded.
+#[allow(clippy::too_many_arguments)]
+async fn embed_items(
+    app_state: &AppState,
+    crate_docs: &CrateDocs,
+    dependency_docs: &[CrateDocs],
+    previous: Option<&ProjectData>,
+    project_embeddings: &mut HashMap<String, Vec<f32>>,
+    truncated: &mut HashSet<String>,
+    text_hashes: &mut HashMap<String, u64>,
+    journal: &mut ProcessingJournal,
+    json_paths: &[PathBuf],
+    progress: &ProgressReporter,
+) -> Result<Option<String>, AppError> {
+    // Jobs submitted while the model is still warming up wait for it instead of skipping embeddings.
+    embedder::wait_until_loaded().await;
+    let mut embedding_model = None;
+    // Only the tokenizer is needed to chunk; the model itself stays on the embedder worker.
+    let loaded = embedder::with_global_embedder(|embedder| embedder.map(|e| (e.model_id(), e.dimension().ok(), e.chunker(), e.passage_prefix().to_string()))).await
+        .map_err(|e| AppError::internal(format!("Embedder worker failed: {:#}", e)))?;
+    if let Some((model_id, dimension, chunker, passage_prefix)) = loaded {
+        // Batches journaled by an interrupted run with another model can't be mixed in.
+        if journal.bind_embedding_model(&model_id, dimension).map_err(journal_error)? {
+            log::info!("Discarding journaled embeddings of {}: they came from another model than {}", crate_docs.crate_name, model_id);
+            project_embeddings.clear();
+        }
+        // Vectors from another model aren't comparable with this one's.
+        let previous = previous.filter(|p| p.embedding_model.as_deref() == Some(model_id.as_str()));
+        embedding_model = Some(model_id.clone());
+        let mut texts_to_embed = Vec::new();
+        let mut item_paths_for_embedding = Vec::new();
+        let mut reused_items = 0;
+
+        let all_items = crate_docs.items.iter().chain(dependency_docs.iter().flat_map(|docs| docs.items.iter()));
+        let members = MemberIndex::build(all_items.clone().map(|(_, item)| item));
+        for (item_full_path, doc_item) in all_items {
+            let Some(desc) = doc_item.description.as_deref().filter(|d| !d.trim().is_empty()) else {
+                continue;
+            };
+            // Long docs are split into overlapping chunks, each embedded on its own.
+            let chunks = chunker.chunk_text(desc).unwrap_or_else(|e| {
+                log::warn!("Failed to chunk docs of {}, embedding them whole: {:?}", item_full_path, e);
+                vec![desc]
+            });
+            let chunk_texts: Vec<String> = chunks.iter()
+                .map(|chunk| format!("{}{}", passage_prefix, embedding_text(doc_item, members.members_of(item_full_path), chunk)))
+                .collect();
+            let example_texts: Vec<String> = doc_item.examples.iter()
+                .map(|example| format!("{}{}", passage_prefix, example_text(doc_item, example)))
+                .collect();
+            let text_hash = incremental::embedded_text_hash(&model_id, chunk_texts.iter().chain(&example_texts));
+            text_hashes.insert(item_full_path.clone(), text_hash);
+            // The same texts were embedded last run: keep their vectors.
+            if let Some((p, reused)) = previous.and_then(|p| Some((p, incremental::reusable_embeddings(p, item_full_path, text_hash, chunk_texts.len())?))) {
+                for (chunk_idx, embedding) in reused.into_iter().enumerate() {
+                    let key = chunking::chunk_key(item_full_path, chunk_idx);
+                    if p.truncated_embeddings.contains(&key) {
+                        truncated.insert(key.clone());
+                    }
+                    project_embeddings.entry(key).or_insert_with(|| embedding.clone());
+                }
+                // The examples are part of the hash, so theirs are unchanged too.
+                for (example_idx, embedding) in chunking::example_embeddings(&p.embeddings, item_full_path).enumerate().take(doc_item.examples.len()) {
+                    let key = chunking::example_key(item_full_path, example_idx);
+                    if p.truncated_embeddings.contains(&key) {
+                        truncated.insert(key.clone());
+                    }
+                    project_embeddings.entry(key).or_insert_with(|| embedding.clone());
+                }
+                reused_items += 1;
+            } else {
+                for (chunk_idx, text) in chunk_texts.into_iter().enumerate() {
+                    let key = chunking::chunk_key(item_full_path, chunk_idx);
+                    if chunker.exceeds_max_length(&text).unwrap_or(false) {
+                        truncated.insert(key.clone());
+                    }
+                    if project_embeddings.contains_key(&key) {
+                        continue; // Embedded before an interruption
+                    }
+                    texts_to_embed.push(text);
+                    item_paths_for_embedding.push(key);
+                }
+            }
+            // Code examples get vectors of their own, so usage questions can match the code itself.
+            for (example_idx, text) in example_texts.into_iter().enumerate() {
+                let key = chunking::example_key(item_full_path, example_idx);
+                if chunker.exceeds_max_length(&text).unwrap_or(false) {
+                    truncated.insert(key.clone());
+                }
+                if project_embeddings.contains_key(&key) {
+                    continue;
+                }
+                texts_to_embed.push(text);
+                item_paths_for_embedding.push(key);
+            }
+        }
+        journal.record_parsed(json_paths, texts_to_embed.len()).map_err(journal_error)?;
+        if reused_items > 0 {
+            log::info!("Reusing embeddings of {} unchanged items of {}", reused_items, crate_docs.crate_name);
+        }
+        if !truncated.is_empty() {
+            log::warn!("{} texts of {} exceed the model's max_length and are embedded truncated", truncated.len(), crate_docs.crate_name);
+        }
+
+        if !texts_to_embed.is_empty() {
+            log::info!("Embedding {} docs for {} ({} already embedded)", texts_to_embed.len(), crate_docs.crate_name, project_embeddings.len());
+            let total_batches = texts_to_embed.len().div_ceil(EMBED_BATCH_SIZE);
+            for (batch_idx, (texts, paths)) in texts_to_embed.chunks(EMBED_BATCH_SIZE).zip(item_paths_for_embedding.chunks(EMBED_BATCH_SIZE)).enumerate() {
+                // Stop between batches; finished batches stay in the journal, so the run can be resumed.
+                progress.check_cancelled()?;
+                let (cache, batch_texts) = (app_state.embedding_cache.clone(), texts.to_vec());
+                match embedder::with_model(&model_id, move |embedder| cache.embed_batch(embedder, &batch_texts)).await {
+                    Ok(embeddings_vec) => {
+                        let batch_embeddings: Vec<(String, Vec<f32>)> = paths.iter().cloned().zip(embeddings_vec).collect();
+                        journal.record_embedded_batch(json_paths, batch_idx, total_batches, batch_embeddings.clone()).map_err(journal_error)?;
+                        project_embeddings.extend(batch_embeddings);
+                    }
+                    // Non-fatal: those items are only found by keyword.
+                    Err(e) => log::error!("Failed to embed batch {}/{} for {}: {:?}", batch_idx + 1, total_batches, crate_docs.crate_name, e),
+                }
+                progress.report(JobPhase::Embed { batch: batch_idx + 1, total_batches });
+            }
+            log::info!("Successfully embedded {} items for {}.", project_embeddings.len(), crate_docs.crate_name);
+        } else {
+            log::info!("No suitable descriptions found for embedding in {}.", crate_docs.crate_name);
+        }
+    } else {
+        log::warn!("Embedder not initialized. Skipping embedding generation for {}.", crate_docs.crate_name);
+    }
+    Ok(embedding_model)
+}
+
+// Phrasings searched per query, the original included; more mostly cost time.
+const MAX_QUERY_VARIANTS: usize = 4;
+// Reciprocal rank fusion constant: damps the weight of the very top ranks (60 is the usual choice).
+const RRF_K: f32 = 60.0;
+
+// Paraphrases of a terse query: scoped to the crate being searched and as a how-to question.
+// Longer or already question-shaped queries only get the crate variant.
+fn expand_query(query: &str, crate_name: Option<&str>) -> Vec<String> {
+    let query = query.trim();
+    let mut variants = Vec::new();
+    if let Some(crate_name) = crate_name.filter(|name| !query.contains(name)) {
+        variants.push(format!("{} {}", crate_name, query));
+    }
+    if query.split_whitespace().count() <= 6 && !query.ends_with('?') {
+        variants.push(format!("How do I {} in Rust?", query));
+    }
+    variants
+}
+
+// The phrasings `opts` asks to search, the original first, without duplicates.
+fn query_variants(opts: &QueryOptions, crate_name: Option<&str>) -> Vec<String> {
+    let expanded = if opts.expand_query { expand_query(&opts.query, crate_name) } else { Vec::new() };
+    let mut variants: Vec<String> = Vec::new();
+    for variant in std::iter::once(&opts.query).chain(&opts.additional_queries).chain(&expanded) {
+        let variant = variant.trim();
+        if !variant.is_empty() && !variants.iter().any(|v| v.eq_ignore_ascii_case(variant)) {
+            variants.push(variant.to_string());
+        }
+    }
+    variants.truncate(MAX_QUERY_VARIANTS);
+    variants
+}
+
+/// Reciprocal rank fusion: merges rankings (each best first) into one by summing `1 / (RRF_K +
+/// rank)` per hit, so items ranked well by several phrasings rise. Scores are those sums.
+pub fn reciprocal_rank_fusion(rankings: Vec<Vec<(String, DocItem, f32)>>) -> Vec<(String, DocItem, f32)> {
+    let mut fused: Vec<(String, DocItem, f32)> = Vec::new();
+    let mut positions: HashMap<(String, String), usize> = HashMap::new();
+    for ranking in rankings {
+        for (rank, (project_path, item, _)) in ranking.into_iter().enumerate() {
+            let contribution = 1.0 / (RRF_K + rank as f32 + 1.0);
+            let key = (project_path.clone(), item.full_path_str.clone());
+            match positions.get(&key) {
+                Some(&i) => fused[i].2 += contribution,
+                None => {
+                    positions.insert(key, fused.len());
+                    fused.push((project_path, item, contribution));
+                }
+            }
+        }
+    }
+    fused.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    fused
+}
+
+// Drops the hits scoring below `min_score`, keeping the order.
+fn retain_relevant(hits: &mut Vec<(String, DocItem, f32)>, min_score: f32) {
+    let before = hits.len();
+    hits.retain(|(_, _, score)| *score >= min_score);
+    if hits.len() < before {
+        log::debug!("Dropped {} hits scoring below {}", before - hits.len(), min_score);
+    }
+}
+
+// Top hits for one query embedding across the candidate projects, best first.
+fn search_projects(candidate_projects: &[(String, ProjectData)], query_embedding: &[f32], query: &str, fetch: usize, keyword_weight: f32, filter: &SearchFilter) -> Vec<(String, DocItem, f32)> {
+    let mut hits = Vec::new();
+    for (project_path, project_data) in candidate_projects {
+        // Top-k per project (ANN index + BM25 keyword scores), merged below.
+        for (doc_item, score) in project_data.hybrid_search(query_embedding, query, fetch, keyword_weight, filter) {
+            hits.push((project_path.clone(), doc_item.clone(), score));
+        }
+    }
+    hits.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    hits
+}
+
+// Share of their score added to items with code examples when a query prefers examples. Relative,
+// so it weighs the same on similarities, reranker relevance and the far smaller RRF sums.
+const EXAMPLE_BOOST: f32 = 0.1;
+// Code examples returned per result.
+const MAX_RESULT_EXAMPLES: usize = 2;
+
+/// A documentation query.
+#[derive(Debug, Clone)]
+pub struct QueryOptions {
+    pub query: String,
+    /// Only search this project; `None` searches every processed project.
+    pub project_path: Option<String>,
+    /// Only search projects of this version: exact ("0.6.3"), a prefix ("0.6") or `*` for every
+    /// kept version. With `project_path`, selects among that project's versions.
+    pub version: Option<String>,
+    pub num_results: usize,
+    pub filter: SearchFilter,
+    /// Rescore the top hits with the cross-encoder (`reranker` settings).
+    pub rerank: bool,
+    /// Rank items with code examples above similarly scored items without, for "how do I use X".
+    pub prefer_examples: bool,
+    /// More phrasings of the same question; each is searched and the rankings are fused.
+    pub additional_queries: Vec<String>,
+    /// Also search generated paraphrases of `query` (see `expand_query`), for terse queries.
+    pub expand_query: bool,
+    /// Drop hits scoring below this, so a query with nothing relevant returns no hits. Compared
+    /// with the hybrid similarity (roughly 0-1) of each phrasing's hits before their rankings are
+    /// fused, or with `rerank`, the reranker's 0-1 relevance. The example boost doesn't count.
+    pub min_score: Option<f32>,
+}
+
+/// The `num_results` items of a project closest to `item_path`, using the item's stored embedding
+/// as the query vector, best first. Scores are cosine similarities.
+pub async fn find_similar_items(app_state: &AppState, project_path: &str, item_path: &str, num_results: usize, filter: &SearchFilter) -> Result<Vec<QueryHit>, AppError> {
+    let project_data = app_state.projects().await.get(project_path).cloned()
+        .ok_or_else(|| AppError::ProjectNotFound { project_path: project_path.to_string() })?;
+    // A re-export path resolves to the item, whose embedding is keyed by its own path.
+    let item_path = project_data.get_item(item_path).map(|item| item.full_path_str.clone())
+        .ok_or_else(|| AppError::InvalidArgument { message: format!("Item '{}' not found in project '{}'", item_path, project_path) })?;
+    let similar = project_data.similar_items(&item_path, num_results, filter)
+        .ok_or_else(|| AppError::InvalidArgument { message: format!("Item '{}' has no embedding; re-process '{}' to embed it", item_path, project_path) })?;
+    Ok(similar.into_iter().map(|(item, score)| QueryHit {
+        project_path: project_path.to_string(),
+        version: project_data.version.clone(),
+        related_items: browse::related_items(&project_data, item),
+        item: item.clone(),
+        score,
+    }).collect())
+}
+
+/// One query hit.
+#[derive(Debug, Clone)]
+pub struct QueryHit {
+    pub project_path: String,
+    /// Package version of the project the hit is from.
+    pub version: Option<String>,
+    pub item: DocItem,
+    pub score: f32,
+    /// The item's parent type or module, siblings and members.
+    pub related_items: Vec<RelatedItem>,
+}
+
+/// Hybrid (embedding + keyword) search across the processed projects, optionally reranked;
+/// the best `num_results` hits, best first. With several phrasings (`additional_queries`,
+/// `expand_query`) each is searched and the rankings are fused, so scores are RRF sums then.
+pub async fn query_docs(app_state: &AppState, opts: &QueryOptions) -> Result<Vec<QueryHit>, AppError> {
+    let config = app_state.config.current();
+    let started = std::time::Instant::now();
+    let (query_embedding, model_id) = embedder::embed_with_global(&opts.query).await
+        .map_err(|e| AppError::internal(format!("Failed to embed query: {}", e)))?
+        .ok_or_else(|| AppError::embedder_not_ready(&config.embedder.model_repo))?;
+
+    // With reranking, every project contributes enough hits to fill the candidate pool.
+    let fetch = if opts.rerank { opts.num_results.max(config.reranker.candidates) } else { opts.num_results };
+    // Only the (cheap, Arc-backed) project handles are taken under the lock; searching happens outside it.
+    let candidate_projects: Vec<(String, ProjectData)> = app_state.projects().await
+        .iter()
+        .filter(|(key, data)| match (&opts.project_path, &opts.version) {
+            (Some(project_path), Some(_)) => is_version_of(key, project_path, data),
+            (Some(project_path), None) => *key == project_path,
+            (None, _) => true,
+        })
+        .filter(|(_, data)| opts.version.as_deref().is_none_or(|pin| data.matches_version(pin)))
+        .map(|(project_path, project_data)| (project_path.clone(), project_data.clone()))
+        .collect();
+    // Vectors from another model would score as noise, so such projects are left out, and a
+    // query that only has such projects to search fails.
+    let mut mismatch = None;
+    let candidate_projects: Vec<(String, ProjectData)> = candidate_projects.into_iter()
+        .filter(|(project_path, data)| match data.check_embedding_model(project_path, &model_id, query_embedding.len()) {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("Skipping {} in the query: {}", project_path, e);
+                mismatch.get_or_insert(e);
+                false
+            }
+        })
+        .collect();
+    if let (true, Some(e)) = (candidate_projects.is_empty(), mismatch) {
+        return Err(e);
+    }
+
+    let keyword_weight = config.search.keyword_weight;
+    // RRF sums are tiny and rank-based, so `min_score` is applied to each phrasing's similarities
+    // before fusion; reranked hits are filtered by their relevance instead.
+    let similarity_floor = opts.min_score.filter(|_| !opts.rerank);
+    let search = |embedding: &[f32], query: &str| {
+        let mut hits = search_projects(&candidate_projects, embedding, query, fetch, keyword_weight, &opts.filter);
+        if let Some(min_score) = similarity_floor {
+            retain_relevant(&mut hits, min_score);
+        }
+        hits
+    };
+    let mut hits = search(&query_embedding, &opts.query);
+    // Scoped to one project, paraphrases can name its crate.
+    let crate_name = opts.project_path.as_ref().and(candidate_projects.first()).map(|(_, data)| data.crate_docs.crate_name.clone());
+    let variants = query_variants(opts, crate_name.as_deref());
+    if variants.len() > 1 {
+        let mut rankings = vec![hits];
+        for variant in &variants[1..] {
+            let (embedding, _) = embedder::embed_with_global(variant).await
+                .map_err(|e| AppError::internal(format!("Failed to embed query: {}", e)))?
+                .ok_or_else(|| AppError::embedder_not_ready(&config.embedder.model_repo))?;
+            rankings.push(search(&embedding, variant));
+        }
+        log::debug!("Fusing the rankings of {} phrasings: {:?}", variants.len(), variants);
+        hits = reciprocal_rank_fusion(rankings);
+    }
+    if opts.rerank {
+        hits = reranker::rerank(&config.reranker, config.embedder.device, &opts.query, hits).await
+            .map_err(|e| AppError::internal(format!("Failed to rerank results: {}", e)))?;
+    }
+    if let (true, Some(min_score)) = (opts.rerank, opts.min_score) {
+        retain_relevant(&mut hits, min_score);
+    }
+    if opts.prefer_examples {
+        boost_examples(&mut hits);
+    }
+    hits.truncate(opts.num_results);
+    let latency = started.elapsed();
+    app_state.analytics.record_query(opts.project_path.as_deref(), latency);
+    app_state.record_query_history(QueryHistoryEntry {
+        timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default(),
+        query: opts.query.clone(),
+        project_path: opts.project_path.clone(),
+        version: opts.version.clone(),
+        filter: opts.filter.clone(),
+        result_count: hits.len(),
+        top_result: hits.first().map(|(_, item, _)| item.full_path_str.clone()),
+        top_score: hits.first().map(|(_, _, score)| *score),
+        latency_ms: latency.as_secs_f64() * 1000.0,
+    });
+
+    Ok(hits.into_iter().map(|(project_path, item, score)| {
+        let project_data = candidate_projects.iter().find(|(p, _)| *p == project_path).map(|(_, data)| data);
+        let related_items = project_data.map(|data| browse::related_items(data, &item)).unwrap_or_default();
+        let version = project_data.and_then(|data| data.version.clone());
+        QueryHit { project_path, version, item, score, related_items }
+    }).collect())
+}
+
+// Raises the items with code examples by `EXAMPLE_BOOST` of their score and re-sorts.
+fn boost_examples(hits: &mut [(String, DocItem, f32)]) {
+    for hit in hits.iter_mut().filter(|(_, item, _)| !item.examples.is_empty()) {
+        hit.2 += hit.2.abs() * EXAMPLE_BOOST;
+    }
+    hits.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+}
+
+/// How much of each item's docs a search result carries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResultDetail {
+    /// Characters kept in `description_snippet`; 0 leaves the snippet out.
+    pub snippet_length: usize,
+    /// Also return the complete docs and the item's declaration (`full_docs`, `signature`), so a
+    /// caller doesn't need a `get_raw_documentation` round trip per result.
+    pub include_full_docs: bool,
+}
+
+impl ResultDetail {
+    /// The request's options, falling back to `search.snippet_length` and snippets only.
+    pub fn new(snippet_length: Option<usize>, include_full_docs: Option<bool>, settings: &SearchSettings) -> Self {
+        Self { snippet_length: snippet_length.unwrap_or(settings.snippet_length), include_full_docs: include_full_docs.unwrap_or(false) }
+    }
+}
+
+/// The item's declaration header rebuilt from its kind, name and generics, e.g.
+/// `fn spawn<F> where F: Future + Send + 'static`. Parameter and field types aren't indexed.
+pub fn item_signature(item: &DocItem) -> String {
+    let keyword = match item.item_type.as_str() {
+        "function" | "method" => "fn",
+        "struct" => "struct",
+        "enum" => "enum",
+        "union" => "union",
+        "trait" => "trait",
+        "trait_alias" => "trait",
+        "type_alias" | "assoc_type" => "type",
+        "constant" | "assoc_const" => "const",
+        "static" => "static",
+        "macro" => "macro_rules!",
+        "module" => "mod",
+        // Variants and fields are declared by name alone.
+        "variant" | "struct_field" => return item.name.clone(),
+        other => other,
+    };
+    let generics = item.generics.as_ref().map(|g| g.render()).unwrap_or_default();
+    let separator = if generics.starts_with('<') || generics.is_empty() { "" } else { " " };
+    format!("{} {}{}{}", keyword, item.name, separator, generics)
+}
+
+/// A search result as the UI and MCP clients receive it.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct QueryDocResultItem {
+    pub project_path: String,
+    /// Package version of the project, if known.
+    pub version: Option<String>,
+    pub item_full_path: String,
+    pub item_type: String,
+    pub description_snippet: Option<String>,
+    /// Generic parameters and where-clauses, e.g. `<T: Serialize> where T: Send`.
+    pub generics: Option<String>,
+    /// Set for `#[deprecated]` items (which only show up with `include_deprecated`), e.g.
+    /// "Deprecated since 0.3.0: use `Config::builder`".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+    /// What the item's `#[cfg]` requires, e.g. "Only available on unix" or "Requires feature `tls`".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub availability: Option<String>,
+    /// Full paths of the items the docs link to, to look up with `get_raw_documentation`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<String>,
+    pub score: f32,
+    /// The item's parent type or module, siblings and members, closest first.
+    pub related_items: Vec<RelatedItem>,
+    /// The first code examples from the item's docs.
+    pub examples: Vec<String>,
+    /// The complete doc comment, with `include_full_docs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full_docs: Option<String>,
+    /// The declaration header (`item_signature`), with `include_full_docs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Byte ranges `[start, end)` of `description_snippet` holding query terms, set by `with_highlights`.
+    pub highlights: Vec<(usize, usize)>,
+}
+
+impl QueryDocResultItem {
+    /// Cuts the docs to `detail.snippet_length` characters, marking a cut with "...".
+    pub fn new(project_path: String, version: Option<String>, item: &DocItem, score: f32, related_items: Vec<RelatedItem>, detail: ResultDetail) -> Self {
+        Self {
+            project_path,
+            version,
+            item_full_path: item.full_path_str.clone(),
+            item_type: item.item_type.clone(),
+            description_snippet: item.description.as_deref().filter(|_| detail.snippet_length > 0).map(|d| snippet(d, detail.snippet_length)),
+            generics: item.generics.as_ref().map(|g| g.render()),
+            deprecated: item.deprecation.as_ref().map(Deprecation::describe),
+            availability: item.cfg.as_ref().map(Cfg::describe),
+            links: item.links.clone(),
+            score,
+            related_items,
+            examples: item.examples.iter().take(MAX_RESULT_EXAMPLES).cloned().collect(),
+            full_docs: item.description.clone().filter(|_| detail.include_full_docs),
+            signature: detail.include_full_docs.then(|| item_signature(item)),
+            highlights: Vec::new(),
+        }
+    }
+
+    /// Marks the words of the snippet that match `query`, so the UI and clients can show why an
+    /// item matched. Embedding-only matches may have none.
+    pub fn with_highlights(mut self, query: &str) -> Self {
+        self.highlights = self.description_snippet.as_deref().map(|s| bm25::match_spans(s, query)).unwrap_or_default();
+        self
+    }
+
+    pub fn from_hit(hit: QueryHit, detail: ResultDetail) -> Self {
+        Self::new(hit.project_path, hit.version, &hit.item, hit.score, hit.related_items, detail)
+    }
+}
+
+fn snippet(docs: &str, max_chars: usize) -> String {
+    match docs.char_indices().nth(max_chars) {
+        Some((cut, _)) => format!("{}...", &docs[..cut]),
+        None => docs.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet_and_outcome_message() {
+        assert_eq!(snippet("Parses a config.", 100), "Parses a config.");
+        assert_eq!(snippet("Parses a config.", 6), "Parses...");
+        assert_eq!(snippet("Grüße", 3), "Grü...");
+
+        let outcome = ProcessOutcome { project_path: "/work/app".to_string(), item_count: 10, embedded_count: 7, up_to_date: true, total_projects: 2 };
+        assert_eq!(outcome.to_string(), "Project /work/app is up to date (10 items, 7 embedded).");
+    }
+
+    #[test]
+    fn test_version_matching() {
+        let docs = rustdoc_processor::CrateDocs { crate_name: "app".to_string(), items: std::collections::HashMap::new() };
+        let data = ProjectData::new(docs, Vec::new(), std::collections::HashMap::new())
+            .with_version(Some("0.6.3".to_string()));
+        assert!(data.matches_version("0.6.3"));
+        assert!(data.matches_version("0.6"));
+        assert!(!data.matches_version("0.60"));
+        assert!(!data.matches_version("0.6.30"));
+        assert!(data.matches_version("*"));
+
+        assert_eq!(versioned_key("/work/app", "0.6.3"), "/work/app@0.6.3");
+        assert!(is_version_of("/work/app", "/work/app", &data));
+        assert!(is_version_of("/work/app@0.6.3", "/work/app", &data));
+        assert!(!is_version_of("/work/app@0.5.0", "/work/app", &data));
+    }
+
+    #[test]
+    fn test_check_embedding_model() {
+        let docs = || rustdoc_processor::CrateDocs { crate_name: "app".to_string(), items: HashMap::new() };
+        let embeddings = HashMap::from([("app::run".to_string(), vec![0.0f32; 4])]);
+        let data = ProjectData::new(docs(), Vec::new(), embeddings).with_embedding_model(Some("BAAI/bge-small@main".to_string()));
+        assert_eq!(data.embedding_dimension, Some(4));
+        assert!(data.check_embedding_model("/work/app", "BAAI/bge-small@main", 4).is_ok());
+        assert_eq!(data.check_embedding_model("/work/app", "BAAI/bge-code-v1@main", 4).unwrap_err().code(), "embedding_model_mismatch");
+        assert!(data.check_embedding_model("/work/app", "BAAI/bge-small@main", 8).is_err());
+
+        // Keyword-only projects can be searched with any model.
+        let unembedded = ProjectData::new(docs(), Vec::new(), HashMap::new());
+        assert!(unembedded.check_embedding_model("/work/app", "BAAI/bge-code-v1@main", 1536).is_ok());
+    }
+
+    #[test]
+    fn test_similar_items() {
+        let item = |name: &str, item_type: &str| (format!("app::{}", name), DocItem { name: name.to_string(), item_type: item_type.to_string(), full_path_str: format!("app::{}", name), ..Default::default() });
+        let items = HashMap::from([item("read", "function"), item("read_to_string", "function"), item("Reader", "struct"), item("write", "function")]);
+        let embeddings = HashMap::from([
+            ("app::read".to_string(), vec![1.0, 0.0, 0.0]),
+            ("app::read_to_string".to_string(), vec![0.9, 0.1, 0.0]),
+            ("app::Reader".to_string(), vec![0.8, 0.0, 0.2]),
+            ("app::write".to_string(), vec![0.0, 1.0, 0.0]),
+        ]);
+        let data = ProjectData::new(rustdoc_processor::CrateDocs { crate_name: "app".to_string(), items }, Vec::new(), embeddings);
+
+        let similar = data.similar_items("app::read", 2, &SearchFilter::default()).unwrap();
+        let paths: Vec<&str> = similar.iter().map(|(item, _)| item.full_path_str.as_str()).collect();
+        // The item itself is left out.
+        assert_eq!(paths, ["app::read_to_string", "app::Reader"]);
+        let functions = SearchFilter { item_types: Some(vec!["function".to_string()]), ..Default::default() };
+        assert_eq!(data.similar_items("app::read", 1, &functions).unwrap()[0].0.full_path_str, "app::read_to_string");
+        assert!(data.similar_items("app::missing", 2, &SearchFilter::default()).is_none());
+    }
+
+    #[test]
+    fn test_query_variants_and_fusion() {
+        let opts = QueryOptions {
+            query: "spawn task".to_string(),
+            project_path: None,
+            version: None,
+            num_results: 5,
+            filter: SearchFilter::default(),
+            rerank: false,
+            prefer_examples: false,
+            additional_queries: vec!["run a future in the background".to_string(), "Spawn Task".to_string()],
+            expand_query: true,
+            min_score: None,
+        };
+        assert_eq!(query_variants(&opts, Some("tokio")), ["spawn task", "run a future in the background", "tokio spawn task", "How do I spawn task in Rust?"]);
+        assert!(expand_query("How does the scheduler pick the next task to run when all workers are busy?", None).is_empty());
+
+        let hit = |path: &str| ("/work/app".to_string(), DocItem { full_path_str: path.to_string(), ..Default::default() }, 0.5);
+        let fused = reciprocal_rank_fusion(vec![vec![hit("app::a"), hit("app::b")], vec![hit("app::b"), hit("app::c")]]);
+        let order: Vec<&str> = fused.iter().map(|(_, item, _)| item.full_path_str.as_str()).collect();
+        assert_eq!(order, ["app::b", "app::a", "app::c"]);
+        assert!((fused[0].2 - (1.0 / 62.0 + 1.0 / 61.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_boost_examples() {
+        let hit = |path: &str, examples: usize, score: f32| ("/work/app".to_string(), DocItem { full_path_str: path.to_string(), examples: vec!["run();".to_string(); examples], ..Default::default() }, score);
+        // RRF sums: the boost lifts an item with examples past a near tie, not past the clear leader.
+        let mut hits = vec![hit("app::a", 0, 2.0 / 61.0), hit("app::b", 0, 1.0 / 61.0), hit("app::c", 1, 1.0 / 62.0)];
+        boost_examples(&mut hits);
+        let order: Vec<&str> = hits.iter().map(|(_, item, _)| item.full_path_str.as_str()).collect();
+        assert_eq!(order, ["app::a", "app::c", "app::b"]);
+    }
+
+    #[test]
+    fn test_retain_relevant() {
+        let hit = |path: &str, score: f32| ("/work/app".to_string(), DocItem { full_path_str: path.to_string(), ..Default::default() }, score);
+        let mut hits = vec![hit("app::a", 0.71), hit("app::b", 0.35), hit("app::c", 0.12)];
+        retain_relevant(&mut hits, 0.35);
+        let kept: Vec<&str> = hits.iter().map(|(_, item, _)| item.full_path_str.as_str()).collect();
+        assert_eq!(kept, ["app::a", "app::b"]);
+        retain_relevant(&mut hits, 0.9);
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn test_result_detail() {
+        let item = DocItem {
+            name: "spawn".to_string(),
+            full_path_str: "rt::spawn".to_string(),
+            item_type: "function".to_string(),
+            description: Some("Spawns a task onto the runtime.".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(item_signature(&item), "fn spawn");
+        let brief = QueryDocResultItem::new("/work/rt".to_string(), None, &item, 0.9, Vec::new(), ResultDetail { snippet_length: 6, include_full_docs: false });
+        assert_eq!(brief.description_snippet.as_deref(), Some("Spawns..."));
+        assert_eq!((brief.full_docs, brief.signature), (None, None));
+        let full = QueryDocResultItem::new("/work/rt".to_string(), None, &item, 0.9, Vec::new(), ResultDetail { snippet_length: 0, include_full_docs: true });
+        assert_eq!(full.description_snippet, None);
+        assert_eq!(full.full_docs.as_deref(), Some("Spawns a task onto the runtime."));
+        assert_eq!(full.signature.as_deref(), Some("fn spawn"));
+    }
+}
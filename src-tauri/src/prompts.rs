@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use crate::pipeline::QueryHit;
+use crate::resources;
+
+// Docs longer than this are cut (on a char boundary) so several items fit one prompt.
+const MAX_PROMPT_DOC_CHARS: usize = 3000;
+// Items retrieved for a prompt when the client doesn't pass `num_results`.
+pub const DEFAULT_PROMPT_RESULTS: usize = 5;
+
+/// A built-in MCP prompt (`prompts/list`, `prompts/get`): a retrieval query built from the
+/// arguments, and the instructions wrapped around the retrieved docs.
+pub struct PromptTemplate {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// `(name, description, required)`.
+    pub arguments: &'static [(&'static str, &'static str, bool)],
+    /// Rank items with code examples first, for "how do I use X" prompts.
+    pub prefer_examples: bool,
+    query: fn(&HashMap<String, String>) -> String,
+    instructions: fn(&HashMap<String, String>) -> String,
+}
+
+// Arguments every template accepts to narrow the retrieval.
+const PROJECT_ARGUMENT: (&str, &str, bool) = ("project_path", "Only use docs from this processed project.", false);
+const NUM_RESULTS_ARGUMENT: (&str, &str, bool) = ("num_results", "How many documentation items to include (default 5).", false);
+
+pub const PROMPT_TEMPLATES: &[PromptTemplate] = &[
+    PromptTemplate {
+        name: "explain_api_usage",
+        description: "Explains how to use a Rust API, with the docs of the API and its related items as context.",
+        arguments: &[
+            ("api", "The item to explain, e.g. `tokio::sync::Mutex` or `Vec::retain`.", true),
+            ("task", "What the user is trying to do with it, to focus the explanation.", false),
+            PROJECT_ARGUMENT,
+            NUM_RESULTS_ARGUMENT,
+        ],
+        prefer_examples: true,
+        query: |args| match args.get("task") {
+            Some(task) => format!("how to use {} to {}", args["api"], task),
+            None => format!("how to use {}", args["api"]),
+        },
+        instructions: |args| {
+            let mut text = format!("Explain how to use `{}`", args["api"]);
+            if let Some(task) = args.get("task") {
+                text.push_str(&format!(" to {}", task));
+            }
+            text.push_str(". Cover what it is for, its signature and important constraints, and give a short \
+                code example. Base the explanation on the documentation below and cite items by their [n].");
+            text
+        },
+    },
+    PromptTemplate {
+        name: "find_equivalent_api",
+        description: "Finds the Rust API in the processed crates that does the same as a function from another language or library.",
+        arguments: &[
+            ("api", "The API to find an equivalent for, e.g. `Python's itertools.groupby` or `Array.prototype.flatMap`.", true),
+            ("description", "What that API does, if its name alone is ambiguous.", false),
+            PROJECT_ARGUMENT,
+            NUM_RESULTS_ARGUMENT,
+        ],
+        prefer_examples: false,
+        query: |args| match args.get("description") {
+            Some(description) => format!("{} {}", args["api"], description),
+            None => args["api"].clone(),
+        },
+        instructions: |args| format!(
+            "Find the Rust equivalent of `{}` among the documentation items below. Name the best match and \
+            explain any differences in behaviour, then mention close alternatives. If none of the items is an \
+            equivalent, say so rather than inventing an API.",
+            args["api"]
+        ),
+    },
+    PromptTemplate {
+        name: "answer_question",
+        description: "Answers a question about the processed Rust code from the most relevant documentation, with citations.",
+        arguments: &[
+            ("question", "The question to answer, in natural language.", true),
+            PROJECT_ARGUMENT,
+            NUM_RESULTS_ARGUMENT,
+        ],
+        prefer_examples: false,
+        query: |args| args["question"].clone(),
+        instructions: |args| format!(
+            "Answer this question from the documentation below only, citing each claim with its [n]. If the \
+            documentation doesn't cover it, say so.\n\nQuestion: {}",
+            args["question"]
+        ),
+    },
+];
+
+pub fn find_template(name: &str) -> Option<&'static PromptTemplate> {
+    PROMPT_TEMPLATES.iter().find(|template| template.name == name)
+}
+
+impl PromptTemplate {
+    /// The arguments with empty values dropped, or the name of the first missing required one.
+    pub fn check_arguments(&self, arguments: Option<HashMap<String, String>>) -> Result<HashMap<String, String>, String> {
+        let arguments: HashMap<String, String> = arguments.unwrap_or_default().into_iter()
+            .map(|(name, value)| (name, value.trim().to_string()))
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+        match self.arguments.iter().find(|(name, _, required)| *required && !arguments.contains_key(*name)) {
+            Some((name, _, _)) => Err(format!("Prompt '{}' requires the argument '{}'", self.name, name)),
+            None => Ok(arguments),
+        }
+    }
+
+    /// What to search for; `arguments` must have passed `check_arguments`.
+    pub fn retrieval_query(&self, arguments: &HashMap<String, String>) -> String {
+        (self.query)(arguments)
+    }
+
+    /// The prompt text: the instructions, then each hit as a numbered markdown section with its
+    /// `rustdoc://` URI, so every template formats its context the same way.
+    pub fn render(&self, arguments: &HashMap<String, String>, hits: &[QueryHit]) -> String {
+        let mut text = (self.instructions)(arguments);
+        if hits.is_empty() {
+            text.push_str("\n\nNo relevant documentation was found in the processed projects; say so rather than guessing.");
+            return text;
+        }
+        text.push_str("\n\n## Documentation\n");
+        for (i, hit) in hits.iter().enumerate() {
+            let item = &hit.item;
+            text.push_str(&format!("\n### [{}] `{}` ({})\n", i + 1, item.full_path_str, item.item_type));
+            text.push_str(&format!("Source: {}\n", resources::item_uri(&hit.project_path, &item.full_path_str)));
+            if let Some(generics) = &item.generics {
+                text.push_str(&format!("Generics: `{}`\n", generics.render()));
+            }
+            text.push('\n');
+            let docs = item.description.as_deref().map(str::trim).filter(|d| !d.is_empty()).unwrap_or("_No documentation._");
+            let mut chars = docs.char_indices();
+            match chars.nth(MAX_PROMPT_DOC_CHARS) {
+                Some((cut, _)) => {
+                    text.push_str(&docs[..cut]);
+                    text.push_str(" […]");
+                }
+                None => text.push_str(docs),
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(pairs: &[(&str, &str)]) -> Option<HashMap<String, String>> {
+        Some(pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+    }
+
+    #[test]
+    fn test_check_arguments() {
+        let template = find_template("explain_api_usage").unwrap();
+        let checked = template.check_arguments(args(&[("api", " Vec::retain "), ("task", "")])).unwrap();
+        assert_eq!(checked.get("api").map(String::as_str), Some("Vec::retain"));
+        assert!(!checked.contains_key("task"));
+        assert_eq!(template.retrieval_query(&checked), "how to use Vec::retain");
+
+        assert!(template.check_arguments(args(&[("api", "  ")])).unwrap_err().contains("'api'"));
+        assert!(template.check_arguments(None).is_err());
+        assert!(find_template("unknown").is_none());
+    }
+
+    #[test]
+    fn test_render_without_hits() {
+        let template = find_template("find_equivalent_api").unwrap();
+        let checked = template.check_arguments(args(&[("api", "itertools.groupby")])).unwrap();
+        let text = template.render(&checked, &[]);
+        assert!(text.starts_with("Find the Rust equivalent of `itertools.groupby`"));
+        assert!(text.contains("No relevant documentation was found"));
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use candle_core::quantized::{gguf_file, QMatMul, QTensor};
+use candle_core::{DType, Device, Module, Tensor};
+use candle_nn::Embedding;
+use candle_transformers::quantized_nn::RmsNorm;
+use candle_transformers::utils::repeat_kv;
+use std::path::Path;
+
+// Used when the GGUF file doesn't specify the RoPE base.
+const DEFAULT_ROPE_THETA: f32 = 10_000.0;
+
+struct Mlp {
+    gate: QMatMul,
+    up: QMatMul,
+    down: QMatMul,
+}
+
+impl Mlp {
+    fn forward(&self, xs: &Tensor) -> Result<Tensor> {
+        let gate = self.gate.forward(xs)?.silu()?;
+        Ok(self.down.forward(&(gate * self.up.forward(xs)?)?)?)
+    }
+}
+
+struct Layer {
+    q: QMatMul,
+    k: QMatMul,
+    v: QMatMul,
+    q_bias: Tensor,
+    k_bias: Tensor,
+    v_bias: Tensor,
+    o: QMatMul,
+    attn_norm: RmsNorm,
+    ffn_norm: RmsNorm,
+    mlp: Mlp,
+}
+
+/// Qwen2 over quantized GGUF weights (e.g. Q4_K_M or Q8_0), returning the final hidden states
+/// rather than logits, so it can stand in for the full-precision model in the embedder.
+///
+/// candle's `quantized_qwen2` is a causal LM (last-token logits, KV cache), hence this small
+/// encoder: the same blocks, run over the whole padded batch at once.
+pub struct QuantizedQwen2 {
+    embeddings: Embedding,
+    layers: Vec<Layer>,
+    norm: RmsNorm,
+    n_head: usize,
+    n_kv_head: usize,
+    head_dim: usize,
+    rope_theta: f32,
+    device: Device,
+}
+
+impl QuantizedQwen2 {
+    /// Loads a llama.cpp-style Qwen2 GGUF file. Weights stay quantized in memory.
+    pub fn from_gguf(path: &Path, device: &Device) -> Result<Self> {
+        let mut file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let content = gguf_file::Content::read(&mut file)
+            .map_err(|e| e.with_path(path))
+            .with_context(|| format!("Failed to read GGUF header of {}", path.display()))?;
+        let metadata = |key: &str| content.metadata.get(key).with_context(|| format!("GGUF metadata is missing {}", key));
+        let n_head = metadata("qwen2.attention.head_count")?.to_u32()? as usize;
+        let n_kv_head = metadata("qwen2.attention.head_count_kv")?.to_u32()? as usize;
+        let hidden_size = metadata("qwen2.embedding_length")?.to_u32()? as usize;
+        let block_count = metadata("qwen2.block_count")?.to_u32()? as usize;
+        let rms_eps = metadata("qwen2.attention.layer_norm_rms_epsilon")?.to_f32()? as f64;
+        let rope_theta = metadata("qwen2.rope.freq_base").ok()
+            .and_then(|value| value.to_f32().ok())
+            .unwrap_or(DEFAULT_ROPE_THETA);
+
+        let mut tensor = |name: &str| -> Result<QTensor> {
+            content.tensor(&mut file, name, device).with_context(|| format!("GGUF file is missing tensor {}", name))
+        };
+        let embeddings = Embedding::new(tensor("token_embd.weight")?.dequantize(device)?, hidden_size);
+        let norm = RmsNorm::from_qtensor(tensor("output_norm.weight")?, rms_eps)?;
+        let mut layers = Vec::with_capacity(block_count);
+        for i in 0..block_count {
+            let prefix = format!("blk.{}", i);
+            let mut matmul = |name: &str| -> Result<QMatMul> { Ok(QMatMul::from_qtensor(tensor(&format!("{}.{}", prefix, name))?)?) };
+            let q = matmul("attn_q.weight")?;
+            let k = matmul("attn_k.weight")?;
+            let v = matmul("attn_v.weight")?;
+            let o = matmul("attn_output.weight")?;
+            let mlp = Mlp { gate: matmul("ffn_gate.weight")?, up: matmul("ffn_up.weight")?, down: matmul("ffn_down.weight")? };
+            layers.push(Layer {
+                q, k, v, o, mlp,
+                q_bias: tensor(&format!("{}.attn_q.bias", prefix))?.dequantize(device)?,
+                k_bias: tensor(&format!("{}.attn_k.bias", prefix))?.dequantize(device)?,
+                v_bias: tensor(&format!("{}.attn_v.bias", prefix))?.dequantize(device)?,
+                attn_norm: RmsNorm::from_qtensor(tensor(&format!("{}.attn_norm.weight", prefix))?, rms_eps)?,
+                ffn_norm: RmsNorm::from_qtensor(tensor(&format!("{}.ffn_norm.weight", prefix))?, rms_eps)?,
+            });
+        }
+
+        Ok(Self {
+            embeddings,
+            layers,
+            norm,
+            n_head,
+            n_kv_head,
+            head_dim: hidden_size / n_head,
+            rope_theta,
+            device: device.clone(),
+        })
+    }
+
+    /// Final hidden states, shape (batch, seq_len, hidden_size), for `token_ids` (batch, seq_len)
+    /// with `attention_mask` (1 = token, 0 = padding).
+    pub fn forward(&self, token_ids: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        let (_, seq_len) = token_ids.dims2()?;
+        let (cos, sin) = self.rotary(seq_len)?;
+        let bias = self.attention_bias(attention_mask, seq_len)?;
+        let mut xs = self.embeddings.forward(token_ids)?;
+        for layer in &self.layers {
+            let attn = self.attention(layer, &layer.attn_norm.forward(&xs)?, &cos, &sin, &bias)?;
+            let xs_attn = (attn + &xs)?;
+            xs = (layer.mlp.forward(&layer.ffn_norm.forward(&xs_attn)?)? + &xs_attn)?;
+        }
+        Ok(self.norm.forward(&xs)?)
+    }
+
+    fn attention(&self, layer: &Layer, xs: &Tensor, cos: &Tensor, sin: &Tensor, bias: &Tensor) -> Result<Tensor> {
+        let (batch, seq_len, _) = xs.dims3()?;
+        let heads = |proj: &QMatMul, proj_bias: &Tensor, n: usize| -> Result<Tensor> {
+            Ok(proj.forward(xs)?.broadcast_add(proj_bias)?
+                .reshape((batch, seq_len, n, self.head_dim))?
+                .transpose(1, 2)?
+                .contiguous()?)
+        };
+        let q = candle_nn::rotary_emb::rope(&heads(&layer.q, &layer.q_bias, self.n_head)?, cos, sin)?;
+        let k = candle_nn::rotary_emb::rope(&heads(&layer.k, &layer.k_bias, self.n_kv_head)?, cos, sin)?;
+        let v = heads(&layer.v, &layer.v_bias, self.n_kv_head)?;
+        let k = repeat_kv(k, self.n_head / self.n_kv_head)?;
+        let v = repeat_kv(v, self.n_head / self.n_kv_head)?.contiguous()?;
+
+        let scores = (q.matmul(&k.t()?)? / (self.head_dim as f64).sqrt())?.broadcast_add(bias)?;
+        let probs = candle_nn::ops::softmax_last_dim(&scores)?;
+        let out = probs.matmul(&v)?
+            .transpose(1, 2)?
+            .reshape((batch, seq_len, self.n_head * self.head_dim))?;
+        Ok(layer.o.forward(&out)?)
+    }
+
+    // RoPE tables for positions 0..seq_len, each (seq_len, head_dim / 2).
+    fn rotary(&self, seq_len: usize) -> Result<(Tensor, Tensor)> {
+        let inv_freq: Vec<f32> = (0..self.head_dim)
+            .step_by(2)
+            .map(|i| 1.0 / self.rope_theta.powf(i as f32 / self.head_dim as f32))
+            .collect();
+        let inv_freq = Tensor::new(inv_freq.as_slice(), &self.device)?.unsqueeze(0)?;
+        let positions = Tensor::arange(0u32, seq_len as u32, &self.device)?.to_dtype(DType::F32)?.unsqueeze(1)?;
+        let freqs = positions.matmul(&inv_freq)?;
+        Ok((freqs.cos()?, freqs.sin()?))
+    }
+
+    // Additive attention bias (batch, 1, seq_len, seq_len): causal, and padded keys masked out.
+    fn attention_bias(&self, attention_mask: &Tensor, seq_len: usize) -> Result<Tensor> {
+        let batch = attention_mask.dim(0)?;
+        let causal: Vec<u8> = (0..seq_len).flat_map(|i| (0..seq_len).map(move |j| u8::from(j <= i))).collect();
+        let causal = Tensor::from_vec(causal, (1, 1, seq_len, seq_len), &self.device)?;
+        let keys = attention_mask.to_dtype(DType::U8)?.reshape((batch, 1, 1, seq_len))?;
+        let allowed = causal.broadcast_mul(&keys)?;
+        let zeros = Tensor::zeros(allowed.shape(), DType::F32, &self.device)?;
+        let neg_inf = Tensor::full(f32::NEG_INFINITY, allowed.shape(), &self.device)?;
+        Ok(allowed.where_cond(&zeros, &neg_inf)?)
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::error::AppError;
+
+// Result sets kept at once; the one expiring first is dropped to make room.
+const MAX_CACHED_QUERIES: usize = 64;
+// Longer TTLs (e.g. a huge `cursor_ttl_secs`) are cut to this, so the expiry can't overflow.
+const MAX_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Results of recent queries that didn't fit one page, so `query_documentation` can hand them
+/// out page by page without searching again. A cursor is `<query id>:<offset>`.
+pub struct QueryResultCache<T> {
+    entries: Mutex<HashMap<String, CachedQuery<T>>>,
+    next_id: AtomicU64,
+}
+
+struct CachedQuery<T> {
+    results: Vec<T>,
+    expires_at: Instant,
+}
+
+/// One page of query results.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryPage<T> {
+    pub results: Vec<T>,
+    /// Pass back as `cursor` for the next page; absent on the last one.
+    pub next_cursor: Option<String>,
+    /// Results of the query across all pages.
+    pub total_results: usize,
+}
+
+impl<T> Default for QueryResultCache<T> {
+    fn default() -> Self {
+        Self { entries: Mutex::new(HashMap::new()), next_id: AtomicU64::new(0) }
+    }
+}
+
+impl<T: Clone> QueryResultCache<T> {
+    /// The first `page_size` results; all of them are kept for `ttl` behind the returned cursor.
+    pub fn first_page(&self, results: Vec<T>, page_size: usize, ttl: Duration) -> QueryPage<T> {
+        let page_size = page_size.max(1);
+        let total_results = results.len();
+        if total_results <= page_size {
+            return QueryPage { results, next_cursor: None, total_results };
+        }
+        let id = self.new_id();
+        let first = results[..page_size].to_vec();
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.expires_at > now);
+        if entries.len() >= MAX_CACHED_QUERIES {
+            if let Some(oldest) = entries.iter().min_by_key(|(_, entry)| entry.expires_at).map(|(id, _)| id.clone()) {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(id.clone(), CachedQuery { results, expires_at: now + ttl.min(MAX_TTL) });
+        QueryPage { results: first, next_cursor: Some(format!("{}:{}", id, page_size)), total_results }
+    }
+
+    /// The page a cursor points at. Each page served keeps the query for another `ttl`, and a
+    /// cursor can be used again until then (e.g. to retry a failed request).
+    pub fn page(&self, cursor: &str, page_size: usize, ttl: Duration) -> Result<QueryPage<T>, AppError> {
+        let invalid = || AppError::InvalidArgument {
+            message: format!("Cursor '{}' is unknown or has expired; repeat the query without a cursor", cursor),
+        };
+        let (id, offset) = cursor.rsplit_once(':').ok_or_else(invalid)?;
+        let offset: usize = offset.parse().map_err(|_| invalid())?;
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        let entry = entries.get_mut(id).filter(|entry| entry.expires_at > now).ok_or_else(invalid)?;
+        if offset > entry.results.len() {
+            return Err(invalid());
+        }
+        entry.expires_at = now + ttl.min(MAX_TTL);
+        let end = offset.saturating_add(page_size.max(1)).min(entry.results.len());
+        Ok(QueryPage {
+            results: entry.results[offset..end].to_vec(),
+            next_cursor: (end < entry.results.len()).then(|| format!("{}:{}", id, end)),
+            total_results: entry.results.len(),
+        })
+    }
+
+    // Unique within the process and not predictable from the previous one alone.
+    fn new_id(&self) -> String {
+        let sequence = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+        format!("q{:x}{:08x}", sequence, nanos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_pages() {
+        let cache = QueryResultCache::default();
+        let page = cache.first_page((0..5).collect(), 2, TTL);
+        assert_eq!((page.results, page.total_results), (vec![0, 1], 5));
+        let page = cache.page(&page.next_cursor.unwrap(), 2, TTL).unwrap();
+        assert_eq!(page.results, vec![2, 3]);
+        let cursor = page.next_cursor.unwrap();
+        // A larger page size on a later request takes the rest.
+        let page = cache.page(&cursor, 10, TTL).unwrap();
+        assert_eq!(page.results, vec![4]);
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(cache.page(&cursor, 10, TTL).unwrap().results, vec![4]);
+        // Huge page sizes and TTLs from clients or the config don't overflow.
+        let page = cache.page(&cursor, usize::MAX, Duration::MAX).unwrap();
+        assert_eq!((page.results, page.next_cursor), (vec![4], None));
+        assert!(cache.first_page((0..3).collect(), 1, Duration::MAX).next_cursor.is_some());
+
+        let page = cache.first_page(vec![1, 2], 2, TTL);
+        assert_eq!((page.results, page.next_cursor), (vec![1, 2], None));
+    }
+
+    #[test]
+    fn test_invalid_cursors() {
+        let cache = QueryResultCache::default();
+        let cursor = cache.first_page((0..3).collect(), 1, Duration::ZERO).next_cursor.unwrap();
+        assert!(cache.page(&cursor, 1, TTL).is_err(), "expired");
+        let cursor = cache.first_page((0..3).collect(), 1, TTL).next_cursor.unwrap();
+        let (id, _) = cursor.rsplit_once(':').unwrap();
+        assert!(cache.page(&format!("{}:4", id), 1, TTL).is_err());
+        assert!(cache.page("unknown:1", 1, TTL).is_err());
+        assert!(cache.page("garbage", 1, TTL).is_err());
+    }
+}
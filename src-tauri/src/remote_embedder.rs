@@ -0,0 +1,166 @@
+use anyhow::{anyhow, Result};
+use candle_core::Tensor;
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::config::RemoteEmbedderSettings;
+use crate::embedder::EmbeddingBackend;
+
+// First wait before retrying a failed request; it doubles with every retry unless the server
+// sends Retry-After.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Embeds through an OpenAI-compatible `/embeddings` endpoint (OpenAI, Ollama, vLLM, LM Studio...)
+/// instead of running a model locally. Requests carry at most `batch_size` texts and are retried
+/// with exponential backoff on connection errors, 429 and 5xx responses.
+pub struct RemoteEmbedder {
+    client: reqwest::blocking::Client,
+    endpoint: String,
+    settings: RemoteEmbedderSettings,
+    batch_size: usize,
+}
+
+#[derive(Serialize)]
+struct EmbeddingsRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    index: Option<usize>,
+}
+
+enum RequestError {
+    Retryable { error: anyhow::Error, retry_after: Option<Duration> },
+    Fatal(anyhow::Error),
+}
+
+impl RemoteEmbedder {
+    pub fn new(settings: &RemoteEmbedderSettings, batch_size: usize) -> Result<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(settings.timeout_secs))
+            .build()?;
+        Ok(Self { client, endpoint: embeddings_endpoint(&settings.url), settings: settings.clone(), batch_size: batch_size.max(1) })
+    }
+
+    fn embed_chunk(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut attempt = 0;
+        loop {
+            match self.request(texts) {
+                Ok(embeddings) => return Ok(embeddings),
+                Err(RequestError::Retryable { error, retry_after }) if attempt < self.settings.max_retries => {
+                    let wait = retry_after.unwrap_or(INITIAL_BACKOFF * 2u32.pow(attempt));
+                    log::warn!("Embedding request to {} failed (attempt {} of {}), retrying in {:?}: {:#}", self.endpoint, attempt + 1, self.settings.max_retries + 1, wait, error);
+                    std::thread::sleep(wait);
+                    attempt += 1;
+                }
+                Err(RequestError::Retryable { error, .. } | RequestError::Fatal(error)) => return Err(error),
+            }
+        }
+    }
+
+    fn request(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, RequestError> {
+        let mut request = self.client.post(&self.endpoint).json(&EmbeddingsRequest { model: &self.settings.model, input: texts });
+        if let Some(api_key) = &self.settings.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request.send().map_err(|e| RequestError::Retryable {
+            error: anyhow!("Failed to reach {}: {}", self.endpoint, e),
+            retry_after: None,
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = response.headers().get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs);
+            let body = response.text().unwrap_or_default();
+            let error = anyhow!("{} returned {}: {}", self.endpoint, status, body.trim());
+            return Err(if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+                RequestError::Retryable { error, retry_after }
+            } else {
+                RequestError::Fatal(error)
+            });
+        }
+        let parsed: EmbeddingsResponse = response.json()
+            .map_err(|e| RequestError::Fatal(anyhow!("Unexpected response from {}: {}", self.endpoint, e)))?;
+        embeddings_in_order(parsed, texts.len()).map_err(RequestError::Fatal)
+    }
+}
+
+impl EmbeddingBackend for RemoteEmbedder {
+    fn forward(&self, _token_ids: &Tensor, _attention_mask: &Tensor) -> Result<Tensor> {
+        anyhow::bail!("The remote embedder takes texts, not token ids")
+    }
+
+    fn embed_texts(&self, texts: &[String]) -> Option<Result<Vec<Vec<f32>>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for chunk in texts.chunks(self.batch_size) {
+            match self.embed_chunk(chunk) {
+                Ok(chunk_embeddings) => embeddings.extend(chunk_embeddings),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        Some(Ok(embeddings))
+    }
+}
+
+// `url` may be the API base (`http://localhost:11434/v1`) or the endpoint itself.
+fn embeddings_endpoint(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    if url.ends_with("/embeddings") { url.to_string() } else { format!("{}/embeddings", url) }
+}
+
+// The response's vectors in input order (servers may reorder them; `index` says where each
+// belongs), L2-normalised like local embeddings. Not every server normalises.
+fn embeddings_in_order(response: EmbeddingsResponse, expected: usize) -> Result<Vec<Vec<f32>>> {
+    if response.data.len() != expected {
+        anyhow::bail!("Asked for {} embeddings but got {}", expected, response.data.len());
+    }
+    let mut embeddings = vec![Vec::new(); expected];
+    for (position, data) in response.data.into_iter().enumerate() {
+        let index = data.index.unwrap_or(position);
+        let slot = embeddings.get_mut(index).ok_or_else(|| anyhow!("Embedding index {} is out of range", index))?;
+        let norm = data.embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+        *slot = if norm > 0.0 { data.embedding.iter().map(|x| x / norm).collect() } else { data.embedding };
+    }
+    if embeddings.iter().any(|embedding| embedding.is_empty()) {
+        anyhow::bail!("The response left some inputs without an embedding");
+    }
+    Ok(embeddings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embeddings_endpoint() {
+        assert_eq!(embeddings_endpoint("http://localhost:11434/v1/"), "http://localhost:11434/v1/embeddings");
+        assert_eq!(embeddings_endpoint("https://api.openai.com/v1/embeddings"), "https://api.openai.com/v1/embeddings");
+    }
+
+    #[test]
+    fn test_embeddings_in_order() -> Result<()> {
+        let response: EmbeddingsResponse = serde_json::from_str(r#"{"object": "list", "data": [
+            {"object": "embedding", "index": 1, "embedding": [0.0, 2.0]},
+            {"object": "embedding", "index": 0, "embedding": [3.0, 4.0]}
+        ], "model": "nomic-embed-text"}"#)?;
+        assert_eq!(embeddings_in_order(response, 2)?, vec![vec![0.6, 0.8], vec![0.0, 1.0]]);
+
+        let short: EmbeddingsResponse = serde_json::from_str(r#"{"data": [{"embedding": [1.0]}]}"#)?;
+        assert!(embeddings_in_order(short, 2).is_err());
+        let duplicate: EmbeddingsResponse = serde_json::from_str(r#"{"data": [{"index": 0, "embedding": [1.0]}, {"index": 0, "embedding": [1.0]}]}"#)?;
+        assert!(embeddings_in_order(duplicate, 2).is_err());
+        Ok(())
+    }
+}
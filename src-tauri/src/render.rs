@@ -0,0 +1,253 @@
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+use crate::rustdoc_processor::{is_rust_block, DocItem};
+
+/// How item docs are returned: the markdown as written, plain text, or HTML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderMode {
+    #[default]
+    Raw,
+    Plain,
+    Html,
+}
+
+impl std::str::FromStr for RenderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "raw" | "markdown" | "md" => Ok(RenderMode::Raw),
+            "plain" | "text" => Ok(RenderMode::Plain),
+            "html" => Ok(RenderMode::Html),
+            other => Err(format!("expected raw, plain or html, got '{}'", other)),
+        }
+    }
+}
+
+/// Doc comment markdown in the given mode. Like rustdoc, tables, footnotes, strikethrough and
+/// task lists are understood and the hidden lines of Rust code blocks (`# use foo;`) are left
+/// out of `Plain` and `Html`.
+pub fn render(markdown: &str, mode: RenderMode) -> String {
+    match mode {
+        RenderMode::Raw => markdown.to_string(),
+        RenderMode::Plain => to_plain(markdown),
+        RenderMode::Html => to_html(markdown),
+    }
+}
+
+/// A copy of the item with its description rendered.
+pub fn rendered_item(item: &DocItem, mode: RenderMode) -> DocItem {
+    let mut item = item.clone();
+    if mode != RenderMode::Raw {
+        item.description = item.description.map(|docs| render(&docs, mode));
+    }
+    item
+}
+
+// The markdown's events, with Rust code blocks labelled `rust` and their hidden lines removed.
+fn events(markdown: &str) -> impl Iterator<Item = Event<'_>> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut in_rust = false;
+    Parser::new_ext(markdown, options).filter_map(move |event| match event {
+        Event::Start(Tag::CodeBlock(kind)) => {
+            in_rust = match &kind {
+                CodeBlockKind::Fenced(info) => is_rust_block(info),
+                CodeBlockKind::Indented => true,
+            };
+            let kind = if in_rust { CodeBlockKind::Fenced(CowStr::Borrowed("rust")) } else { kind };
+            Some(Event::Start(Tag::CodeBlock(kind)))
+        }
+        Event::End(TagEnd::CodeBlock) => {
+            in_rust = false;
+            Some(event)
+        }
+        Event::Text(code) if in_rust => {
+            let shown = shown_code(&code);
+            (!shown.is_empty()).then(|| Event::Text(shown.into()))
+        }
+        _ => Some(event),
+    })
+}
+
+// Rust code without the lines rustdoc hides; `##` at the start of a line stands for a literal `#`.
+fn shown_code(code: &str) -> String {
+    code.split_inclusive('\n')
+        .filter_map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("##") {
+                Some(line.replacen("##", "#", 1))
+            } else if trimmed.trim_end() == "#" || trimmed.starts_with("# ") {
+                None
+            } else {
+                Some(line.to_string())
+            }
+        })
+        .collect()
+}
+
+// HTML with the docs' own HTML escaped and links using script-capable schemes dropped (their
+// text is kept), so it can be inserted into a page as is.
+fn to_html(markdown: &str) -> String {
+    // For each open link or image: whether its tags are dropped.
+    let mut dropped: Vec<bool> = Vec::new();
+    let events = events(markdown).filter_map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Some(Event::Text(html)),
+        Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }) if !is_safe_url(&dest_url) => {
+            dropped.push(true);
+            None
+        }
+        Event::Start(Tag::Link { .. } | Tag::Image { .. }) => {
+            dropped.push(false);
+            Some(event)
+        }
+        Event::End(TagEnd::Link | TagEnd::Image) => {
+            if dropped.pop().unwrap_or(false) { None } else { Some(event) }
+        }
+        _ => Some(event),
+    });
+    let mut out = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut out, events);
+    out
+}
+
+// Relative URLs and resolved intra-doc links (`app::net::Client`) are kept; only schemes that
+// can run code or read local files are not.
+fn is_safe_url(url: &str) -> bool {
+    let url: String = url.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+    match url.split_once(':') {
+        Some((scheme, _)) => !matches!(scheme.to_ascii_lowercase().as_str(), "javascript" | "vbscript" | "data" | "file"),
+        None => true,
+    }
+}
+
+// Text without markup: headings and paragraphs separated by blank lines, list items as `- `
+// (or `1. `) lines, table rows as cells joined with ` | ` and code blocks verbatim.
+fn to_plain(markdown: &str) -> String {
+    let mut out = String::new();
+    // The next number of each open list, `None` for bullet lists.
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    // The cells of the table row being read.
+    let mut row: Option<Vec<String>> = None;
+    for event in events(markdown) {
+        match event {
+            Event::Text(text) | Event::Code(text) => sink(&mut out, &mut row).push_str(&text),
+            Event::SoftBreak => sink(&mut out, &mut row).push(' '),
+            Event::HardBreak => sink(&mut out, &mut row).push('\n'),
+            Event::FootnoteReference(label) => sink(&mut out, &mut row).push_str(&format!("[{}]", label)),
+            Event::TaskListMarker(done) => out.push_str(if done { "[x] " } else { "[ ] " }),
+            Event::Rule => {
+                end_line(&mut out);
+                out.push('\n');
+            }
+            Event::Start(Tag::TableHead | Tag::TableRow) => row = Some(Vec::new()),
+            Event::Start(Tag::TableCell) => row.get_or_insert_with(Vec::new).push(String::new()),
+            Event::End(TagEnd::TableHead | TagEnd::TableRow) => {
+                if let Some(cells) = row.take() {
+                    out.push_str(&cells.iter().map(|cell| cell.trim()).collect::<Vec<_>>().join(" | "));
+                    out.push('\n');
+                }
+            }
+            Event::Start(Tag::List(start)) => {
+                end_line(&mut out);
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    out.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                end_line(&mut out);
+                out.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        out.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => out.push_str("- "),
+                }
+            }
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                end_line(&mut out);
+                out.push_str(&format!("[{}]: ", label));
+            }
+            Event::Start(Tag::CodeBlock(_)) => end_line(&mut out),
+            Event::End(TagEnd::Paragraph) if !lists.is_empty() => end_line(&mut out),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock | TagEnd::Table) => {
+                end_line(&mut out);
+                out.push('\n');
+            }
+            Event::End(TagEnd::Item) => end_line(&mut out),
+            _ => {}
+        }
+    }
+    normalize(&out)
+}
+
+// Where text goes: the current table cell, if any.
+fn sink<'a>(out: &'a mut String, row: &'a mut Option<Vec<String>>) -> &'a mut String {
+    match row.as_mut().and_then(|cells| cells.last_mut()) {
+        Some(cell) => cell,
+        None => out,
+    }
+}
+
+fn end_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+// Trailing spaces removed and runs of blank lines collapsed to one.
+fn normalize(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() && matches!(lines.last(), None | Some(&"")) {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n").trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCS: &str = "Opens a [`Client`](app::net::Client).\n\n# Errors\n\n| Code | Meaning |\n|------|---------|\n| 1 | timeout |\n\n* first\n* second <b>bold</b>\n\n```\n# use app::Client;\nlet c = Client::new();\n```\n\n[evil](javascript:alert(1))";
+
+    #[test]
+    fn test_render_plain() {
+        assert_eq!(render(DOCS, RenderMode::Raw), DOCS);
+        assert_eq!(
+            render(DOCS, RenderMode::Plain),
+            "Opens a Client.\n\nErrors\n\nCode | Meaning\n1 | timeout\n\n- first\n- second bold\n\nlet c = Client::new();\n\nevil"
+        );
+        assert_eq!(render("1. one\n2. two\n   * nested", RenderMode::Plain), "1. one\n2. two\n  - nested");
+    }
+
+    #[test]
+    fn test_render_html() {
+        let html = render(DOCS, RenderMode::Html);
+        assert!(html.contains("<a href=\"app::net::Client\"><code>Client</code></a>"));
+        assert!(html.contains("<h1>Errors</h1>"));
+        assert!(html.contains("<table>"));
+        assert!(html.contains("<td>timeout</td>"));
+        // The docs' own HTML is shown, not interpreted.
+        assert!(html.contains("&lt;b&gt;bold&lt;/b&gt;"));
+        assert!(html.contains("<pre><code class=\"language-rust\">let c = Client::new();\n</code></pre>"));
+        assert!(!html.contains("use app::Client"));
+        assert!(!html.contains("javascript"));
+        assert!(html.contains("<p>evil</p>"));
+    }
+
+    #[test]
+    fn test_shown_code() {
+        assert_eq!(shown_code("# fn main() {\n#\nlet x = 1;\n## not hidden\n#[derive(Debug)]\n# }\n"), "let x = 1;\n# not hidden\n#[derive(Debug)]\n");
+        assert_eq!("HTML".parse::<RenderMode>(), Ok(RenderMode::Html));
+        assert!("pdf".parse::<RenderMode>().is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{Context, Result, Error as AnyhowError};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::xlm_roberta::{Config as XLMRobertaConfig, XLMRobertaForSequenceClassification};
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams, TruncationStrategy};
+
+use crate::config::{DevicePreference, RerankerSettings};
+use crate::embedder::select_device;
+use crate::rustdoc_processor::DocItem;
+
+// Docs past this are cut before tokenizing; the pair is truncated to `max_length` tokens anyway.
+const MAX_PASSAGE_CHARS: usize = 4000;
+
+/// A cross-encoder (BGE-reranker style, XLM-RoBERTa with a one-logit classification head) that
+/// reads the query and a document together and scores how well the document answers it. Much
+/// slower than comparing embeddings, so it only rescores the top hits of the embedding search.
+pub struct Reranker {
+    model: XLMRobertaForSequenceClassification,
+    tokenizer: Tokenizer,
+    device: Device,
+    model_id: String,
+}
+
+impl Reranker {
+    pub fn new(settings: &RerankerSettings, device: DevicePreference) -> Result<Self> {
+        let model_repo = settings.model_repo.as_str();
+        log::info!("Initializing reranker with model: {} (revision {})", model_repo, settings.model_revision);
+        let device = select_device(device)?;
+
+        let api = Api::new().context("Failed to create HuggingFace API client")?;
+        let repo = api.repo(Repo::with_revision(model_repo.to_string(), RepoType::Model, settings.model_revision.clone()));
+        let config_path = repo.get("config.json").with_context(|| format!("Failed to get config.json from {}", model_repo))?;
+        let tokenizer_path = repo.get("tokenizer.json").with_context(|| format!("Failed to get tokenizer.json from {}", model_repo))?;
+        let weights_path = repo.get("model.safetensors").with_context(|| format!("Failed to get model.safetensors from {}", model_repo))?;
+
+        let config: XLMRobertaConfig = serde_json::from_str(&std::fs::read_to_string(&config_path)?)
+            .with_context(|| format!("Failed to parse config.json of {}", model_repo))?;
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], DType::F32, &device)? };
+        let model = XLMRobertaForSequenceClassification::new(1, &config, vb)
+            .with_context(|| format!("Failed to load reranker weights from {}", model_repo))?;
+
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| AnyhowError::msg(format!("Failed to load tokenizer for {}: {}", model_repo, e)))?;
+        let pad_id = tokenizer.token_to_id("<pad>").unwrap_or(config.pad_token_id);
+        tokenizer.with_padding(Some(PaddingParams { pad_id, pad_token: "<pad>".to_string(), ..Default::default() }));
+        // Long docs lose their tail, never the query.
+        tokenizer.with_truncation(Some(TruncationParams {
+            max_length: settings.max_length,
+            strategy: TruncationStrategy::OnlySecond,
+            ..Default::default()
+        })).map_err(|e| AnyhowError::msg(format!("Invalid reranker max_length {}: {}", settings.max_length, e)))?;
+
+        log::info!("Reranker model {} loaded on {:?}.", model_repo, device);
+        Ok(Self { model, tokenizer, device, model_id: model_id(settings) })
+    }
+
+    /// Relevance of each passage to `query`, in input order, from 0 (unrelated) to 1.
+    pub fn score(&self, query: &str, passages: &[String]) -> Result<Vec<f32>> {
+        if passages.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pairs: Vec<(&str, &str)> = passages.iter().map(|p| (query, p.as_str())).collect();
+        let encodings = self.tokenizer.encode_batch(pairs, true)
+            .map_err(|e| AnyhowError::msg(format!("Failed to tokenize reranker pairs: {}", e)))?;
+        let (rows, len) = (encodings.len(), encodings[0].get_ids().len());
+        let flat = |f: fn(&tokenizers::Encoding) -> &[u32]| encodings.iter().flat_map(|e| f(e).iter().copied()).collect::<Vec<u32>>();
+        let token_ids = Tensor::from_vec(flat(|e| e.get_ids()), (rows, len), &self.device)?;
+        let attention_mask = Tensor::from_vec(flat(|e| e.get_attention_mask()), (rows, len), &self.device)?;
+        let token_type_ids = Tensor::from_vec(flat(|e| e.get_type_ids()), (rows, len), &self.device)?;
+
+        // One logit per pair, shape (rows, 1).
+        let logits = self.model.forward(&token_ids, &attention_mask, &token_type_ids)?;
+        Ok(candle_nn::ops::sigmoid(&logits.to_dtype(DType::F32)?.squeeze(1)?)?.to_vec1::<f32>()?)
+    }
+}
+
+fn model_id(settings: &RerankerSettings) -> String {
+    format!("{}@{}", settings.model_repo, settings.model_revision)
+}
+
+/// The text an item is reranked on: its path and kind, then its docs.
+pub fn passage(item: &DocItem) -> String {
+    let mut text = format!("{} ({})", item.full_path_str, item.item_type);
+    if let Some(docs) = item.description.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        text.push('\n');
+        text.extend(docs.chars().take(MAX_PASSAGE_CHARS));
+    }
+    text
+}
+
+// Loaded on the first reranked query rather than at startup, since most setups never use it.
+static GLOBAL_RERANKER: Lazy<Mutex<Option<Reranker>>> = Lazy::new(|| Mutex::new(None));
+
+/// Scores with the global reranker, loading it first if it isn't loaded or `settings` name
+/// another model. Blocking; the first call downloads the model.
+pub fn score_with_global(settings: &RerankerSettings, device: DevicePreference, query: &str, passages: &[String]) -> Result<Vec<f32>> {
+    let mut guard = GLOBAL_RERANKER.lock().map_err(|e| AnyhowError::msg(format!("Failed to acquire lock on GLOBAL_RERANKER: {}", e)))?;
+    if guard.as_ref().is_none_or(|r| r.model_id != model_id(settings)) {
+        *guard = None;
+        *guard = Some(Reranker::new(settings, device)?);
+    }
+    guard.as_ref().expect("reranker loaded above").score(query, passages)
+}
+
+/// Rescores the first `settings.candidates` of `hits` (project path, item, score) with the
+/// cross-encoder and returns them best first; hits past the candidates are dropped. The
+/// scores become the reranker's 0..1 relevance.
+pub async fn rerank(settings: &RerankerSettings, device: DevicePreference, query: &str, mut hits: Vec<(String, DocItem, f32)>) -> Result<Vec<(String, DocItem, f32)>> {
+    hits.truncate(settings.candidates.max(1));
+    let passages: Vec<String> = hits.iter().map(|(_, item, _)| passage(item)).collect();
+    let (settings, query) = (settings.clone(), query.to_string());
+    let scores = tokio::task::spawn_blocking(move || score_with_global(&settings, device, &query, &passages))
+        .await
+        .context("Reranking panicked")??;
+    for (hit, score) in hits.iter_mut().zip(scores) {
+        hit.2 = score;
+    }
+    hits.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passage() {
+        let mut item = DocItem {
+            full_path_str: "app::Config::new".to_string(),
+            item_type: "function".to_string(),
+            description: Some("  Creates a config.\n".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(passage(&item), "app::Config::new (function)\nCreates a config.");
+        item.description = Some(" ".to_string());
+        assert_eq!(passage(&item), "app::Config::new (function)");
+        item.description = Some("x".repeat(MAX_PASSAGE_CHARS + 10));
+        assert_eq!(passage(&item).len(), "app::Config::new (function)\n".len() + MAX_PASSAGE_CHARS);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::rustdoc_processor::DocItem;
+
+/// Scheme of the MCP resources exposing processed items: `rustdoc://<project>/<item path>`, with
+/// the project path percent-encoded so its slashes don't read as URI segments.
+pub const RESOURCE_URI_SCHEME: &str = "rustdoc://";
+pub const RESOURCE_MIME_TYPE: &str = "text/markdown";
+/// The recent queries (`get_query_history`) as a JSON array, newest first. It has no item
+/// segment, so it never parses as an item URI.
+pub const QUERY_HISTORY_URI: &str = "rustdoc://history";
+pub const QUERY_HISTORY_MIME_TYPE: &str = "application/json";
+/// Queries served by the query history resource.
+pub const QUERY_HISTORY_RESOURCE_LIMIT: usize = 100;
+
+/// Resource URI of an item, e.g. `rustdoc://%2Fwork%2Fapp/app::Config::new`.
+pub fn item_uri(project_path: &str, item_path: &str) -> String {
+    format!("{}{}/{}", RESOURCE_URI_SCHEME, encode(project_path), encode(item_path))
+}
+
+/// The `(project path, item path)` a resource URI points at, or `None` if it isn't an item URI.
+pub fn parse_item_uri(uri: &str) -> Option<(String, String)> {
+    let (project, item) = uri.strip_prefix(RESOURCE_URI_SCHEME)?.split_once('/')?;
+    if project.is_empty() || item.is_empty() {
+        return None;
+    }
+    Some((decode(project)?, decode(item)?))
+}
+
+/// The full documentation of an item as markdown, as served by `resources/read`.
+pub fn render_item(item: &DocItem) -> String {
+    let mut text = format!("# `{}`\n\n*{}*", item.full_path_str, item.item_type);
+    if let Some(parent) = &item.parent {
+        text.push_str(&format!(" of `{}`", parent));
+    }
+    if let Some(trait_name) = &item.trait_name {
+        text.push_str(&format!(" (impl `{}`)", trait_name));
+    }
+    text.push_str(&format!(" in crate `{}`\n\n", item.crate_name));
+    if let Some(generics) = &item.generics {
+        text.push_str(&format!("Generics: `{}`\n\n", generics.render()));
+    }
+    match item.description.as_deref().map(str::trim) {
+        Some(docs) if !docs.is_empty() => text.push_str(docs),
+        _ => text.push_str("_No documentation._"),
+    }
+    text.push('\n');
+    text
+}
+
+// Percent-encodes everything but unreserved characters and `:`, which keeps item paths readable.
+fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~' | b':') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_uri_roundtrip() {
+        let uri = item_uri("/work/my app", "app::Config::new");
+        assert_eq!(uri, "rustdoc://%2Fwork%2Fmy%20app/app::Config::new");
+        assert_eq!(parse_item_uri(&uri), Some(("/work/my app".to_string(), "app::Config::new".to_string())));
+        // Markdown doc source paths contain `#`, which must not end up as a URI fragment.
+        let uri = item_uri("/work/guide", "guide::intro.md#Setup");
+        assert_eq!(parse_item_uri(&uri).unwrap().1, "guide::intro.md#Setup");
+
+        assert_eq!(parse_item_uri("https://example.com/a"), None);
+        assert_eq!(parse_item_uri("rustdoc://%2Fwork%2Fapp"), None);
+        assert_eq!(parse_item_uri("rustdoc://%2Fwork%2Fapp/app::%Z1"), None);
+        assert_eq!(parse_item_uri(QUERY_HISTORY_URI), None);
+    }
+}
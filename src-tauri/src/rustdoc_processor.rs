@@ -1,13 +1,28 @@
 use anyhow::{Context, Result};
 use serde_json::Value; // For parsing rustdoc JSON
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output};
 use std::fs;
 
+use crate::cfg::Cfg;
+use crate::config::RustdocSettings;
+use crate::generics::{render_generic_args, Generics};
+use crate::jobs::CancelFlag;
+use crate::sandbox;
+
+/// rustdoc JSON `format_version`s this parser has been checked against. Older files are
+/// rejected; newer ones are still parsed (nightlies move fast, and most changes don't touch the
+/// fields read here), with a warning that is also surfaced by the startup checks.
+pub const SUPPORTED_FORMAT_VERSIONS: std::ops::RangeInclusive<u32> = 24..=39;
+
+/// Bumped when the parser changes which items it keeps (2: enum variants and struct fields, 3: the
+/// same in older formats, 4: trait items under their trait, 5: trait impl members under their
+/// trait), so projects processed before aren't skipped as up to date.
+pub const PARSER_VERSION: u32 = 5;
+
 // Basic structure for storing extracted documentation.
 // This will likely expand as we understand the rustdoc JSON format better.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct DocItem {
     pub id: String, // The ItemId from rustdoc JSON
     pub crate_name: String,
@@ -16,6 +31,138 @@ pub struct DocItem {
     pub description: Option<String>,
     pub item_type: String, // e.g., "function", "struct", "module"
     pub full_path_str: String, // e.g., my_crate::module::MyStruct
+    /// For methods and associated items: full path of the type whose impl block defines them.
+    pub parent: Option<String>,
+    /// For items of a trait impl: the implemented trait, e.g. `Display`.
+    pub trait_name: Option<String>,
+    /// Where the item is defined, if rustdoc reported it.
+    pub span: Option<SourceSpan>,
+    /// Type, lifetime and const parameters and where-clauses, for items that declare any.
+    pub generics: Option<Generics>,
+    /// The Rust code blocks of the docs, with rustdoc's hidden `# ` lines shown; see
+    /// `extract_examples`.
+    pub examples: Vec<String>,
+    /// Other public paths of the item through `pub use` re-exports in its crate, e.g.
+    /// `app::Client` for `app::net::Client`; lookups and keyword searches accept them too.
+    pub reexports: Vec<String>,
+    /// Set on a stand-in for an item another crate defines and this one re-exports (`pub use
+    /// serde::Serialize`): the item's path in its own crate, e.g. `serde::ser::Serialize`.
+    pub reexport_of: Option<String>,
+    /// From `#[deprecated]`; searches leave deprecated items out unless asked not to.
+    pub deprecation: Option<Deprecation>,
+    /// Only rustdoc runs with `--document-private-items` produce anything but `Public`.
+    pub visibility: Visibility,
+    /// The `#[cfg]` the item (or the impl block of a method) is gated on, e.g. `unix` or
+    /// `feature = "tls"`.
+    pub cfg: Option<Cfg>,
+    /// Full paths of the items the docs link to (intra-doc links), sorted. The links in
+    /// `description` are rewritten to these paths; see `resolve_intra_doc_links`.
+    pub links: Vec<String>,
+}
+
+impl DocItem {
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecation.is_some()
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.visibility == Visibility::Public
+    }
+}
+
+/// Fixtures shared by the tests of the modules that work on `DocItem`s.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::DocItem;
+
+    /// An item keyed by its full path, as in `CrateDocs::items`; the crate and the name are
+    /// the first and last segments of `full_path`.
+    pub(crate) fn doc_entry(full_path: &str, item_type: &str, description: &str) -> (String, DocItem) {
+        (full_path.to_string(), DocItem {
+            crate_name: full_path.split("::").next().unwrap().to_string(),
+            name: full_path.rsplit("::").next().unwrap().to_string(),
+            item_type: item_type.to_string(),
+            description: Some(description.to_string()),
+            full_path_str: full_path.to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+/// An item's visibility, from the rustdoc JSON `visibility`. Enum variants, trait items and the
+/// members of trait impls take their parent's, as in Rust, and no member or field is counted
+/// as more visible than its parent type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    #[default]
+    Public,
+    /// `pub(crate)`.
+    Crate,
+    /// `pub(in path)`, `pub(super)` or `pub(self)`, with the path rustdoc reports, e.g. `::net`.
+    Restricted(String),
+    /// No `pub`: only visible in its own module.
+    Private,
+}
+
+impl Visibility {
+    // Higher reaches further.
+    fn reach(&self) -> u8 {
+        match self {
+            Visibility::Public => 3,
+            Visibility::Crate => 2,
+            Visibility::Restricted(_) => 1,
+            Visibility::Private => 0,
+        }
+    }
+
+    // `self`, unless `parent` is narrower.
+    fn within(self, parent: &Visibility) -> Visibility {
+        if parent.reach() < self.reach() { parent.clone() } else { self }
+    }
+}
+
+/// An item's location in its crate's sources, from the rustdoc JSON `span`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SourceSpan {
+    /// As rustdoc reports it: relative to the workspace root for local crates, absolute for
+    /// registry dependencies.
+    pub filename: String,
+    /// 1-based, inclusive.
+    pub begin_line: usize,
+    pub end_line: usize,
+}
+
+/// An item's `#[deprecated(since = "..", note = "..")]`, both parts optional.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Deprecation {
+    pub since: Option<String>,
+    pub note: Option<String>,
+}
+
+impl Deprecation {
+    /// "Deprecated since 0.3.0: use `Config::builder`", for rendered docs.
+    pub fn describe(&self) -> String {
+        let mut text = "Deprecated".to_string();
+        if let Some(since) = &self.since {
+            text.push_str(&format!(" since {}", since));
+        }
+        if let Some(note) = &self.note {
+            text.push_str(&format!(": {}", note));
+        }
+        text
+    }
+}
+
+/// The source code of an item, as returned by `get_item_source`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ItemSource {
+    pub item_path: String,
+    /// The file the snippet was read from.
+    pub file: String,
+    pub begin_line: usize,
+    pub end_line: usize,
+    pub source: String,
 }
 
 // A collection of docs for a whole crate
@@ -26,18 +173,234 @@ pub struct CrateDocs {
     // Could also store the original rustdoc JSON path or root module ID
 }
 
+impl CrateDocs {
+    /// Drops every item that isn't `pub` (see `Visibility`), for `rustdoc.skip_private_items`.
+    /// Returns how many were dropped.
+    pub fn retain_public(&mut self) -> usize {
+        let before = self.items.len();
+        self.items.retain(|_, item| item.is_public());
+        before - self.items.len()
+    }
+}
+
+/// Which of a package's targets are documented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocTargets {
+    /// The library if there is one, otherwise the binaries.
+    #[default]
+    Auto,
+    Lib,
+    Bin,
+    Example,
+    /// Library, binaries and examples.
+    All,
+}
+
+impl std::str::FromStr for DocTargets {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(DocTargets::Auto),
+            "lib" => Ok(DocTargets::Lib),
+            "bin" => Ok(DocTargets::Bin),
+            "example" => Ok(DocTargets::Example),
+            "all" => Ok(DocTargets::All),
+            other => Err(format!("expected auto, lib, bin, example or all, got '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetKind {
+    Lib,
+    Bin,
+    Example,
+}
+
+/// A documentable target of the project's package, as listed by `cargo metadata`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageTarget {
+    pub name: String,
+    pub kind: TargetKind,
+}
+
+impl PackageTarget {
+    // Selects the target in `cargo rustdoc`.
+    fn cargo_args(&self) -> Vec<String> {
+        match self.kind {
+            TargetKind::Lib => vec!["--lib".to_string()],
+            TargetKind::Bin => vec!["--bin".to_string(), self.name.clone()],
+            TargetKind::Example => vec!["--example".to_string(), self.name.clone()],
+        }
+    }
+
+    /// The name rustdoc gives the crate (and its JSON file). For a library that is the `[lib]`
+    /// name, which may differ from the package name.
+    fn crate_name(&self) -> String {
+        self.name.replace('-', "_")
+    }
+
+    // Name of the JSON in our output directory, prefixed with the crate name of the first
+    // documented target: the name the merged docs get, so `generated_json_files` finds them from
+    // it. Binaries and examples get a suffix so they don't overwrite the library's JSON when they
+    // share its name.
+    fn output_file_name(&self, primary_crate_name: &str) -> String {
+        match self.kind {
+            TargetKind::Lib => format!("{}.json", primary_crate_name),
+            TargetKind::Bin => format!("{}.bin.{}.json", primary_crate_name, self.crate_name()),
+            TargetKind::Example => format!("{}.example.{}.json", primary_crate_name, self.crate_name()),
+        }
+    }
+}
+
+// Crate types a `[lib]` target can have; `cargo rustdoc --lib` documents any of them.
+fn is_library_kind(kind: &str) -> bool {
+    matches!(kind, "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro")
+}
+
+// Runs `cargo metadata --format-version 1` in `project_path`, without the dependency graph if
+// `no_deps`.
+fn cargo_metadata(project_path: &Path, settings: &RustdocSettings, no_deps: bool) -> Result<Value> {
+    let output = sandbox::cargo_command(project_path, &settings.sandbox)
+        .arg(format!("+{}", settings.toolchain))
+        .arg("metadata")
+        .arg("--format-version").arg("1")
+        .args(no_deps.then_some("--no-deps"))
+        .args(sandbox::cargo_flags(&settings.sandbox))
+        .output()
+        .context("Failed to execute `cargo metadata`")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "`cargo metadata` failed. STDERR: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    serde_json::from_slice(&output.stdout).context("Failed to parse `cargo metadata` output")
+}
+
+/// Lists the lib, bin and example targets of the package whose manifest is in `project_path`.
+pub fn package_targets(project_path: &Path, settings: &RustdocSettings) -> Result<Vec<PackageTarget>> {
+    let metadata = cargo_metadata(project_path, settings, true)?;
+    parse_package_targets(&metadata, &canonical_manifest_path(project_path))
+}
+
+/// Directories holding the manifests of the workspace members of the project in `project_path`,
+/// the project's own package among them. Blocking.
+pub fn workspace_member_dirs(project_path: &Path, settings: &RustdocSettings) -> Result<Vec<PathBuf>> {
+    let metadata = cargo_metadata(project_path, settings, true)?;
+    Ok(parse_workspace_member_dirs(&metadata))
+}
+
+fn parse_workspace_member_dirs(metadata: &Value) -> Vec<PathBuf> {
+    let members: Vec<&str> = metadata.get("workspace_members").and_then(|v| v.as_array()).into_iter().flatten()
+        .filter_map(|id| id.as_str())
+        .collect();
+    metadata.get("packages").and_then(|v| v.as_array()).into_iter().flatten()
+        .filter(|p| p.get("id").and_then(|id| id.as_str()).is_some_and(|id| members.contains(&id)))
+        .filter_map(|p| Path::new(p.get("manifest_path")?.as_str()?).parent().map(Path::to_path_buf))
+        .collect()
+}
+
+fn canonical_manifest_path(project_path: &Path) -> PathBuf {
+    let manifest_path = project_path.join("Cargo.toml");
+    manifest_path.canonicalize().unwrap_or(manifest_path)
+}
+
+// The directory cargo builds into: the isolated one of `settings` if set, otherwise cargo's, with
+// `CARGO_TARGET_DIR`, `build.target-dir` in `.cargo/config.toml` and the workspace root taken into
+// account. `cargo rustdoc` writes its JSON to `doc/` below it.
+fn resolve_cargo_target_dir(metadata: &Value, project_path: &Path, settings: &RustdocSettings) -> PathBuf {
+    if let Some(dir) = &settings.cargo_target_dir {
+        return dir.clone();
+    }
+    metadata.get("target_directory").and_then(|v| v.as_str())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| project_path.join("target"))
+}
+
+// Split out from package_targets so it can be tested without running cargo.
+fn parse_package_targets(metadata: &Value, manifest_path: &Path) -> Result<Vec<PackageTarget>> {
+    let packages = metadata.get("packages").and_then(|v| v.as_array()).context("`cargo metadata` has no packages")?;
+    let package = packages.iter()
+        .find(|p| p.get("manifest_path").and_then(|m| m.as_str()).is_some_and(|m| Path::new(m) == manifest_path))
+        .or_else(|| (packages.len() == 1).then(|| &packages[0]))
+        .with_context(|| format!("No package with manifest {} (virtual workspace manifest?)", manifest_path.display()))?;
+
+    let mut targets = Vec::new();
+    for target in package.get("targets").and_then(|v| v.as_array()).into_iter().flatten() {
+        let kinds: Vec<&str> = target.get("kind").and_then(|k| k.as_array()).into_iter().flatten().filter_map(|k| k.as_str()).collect();
+        let kind = if kinds.contains(&"example") {
+            TargetKind::Example
+        } else if kinds.contains(&"bin") {
+            TargetKind::Bin
+        } else if kinds.iter().any(|k| is_library_kind(k)) {
+            TargetKind::Lib
+        } else {
+            continue; // tests, benches, build scripts
+        };
+        let name = target.get("name").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        targets.push(PackageTarget { name, kind });
+    }
+    Ok(targets)
+}
+
+/// The package version from the project's Cargo.toml. A version inherited with
+/// `version.workspace = true` is looked up in `[workspace.package]` of the enclosing workspace.
+pub fn package_version(project_path: &Path) -> Option<String> {
+    let read_manifest = |dir: &Path| -> Option<toml::Value> {
+        fs::read_to_string(dir.join("Cargo.toml")).ok()?.parse().ok()
+    };
+    let manifest = read_manifest(project_path)?;
+    let version = manifest.get("package")?.get("version")?;
+    if let Some(version) = version.as_str() {
+        return Some(version.to_string());
+    }
+    if version.get("workspace").and_then(|w| w.as_bool()) != Some(true) {
+        return None;
+    }
+    project_path.ancestors().skip(1)
+        .filter_map(read_manifest)
+        .find_map(|m| m.get("workspace")?.get("package")?.get("version")?.as_str().map(str::to_string))
+}
+
+/// The targets `selection` picks out of `available`, library first.
+pub fn select_targets(available: &[PackageTarget], selection: DocTargets) -> Result<Vec<PackageTarget>> {
+    let of_kind = |kind: TargetKind| available.iter().filter(move |t| t.kind == kind).cloned();
+    let has_lib = of_kind(TargetKind::Lib).next().is_some();
+    let selected: Vec<PackageTarget> = match selection {
+        DocTargets::Auto if has_lib => of_kind(TargetKind::Lib).collect(),
+        DocTargets::Auto | DocTargets::Bin => of_kind(TargetKind::Bin).collect(),
+        DocTargets::Lib => of_kind(TargetKind::Lib).collect(),
+        DocTargets::Example => of_kind(TargetKind::Example).collect(),
+        DocTargets::All => of_kind(TargetKind::Lib).chain(of_kind(TargetKind::Bin)).chain(of_kind(TargetKind::Example)).collect(),
+    };
+    if selected.is_empty() {
+        let names: Vec<String> = available.iter().map(|t| format!("{} ({:?})", t.name, t.kind).to_lowercase()).collect();
+        anyhow::bail!("The package has no targets matching '{:?}'. Available targets: {}", selection, if names.is_empty() { "none".to_string() } else { names.join(", ") });
+    }
+    Ok(selected)
+}
+
 /// Executes rustdoc to generate documentation JSON for a given project path.
 ///
 /// # Arguments
 /// * `project_path`: Path to the root of the Rust project (where Cargo.toml is).
 /// * `target_dir`: A directory where the rustdoc JSON output should be stored.
+/// * `settings`: Toolchain and flags to run rustdoc with (from the `[rustdoc]` config section).
+/// * `targets`: Which of the package's targets to document (found with `cargo metadata`).
+/// * `cancel`: Kills `cargo rustdoc` and returns an error when set.
 ///
 /// # Returns
-/// Path to the generated JSON file.
-pub fn generate_rustdoc_json(project_path: &Path, target_dir: &Path) -> Result<PathBuf> {
+/// Paths to the generated JSON files, one per documented target, library first. Parse them
+/// together with `parse_rustdoc_json_files`.
+#[tracing::instrument(skip(target_dir, settings), fields(toolchain = %settings.toolchain))]
+pub fn generate_rustdoc_json(project_path: &Path, target_dir: &Path, settings: &RustdocSettings, targets: DocTargets, cancel: &CancelFlag) -> Result<Vec<PathBuf>> {
     log::info!(
-        "Generating rustdoc JSON for project at: {}",
-        project_path.display()
+        "Generating rustdoc JSON for project at: {} (targets: {:?})",
+        project_path.display(),
+        targets
     );
 
     if !project_path.join("Cargo.toml").exists() {
@@ -47,18 +410,249 @@ pub fn generate_rustdoc_json(project_path: &Path, target_dir: &Path) -> Result<P
         ));
     }
 
+    // Fail before cargo does, with rustup's wording so it is reported as `toolchain_missing`. In
+    // hardened mode cargo may run somewhere else (a container), so the local rustup says nothing.
+    if !settings.sandbox.enabled && matches!(toolchain_installed(&settings.toolchain), Ok(false)) {
+        return Err(anyhow::anyhow!("toolchain '{}' is not installed", settings.toolchain));
+    }
+
     // Ensure target_dir exists
     fs::create_dir_all(target_dir)
         .with_context(|| format!("Failed to create target directory: {}", target_dir.display()))?;
 
-    // Determine a unique name for the output file, perhaps based on crate name or a hash
-    // For now, let's use a fixed name, but this needs to be more robust for multiple crates.
-    // Ideally, we'd parse Cargo.toml to get the crate name first.
-    let crate_name_fallback = project_path.file_name().unwrap_or_default().to_string_lossy().to_string();
-    // let output_file_name = format!("{}.json", crate_name); // Will use determined_crate_name later
-    // let output_path = target_dir.join(output_file_name); // Will define later
+    // Target names come from `cargo metadata`, so a renamed `[lib]` (or a cdylib or proc-macro
+    // crate) is looked for under the file name rustdoc actually writes.
+    let metadata = cargo_metadata(project_path, settings, true)?;
+    let selected = select_targets(&parse_package_targets(&metadata, &canonical_manifest_path(project_path))?, targets)?;
+    let cargo_target_dir = resolve_cargo_target_dir(&metadata, project_path, settings);
+    // The first target's crate name prefixes every output file, so they can be found (and
+    // removed) together from the parsed docs' name.
+    let primary_crate_name = selected[0].crate_name();
+    let mut json_paths = Vec::new();
+    for target in &selected {
+        let output_path = target_dir.join(target.output_file_name(&primary_crate_name));
+        json_paths.push(run_cargo_rustdoc(project_path, None, &target.cargo_args(), &target.crate_name(), &cargo_target_dir, &output_path, settings.document_private_items, settings, cancel)?);
+    }
+    Ok(json_paths)
+}
+
+/// The JSON files `generate_rustdoc_json` may have written for the crate `crate_name` (the name
+/// of its parsed docs; any target), as found in `target_dir`.
+pub fn generated_json_files(target_dir: &Path, crate_name: &str) -> Vec<PathBuf> {
+    let lib_file = format!("{}.json", crate_name);
+    let (bin_prefix, example_prefix) = (format!("{}.bin.", crate_name), format!("{}.example.", crate_name));
+    let Ok(entries) = fs::read_dir(target_dir) else { return Vec::new() };
+    entries.filter_map(|e| e.ok())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name == lib_file || ((name.starts_with(&bin_prefix) || name.starts_with(&example_prefix)) && name.ends_with(".json"))
+        })
+        .map(|e| e.path())
+        .collect()
+}
+
+/// A direct (non-dev, non-build) dependency of a project, as resolved by `cargo metadata`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DependencyPackage {
+    pub name: String,
+    pub version: String,
+    /// Name of the library target (hyphens replaced), i.e. the name rustdoc uses for the JSON file.
+    pub lib_name: String,
+}
+
+/// Lists the direct dependencies of the project's root package that have a library target,
+/// with the cargo target directory to pass to `generate_dependency_rustdoc_json`.
+#[tracing::instrument(skip(settings))]
+pub fn direct_dependencies(project_path: &Path, settings: &RustdocSettings) -> Result<(Vec<DependencyPackage>, PathBuf)> {
+    let metadata = cargo_metadata(project_path, settings, false)?;
+    Ok((parse_direct_dependencies(&metadata)?, resolve_cargo_target_dir(&metadata, project_path, settings)))
+}
+
+// Split out from direct_dependencies so it can be tested without running cargo.
+fn parse_direct_dependencies(metadata: &Value) -> Result<Vec<DependencyPackage>> {
+    let root_id = metadata.pointer("/resolve/root").and_then(|v| v.as_str())
+        .context("`cargo metadata` has no root package (virtual workspace manifest?)")?;
+    let root_node = metadata.pointer("/resolve/nodes").and_then(|v| v.as_array())
+        .and_then(|nodes| nodes.iter().find(|n| n.get("id").and_then(|id| id.as_str()) == Some(root_id)))
+        .context("Root package missing from `cargo metadata` resolve graph")?;
+    let packages = metadata.get("packages").and_then(|v| v.as_array()).context("`cargo metadata` has no packages")?;
+
+    let mut dependencies = Vec::new();
+    for dep in root_node.get("deps").and_then(|v| v.as_array()).into_iter().flatten() {
+        // `kind` is null for normal dependencies, "dev"/"build" otherwise.
+        let is_normal = dep.get("dep_kinds").and_then(|v| v.as_array())
+            .is_some_and(|kinds| kinds.iter().any(|k| k.get("kind").is_none_or(|kind| kind.is_null())));
+        if !is_normal {
+            continue;
+        }
+        let Some(pkg_id) = dep.get("pkg").and_then(|v| v.as_str()) else { continue };
+        let Some(package) = packages.iter().find(|p| p.get("id").and_then(|id| id.as_str()) == Some(pkg_id)) else { continue };
+        let lib_target = package.get("targets").and_then(|v| v.as_array()).and_then(|targets| {
+            targets.iter().find(|t| {
+                t.get("kind").and_then(|k| k.as_array())
+                    .is_some_and(|kinds| kinds.iter().any(|k| k.as_str().is_some_and(is_library_kind)))
+            })
+        });
+        let Some(lib_target) = lib_target else { continue };
+        dependencies.push(DependencyPackage {
+            name: package.get("name").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+            version: package.get("version").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+            lib_name: lib_target.get("name").and_then(|v| v.as_str()).unwrap_or_default().replace('-', "_"),
+        });
+    }
+    Ok(dependencies)
+}
+
+/// Runs rustdoc on one dependency of the project (`cargo rustdoc -p name@version`), whose output
+/// lands in `cargo_target_dir` (as reported by `direct_dependencies`) before it is moved to
+/// `target_dir`. Only public items are documented, regardless of `document_private_items`.
+#[tracing::instrument(skip(cargo_target_dir, target_dir, settings), fields(dependency = %dependency.name))]
+pub fn generate_dependency_rustdoc_json(project_path: &Path, dependency: &DependencyPackage, cargo_target_dir: &Path, target_dir: &Path, settings: &RustdocSettings, cancel: &CancelFlag) -> Result<PathBuf> {
+    fs::create_dir_all(target_dir)
+        .with_context(|| format!("Failed to create target directory: {}", target_dir.display()))?;
+    let package_spec = format!("{}@{}", dependency.name, dependency.version);
+    // Versioned file name: different projects may depend on different versions.
+    let output_path = target_dir.join(format!("{}-{}.json", dependency.lib_name, dependency.version));
+    run_cargo_rustdoc(project_path, Some(&package_spec), &["--lib".to_string()], &dependency.lib_name, cargo_target_dir, &output_path, false, settings, cancel)
+}
+
+/// The standard library crates the `rust-docs-json` component ships, `std` first.
+pub const STD_LIBRARY_CRATES: [&str; 3] = ["std", "core", "alloc"];
+
+/// Rustdoc JSON of the standard library, as shipped by the `rust-docs-json` component.
+#[derive(Debug, Clone)]
+pub struct StdLibraryJson {
+    /// The toolchain's rustc version, e.g. `1.83.0-nightly`.
+    pub rustc_version: String,
+    /// One file per crate, in `STD_LIBRARY_CRATES` order.
+    pub json_paths: Vec<PathBuf>,
+}
+
+// Runs a toolchain command and returns its trimmed stdout.
+fn run_tool(program: &str, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to execute `{} {}`", program, args.join(" ")))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "`{} {}` failed. STDERR: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The rustdoc flags for JSON output on `toolchain`. Nightly (and custom) toolchains need
+/// `-Z unstable-options`; stable and beta reject `-Z` flags, so they only get `--output-format json`
+/// and work once JSON output is stabilized (`compat::probe_format_version` tells).
+pub fn json_output_args(toolchain: &str) -> Vec<&'static str> {
+    let channel = toolchain.split('-').next().unwrap_or(toolchain);
+    let release = channel == "stable" || channel == "beta" || channel.starts_with(|c: char| c.is_ascii_digit());
+    if release {
+        vec!["--output-format", "json"]
+    } else {
+        vec!["-Z", "unstable-options", "--output-format", "json"]
+    }
+}
+
+/// Whether rustup lists `toolchain` as installed. Fails if rustup can't be run (e.g. a toolchain
+/// from the system package manager); callers should then just try to use the toolchain.
+pub fn toolchain_installed(toolchain: &str) -> Result<bool> {
+    Ok(toolchain_listed(&run_tool("rustup", &["toolchain", "list"])?, toolchain))
+}
+
+/// The toolchains `rustup toolchain list` prints, e.g. `nightly-x86_64-unknown-linux-gnu (default)`.
+pub fn installed_toolchains() -> Result<Vec<String>> {
+    Ok(run_tool("rustup", &["toolchain", "list"])?.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from).collect())
+}
+
+// `rustup toolchain list` prints `<name>-<host triple>` per line, e.g. `nightly-x86_64-unknown-linux-gnu (default)`.
+// A dated toolchain like `nightly-2024-05-01-<host>` doesn't count as `nightly`.
+fn toolchain_listed(list: &str, toolchain: &str) -> bool {
+    list.lines().filter_map(|line| line.split_whitespace().next()).any(|name| {
+        name == toolchain || name.strip_prefix(toolchain).and_then(|rest| rest.strip_prefix('-'))
+            .is_some_and(|host| !host.starts_with(|c: char| c.is_ascii_digit()))
+    })
+}
+
+/// Runs `rustup toolchain install <toolchain> --profile minimal`, passing each line of rustup's
+/// progress output to `on_output`. Kills rustup and fails once `cancel` is set.
+pub fn install_toolchain(toolchain: &str, cancel: &CancelFlag, mut on_output: impl FnMut(&str)) -> Result<()> {
+    use std::io::BufRead;
+    log::info!("Installing toolchain {} with rustup", toolchain);
+    let mut child = std::process::Command::new("rustup")
+        .args(["toolchain", "install", toolchain, "--profile", "minimal"])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("Failed to execute `rustup`; is rustup installed?")?;
+    let stderr = child.stderr.take().context("rustup stderr was not captured")?;
+    let mut last_output = String::new();
+    for line in std::io::BufReader::new(stderr).split(b'\n') {
+        if cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow::anyhow!("Cancelled installing toolchain {}", toolchain));
+        }
+        let line = line.context("Failed to read rustup output")?;
+        // Download progress redraws one line with `\r`; the last redraw is the current state.
+        let line = String::from_utf8_lossy(&line);
+        if let Some(output) = line.split('\r').map(str::trim).filter(|s| !s.is_empty()).last() {
+            on_output(output);
+            last_output = output.to_string();
+        }
+    }
+    let status = child.wait().context("Failed to wait for rustup")?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("`rustup toolchain install {}` failed: {}", toolchain, last_output));
+    }
+    log::info!("Installed toolchain {}", toolchain);
+    Ok(())
+}
 
+/// Installs the `rust-docs-json` component for the configured toolchain (a no-op when it is
+/// already installed) and locates the JSON of std, core and alloc in the toolchain's sysroot.
+/// Blocking; the first call downloads the component.
+pub fn std_library_json(settings: &RustdocSettings) -> Result<StdLibraryJson> {
+    let toolchain = settings.toolchain.as_str();
+    log::info!("Installing the rust-docs-json component for toolchain {}", toolchain);
+    run_tool("rustup", &["component", "add", "rust-docs-json", "--toolchain", toolchain])?;
+    let sysroot = PathBuf::from(run_tool("rustc", &[&format!("+{}", toolchain), "--print", "sysroot"])?);
+    let rustc_version = parse_rustc_version(&run_tool("rustc", &[&format!("+{}", toolchain), "--version"])?)
+        .context("Unexpected `rustc --version` output")?;
 
+    let json_dir = sysroot.join("share").join("doc").join("rust").join("json");
+    let json_paths = STD_LIBRARY_CRATES.iter()
+        .map(|name| {
+            let path = json_dir.join(format!("{}.json", name));
+            if path.exists() { Ok(path) } else { Err(anyhow::anyhow!("rust-docs-json has no {}", path.display())) }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(StdLibraryJson { rustc_version, json_paths })
+}
+
+// `rustc 1.83.0-nightly (90b35a623 2024-11-26)` -> `1.83.0-nightly`.
+fn parse_rustc_version(output: &str) -> Option<String> {
+    output.strip_prefix("rustc ")?.split_whitespace().next().map(str::to_string)
+}
+
+// Runs `cargo rustdoc` on one target (`target_args`, e.g. `--lib` or `--bin <name>`; optionally of
+// `-p <package_spec>`) and moves `<cargo_target_dir>/doc/<crate_name>.json` to `output_path`.
+#[allow(clippy::too_many_arguments)]
+fn run_cargo_rustdoc(
+    project_path: &Path,
+    package_spec: Option<&str>,
+    target_args: &[String],
+    crate_name: &str,
+    cargo_target_dir: &Path,
+    output_path: &Path,
+    document_private_items: bool,
+    settings: &RustdocSettings,
+    cancel: &CancelFlag,
+) -> Result<PathBuf> {
     // Using nightly toolchain explicitly for potentially unstable rustdoc JSON format.
     // Users might need to have `nightly` toolchain installed: `rustup toolchain install nightly`
     // Alternatively, try to use the default `rustdoc` and hope the JSON format is stable enough.
@@ -67,57 +661,45 @@ pub fn generate_rustdoc_json(project_path: &Path, target_dir: &Path) -> Result<P
     // let rustdoc_executable = "rustdoc";
 
     // Simpler approach: use `cargo rustdoc`
-    let cargo_cmd = Command::new("cargo");
-    let mut cargo_cmd_configured = cargo_cmd;
+    // In hardened mode this runs through the configured wrapper with a scrubbed environment.
+    let mut cargo_cmd_configured = sandbox::cargo_command(project_path, &settings.sandbox);
     cargo_cmd_configured
-        .current_dir(project_path)
-        .arg("+nightly") // Using nightly for -Z unstable-options
+        .arg(format!("+{}", settings.toolchain))
         .arg("rustdoc")
         .arg("-q") // quiet mode for cargo
-        .arg("--lib") // Assuming we are primarily interested in the library part of a crate
-                      // For workspaces or multiple targets, this might need to be more specific.
+        .args(package_spec.map(|spec| vec!["-p", spec]).unwrap_or_default())
+        .args(target_args)
+        .args(sandbox::cargo_flags(&settings.sandbox));
+    if settings.cargo_target_dir.is_some() {
+        cargo_cmd_configured.arg("--target-dir").arg(cargo_target_dir);
+    }
+    cargo_cmd_configured
         .arg("--") // Separator for arguments to rustdoc itself
-        .arg("-Z").arg("unstable-options")
-        .arg("--output-format").arg("json")
-        .arg("--document-private-items"); // Optional
-
-    // The output of `cargo rustdoc -- --output-format json` goes into `target/doc/your_crate.json` by default.
-    // We need to capture this output or make it predictable.
-    // A common way is to use `cargo rustdoc` which places it in `target/doc/<crate_name>.json`.
-    // Let's try to predict this path.
-    // First, get crate name from Cargo.toml (simplified)
-    // A proper Cargo.toml parser would be better (e.g., the `cargo_toml` crate).
-    let manifest_path = project_path.join("Cargo.toml");
-    let manifest_content = fs::read_to_string(&manifest_path) // Added borrow here
-        .with_context(|| format!("Failed to read Cargo.toml from {}", project_path.display()))?;
-
-    let parsed_manifest: toml::Value = manifest_content.parse()
-        .with_context("Failed to parse Cargo.toml")?;
-
-    let determined_crate_name = parsed_manifest.get("package")
-        .and_then(|p| p.get("name"))
-        .and_then(|n| n.as_str())
-        .unwrap_or(&crate_name_fallback) // Fallback to directory name
-        .replace("-", "_"); // Rustdoc often replaces hyphens with underscores in the output JSON file name.
-
-    let output_file_name = format!("{}.json", determined_crate_name);
-    let output_path = target_dir.join(output_file_name);
-
+        .args(json_output_args(&settings.toolchain));
+    if document_private_items {
+        cargo_cmd_configured.arg("--document-private-items");
+    }
 
-    let default_rustdoc_json_path = project_path
-        .join("target")
+    let default_rustdoc_json_path = cargo_target_dir
         .join("doc")
-        .join(format!("{}.json", determined_crate_name));
+        .join(format!("{}.json", crate_name));
 
     log::info!(
-        "Attempting to run: cargo +nightly rustdoc -q --lib -- -Z unstable-options --output-format json --document-private-items in directory {}",
-        project_path.display()
+        "Attempting to run: cargo +{} rustdoc -q{} {}{}{} -- {}{} in directory {}{}",
+        settings.toolchain,
+        package_spec.map(|spec| format!(" -p {}", spec)).unwrap_or_default(),
+        target_args.join(" "),
+        sandbox::cargo_flags(&settings.sandbox).iter().map(|f| format!(" {}", f)).collect::<String>(),
+        settings.cargo_target_dir.as_ref().map(|dir| format!(" --target-dir {}", dir.display())).unwrap_or_default(),
+        json_output_args(&settings.toolchain).join(" "),
+        if document_private_items { " --document-private-items" } else { "" },
+        project_path.display(),
+        if settings.sandbox.enabled { " (hardened mode)" } else { "" }
     );
     // For logging, it's better to reconstruct the command string or log args separately
     // log::info!("Cargo command args: {:?}", cargo_cmd_configured.get_args().collect::<Vec<_>>());
 
-    let output = cargo_cmd_configured
-        .output()
+    let output = sandbox::run_cancellable(cargo_cmd_configured, cancel)
         .context("Failed to execute `cargo rustdoc` command")?;
 
     if !output.status.success() {
@@ -143,8 +725,8 @@ pub fn generate_rustdoc_json(project_path: &Path, target_dir: &Path) -> Result<P
 
     if !default_rustdoc_json_path.exists() {
         log::error!("Expected rustdoc JSON output not found at: {}. Check rustdoc output.", default_rustdoc_json_path.display());
-        // List files in target/doc to help debug
-        let doc_dir = project_path.join("target").join("doc");
+        // List the doc directory to help debug
+        let doc_dir = cargo_target_dir.join("doc");
         if doc_dir.exists() {
             log::info!("Contents of {}:", doc_dir.display());
             for entry in fs::read_dir(doc_dir)? {
@@ -158,7 +740,7 @@ pub fn generate_rustdoc_json(project_path: &Path, target_dir: &Path) -> Result<P
     }
 
     // Move the generated file to our target_dir
-    fs::rename(&default_rustdoc_json_path, &output_path).with_context(|| {
+    fs::rename(&default_rustdoc_json_path, output_path).with_context(|| {
         format!(
             "Failed to move rustdoc JSON from {} to {}",
             default_rustdoc_json_path.display(),
@@ -170,18 +752,42 @@ pub fn generate_rustdoc_json(project_path: &Path, target_dir: &Path) -> Result<P
         "Successfully generated rustdoc JSON at: {}",
         output_path.display()
     );
-    Ok(output_path)
+    Ok(output_path.to_path_buf())
+}
+
+/// Parses the JSON of several targets of one package (as returned by `generate_rustdoc_json`)
+/// into a single `CrateDocs`, named after the first. Where paths collide, e.g. a binary named
+/// like the library, the earlier target's item is kept.
+pub fn parse_rustdoc_json_files(json_paths: &[PathBuf]) -> Result<CrateDocs> {
+    let targets = json_paths.iter().map(|json_path| parse_rustdoc_json_file(json_path)).collect::<Result<Vec<_>>>()?;
+    merge_target_docs(targets)
+}
+
+/// Merges the parsed targets of one package, in `generate_rustdoc_json` order, as
+/// `parse_rustdoc_json_files` does.
+pub fn merge_target_docs(targets: Vec<CrateDocs>) -> Result<CrateDocs> {
+    let mut targets = targets.into_iter();
+    let mut crate_docs = targets.next().context("No rustdoc JSON files to parse")?;
+    for target_docs in targets {
+        log::info!("Merging {} items of target {} into {}", target_docs.items.len(), target_docs.crate_name, crate_docs.crate_name);
+        for (full_path, item) in target_docs.items {
+            crate_docs.items.entry(full_path).or_insert(item);
+        }
+    }
+    Ok(crate_docs)
 }
 
 /// Parses the rustdoc JSON file and extracts documentation items.
-/// (This is a complex part and will be an initial, simplified version)
+///
+/// The file is streamed into compact per-item records rather than a `serde_json::Value` tree,
+/// so memory stays proportional to the docs kept, not to the (often several hundred MB) JSON.
+#[tracing::instrument]
 pub fn parse_rustdoc_json_file(json_path: &Path) -> Result<CrateDocs> {
     log::info!("Parsing rustdoc JSON from: {}", json_path.display());
-    let content = fs::read_to_string(json_path)
+    let file = fs::File::open(json_path)
         .with_context(|| format!("Failed to read rustdoc JSON file: {}", json_path.display()))?;
-
-    let root: Value = serde_json::from_str(&content)
-        .with_context("Failed to parse rustdoc JSON content")?;
+    let raw: RawCrate = serde_json::from_reader(std::io::BufReader::new(file))
+        .context("Failed to parse rustdoc JSON content")?;
 
     // The rustdoc JSON format is complex. We need to navigate it.
     // Key parts: "index" (map of ItemId to Item), "paths" (map of ItemId to path info), "format_version"
@@ -189,44 +795,54 @@ pub fn parse_rustdoc_json_file(json_path: &Path) -> Result<CrateDocs> {
     // See: https://rust-lang.github.io/rfcs/2963-rustdoc-json.html (though it might be outdated)
     // And: https://github.com/rust-lang/rust/blob/master/src/librustdoc/json/conversions.rs for the current structure.
 
-    let index = root.get("index").and_then(|i| i.as_object()).context("Missing 'index' in rustdoc JSON")?;
-    let paths = root.get("paths").and_then(|p| p.as_object()).context("Missing 'paths' in rustdoc JSON")?;
-    let crate_id_val = root.get("root").and_then(|r| r.as_str()).context("Missing 'root' crate ID in rustdoc JSON")?; // Renamed to avoid conflict
+    check_format_version(raw.format_version)?;
 
-    let root_item = index.get(crate_id_val).context("Root crate item not found in index")?; // Use crate_id_val
-    let crate_name = root_item.get("name").and_then(|n| n.as_str()).unwrap_or("unknown_crate").to_string();
+    let index = &raw.index;
+    let paths = &raw.paths;
+    let crate_id_val = raw.root.as_ref().and_then(id_string).context("Missing 'root' crate ID in rustdoc JSON")?;
+
+    let root_item = index.get(&crate_id_val).context("Root crate item not found in index")?;
+    let crate_name = root_item.name.clone().unwrap_or_else(|| "unknown_crate".to_string());
 
     let mut items_map = HashMap::new();
+    let impl_members = collect_impl_members(index, paths, &crate_name);
+    let trait_items: HashSet<&str> = index.values()
+        .flat_map(|raw_item| raw_item.inner.trait_items.iter().map(String::as_str))
+        .collect();
+    // Full and module path of each item placed so far, by id, for keying variants and fields.
+    let mut located: HashMap<&str, (String, Vec<String>)> = HashMap::new();
 
-    for (item_id, item_json) in index {
-        let name = item_json.get("name").and_then(|n| n.as_str());
-        let docs = item_json.get("docs").and_then(|d| d.as_str());
-        let kind = item_json.get("kind").and_then(|k| k.as_str()).unwrap_or("unknown");
+    for (item_id, raw_item) in index {
+        // Methods and associated items are keyed under their parent type below.
+        if impl_members.contains_key(item_id) {
+            continue;
+        }
+        // Trait methods and associated items under their trait, with the variants and fields.
+        if trait_items.contains(item_id.as_str()) {
+            continue;
+        }
+        let name = raw_item.name.as_deref();
+        let kind = raw_item.kind();
+        // Variants and fields too; tuple fields (`0`, `1`) are never listed as children and dropped.
+        if matches!(kind, "variant" | "struct_field") {
+            continue;
+        }
+        // `use` items become the re-exported item's `reexports` (or a stand-in) below.
+        if matches!(kind, "use" | "import") {
+            continue;
+        }
 
         // Visibility check might be needed if not using --document-private-items
         // let visibility = item_json.get("visibility").and_then(|v| v.as_str()).unwrap_or("public");
         // if visibility != "public" { continue; }
 
-        let path_info = paths.get(item_id);
-        let path_array: Vec<String> = path_info
-            .and_then(|pi| pi.get("path"))
-            .and_then(|p_arr| p_arr.as_array())
-            .map(|arr| arr.iter().filter_map(|v| v.as_str().map(String::from)).collect())
-            .unwrap_or_default();
+        let path_array = module_path(paths, item_id, &crate_name, name);
 
+        // `crate::module::name`; the crate root itself is just `crate`.
         let mut full_path_parts = vec![crate_name.clone()];
         full_path_parts.extend(path_array.clone());
-
-        // Only add item name to path if it's not a module (modules already have their name in path_array from rustdoc)
-        // Or if it's a module, path_array might already contain its own name if it's a sub-module.
-        // The name from item_json.get("name") is the item's own name.
-        // The path_array is the module path *to* the item.
-        // So, for an item `my_mod::my_func`, path_array could be `["my_mod"]` and name `my_func`.
-        // Or for `my_mod::sub_mod::MyStruct`, path_array could be `["my_mod", "sub_mod"]` and name `MyStruct`.
-        // If the item is a module itself, e.g. `my_mod::sub_mod`, path_array `["my_mod"]` and name `sub_mod`.
-        // This logic seems correct:
         if let Some(item_name_str) = name {
-             if kind != "module" || !path_array.last().map_or(false, |last_part| last_part == item_name_str) {
+            if *item_id != crate_id_val {
                 full_path_parts.push(item_name_str.to_string());
             }
         }
@@ -234,27 +850,161 @@ pub fn parse_rustdoc_json_file(json_path: &Path) -> Result<CrateDocs> {
 
 
         if let Some(item_name) = name {
-            // Only include items that are not "hidden" or implicitly generated (e.g. some impls)
-            // This filtering needs refinement based on what's useful.
-            if item_json.get("inner").and_then(|i| i.get("is_stripped")).and_then(|s| s.as_bool()).unwrap_or(false) {
-                 // Skip "stripped" items, often private items that weren't fully documented due to privacy even with --document-private-items
-                 // This can happen if a public item re-exports a private one without `#[doc(inline)]` or similar.
-                 // Or it might be an item that rustdoc couldn't fully resolve/render.
-                 // log::trace!("Skipping stripped item: {} ({})", full_path_str, item_id);
-                 // continue;
-            }
-
-
             let doc_item = DocItem {
                 id: item_id.clone(),
                 crate_name: crate_name.clone(),
                 name: item_name.to_string(),
                 path: path_array, // This is the module path, not including the item name itself
-                description: docs.map(String::from),
+                description: raw_item.docs.clone(),
                 item_type: kind.to_string(),
                 full_path_str: full_path_str.clone(),
+                span: raw_item.span.as_ref().map(RawSpan::to_span),
+                generics: raw_item.inner.generics.clone(),
+                examples: extract_examples(raw_item.docs.as_deref().unwrap_or_default()),
+                deprecation: raw_item.deprecation.clone(),
+                visibility: raw_item.visibility.clone().unwrap_or(Visibility::Private),
+                cfg: raw_item.cfg.clone(),
+                ..Default::default()
+            };
+            located.insert(item_id.as_str(), (full_path_str.clone(), doc_item.path.clone()));
+            items_map.insert(full_path_str, doc_item);
+        }
+    }
+
+    // A struct variant's fields wait for the variant, which waits for its enum. Trait items are
+    // keyed the same way: `app::Run::run`.
+    let mut pending: Vec<(&str, &str)> = index.iter()
+        .flat_map(|(parent_id, raw_item)| raw_item.inner.children.iter().chain(&raw_item.inner.trait_items)
+            .map(move |child_id| (child_id.as_str(), parent_id.as_str())))
+        .collect();
+    while !pending.is_empty() {
+        let before = pending.len();
+        pending.retain(|&(child_id, parent_id)| {
+            let Some((parent, parent_path)) = located.get(parent_id).cloned() else { return true };
+            let Some(raw_item) = index.get(child_id) else { return false };
+            let Some(name) = raw_item.name.as_deref() else { return false };
+            let full_path_str = format!("{}::{}", parent, name);
+            // Variants and their fields are as visible as the enum, trait items as the trait; other
+            // fields default to private.
+            let parent_visibility = items_map.get(&parent).map(|p| p.visibility.clone()).unwrap_or_default();
+            let inherits = index.get(parent_id).is_some_and(|p| matches!(p.kind(), "enum" | "variant" | "trait"));
+            let visibility = match &raw_item.visibility {
+                Some(visibility) => visibility.clone(),
+                None if inherits => parent_visibility.clone(),
+                None => Visibility::Private,
+            }.within(&parent_visibility);
+            let doc_item = DocItem {
+                id: child_id.to_string(),
+                crate_name: crate_name.clone(),
+                name: name.to_string(),
+                path: parent_path.clone(),
+                description: raw_item.docs.clone(),
+                item_type: raw_item.kind().to_string(),
+                full_path_str: full_path_str.clone(),
+                parent: Some(parent),
+                span: raw_item.span.as_ref().map(RawSpan::to_span),
+                generics: raw_item.inner.generics.clone(),
+                examples: extract_examples(raw_item.docs.as_deref().unwrap_or_default()),
+                deprecation: raw_item.deprecation.clone(),
+                visibility,
+                cfg: raw_item.cfg.clone(),
+                ..Default::default()
             };
+            located.insert(child_id, (full_path_str.clone(), parent_path));
             items_map.insert(full_path_str, doc_item);
+            false
+        });
+        // The rest hang off items that weren't kept, e.g. variants of an unnamed type.
+        if pending.len() == before {
+            break;
+        }
+    }
+
+    for (item_id, member) in &impl_members {
+        let Some(raw_item) = index.get(item_id) else { continue };
+        let Some(name) = raw_item.name.as_deref() else { continue };
+        // Trait members go under their trait, `app::Config::<Display>::fmt`, so an inherent
+        // method and the members of several impls with the same name (`Debug::fmt` and
+        // `Display::fmt`, `From<A>::from` and `From<B>::from`) are all kept.
+        let full_path_str = match &member.trait_name {
+            Some(trait_name) => format!("{}::<{}>::{}", member.parent, trait_name, name),
+            None => format!("{}::{}", member.parent, name),
+        };
+        // Trait impl members are as visible as the type; inherent ones default to private.
+        let parent_visibility = items_map.get(&member.parent).map(|p| p.visibility.clone()).unwrap_or_default();
+        let visibility = match &raw_item.visibility {
+            Some(visibility) => visibility.clone(),
+            None if member.trait_name.is_some() => parent_visibility.clone(),
+            None => Visibility::Private,
+        }.within(&parent_visibility);
+        let doc_item = DocItem {
+            id: item_id.clone(),
+            crate_name: crate_name.clone(),
+            name: name.to_string(),
+            path: member.parent_path.clone(),
+            description: raw_item.docs.clone(),
+            item_type: raw_item.kind().to_string(),
+            full_path_str: full_path_str.clone(),
+            parent: Some(member.parent.clone()),
+            trait_name: member.trait_name.clone(),
+            span: raw_item.span.as_ref().map(RawSpan::to_span),
+            generics: raw_item.inner.generics.clone(),
+            examples: extract_examples(raw_item.docs.as_deref().unwrap_or_default()),
+            deprecation: raw_item.deprecation.clone(),
+            visibility,
+            cfg: Cfg::and(member.cfg.clone(), raw_item.cfg.clone()),
+            ..Default::default()
+        };
+        items_map.insert(full_path_str, doc_item);
+    }
+
+    // Re-exported items get their public paths; items re-exported from other crates, which
+    // aren't in this JSON, a stand-in pointing at their own crate's path.
+    for (target_id, public_path) in public_paths(index, &crate_id_val, &crate_name) {
+        let public_path_str = public_path.join("::");
+        if items_map.contains_key(&public_path_str) {
+            continue;
+        }
+        if let Some((full_path, _)) = located.get(target_id) {
+            if let Some(item) = items_map.get_mut(full_path) {
+                if !item.reexports.contains(&public_path_str) {
+                    item.reexports.push(public_path_str);
+                }
+            }
+        } else if let Some(entry) = paths.get(target_id).filter(|entry| !entry.path.is_empty()) {
+            let Some((name, module)) = public_path.split_last() else { continue };
+            items_map.insert(public_path_str.clone(), DocItem {
+                id: target_id.to_string(),
+                crate_name: crate_name.clone(),
+                name: name.clone(),
+                path: module[1..].to_vec(),
+                item_type: entry.kind.clone().unwrap_or_else(|| "unknown".to_string()),
+                full_path_str: public_path_str,
+                reexport_of: Some(entry.path.join("::")),
+                ..Default::default()
+            });
+        }
+    }
+    // Intra-doc links name their targets by id: items of this crate, or `paths` entries of others.
+    let link_paths: HashMap<String, String> = items_map.values()
+        .filter(|item| item.reexport_of.is_none())
+        .map(|item| (item.id.clone(), item.full_path_str.clone()))
+        .collect();
+    for item in items_map.values_mut() {
+        item.reexports.sort();
+        let Some(raw_item) = index.get(&item.id).filter(|raw_item| !raw_item.links.is_empty()) else { continue };
+        let targets: HashMap<&str, String> = raw_item.links.iter()
+            .filter_map(|(text, id)| {
+                let path = link_paths.get(id).cloned()
+                    .or_else(|| paths.get(id).filter(|entry| !entry.path.is_empty()).map(|entry| entry.path.join("::")))?;
+                Some((text.as_str(), path))
+            })
+            .collect();
+        item.links = targets.values().cloned().collect();
+        item.links.sort();
+        item.links.dedup();
+        if let Some(description) = &item.description {
+            item.description = Some(resolve_intra_doc_links(description, &targets));
         }
     }
 
@@ -266,6 +1016,480 @@ pub fn parse_rustdoc_json_file(json_path: &Path) -> Result<CrateDocs> {
     })
 }
 
+// Whether rustdoc treats a code block with this info string as Rust: no language, `rust`, or
+// only doctest attributes such as `no_run` or `edition2021`.
+pub(crate) fn is_rust_block(info: &str) -> bool {
+    info.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .all(|token| matches!(token, "rust" | "no_run" | "should_panic" | "ignore" | "compile_fail" | "test_harness")
+            || token.starts_with("edition")
+            || token.starts_with("ignore-"))
+}
+
+/// The fenced Rust code blocks of a doc comment, in order. Lines rustdoc hides (`# use foo;`)
+/// are kept without the marker, so each example is the code the doctest runs.
+pub fn extract_examples(docs: &str) -> Vec<String> {
+    let mut examples = Vec::new();
+    // The open fence (e.g. "```") and the code so far, while inside a Rust block.
+    let mut current: Option<(&str, Vec<&str>)> = None;
+    let mut skipping: Option<&str> = None;
+    for line in docs.lines() {
+        let trimmed = line.trim_start();
+        let fence_len = trimmed.len() - trimmed.trim_start_matches(['`', '~']).len();
+        let fence = &trimmed[..fence_len];
+        let is_fence = fence_len >= 3 && fence.chars().all(|c| c == fence.chars().next().unwrap_or('`'));
+        if let Some((open, code)) = current.as_mut() {
+            if is_fence && fence.starts_with(*open) && trimmed[fence_len..].trim().is_empty() {
+                let example = code.join("\n").trim().to_string();
+                if !example.is_empty() {
+                    examples.push(example);
+                }
+                current = None;
+            } else {
+                code.push(match line.trim_start().strip_prefix('#') {
+                    Some(rest) if rest.is_empty() => "",
+                    Some(rest) if rest.starts_with(' ') => &rest[1..],
+                    _ => line,
+                });
+            }
+        } else if let Some(open) = skipping {
+            if is_fence && fence.starts_with(open) && trimmed[fence_len..].trim().is_empty() {
+                skipping = None;
+            }
+        } else if is_fence {
+            if is_rust_block(&trimmed[fence_len..]) {
+                current = Some((fence, Vec::new()));
+            } else {
+                skipping = Some(fence);
+            }
+        }
+    }
+    examples
+}
+
+/// Points the intra-doc links of a doc comment (`[`Foo`]`, `[text](Foo)`, `[text][Foo]` and
+/// `[Foo]: Foo` definitions) at full item paths, e.g. `[`Foo`](app::net::Foo)`, so the docs stand
+/// on their own outside rustdoc. `targets` maps destinations as written (the keys of the rustdoc
+/// JSON `links`) to paths; other links and code blocks are left as they are.
+pub fn resolve_intra_doc_links(docs: &str, targets: &HashMap<&str, String>) -> String {
+    let mut resolved = Vec::new();
+    let mut open_fence: Option<&str> = None;
+    for line in docs.split('\n') {
+        let trimmed = line.trim_start();
+        let fence_len = trimmed.len() - trimmed.trim_start_matches(['`', '~']).len();
+        if fence_len >= 3 {
+            let fence = &trimmed[..fence_len];
+            match open_fence {
+                Some(open) if fence.starts_with(open) => open_fence = None,
+                None => open_fence = Some(fence),
+                Some(_) => {}
+            }
+            resolved.push(line.to_string());
+        } else if open_fence.is_some() {
+            resolved.push(line.to_string());
+        } else {
+            resolved.push(resolve_line_links(line, targets));
+        }
+    }
+    resolved.join("\n")
+}
+
+fn resolve_line_links(line: &str, targets: &HashMap<&str, String>) -> String {
+    let trimmed = line.trim_start();
+    if let Some((label, destination)) = trimmed.strip_prefix('[').and_then(|rest| rest.split_once("]:")) {
+        if let Some(path) = targets.get(destination.trim()) {
+            return format!("{}[{}]: {}", &line[..line.len() - trimmed.len()], label, path);
+        }
+    }
+    let mut resolved = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(open) = rest.find('[') {
+        let Some(close) = rest[open..].find(']').map(|i| open + i) else { break };
+        let label = &rest[open + 1..close];
+        let after = &rest[close + 1..];
+        // The destination as written, and how much of `after` spells it.
+        let (destination, consumed) = match (after.strip_prefix('('), after.strip_prefix('[')) {
+            (Some(inner), _) => inner.find(')').map_or((label, 0), |end| (&inner[..end], end + 2)),
+            // `[text][]` uses the text.
+            (_, Some(inner)) => inner.find(']').map_or((label, 0), |end| (if end == 0 { label } else { &inner[..end] }, end + 2)),
+            _ => (label, 0),
+        };
+        resolved.push_str(&rest[..open]);
+        match targets.get(destination) {
+            Some(path) => resolved.push_str(&format!("[{}]({})", label, path)),
+            None => resolved.push_str(&rest[open..close + 1 + consumed]),
+        }
+        rest = &rest[close + 1 + consumed..];
+    }
+    resolved.push_str(rest);
+    resolved
+}
+
+// The parts of a rustdoc JSON crate the parser uses; everything else is skipped while reading.
+#[derive(serde::Deserialize)]
+struct RawCrate {
+    root: Option<Value>,
+    index: HashMap<String, RawItem>,
+    paths: HashMap<String, RawPathEntry>,
+    format_version: Option<u32>,
+}
+
+#[derive(serde::Deserialize)]
+struct RawItem {
+    name: Option<String>,
+    docs: Option<String>,
+    // Older formats only; newer ones name the kind by the variant of `inner`.
+    kind: Option<String>,
+    #[serde(default, deserialize_with = "compact_inner")]
+    inner: RawInner,
+    span: Option<RawSpan>,
+    // `{ "since": .., "note": .. }`, or null for items that aren't deprecated.
+    deprecation: Option<Deprecation>,
+    // `None` for rustdoc's "default": private, or inherited where Rust has no `pub` to write.
+    #[serde(default, deserialize_with = "raw_visibility")]
+    visibility: Option<Visibility>,
+    #[serde(default, rename = "attrs", deserialize_with = "raw_cfg")]
+    cfg: Option<Cfg>,
+    // Intra-doc link destinations as written, with the ids of the items they resolved to.
+    #[serde(default, deserialize_with = "raw_links")]
+    links: Vec<(String, String)>,
+}
+
+impl RawItem {
+    fn kind(&self) -> &str {
+        self.kind.as_deref().or(self.inner.variant.as_deref()).unwrap_or("unknown")
+    }
+}
+
+// `inner` reduced to what the parser needs. It can be large (full signatures, generics), so each
+// one is read, summarised and dropped right away.
+#[derive(Default)]
+struct RawInner {
+    variant: Option<String>,
+    impl_block: Option<RawImpl>,
+    generics: Option<Generics>,
+    // Ids of an enum's variants or the named fields of a struct, union or struct variant.
+    children: Vec<String>,
+    // Ids of a module's items, `use` items included.
+    module_items: Vec<String>,
+    // Ids of a trait's methods, associated types and constants.
+    trait_items: Vec<String>,
+    use_item: Option<RawUse>,
+}
+
+// A `use` item (`import` in older formats): `pub use net::Client as HttpClient;` has the name
+// `HttpClient` and the id of `Client`, which is missing for re-exports of primitives and the like.
+struct RawUse {
+    name: String,
+    target_id: Option<String>,
+    glob: bool,
+}
+
+// An impl block that can contribute members: not auto-trait (`is_synthetic`) or blanket, and
+// implemented for a named type.
+struct RawImpl {
+    for_id: String,
+    // Last segment of the implemented trait's path with its generic args, e.g. `Display` or
+    // `From<String>`.
+    trait_name: Option<String>,
+    items: Vec<String>,
+}
+
+fn compact_inner<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<RawInner, D::Error> {
+    let value = <Value as serde::Deserialize>::deserialize(deserializer)?;
+    let variant = value.as_object().and_then(|o| o.keys().next()).cloned();
+    // `inner` is the impl itself in old formats and `{ "impl": { ... } }` in newer ones.
+    let impl_json = match value.get("impl") {
+        Some(impl_json) => Some(impl_json),
+        None if value.get("for").is_some() && value.get("items").is_some() => Some(&value),
+        None => None,
+    };
+    // `generics` sits in the variant's payload in newer formats, in `inner` itself in old ones.
+    let generics = value.get("generics")
+        .or_else(|| value.as_object().and_then(|o| o.values().next()).and_then(|payload| payload.get("generics")))
+        .and_then(Generics::from_json);
+    let children = value.as_object()
+        .and_then(|o| o.iter().next())
+        .filter(|(kind, _)| matches!(kind.as_str(), "enum" | "struct" | "union" | "variant"))
+        .and_then(|(_, payload)| raw_children(payload))
+        // Old formats: `inner` is the payload itself.
+        .or_else(|| raw_children(&value))
+        .unwrap_or_default();
+    let ids = |items: Option<&Value>| items.and_then(|i| i.as_array()).into_iter().flatten().filter_map(id_string).collect();
+    let trait_items = match value.as_object().and_then(|o| o.iter().next()) {
+        Some((kind, payload)) if kind == "trait" => ids(payload.get("items")),
+        // Old formats: `inner` is the payload itself.
+        _ if value.get("is_auto").is_some() => ids(value.get("items")),
+        _ => Vec::new(),
+    };
+    let (module_items, use_item) = match value.as_object().and_then(|o| o.iter().next()) {
+        Some((kind, payload)) if kind == "module" => (ids(payload.get("items")), None),
+        Some((kind, payload)) if kind == "use" || kind == "import" => (Vec::new(), raw_use(payload)),
+        // Old formats: `inner` is the payload itself.
+        _ if value.get("is_crate").is_some() => (ids(value.get("items")), None),
+        _ if value.get("source").is_some() => (Vec::new(), raw_use(&value)),
+        _ => (Vec::new(), None),
+    };
+    Ok(RawInner { impl_block: impl_json.and_then(raw_impl), variant, generics, children, module_items, trait_items, use_item })
+}
+
+// `{ "`Foo`": id, "crate::net": id }`.
+fn raw_links<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<(String, String)>, D::Error> {
+    let links = <Option<HashMap<String, Value>> as serde::Deserialize>::deserialize(deserializer)?.unwrap_or_default();
+    Ok(links.into_iter().filter_map(|(text, id)| Some((text, id_string(&id)?))).collect())
+}
+
+// `["#[cfg(unix)]", "#[inline]"]`; newer formats' structured attributes are skipped.
+fn raw_cfg<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<Cfg>, D::Error> {
+    let attrs = <Option<Vec<Value>> as serde::Deserialize>::deserialize(deserializer)?.unwrap_or_default();
+    Ok(Cfg::from_attrs(attrs.iter().filter_map(|attr| attr.as_str())))
+}
+
+// `"public"`, `"crate"`, `{ "restricted": { "parent": id, "path": "::net" } }` or `"default"`.
+fn raw_visibility<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<Visibility>, D::Error> {
+    let value = <Value as serde::Deserialize>::deserialize(deserializer)?;
+    Ok(match value.as_str() {
+        Some("public") => Some(Visibility::Public),
+        Some("crate") => Some(Visibility::Crate),
+        Some(_) => None,
+        None => value.get("restricted")
+            .map(|restricted| Visibility::Restricted(restricted.get("path").and_then(|p| p.as_str()).unwrap_or_default().to_string())),
+    })
+}
+
+fn raw_use(payload: &Value) -> Option<RawUse> {
+    Some(RawUse {
+        name: payload.get("name")?.as_str()?.to_string(),
+        target_id: payload.get("id").and_then(id_string),
+        glob: payload.get("is_glob").or_else(|| payload.get("glob")).and_then(|g| g.as_bool()).unwrap_or(false),
+    })
+}
+
+// `{ "variants": [..] }` (enum), `{ "fields": [..] }` (union), or a `kind` of
+// `{ "plain": { "fields": [..] } }` (struct) or `{ "struct": { "fields": [..] } }` (variant).
+// Tuple and unit kinds have no named children.
+fn raw_children(payload: &Value) -> Option<Vec<String>> {
+    let ids = payload.get("variants")
+        .or_else(|| payload.get("fields"))
+        .or_else(|| payload.get("kind").and_then(|k| k.get("plain").or_else(|| k.get("struct"))).and_then(|k| k.get("fields")))?;
+    Some(ids.as_array()?.iter().filter_map(id_string).collect())
+}
+
+fn raw_impl(inner: &Value) -> Option<RawImpl> {
+    if inner.get("is_synthetic").and_then(|s| s.as_bool()).unwrap_or(false)
+        || inner.get("blanket_impl").is_some_and(|b| !b.is_null())
+    {
+        return None;
+    }
+    let for_path = inner.get("for")?.get("resolved_path")?;
+    Some(RawImpl {
+        for_id: for_path.get("id").and_then(id_string)?,
+        trait_name: inner.get("trait").filter(|t| !t.is_null()).and_then(|t| {
+            let name = t.get("path").or_else(|| t.get("name")).and_then(|n| n.as_str())?;
+            let args = t.get("args").filter(|a| !a.is_null()).map(render_generic_args).unwrap_or_default();
+            Some(format!("{}{}", name.rsplit("::").next().unwrap_or(name), args))
+        }),
+        items: inner.get("items").and_then(|i| i.as_array()).into_iter().flatten().filter_map(id_string).collect(),
+    })
+}
+
+// `"span": { "filename": "src/lib.rs", "begin": [line, col], "end": [line, col] }`, lines 1-based.
+#[derive(serde::Deserialize)]
+struct RawSpan {
+    filename: String,
+    begin: (usize, usize),
+    end: (usize, usize),
+}
+
+impl RawSpan {
+    fn to_span(&self) -> SourceSpan {
+        SourceSpan { filename: self.filename.clone(), begin_line: self.begin.0, end_line: self.end.0 }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct RawPathEntry {
+    // 0 is the documented crate; others are its dependencies and the standard library.
+    #[serde(default)]
+    crate_id: u32,
+    #[serde(default)]
+    path: Vec<String>,
+    kind: Option<String>,
+}
+
+// Where an item defined in an impl block belongs.
+struct ImplMember {
+    parent: String,
+    parent_path: Vec<String>,
+    trait_name: Option<String>,
+    // The impl block's `#[cfg]`, which applies to all its members.
+    cfg: Option<Cfg>,
+}
+
+/// Reads the lines `span` covers. Relative filenames are resolved against `project_dir` and then
+/// its ancestors, since rustdoc reports them relative to the workspace root. Blocking.
+pub fn read_item_source(project_dir: &Path, item_path: &str, span: &SourceSpan) -> Result<ItemSource> {
+    let filename = Path::new(&span.filename);
+    let file = if filename.is_absolute() {
+        filename.to_path_buf()
+    } else {
+        project_dir.ancestors()
+            .map(|dir| dir.join(filename))
+            .find(|candidate| candidate.is_file())
+            .with_context(|| format!("Source file {} not found under {}", span.filename, project_dir.display()))?
+    };
+    let content = fs::read_to_string(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let source: Vec<&str> = content.lines()
+        .skip(span.begin_line.saturating_sub(1))
+        .take(span.end_line.saturating_sub(span.begin_line) + 1)
+        .collect();
+    if source.is_empty() {
+        anyhow::bail!("{} has no lines {}-{}; the sources changed since the project was processed", file.display(), span.begin_line, span.end_line);
+    }
+    Ok(ItemSource {
+        item_path: item_path.to_string(),
+        file: file.display().to_string(),
+        begin_line: span.begin_line,
+        end_line: span.end_line,
+        source: source.join("\n"),
+    })
+}
+
+// Ids are strings in older formats and integers in newer ones; `index` keys are always strings.
+fn id_string(id: &Value) -> Option<String> {
+    match id {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+// Rejects files older than the parser knows; newer ones are parsed with a warning.
+fn check_format_version(format_version: Option<u32>) -> Result<()> {
+    let Some(version) = format_version else {
+        anyhow::bail!("rustdoc JSON has no format_version field; is it really `rustdoc --output-format json` output?");
+    };
+    if version < *SUPPORTED_FORMAT_VERSIONS.start() {
+        anyhow::bail!(
+            "rustdoc JSON format_version {} is older than the oldest supported version ({}). Update the toolchain (`rustup update nightly`) and re-process the project.",
+            version, SUPPORTED_FORMAT_VERSIONS.start()
+        );
+    }
+    if version > *SUPPORTED_FORMAT_VERSIONS.end() {
+        log::warn!("rustdoc JSON format_version {} is newer than the supported range {:?}; some items may be missed.", version, SUPPORTED_FORMAT_VERSIONS);
+    } else {
+        log::debug!("rustdoc JSON format_version {}", version);
+    }
+    Ok(())
+}
+
+// The module path of an item, between the crate name and the item's own name (`["net"]` for
+// `app::net::Client`), the form `DocItem::path` uses. `paths` entries hold the fully qualified
+// path (`["app", "net", "Client"]`), but both ends are only stripped when present, since some
+// producers (and older formats) leave them out.
+fn module_path(paths: &HashMap<String, RawPathEntry>, item_id: &str, crate_name: &str, name: Option<&str>) -> Vec<String> {
+    let Some(entry) = paths.get(item_id) else { return Vec::new() };
+    let mut path = entry.path.as_slice();
+    if path.first().is_some_and(|first| first == crate_name) {
+        path = &path[1..];
+    }
+    if let (Some(name), Some((last, rest))) = (name, path.split_last()) {
+        if last == name {
+            path = rest;
+        }
+    }
+    path.to_vec()
+}
+
+// Every path the crate's items can be named by, with the crate name, found by walking the module
+// tree from the root: each module's items, and the targets of its `pub use` re-exports of single
+// items, whole modules and globs. Items of private modules that rustdoc inlined into a public one
+// show up under the public path too. Targets may be items of other crates, not in `index`.
+fn public_paths<'a>(index: &'a HashMap<String, RawItem>, root_id: &'a str, crate_name: &str) -> Vec<(&'a str, Vec<String>)> {
+    let mut found = Vec::new();
+    walk_module(index, root_id, &[crate_name.to_string()], &mut Vec::new(), &mut found);
+    found
+}
+
+fn walk_module<'a>(index: &'a HashMap<String, RawItem>, module_id: &'a str, prefix: &[String], ancestors: &mut Vec<&'a str>, found: &mut Vec<(&'a str, Vec<String>)>) {
+    let Some(module) = index.get(module_id) else { return };
+    ancestors.push(module_id);
+    for child_id in &module.inner.module_items {
+        let Some(child) = index.get(child_id) else { continue };
+        let (target_id, name) = match &child.inner.use_item {
+            Some(use_item) => {
+                // Private imports (kept with `--document-private-items`) aren't re-exports.
+                if child.visibility != Some(Visibility::Public) {
+                    continue;
+                }
+                let Some(target_id) = use_item.target_id.as_deref() else { continue };
+                // `pub use Trait as _` only brings the trait's methods into scope.
+                if use_item.name == "_" {
+                    continue;
+                }
+                if use_item.glob {
+                    // `pub use net::*` puts the module's items next to the `use`.
+                    if !ancestors.contains(&target_id) {
+                        walk_module(index, target_id, prefix, ancestors, found);
+                    }
+                    continue;
+                }
+                (target_id, use_item.name.as_str())
+            }
+            None => match child.name.as_deref() {
+                Some(name) => (child_id.as_str(), name),
+                None => continue,
+            },
+        };
+        let mut path = prefix.to_vec();
+        path.push(name.to_string());
+        // Ancestors are skipped, so `pub use super::*` cycles end.
+        if index.get(target_id).is_some_and(|target| target.kind() == "module") && !ancestors.contains(&target_id) {
+            walk_module(index, target_id, &path, ancestors, found);
+        }
+        found.push((target_id, path));
+    }
+    ancestors.pop();
+}
+
+// Maps the ids of items defined in impl blocks (methods, associated consts and types) to the
+// type they belong to. Auto-trait (`is_synthetic`) and blanket impls are skipped: they only
+// repeat foreign trait items on every type.
+fn collect_impl_members(index: &HashMap<String, RawItem>, paths: &HashMap<String, RawPathEntry>, crate_name: &str) -> HashMap<String, ImplMember> {
+    let mut members = HashMap::new();
+    for (impl_block, impl_item) in index.values().filter_map(|item| item.inner.impl_block.as_ref().map(|block| (block, item))) {
+        let Some((parent, parent_path)) = impl_parent(index, paths, &impl_block.for_id, crate_name) else { continue };
+
+        for member_id in &impl_block.items {
+            members.insert(member_id.clone(), ImplMember {
+                parent: parent.clone(),
+                parent_path: parent_path.clone(),
+                trait_name: impl_block.trait_name.clone(),
+                cfg: impl_item.cfg.clone(),
+            });
+        }
+    }
+    members
+}
+
+// Full and module path of the type an impl block is for. Local types follow the main loop's rule
+// (crate name, the `paths` entry, then the name); types of other crates, e.g. `Vec` in `impl
+// MyTrait for Vec<T>`, keep their own crate's `paths` entry. None if the type can't be placed.
+fn impl_parent(index: &HashMap<String, RawItem>, paths: &HashMap<String, RawPathEntry>, for_id: &str, crate_name: &str) -> Option<(String, Vec<String>)> {
+    let entry = paths.get(for_id);
+    if let Some(entry) = entry.filter(|entry| entry.crate_id != 0) {
+        let (_, module) = entry.path.split_last()?;
+        return Some((entry.path.join("::"), module.to_vec()));
+    }
+    let name = index.get(for_id).and_then(|p| p.name.as_deref())
+        .or_else(|| entry.and_then(|entry| entry.path.last()).map(String::as_str))?;
+    let parent_path = module_path(paths, for_id, crate_name, Some(name));
+    let mut parent_parts = vec![crate_name.to_string()];
+    parent_parts.extend(parent_path.iter().cloned());
+    parent_parts.push(name.to_string());
+    Some((parent_parts.join("::"), parent_path))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,7 +1501,7 @@ mod tests {
     fn create_dummy_project(dir: &Path, crate_name: &str) -> Result<()> {
         let src_dir = dir.join("src");
         fs::create_dir_all(&src_dir)?;
-        fs::write(dir.join("Cargo.toml"), format!("[package]
+        fs::write(dir.join("Cargo.toml"), format!(r#"[package]
 name = "{}"
 version = "0.1.0"
 edition = "2021"
@@ -285,8 +1509,8 @@ edition = "2021"
 [lib]
 name = "{}"
 path = "src/lib.rs"
-", crate_name, crate_name.replace("-", "_")))?; // Ensure lib name is valid
-        fs::write(src_dir.join("lib.rs"), "/// A test function
+"#, crate_name, crate_name.replace("-", "_")))?; // Ensure lib name is valid
+        fs::write(src_dir.join("lib.rs"), r#"/// A test function
 pub fn hello() -> &'static str { "hello" }
 /// A test struct
 pub struct TestStruct { pub field: i32 }
@@ -295,7 +1519,7 @@ pub mod my_module {
     /// A function inside a module
     pub fn goodbye() {}
 }
-")?;
+"#)?;
         Ok(())
     }
 
@@ -314,10 +1538,11 @@ pub mod my_module {
         // It might fail if the nightly toolchain is not installed or if there are
         // issues with the rustdoc JSON output on the specific nightly version.
 
-        let json_path = generate_rustdoc_json(temp_project_dir.path(), temp_output_dir.path())?;
-        assert!(json_path.exists(), "JSON file should be generated");
+        let json_paths = generate_rustdoc_json(temp_project_dir.path(), temp_output_dir.path(), &RustdocSettings::default(), DocTargets::Auto, &CancelFlag::default())?;
+        assert_eq!(json_paths.len(), 1, "Only the library should be documented");
+        assert!(json_paths[0].exists(), "JSON file should be generated");
 
-        let crate_docs = parse_rustdoc_json_file(&json_path)?;
+        let crate_docs = parse_rustdoc_json_files(&json_paths)?;
         assert_eq!(crate_docs.crate_name, crate_name.replace("-", "_"), "Crate name should match and be sanitized");
 
         // Check for specific items (adjust paths based on actual rustdoc output)
@@ -361,4 +1586,470 @@ pub mod my_module {
         temp_output_dir.close()?;
         Ok(())
     }
+
+    #[test]
+    fn test_parse_impl_methods() -> Result<()> {
+        let json = serde_json::json!({
+            "format_version": 39,
+            "root": "0",
+            "index": {
+                "0": { "name": "app", "docs": null, "inner": { "module": { "items": ["1", "2", "5"] } } },
+                "1": { "name": "Config", "docs": "App settings.", "inner": { "struct": {} } },
+                "2": { "name": null, "docs": null, "inner": { "impl": {
+                    "is_synthetic": false, "blanket_impl": null, "trait": null,
+                    "for": { "resolved_path": { "path": "Config", "id": "1", "args": null } },
+                    "items": ["3"]
+                } } },
+                "3": { "name": "new", "docs": "Creates the default config.", "inner": { "function": {
+                           "generics": { "params": [{ "name": "T", "kind": { "type": { "bounds": [], "default": null, "is_synthetic": false } } }], "where_predicates": [] }
+                       } },
+                       "span": { "filename": "src/lib.rs", "begin": [4, 4], "end": [6, 5] },
+                       "deprecation": { "since": "0.3.0", "note": "use `Config::builder`" },
+                       "attrs": ["#[cfg(unix)]"] },
+                "5": { "name": null, "docs": null, "attrs": ["#[cfg_attr(docsrs, doc(cfg(feature = \"fmt\")))]"], "inner": { "impl": {
+                    "is_synthetic": false, "blanket_impl": null,
+                    "trait": { "path": "std::fmt::Display", "id": "9", "args": null },
+                    "for": { "resolved_path": { "path": "Config", "id": "1", "args": null } },
+                    "items": ["6"]
+                } } },
+                "6": { "name": "fmt", "docs": null, "inner": { "function": {} } }
+            },
+            "paths": { "1": { "crate_id": 0, "path": [], "kind": "struct" } }
+        });
+        let dir = tempdir()?;
+        let json_path = dir.path().join("app.json");
+        fs::write(&json_path, json.to_string())?;
+
+        let docs = parse_rustdoc_json_file(&json_path)?;
+        let new = &docs.items["app::Config::new"];
+        assert_eq!(new.item_type, "function");
+        assert_eq!(new.parent.as_deref(), Some("app::Config"));
+        assert_eq!(new.trait_name, None);
+        assert_eq!(new.generics.as_ref().map(Generics::render).as_deref(), Some("<T>"));
+        assert_eq!(new.span, Some(SourceSpan { filename: "src/lib.rs".to_string(), begin_line: 4, end_line: 6 }));
+        assert_eq!(new.deprecation.as_ref().map(Deprecation::describe).as_deref(), Some("Deprecated since 0.3.0: use `Config::builder`"));
+        assert!(!docs.items["app::Config"].is_deprecated());
+        assert_eq!(new.cfg, Some(Cfg::Name("unix".to_string())));
+        // An impl block's cfg carries over to its members.
+        let fmt = &docs.items["app::Config::<Display>::fmt"];
+        assert_eq!(fmt.cfg.as_ref().map(Cfg::describe).as_deref(), Some("Requires feature `fmt`"));
+        assert_eq!(fmt.trait_name.as_deref(), Some("Display"));
+        assert_eq!(fmt.parent.as_deref(), Some("app::Config"));
+        assert_eq!(docs.items["app::Config"].item_type, "struct");
+        // Methods are no longer listed at the crate root.
+        assert!(!docs.items.contains_key("app::new"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_trait_impl_members() -> Result<()> {
+        let trait_impl = |trait_path: &str, args: Value, for_path: &str, for_id: &str, item: &str| serde_json::json!({
+            "name": null, "docs": null, "inner": { "impl": {
+                "is_synthetic": false, "blanket_impl": null,
+                "trait": { "path": trait_path, "id": "90", "args": args },
+                "for": { "resolved_path": { "path": for_path, "id": for_id, "args": null } },
+                "items": [item]
+            } }
+        });
+        let from = |ty: &str| serde_json::json!({ "angle_bracketed": { "args": [
+            { "type": { "resolved_path": { "path": ty, "id": "91", "args": null } } }
+        ], "constraints": [] } });
+        let function = |name: &str| serde_json::json!({ "name": name, "docs": null, "inner": { "function": {} } });
+        let json = serde_json::json!({
+            "format_version": 39,
+            "root": "0",
+            "index": {
+                "0": { "name": "app", "docs": null, "inner": { "module": { "items": ["1"] } } },
+                "1": { "name": "Id", "docs": null, "inner": { "struct": {} } },
+                "2": trait_impl("std::fmt::Display", Value::Null, "Id", "1", "3"),
+                "3": function("fmt"),
+                "4": trait_impl("std::fmt::Debug", Value::Null, "Id", "1", "5"),
+                "5": function("fmt"),
+                "6": trait_impl("From", from("u32"), "Id", "1", "7"),
+                "7": function("from"),
+                "8": trait_impl("From", from("String"), "Id", "1", "9"),
+                "9": function("from"),
+                // A local trait implemented for a type of another crate.
+                "10": trait_impl("Named", Value::Null, "std::vec::Vec", "20", "11"),
+                "11": function("name")
+            },
+            "paths": {
+                "1": { "crate_id": 0, "path": ["app", "Id"], "kind": "struct" },
+                "20": { "crate_id": 1, "path": ["alloc", "vec", "Vec"], "kind": "struct" }
+            }
+        });
+        let dir = tempdir()?;
+        let json_path = dir.path().join("app.json");
+        fs::write(&json_path, json.to_string())?;
+
+        let docs = parse_rustdoc_json_file(&json_path)?;
+        let mut members: Vec<&str> = docs.items.keys().map(String::as_str).filter(|path| path.contains(">::")).collect();
+        members.sort();
+        assert_eq!(members, [
+            "alloc::vec::Vec::<Named>::name",
+            "app::Id::<Debug>::fmt",
+            "app::Id::<Display>::fmt",
+            "app::Id::<From<String>>::from",
+            "app::Id::<From<u32>>::from",
+        ]);
+        assert_eq!(docs.items["app::Id::<From<u32>>::from"].trait_name.as_deref(), Some("From<u32>"));
+        assert_eq!(docs.items["app::Id::<Debug>::fmt"].parent.as_deref(), Some("app::Id"));
+        let name = &docs.items["alloc::vec::Vec::<Named>::name"];
+        assert_eq!((name.parent.as_deref(), name.path.clone()), (Some("alloc::vec::Vec"), vec!["alloc".to_string(), "vec".to_string()]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_reexports() -> Result<()> {
+        let json = serde_json::json!({
+            "format_version": 39,
+            "root": "0",
+            "index": {
+                "0": { "name": "app", "docs": null, "inner": { "module": { "items": ["3", "4", "5", "7"] } } },
+                "1": { "name": "net", "docs": null, "inner": { "module": { "items": ["2"], "is_stripped": true } } },
+                "2": { "name": "Client", "docs": "An HTTP client.", "inner": { "struct": {} } },
+                "3": { "name": null, "docs": null, "visibility": "public", "inner": { "use": { "source": "net::Client", "name": "Client", "id": "2", "is_glob": false } } },
+                "4": { "name": null, "docs": null, "visibility": "public", "inner": { "use": { "source": "serde::Serialize", "name": "Serialize", "id": "9", "is_glob": false } } },
+                "5": { "name": null, "docs": null, "visibility": "public", "inner": { "use": { "source": "util", "name": "util", "id": "6", "is_glob": true } } },
+                "6": { "name": "util", "docs": null, "inner": { "module": { "items": ["8"], "is_stripped": true } } },
+                "7": { "name": null, "docs": null, "visibility": "public", "inner": { "use": { "source": "net::Client", "name": "_", "id": "2", "is_glob": false } } },
+                "8": { "name": "retry", "docs": "Retries a [`Client`] request, see [the trait][`Serialize`].", "inner": { "function": {} },
+                       "links": { "`Client`": "2", "`Serialize`": "9" } }
+            },
+            "paths": {
+                "1": { "crate_id": 0, "path": ["app", "net"], "kind": "module" },
+                "2": { "crate_id": 0, "path": ["app", "net", "Client"], "kind": "struct" },
+                "6": { "crate_id": 0, "path": ["app", "util"], "kind": "module" },
+                "8": { "crate_id": 0, "path": ["app", "util", "retry"], "kind": "function" },
+                "9": { "crate_id": 1, "path": ["serde", "ser", "Serialize"], "kind": "trait" }
+            }
+        });
+        let dir = tempdir()?;
+        let json_path = dir.path().join("app.json");
+        fs::write(&json_path, json.to_string())?;
+
+        let docs = parse_rustdoc_json_file(&json_path)?;
+        assert_eq!(docs.items["app::net::Client"].reexports, ["app::Client"]);
+        let retry = &docs.items["app::util::retry"];
+        assert_eq!(retry.reexports, ["app::retry"]);
+        // Intra-doc links point at the canonical paths, also of other crates' items.
+        assert_eq!(retry.links, ["app::net::Client", "serde::ser::Serialize"]);
+        assert_eq!(retry.description.as_deref(), Some("Retries a [`Client`](app::net::Client) request, see [the trait](serde::ser::Serialize)."));
+        let serialize = &docs.items["app::Serialize"];
+        assert_eq!(serialize.reexport_of.as_deref(), Some("serde::ser::Serialize"));
+        assert_eq!(serialize.item_type, "trait");
+        assert!(serialize.path.is_empty());
+        assert!(docs.items.values().all(|item| item.item_type != "use"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_visibility() -> Result<()> {
+        let json = serde_json::json!({
+            "format_version": 39,
+            "root": "0",
+            "index": {
+                "0": { "name": "app", "visibility": "public", "inner": { "module": { "items": ["1", "4", "5", "6", "9", "11"] } } },
+                "1": { "name": "Config", "visibility": "public", "inner": { "struct": { "kind": { "plain": { "fields": ["2", "3"], "has_stripped_fields": false } } } } },
+                "2": { "name": "timeout", "visibility": "public", "inner": { "struct_field": { "primitive": "u64" } } },
+                "3": { "name": "secret", "visibility": "default", "inner": { "struct_field": { "primitive": "u64" } } },
+                "4": { "name": "helper", "visibility": "crate", "inner": { "function": {} } },
+                "5": { "name": "Color", "visibility": { "restricted": { "parent": "0", "path": "::app" } }, "inner": { "enum": { "variants": ["7"] } } },
+                "7": { "name": "Red", "visibility": "default", "inner": { "variant": { "kind": "plain" } } },
+                "6": { "name": "Run", "visibility": "public", "inner": { "trait": { "items": ["8"] } } },
+                "8": { "name": "run", "visibility": "default", "inner": { "function": {} } },
+                "9": { "name": "Inner", "visibility": "default", "inner": { "struct": {} } },
+                "10": { "name": null, "visibility": "default", "inner": { "impl": {
+                    "is_synthetic": false, "blanket_impl": null, "trait": null,
+                    "for": { "resolved_path": { "path": "Inner", "id": "9", "args": null } },
+                    "items": ["12"]
+                } } },
+                "12": { "name": "new", "visibility": "public", "inner": { "function": {} } },
+                "11": { "name": null, "visibility": "default", "inner": { "use": { "source": "std::fmt", "name": "fmt", "id": "20", "is_glob": false } } }
+            },
+            "paths": {
+                "1": { "crate_id": 0, "path": ["app", "Config"], "kind": "struct" },
+                "20": { "crate_id": 1, "path": ["std", "fmt"], "kind": "module" }
+            }
+        });
+        let dir = tempdir()?;
+        let json_path = dir.path().join("app.json");
+        fs::write(&json_path, json.to_string())?;
+
+        let mut docs = parse_rustdoc_json_file(&json_path)?;
+        let visibility = |path: &str| docs.items[path].visibility.clone();
+        assert_eq!(visibility("app::Config"), Visibility::Public);
+        assert_eq!(visibility("app::Config::timeout"), Visibility::Public);
+        assert_eq!(visibility("app::Config::secret"), Visibility::Private);
+        assert_eq!(visibility("app::helper"), Visibility::Crate);
+        // Variants and trait items take their parent's visibility.
+        assert_eq!(visibility("app::Color::Red"), Visibility::Restricted("::app".to_string()));
+        assert_eq!(visibility("app::Run::run"), Visibility::Public);
+        // A `pub fn` of a private type is private too.
+        assert_eq!(visibility("app::Inner::new"), Visibility::Private);
+        // A private `use` is no re-export.
+        assert!(!docs.items.contains_key("app::fmt"));
+
+        assert_eq!(docs.retain_public(), 6);
+        let mut kept: Vec<&str> = docs.items.keys().map(String::as_str).collect();
+        kept.sort();
+        assert_eq!(kept, ["app", "app::Config", "app::Config::timeout", "app::Run", "app::Run::run"]);
+        assert_eq!(docs.items["app::Run::run"].parent.as_deref(), Some("app::Run"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_variants_and_fields() -> Result<()> {
+        let json = serde_json::json!({
+            "format_version": 39,
+            "root": 0,
+            "index": {
+                "0": { "name": "app", "docs": null, "inner": { "module": { "items": [1, 4] } } },
+                "1": { "name": "Color", "docs": "A color.", "inner": { "enum": { "variants": [2, 3], "generics": { "params": [], "where_predicates": [] } } } },
+                "2": { "name": "Red", "docs": "Pure red.", "inner": { "variant": { "kind": "plain", "discriminant": null } } },
+                "3": { "name": "Rgb", "docs": null, "inner": { "variant": { "kind": { "struct": { "fields": [7], "has_stripped_fields": false } } } } },
+                "7": { "name": "r", "docs": "Red channel.", "inner": { "struct_field": { "primitive": "u8" } } },
+                "4": { "name": "Config", "docs": null, "inner": { "struct": { "kind": { "plain": { "fields": [5], "has_stripped_fields": false } } } } },
+                "5": { "name": "timeout", "docs": "Request timeout in seconds.", "inner": { "struct_field": { "primitive": "u64" } } },
+                "8": { "name": "Id", "docs": null, "inner": { "struct": { "kind": { "tuple": [9] } } } },
+                "9": { "name": "0", "docs": null, "inner": { "struct_field": { "primitive": "u32" } } }
+            },
+            "paths": {
+                "1": { "crate_id": 0, "path": ["app", "Color"], "kind": "enum" },
+                "2": { "crate_id": 0, "path": ["app", "Color", "Red"], "kind": "variant" },
+                "4": { "crate_id": 0, "path": ["app", "Config"], "kind": "struct" },
+                "8": { "crate_id": 0, "path": ["app", "Id"], "kind": "struct" }
+            }
+        });
+        let dir = tempdir()?;
+        let json_path = dir.path().join("app.json");
+        fs::write(&json_path, json.to_string())?;
+
+        let docs = parse_rustdoc_json_file(&json_path)?;
+        let red = &docs.items["app::Color::Red"];
+        assert_eq!(red.item_type, "variant");
+        assert_eq!(red.parent.as_deref(), Some("app::Color"));
+        assert_eq!(red.description.as_deref(), Some("Pure red."));
+        let timeout = &docs.items["app::Config::timeout"];
+        assert_eq!(timeout.item_type, "struct_field");
+        assert_eq!(timeout.parent.as_deref(), Some("app::Config"));
+        assert!(timeout.path.is_empty());
+        assert_eq!(docs.items["app::Color::Rgb::r"].parent.as_deref(), Some("app::Color::Rgb"));
+        // Fields are never keyed at the crate root, and tuple fields are dropped.
+        assert!(!docs.items.contains_key("app::timeout"));
+        assert!(!docs.items.contains_key("app::Id::0") && !docs.items.contains_key("app::0"));
+        assert_eq!(docs.items.len(), 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_qualified_paths_and_versions() -> Result<()> {
+        let crate_json = |format_version: u32| serde_json::json!({
+            "format_version": format_version,
+            "root": 0,
+            "index": {
+                "0": { "name": "app", "docs": "The app.", "inner": { "module": { "items": [1] } } },
+                "1": { "name": "net", "docs": null, "inner": { "module": { "items": [2] } } },
+                "2": { "name": "Client", "docs": "An HTTP client.", "inner": { "struct": {} } }
+            },
+            "paths": {
+                "0": { "crate_id": 0, "path": ["app"], "kind": "module" },
+                "1": { "crate_id": 0, "path": ["app", "net"], "kind": "module" },
+                "2": { "crate_id": 0, "path": ["app", "net", "Client"], "kind": "struct" }
+            }
+        });
+        let dir = tempdir()?;
+        let json_path = dir.path().join("app.json");
+        fs::write(&json_path, crate_json(*SUPPORTED_FORMAT_VERSIONS.end()).to_string())?;
+
+        // `paths` holds fully qualified paths; the crate and item names aren't repeated.
+        let docs = parse_rustdoc_json_file(&json_path)?;
+        let mut keys: Vec<&str> = docs.items.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["app", "app::net", "app::net::Client"]);
+        assert_eq!(docs.items["app::net::Client"].path, vec!["net"]);
+
+        // Newer formats are attempted, older ones refused with the fix in the message.
+        fs::write(&json_path, crate_json(SUPPORTED_FORMAT_VERSIONS.end() + 1).to_string())?;
+        assert_eq!(parse_rustdoc_json_file(&json_path)?.items.len(), 3);
+        fs::write(&json_path, crate_json(SUPPORTED_FORMAT_VERSIONS.start() - 1).to_string())?;
+        let error = format!("{:#}", parse_rustdoc_json_file(&json_path).unwrap_err());
+        assert!(error.contains("older than the oldest supported"), "{}", error);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_item_source() -> Result<()> {
+        let workspace = tempdir()?;
+        let member = workspace.path().join("crates/app");
+        fs::create_dir_all(workspace.path().join("crates/app/src"))?;
+        fs::write(member.join("src/lib.rs"), "pub struct Config;\n\nimpl Config {\n    pub fn new() -> Self {\n        Config\n    }\n}\n")?;
+
+        let span = SourceSpan { filename: "src/lib.rs".to_string(), begin_line: 4, end_line: 6 };
+        let source = read_item_source(&member, "app::Config::new", &span)?;
+        assert_eq!(source.source, "    pub fn new() -> Self {\n        Config\n    }");
+        // Workspace members report paths relative to the workspace root.
+        let span = SourceSpan { filename: "crates/app/src/lib.rs".to_string(), begin_line: 1, end_line: 1 };
+        assert_eq!(read_item_source(&member, "app::Config", &span)?.source, "pub struct Config;");
+        // Spans past the end of a since-edited file are an error, not an empty snippet.
+        let span = SourceSpan { filename: "src/lib.rs".to_string(), begin_line: 40, end_line: 42 };
+        assert!(read_item_source(&member, "app::Config::old", &span).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_and_select_package_targets() -> Result<()> {
+        let metadata = serde_json::json!({
+            "packages": [{
+                "name": "my-tool",
+                "manifest_path": "/work/my-tool/Cargo.toml",
+                "targets": [
+                    { "kind": ["bin"], "name": "my-tool" },
+                    { "kind": ["example"], "name": "demo" },
+                    { "kind": ["test"], "name": "integration" },
+                    { "kind": ["custom-build"], "name": "build-script-build" }
+                ]
+            }]
+        });
+        let targets = parse_package_targets(&metadata, Path::new("/work/my-tool/Cargo.toml"))?;
+        assert_eq!(targets, vec![
+            PackageTarget { name: "my-tool".to_string(), kind: TargetKind::Bin },
+            PackageTarget { name: "demo".to_string(), kind: TargetKind::Example },
+        ]);
+        // A pure binary crate falls back to its binaries instead of failing with "no library targets".
+        assert_eq!(select_targets(&targets, DocTargets::Auto)?, targets[..1].to_vec());
+        assert!(select_targets(&targets, DocTargets::Lib).is_err());
+        assert_eq!(select_targets(&targets, DocTargets::All)?.len(), 2);
+
+        // A `[lib]` renamed from the package and built as a cdylib is still the library, and its
+        // JSON is named after the lib.
+        let metadata = serde_json::json!({
+            "packages": [{
+                "name": "py-bindings",
+                "manifest_path": "/work/py-bindings/Cargo.toml",
+                "targets": [{ "kind": ["cdylib", "rlib"], "name": "fast_py" }]
+            }]
+        });
+        let lib = parse_package_targets(&metadata, Path::new("/work/py-bindings/Cargo.toml"))?;
+        assert_eq!(lib, vec![PackageTarget { name: "fast_py".to_string(), kind: TargetKind::Lib }]);
+        assert_eq!(lib[0].output_file_name(&lib[0].crate_name()), "fast_py.json");
+        assert_eq!(targets[0].output_file_name("my_tool"), "my_tool.bin.my_tool.json");
+        assert_eq!(targets[1].cargo_args(), vec!["--example", "demo"]);
+        assert_eq!("ALL".parse::<DocTargets>(), Ok(DocTargets::All));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_workspace_member_dirs() {
+        let metadata = serde_json::json!({
+            "packages": [
+                { "id": "app 0.1.0", "manifest_path": "/work/app/Cargo.toml" },
+                { "id": "app-core 0.1.0", "manifest_path": "/work/app/crates/core/Cargo.toml" },
+                { "id": "serde 1.0.0", "manifest_path": "/registry/serde-1.0.0/Cargo.toml" }
+            ],
+            "workspace_members": ["app 0.1.0", "app-core 0.1.0"]
+        });
+        assert_eq!(parse_workspace_member_dirs(&metadata), [PathBuf::from("/work/app"), PathBuf::from("/work/app/crates/core")]);
+    }
+
+    #[test]
+    fn test_package_version() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("Cargo.toml"), "[workspace]\nmembers = [\"app\", \"cli\"]\n\n[workspace.package]\nversion = \"0.6.3\"\n")?;
+        let (app, cli) = (dir.path().join("app"), dir.path().join("cli"));
+        fs::create_dir_all(&app)?;
+        fs::create_dir_all(&cli)?;
+        fs::write(app.join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"1.2.0\"\n")?;
+        fs::write(cli.join("Cargo.toml"), "[package]\nname = \"cli\"\nversion.workspace = true\n")?;
+        assert_eq!(package_version(&app).as_deref(), Some("1.2.0"));
+        assert_eq!(package_version(&cli).as_deref(), Some("0.6.3"));
+        assert_eq!(package_version(dir.path()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_intra_doc_links() {
+        let targets = HashMap::from([("`Config`", "app::Config".to_string()), ("Config::new", "app::Config::new".to_string())]);
+        let docs = "Reads a [`Config`], see [new](Config::new) and [`Builder`].\n\n```\nlet v = [`Config`];\n```\n[the constructor]: Config::new\n";
+        assert_eq!(
+            resolve_intra_doc_links(docs, &targets),
+            "Reads a [`Config`](app::Config), see [new](app::Config::new) and [`Builder`].\n\n```\nlet v = [`Config`];\n```\n[the constructor]: app::Config::new\n",
+        );
+    }
+
+    #[test]
+    fn test_extract_examples() {
+        let docs = "Parses a config.\n\n```\n# use app::Config;\nlet c = Config::parse(\"x\")?;\n#\n# Ok::<(), app::Error>(())\n```\n\n```toml\nname = \"x\"\n```\n\n````rust,no_run\n#[derive(Debug)]\nstruct A;\n```\n````\n\n```text\nnot code\n```";
+        assert_eq!(extract_examples(docs), vec![
+            "use app::Config;\nlet c = Config::parse(\"x\")?;\n\nOk::<(), app::Error>(())".to_string(),
+            "#[derive(Debug)]\nstruct A;\n```".to_string(),
+        ]);
+        assert!(extract_examples("No code here.").is_empty());
+        assert!(is_rust_block("ignore-wasm32, edition2021"));
+        assert!(!is_rust_block("sh"));
+    }
+
+    #[test]
+    fn test_parse_rustc_version() {
+        assert_eq!(parse_rustc_version("rustc 1.83.0-nightly (90b35a623 2024-11-26)").as_deref(), Some("1.83.0-nightly"));
+        assert_eq!(parse_rustc_version("rustc 1.82.0"), Some("1.82.0".to_string()));
+        assert_eq!(parse_rustc_version("cargo 1.82.0"), None);
+    }
+
+    #[test]
+    fn test_toolchain_listed() {
+        let list = "stable-x86_64-unknown-linux-gnu (default)\nnightly-2024-05-01-x86_64-unknown-linux-gnu\n";
+        assert!(toolchain_listed(list, "stable"));
+        assert!(toolchain_listed(list, "nightly-2024-05-01"));
+        assert!(!toolchain_listed(list, "nightly"));
+        assert!(toolchain_listed("nightly-aarch64-apple-darwin\n", "nightly"));
+        assert!(!toolchain_listed("", "nightly"));
+    }
+
+    #[test]
+    fn test_json_output_args() {
+        assert_eq!(json_output_args("nightly-2024-05-01"), ["-Z", "unstable-options", "--output-format", "json"]);
+        assert_eq!(json_output_args("stable"), ["--output-format", "json"]);
+        assert_eq!(json_output_args("1.85.0"), ["--output-format", "json"]);
+    }
+
+    #[test]
+    fn test_parse_direct_dependencies() -> Result<()> {
+        let metadata = serde_json::json!({
+            "packages": [
+                { "id": "app 0.1.0", "name": "app", "version": "0.1.0", "targets": [{ "kind": ["bin"], "name": "app" }] },
+                { "id": "serde-json 1.0.0", "name": "serde-json", "version": "1.0.0", "targets": [{ "kind": ["lib"], "name": "serde-json" }] },
+                { "id": "cc 1.0.0", "name": "cc", "version": "1.0.0", "targets": [{ "kind": ["lib"], "name": "cc" }] },
+                { "id": "derive-utils 0.3.0", "name": "derive-utils", "version": "0.3.0", "targets": [{ "kind": ["proc-macro"], "name": "derive_utils_impl" }] },
+                { "id": "tool 0.2.0", "name": "tool", "version": "0.2.0", "targets": [{ "kind": ["bin"], "name": "tool" }] }
+            ],
+            "target_directory": "/shared/target",
+            "resolve": {
+                "root": "app 0.1.0",
+                "nodes": [{
+                    "id": "app 0.1.0",
+                    "deps": [
+                        { "name": "serde_json", "pkg": "serde-json 1.0.0", "dep_kinds": [{ "kind": null, "target": null }] },
+                        { "name": "cc", "pkg": "cc 1.0.0", "dep_kinds": [{ "kind": "build", "target": null }] },
+                        { "name": "tool", "pkg": "tool 0.2.0", "dep_kinds": [{ "kind": null, "target": null }] },
+                        { "name": "derive_utils", "pkg": "derive-utils 0.3.0", "dep_kinds": [{ "kind": null, "target": null }] }
+                    ]
+                }]
+            }
+        });
+        let deps = parse_direct_dependencies(&metadata)?;
+        // Build deps and packages without a library target are skipped.
+        assert_eq!(deps, vec![
+            DependencyPackage { name: "serde-json".to_string(), version: "1.0.0".to_string(), lib_name: "serde_json".to_string() },
+            // The JSON of a renamed lib is named after the lib, not the package.
+            DependencyPackage { name: "derive-utils".to_string(), version: "0.3.0".to_string(), lib_name: "derive_utils_impl".to_string() },
+        ]);
+        // Output goes where cargo says (CARGO_TARGET_DIR, build.target-dir), not `<project>/target`.
+        let settings = RustdocSettings::default();
+        assert_eq!(resolve_cargo_target_dir(&metadata, Path::new("/work/app"), &settings), PathBuf::from("/shared/target"));
+        assert_eq!(resolve_cargo_target_dir(&serde_json::json!({}), Path::new("/work/app"), &settings), PathBuf::from("/work/app/target"));
+        let isolated = RustdocSettings { cargo_target_dir: Some(PathBuf::from("/cache/cargo_target/app-1")), ..settings };
+        assert_eq!(resolve_cargo_target_dir(&metadata, Path::new("/work/app"), &isolated), PathBuf::from("/cache/cargo_target/app-1"));
+        Ok(())
+    }
 }
@@ -0,0 +1,191 @@
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::Duration;
+
+use crate::config::SandboxSettings;
+use crate::jobs::CancelFlag;
+
+// How often a running build checks whether it was cancelled.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Variables kept in the build environment when `scrub_env` is on. Enough for cargo/rustup to work.
+const BASE_ENV_ALLOWLIST: &[&str] = &[
+    "PATH", "HOME", "USER", "LANG", "TMPDIR", "TEMP", "TMP",
+    "CARGO_HOME", "RUSTUP_HOME", "RUSTUP_TOOLCHAIN",
+    // Windows needs these for the toolchain to start at all.
+    "SYSTEMROOT", "USERPROFILE", "APPDATA", "LOCALAPPDATA", "PATHEXT",
+];
+
+// Replaced in `wrapper_command` arguments with the project directory.
+const PROJECT_DIR_PLACEHOLDER: &str = "{project_dir}";
+
+/// Builds the `cargo` command used to run rustdoc on `project_dir`, applying the hardened-mode
+/// settings: an optional jail/container wrapper, a scrubbed environment and `--offline --locked`.
+///
+/// Build scripts and proc macros run with the user's privileges, so hardened mode is recommended
+/// for projects that aren't fully trusted.
+pub fn cargo_command(project_dir: &Path, settings: &SandboxSettings) -> Command {
+    let mut cmd = match settings.wrapper_command.split_first().filter(|_| settings.enabled) {
+        Some((program, args)) => {
+            let project_dir_str = project_dir.to_string_lossy();
+            let mut cmd = Command::new(program);
+            cmd.args(args.iter().map(|arg| arg.replace(PROJECT_DIR_PLACEHOLDER, &project_dir_str)));
+            cmd.arg("cargo");
+            cmd
+        }
+        None => Command::new("cargo"),
+    };
+    cmd.current_dir(project_dir);
+    if settings.enabled && settings.scrub_env {
+        cmd.env_clear();
+        cmd.envs(allowed_env(settings, |key| std::env::var_os(key)));
+    }
+    cmd
+}
+
+/// Cargo flags (placed before `--`) added in hardened mode.
+pub fn cargo_flags(settings: &SandboxSettings) -> Vec<&'static str> {
+    if settings.enabled && settings.offline {
+        vec!["--offline", "--locked"]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Runs `cmd` to completion like `Command::output`, but kills it as soon as `cancel` is set.
+/// Blocking.
+pub fn run_cancellable(mut cmd: Command, cancel: &CancelFlag) -> Result<Output> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().context("Failed to spawn command")?;
+    // Drain the pipes on threads so a chatty build can't block on a full pipe.
+    let stdout_reader = child.stdout.take().map(|pipe| std::thread::spawn(move || read_to_end(pipe)));
+    let stderr_reader = child.stderr.take().map(|pipe| std::thread::spawn(move || read_to_end(pipe)));
+    let status = loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for command")? {
+            break status;
+        }
+        if cancel.is_cancelled() {
+            log::info!("Cancelled; killing {:?}", cmd.get_program());
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("Cancelled");
+        }
+        std::thread::sleep(CANCEL_POLL_INTERVAL);
+    };
+    let join = |reader: Option<std::thread::JoinHandle<Vec<u8>>>| reader.and_then(|r| r.join().ok()).unwrap_or_default();
+    Ok(Output { status, stdout: join(stdout_reader), stderr: join(stderr_reader) })
+}
+
+fn read_to_end(mut pipe: impl Read) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    let _ = pipe.read_to_end(&mut buffer);
+    buffer
+}
+
+/// Whether `project_dir` may be built without asking the user first. Always true unless
+/// hardened mode is on; then the project must be inside one of `trusted_dirs`.
+pub fn is_trusted(project_dir: &Path, settings: &SandboxSettings) -> bool {
+    if !settings.enabled {
+        return true;
+    }
+    let project_dir = canonical(project_dir);
+    settings.trusted_dirs.iter().any(|trusted| project_dir.starts_with(canonical(trusted)))
+}
+
+fn allowed_env<F>(settings: &SandboxSettings, lookup: F) -> Vec<(String, std::ffi::OsString)>
+where
+    F: Fn(&str) -> Option<std::ffi::OsString>,
+{
+    BASE_ENV_ALLOWLIST
+        .iter()
+        .map(|key| key.to_string())
+        .chain(settings.env_allowlist.iter().cloned())
+        .filter_map(|key| lookup(&key).map(|value| (key, value)))
+        .collect()
+}
+
+// Symlinks and `..` must not let a project escape the trusted directories.
+fn canonical(path: &Path) -> PathBuf {
+    canonicalize_plain(path).unwrap_or_else(|| path.to_path_buf())
+}
+
+fn canonicalize_plain(path: &Path) -> Option<PathBuf> {
+    let canonical = std::fs::canonicalize(path).ok()?;
+    // Strip the Windows verbatim prefix (\\?\) so prefixes compare equal.
+    let stripped = canonical.to_str().and_then(|s| s.strip_prefix(r"\\?\")).map(PathBuf::from);
+    Some(stripped.unwrap_or(canonical))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsString;
+
+    fn hardened() -> SandboxSettings {
+        SandboxSettings { enabled: true, ..Default::default() }
+    }
+
+    #[test]
+    fn test_trusted_dirs() {
+        let trusted = tempfile::tempdir().unwrap();
+        let project = trusted.path().join("my_project");
+        std::fs::create_dir(&project).unwrap();
+        let outside = tempfile::tempdir().unwrap();
+
+        let settings = SandboxSettings { trusted_dirs: vec![trusted.path().to_path_buf()], ..hardened() };
+        assert!(is_trusted(&project, &settings));
+        assert!(!is_trusted(outside.path(), &settings));
+        assert!(!is_trusted(&project.join("..").join(".."), &settings));
+        // Without hardened mode everything is trusted.
+        assert!(is_trusted(outside.path(), &SandboxSettings::default()));
+    }
+
+    #[test]
+    fn test_env_allowlist_and_flags() {
+        let settings = SandboxSettings { env_allowlist: vec!["HTTPS_PROXY".to_string()], ..hardened() };
+        let env = allowed_env(&settings, |key| match key {
+            "PATH" | "HTTPS_PROXY" | "AWS_SECRET_ACCESS_KEY" => Some(OsString::from("x")),
+            _ => None,
+        });
+        let keys: Vec<&str> = env.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["PATH", "HTTPS_PROXY"]);
+
+        assert_eq!(cargo_flags(&settings), vec!["--offline", "--locked"]);
+        assert!(cargo_flags(&SandboxSettings::default()).is_empty());
+    }
+
+    #[test]
+    fn test_wrapper_command() {
+        let settings = SandboxSettings {
+            wrapper_command: vec!["firejail".to_string(), "--whitelist={project_dir}".to_string()],
+            ..hardened()
+        };
+        let cmd = cargo_command(Path::new("/work/proj"), &settings);
+        assert_eq!(cmd.get_program(), "firejail");
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args, vec!["--whitelist=/work/proj", "cargo"]);
+
+        let plain = cargo_command(Path::new("/work/proj"), &SandboxSettings { enabled: false, ..settings });
+        assert_eq!(plain.get_program(), "cargo");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_cancellable() {
+        let mut echo = Command::new("sh");
+        echo.args(["-c", "echo out; echo err >&2"]);
+        let output = run_cancellable(echo, &CancelFlag::default()).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+
+        let cancel = CancelFlag::default();
+        cancel.cancel();
+        let mut sleep = Command::new("sleep");
+        sleep.arg("30");
+        let started = std::time::Instant::now();
+        assert!(run_cancellable(sleep, &cancel).is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
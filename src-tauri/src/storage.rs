@@ -0,0 +1,438 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::mcp_server::SearchFilter;
+use crate::rustdoc_processor::{CrateDocs, DocItem};
+
+// Bump when the schema or the encoded `DocItem` changes; older databases are dropped and filled again as projects are processed.
+const SCHEMA_VERSION: i32 = 7;
+
+const SCHEMA: &str = "
+    CREATE TABLE items (
+        id INTEGER PRIMARY KEY,
+        project_path TEXT NOT NULL,
+        crate_name TEXT NOT NULL,
+        is_dependency INTEGER NOT NULL,
+        full_path TEXT NOT NULL,
+        -- `full_path` and the item's re-export paths, space-separated, for the FTS index.
+        search_paths TEXT NOT NULL,
+        name TEXT NOT NULL,
+        item_type TEXT NOT NULL,
+        description TEXT,
+        deprecated INTEGER NOT NULL,
+        public INTEGER NOT NULL,
+        -- The whole DocItem, bincode-encoded; the columns above are for querying.
+        item BLOB NOT NULL,
+        UNIQUE (project_path, crate_name, full_path)
+    );
+    CREATE INDEX items_by_type ON items (project_path, item_type);
+    -- `_` is part of a word so `from_str` matches as a whole; `::` separates path segments.
+    CREATE VIRTUAL TABLE items_fts USING fts5 (
+        name, search_paths, description,
+        content = 'items', content_rowid = 'id',
+        tokenize = \"unicode61 tokenchars '_'\"
+    );
+    CREATE TRIGGER items_fts_insert AFTER INSERT ON items BEGIN
+        INSERT INTO items_fts (rowid, name, search_paths, description) VALUES (new.id, new.name, new.search_paths, new.description);
+    END;
+    CREATE TRIGGER items_fts_delete AFTER DELETE ON items BEGIN
+        INSERT INTO items_fts (items_fts, rowid, name, search_paths, description) VALUES ('delete', old.id, old.name, old.search_paths, old.description);
+    END;
+";
+
+// Not part of `SCHEMA`: the history isn't derived from the docs, so a schema bump keeps it.
+const QUERY_HISTORY_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS query_history (
+        id INTEGER PRIMARY KEY,
+        timestamp_ms INTEGER NOT NULL,
+        query TEXT NOT NULL,
+        project_path TEXT,
+        version TEXT,
+        -- `SearchFilter` as JSON.
+        filter TEXT NOT NULL,
+        result_count INTEGER NOT NULL,
+        top_result TEXT,
+        top_score REAL,
+        latency_ms REAL NOT NULL
+    );
+";
+
+// Column weights for bm25(): a hit in the name counts most, then the path, then the docs.
+const NAME_WEIGHT: f64 = 10.0;
+const PATH_WEIGHT: f64 = 5.0;
+const DESCRIPTION_WEIGHT: f64 = 1.0;
+
+/// An exact keyword match from `DocStorage::keyword_search`.
+#[derive(Debug, Clone)]
+pub struct KeywordHit {
+    pub project_path: String,
+    pub item: DocItem,
+    /// FTS5 BM25 relevance, higher is better. Only comparable within one search.
+    pub score: f32,
+}
+
+/// Entries `get_query_history` returns when no limit is given.
+pub const DEFAULT_QUERY_HISTORY_LIMIT: usize = 50;
+
+/// A past query, as recorded by `DocStorage::record_query`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryHistoryEntry {
+    pub timestamp_ms: u64,
+    pub query: String,
+    pub project_path: Option<String>,
+    pub version: Option<String>,
+    pub filter: SearchFilter,
+    pub result_count: usize,
+    /// Full path of the best hit, None if nothing was found.
+    pub top_result: Option<String>,
+    pub top_score: Option<f32>,
+    pub latency_ms: f64,
+}
+
+/// SQLite database holding the parsed docs of every processed project, with an FTS5 index over
+/// item names, paths and descriptions. It is the persisted copy of `ProjectData::crate_docs` and
+/// `dependency_docs`; the in-memory maps are loaded from it at startup.
+#[derive(Debug, Clone)]
+pub struct DocStorage {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl DocStorage {
+    /// Opens (or creates) the database at `path`. Blocking.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let conn = Connection::open(path).with_context(|| format!("Failed to open doc database {}", path.display()))?;
+        Self::init(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version != SCHEMA_VERSION {
+            if version != 0 {
+                log::warn!("Doc database has schema version {}, expected {}; recreating it", version, SCHEMA_VERSION);
+            }
+            conn.execute_batch("
+                DROP TRIGGER IF EXISTS items_fts_insert;
+                DROP TRIGGER IF EXISTS items_fts_delete;
+                DROP TABLE IF EXISTS items_fts;
+                DROP TABLE IF EXISTS items;
+            ")?;
+            conn.execute_batch(SCHEMA).context("Failed to create the doc database schema")?;
+            conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+        conn.execute_batch(QUERY_HISTORY_SCHEMA).context("Failed to create the query history table")?;
+        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    fn conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
+        self.conn.lock().map_err(|e| anyhow::anyhow!("Doc database lock poisoned: {}", e))
+    }
+
+    /// Replaces every item stored for `project_path` in one transaction. Blocking.
+    pub fn replace_project(&self, project_path: &str, crate_docs: &CrateDocs, dependency_docs: &[&CrateDocs]) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM items WHERE project_path = ?1", params![project_path])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO items (project_path, crate_name, is_dependency, full_path, search_paths, name, item_type, description, deprecated, public, item)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            let crates = std::iter::once((crate_docs, false)).chain(dependency_docs.iter().map(|docs| (*docs, true)));
+            for (docs, is_dependency) in crates {
+                for (full_path, item) in &docs.items {
+                    let search_paths = std::iter::once(full_path).chain(&item.reexports).cloned().collect::<Vec<_>>().join(" ");
+                    insert.execute(params![
+                        project_path,
+                        docs.crate_name,
+                        is_dependency,
+                        full_path,
+                        search_paths,
+                        item.name,
+                        item.item_type,
+                        item.description,
+                        item.is_deprecated(),
+                        item.is_public(),
+                        bincode::serialize(item)?,
+                    ])?;
+                }
+            }
+        }
+        tx.commit().with_context(|| format!("Failed to store the docs of {}", project_path))?;
+        Ok(())
+    }
+
+    /// The project's own docs and its dependency docs, or `None` if nothing is stored for it.
+    /// Blocking.
+    pub fn load_project(&self, project_path: &str) -> Result<Option<(CrateDocs, Vec<CrateDocs>)>> {
+        let conn = self.conn()?;
+        let root_crate: Option<String> = conn.query_row(
+            "SELECT crate_name FROM items WHERE project_path = ?1 AND is_dependency = 0 LIMIT 1",
+            params![project_path],
+            |row| row.get(0),
+        ).optional()?;
+        let Some(root_crate) = root_crate else { return Ok(None) };
+
+        let mut crate_docs = CrateDocs { crate_name: root_crate, items: HashMap::new() };
+        let mut dependencies: HashMap<String, CrateDocs> = HashMap::new();
+        let mut query = conn.prepare("SELECT crate_name, is_dependency, full_path, item FROM items WHERE project_path = ?1")?;
+        let mut rows = query.query(params![project_path])?;
+        while let Some(row) = rows.next()? {
+            let (crate_name, is_dependency, full_path): (String, bool, String) = (row.get(0)?, row.get(1)?, row.get(2)?);
+            let item: DocItem = bincode::deserialize(&row.get::<_, Vec<u8>>(3)?)
+                .with_context(|| format!("Corrupt stored item {} of {}", full_path, project_path))?;
+            let docs = if is_dependency {
+                dependencies.entry(crate_name.clone()).or_insert_with(|| CrateDocs { crate_name, items: HashMap::new() })
+            } else {
+                &mut crate_docs
+            };
+            docs.items.insert(full_path, item);
+        }
+        let mut dependency_docs: Vec<CrateDocs> = dependencies.into_values().collect();
+        dependency_docs.sort_by(|a, b| a.crate_name.cmp(&b.crate_name));
+        Ok(Some((crate_docs, dependency_docs)))
+    }
+
+    /// Blocking.
+    pub fn remove_project(&self, project_path: &str) -> Result<()> {
+        self.conn()?.execute("DELETE FROM items WHERE project_path = ?1", params![project_path])?;
+        Ok(())
+    }
+
+    /// Items containing every word of `query` (in their name, path or docs), best BM25 match
+    /// first. Words are matched exactly, not by meaning; `project_path` limits the search to one
+    /// project. Blocking.
+    pub fn keyword_search(&self, query: &str, project_path: Option<&str>, filter: &SearchFilter, limit: usize) -> Result<Vec<KeywordHit>> {
+        let Some(fts_query) = fts_query(query) else { return Ok(Vec::new()) };
+        let mut sql = format!(
+            "SELECT items.project_path, items.item, -bm25(items_fts, {}, {}, {}) AS score
+             FROM items_fts JOIN items ON items.id = items_fts.rowid
+             WHERE items_fts MATCH ?",
+            NAME_WEIGHT, PATH_WEIGHT, DESCRIPTION_WEIGHT,
+        );
+        let mut values: Vec<String> = vec![fts_query];
+        if let Some(project_path) = project_path {
+            sql.push_str(" AND items.project_path = ?");
+            values.push(project_path.to_string());
+        }
+        if let Some(types) = &filter.item_types {
+            sql.push_str(&format!(" AND lower(items.item_type) IN ({})", vec!["?"; types.len()].join(", ")));
+            values.extend(types.iter().map(|t| t.to_lowercase()));
+        }
+        if let Some(crate_name) = filter.crate_name() {
+            sql.push_str(" AND items.crate_name = ?");
+            values.push(crate_name);
+        }
+        if !filter.include_deprecated {
+            sql.push_str(" AND NOT items.deprecated");
+        }
+        if filter.public_only {
+            sql.push_str(" AND items.public");
+        }
+        if let Some(prefix) = filter.module_prefix() {
+            // substr rather than LIKE, whose wildcards `_` is full of.
+            sql.push_str(" AND (items.full_path = ? OR substr(items.full_path, 1, length(?)) = ?)");
+            let inside = format!("{}::", prefix);
+            values.extend([prefix.to_string(), inside.clone(), inside]);
+        }
+        // `#[cfg]` conditions can't be checked in SQL; with a target, matches are filtered below.
+        sql.push_str(" ORDER BY score DESC");
+        if filter.target.is_none() {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let conn = self.conn()?;
+        let mut statement = conn.prepare(&sql)?;
+        let hits = statement.query_map(params_from_iter(values.iter()), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?, row.get::<_, f64>(2)?))
+        })?;
+        let hits = hits.map(|hit| -> Result<KeywordHit> {
+            let (project_path, item, score) = hit?;
+            Ok(KeywordHit { project_path, item: bincode::deserialize(&item)?, score: score as f32 })
+        });
+        // Errors are kept so `collect` returns them.
+        hits.filter(|hit| hit.as_ref().ok().is_none_or(|hit| filter.allows_target(&hit.item)))
+            .take(limit)
+            .collect()
+    }
+
+    /// Appends `entry` to the query history, keeping the newest `max_entries`. Blocking.
+    pub fn record_query(&self, entry: &QueryHistoryEntry, max_entries: usize) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO query_history (timestamp_ms, query, project_path, version, filter, result_count, top_result, top_score, latency_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                entry.timestamp_ms as i64,
+                entry.query,
+                entry.project_path,
+                entry.version,
+                serde_json::to_string(&entry.filter)?,
+                entry.result_count as i64,
+                entry.top_result,
+                entry.top_score,
+                entry.latency_ms,
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM query_history WHERE id NOT IN (SELECT id FROM query_history ORDER BY id DESC LIMIT ?1)",
+            params![max_entries as i64],
+        )?;
+        Ok(())
+    }
+
+    /// The newest `limit` recorded queries, newest first; `project_path` keeps the queries scoped
+    /// to that project. Blocking.
+    pub fn query_history(&self, project_path: Option<&str>, limit: usize) -> Result<Vec<QueryHistoryEntry>> {
+        let conn = self.conn()?;
+        let mut statement = conn.prepare(
+            "SELECT timestamp_ms, query, project_path, version, filter, result_count, top_result, top_score, latency_ms
+             FROM query_history WHERE ?1 IS NULL OR project_path = ?1 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let rows = statement.query_map(params![project_path, limit as i64], |row| {
+            Ok((
+                QueryHistoryEntry {
+                    timestamp_ms: row.get::<_, i64>(0)? as u64,
+                    query: row.get(1)?,
+                    project_path: row.get(2)?,
+                    version: row.get(3)?,
+                    filter: SearchFilter::default(),
+                    result_count: row.get::<_, i64>(5)? as usize,
+                    top_result: row.get(6)?,
+                    top_score: row.get(7)?,
+                    latency_ms: row.get(8)?,
+                },
+                row.get::<_, String>(4)?,
+            ))
+        })?;
+        rows.map(|row| {
+            let (mut entry, filter) = row?;
+            entry.filter = serde_json::from_str(&filter).context("Corrupt filter in the query history")?;
+            Ok(entry)
+        }).collect()
+    }
+
+    /// Deletes the query history and returns the number of entries removed. Blocking.
+    pub fn clear_query_history(&self) -> Result<usize> {
+        Ok(self.conn()?.execute("DELETE FROM query_history", [])?)
+    }
+}
+
+// Quotes every word so FTS5 operators and punctuation in user input are taken literally, e.g.
+// `HashMap::new "x"` becomes `"HashMap" "new" "x"` (all must match).
+fn fts_query(query: &str) -> Option<String> {
+    let words: Vec<String> = query
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"", word))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rustdoc_processor::{Deprecation, Visibility};
+
+    fn docs(crate_name: &str, items: &[(&str, &str, &str)]) -> CrateDocs {
+        // A "deprecated" description marks the item deprecated, a "private" one private.
+        let items = items.iter().map(|(name, item_type, description)| {
+            let full_path = format!("{}::{}", crate_name, name);
+            (full_path.clone(), DocItem {
+                id: "0:1".to_string(),
+                crate_name: crate_name.to_string(),
+                name: name.to_string(),
+                path: vec![crate_name.to_string(), name.to_string()],
+                description: Some(description.to_string()),
+                item_type: item_type.to_string(),
+                full_path_str: full_path,
+                deprecation: (*description == "deprecated").then(Deprecation::default),
+                visibility: if *description == "private" { Visibility::Private } else { Visibility::Public },
+                ..Default::default()
+            })
+        }).collect();
+        CrateDocs { crate_name: crate_name.to_string(), items }
+    }
+
+    #[test]
+    fn test_store_load_and_keyword_search() -> Result<()> {
+        let storage = DocStorage::open_in_memory()?;
+        let app = docs("app", &[("from_str", "function", "Parses a config."), ("Config", "struct", "The app config, parsed from TOML.")]);
+        let dep = docs("toml", &[("from_str", "function", "Deserializes TOML text."), ("from_slice", "function", "deprecated"), ("from_reader", "function", "private")]);
+        storage.replace_project("/work/app", &app, &[&dep])?;
+
+        let (crate_docs, dependency_docs) = storage.load_project("/work/app")?.unwrap();
+        assert_eq!(crate_docs.crate_name, "app");
+        assert_eq!(crate_docs.items.len(), 2);
+        assert_eq!(dependency_docs.len(), 1);
+        assert!(dependency_docs[0].items.contains_key("toml::from_str"));
+        assert!(storage.load_project("/work/other")?.is_none());
+
+        // Names weigh more than descriptions; punctuation in the query is harmless.
+        let hits = storage.keyword_search("config\"*", None, &SearchFilter::default(), 10)?;
+        assert_eq!(hits[0].item.full_path_str, "app::Config");
+        // Underscores are part of words.
+        let hits = storage.keyword_search("from_str", Some("/work/app"), &SearchFilter::default(), 10)?;
+        assert_eq!(hits.len(), 2);
+        assert!(storage.keyword_search("str", None, &SearchFilter::default(), 10)?.is_empty());
+        let structs = SearchFilter { item_types: Some(vec!["Struct".to_string()]), ..Default::default() };
+        assert!(storage.keyword_search("from_str", None, &structs, 10)?.is_empty());
+        // Deprecated items only when asked for.
+        assert!(storage.keyword_search("from_slice", None, &SearchFilter::default(), 10)?.is_empty());
+        let with_deprecated = SearchFilter { include_deprecated: true, ..Default::default() };
+        assert_eq!(storage.keyword_search("from_slice", None, &with_deprecated, 10)?.len(), 1);
+        // Private items likewise.
+        assert!(storage.keyword_search("from_reader", None, &SearchFilter::default(), 10)?.is_empty());
+        let with_private = SearchFilter { public_only: false, ..Default::default() };
+        assert_eq!(storage.keyword_search("from_reader", None, &with_private, 10)?.len(), 1);
+
+        // Replacing drops items that are gone, including from the FTS index.
+        storage.replace_project("/work/app", &docs("app", &[("Config", "struct", "The app config.")]), &[])?;
+        assert!(storage.keyword_search("from_str", None, &SearchFilter::default(), 10)?.is_empty());
+        storage.remove_project("/work/app")?;
+        assert!(storage.load_project("/work/app")?.is_none());
+        assert!(storage.keyword_search("config", None, &SearchFilter::default(), 10)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_history() -> Result<()> {
+        let storage = DocStorage::open_in_memory()?;
+        let entry = |timestamp_ms: u64, project_path: Option<&str>| QueryHistoryEntry {
+            timestamp_ms,
+            query: format!("query {}", timestamp_ms),
+            project_path: project_path.map(str::to_string),
+            version: None,
+            filter: SearchFilter { item_types: Some(vec!["function".to_string()]), ..Default::default() },
+            result_count: 1,
+            top_result: Some("app::Config::new".to_string()),
+            top_score: Some(0.5),
+            latency_ms: 12.5,
+        };
+        for (timestamp_ms, project_path) in [(1, Some("/work/app")), (2, None), (3, Some("/work/app")), (4, Some("/work/other"))] {
+            storage.record_query(&entry(timestamp_ms, project_path), 3)?;
+        }
+
+        // Only the newest 3 are kept, newest first.
+        let history = storage.query_history(None, 10)?;
+        assert_eq!(history.iter().map(|e| e.timestamp_ms).collect::<Vec<_>>(), vec![4, 3, 2]);
+        assert_eq!(history[1], entry(3, Some("/work/app")));
+        assert_eq!(storage.query_history(Some("/work/app"), 10)?.len(), 1);
+        assert_eq!(storage.query_history(None, 1)?.len(), 1);
+
+        assert_eq!(storage.clear_query_history()?, 3);
+        assert!(storage.query_history(None, 10)?.is_empty());
+        Ok(())
+    }
+}
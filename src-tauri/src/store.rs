@@ -0,0 +1,366 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::ann::{HnswIndex, HnswParams};
+use crate::mcp_server::ProjectData;
+use crate::rustdoc_processor::DocTargets;
+use crate::storage::DocStorage;
+
+// Bump when the persisted layout or the embedded text (`embedding_text`) changes; files with
+// another version are ignored (and re-created the next time the project is processed).
+const STORE_FORMAT_VERSION: u32 = 19;
+const PROJECT_FILE_EXTENSION: &str = "bin";
+const DOC_DATABASE_FILE_NAME: &str = "docs.sqlite";
+const REGISTRY_FILE_NAME: &str = "registry.json";
+
+#[derive(Serialize)]
+struct PersistedProjectRef<'a> {
+    project_path: &'a str,
+    embeddings: &'a HashMap<String, Vec<f32>>,
+    index: &'a HnswIndex,
+    source_fingerprint: Option<u64>,
+    embedding_model: Option<&'a str>,
+    version: Option<&'a str>,
+    doc_targets: DocTargets,
+    truncated_embeddings: &'a HashSet<String>,
+    embedded_text_hashes: &'a HashMap<String, u64>,
+}
+
+#[derive(Deserialize)]
+struct PersistedProject {
+    project_path: String,
+    embeddings: HashMap<String, Vec<f32>>,
+    index: HnswIndex,
+    source_fingerprint: Option<u64>,
+    embedding_model: Option<String>,
+    version: Option<String>,
+    doc_targets: DocTargets,
+    truncated_embeddings: HashSet<String>,
+    embedded_text_hashes: HashMap<String, u64>,
+}
+
+/// The rustdoc JSON a processed project was parsed from, kept in `registry.json`. It is plain JSON
+/// and independent of `STORE_FORMAT_VERSION`, so a project whose file can't be loaded is restored
+/// from it (see `pipeline::restore_registry`) instead of disappearing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    pub project_path: String,
+    pub json_paths: Vec<PathBuf>,
+    pub version: Option<String>,
+}
+
+/// On-disk copy of the processed projects in `<cache_dir>/projects/`, so a restart doesn't force
+/// re-processing and re-embedding: the parsed docs live in the SQLite `DocStorage` (`docs.sqlite`),
+/// embeddings and ANN graph in one bincode file per project.
+#[derive(Debug, Clone)]
+pub struct ProjectStore {
+    dir: PathBuf,
+    docs: DocStorage,
+    // Serializes read-modify-write cycles of the registry between concurrent jobs.
+    registry_lock: Arc<Mutex<()>>,
+}
+
+impl ProjectStore {
+    /// Opens the store in `dir`, creating it and its doc database if needed. Blocking.
+    pub fn open(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create project store directory: {}", dir.display()))?;
+        let docs = DocStorage::open(&dir.join(DOC_DATABASE_FILE_NAME))?;
+        Ok(Self { dir, docs, registry_lock: Arc::default() })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The doc database, for keyword searches over every stored project.
+    pub fn docs(&self) -> &DocStorage {
+        &self.docs
+    }
+
+    /// Writes the project, replacing any previous copy. Blocking.
+    pub fn save(&self, project_path: &str, data: &ProjectData) -> Result<()> {
+        let dependency_docs: Vec<_> = data.dependency_docs.iter().map(|docs| docs.as_ref()).collect();
+        self.docs.replace_project(project_path, &data.crate_docs, &dependency_docs)?;
+        let path = self.file_path(project_path);
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(fs::File::create(&tmp_path)
+                .with_context(|| format!("Failed to create {}", tmp_path.display()))?);
+            bincode::serialize_into(&mut writer, &STORE_FORMAT_VERSION)?;
+            bincode::serialize_into(&mut writer, &PersistedProjectRef {
+                project_path,
+                embeddings: &data.embeddings,
+                index: &data.index,
+                source_fingerprint: data.source_fingerprint,
+                embedding_model: data.embedding_model.as_deref(),
+                version: data.version.as_deref(),
+                doc_targets: data.doc_targets,
+                truncated_embeddings: &data.truncated_embeddings,
+                embedded_text_hashes: &data.embedded_text_hashes,
+            })?;
+            writer.flush()?;
+        }
+        // Rename so a crash mid-write never leaves a truncated file behind.
+        fs::rename(&tmp_path, &path).with_context(|| format!("Failed to replace {}", path.display()))?;
+        log::info!("Saved project {} to {}", project_path, path.display());
+        Ok(())
+    }
+
+    /// Loads every stored project. Unreadable or outdated files are skipped with a warning. Blocking.
+    pub fn load_all(&self) -> Vec<(String, ProjectData)> {
+        let Ok(entries) = fs::read_dir(&self.dir) else { return Vec::new() };
+        let mut projects = Vec::new();
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some(PROJECT_FILE_EXTENSION) {
+                continue;
+            }
+            match read_project(&path, &self.docs) {
+                Ok(Some(project)) => {
+                    // Files named by an older scheme are moved to the current name, or dropped
+                    // when the project has also been saved under it since.
+                    let expected = self.file_path(&project.0);
+                    if path != expected {
+                        if expected.exists() {
+                            log::info!("Removing stale project file {}", path.display());
+                            if let Err(e) = fs::remove_file(&path) {
+                                log::warn!("Failed to remove {}: {}", path.display(), e);
+                            }
+                            continue;
+                        }
+                        if let Err(e) = fs::rename(&path, &expected) {
+                            log::warn!("Failed to rename {} to {}: {}", path.display(), expected.display(), e);
+                        }
+                    }
+                    projects.push(project);
+                }
+                Ok(None) => log::warn!("Ignoring {}: stored with an older format version", path.display()),
+                Err(e) => log::warn!("Ignoring unreadable project file {}: {:?}", path.display(), e),
+            }
+        }
+        log::info!("Loaded {} persisted project(s) from {}", projects.len(), self.dir.display());
+        projects
+    }
+
+    pub fn remove(&self, project_path: &str) -> Result<()> {
+        self.docs.remove_project(project_path)?;
+        self.update_registry(|entries| entries.retain(|entry| entry.project_path != project_path))?;
+        let path = self.file_path(project_path);
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Size in bytes and modification time of the project's embeddings file, None if it isn't stored.
+    pub fn file_info(&self, project_path: &str) -> Option<(u64, SystemTime)> {
+        let metadata = fs::metadata(self.file_path(project_path)).ok()?;
+        Some((metadata.len(), metadata.modified().ok()?))
+    }
+
+    /// Records the rustdoc JSON `project_path` was parsed from, replacing an earlier entry. Blocking.
+    pub fn record_json_paths(&self, project_path: &str, json_paths: &[PathBuf], version: Option<&str>) -> Result<()> {
+        self.update_registry(|entries| {
+            entries.retain(|entry| entry.project_path != project_path);
+            entries.push(RegistryEntry {
+                project_path: project_path.to_string(),
+                json_paths: json_paths.to_vec(),
+                version: version.map(str::to_string),
+            });
+        })
+    }
+
+    /// The recorded projects, sorted by path. A missing or unreadable registry is empty. Blocking.
+    pub fn registry(&self) -> Vec<RegistryEntry> {
+        let path = self.dir.join(REGISTRY_FILE_NAME);
+        let Ok(content) = fs::read_to_string(&path) else { return Vec::new() };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("Ignoring unreadable project registry {}: {}", path.display(), e);
+            Vec::new()
+        })
+    }
+
+    fn update_registry(&self, update: impl FnOnce(&mut Vec<RegistryEntry>)) -> Result<()> {
+        let _guard = self.registry_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut entries = self.registry();
+        update(&mut entries);
+        entries.sort_by(|a, b| a.project_path.cmp(&b.project_path));
+        let path = self.dir.join(REGISTRY_FILE_NAME);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(&entries)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path).with_context(|| format!("Failed to replace {}", path.display()))
+    }
+
+    // Named by SHA-256 of the path, so the file stays the same across Rust releases.
+    fn file_path(&self, project_path: &str) -> PathBuf {
+        let digest = Sha256::digest(project_path.as_bytes());
+        let hash: String = digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect();
+        self.dir.join(format!("{}.{}", hash, PROJECT_FILE_EXTENSION))
+    }
+}
+
+fn read_project(path: &Path, docs: &DocStorage) -> Result<Option<(String, ProjectData)>> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let version: u32 = bincode::deserialize_from(&mut reader)?;
+    if version != STORE_FORMAT_VERSION {
+        return Ok(None);
+    }
+    let project: PersistedProject = bincode::deserialize_from(&mut reader)?;
+    let (crate_docs, dependency_docs) = docs.load_project(&project.project_path)?
+        .with_context(|| format!("The doc database has no items for {}", project.project_path))?;
+    let mut index = project.index;
+    // The graph is stored without vectors; rebuild it if it doesn't match the embeddings.
+    if !index.attach_vectors(&project.embeddings) {
+        log::warn!("Rebuilding ANN index for {}: stored graph doesn't match its embeddings", project.project_path);
+        index = HnswIndex::build(HnswParams::default(), &project.embeddings);
+    }
+    let data = ProjectData::from_parts(crate_docs, dependency_docs, project.embeddings, index)
+        .with_source_fingerprint(project.source_fingerprint)
+        .with_embedding_model(project.embedding_model)
+        .with_version(project.version)
+        .with_doc_targets(project.doc_targets)
+        .with_truncated_embeddings(project.truncated_embeddings)
+        .with_embedded_text_hashes(project.embedded_text_hashes);
+    Ok(Some((project.project_path, data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp_server::SearchFilter;
+    use crate::rustdoc_processor::{CrateDocs, DocItem};
+
+    fn sample_docs(crate_name: &str) -> CrateDocs {
+        let full_path = format!("{}::run", crate_name);
+        let item = DocItem {
+            id: "0:1".to_string(),
+            crate_name: crate_name.to_string(),
+            name: "run".to_string(),
+            path: vec![crate_name.to_string(), "run".to_string()],
+            description: Some("Runs it.".to_string()),
+            item_type: "function".to_string(),
+            full_path_str: full_path.clone(),
+            ..Default::default()
+        };
+        CrateDocs { crate_name: crate_name.to_string(), items: HashMap::from([(full_path, item)]) }
+    }
+
+    #[test]
+    fn test_save_load_remove_roundtrip() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = ProjectStore::open(dir.path().join("projects"))?;
+        let data = ProjectData::new(
+            sample_docs("app"),
+            vec![sample_docs("dep")],
+            HashMap::from([
+                ("app::run".to_string(), vec![0.5, -1.0]),
+                (crate::chunking::chunk_key("app::run", 1), vec![1.0, 0.0]),
+            ]),
+        ).with_source_fingerprint(Some(42)).with_embedding_model(Some("BAAI/bge-small@main".to_string()))
+        .with_version(Some("0.6.3".to_string()))
+        .with_doc_targets(DocTargets::All)
+        .with_truncated_embeddings(HashSet::from([crate::chunking::chunk_key("app::run", 1)]))
+        .with_embedded_text_hashes(HashMap::from([("app::run".to_string(), 7)]));
+        store.save("/work/app", &data)?;
+        // Stray files in the directory are ignored.
+        fs::write(store.dir().join("notes.txt"), "hello")?;
+
+        let loaded = store.load_all();
+        assert_eq!(loaded.len(), 1);
+        let (path, loaded_data) = &loaded[0];
+        assert_eq!(path, "/work/app");
+        assert_eq!(loaded_data.item_count(), 2);
+        assert!(loaded_data.get_item("dep::run").is_some());
+        assert_eq!(loaded_data.embeddings.get("app::run"), Some(&vec![0.5, -1.0]));
+        let hits = loaded_data.search(&[0.5, -1.0], 1, &SearchFilter::default());
+        assert_eq!(hits[0].0.full_path_str, "app::run");
+        // A later chunk matching better still yields the item once, with the chunk's score.
+        let hits = loaded_data.search(&[1.0, 0.0], 2, &SearchFilter::default());
+        assert_eq!(hits.len(), 1);
+        assert!((hits[0].1 - 1.0).abs() < 1e-6);
+        assert_eq!(loaded_data.embedded_item_count(), 1);
+        assert_eq!(loaded_data.source_fingerprint, Some(42));
+        assert_eq!(loaded_data.embedding_model.as_deref(), Some("BAAI/bge-small@main"));
+        assert_eq!(loaded_data.version.as_deref(), Some("0.6.3"));
+        assert_eq!(loaded_data.doc_targets, DocTargets::All);
+        assert!(loaded_data.truncated_embeddings.contains(&crate::chunking::chunk_key("app::run", 1)));
+        assert_eq!(loaded_data.embedded_text_hashes.get("app::run"), Some(&7));
+        // The keyword index is rebuilt on load; dep::run has no embedding but still matches by name.
+        let hybrid: Vec<&str> = loaded_data.hybrid_search(&[0.5, -1.0], "run", 2, 0.5, &SearchFilter::default())
+            .iter()
+            .map(|(item, _)| item.full_path_str.as_str())
+            .collect();
+        assert_eq!(hybrid, vec!["app::run", "dep::run"]);
+        // Kind filters apply before truncation and ignore case.
+        let structs_only = SearchFilter { item_types: Some(vec!["struct".to_string()]), ..Default::default() };
+        assert!(loaded_data.hybrid_search(&[0.5, -1.0], "run", 2, 0.5, &structs_only).is_empty());
+        let functions = SearchFilter { item_types: Some(vec!["Function".to_string()]), ..Default::default() };
+        assert_eq!(loaded_data.search(&[0.5, -1.0], 1, &functions).len(), 1);
+        // Crate and module scopes narrow to the dependency's items.
+        let dep_only = SearchFilter { crate_name: Some("dep".to_string()), ..Default::default() };
+        let scoped: Vec<&str> = loaded_data.hybrid_search(&[0.5, -1.0], "run", 2, 0.5, &dep_only).iter().map(|(item, _)| item.full_path_str.as_str()).collect();
+        assert_eq!(scoped, vec!["dep::run"]);
+        let under_app = SearchFilter { module_prefix: Some("app::".to_string()), ..Default::default() };
+        assert_eq!(store.docs().keyword_search("run", None, &under_app, 10)?.len(), 1);
+        let lookalike = SearchFilter { module_prefix: Some("ap".to_string()), ..Default::default() };
+        assert!(store.docs().keyword_search("run", None, &lookalike, 10)?.is_empty());
+
+        // Docs come from the database, which also serves exact keyword searches.
+        assert_eq!(store.docs().keyword_search("run", None, &SearchFilter::default(), 10)?.len(), 2);
+
+        store.remove("/work/app")?;
+        assert!(store.load_all().is_empty());
+        assert!(store.docs().keyword_search("run", None, &SearchFilter::default(), 10)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_file_names_are_migrated() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = ProjectStore::open(dir.path().join("projects"))?;
+        let data = ProjectData::new(sample_docs("app"), Vec::new(), HashMap::from([("app::run".to_string(), vec![1.0, 0.0])]));
+        store.save("/work/app", &data)?;
+        let legacy = store.dir().join(format!("0123456789abcdef.{}", PROJECT_FILE_EXTENSION));
+        fs::rename(store.file_path("/work/app"), &legacy)?;
+
+        assert_eq!(store.load_all().len(), 1);
+        assert!(!legacy.exists());
+        assert!(store.file_path("/work/app").exists());
+
+        // A stale copy next to the current file is dropped instead of loaded twice.
+        fs::copy(store.file_path("/work/app"), &legacy)?;
+        assert_eq!(store.load_all().len(), 1);
+        assert!(!legacy.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_registry() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = ProjectStore::open(dir.path().join("projects"))?;
+        assert!(store.registry().is_empty());
+
+        let json_paths = vec![dir.path().join("doc/app.json")];
+        store.record_json_paths("/work/b", &json_paths, None)?;
+        store.record_json_paths("/work/a", &json_paths, Some("0.1.0"))?;
+        store.record_json_paths("/work/a", &json_paths, Some("0.2.0"))?;
+        let entries = store.registry();
+        assert_eq!(entries.iter().map(|e| e.project_path.as_str()).collect::<Vec<_>>(), ["/work/a", "/work/b"]);
+        assert_eq!(entries[0], RegistryEntry { project_path: "/work/a".to_string(), json_paths, version: Some("0.2.0".to_string()) });
+
+        // Outlives the project files: a store format change doesn't touch it.
+        store.remove("/work/b")?;
+        assert_eq!(store.registry().len(), 1);
+        fs::write(store.dir().join(REGISTRY_FILE_NAME), "not json")?;
+        assert!(store.registry().is_empty());
+        Ok(())
+    }
+}
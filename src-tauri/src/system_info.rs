@@ -0,0 +1,171 @@
+use serde::Serialize;
+use std::path::Path;
+
+use crate::config::{DevicePreference, EmbedderBackend};
+use crate::embedder::{self, EmbedderStatus};
+use crate::mcp_server::AppState;
+use crate::rustdoc_processor;
+
+/// What the app runs on, to debug why processing is slow or failing (`get_system_info`).
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemInfo {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub cpu_threads: usize,
+    pub embedder: EmbedderDiagnostics,
+    /// Resident memory of this process, where the OS reports it (Linux).
+    pub process_memory_bytes: Option<u64>,
+    /// The app's cache directory, each entry of it, and the Hugging Face model cache.
+    pub cache_dirs: Vec<DirectoryUsage>,
+    pub configured_toolchain: String,
+    /// Output of `rustup toolchain list`, one toolchain per entry.
+    pub rustup_toolchains: Vec<String>,
+    /// Why `rustup_toolchains` is empty, e.g. rustup is not installed.
+    pub rustup_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbedderDiagnostics {
+    pub status: EmbedderStatus,
+    pub model_repo: String,
+    /// `Embedder::model_id` of the loaded model.
+    pub model_id: Option<String>,
+    pub backend: EmbedderBackend,
+    pub device_preference: DevicePreference,
+    /// Device in use ("cpu", "cuda", "metal" or "remote"); `None` while no model is loaded.
+    pub device: Option<String>,
+    pub cuda_available: bool,
+    pub metal_available: bool,
+    pub dimension: Option<usize>,
+    /// Texts per forward pass; below `max_batch_size` after the GPU ran out of memory.
+    pub batch_size: Option<usize>,
+    /// Estimated memory the weights take, from the size of their files: VRAM on a GPU, RAM on the CPU.
+    pub estimated_vram_bytes: Option<u64>,
+    pub estimated_ram_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryUsage {
+    pub path: String,
+    pub bytes: u64,
+    pub files: u64,
+}
+
+/// Gathers the report. Runs rustup and walks the cache directories, so it may take a moment.
+pub async fn collect(app_state: &AppState) -> SystemInfo {
+    let config = app_state.config.current();
+    let loaded = embedder::with_global_embedder(|embedder| embedder.map(|e| {
+        let weights = e.weights_size_bytes();
+        let on_gpu = matches!(e.device_name(), "cuda" | "metal");
+        LoadedModel {
+            model_id: e.model_id(),
+            device: e.device_name().to_string(),
+            dimension: e.dimension().map_err(|err| log::warn!("Failed to get the embedding dimension: {:#}", err)).ok(),
+            batch_size: e.batch_size(),
+            vram: if on_gpu { weights } else { 0 },
+            ram: if on_gpu { 0 } else { weights },
+        }
+    })).await.ok().flatten();
+
+    let cache_dir = app_state.cache_dir.clone();
+    let toolchain = config.rustdoc.toolchain.clone();
+    let (cache_dirs, toolchains) = tokio::task::spawn_blocking(move || {
+        let mut dirs = vec![directory_usage(&cache_dir)];
+        if let Ok(entries) = std::fs::read_dir(&cache_dir) {
+            let mut children: Vec<_> = entries.flatten().map(|entry| directory_usage(&entry.path())).collect();
+            children.sort_by(|a, b| a.path.cmp(&b.path));
+            dirs.extend(children);
+        }
+        dirs.push(directory_usage(hf_hub::Cache::default().path()));
+        (dirs, rustdoc_processor::installed_toolchains())
+    }).await.unwrap_or_else(|e| (Vec::new(), Err(anyhow::anyhow!("Collecting disk usage panicked: {}", e))));
+
+    let (rustup_toolchains, rustup_error) = match toolchains {
+        Ok(toolchains) => (toolchains, None),
+        Err(e) => (Vec::new(), Some(format!("{:#}", e))),
+    };
+    SystemInfo {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        cpu_threads: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        embedder: EmbedderDiagnostics {
+            status: embedder::embedder_status(),
+            model_repo: config.embedder.model_repo.clone(),
+            model_id: loaded.as_ref().map(|m| m.model_id.clone()),
+            backend: config.embedder.backend,
+            device_preference: config.embedder.device,
+            device: loaded.as_ref().map(|m| m.device.clone()),
+            cuda_available: candle_core::utils::cuda_is_available(),
+            metal_available: candle_core::utils::metal_is_available(),
+            dimension: loaded.as_ref().and_then(|m| m.dimension),
+            batch_size: loaded.as_ref().map(|m| m.batch_size),
+            estimated_vram_bytes: loaded.as_ref().map(|m| m.vram),
+            estimated_ram_bytes: loaded.as_ref().map(|m| m.ram),
+        },
+        process_memory_bytes: process_memory_bytes(),
+        cache_dirs,
+        configured_toolchain: toolchain,
+        rustup_toolchains,
+        rustup_error,
+    }
+}
+
+struct LoadedModel {
+    model_id: String,
+    device: String,
+    dimension: Option<usize>,
+    batch_size: usize,
+    vram: u64,
+    ram: u64,
+}
+
+/// Total size and count of the files under `path` (or of `path` itself if it is a file).
+pub fn directory_usage(path: &Path) -> DirectoryUsage {
+    let (mut bytes, mut files) = (0, 0);
+    for entry in walkdir::WalkDir::new(path).into_iter().flatten() {
+        if let Ok(metadata) = entry.metadata() {
+            if metadata.is_file() {
+                bytes += metadata.len();
+                files += 1;
+            }
+        }
+    }
+    DirectoryUsage { path: path.display().to_string(), bytes, files }
+}
+
+// VmRSS from /proc/self/status; `None` on other systems.
+fn process_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    parse_vm_rss(&status)
+}
+
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vm_rss() {
+        assert_eq!(parse_vm_rss("Name:\tapp\nVmPeak:\t  20000 kB\nVmRSS:\t   1234 kB\n"), Some(1234 * 1024));
+        assert_eq!(parse_vm_rss("Name:\tapp\n"), None);
+    }
+
+    #[test]
+    fn test_directory_usage() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir(dir.path().join("nested"))?;
+        std::fs::write(dir.path().join("a.json"), "12345")?;
+        std::fs::write(dir.path().join("nested").join("b.json"), "123")?;
+        let usage = directory_usage(dir.path());
+        assert_eq!((usage.bytes, usage.files), (8, 2));
+        assert_eq!(directory_usage(&dir.path().join("missing")).files, 0);
+        Ok(())
+    }
+}
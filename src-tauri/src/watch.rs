@@ -0,0 +1,207 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::error::AppError;
+use crate::events::StateEvent;
+use crate::jobs::JobPriority;
+use crate::mcp_server::AppState;
+use crate::pipeline::{self, ProcessOptions};
+use crate::rustdoc_processor;
+
+/// Job kind of the re-processing runs started by a watcher.
+pub const WATCH_JOB_KIND: &str = "watch_refresh";
+
+// A burst of saves (formatting, a branch switch) triggers one run once it has been quiet this long.
+const DEBOUNCE: Duration = Duration::from_millis(1500);
+
+/// The file watchers of the projects with watch mode on, keyed by project path. Dropping a
+/// watcher closes its event channel, which ends its debounce task.
+#[derive(Default)]
+pub struct WatchRegistry {
+    watchers: Mutex<HashMap<String, RecommendedWatcher>>,
+}
+
+impl WatchRegistry {
+    pub fn is_watching(&self, project_path: &str) -> bool {
+        self.watchers.lock().expect("watch registry lock poisoned").contains_key(project_path)
+    }
+
+    /// Paths of the watched projects, sorted.
+    pub fn watched(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self.watchers.lock().expect("watch registry lock poisoned").keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    /// Stops watching `project_path`; returns whether it was watched.
+    pub fn remove(&self, project_path: &str) -> bool {
+        self.watchers.lock().expect("watch registry lock poisoned").remove(project_path).is_some()
+    }
+}
+
+/// Whether a change to `path` can change the docs of a project whose workspace members live in
+/// `member_dirs`: Rust sources under a member's `src/` and its `Cargo.toml`. Build output and
+/// editor files are ignored.
+pub fn is_relevant(member_dirs: &[PathBuf], path: &Path) -> bool {
+    member_dirs.iter().any(|member_dir| {
+        let Ok(relative) = path.strip_prefix(member_dir) else {
+            return false;
+        };
+        if relative == Path::new("Cargo.toml") {
+            return true;
+        }
+        relative.starts_with("src") && relative.extension().is_some_and(|ext| ext == "rs")
+    })
+}
+
+// The workspace member directories of the project in `project_dir`, as paths below `project_dir`
+// where they are inside it, since that is how the watcher reports them. Falls back to the project
+// alone if `cargo metadata` fails.
+async fn member_dirs(app_state: &AppState, project_path: &str) -> Vec<PathBuf> {
+    let project_dir = Path::new(project_path);
+    let settings = app_state.config.current().rustdoc_for_project(project_path, &app_state.cache_dir);
+    let dir = project_dir.to_path_buf();
+    let members = tokio::task::spawn_blocking(move || rustdoc_processor::workspace_member_dirs(&dir, &settings)).await;
+    let members = match members {
+        Ok(Ok(members)) => members,
+        Ok(Err(e)) => {
+            log::warn!("Watching only {}: could not list its workspace members: {:#}", project_dir.display(), e);
+            Vec::new()
+        }
+        Err(e) => {
+            log::warn!("Watching only {}: listing its workspace members panicked: {}", project_dir.display(), e);
+            Vec::new()
+        }
+    };
+    let canonical_dir = project_dir.canonicalize().unwrap_or_else(|_| project_dir.to_path_buf());
+    let mut dirs = vec![project_dir.to_path_buf()];
+    for member in members {
+        let member = match member.strip_prefix(&canonical_dir) {
+            Ok(relative) => project_dir.join(relative),
+            Err(_) => member,
+        };
+        if !dirs.contains(&member) {
+            dirs.push(member);
+        }
+    }
+    dirs
+}
+
+/// Starts watching a processed project: changes to its sources re-process it (incrementally,
+/// reusing unchanged embeddings) as a low-priority job, and windows get the usual
+/// `index_refreshed` state event when the new index is in place. Every member of the project's
+/// workspace is watched, and runs document the targets the project was last processed with. A
+/// no-op if already watched.
+pub async fn enable_watch(app_state: Arc<AppState>, project_path: &str) -> Result<(), AppError> {
+    let (include_dependencies, targets) = {
+        let projects_guard = app_state.projects().await;
+        let project_data = projects_guard.get(project_path)
+            .ok_or_else(|| AppError::ProjectNotFound { project_path: project_path.to_string() })?;
+        (!project_data.dependency_docs.is_empty(), project_data.doc_targets)
+    };
+    let project_dir = PathBuf::from(project_path);
+    if !project_dir.is_dir() {
+        return Err(AppError::PathNotFound { path: project_path.to_string() });
+    }
+    if app_state.watches.is_watching(project_path) {
+        return Ok(());
+    }
+
+    let member_dirs = member_dirs(&app_state, project_path).await;
+    // Members inside another watched directory are covered by its recursive watch.
+    let watch_roots: Vec<PathBuf> = member_dirs.iter()
+        .filter(|dir| !member_dirs.iter().any(|other| other != *dir && dir.starts_with(other)))
+        .cloned()
+        .collect();
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let filter_dir = project_dir.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+            if event.paths.iter().any(|path| is_relevant(&member_dirs, path)) {
+                let _ = sender.send(());
+            }
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("File watcher error for {}: {:?}", filter_dir.display(), e),
+    })
+    .map_err(|e| AppError::internal(format!("Failed to create a file watcher for {}: {}", project_path, e)))?;
+    for root in &watch_roots {
+        watcher.watch(root, RecursiveMode::Recursive)
+            .map_err(|e| AppError::internal(format!("Failed to watch {}: {}", root.display(), e)))?;
+    }
+
+    {
+        let mut watchers = app_state.watches.watchers.lock().expect("watch registry lock poisoned");
+        // Another call may have started watching while the members were listed.
+        if watchers.contains_key(project_path) {
+            return Ok(());
+        }
+        watchers.insert(project_path.to_string(), watcher);
+    }
+    let opts = ProcessOptions { path: project_path.to_string(), include_dependencies, targets };
+    tokio::spawn(refresh_on_changes(app_state.clone(), opts, receiver));
+    log::info!("Watching {} for source changes", project_path);
+    app_state.emit_state_event(StateEvent::WatchChanged { project_path: project_path.to_string(), watching: true });
+    Ok(())
+}
+
+/// Stops watching a project. Errors if it wasn't watched.
+pub fn disable_watch(app_state: &AppState, project_path: &str) -> Result<(), AppError> {
+    if !app_state.watches.remove(project_path) {
+        return Err(AppError::InvalidArgument { message: format!("Project '{}' is not being watched", project_path) });
+    }
+    log::info!("Stopped watching {}", project_path);
+    app_state.emit_state_event(StateEvent::WatchChanged { project_path: project_path.to_string(), watching: false });
+    Ok(())
+}
+
+// Waits for a change, lets the burst settle, then re-processes. Runs until the watcher is dropped.
+async fn refresh_on_changes(app_state: Arc<AppState>, opts: ProcessOptions, mut changes: mpsc::UnboundedReceiver<()>) {
+    while changes.recv().await.is_some() {
+        loop {
+            match tokio::time::timeout(DEBOUNCE, changes.recv()).await {
+                Ok(Some(())) => continue,
+                Ok(None) => return,
+                Err(_) => break,
+            }
+        }
+        // A run that is still queued will see these changes too.
+        if app_state.jobs.find_active(WATCH_JOB_KIND, &opts.path).is_some() {
+            continue;
+        }
+        log::info!("Sources of {} changed; re-processing", opts.path);
+        let (job_state, job_opts) = (app_state.clone(), opts.clone());
+        let submitted = app_state.jobs.submit_with_progress(WATCH_JOB_KIND, &opts.path, JobPriority::Low, |progress| async move {
+            pipeline::process_project(job_state, job_opts, progress).await.map(|outcome| outcome.to_string())
+        });
+        // The next change retries.
+        if let Err(e) = submitted {
+            log::warn!("Could not queue re-processing {}: {}", opts.path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_relevant() {
+        let members = [PathBuf::from("/work/my-crate"), PathBuf::from("/work/my-crate/crates/foo")];
+        assert!(is_relevant(&members, Path::new("/work/my-crate/src/lib.rs")));
+        assert!(is_relevant(&members, Path::new("/work/my-crate/src/net/mod.rs")));
+        assert!(is_relevant(&members, Path::new("/work/my-crate/Cargo.toml")));
+        assert!(is_relevant(&members, Path::new("/work/my-crate/crates/foo/src/lib.rs")));
+        assert!(is_relevant(&members, Path::new("/work/my-crate/crates/foo/Cargo.toml")));
+        assert!(!is_relevant(&members, Path::new("/work/my-crate/crates/bar/src/lib.rs")));
+        assert!(!is_relevant(&members, Path::new("/work/my-crate/target/debug/build.rs")));
+        assert!(!is_relevant(&members, Path::new("/work/my-crate/src/.lib.rs.swp")));
+        assert!(!is_relevant(&members, Path::new("/work/my-crate/README.md")));
+        assert!(!is_relevant(&members, Path::new("/work/other/src/lib.rs")));
+    }
+}
@@ -0,0 +1,124 @@
+//! Golden tests for `parse_rustdoc_json_file` over the pre-generated fixtures in `tests/fixtures`.
+//! No nightly toolchain or network is needed.
+//!
+//! Each `<name>.json` fixture has a `<name>.golden.json` holding the expected parse result. Every
+//! fixture must parse: a parser bug is fixed, not snapshotted. After an intentional parser change,
+//! review and regenerate them with:
+//! `UPDATE_GOLDEN=1 cargo test --test golden_parse`
+
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri_nextjs_template_lib::rustdoc_processor::parse_rustdoc_json_file;
+
+fn fixtures_dir() -> PathBuf {
+  Path::new(env!("CARGO_MANIFEST_DIR"))
+    .join("tests")
+    .join("fixtures")
+}
+
+fn fixture_paths() -> Vec<PathBuf> {
+  let mut paths: Vec<PathBuf> = fs::read_dir(fixtures_dir())
+    .expect("tests/fixtures should exist")
+    .filter_map(|entry| entry.ok().map(|e| e.path()))
+    .filter(|path| {
+      let name = path.file_name().unwrap().to_string_lossy();
+      name.ends_with(".json") && !name.ends_with(".golden.json")
+    })
+    .collect();
+  paths.sort();
+  paths
+}
+
+// Stable, order-independent view of the parse result. Item ids are left out because they are
+// rustdoc-internal and change between format versions.
+fn golden_view(fixture: &Path) -> Value {
+  match parse_rustdoc_json_file(fixture) {
+    Ok(crate_docs) => {
+      let items: BTreeMap<&String, Value> = crate_docs
+        .items
+        .iter()
+        .map(|(full_path, item)| {
+          (
+            full_path,
+            json!({
+                "name": item.name,
+                "item_type": item.item_type,
+                "path": item.path,
+                "description": item.description,
+            }),
+          )
+        })
+        .collect();
+      let mut view = Map::new();
+      view.insert("crate_name".to_string(), json!(crate_docs.crate_name));
+      view.insert("items".to_string(), json!(items));
+      Value::Object(view)
+    }
+    Err(e) => json!({ "error": e.to_string() }),
+  }
+}
+
+#[test]
+fn parse_matches_golden_files() {
+  let update = std::env::var("UPDATE_GOLDEN").is_ok();
+  let fixtures = fixture_paths();
+  assert!(
+    !fixtures.is_empty(),
+    "No fixtures found in {}",
+    fixtures_dir().display()
+  );
+
+  let mut mismatches = Vec::new();
+  for fixture in fixtures {
+    let golden_path = fixture.with_extension("golden.json");
+    let actual = golden_view(&fixture);
+    if let Some(error) = actual.get("error") {
+      mismatches.push(format!("{}: failed to parse: {}", fixture.display(), error));
+      continue;
+    }
+
+    if update {
+      fs::write(
+        &golden_path,
+        serde_json::to_string_pretty(&actual).unwrap() + "\n",
+      )
+      .unwrap();
+      continue;
+    }
+
+    let expected: Value =
+      serde_json::from_str(&fs::read_to_string(&golden_path).unwrap_or_else(|_| {
+        panic!(
+          "Missing golden file {}. Run with UPDATE_GOLDEN=1 to create it.",
+          golden_path.display()
+        )
+      }))
+      .unwrap();
+    if actual != expected {
+      mismatches.push(format!(
+        "{}:\n  expected: {}\n  actual:   {}",
+        fixture.display(),
+        expected,
+        actual
+      ));
+    }
+  }
+
+  assert!(
+    mismatches.is_empty(),
+    "Parser output differs from golden files:\n{}",
+    mismatches.join("\n")
+  );
+}
+
+// Format 37 switched ids (`root` included) from strings to integers.
+#[test]
+fn integer_ids_parse() {
+  let crate_docs = parse_rustdoc_json_file(&fixtures_dir().join("format_v37_modern.json"))
+    .expect("format 37 fixture should parse");
+  assert_eq!(crate_docs.crate_name, "fixture_modern");
+  assert!(crate_docs.items.contains_key("fixture_modern::connect"));
+  assert!(crate_docs.items.contains_key("fixture_modern::Client"));
+}
@@ -2,17 +2,179 @@
 
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/tauri'; // For calling Rust backend
+import { listen } from '@tauri-apps/api/event';
+import { describeError, isAppError, remediation, type AppError } from '@/lib/appError';
 
 interface Project {
   path: string;
   status: 'idle' | 'processing' | 'processed' | 'error';
   message?: string; // For error messages or other info
+  percent?: number; // Progress of the running processing job
+  jobId?: number; // Id of the running processing job, used to cancel it
+  needsReembed?: boolean; // Embedded with a previous model; re-embed before querying it
+  watching?: boolean; // Watch mode: re-processed automatically when its sources change
+}
+
+// Mirrors jobs::JobProgressEvent on the Rust side.
+type JobPhase =
+  | { phase: 'rustdoc' | 'parse' | 'dependencies' | 'index' }
+  | { phase: 'crate_parsed'; crate_name: string; done: number; total: number }
+  | { phase: 'embed'; batch: number; total_batches: number }
+  | { phase: 'install'; message: string }
+  | { phase: 'finished'; status: { state: 'completed'; message: string } | { state: 'failed'; error: AppError } | { state: 'cancelled' } };
+
+type JobProgressEvent = JobPhase & { job_id: number; kind: string; label: string; percent: number };
+
+function phaseLabel(event: JobProgressEvent): string {
+  switch (event.phase) {
+    case 'rustdoc': return 'Running cargo rustdoc';
+    case 'parse': return 'Parsing rustdoc JSON';
+    case 'dependencies': return 'Documenting dependencies';
+    case 'crate_parsed': return `Parsed ${event.crate_name} (${event.done}/${event.total})`;
+    case 'embed': return `Embedding batch ${event.batch}/${event.total_batches}`;
+    case 'index': return 'Building search index';
+    case 'install': return event.message;
+    case 'finished': return 'Finished';
+  }
+}
+
+// Mirrors embedder::EmbedderInfo on the Rust side.
+type DevicePreference = 'auto' | 'cpu' | 'cuda' | 'metal';
+type ModelPreset = 'fast' | 'balanced' | 'best';
+
+// Mirrors embedder::ModelPreset.
+const MODEL_PRESETS: { preset: ModelPreset; label: string; description: string }[] = [
+  { preset: 'fast', label: 'Fast', description: 'all-MiniLM-L6-v2, 384 dimensions; fine on any CPU' },
+  { preset: 'balanced', label: 'Balanced', description: 'bge-base-en-v1.5, 768 dimensions' },
+  { preset: 'best', label: 'Best', description: 'bge-code-v1, 1536 dimensions; wants a GPU' },
+];
+
+interface EmbedderInfo {
+  loaded: boolean;
+  model_repo: string;
+  model_revision: string;
+  device: string | null;
+  dtype: string | null;
+  quantized_file: string | null;
+  pooling: string | null;
+  preset: ModelPreset | null;
+  backend: 'candle' | 'onnx' | 'remote';
+  device_preference: DevicePreference;
+  cuda_available: boolean;
+  metal_available: boolean;
+}
+
+// Mirrors embedder::EmbedderStatus; the model loads in the background after startup.
+type EmbedderStatus =
+  | { state: 'not_started' }
+  | { state: 'downloading'; model_repo: string }
+  | { state: 'loading'; model_repo: string }
+  | { state: 'ready'; model_repo: string }
+  | { state: 'failed'; model_repo: string; message: string };
+
+function embedderStatusLabel(status: EmbedderStatus): string | null {
+  switch (status.state) {
+    case 'downloading': return `Downloading ${status.model_repo} (first run only, can take a few minutes)...`;
+    case 'loading': return `Loading ${status.model_repo}...`;
+    case 'failed': return `Failed to load ${status.model_repo}: ${status.message}`;
+    default: return null;
+  }
+}
+
+// Mirrors commands::AuthSettings: the token MCP (HTTP) and HTTP API clients must send when required.
+interface AuthSettings {
+  auth_required: boolean;
+  auth_token: string | null;
+}
+
+// Project key of the standard library docs indexed by `process_std_library`.
+const STD_PROJECT_PATH = 'rust-std';
+
+// Which package targets `invoke_process_rust_project` documents; 'auto' is the library, or the binaries if there is none.
+type DocTargets = 'auto' | 'lib' | 'bin' | 'example' | 'all';
+
+function errorMessage(error: unknown): string {
+  const fix = remediation(error);
+  return fix ? `${describeError(error)} ${fix}` : describeError(error);
 }
 
 export default function ProjectsPage() {
   const [projects, setProjects] = useState<Project[]>([]);
   const [newProjectPath, setNewProjectPath] = useState<string>('');
   const [isLoading, setIsLoading] = useState<boolean>(false); // For loading state of an action
+  const [includeDependencies, setIncludeDependencies] = useState<boolean>(false);
+  const [targets, setTargets] = useState<DocTargets>('auto');
+  const [modelRepo, setModelRepo] = useState<string>('');
+  const [modelMessage, setModelMessage] = useState<string>('');
+  const [device, setDevice] = useState<DevicePreference>('auto');
+  const [embedderInfo, setEmbedderInfo] = useState<EmbedderInfo | null>(null);
+  const [embedderStatus, setEmbedderStatus] = useState<EmbedderStatus | null>(null);
+  const [authSettings, setAuthSettings] = useState<AuthSettings | null>(null);
+  const [importJsonPath, setImportJsonPath] = useState<string>('');
+  const [importLabel, setImportLabel] = useState<string>('');
+  const [missingToolchain, setMissingToolchain] = useState<string | null>(null); // From a `toolchain_missing` error
+  const [toolchainInstall, setToolchainInstall] = useState<string | null>(null); // Latest rustup output, or why the install failed
+  const [installingToolchain, setInstallingToolchain] = useState<boolean>(false);
+
+  const refreshEmbedderInfo = async () => {
+    try {
+      const info = await invoke<EmbedderInfo>('get_embedder_info');
+      setEmbedderInfo(info);
+      setDevice(info.device_preference);
+    } catch (error) {
+      console.error("Failed to get embedder info:", error);
+    }
+  };
+
+  useEffect(() => {
+    refreshEmbedderInfo();
+  }, []);
+
+  useEffect(() => {
+    invoke<AuthSettings>('get_auth_settings')
+      .then(setAuthSettings)
+      .catch(error => console.error("Failed to get auth settings:", error));
+  }, []);
+
+  const handleToggleAuth = async (required: boolean) => {
+    try {
+      setAuthSettings(await invoke<AuthSettings>('set_auth_required', { required }));
+    } catch (error) {
+      console.error("Failed to change auth settings:", error);
+    }
+  };
+
+  // Poll while the model warms up, then refresh the info once it settles.
+  useEffect(() => {
+    let timer: ReturnType<typeof setTimeout> | undefined;
+    const poll = async () => {
+      try {
+        const status = await invoke<EmbedderStatus>('get_embedder_status');
+        setEmbedderStatus(status);
+        if (status.state === 'downloading' || status.state === 'loading') {
+          timer = setTimeout(poll, 2000);
+        } else {
+          refreshEmbedderInfo();
+        }
+      } catch (error) {
+        console.error("Failed to get embedder status:", error);
+      }
+    };
+    poll();
+    return () => clearTimeout(timer);
+  }, []);
+
+  // Out of GPU memory: the embedder keeps going on the CPU, which is much slower.
+  useEffect(() => {
+    const unlisten = listen<{ type: string; model_repo?: string; from_device?: string }>('state-changed', ({ payload }) => {
+      if (payload.type !== 'embedder_device_fallback') return;
+      setModelMessage(`Warning: ${payload.model_repo} ran out of ${payload.from_device?.toUpperCase()} memory and now runs on the CPU. Try a quantized model or a lower max_batch_size.`);
+      refreshEmbedderInfo();
+    });
+    return () => {
+      unlisten.then(f => f());
+    };
+  }, []);
 
   // Function to load projects from backend (if stored) - Placeholder for now
   // useEffect(() => {
@@ -23,6 +185,67 @@ export default function ProjectsPage() {
   //   loadProjects();
   // }, []);
 
+  // Processing runs in the background; progress and the final result arrive as events.
+  useEffect(() => {
+    const unlisten = listen<JobProgressEvent>('process-progress', ({ payload }) => {
+      if (payload.kind === 'install_toolchain') {
+        if (payload.phase === 'install') {
+          setToolchainInstall(payload.message);
+        } else if (payload.phase === 'finished') {
+          const status = payload.status;
+          setInstallingToolchain(false);
+          if (status.state === 'completed') setMissingToolchain(null);
+          setToolchainInstall(status.state === 'completed' ? null : status.state === 'failed' ? errorMessage(status.error) : 'Cancelled');
+        }
+        return;
+      }
+      if (payload.phase === 'finished' && payload.status.state === 'failed' && payload.status.error.code === 'toolchain_missing') {
+        setMissingToolchain(payload.status.error.details.toolchain);
+      }
+      if (!['process_rust_project', 'process_std_library', 'reembed_project', 'watch_refresh', 'import_rustdoc_json'].includes(payload.kind)) return;
+      setProjects(prev => prev.map(p => {
+        if (p.path !== payload.label) return p;
+        if (payload.phase !== 'finished') {
+          return { ...p, status: 'processing', percent: payload.percent, message: phaseLabel(payload) };
+        }
+        switch (payload.status.state) {
+          case 'completed':
+            return { ...p, status: 'processed', percent: undefined, jobId: undefined, needsReembed: false, message: payload.status.message };
+          case 'failed':
+            return { ...p, status: 'error', percent: undefined, jobId: undefined, message: errorMessage(payload.status.error) };
+          case 'cancelled':
+            return { ...p, status: 'idle', percent: undefined, jobId: undefined, message: 'Cancelled' };
+        }
+      }));
+    });
+    return () => {
+      unlisten.then(f => f());
+    };
+  }, []);
+
+  // Watch mode is toggled here and in other windows; `watch_changed` keeps them in sync.
+  useEffect(() => {
+    invoke<string[]>('get_watched_projects')
+      .then(watched => setProjects(prev => prev.map(p => ({ ...p, watching: watched.includes(p.path) }))))
+      .catch(error => console.error("Failed to get watched projects:", error));
+    const unlisten = listen<{ type: string; project_path?: string; watching?: boolean }>('state-changed', ({ payload }) => {
+      if (payload.type !== 'watch_changed') return;
+      setProjects(prev => prev.map(p => p.path === payload.project_path ? { ...p, watching: payload.watching } : p));
+    });
+    return () => {
+      unlisten.then(f => f());
+    };
+  }, []);
+
+  const handleToggleWatch = async (project: Project) => {
+    try {
+      await invoke(project.watching ? 'disable_watch' : 'enable_watch', { projectPath: project.path });
+    } catch (error) {
+      console.error("Failed to toggle watch mode:", error);
+      setProjects(prev => prev.map(p => p.path === project.path ? { ...p, message: errorMessage(error) } : p));
+    }
+  };
+
   const handleAddProject = () => {
     if (newProjectPath.trim() && !projects.find(p => p.path === newProjectPath.trim())) {
       setProjects([...projects, { path: newProjectPath.trim(), status: 'idle' }]);
@@ -34,25 +257,154 @@ export default function ProjectsPage() {
     }
   };
 
-  const handleRemoveProject = (pathToRemove: string) => {
-    setProjects(projects.filter(p => p.path !== pathToRemove));
-    // TODO: Optionally, call backend to remove/unmanage project
+  const handleRemoveProject = async (project: Project) => {
+    // Processed projects are also unloaded from the backend, which deletes their index from disk.
+    if (project.status === 'processed') {
+      try {
+        await invoke('remove_processed_project', { path: project.path });
+      } catch (error) {
+        // Not loaded in the backend (e.g. removed through MCP): just drop it from the list.
+        if (!isAppError(error) || error.code !== 'project_not_found') {
+          console.error("Failed to remove project:", error);
+          setProjects(prev => prev.map(p => p.path === project.path ? { ...p, message: errorMessage(error) } : p));
+          return;
+        }
+      }
+    }
+    setProjects(prev => prev.filter(p => p.path !== project.path));
   };
 
   const handleProcessProject = async (projectPath: string) => {
-    setProjects(prev => prev.map(p => p.path === projectPath ? { ...p, status: 'processing', message: '' } : p));
+    setProjects(prev => prev.map(p => p.path === projectPath ? { ...p, status: 'processing', message: 'Queued', percent: 0 } : p));
     setIsLoading(true);
     try {
-      // This will call the Tauri command, which in turn calls the MCP tool.
-      // The MCP tool `process_rust_project` is what we'll eventually connect this to.
-      // For now, the Tauri command `invoke_process_rust_project` is a placeholder.
-      const result = await invoke<string>('invoke_process_rust_project', { path: projectPath });
-      setProjects(prev => prev.map(p => p.path === projectPath ? { ...p, status: 'processed', message: result } : p));
+      // Returns as soon as the job is queued; the `process-progress` listener tracks it from there.
+      let jobId: number;
+      try {
+        jobId = await invoke<number>('invoke_process_rust_project', { path: projectPath, includeDependencies, targets });
+      } catch (error) {
+        // Hardened mode: building runs the project's build scripts, so ask before building untrusted code.
+        if (!isAppError(error) || error.code !== 'untrusted_project' ||
+            !window.confirm(`${describeError(error)} Building it runs its build scripts. Continue?`)) {
+          throw error;
+        }
+        jobId = await invoke<number>('invoke_process_rust_project', { path: projectPath, includeDependencies, targets, allowUntrusted: true });
+      }
+      setProjects(prev => prev.map(p => p.path === projectPath && p.status === 'processing' ? { ...p, jobId } : p));
     } catch (error: any) {
       console.error("Failed to process project:", error);
-      setProjects(prev => prev.map(p => p.path === projectPath ? { ...p, status: 'error', message: error.toString() } : p));
+      setProjects(prev => prev.map(p => p.path === projectPath ? { ...p, status: 'error', percent: undefined, message: errorMessage(error) } : p));
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
+  // Per-project toolchain (e.g. a pinned nightly); an empty answer goes back to the default.
+  const handleSetToolchain = async (project: Project) => {
+    const answer = window.prompt(`Toolchain for ${project.path} (e.g. nightly-2024-05-01 or stable; empty for the default):`);
+    if (answer === null) return;
+    try {
+      const result = await invoke<{ toolchain: string; format_version: number | null }>(
+        'set_project_toolchain', { projectPath: project.path, toolchain: answer.trim() || null });
+      const format = result.format_version !== null ? `, rustdoc JSON format ${result.format_version}` : '';
+      setProjects(prev => prev.map(p => p.path === project.path ? { ...p, message: `Toolchain: ${result.toolchain}${format}. Re-process to apply.` } : p));
+    } catch (error) {
+      console.error("Failed to set the toolchain:", error);
+      if (isAppError(error) && error.code === 'toolchain_missing') setMissingToolchain(error.details.toolchain);
+      setProjects(prev => prev.map(p => p.path === project.path ? { ...p, message: errorMessage(error) } : p));
+    }
+  };
+
+  const handleInstallToolchain = async () => {
+    setInstallingToolchain(true);
+    setToolchainInstall('Starting rustup...');
+    try {
+      await invoke<number>('install_nightly_toolchain', { toolchain: missingToolchain });
+    } catch (error) {
+      console.error("Failed to install the toolchain:", error);
+      setToolchainInstall(errorMessage(error));
+      setInstallingToolchain(false);
+    }
+  };
+
+  // The standard library is listed as the project `rust-std` and tracked like any other job.
+  const handleProcessStdLibrary = async () => {
+    setProjects(prev => [
+      ...prev.filter(p => p.path !== STD_PROJECT_PATH),
+      { path: STD_PROJECT_PATH, status: 'processing', message: 'Installing rust-docs-json', percent: 0 },
+    ]);
+    try {
+      const jobId = await invoke<number>('process_std_library');
+      setProjects(prev => prev.map(p => p.path === STD_PROJECT_PATH && p.status === 'processing' ? { ...p, jobId } : p));
+    } catch (error) {
+      console.error("Failed to index the standard library:", error);
+      setProjects(prev => prev.map(p => p.path === STD_PROJECT_PATH ? { ...p, status: 'error', percent: undefined, message: errorMessage(error) } : p));
+    }
+  };
+
+  // An imported rustdoc JSON file is listed (and queried) under its label.
+  const handleImportJson = async () => {
+    const projectLabel = importLabel.trim();
+    setProjects(prev => [
+      ...prev.filter(p => p.path !== projectLabel),
+      { path: projectLabel, status: 'processing', message: 'Queued', percent: 0 },
+    ]);
+    try {
+      const jobId = await invoke<number>('import_rustdoc_json', { jsonPath: importJsonPath.trim(), projectLabel });
+      setProjects(prev => prev.map(p => p.path === projectLabel && p.status === 'processing' ? { ...p, jobId } : p));
+      setImportJsonPath('');
+      setImportLabel('');
+    } catch (error) {
+      console.error("Failed to import rustdoc JSON:", error);
+      setProjects(prev => prev.map(p => p.path === projectLabel ? { ...p, status: 'error', percent: undefined, message: errorMessage(error) } : p));
+    }
+  };
+
+  // Re-embeds with the loaded model only; the docs are not rebuilt.
+  const handleReembedProject = async (projectPath: string) => {
+    setProjects(prev => prev.map(p => p.path === projectPath ? { ...p, status: 'processing', message: 'Queued', percent: 0 } : p));
+    try {
+      const jobId = await invoke<number>('reembed_project', { path: projectPath });
+      setProjects(prev => prev.map(p => p.path === projectPath && p.status === 'processing' ? { ...p, jobId } : p));
+    } catch (error) {
+      console.error("Failed to re-embed project:", error);
+      setProjects(prev => prev.map(p => p.path === projectPath ? { ...p, status: 'error', percent: undefined, message: errorMessage(error) } : p));
+    }
+  };
+
+  const handleCancelProject = async (project: Project) => {
+    if (project.jobId === undefined) return;
+    try {
+      await invoke('cancel_processing', { jobId: project.jobId });
+      setProjects(prev => prev.map(p => p.path === project.path ? { ...p, message: 'Cancelling...' } : p));
+    } catch (error) {
+      console.error("Failed to cancel processing:", error);
+    }
+  };
+
+  const handleSetEmbeddingModel = () =>
+    switchEmbeddingModel(modelRepo.trim(), 'set_embedding_model', { modelRepo: modelRepo.trim(), device });
+
+  const handleSetEmbeddingPreset = (preset: ModelPreset) =>
+    switchEmbeddingModel(`the ${preset} preset`, 'set_embedding_preset', { preset });
+
+  const switchEmbeddingModel = async (label: string, command: string, args: Record<string, unknown>) => {
+    setIsLoading(true);
+    setModelMessage(`Loading ${label}...`);
+    try {
+      const change = await invoke<{ model_repo: string; model_revision: string; projects_to_reembed: string[] }>(command, args);
+      // Vectors from the old model can't be compared with the new model's queries; flag them for re-processing.
+      setProjects(prev => prev.map(p => change.projects_to_reembed.includes(p.path)
+        ? { ...p, needsReembed: true, message: 'Embedded with the previous model; re-embed to query it' } : p));
+      setModelMessage(change.projects_to_reembed.length > 0
+        ? `Now using ${change.model_repo}. ${change.projects_to_reembed.length} project(s) need re-processing.`
+        : `Now using ${change.model_repo}.`);
+    } catch (error) {
+      console.error("Failed to switch embedding model:", error);
+      setModelMessage(describeError(error));
     } finally {
       setIsLoading(false);
+      refreshEmbedderInfo();
     }
   };
 
@@ -79,8 +431,159 @@ export default function ProjectsPage() {
             Add Project
           </button>
         </div>
+        <label className="flex items-center mt-3 text-sm text-gray-700">
+          <input
+            type="checkbox"
+            checked={includeDependencies}
+            onChange={(e) => setIncludeDependencies(e.target.checked)}
+            className="mr-2"
+          />
+          Also index direct dependencies when processing (slower)
+        </label>
+        <button
+          onClick={handleProcessStdLibrary}
+          disabled={projects.some(p => p.path === STD_PROJECT_PATH && p.status === 'processing')}
+          className="mt-3 px-4 py-2 bg-gray-700 text-white rounded-md hover:bg-gray-800 disabled:bg-gray-400"
+        >
+          Index the Standard Library (std, core, alloc)
+        </button>
+        <label className="flex items-center mt-3 text-sm text-gray-700">
+          Document targets:
+          <select
+            value={targets}
+            onChange={(e) => setTargets(e.target.value as DocTargets)}
+            className="ml-2 p-1 border border-gray-300 rounded-md"
+          >
+            <option value="auto">Library (binaries if there is no library)</option>
+            <option value="lib">Library</option>
+            <option value="bin">Binaries</option>
+            <option value="example">Examples</option>
+            <option value="all">All (library, binaries and examples)</option>
+          </select>
+        </label>
+        <div className="flex space-x-2 mt-3">
+          <input
+            type="text"
+            value={importJsonPath}
+            onChange={(e) => setImportJsonPath(e.target.value)}
+            placeholder="Existing rustdoc JSON file (e.g. from CI)"
+            className="flex-grow p-2 border border-gray-300 rounded-md text-sm"
+          />
+          <input
+            type="text"
+            value={importLabel}
+            onChange={(e) => setImportLabel(e.target.value)}
+            placeholder="Project label"
+            className="w-40 p-2 border border-gray-300 rounded-md text-sm"
+          />
+          <button
+            onClick={handleImportJson}
+            disabled={!importJsonPath.trim() || !importLabel.trim()}
+            className="px-4 py-2 bg-gray-700 text-white rounded-md hover:bg-gray-800 disabled:bg-gray-400 text-sm"
+          >
+            Import JSON
+          </button>
+        </div>
+      </div>
+
+      {/* Embedding Model */}
+      <div className="mb-6 p-4 border rounded-lg shadow-sm bg-white">
+        <h2 className="text-xl font-semibold mb-3 text-gray-700">Embedding Model</h2>
+        <div className="flex space-x-2">
+          <input
+            type="text"
+            value={modelRepo}
+            onChange={(e) => setModelRepo(e.target.value)}
+            placeholder="Hugging Face model repo, e.g. BAAI/bge-code-v1"
+            className="flex-grow p-2 border border-gray-300 rounded-md focus:ring-blue-500 focus:border-blue-500 shadow-sm"
+          />
+          <select
+            value={device}
+            onChange={(e) => setDevice(e.target.value as DevicePreference)}
+            className="p-2 border border-gray-300 rounded-md shadow-sm"
+          >
+            <option value="auto">Auto</option>
+            <option value="cpu">CPU</option>
+            <option value="cuda" disabled={!embedderInfo?.cuda_available}>CUDA</option>
+            <option value="metal" disabled={!embedderInfo?.metal_available}>Metal</option>
+          </select>
+          <button
+            onClick={handleSetEmbeddingModel}
+            disabled={!modelRepo.trim() || isLoading}
+            className="px-4 py-2 bg-blue-600 text-white rounded-md hover:bg-blue-700 disabled:bg-gray-400 focus:outline-none focus:ring-2 focus:ring-blue-500 focus:ring-opacity-50 transition ease-in-out duration-150"
+          >
+            Switch Model
+          </button>
+        </div>
+        <div className="flex space-x-2 mt-2">
+          {MODEL_PRESETS.map(({ preset, label, description }) => (
+            <button
+              key={preset}
+              onClick={() => handleSetEmbeddingPreset(preset)}
+              disabled={isLoading}
+              title={description}
+              className={`px-3 py-1 text-sm rounded-md border ${embedderInfo?.preset === preset ? 'bg-blue-100 border-blue-500 text-blue-800' : 'border-gray-300 text-gray-700 hover:bg-gray-100'} disabled:opacity-50`}
+            >
+              {label}
+            </button>
+          ))}
+        </div>
+        {embedderInfo && (
+          <p className="mt-2 text-sm text-gray-600">
+            {embedderInfo.loaded
+              ? `Loaded: ${embedderInfo.model_repo} on ${embedderInfo.device?.toUpperCase()}${embedderInfo.backend === 'onnx' ? ' via ONNX Runtime' : ''} (${embedderInfo.quantized_file ?? embedderInfo.dtype}, ${embedderInfo.pooling} pooling)`
+              : `Not loaded: ${embedderInfo.model_repo}`}
+          </p>
+        )}
+        {embedderStatus && embedderStatusLabel(embedderStatus) && (
+          <p className={`mt-2 text-sm ${embedderStatus.state === 'failed' ? 'text-red-600' : 'text-gray-600'}`}>
+            {embedderStatusLabel(embedderStatus)}
+          </p>
+        )}
+        {modelMessage && <p className="mt-2 text-sm text-gray-600">{modelMessage}</p>}
       </div>
 
+      {/* Missing toolchain: offer to install it instead of only printing the rustup command */}
+      {missingToolchain && (
+        <div className="mb-6 p-4 border border-yellow-300 rounded-lg shadow-sm bg-yellow-50">
+          <p className="text-sm text-gray-700">
+            Processing needs the <code>{missingToolchain}</code> Rust toolchain, which is not installed.
+          </p>
+          <button
+            onClick={handleInstallToolchain}
+            disabled={installingToolchain}
+            className="mt-2 px-3 py-1.5 bg-yellow-500 text-white rounded-md hover:bg-yellow-600 disabled:bg-gray-400 text-sm focus:outline-none focus:ring-2 focus:ring-yellow-500 focus:ring-opacity-50 transition ease-in-out duration-150"
+          >
+            Install {missingToolchain} with rustup
+          </button>
+          {toolchainInstall && <p className="mt-2 text-xs text-gray-600 font-mono truncate">{toolchainInstall}</p>}
+        </div>
+      )}
+
+      {/* Server Access */}
+      {authSettings && (
+        <div className="mb-6 p-4 border rounded-lg shadow-sm bg-white">
+          <h2 className="text-xl font-semibold mb-3 text-gray-700">Server Access</h2>
+          <label className="flex items-center text-sm text-gray-700">
+            <input
+              type="checkbox"
+              checked={authSettings.auth_required}
+              onChange={(e) => handleToggleAuth(e.target.checked)}
+              className="mr-2"
+            />
+            Require the auth token for MCP (HTTP) and HTTP API clients
+          </label>
+          {authSettings.auth_token && (
+            <p className="mt-2 text-sm text-gray-600">
+              Token: <code className="px-1 bg-gray-100 rounded select-all">{authSettings.auth_token}</code>
+            </p>
+          )}
+          <p className="mt-1 text-xs text-gray-500">
+            MCP (HTTP) and HTTP API clients send it as an <code>Authorization: Bearer &lt;token&gt;</code> header.
+          </p>
+        </div>
+      )}
+
       {/* Project List */}
       <div className="space-y-4">
         {projects.length === 0 && (
@@ -102,6 +605,11 @@ export default function ProjectsPage() {
                   Status: {project.status}
                   {project.message && <span className="ml-2 text-xs text-gray-600">({project.message})</span>}
                 </p>
+                {project.status === 'processing' && project.percent !== undefined && (
+                  <div className="mt-2 h-2 w-full bg-gray-200 rounded">
+                    <div className="h-2 bg-yellow-500 rounded transition-all duration-300" style={{ width: `${project.percent}%` }} />
+                  </div>
+                )}
               </div>
               <div className="flex-shrink-0 space-x-2">
                 <button
@@ -111,8 +619,41 @@ export default function ProjectsPage() {
                 >
                   {project.status === 'processing' ? 'Processing...' : 'Process'}
                 </button>
+                {project.needsReembed && project.status !== 'processing' && (
+                  <button
+                    onClick={() => handleReembedProject(project.path)}
+                    className="px-3 py-1.5 bg-yellow-500 text-white rounded-md hover:bg-yellow-600 text-sm focus:outline-none focus:ring-2 focus:ring-yellow-500 focus:ring-opacity-50 transition ease-in-out duration-150"
+                  >
+                    Re-embed
+                  </button>
+                )}
+                {project.path !== STD_PROJECT_PATH && project.status !== 'processing' && (
+                  <button
+                    onClick={() => handleSetToolchain(project)}
+                    className="px-3 py-1.5 bg-gray-200 text-gray-700 rounded-md hover:bg-gray-300 text-sm focus:outline-none focus:ring-2 focus:ring-gray-400 focus:ring-opacity-50 transition ease-in-out duration-150"
+                  >
+                    Toolchain
+                  </button>
+                )}
+                {(project.status === 'processed' || project.watching) && (
+                  <button
+                    onClick={() => handleToggleWatch(project)}
+                    title="Re-process automatically when files under src/ or Cargo.toml change"
+                    className="px-3 py-1.5 bg-indigo-500 text-white rounded-md hover:bg-indigo-600 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:ring-opacity-50 transition ease-in-out duration-150"
+                  >
+                    {project.watching ? 'Stop watching' : 'Watch'}
+                  </button>
+                )}
+                {project.status === 'processing' && project.jobId !== undefined && (
+                  <button
+                    onClick={() => handleCancelProject(project)}
+                    className="px-3 py-1.5 bg-gray-500 text-white rounded-md hover:bg-gray-600 text-sm focus:outline-none focus:ring-2 focus:ring-gray-500 focus:ring-opacity-50 transition ease-in-out duration-150"
+                  >
+                    Cancel
+                  </button>
+                )}
                 <button
-                  onClick={() => handleRemoveProject(project.path)}
+                  onClick={() => handleRemoveProject(project)}
                   className="px-3 py-1.5 bg-red-500 text-white rounded-md hover:bg-red-600 text-sm focus:outline-none focus:ring-2 focus:ring-red-500 focus:ring-opacity-50 transition ease-in-out duration-150"
                 >
                   Remove
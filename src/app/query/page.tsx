@@ -1,7 +1,9 @@
 'use client'; // Required for client-side interactivity
 
-import { useState, useEffect } from 'react';
+import { useState, useEffect, type ReactNode } from 'react';
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
+import { describeError, remediation } from '@/lib/appError';
 
 // Assuming ProjectData and QueryDocResultItem might be defined elsewhere
 // or we define simplified versions here for UI state.
@@ -12,16 +14,66 @@ interface Project {
 
 interface QueryResultItem {
   project_path: string;
+  version?: string;
   item_full_path: string;
   item_type: string;
   description_snippet?: string;
+  full_docs?: string;
+  signature?: string;
+  generics?: string;
+  // Set for #[deprecated] items, e.g. "Deprecated since 0.3.0: use `Config::builder`".
+  deprecated?: string;
+  // What the item's #[cfg] requires, e.g. "Only available on unix".
+  availability?: string;
   score: number;
+  related_items: RelatedItem[];
+  examples: string[];
+  // Byte ranges [start, end) of description_snippet that match query terms.
+  highlights: [number, number][];
 }
 
+interface RelatedItem {
+  full_path: string;
+  item_type: string;
+  relation: 'parent' | 'sibling' | 'child';
+}
+
+// The snippet with its highlighted byte ranges wrapped in <mark>.
+function renderHighlighted(text: string, highlights: [number, number][]) {
+  if (highlights.length === 0) {
+    return text;
+  }
+  const bytes = new TextEncoder().encode(text);
+  const decoder = new TextDecoder();
+  const parts: ReactNode[] = [];
+  let position = 0;
+  highlights.forEach(([start, end], i) => {
+    parts.push(decoder.decode(bytes.slice(position, start)));
+    parts.push(<mark key={i} className="bg-yellow-100">{decoder.decode(bytes.slice(start, end))}</mark>);
+    position = end;
+  });
+  parts.push(decoder.decode(bytes.slice(position)));
+  return parts;
+}
+
+// Item kinds offered as filters; an empty selection searches every kind.
+const ITEM_TYPE_OPTIONS = ['function', 'method', 'struct', 'enum', 'trait', 'macro', 'module', 'type_alias', 'constant'];
+
 export default function QueryPage() {
   const [query, setQuery] = useState<string>('');
   const [selectedProjectPath, setSelectedProjectPath] = useState<string>(''); // Store the path
   const [availableProjects, setAvailableProjects] = useState<Project[]>([]); // To populate dropdown
+  const [selectedItemTypes, setSelectedItemTypes] = useState<string[]>([]);
+  const [rerank, setRerank] = useState<boolean>(false);
+  const [version, setVersion] = useState<string>('');
+  const [modulePrefix, setModulePrefix] = useState<string>('');
+  const [target, setTarget] = useState<string>('');
+  const [preferExamples, setPreferExamples] = useState<boolean>(false);
+  const [expandQuery, setExpandQuery] = useState<boolean>(false);
+  const [fullDocs, setFullDocs] = useState<boolean>(false);
+  const [includeDeprecated, setIncludeDeprecated] = useState<boolean>(false);
+  const [publicOnly, setPublicOnly] = useState<boolean>(true);
+  const [minScore, setMinScore] = useState<string>('');
   const [results, setResults] = useState<QueryResultItem[]>([]);
   const [isLoading, setIsLoading] = useState<boolean>(false);
   const [error, setError] = useState<string | null>(null);
@@ -52,6 +104,16 @@ export default function QueryPage() {
       }
     }
     fetchProjects();
+
+    // Refresh the dropdown whenever any window (or the MCP server) adds/removes a project.
+    const unlisten = listen<{ type: string }>('state-changed', (event) => {
+      if (['project_added', 'project_removed', 'index_refreshed'].includes(event.payload.type)) {
+        fetchProjects();
+      }
+    });
+    return () => {
+      unlisten.then(fn => fn());
+    };
    }, []); // Empty dependency array means this runs once on component mount
 
   const handleQuery = async () => {
@@ -67,21 +129,37 @@ export default function QueryPage() {
       const queryResults = await invoke<QueryResultItem[]>('invoke_query_documentation', {
         naturalLanguageQuery: query.trim(), // Ensure key matches Rust struct
         projectPath: selectedProjectPath || null,
-        numResults: 10 // Example: make this configurable later if needed
+        version: version.trim() || null,
+        modulePrefix: modulePrefix.trim() || null,
+        target: target.trim() || null,
+        numResults: 10, // Example: make this configurable later if needed
+        itemTypes: selectedItemTypes.length > 0 ? selectedItemTypes : null,
+        rerank,
+        preferExamples,
+        expandQuery,
+        includeFullDocs: fullDocs,
+        includeDeprecated,
+        publicOnly,
+        minScore: minScore.trim() ? Number(minScore) : null
       });
       console.log("Query results from backend:", queryResults);
       setResults(queryResults);
       if (queryResults.length === 0) {
-        setError("No results found for your query.");
+        setError(minScore.trim() ? "No relevant documentation found (no result reached the minimum score)." : "No results found for your query.");
       }
     } catch (err: any) {
       console.error("Failed to execute query:", err);
-      setError(typeof err === 'string' ? err : err.message || "An unknown error occurred during query execution.");
+      const fix = remediation(err);
+      setError(fix ? `${describeError(err)} ${fix}` : describeError(err));
     } finally {
       setIsLoading(false);
     }
   };
 
+  const toggleItemType = (itemType: string) => {
+    setSelectedItemTypes(prev => prev.includes(itemType) ? prev.filter(t => t !== itemType) : [...prev, itemType]);
+  };
+
   return (
     <div className="container mx-auto p-4">
       <h1 className="text-2xl font-bold mb-6 text-gray-800">Query Documentation</h1>
@@ -122,6 +200,153 @@ export default function QueryPage() {
           </div>
         )}
 
+        {/* Version Filter (Optional) */}
+        <div>
+          <label htmlFor="version-filter" className="block text-sm font-medium text-gray-700 mb-1">
+            Version (optional, e.g. 0.6.3, 0.6, or * for every kept version):
+          </label>
+          <input
+            id="version-filter"
+            type="text"
+            value={version}
+            onChange={(e) => setVersion(e.target.value)}
+            placeholder="Current version"
+            className="w-full p-2 border border-gray-300 rounded-md shadow-sm focus:ring-blue-500 focus:border-blue-500"
+          />
+        </div>
+
+        {/* Module Scope (Optional) */}
+        <div>
+          <label htmlFor="module-prefix" className="block text-sm font-medium text-gray-700 mb-1">
+            Only under this path (optional, e.g. tokio::sync):
+          </label>
+          <input
+            id="module-prefix"
+            type="text"
+            value={modulePrefix}
+            onChange={(e) => setModulePrefix(e.target.value)}
+            placeholder="Whole index"
+            className="w-full p-2 border border-gray-300 rounded-md shadow-sm focus:ring-blue-500 focus:border-blue-500"
+          />
+        </div>
+
+        {/* Target Platform (Optional) */}
+        <div>
+          <label htmlFor="target" className="block text-sm font-medium text-gray-700 mb-1">
+            Only items available on this platform (optional, e.g. windows, linux or x86_64-pc-windows-msvc):
+          </label>
+          <input
+            id="target"
+            type="text"
+            value={target}
+            onChange={(e) => setTarget(e.target.value)}
+            placeholder="Any platform"
+            className="w-full p-2 border border-gray-300 rounded-md shadow-sm focus:ring-blue-500 focus:border-blue-500"
+          />
+        </div>
+
+        {/* Item Kind Filter (Optional) */}
+        <div>
+          <span className="block text-sm font-medium text-gray-700 mb-1">
+            Only these kinds (optional):
+          </span>
+          <div className="flex flex-wrap gap-3">
+            {ITEM_TYPE_OPTIONS.map(itemType => (
+              <label key={itemType} className="inline-flex items-center text-sm text-gray-700">
+                <input
+                  type="checkbox"
+                  checked={selectedItemTypes.includes(itemType)}
+                  onChange={() => toggleItemType(itemType)}
+                  className="mr-1"
+                />
+                {itemType}
+              </label>
+            ))}
+          </div>
+        </div>
+
+        {/* Reranking Toggle */}
+        <label className="inline-flex items-center text-sm text-gray-700">
+          <input
+            type="checkbox"
+            checked={rerank}
+            onChange={() => setRerank(!rerank)}
+            className="mr-2"
+          />
+          Rerank top hits with the cross-encoder (slower, more precise ordering; the model is downloaded on first use)
+        </label>
+
+        {/* Prefer Examples Toggle */}
+        <label className="inline-flex items-center text-sm text-gray-700">
+          <input
+            type="checkbox"
+            checked={preferExamples}
+            onChange={() => setPreferExamples(!preferExamples)}
+            className="mr-2"
+          />
+          Prefer items with code examples (for &quot;how do I use X&quot; questions)
+        </label>
+
+        {/* Query Expansion Toggle */}
+        <label className="inline-flex items-center text-sm text-gray-700">
+          <input
+            type="checkbox"
+            checked={expandQuery}
+            onChange={() => setExpandQuery(!expandQuery)}
+            className="mr-2"
+          />
+          Also search paraphrases of the query (better recall for terse queries)
+        </label>
+
+        {/* Full Docs Toggle */}
+        <label className="inline-flex items-center text-sm text-gray-700">
+          <input
+            type="checkbox"
+            checked={fullDocs}
+            onChange={() => setFullDocs(!fullDocs)}
+            className="mr-2"
+          />
+          Show the complete documentation of each result
+        </label>
+
+        {/* Include Deprecated Toggle */}
+        <label className="inline-flex items-center text-sm text-gray-700">
+          <input
+            type="checkbox"
+            checked={includeDeprecated}
+            onChange={() => setIncludeDeprecated(!includeDeprecated)}
+            className="mr-2"
+          />
+          Include deprecated items
+        </label>
+
+        {/* Public Only Toggle */}
+        <label className="inline-flex items-center text-sm text-gray-700">
+          <input
+            type="checkbox"
+            checked={publicOnly}
+            onChange={() => setPublicOnly(!publicOnly)}
+            className="mr-2"
+          />
+          Public API only (uncheck to also search private items)
+        </label>
+
+        {/* Minimum Score Input */}
+        <label className="flex items-center text-sm text-gray-700">
+          Minimum score
+          <input
+            type="number"
+            step="0.05"
+            min="0"
+            max="1"
+            title="0-1 similarity (or rerank relevance); relevant matches usually score above 0.5"
+            value={minScore}
+            onChange={(e) => setMinScore(e.target.value)}
+            placeholder="none"
+            className="ml-2 w-24 px-2 py-1 border border-gray-300 rounded-md"
+          />
+        </label>
+
         {/* Submit Button */}
         <button
           onClick={handleQuery}
@@ -162,11 +387,44 @@ export default function QueryPage() {
                   {item.item_full_path}
                 </h3>
                 <p className="text-xs text-gray-500 mb-1">
-                  Project: <span className="font-medium">{item.project_path}</span> | Type: <span className="font-medium">{item.item_type}</span>
-                </p>
-                <p className="text-sm text-gray-700 mb-2 leading-relaxed">
-                  {item.description_snippet || 'No description available.'}
+                  Project: <span className="font-medium">{item.project_path}</span>
+                  {item.version && <> | Version: <span className="font-medium">{item.version}</span></>} | Type: <span className="font-medium">{item.item_type}</span>
                 </p>
+                {item.signature ? (
+                  <p className="text-xs font-mono text-gray-600 mb-1">{item.signature}</p>
+                ) : item.generics && (
+                  <p className="text-xs font-mono text-gray-600 mb-1">{item.generics}</p>
+                )}
+                {item.deprecated && (
+                  <p className="text-xs text-amber-700 mb-1">{item.deprecated}</p>
+                )}
+                {item.availability && (
+                  <p className="text-xs text-indigo-700 mb-1">{item.availability}</p>
+                )}
+                {item.full_docs !== undefined ? (
+                  <p className="text-sm text-gray-700 mb-2 leading-relaxed whitespace-pre-wrap">
+                    {item.full_docs || 'No description available.'}
+                  </p>
+                ) : (
+                  <p className="text-sm text-gray-700 mb-2 leading-relaxed">
+                    {item.description_snippet ? renderHighlighted(item.description_snippet, item.highlights) : 'No description available.'}
+                  </p>
+                )}
+                {item.related_items.length > 0 && (
+                  <p className="text-xs text-gray-500 mb-1">
+                    Related:{' '}
+                    {item.related_items.map((related) => (
+                      <span key={related.full_path} className="font-mono mr-2" title={`${related.relation}, ${related.item_type}`}>
+                        {related.full_path}
+                      </span>
+                    ))}
+                  </p>
+                )}
+                {item.examples.map((example, exampleIndex) => (
+                  <pre key={exampleIndex} className="text-xs font-mono bg-gray-50 border border-gray-200 rounded p-2 mb-1 overflow-x-auto">
+                    {example}
+                  </pre>
+                ))}
                 <p className="text-xs text-gray-600 font-medium">
                   Similarity Score: <span className="text-blue-600">{item.score.toFixed(4)}</span>
                 </p>
@@ -0,0 +1,195 @@
+'use client'; // Required for client-side interactivity
+
+import { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
+import { describeError } from '@/lib/appError';
+
+// Mirrors analytics::IndexAnalytics on the Rust side. All data is stored locally.
+interface IndexSnapshot {
+  timestamp_ms: number;
+  projects: number;
+  total_items: number;
+  total_embeddings: number;
+  disk_usage_bytes: number;
+}
+
+interface DailyQueryLatency {
+  day: number; // days since the Unix epoch
+  query_count: number;
+  total_latency_ms: number;
+}
+
+interface IndexAnalytics {
+  history: IndexSnapshot[];
+  daily_query_latency: DailyQueryLatency[];
+  average_query_latency_ms: number | null;
+}
+
+// Mirrors analytics::ProjectStats.
+interface ProjectStats {
+  project_path: string;
+  version: string | null;
+  items_indexed: number;
+  embeddings_stored: number;
+  last_processed_ms: number | null;
+  query_count: number;
+  average_query_latency_ms: number | null;
+  index_size_bytes: number;
+}
+
+function formatBytes(bytes: number): string {
+  const units = ['B', 'KB', 'MB', 'GB'];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit++;
+  }
+  return `${value.toFixed(1)} ${units[unit]}`;
+}
+
+export default function StatsPage() {
+  const [days, setDays] = useState<number>(30);
+  const [analytics, setAnalytics] = useState<IndexAnalytics | null>(null);
+  const [projectStats, setProjectStats] = useState<ProjectStats[]>([]);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    async function fetchAnalytics() {
+      try {
+        setAnalytics(await invoke<IndexAnalytics>('get_index_analytics', { days }));
+        const projectPaths = await invoke<string[]>('get_processed_project_list');
+        setProjectStats(await Promise.all(projectPaths.map(projectPath => invoke<ProjectStats>('get_project_stats', { projectPath }))));
+        setError(null);
+      } catch (err: any) {
+        console.error("Failed to fetch index analytics:", err);
+        setError(describeError(err));
+      }
+    }
+    fetchAnalytics();
+
+    // A new snapshot is recorded whenever the index changes.
+    const unlisten = listen('state-changed', () => fetchAnalytics());
+    return () => {
+      unlisten.then(f => f());
+    };
+  }, [days]);
+
+  const latest = analytics?.history[analytics.history.length - 1];
+
+  return (
+    <div className="container mx-auto p-4">
+      <h1 className="text-2xl font-bold mb-4 text-gray-800">Index Statistics</h1>
+
+      <div className="mb-4">
+        <label htmlFor="days" className="mr-2 text-gray-700">Time range:</label>
+        <select
+          id="days"
+          value={days}
+          onChange={(e) => setDays(Number(e.target.value))}
+          className="p-2 border border-gray-300 rounded-md shadow-sm"
+        >
+          <option value={7}>Last 7 days</option>
+          <option value={30}>Last 30 days</option>
+          <option value={90}>Last 90 days</option>
+        </select>
+      </div>
+
+      {error && <p className="text-red-600 mb-4">Error: {error}</p>}
+
+      <div className="grid grid-cols-2 md:grid-cols-4 gap-4 mb-6">
+        <div className="p-4 border rounded-lg shadow-sm bg-white">
+          <p className="text-sm text-gray-500">Projects indexed</p>
+          <p className="text-2xl font-semibold">{latest?.projects ?? 0}</p>
+        </div>
+        <div className="p-4 border rounded-lg shadow-sm bg-white">
+          <p className="text-sm text-gray-500">Total items</p>
+          <p className="text-2xl font-semibold">{latest?.total_items ?? 0}</p>
+        </div>
+        <div className="p-4 border rounded-lg shadow-sm bg-white">
+          <p className="text-sm text-gray-500">Disk usage</p>
+          <p className="text-2xl font-semibold">{formatBytes(latest?.disk_usage_bytes ?? 0)}</p>
+        </div>
+        <div className="p-4 border rounded-lg shadow-sm bg-white">
+          <p className="text-sm text-gray-500">Avg. query latency</p>
+          <p className="text-2xl font-semibold">
+            {analytics?.average_query_latency_ms != null ? `${analytics.average_query_latency_ms.toFixed(1)} ms` : 'n/a'}
+          </p>
+        </div>
+      </div>
+
+      <h2 className="text-xl font-semibold mb-2 text-gray-700">Projects</h2>
+      <table className="min-w-full mb-6 bg-white border">
+        <thead>
+          <tr className="text-left text-gray-600">
+            <th className="p-2">Project</th>
+            <th className="p-2">Items</th>
+            <th className="p-2">Embeddings</th>
+            <th className="p-2">Last processed</th>
+            <th className="p-2">Queries</th>
+            <th className="p-2">Avg. latency</th>
+            <th className="p-2">Index size</th>
+          </tr>
+        </thead>
+        <tbody>
+          {projectStats.map(stats => (
+            <tr key={stats.project_path} className="border-t">
+              <td className="p-2">{stats.project_path}{stats.version ? ` (${stats.version})` : ''}</td>
+              <td className="p-2">{stats.items_indexed}</td>
+              <td className="p-2">{stats.embeddings_stored}</td>
+              <td className="p-2">{stats.last_processed_ms != null ? new Date(stats.last_processed_ms).toLocaleString() : 'n/a'}</td>
+              <td className="p-2">{stats.query_count}</td>
+              <td className="p-2">{stats.average_query_latency_ms != null ? `${stats.average_query_latency_ms.toFixed(1)} ms` : 'n/a'}</td>
+              <td className="p-2">{formatBytes(stats.index_size_bytes)}</td>
+            </tr>
+          ))}
+        </tbody>
+      </table>
+
+      <h2 className="text-xl font-semibold mb-2 text-gray-700">Queries per day</h2>
+      <table className="min-w-full mb-6 bg-white border">
+        <thead>
+          <tr className="text-left text-gray-600">
+            <th className="p-2">Day</th>
+            <th className="p-2">Queries</th>
+            <th className="p-2">Avg. latency</th>
+          </tr>
+        </thead>
+        <tbody>
+          {analytics?.daily_query_latency.map(bucket => (
+            <tr key={bucket.day} className="border-t">
+              <td className="p-2">{new Date(bucket.day * 86_400_000).toLocaleDateString()}</td>
+              <td className="p-2">{bucket.query_count}</td>
+              <td className="p-2">{(bucket.total_latency_ms / Math.max(bucket.query_count, 1)).toFixed(1)} ms</td>
+            </tr>
+          ))}
+        </tbody>
+      </table>
+
+      <h2 className="text-xl font-semibold mb-2 text-gray-700">Index history</h2>
+      <table className="min-w-full bg-white border">
+        <thead>
+          <tr className="text-left text-gray-600">
+            <th className="p-2">Time</th>
+            <th className="p-2">Projects</th>
+            <th className="p-2">Items</th>
+            <th className="p-2">Embeddings</th>
+            <th className="p-2">Disk usage</th>
+          </tr>
+        </thead>
+        <tbody>
+          {analytics?.history.slice().reverse().map(snapshot => (
+            <tr key={snapshot.timestamp_ms} className="border-t">
+              <td className="p-2">{new Date(snapshot.timestamp_ms).toLocaleString()}</td>
+              <td className="p-2">{snapshot.projects}</td>
+              <td className="p-2">{snapshot.total_items}</td>
+              <td className="p-2">{snapshot.total_embeddings}</td>
+              <td className="p-2">{formatBytes(snapshot.disk_usage_bytes)}</td>
+            </tr>
+          ))}
+        </tbody>
+      </table>
+    </div>
+  );
+}
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Error returned by every Tauri command (and carried by failed jobs).
+ *
+ * Serialized as `{ "code": "<snake_case code>", "details": { ... } }`. The codes are stable so the
+ * frontend can switch on them and offer a targeted fix; the TypeScript definition in
+ * `src/bindings/AppError.ts` is generated from this type by `cargo test`.
+ */
+export type AppError = { "code": "toolchain_missing", "details": { toolchain: string, } } | { "code": "rustdoc_failed", "details": { message: string, } } | { "code": "parse_failed", "details": { message: string, } } | { "code": "embedder_unavailable", "details": { model_repo: string, } } | { "code": "embedder_loading", "details": { model_repo: string, } } | { "code": "embedding_model_mismatch", "details": { project_path: string, project_model: string, current_model: string, } } | { "code": "server_port_in_use", "details": { host: string, port: number, } } | { "code": "path_not_found", "details": { path: string, } } | { "code": "untrusted_project", "details": { path: string, } } | { "code": "project_not_found", "details": { project_path: string, } } | { "code": "job_not_found", "details": { job_id: number, } } | { "code": "job_cancelled", "details": { job_id: number, } } | { "code": "queue_full", "details": { max_queued_jobs: number, } } | { "code": "invalid_argument", "details": { message: string, } } | { "code": "unauthorized", "details": { message: string, } } | { "code": "internal", "details": { message: string, } };
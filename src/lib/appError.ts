@@ -0,0 +1,78 @@
+import type { AppError } from '@/bindings/AppError';
+
+export type { AppError };
+
+export function isAppError(err: unknown): err is AppError {
+  return typeof err === 'object' && err !== null && 'code' in err && 'details' in err;
+}
+
+// Short description of what went wrong.
+export function describeError(err: unknown): string {
+  if (!isAppError(err)) {
+    return typeof err === 'string' ? err : String(err);
+  }
+  switch (err.code) {
+    case 'toolchain_missing':
+      return `The '${err.details.toolchain}' Rust toolchain is not installed.`;
+    case 'rustdoc_failed':
+      return `rustdoc failed: ${err.details.message}`;
+    case 'parse_failed':
+      return `Could not parse the rustdoc JSON: ${err.details.message}`;
+    case 'embedder_unavailable':
+      return `The embedding model ${err.details.model_repo} is not loaded.`;
+    case 'embedder_loading':
+      return `The embedding model ${err.details.model_repo} is still warming up.`;
+    case 'embedding_model_mismatch':
+      return `${err.details.project_path} was embedded with ${err.details.project_model}, not the loaded ${err.details.current_model}.`;
+    case 'server_port_in_use':
+      return `${err.details.host}:${err.details.port} is already in use, so the MCP server could not start.`;
+    case 'path_not_found':
+      return `Path does not exist: ${err.details.path}`;
+    case 'untrusted_project':
+      return `${err.details.path} is outside the trusted directories.`;
+    case 'project_not_found':
+      return `Project has not been processed: ${err.details.project_path}`;
+    case 'job_not_found':
+      return `Unknown job ${err.details.job_id}`;
+    case 'job_cancelled':
+      return 'Cancelled.';
+    case 'queue_full':
+      return `The job queue is full (${err.details.max_queued_jobs} jobs waiting).`;
+    case 'unauthorized':
+      return `Not authorized: ${err.details.message}`;
+    case 'invalid_argument':
+    case 'internal':
+      return err.details.message;
+  }
+}
+
+// Suggested fix for the error, if there is a specific one.
+export function remediation(err: unknown): string | null {
+  if (!isAppError(err)) {
+    return null;
+  }
+  switch (err.code) {
+    case 'toolchain_missing':
+      return `Install it with: rustup toolchain install ${err.details.toolchain}`;
+    case 'rustdoc_failed':
+      return 'Make sure the project builds with `cargo build`, then try again.';
+    case 'parse_failed':
+      return 'Update the toolchain with `rustup update nightly` or pin a supported nightly in the config.';
+    case 'embedder_unavailable':
+      return `Delete the cached ${err.details.model_repo} files from the Hugging Face cache so they are re-downloaded, then restart the app.`;
+    case 'embedder_loading':
+      return 'It is downloaded on first run, which can take a few minutes. Try again once it is ready.';
+    case 'embedding_model_mismatch':
+      return 'Re-embed the project from the Projects page (reembed_project) to search it with the current model.';
+    case 'server_port_in_use':
+      return `Stop the process using port ${err.details.port} or set a different [server] port in the config file.`;
+    case 'unauthorized':
+      return 'Send the auth token shown on the Projects page (server.auth_token in the config file).';
+    case 'untrusted_project':
+      return 'Confirm to build it anyway, or add its parent folder to [rustdoc.sandbox] trusted_dirs.';
+    case 'queue_full':
+      return 'Wait for queued jobs to finish, cancel some, or raise [jobs] max_queued_jobs in the config file.';
+    default:
+      return null;
+  }
+}